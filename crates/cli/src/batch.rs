@@ -0,0 +1,189 @@
+//! Modo batch `noctra --file script.rql`
+//!
+//! El script se recorre con `noctra_core::run_script` y cada sentencia pasa
+//! por el procesador RQL del REPL, así que LET, USE, ASSERT y demás comandos
+//! funcionan igual que en `noctra batch`. El resultado de cada consulta SQL
+//! con filas se escribe con el formateador de `--output`; los demás comandos
+//! informan como en el REPL. Una sentencia que falla se informa (posición,
+//! línea y SQL) y el script sigue, salvo con `--fail-fast`.
+
+use std::io::Write;
+
+use noctra_core::{run_script, NoctraError, Result, StatementOutcome};
+use noctra_parser::RqlStatement;
+
+use crate::cli::OutputFormat;
+use crate::output::{
+    format_error, CsvFormatter, JsonFormatter, OutputFormatter, ParquetFormatter, TableFormatter,
+};
+use crate::repl::{parse_rql, Repl};
+
+/// Formateador de `--output` para el modo `--file`
+pub fn batch_formatter(format: &OutputFormat) -> Result<Box<dyn OutputFormatter>> {
+    match format {
+        OutputFormat::Table => Ok(Box::new(TableFormatter::new())),
+        OutputFormat::Csv => Ok(Box::new(CsvFormatter::new(','))),
+        OutputFormat::Json => Ok(Box::new(JsonFormatter::new(false))),
+        OutputFormat::Parquet => Ok(Box::new(ParquetFormatter::new())),
+        other => Err(NoctraError::Configuration(format!(
+            "formato de salida no soportado con --file: {:?} (use table, csv, json o parquet)",
+            other
+        ))),
+    }
+}
+
+/// Ejecutar un script `.rql` con el executor y la sesión de `repl`
+///
+/// Los resultados van a `out` y los errores a `errors`. Parquet es un
+/// archivo por resultado, así que con ese formato sólo se admite una
+/// consulta con filas. Devuelve cuántas sentencias fallaron.
+pub fn run_script_file(
+    repl: &mut Repl,
+    script: &str,
+    format: &OutputFormat,
+    fail_fast: bool,
+    out: &mut dyn Write,
+    errors: &mut dyn Write,
+) -> Result<usize> {
+    let formatter = batch_formatter(format)?;
+    let mut results_written = 0;
+    let mut index = 0;
+
+    let results = run_script(script, fail_fast, |statement| {
+        index += 1;
+        let outcome = parse_rql(&statement.sql).and_then(|ast| {
+            for rql_statement in &ast.statements {
+                let RqlStatement::Sql {
+                    sql,
+                    is_duckdb_specific,
+                    ..
+                } = rql_statement
+                else {
+                    repl.execute_statement(rql_statement)?;
+                    continue;
+                };
+                let result_set = repl.query_sql(sql, *is_duckdb_specific)?;
+                if result_set.columns.is_empty() {
+                    continue;
+                }
+                if matches!(format, OutputFormat::Parquet) && results_written > 0 {
+                    return Err(NoctraError::Validation(
+                        "--output parquet admite una sola consulta con filas".to_string(),
+                    ));
+                }
+                formatter.write_result(&result_set, out)?;
+                if !matches!(format, OutputFormat::Parquet) {
+                    writeln!(out)?;
+                }
+                results_written += 1;
+            }
+            Ok(())
+        });
+
+        match outcome {
+            Ok(()) => Ok(StatementOutcome::RowsAffected(0)),
+            Err(e) => {
+                writeln!(
+                    errors,
+                    "❌ Sentencia {} (línea {}): {}",
+                    index, statement.line, statement.sql
+                )?;
+                writeln!(errors, "{}", format_error(&e))?;
+                Ok(StatementOutcome::Error(e))
+            }
+        }
+    })?;
+
+    Ok(results.iter().filter(|result| result.is_error()).count())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cli::ReplArgs;
+    use crate::config::CliConfig;
+
+    fn memory_repl() -> Repl {
+        let mut config = CliConfig::default();
+        config.database.connection_string = ":memory:".to_string();
+        Repl::new(config, ReplArgs::default()).unwrap()
+    }
+
+    #[test]
+    fn test_run_script_file_writes_select_output() {
+        let script = "CREATE TABLE clientes (id INTEGER, nombre TEXT);\n\
+                      INSERT INTO clientes VALUES (1, 'Ana'), (2, 'Luis');\n\
+                      SELECT nombre FROM clientes ORDER BY id;\n";
+
+        let (mut out, mut errors) = (Vec::new(), Vec::new());
+        let failed = run_script_file(
+            &mut memory_repl(),
+            script,
+            &OutputFormat::Csv,
+            false,
+            &mut out,
+            &mut errors,
+        )
+        .unwrap();
+
+        assert_eq!(failed, 0);
+        assert_eq!(String::from_utf8(out).unwrap(), "nombre\nAna\nLuis\n\n");
+        assert!(errors.is_empty());
+    }
+
+    #[test]
+    fn test_run_script_file_runs_rql_commands() {
+        let script = "CREATE TABLE t (n INTEGER);\n\
+                      INSERT INTO t VALUES (1), (2), (3);\n\
+                      LET minimo = 2;\n\
+                      ASSERT (SELECT count(*) FROM t) = 3;\n\
+                      SELECT n FROM t\n  WHERE n >= #minimo ORDER BY n;";
+
+        let (mut out, mut errors) = (Vec::new(), Vec::new());
+        let failed = run_script_file(
+            &mut memory_repl(),
+            script,
+            &OutputFormat::Csv,
+            true,
+            &mut out,
+            &mut errors,
+        )
+        .unwrap();
+
+        assert_eq!(failed, 0, "{}", String::from_utf8_lossy(&errors));
+        assert_eq!(String::from_utf8(out).unwrap(), "n\n2\n3\n\n");
+    }
+
+    #[test]
+    fn test_run_script_file_reports_errors_and_fails_fast() {
+        let script = "SELECT * FROM inexistente;\nSELECT 1 AS uno;";
+
+        let (mut out, mut errors) = (Vec::new(), Vec::new());
+        let failed = run_script_file(
+            &mut memory_repl(),
+            script,
+            &OutputFormat::Json,
+            false,
+            &mut out,
+            &mut errors,
+        )
+        .unwrap();
+        assert_eq!(failed, 1);
+        let errors = String::from_utf8(errors).unwrap();
+        assert!(errors.contains("Sentencia 1 (línea 1): SELECT * FROM inexistente"));
+        assert!(String::from_utf8(out).unwrap().contains("uno"));
+
+        let (mut out, mut errors) = (Vec::new(), Vec::new());
+        let failed = run_script_file(
+            &mut memory_repl(),
+            script,
+            &OutputFormat::Json,
+            true,
+            &mut out,
+            &mut errors,
+        )
+        .unwrap();
+        assert_eq!(failed, 1);
+        assert!(out.is_empty());
+    }
+}
@@ -16,8 +16,8 @@ pub struct NoctraArgs {
     #[arg(short, long, value_name = "FILE")]
     pub config: Option<PathBuf>,
 
-    /// Base de datos SQLite (archivo)
-    #[arg(long, value_name = "FILE")]
+    /// Base de datos SQLite (archivo) o URL `postgres://`
+    #[arg(long, value_name = "FILE|URL")]
     pub database: Option<PathBuf>,
 
     /// Base de datos en memoria
@@ -36,6 +36,26 @@ pub struct NoctraArgs {
     #[arg(long, value_enum)]
     pub color: Option<ColorChoice>,
 
+    /// Ejecutar un script .rql y escribir los resultados en stdout
+    #[arg(long, value_name = "FILE")]
+    pub file: Option<PathBuf>,
+
+    /// Formato de los resultados de --file (table, csv, json, parquet)
+    #[arg(long, value_enum, value_name = "FORMAT", requires = "file")]
+    pub output: Option<OutputFormat>,
+
+    /// Con --file, detener el script en la primera sentencia con error
+    #[arg(long, requires = "file")]
+    pub fail_fast: bool,
+
+    /// Registrar cada consulta (con los literales redactados) en un archivo JSONL
+    #[arg(long, value_name = "FILE")]
+    pub query_log: Option<PathBuf>,
+
+    /// Limitar IMPORT, EXPORT y demás archivos a este directorio (repetible)
+    #[arg(long = "allow-path", value_name = "DIR")]
+    pub allowed_paths: Vec<PathBuf>,
+
     /// Comando a ejecutar
     #[command(subcommand)]
     pub command: Option<NoctraSubcommand>,
@@ -71,6 +91,10 @@ pub enum NoctraSubcommand {
     /// Configuración
     #[command(name = "config")]
     Config(ConfigArgs),
+
+    /// Asistente para construir consultas sin escribir SQL
+    #[command(name = "query-builder")]
+    QueryBuilder,
 }
 
 /// Argumentos del REPL
@@ -87,6 +111,10 @@ pub struct ReplArgs {
     /// Historial personalizado
     #[arg(long, value_name = "FILE")]
     pub history: Option<PathBuf>,
+
+    /// Archivo de sesión (.toml o .json): se restaura al iniciar y se guarda tras cada comando
+    #[arg(long, value_name = "FILE")]
+    pub session: Option<PathBuf>,
 }
 
 /// Argumentos del TUI
@@ -103,6 +131,10 @@ pub struct TuiArgs {
     /// Archivo de base de datos SQLite
     #[arg(short, long, value_name = "DATABASE")]
     pub database: Option<PathBuf>,
+
+    /// Archivo de sesión (.toml o .json): se restaura al iniciar y se guarda tras cada comando
+    #[arg(long, value_name = "FILE")]
+    pub session: Option<PathBuf>,
 }
 
 /// Argumentos de batch processing
@@ -131,6 +163,14 @@ pub struct BatchArgs {
     /// Continuar en caso de error
     #[arg(long)]
     pub continue_on_error: bool,
+
+    /// Ejecutar el script en una transacción (se revierte si alguna sentencia falla)
+    #[arg(long)]
+    pub transaction: bool,
+
+    /// Abortar el script (código de salida no-cero) si falla un ASSERT
+    #[arg(long)]
+    pub strict: bool,
 }
 
 /// Argumentos de formulario
@@ -259,6 +299,30 @@ pub struct ConfigArgs {
     /// Resetear configuración
     #[arg(short, long)]
     pub reset: bool,
+
+    /// Subcomando de configuración
+    #[command(subcommand)]
+    pub command: Option<ConfigSubcommand>,
+}
+
+/// Subcomandos de Config
+#[derive(Subcommand, Debug, Clone)]
+pub enum ConfigSubcommand {
+    /// Escribir configuración por defecto (~/.config/noctra/config.toml)
+    #[command(name = "init")]
+    Init(ConfigInitArgs),
+}
+
+/// Argumentos de config init
+#[derive(Args, Debug, Clone)]
+pub struct ConfigInitArgs {
+    /// Ruta de destino (por defecto ~/.config/noctra/config.toml)
+    #[arg(short, long, value_name = "FILE")]
+    pub path: Option<PathBuf>,
+
+    /// Sobrescribir si el archivo ya existe
+    #[arg(short, long)]
+    pub force: bool,
 }
 
 /// Choice para colores
@@ -285,6 +349,8 @@ pub enum OutputFormat {
     Xml,
     /// Markdown
     Markdown,
+    /// Parquet (binario)
+    Parquet,
 }
 
 /// Key-Value argument
@@ -332,6 +398,10 @@ impl NoctraApp {
 
     /// Ejecutar aplicación
     pub async fn run(mut self) -> Result<(), Box<dyn std::error::Error>> {
+        if let Some(file) = self.args.file.take() {
+            return self.run_file(file).await;
+        }
+
         let command = self.args.command.take();
         let result = match command {
             Some(cmd) => match cmd {
@@ -342,6 +412,7 @@ impl NoctraApp {
                 NoctraSubcommand::Query(args) => self.run_query(args).await,
                 NoctraSubcommand::Info(args) => self.run_info(args),
                 NoctraSubcommand::Config(args) => self.run_config(args),
+                NoctraSubcommand::QueryBuilder => self.run_query_builder().await,
             },
             None => self.run_interactive().await,
         };
@@ -365,6 +436,7 @@ impl NoctraApp {
             Query(args) => self.run_query(args).await,
             Info(args) => self.run_info(args),
             Config(args) => self.run_config(args),
+            QueryBuilder => self.run_query_builder().await,
         }
     }
 
@@ -411,6 +483,14 @@ impl NoctraApp {
         } else {
             NoctraTui::new()?
         };
+        tui.set_executor_options(self.config.executor_options());
+        if let Some(query_log) = &self.config.global.query_log {
+            tui.set_query_logger(Box::new(noctra_core::JsonlQueryLogger::open(query_log)?));
+        }
+        tui.set_allowed_paths(self.config.global.allowed_paths.clone());
+        if let Some(session_file) = args.session {
+            tui.restore_session(session_file);
+        }
 
         tui.run()?;
 
@@ -420,23 +500,54 @@ impl NoctraApp {
 
     /// Ejecutar batch processing
     async fn run_batch(self, args: BatchArgs) -> Result<(), Box<dyn std::error::Error>> {
-        let _script_content = std::fs::read_to_string(&args.script)
+        let script_content = std::fs::read_to_string(&args.script)
             .map_err(|e| format!("Error reading script file: {}", e))?;
 
         println!("📜 Ejecutando script: {}", args.script.display());
 
-        // Crear parámetros desde argumentos
-        let mut parameters = std::collections::HashMap::new();
+        let mut repl = crate::repl::Repl::new(self.config, ReplArgs::default())?;
+        // Los parámetros quedan como variables de sesión (`#clave` en el script)
         for param in args.param {
-            parameters.insert(param.key, param.value);
+            repl.set_variable(param.key, param.value);
         }
+        let options = noctra_core::ScriptOptions {
+            stop_on_error: !args.continue_on_error,
+            transaction: args.transaction,
+        };
+        let failed_assertions = repl.run_script(&script_content, args.strict, options)?;
 
-        // TODO: Implementar ejecución de script
-        println!("⚠️  Script processing no implementado aún");
+        if failed_assertions > 0 {
+            println!("⚠️  {} aserción(es) fallida(s)", failed_assertions);
+        }
 
         Ok(())
     }
 
+    /// Ejecutar un script con --file, con los resultados en stdout
+    ///
+    /// Falla (código de salida 1) si alguna sentencia dio error.
+    async fn run_file(self, file: PathBuf) -> Result<(), Box<dyn std::error::Error>> {
+        let script = std::fs::read_to_string(&file)
+            .map_err(|e| format!("Error reading script file: {}", e))?;
+
+        let format = self.args.output.clone().unwrap_or(OutputFormat::Table);
+        let fail_fast = self.args.fail_fast;
+        let mut repl = crate::repl::Repl::new(self.config, ReplArgs::default())?;
+        let failed = crate::batch::run_script_file(
+            &mut repl,
+            &script,
+            &format,
+            fail_fast,
+            &mut std::io::stdout().lock(),
+            &mut std::io::stderr().lock(),
+        )?;
+
+        if failed > 0 {
+            return Err(format!("{} sentencia(s) con error en {}", failed, file.display()).into());
+        }
+        Ok(())
+    }
+
     /// Ejecutar formulario
     async fn run_form(self, args: FormArgs) -> Result<(), Box<dyn std::error::Error>> {
         use FormSubcommand::*;
@@ -648,6 +759,43 @@ impl NoctraApp {
         Ok(())
     }
 
+    /// Ejecutar el asistente de consultas
+    ///
+    /// Al confirmar, la consulta se ejecuta y se muestra su resultado, o se
+    /// abre el REPL con el SQL en el editor.
+    async fn run_query_builder(self) -> Result<(), Box<dyn std::error::Error>> {
+        use crate::commands::query_builder::{
+            catalog_from_executor, run_query_builder, BuilderOutcome,
+        };
+
+        let mut repl = crate::repl::Repl::new(self.config, ReplArgs::default())?;
+        let catalog = catalog_from_executor(repl.executor());
+        let (source, sql) = match run_query_builder(catalog)? {
+            BuilderOutcome::Execute { source, sql } => (source, Some(sql)),
+            BuilderOutcome::Edit { source, sql } => {
+                repl.set_initial_input(sql);
+                (source, None)
+            }
+            BuilderOutcome::Cancel => {
+                println!("ℹ️  Asistente cancelado");
+                return Ok(());
+            }
+        };
+
+        if let Some(alias) = source {
+            repl.run_script(&format!("USE SOURCE {};", alias), false, Default::default())?;
+        }
+        match sql {
+            Some(sql) => {
+                println!("📝 {}", sql);
+                repl.run_script(&format!("{};", sql), false, Default::default())?;
+            }
+            None => repl.run().await?,
+        }
+
+        Ok(())
+    }
+
     /// Ejecutar comando info
     fn run_info(self, args: InfoArgs) -> Result<(), Box<dyn std::error::Error>> {
         if args.version {
@@ -667,6 +815,10 @@ impl NoctraApp {
 
     /// Ejecutar comando config
     fn run_config(mut self, args: ConfigArgs) -> Result<(), Box<dyn std::error::Error>> {
+        if let Some(ConfigSubcommand::Init(init_args)) = args.command {
+            return Self::init_config(init_args);
+        }
+
         if args.show {
             self.show_config();
         } else if args.edit {
@@ -739,6 +891,30 @@ impl NoctraApp {
         Ok(())
     }
 
+    /// Escribir archivo de configuración por defecto
+    fn init_config(args: ConfigInitArgs) -> Result<(), Box<dyn std::error::Error>> {
+        let config_path = match args.path {
+            Some(path) => path,
+            None => CliConfig::default_config_path()?,
+        };
+
+        if config_path.exists() && !args.force {
+            return Err(format!(
+                "El archivo {} ya existe (usa --force para sobrescribir)",
+                config_path.display()
+            )
+            .into());
+        }
+
+        CliConfig::default().to_toml_file(&config_path)?;
+        println!(
+            "📝 Configuración por defecto escrita en {}",
+            config_path.display()
+        );
+
+        Ok(())
+    }
+
     /// Resetear configuración
     fn reset_config(&mut self) -> Result<(), Box<dyn std::error::Error>> {
         self.config = CliConfig::default();
@@ -753,15 +929,13 @@ fn load_config(args: &NoctraArgs) -> Result<CliConfig, Box<dyn std::error::Error
         CliConfig::load_from_file(config_file)?
     } else {
         // Intentar cargar configuración por defecto
-        match CliConfig::default_config_path() {
-            Ok(default_path) => {
-                if default_path.exists() {
-                    CliConfig::load_from_file(&default_path)?
-                } else {
-                    CliConfig::default()
-                }
-            }
-            Err(_) => CliConfig::default(),
+        let candidates = [
+            CliConfig::default_config_path(),
+            CliConfig::legacy_config_path(),
+        ];
+        match candidates.into_iter().flatten().find(|path| path.exists()) {
+            Some(path) => CliConfig::from_toml_file(&path)?,
+            None => CliConfig::default(),
         }
     };
 
@@ -779,8 +953,23 @@ fn apply_cli_overrides(config: &mut CliConfig, args: &NoctraArgs) {
         config.database.backend_type = crate::config::BackendType::Sqlite;
         config.database.connection_string = ":memory:".to_string();
     } else if let Some(db_path) = &args.database {
-        config.database.backend_type = crate::config::BackendType::Sqlite;
-        config.database.connection_string = db_path.to_string_lossy().to_string();
+        let connection_string = db_path.to_string_lossy().to_string();
+        let is_postgres = ["postgres://", "postgresql://"]
+            .iter()
+            .any(|scheme| connection_string.starts_with(scheme));
+        config.database.backend_type = if is_postgres {
+            crate::config::BackendType::Postgres
+        } else {
+            crate::config::BackendType::Sqlite
+        };
+        config.database.connection_string = connection_string;
+    }
+
+    if let Some(query_log) = &args.query_log {
+        config.global.query_log = Some(query_log.clone());
+    }
+    if !args.allowed_paths.is_empty() {
+        config.global.allowed_paths = args.allowed_paths.clone();
     }
 
     // Verbose/Debug
@@ -3,6 +3,8 @@
 //! Sistema de comandos extensible que integra core, parser, formlib y tui.
 //! Incluye comandos para REPL, batch processing, formularios y configuración.
 
+pub mod query_builder;
+
 use serde_json;
 use std::collections::HashMap;
 use std::path::PathBuf;
@@ -0,0 +1,651 @@
+//! Asistente de consultas (`noctra query-builder`)
+//!
+//! Construye un SELECT paso a paso sin escribir SQL: fuente, tabla,
+//! columnas, filtros WHERE (columna, operador y valor), ORDER BY y LIMIT.
+//! `QueryBuilder` sólo recibe teclas y produce líneas de texto, así que se
+//! puede probar sin terminal; `run_query_builder` lo dibuja con crossterm.
+//! Los identificadores se generan siempre entre comillas dobles.
+
+use crossterm::{
+    cursor::{Hide, MoveTo, Show},
+    event::{self, Event, KeyCode, KeyEvent, KeyEventKind, KeyModifiers},
+    execute, queue,
+    style::Print,
+    terminal::{
+        disable_raw_mode, enable_raw_mode, Clear, ClearType, EnterAlternateScreen,
+        LeaveAlternateScreen,
+    },
+};
+use std::io::{stdout, Write};
+
+use noctra_core::datasource::TableInfo;
+use noctra_core::types::is_numeric_type;
+use noctra_core::{Executor, NoctraError, Result};
+
+/// Operadores de los filtros; los dos últimos no llevan valor
+pub const OPERATORS: [&str; 9] = [
+    "=",
+    "<>",
+    "<",
+    "<=",
+    ">",
+    ">=",
+    "LIKE",
+    "IS NULL",
+    "IS NOT NULL",
+];
+
+/// Tablas de una fuente (`source = None` es el backend principal)
+#[derive(Debug, Clone)]
+pub struct SourceTables {
+    /// Alias de la fuente registrada
+    pub source: Option<String>,
+
+    /// Nombre mostrado en el asistente
+    pub label: String,
+
+    /// Tablas y vistas con sus columnas
+    pub tables: Vec<TableInfo>,
+}
+
+/// Fuentes y tablas disponibles, como las listan SHOW SOURCES y SHOW TABLES
+///
+/// El backend principal va primero; las fuentes cuyo esquema no se puede
+/// leer se omiten.
+pub fn catalog_from_executor(executor: &Executor) -> Vec<SourceTables> {
+    let mut catalog = vec![SourceTables {
+        source: None,
+        label: executor.backend_info().name,
+        tables: executor.backend_schema().unwrap_or_default(),
+    }];
+    let registry = executor.source_registry();
+    for (alias, source_type) in registry.list_sources() {
+        if let Some(Ok(tables)) = registry.get(&alias).map(|source| source.schema()) {
+            catalog.push(SourceTables {
+                label: format!("{} ({})", alias, source_type.type_name()),
+                source: Some(alias),
+                tables,
+            });
+        }
+    }
+    catalog
+}
+
+/// Identificador entre comillas dobles (las internas se duplican)
+///
+/// Un nombre cualificado (`main.users`) se cita por partes:
+/// `"main"."users"`.
+pub fn quote_identifier(name: &str) -> String {
+    name.split('.')
+        .map(|part| format!("\"{}\"", part.replace('"', "\"\"")))
+        .collect::<Vec<_>>()
+        .join(".")
+}
+
+/// Literal SQL de un valor escrito por el usuario
+///
+/// En columnas numéricas los números van tal cual; el resto (y cualquier
+/// valor de una columna de texto, aunque parezca un número) va entre
+/// comillas simples.
+fn quote_value(value: &str, numeric: bool) -> String {
+    let trimmed = value.trim();
+    if numeric && trimmed.parse::<f64>().is_ok_and(f64::is_finite) {
+        trimmed.to_string()
+    } else {
+        format!("'{}'", value.replace('\'', "''"))
+    }
+}
+
+/// Condición del WHERE
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Filter {
+    /// Columna filtrada
+    pub column: String,
+
+    /// Uno de `OPERATORS`
+    pub operator: String,
+
+    /// Valor tal como se escribió (vacío con IS NULL / IS NOT NULL)
+    pub value: String,
+
+    /// Si la columna es numérica (según `ColumnInfo::data_type`)
+    pub numeric: bool,
+}
+
+impl Filter {
+    fn to_sql(&self) -> String {
+        if self.operator.starts_with("IS ") {
+            format!("{} {}", quote_identifier(&self.column), self.operator)
+        } else {
+            format!(
+                "{} {} {}",
+                quote_identifier(&self.column),
+                self.operator,
+                quote_value(&self.value, self.numeric)
+            )
+        }
+    }
+}
+
+/// Consulta elegida en el asistente
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct QuerySpec {
+    /// Tabla consultada
+    pub table: String,
+
+    /// Columnas seleccionadas, en el orden de la tabla
+    pub columns: Vec<String>,
+
+    /// Condiciones unidas con AND
+    pub filters: Vec<Filter>,
+
+    /// Columna de ORDER BY y si es descendente
+    pub order_by: Option<(String, bool)>,
+
+    /// LIMIT
+    pub limit: Option<u64>,
+}
+
+impl QuerySpec {
+    /// SQL de la consulta
+    pub fn to_sql(&self) -> String {
+        let columns = if self.columns.is_empty() {
+            "*".to_string()
+        } else {
+            self.columns
+                .iter()
+                .map(|column| quote_identifier(column))
+                .collect::<Vec<_>>()
+                .join(", ")
+        };
+        let mut sql = format!("SELECT {} FROM {}", columns, quote_identifier(&self.table));
+        if !self.filters.is_empty() {
+            let conditions: Vec<String> = self.filters.iter().map(Filter::to_sql).collect();
+            sql.push_str(&format!(" WHERE {}", conditions.join(" AND ")));
+        }
+        if let Some((column, descending)) = &self.order_by {
+            let direction = if *descending { "DESC" } else { "ASC" };
+            sql.push_str(&format!(
+                " ORDER BY {} {}",
+                quote_identifier(column),
+                direction
+            ));
+        }
+        if let Some(limit) = self.limit {
+            sql.push_str(&format!(" LIMIT {}", limit));
+        }
+        sql
+    }
+}
+
+/// Cómo terminó el asistente
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum BuilderOutcome {
+    /// Ejecutar `sql` en `source` (None = backend principal)
+    Execute { source: Option<String>, sql: String },
+
+    /// Dejar `sql` en el editor de comandos del REPL
+    Edit { source: Option<String>, sql: String },
+
+    /// Cancelado con Esc o Ctrl+C
+    Cancel,
+}
+
+/// Paso del asistente
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Step {
+    Source,
+    Table,
+    Columns,
+    FilterColumn,
+    FilterOperator,
+    FilterValue,
+    OrderBy,
+    OrderDirection,
+    Limit,
+    Confirm,
+}
+
+/// Estado del asistente de consultas
+#[derive(Debug)]
+pub struct QueryBuilder {
+    catalog: Vec<SourceTables>,
+    step: Step,
+    cursor: usize,
+    source: usize,
+    table: usize,
+    checked: Vec<bool>,
+    pending_filter: Option<Filter>,
+    input: String,
+    spec: QuerySpec,
+}
+
+impl QueryBuilder {
+    /// Asistente sobre las fuentes de `catalog`
+    pub fn new(catalog: Vec<SourceTables>) -> Self {
+        Self {
+            catalog,
+            step: Step::Source,
+            cursor: 0,
+            source: 0,
+            table: 0,
+            checked: Vec::new(),
+            pending_filter: None,
+            input: String::new(),
+            spec: QuerySpec::default(),
+        }
+    }
+
+    /// Consulta construida hasta ahora
+    pub fn spec(&self) -> &QuerySpec {
+        &self.spec
+    }
+
+    fn tables(&self) -> &[TableInfo] {
+        self.catalog
+            .get(self.source)
+            .map(|source| source.tables.as_slice())
+            .unwrap_or(&[])
+    }
+
+    fn columns(&self) -> Vec<String> {
+        self.tables()
+            .get(self.table)
+            .map(|table| table.columns.iter().map(|c| c.name.clone()).collect())
+            .unwrap_or_default()
+    }
+
+    /// Opciones de los pasos de selección
+    fn options(&self) -> Vec<String> {
+        match self.step {
+            Step::Source => self.catalog.iter().map(|s| s.label.clone()).collect(),
+            Step::Table => self.tables().iter().map(|t| t.name.clone()).collect(),
+            Step::Columns => self.columns(),
+            Step::FilterColumn => std::iter::once("(sin más filtros)".to_string())
+                .chain(self.columns())
+                .collect(),
+            Step::FilterOperator => OPERATORS.iter().map(|op| op.to_string()).collect(),
+            Step::OrderBy => std::iter::once("(sin orden)".to_string())
+                .chain(self.columns())
+                .collect(),
+            Step::OrderDirection => vec!["ASC".to_string(), "DESC".to_string()],
+            Step::FilterValue | Step::Limit | Step::Confirm => Vec::new(),
+        }
+    }
+
+    fn go(&mut self, step: Step) {
+        self.step = step;
+        self.cursor = 0;
+        self.input.clear();
+    }
+
+    /// Procesar una tecla; devuelve el resultado cuando el asistente termina
+    pub fn handle_key(&mut self, key: KeyEvent) -> Option<BuilderOutcome> {
+        if key.code == KeyCode::Esc
+            || (key.code == KeyCode::Char('c') && key.modifiers.contains(KeyModifiers::CONTROL))
+        {
+            return Some(BuilderOutcome::Cancel);
+        }
+
+        match self.step {
+            Step::FilterValue | Step::Limit => {
+                match key.code {
+                    KeyCode::Char(c) if self.step == Step::FilterValue || c.is_ascii_digit() => {
+                        self.input.push(c)
+                    }
+                    KeyCode::Backspace => {
+                        self.input.pop();
+                    }
+                    KeyCode::Enter => self.confirm_input(),
+                    _ => {}
+                }
+                None
+            }
+            Step::Confirm => {
+                let source = self.catalog.get(self.source).and_then(|s| s.source.clone());
+                let sql = self.spec.to_sql();
+                match key.code {
+                    KeyCode::Enter => Some(BuilderOutcome::Execute { source, sql }),
+                    KeyCode::Char('e') | KeyCode::Char('E') => {
+                        Some(BuilderOutcome::Edit { source, sql })
+                    }
+                    _ => None,
+                }
+            }
+            _ => {
+                let count = self.options().len();
+                match key.code {
+                    KeyCode::Up => self.cursor = self.cursor.saturating_sub(1),
+                    KeyCode::Down if self.cursor + 1 < count => self.cursor += 1,
+                    KeyCode::Char(' ') if self.step == Step::Columns => {
+                        if let Some(checked) = self.checked.get_mut(self.cursor) {
+                            *checked = !*checked;
+                        }
+                    }
+                    KeyCode::Enter if count > 0 => self.select(),
+                    _ => {}
+                }
+                None
+            }
+        }
+    }
+
+    /// Enter sobre la opción marcada por el cursor
+    fn select(&mut self) {
+        let columns = self.columns();
+        match self.step {
+            Step::Source => {
+                self.source = self.cursor;
+                self.go(Step::Table);
+            }
+            Step::Table => {
+                self.table = self.cursor;
+                self.spec = QuerySpec {
+                    table: self.tables()[self.table].name.clone(),
+                    ..QuerySpec::default()
+                };
+                self.checked = vec![false; self.columns().len()];
+                self.go(Step::Columns);
+            }
+            Step::Columns => {
+                // Sin ninguna marcada se seleccionan todas
+                self.spec.columns = columns
+                    .into_iter()
+                    .zip(&self.checked)
+                    .filter(|(_, checked)| **checked)
+                    .map(|(column, _)| column)
+                    .collect();
+                self.go(Step::FilterColumn);
+            }
+            Step::FilterColumn if self.cursor == 0 => self.go(Step::OrderBy),
+            Step::FilterColumn => {
+                let data_type = &self.tables()[self.table].columns[self.cursor - 1].data_type;
+                self.pending_filter = Some(Filter {
+                    column: columns[self.cursor - 1].clone(),
+                    operator: String::new(),
+                    value: String::new(),
+                    numeric: is_numeric_type(data_type),
+                });
+                self.go(Step::FilterOperator);
+            }
+            Step::FilterOperator => {
+                let operator = OPERATORS[self.cursor];
+                if let Some(filter) = self.pending_filter.as_mut() {
+                    filter.operator = operator.to_string();
+                }
+                if operator.starts_with("IS ") {
+                    self.spec.filters.extend(self.pending_filter.take());
+                    self.go(Step::FilterColumn);
+                } else {
+                    self.go(Step::FilterValue);
+                }
+            }
+            Step::OrderBy if self.cursor == 0 => self.go(Step::Limit),
+            Step::OrderBy => {
+                self.spec.order_by = Some((columns[self.cursor - 1].clone(), false));
+                self.go(Step::OrderDirection);
+            }
+            Step::OrderDirection => {
+                if let Some((_, descending)) = self.spec.order_by.as_mut() {
+                    *descending = self.cursor == 1;
+                }
+                self.go(Step::Limit);
+            }
+            Step::FilterValue | Step::Limit | Step::Confirm => {}
+        }
+    }
+
+    /// Enter en los pasos con texto libre
+    fn confirm_input(&mut self) {
+        match self.step {
+            Step::FilterValue => {
+                if let Some(mut filter) = self.pending_filter.take() {
+                    filter.value = self.input.clone();
+                    self.spec.filters.push(filter);
+                }
+                self.go(Step::FilterColumn);
+            }
+            Step::Limit => {
+                self.spec.limit = self.input.parse().ok();
+                self.go(Step::Confirm);
+            }
+            _ => {}
+        }
+    }
+
+    /// Líneas de la pantalla del paso actual
+    pub fn lines(&self) -> Vec<String> {
+        let (number, title) = match self.step {
+            Step::Source => (1, "Fuente".to_string()),
+            Step::Table => (2, "Tabla".to_string()),
+            Step::Columns => (3, "Columnas".to_string()),
+            Step::FilterColumn => (4, "Filtro: columna".to_string()),
+            Step::FilterOperator => (4, "Filtro: operador".to_string()),
+            Step::FilterValue => (4, "Filtro: valor".to_string()),
+            Step::OrderBy => (5, "ORDER BY".to_string()),
+            Step::OrderDirection => (5, "Dirección".to_string()),
+            Step::Limit => (5, "LIMIT (vacío = sin límite)".to_string()),
+            Step::Confirm => (6, "Confirmar".to_string()),
+        };
+
+        let mut lines = vec![
+            format!(
+                "Noctra · Asistente de consultas — Paso {}/6: {}",
+                number, title
+            ),
+            String::new(),
+        ];
+        match self.step {
+            Step::FilterValue | Step::Limit => lines.push(format!("> {}_", self.input)),
+            Step::Confirm => lines.push(self.spec.to_sql()),
+            _ => {
+                for (i, option) in self.options().iter().enumerate() {
+                    let marker = if i == self.cursor { "▶" } else { " " };
+                    if self.step == Step::Columns {
+                        let check = if self.checked.get(i) == Some(&true) {
+                            "x"
+                        } else {
+                            " "
+                        };
+                        lines.push(format!("{} [{}] {}", marker, check, option));
+                    } else {
+                        lines.push(format!("{} {}", marker, option));
+                    }
+                }
+            }
+        }
+        if !self.spec.table.is_empty() && self.step != Step::Confirm {
+            lines.push(String::new());
+            lines.push(format!("SQL: {}", self.spec.to_sql()));
+        }
+
+        lines.push(String::new());
+        lines.push(
+            match self.step {
+                Step::Columns => "↑↓ mover · Espacio marcar · Enter continuar · Esc cancelar",
+                Step::FilterValue | Step::Limit => "Enter continuar · Esc cancelar",
+                Step::Confirm => "Enter ejecutar · E editar en el REPL · Esc cancelar",
+                _ => "↑↓ mover · Enter elegir · Esc cancelar",
+            }
+            .to_string(),
+        );
+        lines
+    }
+}
+
+fn terminal_error(e: std::io::Error) -> NoctraError {
+    NoctraError::Io(format!("Error de terminal: {}", e))
+}
+
+/// Ejecutar el asistente en la terminal (pantalla alternativa, modo raw)
+pub fn run_query_builder(catalog: Vec<SourceTables>) -> Result<BuilderOutcome> {
+    let mut builder = QueryBuilder::new(catalog);
+    let mut out = stdout();
+    enable_raw_mode().map_err(terminal_error)?;
+    execute!(out, EnterAlternateScreen, Hide).map_err(terminal_error)?;
+
+    let outcome = loop {
+        let drawn = queue!(out, Clear(ClearType::All)).and_then(|_| {
+            for (row, line) in builder.lines().iter().enumerate() {
+                queue!(out, MoveTo(0, row as u16), Print(line))?;
+            }
+            out.flush()
+        });
+        if let Err(e) = drawn {
+            break Err(terminal_error(e));
+        }
+
+        match event::read() {
+            Ok(Event::Key(key)) if key.kind == KeyEventKind::Press => {
+                if let Some(outcome) = builder.handle_key(key) {
+                    break Ok(outcome);
+                }
+            }
+            Ok(_) => {}
+            Err(e) => break Err(terminal_error(e)),
+        }
+    };
+
+    let _ = execute!(out, Show, LeaveAlternateScreen);
+    let _ = disable_raw_mode();
+    outcome
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use noctra_core::datasource::ColumnInfo;
+
+    fn catalog() -> Vec<SourceTables> {
+        let column = |name: &str, data_type: &str| ColumnInfo {
+            name: name.to_string(),
+            data_type: data_type.to_string(),
+            nullable: true,
+            default_value: None,
+            primary_key: false,
+        };
+        vec![SourceTables {
+            source: None,
+            label: "SQLite".to_string(),
+            tables: vec![TableInfo {
+                name: "empleados".to_string(),
+                columns: vec![
+                    column("nombre", "TEXT"),
+                    column("depto", "VARCHAR(10)"),
+                    column("salario", "INTEGER"),
+                ],
+                row_count: None,
+                row_count_updated_at: None,
+                is_view: false,
+            }],
+        }]
+    }
+
+    fn press(builder: &mut QueryBuilder, keys: &[KeyCode]) -> Option<BuilderOutcome> {
+        let mut outcome = None;
+        for code in keys {
+            outcome = builder.handle_key(KeyEvent::new(*code, KeyModifiers::NONE));
+        }
+        outcome
+    }
+
+    fn typed(text: &str) -> Vec<KeyCode> {
+        text.chars().map(KeyCode::Char).collect()
+    }
+
+    #[test]
+    fn test_two_filters_generate_quoted_sql() {
+        use KeyCode::*;
+        let mut builder = QueryBuilder::new(catalog());
+
+        // Fuente y tabla
+        press(&mut builder, &[Enter, Enter]);
+        // Columnas: nombre y salario
+        press(&mut builder, &[Char(' '), Down, Down, Char(' '), Enter]);
+        // depto = 'O'Brien'
+        press(&mut builder, &[Down, Down, Enter, Enter]);
+        press(&mut builder, &typed("O'Brien"));
+        press(&mut builder, &[Enter]);
+        // salario >= 1500
+        press(
+            &mut builder,
+            &[Down, Down, Down, Enter, Down, Down, Down, Down, Down, Enter],
+        );
+        press(&mut builder, &typed("1500"));
+        press(&mut builder, &[Enter]);
+        // Sin más filtros, ORDER BY salario DESC, LIMIT 10
+        press(&mut builder, &[Enter, Down, Down, Down, Enter, Down, Enter]);
+        press(&mut builder, &typed("1x0"));
+        press(&mut builder, &[Enter]);
+
+        assert!(builder.lines().iter().any(|line| line.contains("Paso 6/6")));
+        let outcome = press(&mut builder, &[Enter]);
+        assert_eq!(
+            outcome,
+            Some(BuilderOutcome::Execute {
+                source: None,
+                sql: "SELECT \"nombre\", \"salario\" FROM \"empleados\" \
+                      WHERE \"depto\" = 'O''Brien' AND \"salario\" >= 1500 \
+                      ORDER BY \"salario\" DESC LIMIT 10"
+                    .to_string(),
+            })
+        );
+    }
+
+    #[test]
+    fn test_edit_and_cancel() {
+        use KeyCode::*;
+        let mut builder = QueryBuilder::new(catalog());
+        // Todas las columnas, IS NULL, sin orden ni límite
+        press(&mut builder, &[Enter, Enter, Enter, Down, Enter]);
+        press(
+            &mut builder,
+            &[Down, Down, Down, Down, Down, Down, Down, Enter],
+        );
+        press(&mut builder, &[Enter, Enter, Enter]);
+        let outcome = press(&mut builder, &[Char('e')]);
+        assert_eq!(
+            outcome,
+            Some(BuilderOutcome::Edit {
+                source: None,
+                sql: "SELECT * FROM \"empleados\" WHERE \"nombre\" IS NULL".to_string(),
+            })
+        );
+
+        let mut builder = QueryBuilder::new(catalog());
+        assert_eq!(
+            press(&mut builder, &[Enter, Esc]),
+            Some(BuilderOutcome::Cancel)
+        );
+        assert_eq!(quote_identifier("a\"b"), "\"a\"\"b\"");
+    }
+
+    #[test]
+    fn test_quote_identifier_schema_qualified() {
+        assert_eq!(quote_identifier("main.users"), "\"main\".\"users\"");
+        assert_eq!(quote_identifier("main.a\"b"), "\"main\".\"a\"\"b\"");
+        assert_eq!(quote_identifier("users"), "\"users\"");
+    }
+
+    #[test]
+    fn test_values_are_quoted_by_column_type() {
+        use KeyCode::*;
+        let mut builder = QueryBuilder::new(catalog());
+        // Todas las columnas; depto = 007 (texto, conserva los ceros)
+        press(
+            &mut builder,
+            &[Enter, Enter, Enter, Down, Down, Enter, Enter],
+        );
+        press(&mut builder, &typed("007"));
+        press(&mut builder, &[Enter]);
+        // salario = abc no es un número: va entre comillas
+        press(&mut builder, &[Down, Down, Down, Enter, Enter]);
+        press(&mut builder, &typed("abc"));
+        press(&mut builder, &[Enter, Enter, Enter]);
+
+        assert_eq!(
+            builder.spec().to_sql(),
+            "SELECT * FROM \"empleados\" WHERE \"depto\" = '007' AND \"salario\" = 'abc'"
+        );
+    }
+}
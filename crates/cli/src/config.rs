@@ -1,10 +1,13 @@
 //! Configuración del CLI de Noctra
 
+use noctra_core::ExecutorOptions;
 use serde::{Deserialize, Serialize};
-use std::path::PathBuf;
+use std::path::{Path, PathBuf};
+use std::time::Duration;
 
 /// Configuración global del CLI
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct GlobalConfig {
     /// Base de datos por defecto
     pub default_database: Option<PathBuf>,
@@ -21,13 +24,14 @@ pub struct GlobalConfig {
     /// Directorio de trabajo
     pub working_dir: PathBuf,
 
-    /// Archivo de historial del REPL
-    pub history_file: PathBuf,
+    /// Archivo de historial del REPL (None = historial solo en memoria)
+    pub history_file: Option<PathBuf>,
 
-    /// Timeout por defecto
+    /// Timeout por defecto de las consultas, en segundos (0 = sin timeout)
     pub default_timeout: u64,
 
-    /// Límite de filas por defecto
+    /// Límite de filas por defecto (en TOML, 0 = sin límite)
+    #[serde(with = "optional_limit")]
     pub default_row_limit: Option<usize>,
 
     /// Formato de salida por defecto
@@ -38,10 +42,21 @@ pub struct GlobalConfig {
 
     /// Tema del CLI
     pub theme: CliTheme,
+
+    /// Paginador para resultados largos (None = `less -R`, "" = sin paginar)
+    pub pager: Option<String>,
+
+    /// Registro de auditoría de consultas en JSONL (None = desactivado)
+    pub query_log: Option<PathBuf>,
+
+    /// Directorios a los que se limitan IMPORT, EXPORT y demás archivos
+    /// (vacío = se rechazan los directorios del sistema, ver `fs_guard`)
+    pub allowed_paths: Vec<PathBuf>,
 }
 
 /// Configuración del CLI específica
-#[derive(Debug, Clone, Serialize, Deserialize, Default)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
+#[serde(default)]
 pub struct CliConfig {
     /// Configuración global
     pub global: GlobalConfig,
@@ -57,7 +72,8 @@ pub struct CliConfig {
 }
 
 /// Configuración del REPL
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct ReplConfig {
     /// Activar REPL
     pub enabled: bool,
@@ -74,7 +90,7 @@ pub struct ReplConfig {
     /// Habilitar syntax highlighting
     pub syntax_highlighting: bool,
 
-    /// Número de líneas de historial
+    /// Número de líneas de historial (también el máximo guardado en disco)
     pub history_size: usize,
 
     /// Editor externo para queries complejas
@@ -85,7 +101,8 @@ pub struct ReplConfig {
 }
 
 /// Configuración de batch processing
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct BatchConfig {
     /// Archivo de script
     pub script_file: Option<PathBuf>,
@@ -110,7 +127,8 @@ pub struct BatchConfig {
 }
 
 /// Configuración de base de datos
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct DatabaseConfig {
     /// Tipo de backend
     pub backend_type: BackendType,
@@ -154,7 +172,7 @@ pub enum OutputFormat {
 }
 
 /// Modos de color
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum ColorMode {
     /// Auto-detectar
     Auto,
@@ -167,7 +185,7 @@ pub enum ColorMode {
 }
 
 /// Temas del CLI
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum CliTheme {
     /// Tema clásico (verde sobre negro)
     Classic,
@@ -186,7 +204,8 @@ pub enum CliTheme {
 }
 
 /// Key bindings del REPL
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
 pub struct KeyBindings {
     /// Ejecutar query
     pub execute: String,
@@ -208,7 +227,7 @@ pub struct KeyBindings {
 }
 
 /// Tipos de backend soportados
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum BackendType {
     /// SQLite (en memoria o archivo)
     Sqlite,
@@ -221,7 +240,7 @@ pub enum BackendType {
 }
 
 /// Modos SSL
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum SslMode {
     /// Requerir SSL
     Require,
@@ -234,7 +253,8 @@ pub enum SslMode {
 }
 
 /// Configuración de autenticación
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
+#[serde(default)]
 pub struct AuthConfig {
     /// Usuario
     pub username: Option<String>,
@@ -258,12 +278,15 @@ impl Default for GlobalConfig {
             debug: false,
             config_file: None,
             working_dir: PathBuf::from("."),
-            history_file: PathBuf::from(format!("{}/.noctra_history", home_dir)),
+            history_file: Some(PathBuf::from(format!("{}/.noctra_history", home_dir))),
             default_timeout: 30,
             default_row_limit: Some(1000),
             default_output_format: OutputFormat::Table,
             color_mode: ColorMode::Auto,
             theme: CliTheme::Classic,
+            pager: None,
+            query_log: None,
+            allowed_paths: Vec::new(),
         }
     }
 }
@@ -325,26 +348,59 @@ impl Default for KeyBindings {
 
 impl CliConfig {
     /// Cargar configuración desde archivo
-    pub fn load_from_file(path: &PathBuf) -> Result<Self, Box<dyn std::error::Error>> {
-        let content = std::fs::read_to_string(path)?;
-        let config: CliConfig = toml::from_str(&content)?;
-        Ok(config)
+    pub fn load_from_file(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        Self::from_toml_file(path)
     }
 
     /// Guardar configuración a archivo
-    pub fn save_to_file(&self, path: &PathBuf) -> Result<(), Box<dyn std::error::Error>> {
-        let content = toml::to_string_pretty(self)?;
-        std::fs::write(path, content)?;
+    pub fn save_to_file(&self, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        self.to_toml_file(path)
+    }
+
+    /// Cargar configuración desde un archivo TOML
+    ///
+    /// Los campos ausentes toman su valor por defecto.
+    pub fn from_toml_file(path: &Path) -> Result<Self, Box<dyn std::error::Error>> {
+        let content = std::fs::read_to_string(path)
+            .map_err(|e| format!("No se pudo leer {}: {}", path.display(), e))?;
+        config_from_toml(&content)
+    }
+
+    /// Escribir configuración a un archivo TOML (crea directorios padre)
+    pub fn to_toml_file(&self, path: &Path) -> Result<(), Box<dyn std::error::Error>> {
+        if let Some(parent) = path.parent() {
+            if !parent.as_os_str().is_empty() {
+                std::fs::create_dir_all(parent)?;
+            }
+        }
+        std::fs::write(path, self.to_toml()?)?;
         Ok(())
     }
 
+    /// Serializar configuración a TOML
+    pub fn to_toml(&self) -> Result<String, Box<dyn std::error::Error>> {
+        Ok(toml::to_string_pretty(self)?)
+    }
+
     /// Obtener archivo de configuración por defecto
+    ///
+    /// `$XDG_CONFIG_HOME/noctra/config.toml`, o `~/.config/noctra/config.toml`.
     pub fn default_config_path() -> Result<PathBuf, Box<dyn std::error::Error>> {
-        let home_dir = std::env::var("HOME")
-            .or_else(|_| std::env::var("USERPROFILE"))
-            .map_err(|e| format!("Cannot get home directory: {}", e))?;
+        if let Ok(xdg_home) = std::env::var("XDG_CONFIG_HOME") {
+            if !xdg_home.is_empty() {
+                return Ok(PathBuf::from(xdg_home).join("noctra").join("config.toml"));
+            }
+        }
+
+        Ok(home_dir()?
+            .join(".config")
+            .join("noctra")
+            .join("config.toml"))
+    }
 
-        Ok(PathBuf::from(format!("{}/.noctra/config.toml", home_dir)))
+    /// Ubicación anterior del archivo de configuración (`~/.noctra/config.toml`)
+    pub fn legacy_config_path() -> Result<PathBuf, Box<dyn std::error::Error>> {
+        Ok(home_dir()?.join(".noctra").join("config.toml"))
     }
 
     /// Validar configuración
@@ -365,6 +421,18 @@ impl CliConfig {
         Ok(())
     }
 
+    /// Opciones del executor derivadas de la configuración global
+    pub fn executor_options(&self) -> ExecutorOptions {
+        ExecutorOptions {
+            timeout: (self.global.default_timeout > 0)
+                .then(|| Duration::from_secs(self.global.default_timeout)),
+            max_rows: None,
+            join_row_limit: None,
+            cursor_idle_timeout: None,
+            read_only: false,
+        }
+    }
+
     /// Configuración para SQLite en memoria
     pub fn for_memory_sqlite() -> Self {
         let mut config = Self::default();
@@ -382,3 +450,98 @@ impl CliConfig {
         config
     }
 }
+
+/// Parsear configuración desde contenido TOML
+pub fn config_from_toml(content: &str) -> Result<CliConfig, Box<dyn std::error::Error>> {
+    let config: CliConfig = toml::from_str(content)?;
+    Ok(config)
+}
+
+/// Serialización de límites opcionales: TOML no tiene `null`, se usa 0 para "sin límite"
+mod optional_limit {
+    use serde::{Deserialize, Deserializer, Serializer};
+
+    pub fn serialize<S: Serializer>(
+        value: &Option<usize>,
+        serializer: S,
+    ) -> Result<S::Ok, S::Error> {
+        serializer.serialize_u64(value.unwrap_or(0) as u64)
+    }
+
+    pub fn deserialize<'de, D: Deserializer<'de>>(
+        deserializer: D,
+    ) -> Result<Option<usize>, D::Error> {
+        let value = usize::deserialize(deserializer)?;
+        Ok((value > 0).then_some(value))
+    }
+}
+
+/// Directorio home del usuario
+fn home_dir() -> Result<PathBuf, Box<dyn std::error::Error>> {
+    let home_dir = std::env::var("HOME")
+        .or_else(|_| std::env::var("USERPROFILE"))
+        .map_err(|e| format!("Cannot get home directory: {}", e))?;
+    Ok(PathBuf::from(home_dir))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn customized_config() -> CliConfig {
+        let mut config = CliConfig::default();
+        config.global.default_database = Some(PathBuf::from("/tmp/noctra.db"));
+        config.global.verbose = true;
+        config.global.default_row_limit = None;
+        config.global.default_output_format = OutputFormat::Custom("tsv".to_string());
+        config.global.color_mode = ColorMode::Never;
+        config.global.theme = CliTheme::Dark;
+        config.global.allowed_paths = vec![PathBuf::from("/srv/datos")];
+        config.repl.prompt = "sql> ".to_string();
+        config.repl.external_editor = Some("vim".to_string());
+        config.repl.key_bindings.exit = "Ctrl+Q".to_string();
+        config.batch.parameters = vec![("dept".to_string(), "10".to_string())];
+        config.batch.output_format = OutputFormat::Json;
+        config.batch.continue_on_error = true;
+        config.database.backend_type = BackendType::Postgres;
+        config.database.connection_string = "postgres://localhost/noctra".to_string();
+        config.database.ssl_mode = Some(SslMode::Require);
+        config.database.auth_config = Some(AuthConfig {
+            username: Some("admin".to_string()),
+            password: None,
+            credential_file: Some(PathBuf::from("/etc/noctra/creds")),
+        });
+        config
+    }
+
+    #[test]
+    fn test_toml_round_trip() {
+        let config = customized_config();
+
+        let toml = config.to_toml().unwrap();
+        let parsed = config_from_toml(&toml).unwrap();
+
+        assert_eq!(parsed, config);
+    }
+
+    #[test]
+    fn test_toml_file_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("nested").join("config.toml");
+        let config = customized_config();
+
+        config.to_toml_file(&path).unwrap();
+        let loaded = CliConfig::from_toml_file(&path).unwrap();
+
+        assert_eq!(loaded, config);
+    }
+
+    #[test]
+    fn test_partial_toml_uses_defaults() {
+        let config = config_from_toml("[repl]\nprompt = \"rql> \"\n").unwrap();
+
+        assert_eq!(config.repl.prompt, "rql> ");
+        assert_eq!(config.repl.history_size, ReplConfig::default().history_size);
+        assert_eq!(config.database, DatabaseConfig::default());
+    }
+}
@@ -8,10 +8,13 @@ use crossterm::{
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
 use ratatui::{backend::CrosstermBackend, Terminal};
+use std::collections::HashMap;
 use std::io::{stdout, Stdout};
 use std::time::Duration;
 
-use noctra_formlib::Form;
+use noctra_core::types::Parameters;
+use noctra_core::{Executor, ResultSet};
+use noctra_formlib::{DependencyGraph, Form, FormExecutionResult, FormPipelineExecutor};
 use noctra_tui::FormRenderer;
 
 /// Error del ejecutor interactivo
@@ -40,6 +43,7 @@ pub type InteractiveResult<T> = Result<T, InteractiveError>;
 /// Ejecutor de formularios interactivo con Ratatui
 pub struct InteractiveFormExecutor {
     renderer: FormRenderer,
+    dependencies: DependencyGraph,
     terminal: Terminal<CrosstermBackend<Stdout>>,
     running: bool,
 }
@@ -47,8 +51,21 @@ pub struct InteractiveFormExecutor {
 impl InteractiveFormExecutor {
     /// Crear nuevo ejecutor
     pub fn new(form: Form) -> InteractiveResult<Self> {
+        // Orden de evaluación de los campos calculados
+        let dependencies = DependencyGraph::from_form(&form)
+            .map_err(|e| InteractiveError::FormError(e.to_string()))?;
+
         // Crear renderer (ratatui se adapta automáticamente al tamaño)
-        let renderer = FormRenderer::new(form);
+        let mut renderer = FormRenderer::new(form);
+
+        // Calcular los campos que dependen de valores por defecto
+        let mut values = renderer.get_values();
+        let _ = dependencies.compute_all(&mut values);
+        for field_name in dependencies.evaluation_order() {
+            if let Some(value) = values.remove(field_name) {
+                let _ = renderer.set_field_value(field_name, value);
+            }
+        }
 
         // Configurar terminal
         enable_raw_mode().map_err(|e| InteractiveError::TerminalError(e.to_string()))?;
@@ -62,6 +79,7 @@ impl InteractiveFormExecutor {
 
         Ok(Self {
             renderer,
+            dependencies,
             terminal,
             running: true,
         })
@@ -78,6 +96,55 @@ impl InteractiveFormExecutor {
         result
     }
 
+    /// Ejecutar el pipeline de una acción con los valores del formulario
+    ///
+    /// Los valores (p. ej. los devueltos por `run`) son los parámetros de
+    /// texto del primer paso.
+    pub fn run_action_pipeline(
+        &self,
+        action_name: &str,
+        values: &HashMap<String, String>,
+        executor: &Executor,
+    ) -> InteractiveResult<ResultSet> {
+        let pipeline = self
+            .renderer
+            .form
+            .actions
+            .get(action_name)
+            .and_then(|action| action.pipeline.as_ref())
+            .ok_or_else(|| {
+                InteractiveError::FormError(format!(
+                    "La acción '{}' no tiene pipeline",
+                    action_name
+                ))
+            })?;
+
+        let params = Parameters::from_text_map(values.clone());
+
+        FormPipelineExecutor::run(pipeline, params, executor)
+            .map_err(|e| InteractiveError::FormError(e.to_string()))
+    }
+
+    /// Ejecutar una acción con los valores del formulario
+    ///
+    /// Las acciones Insert/Update/Delete escriben en la fuente activa del
+    /// executor (o en su backend); ver `FormPipelineExecutor::run_action`.
+    pub fn run_action(
+        &self,
+        action_name: &str,
+        values: &HashMap<String, String>,
+        executor: &Executor,
+    ) -> InteractiveResult<FormExecutionResult> {
+        let action = self.renderer.form.actions.get(action_name).ok_or_else(|| {
+            InteractiveError::FormError(format!("La acción '{}' no existe", action_name))
+        })?;
+
+        let params = Parameters::from_text_map(values.clone());
+
+        FormPipelineExecutor::run_action(action, params, executor)
+            .map_err(|e| InteractiveError::FormError(e.to_string()))
+    }
+
     /// Limpiar terminal
     fn cleanup_terminal(&mut self) -> InteractiveResult<()> {
         disable_raw_mode().map_err(|e| InteractiveError::TerminalError(e.to_string()))?;
@@ -90,6 +157,7 @@ impl InteractiveFormExecutor {
     }
 
     /// Loop principal de eventos
+    #[allow(clippy::collapsible_match)]
     fn run_loop(&mut self) -> InteractiveResult<Option<std::collections::HashMap<String, String>>> {
         while self.running {
             // Renderizar usando ratatui
@@ -169,7 +237,7 @@ impl InteractiveFormExecutor {
 
             // Backspace - Eliminar carácter
             KeyCode::Backspace => {
-                if let Some(field_name) = self.renderer.get_focused_field().map(|s| s.to_string()) {
+                if let Some(field_name) = self.editable_focused_field() {
                     let current_value = self
                         .renderer
                         .get_field_value(&field_name)
@@ -178,6 +246,7 @@ impl InteractiveFormExecutor {
                     if !current_value.is_empty() {
                         let new_value = current_value[..current_value.len() - 1].to_string();
                         let _ = self.renderer.set_field_value(&field_name, new_value);
+                        self.recompute_dependents(&field_name);
                     }
                 }
                 true
@@ -185,7 +254,7 @@ impl InteractiveFormExecutor {
 
             // Caracteres normales
             KeyCode::Char(c) => {
-                if let Some(field_name) = self.renderer.get_focused_field().map(|s| s.to_string()) {
+                if let Some(field_name) = self.editable_focused_field() {
                     let current_value = self
                         .renderer
                         .get_field_value(&field_name)
@@ -193,6 +262,7 @@ impl InteractiveFormExecutor {
                         .to_string();
                     let new_value = format!("{}{}", current_value, c);
                     let _ = self.renderer.set_field_value(&field_name, new_value);
+                    self.recompute_dependents(&field_name);
                 }
                 true
             }
@@ -200,6 +270,26 @@ impl InteractiveFormExecutor {
             _ => true,
         }
     }
+
+    /// Campo enfocado si se puede editar (los calculados no)
+    fn editable_focused_field(&self) -> Option<String> {
+        self.renderer
+            .get_focused_field()
+            .filter(|field_name| !self.dependencies.is_computed(field_name))
+            .map(|s| s.to_string())
+    }
+
+    /// Recalcular los campos calculados que dependen de `field_name`
+    fn recompute_dependents(&mut self, field_name: &str) {
+        let mut values = self.renderer.get_values();
+        // Una fórmula que falla (p. ej. división por cero) deja el campo vacío
+        let _ = self.dependencies.recompute(field_name, &mut values);
+        for computed in self.dependencies.dependents_of(field_name) {
+            if let Some(value) = values.remove(computed) {
+                let _ = self.renderer.set_field_value(computed, value);
+            }
+        }
+    }
 }
 
 impl Drop for InteractiveFormExecutor {
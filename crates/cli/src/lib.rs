@@ -4,6 +4,7 @@
 //! form execution y daemon mode.
 
 pub mod app;
+pub mod batch;
 pub mod cli;
 pub mod commands;
 pub mod config;
@@ -14,7 +15,10 @@ pub mod repl;
 pub use app::{build_cli as build_app, NoctraApp as App};
 pub use cli::{build_cli, NoctraApp, NoctraArgs, ReplArgs};
 pub use commands::{execute_command, CommandContext, CommandResult};
-pub use config::{CliConfig, GlobalConfig};
+pub use config::{config_from_toml, CliConfig, GlobalConfig};
 pub use interactive_form::InteractiveFormExecutor;
-pub use output::{format_result_set, CsvFormatter, JsonFormatter, OutputFormatter, TableFormatter};
+pub use output::{
+    format_result_set, CsvFormatter, JsonFormatter, OutputFormatter, ParquetFormatter,
+    TableFormatter, XlsxFormatter,
+};
 pub use repl::{Repl, ReplHandler};
@@ -11,7 +11,9 @@ use clap::Parser;
 use log::error;
 use std::process::ExitCode;
 
+use noctra_cli::output::format_error;
 use noctra_cli::{NoctraApp, NoctraArgs};
+use noctra_core::NoctraError;
 
 #[tokio::main]
 async fn main() -> ExitCode {
@@ -20,6 +22,8 @@ async fn main() -> ExitCode {
 
     // Parse CLI arguments
     let args = NoctraArgs::parse();
+    // Con --file, stdout lleva los resultados (y los avisos de los comandos RQL)
+    let quiet = args.file.is_some();
 
     // Build and run the application
     let app = match NoctraApp::new(args) {
@@ -32,11 +36,16 @@ async fn main() -> ExitCode {
 
     match app.run().await {
         Ok(_) => {
-            println!("👋 ¡Noctra finalizado correctamente!");
+            if !quiet {
+                println!("👋 ¡Noctra finalizado correctamente!");
+            }
             ExitCode::from(0)
         }
         Err(e) => {
-            error!("❌ Error crítico: {}", e);
+            match e.downcast_ref::<NoctraError>() {
+                Some(error) => error!("{}", format_error(error)),
+                None => error!("❌ Error crítico: {}", e),
+            }
             error!("💡 Para ayuda, prueba: noctra --help");
             ExitCode::from(1)
         }
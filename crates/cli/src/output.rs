@@ -1,8 +1,18 @@
 //! Formateadores de output para Noctra
 
-use noctra_core::ResultSet;
+use noctra_core::export::{write_xlsx, xlsx_to_buffer, XlsxOptions};
+use noctra_core::{NoctraError, ResultSet};
+use noctra_duckdb::{DuckDBSource, QueryOptions};
 use serde_json;
-use std::io::{stdout, Write};
+use std::io::{stdout, IsTerminal, Write};
+use std::process::{Command, Stdio};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// Paginador por defecto para resultados que no caben en la terminal
+pub const DEFAULT_PAGER: &str = "less -R";
+
+/// Variable de entorno que reemplaza al paginador configurado
+pub const PAGER_ENV: &str = "NOCTRA_PAGER";
 
 /// Trait para formateadores de output
 pub trait OutputFormatter {
@@ -14,7 +24,104 @@ pub trait OutputFormatter {
 }
 
 /// Formateador de tabla
-pub struct TableFormatter;
+///
+/// Con paginador, `write_paged` envía la salida al paginador cuando tiene
+/// más líneas que la terminal.
+#[derive(Debug, Clone, Default)]
+pub struct TableFormatter {
+    /// Comando del paginador (None = sin paginar)
+    pager: Option<String>,
+
+    /// Altura de la terminal (None = la de `crossterm::terminal::size()`)
+    terminal_height: Option<u16>,
+}
+
+impl TableFormatter {
+    /// Crear formateador sin paginador
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Paginar con `pager_cmd` (por defecto `less -R`)
+    ///
+    /// `NOCTRA_PAGER` tiene prioridad sobre el comando indicado; un comando
+    /// vacío desactiva la paginación.
+    pub fn with_pager(pager_cmd: Option<String>) -> Self {
+        Self::resolve_pager(std::env::var(PAGER_ENV).ok(), pager_cmd)
+    }
+
+    /// Paginar con `env_pager` (el valor de `NOCTRA_PAGER`) o con `pager_cmd`
+    fn resolve_pager(env_pager: Option<String>, pager_cmd: Option<String>) -> Self {
+        let pager = env_pager
+            .or(pager_cmd)
+            .unwrap_or_else(|| DEFAULT_PAGER.to_string());
+
+        Self {
+            pager: Some(pager).filter(|cmd| !cmd.trim().is_empty()),
+            terminal_height: None,
+        }
+    }
+
+    /// Fijar la altura de la terminal en lugar de consultarla
+    pub fn with_terminal_height(mut self, rows: u16) -> Self {
+        self.terminal_height = Some(rows);
+        self
+    }
+
+    /// Escribir `output` en stdout o, si no cabe en la terminal, en el paginador
+    ///
+    /// Sin altura fija solo se pagina cuando stdout es una terminal. Si el
+    /// paginador no puede lanzarse se escribe en stdout. Devuelve si se usó
+    /// el paginador.
+    pub fn write_paged(&self, output: &str) -> std::io::Result<bool> {
+        if let Some(pager) = &self.pager {
+            let height = match self.terminal_height {
+                Some(rows) => Some(rows),
+                None if stdout().is_terminal() => {
+                    crossterm::terminal::size().ok().map(|(_, rows)| rows)
+                }
+                None => None,
+            };
+
+            if height.is_some_and(|rows| output.lines().count() > rows as usize) {
+                match spawn_pager(pager) {
+                    Ok(mut child) => {
+                        if let Some(mut stdin) = child.stdin.take() {
+                            // El usuario puede cerrar el paginador antes del final
+                            if let Err(e) = stdin.write_all(output.as_bytes()) {
+                                if e.kind() != std::io::ErrorKind::BrokenPipe {
+                                    return Err(e);
+                                }
+                            }
+                        }
+                        child.wait()?;
+                        return Ok(true);
+                    }
+                    Err(e) => log::warn!("No se pudo lanzar el paginador '{}': {}", pager, e),
+                }
+            }
+        }
+
+        let mut stdout = stdout();
+        stdout.write_all(output.as_bytes())?;
+        stdout.flush()?;
+        Ok(false)
+    }
+}
+
+/// Lanzar el paginador a través del shell, con la salida por su stdin
+fn spawn_pager(pager: &str) -> std::io::Result<std::process::Child> {
+    let mut command = if cfg!(windows) {
+        let mut command = Command::new("cmd");
+        command.args(["/C", pager]);
+        command
+    } else {
+        let mut command = Command::new("sh");
+        command.args(["-c", pager]);
+        command
+    };
+    command.stdin(Stdio::piped()).spawn()
+}
 
 impl OutputFormatter for TableFormatter {
     fn format_result(&self, result: &ResultSet) -> String {
@@ -92,14 +199,110 @@ impl OutputFormatter for JsonFormatter {
     }
 }
 
+/// Formateador XLSX (Excel)
+///
+/// XLSX es un formato binario: `write_result` escribe el workbook completo,
+/// mientras que `format_result` solo retorna un resumen legible.
+pub struct XlsxFormatter {
+    options: XlsxOptions,
+}
+
+impl XlsxFormatter {
+    pub fn new(options: XlsxOptions) -> Self {
+        Self { options }
+    }
+
+    /// Escribir result set directamente a un archivo XLSX
+    pub fn write_to_path(&self, result: &ResultSet, path: &str) -> noctra_core::Result<()> {
+        write_xlsx(result, path, &self.options)
+    }
+}
+
+impl Default for XlsxFormatter {
+    fn default() -> Self {
+        Self::new(XlsxOptions::default())
+    }
+}
+
+impl OutputFormatter for XlsxFormatter {
+    fn format_result(&self, result: &ResultSet) -> String {
+        format!(
+            "[XLSX: hoja '{}', {} filas, {} columnas]",
+            self.options.sheet_name,
+            result.row_count(),
+            result.column_count()
+        )
+    }
+
+    fn write_result(&self, result: &ResultSet, writer: &mut dyn Write) -> std::io::Result<()> {
+        let buffer = xlsx_to_buffer(result, &self.options)
+            .map_err(|e| std::io::Error::other(e.to_string()))?;
+        writer.write_all(&buffer)
+    }
+}
+
+/// Formateador Parquet
+///
+/// Parquet también es binario y lo escribe DuckDB: el result set se carga
+/// en una base en memoria y se copia con `COPY ... (FORMAT PARQUET)` a un
+/// archivo temporal, cuyo contenido va al writer.
+#[derive(Debug, Clone, Default)]
+pub struct ParquetFormatter;
+
+impl ParquetFormatter {
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// Bytes del archivo Parquet con el result set
+    pub fn to_bytes(&self, result: &ResultSet) -> noctra_core::Result<Vec<u8>> {
+        static NEXT_FILE: AtomicUsize = AtomicUsize::new(0);
+
+        let mut source = DuckDBSource::new_in_memory()?;
+        source.register_result_set("resultado", result)?;
+
+        let path = std::env::temp_dir().join(format!(
+            "noctra-{}-{}.parquet",
+            std::process::id(),
+            NEXT_FILE.fetch_add(1, Ordering::Relaxed)
+        ));
+        let copy = format!(
+            "COPY resultado TO '{}' (FORMAT PARQUET)",
+            path.to_string_lossy().replace('\'', "''")
+        );
+        let written = source
+            .query_with_options(&copy, QueryOptions::default())
+            .and_then(|_| std::fs::read(&path).map_err(NoctraError::from));
+        let _ = std::fs::remove_file(&path);
+        written
+    }
+}
+
+impl OutputFormatter for ParquetFormatter {
+    fn format_result(&self, result: &ResultSet) -> String {
+        format!(
+            "[Parquet: {} filas, {} columnas]",
+            result.row_count(),
+            result.column_count()
+        )
+    }
+
+    fn write_result(&self, result: &ResultSet, writer: &mut dyn Write) -> std::io::Result<()> {
+        let buffer = self
+            .to_bytes(result)
+            .map_err(|e| std::io::Error::other(e.to_string()))?;
+        writer.write_all(&buffer)
+    }
+}
+
 /// Utility para output estándar
 pub fn format_output(result: &ResultSet, format_type: &crate::config::OutputFormat) -> String {
     match format_type {
-        crate::config::OutputFormat::Table => TableFormatter.format_result(result),
+        crate::config::OutputFormat::Table => TableFormatter::new().format_result(result),
         crate::config::OutputFormat::Csv => CsvFormatter::new(',').format_result(result),
         crate::config::OutputFormat::Json => JsonFormatter::new(false).format_result(result),
         crate::config::OutputFormat::Custom(_) => "Custom format not implemented".to_string(),
-        _ => TableFormatter.format_result(result),
+        _ => TableFormatter::new().format_result(result),
     }
 }
 
@@ -110,18 +313,98 @@ pub fn write_to_stdout(
 ) -> std::io::Result<()> {
     let mut stdout = stdout();
     match format_type {
-        crate::config::OutputFormat::Table => TableFormatter.write_result(result, &mut stdout),
+        crate::config::OutputFormat::Table => {
+            TableFormatter::new().write_result(result, &mut stdout)
+        }
         crate::config::OutputFormat::Csv => {
             CsvFormatter::new(',').write_result(result, &mut stdout)
         }
         crate::config::OutputFormat::Json => {
             JsonFormatter::new(false).write_result(result, &mut stdout)
         }
-        _ => TableFormatter.write_result(result, &mut stdout),
+        _ => TableFormatter::new().write_result(result, &mut stdout),
     }
 }
 
 /// Helper para formatear result set como tabla (usado por REPL)
 pub fn format_result_set(result: &ResultSet) -> String {
-    TableFormatter.format_result(result)
+    TableFormatter::new().format_result(result)
+}
+
+/// Mensaje de error para la terminal: código estable y sugerencia si la hay
+///
+/// Los errores internos muestran un mensaje genérico; el detalle se deja en
+/// el log.
+pub fn format_error(error: &NoctraError) -> String {
+    if error.is_internal() {
+        log::error!("{}", error);
+    }
+    let mut message = format!("❌ {} [{}]", error.user_message(), error.code());
+    if let Some(snippet) = error.snippet() {
+        message.push_str(&format!("\n{}", snippet));
+    }
+    if let Some(hint) = error.hint() {
+        message.push_str(&format!("\n💡 {}", hint));
+    }
+    message
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use noctra_core::{Column, Row, Value};
+
+    fn numbers(count: i64) -> ResultSet {
+        let mut result = ResultSet::new(vec![Column::new("n", "INTEGER", 0)]);
+        for n in 0..count {
+            result.add_row(Row::new(vec![Value::Integer(n)]));
+        }
+        result
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_long_output_goes_through_pager() {
+        let dir = tempfile::tempdir().unwrap();
+        let captured = dir.path().join("pager.txt");
+        // NOCTRA_PAGER tiene prioridad sobre el paginador configurado
+        let formatter = TableFormatter::resolve_pager(
+            Some(format!("cat > '{}'", captured.display())),
+            Some("false".to_string()),
+        )
+        .with_terminal_height(5);
+
+        let output = formatter.format_result(&numbers(50));
+        assert!(formatter.write_paged(&output).unwrap());
+        assert_eq!(std::fs::read_to_string(&captured).unwrap(), output);
+    }
+
+    #[test]
+    fn test_short_output_is_not_paged() {
+        let formatter = TableFormatter::resolve_pager(None, Some("false".to_string()))
+            .with_terminal_height(100);
+        let output = formatter.format_result(&numbers(3));
+        assert!(!formatter.write_paged(&output).unwrap());
+
+        // Sin paginador nunca se pagina
+        let formatter = TableFormatter::new().with_terminal_height(1);
+        assert!(!formatter.write_paged(&output).unwrap());
+        let formatter =
+            TableFormatter::resolve_pager(Some(" ".to_string()), None).with_terminal_height(1);
+        assert!(!formatter.write_paged(&output).unwrap());
+    }
+
+    #[test]
+    fn test_format_error_with_code_and_hint() {
+        let error = NoctraError::not_found("fuente", "ventas");
+        assert_eq!(
+            format_error(&error),
+            "❌ No encontrado: fuente 'ventas' [not_found]\n💡 SHOW SOURCES lista las fuentes registradas"
+        );
+
+        let error = NoctraError::Internal("lock envenenado en /tmp/x".to_string());
+        let message = format_error(&error);
+        assert!(message.ends_with("[internal]"));
+        assert!(!message.contains("/tmp/x"));
+    }
 }
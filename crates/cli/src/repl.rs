@@ -1,13 +1,23 @@
 //! REPL (Read-Eval-Print Loop) para Noctra
 
 use crate::cli::ReplArgs;
-use crate::config::CliConfig;
-use crate::output::format_result_set;
-use noctra_core::{Executor, NoctraError, RqlQuery, Session, SqliteBackend};
-use noctra_parser::{RqlProcessor, RqlStatement};
+use crate::config::{BackendType, CliConfig};
+use crate::output::{format_error, format_result_set, TableFormatter};
+use noctra_core::assertion::check_assertion;
+use noctra_core::{
+    run_script, Executor, InMemoryRecorder, NoctraError, Parameters, ResultSet, RqlQuery,
+    ScriptOptions, Session, SourceDescriptor, SqliteBackend, StatementOutcome, StatementResult,
+    Value,
+};
+use noctra_parser::{RqlAst, RqlProcessor, RqlStatement, TransactionCommand};
+use rustyline::error::ReadlineError;
+use rustyline::DefaultEditor;
 use std::collections::HashMap;
+use std::fs::{self, OpenOptions};
 use std::io::{self, Write};
+use std::path::{Path, PathBuf};
 use std::sync::Arc;
+use std::time::Duration;
 
 type Result<T> = std::result::Result<T, NoctraError>;
 
@@ -23,6 +33,12 @@ pub struct ReplHandler {
     /// Historial de comandos
     history: Vec<String>,
 
+    /// Entradas de `history` que ya están guardadas en disco
+    saved_history: usize,
+
+    /// Archivo de historial persistente (None = solo en memoria)
+    history_file: Option<PathBuf>,
+
     /// Contador de líneas
     line_count: usize,
 }
@@ -55,40 +71,215 @@ pub struct Repl {
     /// Executor de queries
     executor: Executor,
 
+    /// Métricas de las consultas del executor (`:stats`)
+    metrics: Arc<InMemoryRecorder>,
+
     /// Sesión actual
     session: Session,
+
+    /// Archivo donde se guarda la sesión (`--session`)
+    session_file: Option<PathBuf>,
+
+    /// Editor de línea (historial navegable y búsqueda inversa con Ctrl+R)
+    editor: Option<DefaultEditor>,
+
+    /// Último resultado con columnas, sobre el que trabajan MAP y FILTER
+    last_result: Option<ResultSet>,
+
+    /// Texto con el que empieza la primera línea del editor
+    initial_input: Option<String>,
+}
+
+/// Crear el executor del backend configurado (SQLite o PostgreSQL)
+pub(crate) fn create_executor(config: &CliConfig) -> Result<Executor> {
+    let connection_string = &config.database.connection_string;
+    let mut executor = match config.database.backend_type {
+        #[cfg(feature = "postgres")]
+        BackendType::Postgres => Executor::new_postgres(connection_string.as_str())?,
+        #[cfg(not(feature = "postgres"))]
+        BackendType::Postgres => {
+            return Err(NoctraError::Configuration(
+                "Noctra se compiló sin soporte PostgreSQL (feature `postgres`)".to_string(),
+            ))
+        }
+        _ => {
+            let backend = SqliteBackend::with_file(connection_string)?;
+            Executor::new(Arc::new(backend))
+        }
+    };
+    executor.set_options(config.executor_options());
+    if let Some(query_log) = &config.global.query_log {
+        executor.set_query_logger(Box::new(noctra_core::JsonlQueryLogger::open(query_log)?));
+    }
+    noctra_duckdb::register_factories(
+        executor.source_registry_mut(),
+        &noctra_duckdb::DuckDBConfig {
+            allowed_paths: config.global.allowed_paths.clone(),
+            ..noctra_duckdb::DuckDBConfig::default()
+        },
+    );
+    Ok(executor)
+}
+
+/// Parsear `query` con `RqlProcessor`
+///
+/// El parser corre en un hilo con su propio runtime para no chocar con el
+/// runtime de Tokio en el que ya se ejecuta el REPL.
+pub(crate) fn parse_rql(query: &str) -> Result<RqlAst> {
+    let query_str = query.to_string();
+    let result = std::thread::spawn(move || {
+        let rt = tokio::runtime::Runtime::new().unwrap();
+        let processor = RqlProcessor::new();
+        rt.block_on(async { processor.process(&query_str).await })
+    })
+    .join();
+
+    match result {
+        Ok(r) => r,
+        Err(_) => {
+            return Err(NoctraError::Internal(
+                "Thread panic during parsing".to_string(),
+            ))
+        }
+    }
+    .map_err(NoctraError::from)
+}
+
+/// Línea de DESCRIBE para una columna: `nombre (TIPO PRIMARY KEY NOT NULL DEFAULT x)`
+fn describe_column(col: &noctra_core::ColumnInfo) -> String {
+    let constraints = col.constraints();
+    if constraints.is_empty() {
+        format!("{} ({})", col.name, col.data_type)
+    } else {
+        format!("{} ({} {})", col.name, col.data_type, constraints)
+    }
+}
+
+/// Interpretar un timeout de `:set timeout=...` (`5s`, `500ms`, `2m`, `off`)
+///
+/// Devuelve `Some(None)` para desactivarlo y `None` si el valor no es válido.
+fn parse_timeout(value: &str) -> Option<Option<Duration>> {
+    let value = value.trim().to_lowercase();
+    if matches!(value.as_str(), "off" | "0" | "none") {
+        return Some(None);
+    }
+    let (number, unit) = value.split_at(value.find(|c: char| !c.is_ascii_digit())?);
+    let number: u64 = number.parse().ok()?;
+    let duration = match unit {
+        "ms" => Duration::from_millis(number),
+        "s" => Duration::from_secs(number),
+        "m" => Duration::from_secs(number.checked_mul(60)?),
+        _ => return None,
+    };
+    Some(Some(duration).filter(|d| !d.is_zero()))
 }
 
 impl Repl {
     /// Crear nuevo REPL
     pub fn new(config: CliConfig, args: ReplArgs) -> Result<Self> {
+        let session_file = args.session.clone();
         let handler = ReplHandler::new(config.clone(), args)?;
 
-        // Crear backend SQLite
-        let backend = SqliteBackend::with_file(&config.database.connection_string)?;
-        let executor = Executor::new(Arc::new(backend));
-
-        // Crear sesión
-        let session = Session::new();
+        let mut executor = create_executor(&config)?;
+        let metrics = Arc::new(InMemoryRecorder::new());
+        executor.set_metrics_recorder(metrics.clone());
+
+        // Crear sesión (o restaurar la de --session; si está dañada, una nueva)
+        let session = match &session_file {
+            Some(path) => {
+                let (session, error) = Session::restore(path);
+                if let Some(e) = error {
+                    println!(
+                        "⚠️  No se pudo restaurar la sesión, se inicia una nueva: {}",
+                        e
+                    );
+                }
+                session
+            }
+            None => Session::new(),
+        };
 
         Ok(Self {
             config,
             handler,
             executor,
+            metrics,
             session,
+            session_file,
+            editor: None,
+            last_result: None,
+            initial_input: None,
         })
     }
 
+    /// Executor de las consultas del REPL
+    pub fn executor(&self) -> &Executor {
+        &self.executor
+    }
+
+    /// Definir una variable de sesión (`#nombre` en las consultas)
+    pub fn set_variable<T: Into<String>, V: Into<Value>>(&mut self, name: T, value: V) {
+        self.session.set_variable(name, value);
+    }
+
+    /// Empezar `run` con `input` ya escrito en el editor (p. ej. el SQL del
+    /// asistente de consultas)
+    pub fn set_initial_input<T: Into<String>>(&mut self, input: T) {
+        self.initial_input = Some(input.into());
+    }
+
     /// Ejecutar REPL
     pub async fn run(&mut self) -> Result<()> {
         println!("🎯 Noctra REPL iniciado - Escribe 'help' para ayuda");
 
+        if let Some(path) = self.handler.history_file.clone() {
+            if let Err(e) = self.load_history(&path) {
+                println!("⚠️  No se pudo cargar el historial: {}", e);
+            }
+        }
+        if self.session_file.is_some() {
+            self.restore_session_sources();
+        }
+
+        // Sin editor de línea (p. ej. terminal no soportada) se lee stdin directo
+        let editor_config = rustyline::Config::builder()
+            .max_history_size(self.config.repl.history_size)
+            .map(|builder| builder.build())
+            .unwrap_or_default();
+        self.editor = DefaultEditor::with_config(editor_config).ok();
+        if let Some(editor) = self.editor.as_mut() {
+            let session_history = self.session_file.as_ref().map(|_| self.session.history());
+            for entry in self
+                .handler
+                .history
+                .iter()
+                .chain(session_history.into_iter().flatten())
+            {
+                let _ = editor.add_history_entry(entry.as_str());
+            }
+        }
+
         loop {
             // Mostrar prompt
             let prompt = self.get_prompt();
 
             // Leer input
-            let input = read_input(&prompt)?;
+            let initial = self.initial_input.take();
+            let input = match self.editor.as_mut() {
+                Some(editor) => match match &initial {
+                    Some(initial) => editor.readline_with_initial(&prompt, (initial, "")),
+                    None => editor.readline(&prompt),
+                } {
+                    Ok(line) => {
+                        let _ = editor.add_history_entry(line.as_str());
+                        line.trim().to_string()
+                    }
+                    Err(ReadlineError::Interrupted) => continue,
+                    Err(ReadlineError::Eof) => break,
+                    Err(e) => return Err(NoctraError::Io(e.to_string())),
+                },
+                None => read_input(&prompt)?,
+            };
 
             // Procesar input
             if self.process_input(&input)? {
@@ -96,10 +287,83 @@ impl Repl {
             }
         }
 
+        if let Some(path) = self.handler.history_file.clone() {
+            if let Err(e) = self.save_history(&path) {
+                println!("⚠️  No se pudo guardar el historial: {}", e);
+            }
+        }
+
         println!("👋 ¡Hasta luego!");
         Ok(())
     }
 
+    /// Cargar historial desde un archivo (una entrada por línea)
+    ///
+    /// Las entradas cargadas quedan al inicio del historial en memoria y se
+    /// consideran ya guardadas. Un archivo inexistente equivale a historial
+    /// vacío.
+    pub fn load_history(&mut self, path: &Path) -> Result<Vec<String>> {
+        let content = match fs::read_to_string(path) {
+            Ok(content) => content,
+            Err(e) if e.kind() == io::ErrorKind::NotFound => String::new(),
+            Err(e) => return Err(e.into()),
+        };
+
+        let mut entries: Vec<String> = content
+            .lines()
+            .filter(|line| !line.trim().is_empty())
+            .map(String::from)
+            .collect();
+        let max_history = self.config.repl.history_size;
+        if entries.len() > max_history {
+            entries.drain(..entries.len() - max_history);
+        }
+
+        let session_entries = self.handler.history.split_off(self.handler.saved_history);
+        self.handler.history = entries.clone();
+        self.handler.saved_history = self.handler.history.len();
+        self.handler.history.extend(session_entries);
+
+        Ok(entries)
+    }
+
+    /// Guardar en el archivo de historial los comandos aún no guardados
+    ///
+    /// Los comandos nuevos se agregan al final; si el archivo supera
+    /// `repl.history_size` líneas se reescribe con las más recientes.
+    pub fn save_history(&mut self, path: &Path) -> Result<()> {
+        let new_entries: Vec<String> = self.handler.history[self.handler.saved_history..]
+            .iter()
+            .map(|entry| entry.replace(['\r', '\n'], " "))
+            .collect();
+        if new_entries.is_empty() {
+            return Ok(());
+        }
+
+        if let Some(parent) = path.parent().filter(|p| !p.as_os_str().is_empty()) {
+            fs::create_dir_all(parent)?;
+        }
+
+        let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+        for entry in &new_entries {
+            writeln!(file, "{}", entry)?;
+        }
+        drop(file);
+        self.handler.saved_history = self.handler.history.len();
+
+        // Recortar al máximo configurado
+        let content = fs::read_to_string(path)?;
+        let lines: Vec<&str> = content.lines().collect();
+        let max_history = self.config.repl.history_size;
+        if lines.len() > max_history {
+            let mut trimmed = lines[lines.len() - max_history..].join("\n");
+            trimmed.push('\n');
+            fs::write(path, trimmed)?;
+        }
+
+        Ok(())
+    }
+
     /// Obtener prompt actual
     fn get_prompt(&self) -> String {
         match &self.handler.state {
@@ -139,9 +403,57 @@ impl Repl {
 
         // Agregar a historial
         self.handler.history.push(input.to_string());
+        self.session.push_history(input);
 
         // Procesar como SQL/RQL
-        self.execute_query(input)
+        let result = self.execute_query(input);
+        self.autosave_session();
+        result
+    }
+
+    /// Volver a ejecutar los USE de la sesión restaurada
+    fn restore_session_sources(&mut self) {
+        let sources = self.session.sources().to_vec();
+        let active = self.session.active_source().map(str::to_string);
+        for source in &sources {
+            if let Err(e) =
+                self.handle_use_source(&source.path, source.alias.as_deref(), &source.options)
+            {
+                println!(
+                    "⚠️  No se pudo restaurar la fuente '{}': {}",
+                    source.name(),
+                    e
+                );
+                // No volver a guardar una fuente que ya no se puede abrir
+                self.session.forget_source(source.name());
+            }
+        }
+        if let Some(alias) = active {
+            if self
+                .executor
+                .source_registry_mut()
+                .set_active(&alias)
+                .is_ok()
+            {
+                self.session.set_active_source(Some(alias));
+            }
+        }
+    }
+
+    /// Guardar la sesión en el archivo de `--session`
+    fn autosave_session(&mut self) {
+        let Some(path) = &self.session_file else {
+            return;
+        };
+        let active = self
+            .executor
+            .source_registry()
+            .active_alias()
+            .map(str::to_string);
+        self.session.set_active_source(active);
+        if let Err(e) = self.session.save_to(path) {
+            println!("⚠️  No se pudo guardar la sesión: {}", e);
+        }
     }
 
     /// Manejar comandos especiales
@@ -163,10 +475,14 @@ impl Repl {
                 self.show_config();
                 Ok(false)
             }
-            ":status" | ":stats" => {
+            ":status" => {
                 self.show_status();
                 Ok(false)
             }
+            ":stats" => {
+                self.show_stats();
+                Ok(false)
+            }
             cmd => {
                 if cmd.starts_with(":set ") {
                     self.handle_set_command(cmd);
@@ -181,90 +497,245 @@ impl Repl {
 
     /// Ejecutar query SQL/RQL
     fn execute_query(&mut self, query: &str) -> Result<bool> {
-        // Parsear query con RqlProcessor en thread separado
-        // para evitar conflictos con runtime de Tokio existente
-        let query_str = query.to_string();
-        let result = std::thread::spawn(move || {
-            let rt = tokio::runtime::Runtime::new().unwrap();
-            let processor = RqlProcessor::new();
-            rt.block_on(async {
-                processor.process(&query_str).await
-            })
-        }).join();
-
-        let ast = match result {
-            Ok(r) => r,
-            Err(_) => return Err(NoctraError::Internal("Thread panic during parsing".to_string())),
-        }.map_err(|e| NoctraError::Internal(format!("Parse error: {}", e)))?;
-
-        // Procesar cada statement
-        for statement in &ast.statements {
-            match statement {
-                RqlStatement::Sql { sql, .. } => {
-                    // Ejecutar SQL normal
-                    self.execute_sql_statement(sql)?;
-                }
+        for statement in &parse_rql(query)?.statements {
+            self.execute_statement(statement)?;
+        }
+        Ok(false)
+    }
 
-                RqlStatement::UseSource { path, alias, options } => {
-                    self.handle_use_source(path, alias.as_deref(), options)?;
-                }
+    /// Ejecutar una sentencia RQL ya parseada
+    pub(crate) fn execute_statement(&mut self, statement: &RqlStatement) -> Result<()> {
+        match statement {
+            RqlStatement::Sql {
+                sql,
+                is_duckdb_specific,
+                ..
+            } => {
+                // Ejecutar SQL normal
+                self.execute_sql_statement(sql, *is_duckdb_specific)?;
+            }
 
-                RqlStatement::ShowSources => {
-                    self.handle_show_sources()?;
-                }
+            RqlStatement::UseSource {
+                path,
+                alias,
+                options,
+            } => {
+                self.handle_use_source(path, alias.as_deref(), options)?;
+            }
 
-                RqlStatement::ShowTables { source } => {
-                    self.handle_show_tables(source.as_deref())?;
-                }
+            RqlStatement::UseSourceAlias { alias } => {
+                self.handle_use_source_alias(alias)?;
+            }
 
-                RqlStatement::ShowVars => {
-                    self.handle_show_vars()?;
-                }
+            RqlStatement::ShowSources => {
+                self.handle_show_sources()?;
+            }
 
-                RqlStatement::Describe { source, table } => {
-                    self.handle_describe(source.as_deref(), table)?;
-                }
+            RqlStatement::ShowTables { source } => {
+                self.handle_show_tables(source.as_deref())?;
+            }
 
-                RqlStatement::Let { variable, expression } => {
-                    self.handle_let(variable, expression)?;
-                }
+            RqlStatement::ShowVars => {
+                self.handle_show_vars()?;
+            }
 
-                RqlStatement::Unset { variables } => {
-                    self.handle_unset(variables)?;
-                }
+            RqlStatement::Describe { source, table } => {
+                self.handle_describe(source.as_deref(), table)?;
+            }
 
-                RqlStatement::Import { file, table, options } => {
-                    self.handle_import(file, table, options)?;
-                }
+            RqlStatement::Profile { source, table } => {
+                self.handle_profile(source.as_deref(), table)?;
+            }
 
-                RqlStatement::Export { query, file, format, options } => {
-                    self.handle_export(query, file, format, options)?;
-                }
+            RqlStatement::Sample {
+                source,
+                table,
+                limit,
+            } => {
+                self.handle_sample(source.as_deref(), table, *limit)?;
+            }
 
-                RqlStatement::Map { expressions } => {
-                    self.handle_map(expressions)?;
-                }
+            RqlStatement::Checkpoint { source } => {
+                self.handle_checkpoint(source.as_deref())?;
+            }
 
-                RqlStatement::Filter { condition } => {
-                    self.handle_filter(condition)?;
-                }
+            RqlStatement::CreateView {
+                name,
+                sql,
+                statement,
+            } => {
+                self.handle_create_view(name, sql, statement)?;
+            }
 
-                _ => {
-                    println!("⚠️  Comando no implementado aún en REPL: {:?}", statement.statement_type());
-                }
+            RqlStatement::Merge { .. } => {
+                self.handle_merge(statement)?;
+            }
+
+            RqlStatement::Transaction { command } => {
+                self.handle_transaction(command)?;
+            }
+
+            RqlStatement::Let {
+                variable,
+                expression,
+            } => {
+                self.handle_let(variable, expression)?;
+            }
+
+            RqlStatement::Unset { variables } => {
+                self.handle_unset(variables)?;
+            }
+
+            RqlStatement::Import {
+                file,
+                table,
+                options,
+            } => {
+                self.handle_import(file, table, options)?;
+            }
+
+            RqlStatement::Export {
+                query,
+                file,
+                format,
+                options,
+            } => {
+                self.handle_export(query, file, format, options)?;
+            }
+
+            RqlStatement::ExportSchema { file } => {
+                self.handle_export_schema(file)?;
+            }
+
+            RqlStatement::ExportSource { alias, path } => {
+                self.handle_export_source(alias, path)?;
+            }
+
+            RqlStatement::SaveBlob { sql, file } => {
+                self.handle_save_blob(sql, file)?;
+            }
+            RqlStatement::SelectInto {
+                sql,
+                file,
+                format,
+                options,
+            } => {
+                self.handle_select_into(sql, file, *format, options)?;
+            }
+
+            RqlStatement::Map { expressions } => {
+                self.handle_map(expressions)?;
+            }
+
+            RqlStatement::Filter { condition } => {
+                self.handle_filter(condition)?;
+            }
+
+            RqlStatement::Assert {
+                sql,
+                expected_count,
+                expected_value,
+                label,
+            } => {
+                self.handle_assert(
+                    sql,
+                    *expected_count,
+                    expected_value.as_ref(),
+                    label.as_deref(),
+                )?;
+            }
+
+            _ => {
+                println!(
+                    "⚠️  Comando no implementado aún en REPL: {:?}",
+                    statement.statement_type()
+                );
             }
         }
 
-        Ok(false)
+        Ok(())
     }
 
     /// Ejecutar statement SQL directo
-    fn execute_sql_statement(&mut self, sql: &str) -> Result<()> {
-        let params = HashMap::new();
-        let rql_query = RqlQuery::new(sql, params);
+    ///
+    /// `duckdb_specific` marca PIVOT/UNPIVOT para que SQLite lo rechace con
+    /// `NoctraError::UnsupportedFeature` en vez de un error de sintaxis.
+    fn execute_sql_statement(&mut self, sql: &str, duckdb_specific: bool) -> Result<()> {
+        let params = Parameters::new();
+        let rql_query = RqlQuery::new(sql, params).with_duckdb_specific(duckdb_specific);
+
+        let result = self.executor.execute_rql(&self.session, rql_query);
+        self.show_execution_result(result)
+    }
+
+    /// Ejecutar SQL sin mostrar el resultado, que queda para MAP y FILTER
+    pub(crate) fn query_sql(&mut self, sql: &str, duckdb_specific: bool) -> Result<ResultSet> {
+        let query = RqlQuery::new(sql, Parameters::new()).with_duckdb_specific(duckdb_specific);
+        let result = self.executor.execute_rql(&self.session, query)?;
+        if !result.columns.is_empty() {
+            self.last_result = Some(result.clone());
+        }
+        Ok(result)
+    }
+
+    /// Manejar MERGE INTO (nativo o emulado en SQLite)
+    fn handle_merge(&mut self, statement: &RqlStatement) -> Result<()> {
+        let merge_sql = statement.merge_sql().unwrap_or_default();
+        let sqlite_statements = statement.sqlite_merge_statements().unwrap_or_default();
+        let result = self
+            .executor
+            .execute_merge(&self.session, &merge_sql, &sqlite_statements);
+        self.show_execution_result(result)
+    }
+
+    /// Manejar BEGIN/COMMIT/ROLLBACK/SAVEPOINT
+    fn handle_transaction(&self, command: &TransactionCommand) -> Result<()> {
+        let (result, message) = match command {
+            TransactionCommand::Begin => (
+                self.executor.begin(&self.session),
+                "Transacción iniciada".to_string(),
+            ),
+            TransactionCommand::Commit => (
+                self.executor.commit(&self.session),
+                "Transacción confirmada".to_string(),
+            ),
+            TransactionCommand::Rollback => (
+                self.executor.rollback(&self.session),
+                "Transacción revertida".to_string(),
+            ),
+            TransactionCommand::Savepoint(name) => (
+                self.executor.savepoint(&self.session, name),
+                format!("Savepoint '{}' creado", name),
+            ),
+            TransactionCommand::RollbackTo(name) => (
+                self.executor.rollback_to_savepoint(&self.session, name),
+                format!("Revertido al savepoint '{}'", name),
+            ),
+            TransactionCommand::Release(name) => (
+                self.executor.release_savepoint(&self.session, name),
+                format!("Savepoint '{}' liberado", name),
+            ),
+        };
+
+        match result {
+            Ok(()) => {
+                println!("✅ {}", message);
+                Ok(())
+            }
+            Err(e) => {
+                println!("❌ Error de transacción: {}", e);
+                Err(e)
+            }
+        }
+    }
 
-        match self.executor.execute_rql(&self.session, rql_query) {
+    /// Mostrar el resultado de una sentencia (o su error)
+    fn show_execution_result(&mut self, result: Result<ResultSet>) -> Result<()> {
+        match result {
             Ok(result_set) => {
+                if !result_set.columns.is_empty() {
+                    self.last_result = Some(result_set.clone());
+                }
                 // Mostrar resultados
                 if result_set.rows.is_empty() {
                     if let Some(affected) = result_set.rows_affected {
@@ -278,64 +749,225 @@ impl Repl {
                     }
                 } else {
                     let table = format_result_set(&result_set);
-                    println!("{}", table);
-                    println!();
-                    println!("({} filas)", result_set.rows.len());
+                    let footer = if result_set.truncated {
+                        format!(
+                            "(mostrando las primeras {} filas; resultado truncado)",
+                            result_set.rows.len()
+                        )
+                    } else {
+                        format!("({} filas)", result_set.rows.len())
+                    };
+                    // Los resultados que no caben en la terminal van al paginador
+                    let formatter = TableFormatter::with_pager(self.config.global.pager.clone());
+                    if let Err(e) = formatter.write_paged(&format!("{}\n\n{}\n", table, footer)) {
+                        println!("❌ Error mostrando resultados: {}", e);
+                    }
                 }
                 Ok(())
             }
             Err(e) => {
-                println!("❌ Error de ejecución: {}", e);
+                println!("{}", format_error(&e));
+                Err(e)
+            }
+        }
+    }
+
+    /// Manejar comando ASSERT
+    fn handle_assert(
+        &mut self,
+        sql: &str,
+        expected_count: Option<usize>,
+        expected_value: Option<&serde_json::Value>,
+        label: Option<&str>,
+    ) -> Result<()> {
+        let rql_query = RqlQuery::new(sql, Parameters::new());
+        let result_set = self.executor.execute_rql(&self.session, rql_query)?;
+        let name = label.unwrap_or(sql);
+
+        match check_assertion(&result_set, expected_count, expected_value, label) {
+            Ok(()) => {
+                println!("✅ ASSERT OK: {}", name);
+                Ok(())
+            }
+            Err(e) => {
+                println!("❌ {}", e);
                 Err(e)
             }
         }
     }
 
+    /// Ejecutar un script RQL (modo batch)
+    ///
+    /// El script se divide y recorre con `noctra_core::run_script`, como en
+    /// `Executor::execute_script`, y cada sentencia pasa por el procesador
+    /// RQL (ASSERT, USE, LET...).
+    ///
+    /// Las aserciones fallidas se reportan y la ejecución continúa, salvo en
+    /// modo `strict`, donde abortan el script. Otros errores abortan salvo
+    /// con `options.stop_on_error = false`. Con `options.transaction` el
+    /// script corre en una transacción que se revierte si alguna sentencia
+    /// falla. Retorna la cantidad de aserciones fallidas.
+    pub fn run_script(
+        &mut self,
+        script: &str,
+        strict: bool,
+        options: ScriptOptions,
+    ) -> Result<usize> {
+        if !options.transaction {
+            return self
+                .run_script_statements(script, strict, options.stop_on_error)
+                .map(|(failed_assertions, _)| failed_assertions);
+        }
+
+        self.executor.begin(&self.session)?;
+        match self.run_script_statements(script, strict, options.stop_on_error) {
+            Ok((failed_assertions, false)) => {
+                self.executor.commit(&self.session)?;
+                Ok(failed_assertions)
+            }
+            Ok((failed_assertions, true)) => {
+                self.executor.rollback(&self.session)?;
+                println!("↩️  Script revertido: hubo sentencias con error");
+                Ok(failed_assertions)
+            }
+            Err(e) => {
+                if let Err(rollback) = self.executor.rollback(&self.session) {
+                    println!("⚠️  No se pudo revertir el script: {}", rollback);
+                }
+                Err(e)
+            }
+        }
+    }
+
+    /// Ejecutar las sentencias del script
+    ///
+    /// Retorna las aserciones fallidas y si alguna otra sentencia falló.
+    fn run_script_statements(
+        &mut self,
+        script: &str,
+        strict: bool,
+        stop_on_error: bool,
+    ) -> Result<(usize, bool)> {
+        let mut failed_assertions = 0;
+        let results = run_script(script, stop_on_error, |statement| {
+            match self.execute_query(&statement.sql) {
+                Ok(_) => Ok(StatementOutcome::RowsAffected(0)),
+                Err(e @ NoctraError::AssertionFailed { .. }) => {
+                    if strict {
+                        return Err(e);
+                    }
+                    failed_assertions += 1;
+                    Ok(StatementOutcome::RowsAffected(0))
+                }
+                Err(e) => {
+                    if !stop_on_error {
+                        println!(
+                            "⚠️  Continuando tras el error en la línea {}",
+                            statement.line
+                        );
+                    }
+                    Ok(StatementOutcome::Error(e))
+                }
+            }
+        })?;
+
+        if stop_on_error {
+            if let Some(e) = results.iter().find_map(StatementResult::error) {
+                return Err(e.clone());
+            }
+        }
+        Ok((
+            failed_assertions,
+            results.iter().any(StatementResult::is_error),
+        ))
+    }
+
     /// Manejar comando USE SOURCE
-    fn handle_use_source(&mut self, path: &str, alias: Option<&str>, _options: &HashMap<String, String>) -> Result<()> {
-        // Detectar tipo de fuente por extensión
-        if path.ends_with(".csv") || path.ends_with(".json") || path.ends_with(".parquet") {
-            // Crear fuente DuckDB (reemplaza CsvDataSource)
-            let source_name = alias.unwrap_or(path);
-            eprintln!("[DEBUG] Loading DuckDB source: {} as {}", path, source_name);
+    fn handle_use_source(
+        &mut self,
+        path: &str,
+        alias: Option<&str>,
+        options: &HashMap<String, String>,
+    ) -> Result<()> {
+        // La fábrica se elige por el esquema de la URI (`csv://`, `file://`,
+        // los registrados por otros crates) o, sin esquema, por la extensión
+        let factory = match self.executor.source_registry().factory_for(path) {
+            Ok(factory) => factory,
+            Err(e) => {
+                println!("❌ {}", e);
+                println!("   (Soportados: .csv, .json, .ndjson, .parquet, .xlsx, .xls; .csv/.json/.ndjson también con .gz o .zst)");
+                return Ok(());
+            }
+        };
 
-            // Usar DuckDBSource desde noctra-duckdb
-            let mut duckdb_source = noctra_duckdb::DuckDBSource::new_in_memory()
-                .map_err(|e| NoctraError::Internal(format!("Error creating DuckDB source: {}", e)))?;
+        let source_name = alias.unwrap_or(path);
+        let created = factory.create(path, source_name, options)?;
+        for warning in &created.warnings {
+            println!("⚠️  {}", warning);
+        }
+        let kind = created.source.name().to_string();
 
-            duckdb_source.register_file(path, &source_name)
-                .map_err(|e| NoctraError::Internal(format!("Error registering file: {}", e)))?;
+        // Registrar fuente (vincula la base SQLite del REPL si es un archivo)
+        match self
+            .executor
+            .register_source(source_name.to_string(), created.source)
+        {
+            Ok(linked) if !linked.is_empty() => {
+                println!("🔗 Tablas SQLite vinculadas: {}", linked.join(", "));
+            }
+            Ok(_) => {}
+            Err(e) => println!("⚠️  No se pudo vincular la base SQLite: {}", e),
+        }
 
-            eprintln!("[DEBUG] DuckDB source created successfully");
+        self.session.record_source(SourceDescriptor {
+            path: path.to_string(),
+            alias: alias.map(str::to_string),
+            options: options.clone(),
+        });
+        println!(
+            "✅ Fuente '{}' cargada como '{}' ({})",
+            path, source_name, kind
+        );
 
-            // Registrar fuente
-            self.executor.source_registry_mut()
-                .register(source_name.to_string(), Box::new(duckdb_source))
-                .map_err(|e| NoctraError::Internal(format!("Error registering source: {}", e)))?;
+        Ok(())
+    }
 
-            eprintln!("[DEBUG] DuckDB source registered");
-            eprintln!("[DEBUG] Active source after registration: {:?}",
-                self.executor.source_registry().active().map(|s| s.name()));
+    /// Manejar comando USE SOURCE alias
+    fn handle_use_source_alias(&mut self, alias: &str) -> Result<()> {
+        let registry = self.executor.source_registry_mut();
+        if registry.set_active(alias).is_ok() {
+            println!("✅ Fuente activa: '{}'", alias);
+            return Ok(());
+        }
 
-            println!("✅ Fuente '{}' cargada como '{}' (DuckDB)", path, source_name);
+        let aliases = registry.aliases();
+        if aliases.is_empty() {
+            println!(
+                "❌ Fuente '{}' no encontrada (no hay fuentes registradas)",
+                alias
+            );
         } else {
-            println!("❌ Tipo de fuente no soportado: {}", path);
-            println!("   (Soportados: .csv, .json, .parquet)");
+            println!(
+                "❌ Fuente '{}' no encontrada. Disponibles: {}",
+                alias,
+                aliases.join(", ")
+            );
         }
-
         Ok(())
     }
 
     /// Manejar comando SHOW SOURCES
     fn handle_show_sources(&self) -> Result<()> {
-        let sources = self.executor.source_registry().list_sources();
+        let registry = self.executor.source_registry();
+        let sources = registry.list_sources();
 
         if sources.is_empty() {
             println!("ℹ️  No hay fuentes registradas");
         } else {
-            println!("📊 Fuentes disponibles:");
+            println!("📊 Fuentes disponibles (▶ = activa):");
             for (alias, source_type) in sources {
-                println!("  • {} ({}) - {}", alias, source_type.type_name(), source_type.display_path());
+                let marker = if registry.active_alias() == Some(alias.as_str()) { "▶" } else { "•" };
+                println!("  {} {} ({}) - {}", marker, alias, source_type.type_name(), source_type.display_path());
             }
         }
 
@@ -354,7 +986,8 @@ impl Repl {
                         } else {
                             println!("📋 Tablas en '{}':", source_name);
                             for table in tables {
-                                println!("  • {} ({} columnas)", table.name, table.columns.len());
+                                let kind = if table.is_view { ", vista" } else { "" };
+                                println!("  • {} ({} columnas{})", table.name, table.columns.len(), kind);
                             }
                         }
                     }
@@ -367,7 +1000,17 @@ impl Repl {
             // Mostrar todas las tablas de todas las fuentes
             let sources = self.executor.source_registry().list_sources();
             if sources.is_empty() {
-                println!("ℹ️  No hay fuentes registradas");
+                // Sin fuentes se listan las tablas del backend (p. ej. PostgreSQL)
+                let tables = self.executor.backend_schema().unwrap_or_default();
+                if tables.is_empty() {
+                    println!("ℹ️  No hay fuentes registradas");
+                } else {
+                    println!("📋 Tablas en {}:", self.executor.backend_info().name);
+                    for table in tables {
+                        let kind = if table.is_view { ", vista" } else { "" };
+                        println!("  • {} ({} columnas{})", table.name, table.columns.len(), kind);
+                    }
+                }
             } else {
                 for (alias, _) in sources {
                     if let Some(data_source) = self.executor.source_registry().get(&alias) {
@@ -375,7 +1018,8 @@ impl Repl {
                             if !tables.is_empty() {
                                 println!("📋 Tablas en '{}':", alias);
                                 for table in tables {
-                                    println!("  • {} ({} columnas)", table.name, table.columns.len());
+                                    let kind = if table.is_view { ", vista" } else { "" };
+                                println!("  • {} ({} columnas{})", table.name, table.columns.len(), kind);
                                 }
                             }
                         }
@@ -390,13 +1034,15 @@ impl Repl {
     /// Manejar comando SHOW VARS
     fn handle_show_vars(&self) -> Result<()> {
         let vars = self.session.list_variables();
+        let used = self.executor.last_used_variables(&self.session);
 
         if vars.is_empty() {
             println!("ℹ️  No hay variables de sesión definidas");
         } else {
             println!("🔧 Variables de sesión:");
             for (name, value) in vars {
-                println!("  {} = {}", name, value);
+                let mark = if used.contains(name) { "  ← usada en la última consulta" } else { "" };
+                println!("  {} = {} ({}){}", name, value, value.type_name(), mark);
             }
         }
 
@@ -414,7 +1060,7 @@ impl Repl {
                             println!("📊 Estructura de {}.{}:", source_name, table);
                             println!("  Columnas:");
                             for col in &table_info.columns {
-                                println!("    • {} ({})", col.name, col.data_type);
+                                println!("    • {}", describe_column(col));
                             }
                             if let Some(row_count) = table_info.row_count {
                                 println!("  Filas: {}", row_count);
@@ -429,7 +1075,117 @@ impl Repl {
                 println!("❌ Fuente '{}' no encontrada", source_name);
             }
         } else {
-            println!("❌ DESCRIBE requiere especificar la fuente: DESCRIBE source.table");
+            // Sin fuente: se resuelve en el catálogo (fuentes y backend)
+            match self.executor.lookup_table(table) {
+                Ok(entry) => {
+                    println!("📊 Estructura de {}:", entry.qualified_name());
+                    println!("  Columnas:");
+                    for col in &entry.table.columns {
+                        println!("    • {}", describe_column(col));
+                    }
+                    if let Some(row_count) = entry.table.row_count {
+                        println!("  Filas: {}", row_count);
+                    }
+                }
+                Err(e) => println!("❌ {}", e),
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Manejar comando PROFILE (estadísticas por columna)
+    fn handle_profile(&self, source: Option<&str>, table: &str) -> Result<()> {
+        let registry = self.executor.source_registry();
+        let data_source = match source {
+            Some(source_name) => registry.get(source_name),
+            None => registry.active(),
+        };
+
+        let Some(data_source) = data_source else {
+            match source {
+                Some(source_name) => println!("❌ Fuente '{}' no encontrada", source_name),
+                None => println!("❌ No hay fuente activa: PROFILE source.table"),
+            }
+            return Ok(());
+        };
+
+        match data_source.profile(table) {
+            Ok(result_set) => {
+                println!("📈 Perfil de {}.{}:", data_source.name(), table);
+                println!("{}", format_result_set(&result_set));
+                println!("({} columnas)", result_set.rows.len());
+            }
+            Err(e) => println!("❌ Error obteniendo perfil: {}", e),
+        }
+
+        Ok(())
+    }
+
+    /// Manejar comando SAMPLE (muestra de filas de una tabla)
+    fn handle_sample(&self, source: Option<&str>, table: &str, limit: usize) -> Result<()> {
+        let registry = self.executor.source_registry();
+        let data_source = match source {
+            Some(source_name) => registry.get(source_name),
+            None => registry.active(),
+        };
+
+        let Some(data_source) = data_source else {
+            match source {
+                Some(source_name) => println!("❌ Fuente '{}' no encontrada", source_name),
+                None => println!("❌ No hay fuente activa: SAMPLE source.table"),
+            }
+            return Ok(());
+        };
+
+        match data_source.sample(table, limit) {
+            Ok(result_set) => {
+                println!("🎲 Muestra de {}.{}:", data_source.name(), table);
+                println!("{}", format_result_set(&result_set));
+                println!("({} filas)", result_set.rows.len());
+            }
+            Err(e) => println!("❌ Error obteniendo muestra: {}", e),
+        }
+
+        Ok(())
+    }
+
+    /// Manejar comando CHECKPOINT
+    fn handle_checkpoint(&self, source: Option<&str>) -> Result<()> {
+        let registry = self.executor.source_registry();
+        let data_source = match source {
+            Some(source_name) => registry.get(source_name),
+            None => registry.active(),
+        };
+
+        let Some(data_source) = data_source else {
+            match source {
+                Some(source_name) => println!("❌ Fuente '{}' no encontrada", source_name),
+                None => println!("❌ No hay fuente activa: CHECKPOINT source"),
+            }
+            return Ok(());
+        };
+
+        match data_source.checkpoint() {
+            Ok(()) => println!("✅ Checkpoint completado en '{}'", data_source.name()),
+            Err(e) => println!("❌ Error en checkpoint: {}", e),
+        }
+
+        Ok(())
+    }
+
+    /// Manejar comando CREATE VIEW
+    ///
+    /// Con una fuente activa la vista se crea en ella; si no, la sentencia
+    /// se ejecuta como SQL sobre el backend.
+    fn handle_create_view(&mut self, name: &str, sql: &str, statement: &str) -> Result<()> {
+        let Some(data_source) = self.executor.source_registry_mut().active_mut() else {
+            return self.execute_sql_statement(statement, false);
+        };
+
+        match data_source.create_view(name, sql) {
+            Ok(()) => println!("✅ Vista '{}' creada en '{}'", name, data_source.name()),
+            Err(e) => println!("❌ Error creando vista: {}", e),
         }
 
         Ok(())
@@ -437,10 +1193,18 @@ impl Repl {
 
     /// Manejar comando LET
     fn handle_let(&mut self, variable: &str, expression: &str) -> Result<()> {
-        // Evaluar la expresión (por ahora, simplemente tomar el valor literal)
-        let value = expression.trim_matches('\'').trim_matches('"');
-        self.session.set_variable(variable.to_string(), value.to_string());
-        println!("✅ Variable '{}' = '{}'", variable, value);
+        match self.session.evaluate(expression) {
+            Ok(value) => {
+                println!(
+                    "✅ Variable '{}' = '{}' ({})",
+                    variable,
+                    value,
+                    value.type_name()
+                );
+                self.session.set_variable(variable.to_string(), value);
+            }
+            Err(e) => println!("❌ Error en LET {}: {}", variable, e),
+        }
         Ok(())
     }
 
@@ -455,13 +1219,18 @@ impl Repl {
 
     /// Manejar comando IMPORT
     /// Sintaxis: IMPORT 'file.csv' AS table OPTIONS (delimiter=',', header=true)
-    fn handle_import(&mut self, file: &str, table: &str, options: &HashMap<String, String>) -> Result<()> {
+    fn handle_import(
+        &mut self,
+        file: &str,
+        table: &str,
+        options: &HashMap<String, String>,
+    ) -> Result<()> {
         use std::fs::File;
         use std::io::{BufRead, BufReader};
         use std::path::Path;
 
         // Validar ruta de archivo (sandboxing)
-        Self::validate_file_path(file)?;
+        noctra_core::fs_guard::check_path(file, &self.config.global.allowed_paths)?;
 
         // Validar nombre de tabla (SQL injection prevention)
         Self::validate_table_name(table)?;
@@ -471,7 +1240,7 @@ impl Repl {
         let is_json = file.ends_with(".json");
 
         if !is_csv && !is_json {
-            return Err(NoctraError::Internal(
+            return Err(NoctraError::Validation(
                 format!("Formato de archivo no soportado: {} (solo .csv y .json)", file)
             ));
         }
@@ -482,7 +1251,7 @@ impl Repl {
             let metadata = std::fs::metadata(path)?;
             const MAX_FILE_SIZE: u64 = 100 * 1024 * 1024;
             if metadata.len() > MAX_FILE_SIZE {
-                return Err(NoctraError::Internal(format!(
+                return Err(NoctraError::Validation(format!(
                     "Archivo demasiado grande: {} bytes (máx: {} bytes)",
                     metadata.len(),
                     MAX_FILE_SIZE
@@ -492,7 +1261,7 @@ impl Repl {
 
         // Leer archivo
         let file_handle = File::open(file)
-            .map_err(|e| NoctraError::Internal(format!("Error abriendo archivo: {}", e)))?;
+            .map_err(|e| NoctraError::Io(format!("Error abriendo archivo: {}", e)))?;
         let reader = BufReader::new(file_handle);
 
         if is_csv {
@@ -510,7 +1279,7 @@ impl Repl {
             let header_line = if let Some(Ok(line)) = lines.next() {
                 line
             } else {
-                return Err(NoctraError::Internal("Archivo CSV vacío".into()));
+                return Err(NoctraError::Validation("Archivo CSV vacío".into()));
             };
 
             let columns: Vec<String> = header_line
@@ -519,7 +1288,7 @@ impl Repl {
                 .collect();
 
             if columns.is_empty() {
-                return Err(NoctraError::Internal("No se encontraron columnas en CSV".into()));
+                return Err(NoctraError::Validation("No se encontraron columnas en CSV".into()));
             }
 
             // Crear tabla en SQLite
@@ -528,8 +1297,7 @@ impl Repl {
                 .collect();
             let create_sql = format!("CREATE TABLE IF NOT EXISTS {} ({})", table, column_defs.join(", "));
 
-            self.executor.execute_sql(&self.session, &create_sql)
-                .map_err(|e| NoctraError::Internal(format!("Error creando tabla: {}", e)))?;
+            self.executor.execute_sql(&self.session, &create_sql)?;
 
             println!("✅ Tabla '{}' creada con {} columnas", table, columns.len());
 
@@ -556,7 +1324,7 @@ impl Repl {
             // Procesar resto de líneas
             for line_result in lines {
                 let line = line_result
-                    .map_err(|e| NoctraError::Internal(format!("Error leyendo línea: {}", e)))?;
+                    .map_err(|e| NoctraError::Io(format!("Error leyendo línea: {}", e)))?;
 
                 let values: Vec<String> = line
                     .split(delimiter)
@@ -585,28 +1353,28 @@ impl Repl {
 
             // Leer todo el archivo
             let json_content = std::io::read_to_string(reader)
-                .map_err(|e| NoctraError::Internal(format!("Error leyendo JSON: {}", e)))?;
+                .map_err(|e| NoctraError::Io(format!("Error leyendo JSON: {}", e)))?;
 
             // Parsear JSON
             let json_data: JsonValue = serde_json::from_str(&json_content)
-                .map_err(|e| NoctraError::Internal(format!("Error parseando JSON: {}", e)))?;
+                .map_err(|e| NoctraError::Serialization(format!("Error parseando JSON: {}", e)))?;
 
             // Verificar que es un array
             let array = match json_data {
                 JsonValue::Array(arr) => arr,
-                _ => return Err(NoctraError::Internal(
+                _ => return Err(NoctraError::Validation(
                     "JSON debe ser un array de objetos".into()
                 )),
             };
 
             if array.is_empty() {
-                return Err(NoctraError::Internal("Array JSON vacío".into()));
+                return Err(NoctraError::Validation("Array JSON vacío".into()));
             }
 
             // Extraer columnas del primer objeto
             let first_obj = match &array[0] {
                 JsonValue::Object(obj) => obj,
-                _ => return Err(NoctraError::Internal(
+                _ => return Err(NoctraError::Validation(
                     "Elementos del array deben ser objetos".into()
                 )),
             };
@@ -614,7 +1382,7 @@ impl Repl {
             let columns: Vec<String> = first_obj.keys().cloned().collect();
 
             if columns.is_empty() {
-                return Err(NoctraError::Internal("No se encontraron columnas en JSON".into()));
+                return Err(NoctraError::Validation("No se encontraron columnas en JSON".into()));
             }
 
             // Inferir tipos de datos del primer objeto
@@ -642,8 +1410,7 @@ impl Repl {
                 .collect();
             let create_sql = format!("CREATE TABLE IF NOT EXISTS {} ({})", table, column_defs.join(", "));
 
-            self.executor.execute_sql(&self.session, &create_sql)
-                .map_err(|e| NoctraError::Internal(format!("Error creando tabla: {}", e)))?;
+            self.executor.execute_sql(&self.session, &create_sql)?;
 
             println!("✅ Tabla '{}' creada con {} columnas", table, columns.len());
 
@@ -690,12 +1457,18 @@ impl Repl {
 
     /// Manejar comando EXPORT
     /// Sintaxis: EXPORT table TO 'file.csv' FORMAT CSV OPTIONS (delimiter=',', header=true)
-    fn handle_export(&mut self, query: &str, file: &str, format: &noctra_parser::ExportFormat, options: &HashMap<String, String>) -> Result<()> {
+    fn handle_export(
+        &mut self,
+        query: &str,
+        file: &str,
+        format: &noctra_parser::ExportFormat,
+        options: &HashMap<String, String>,
+    ) -> Result<()> {
         use std::fs::File;
         use std::io::Write;
 
         // Validar ruta de archivo (sandboxing)
-        Self::validate_file_path(file)?;
+        noctra_core::fs_guard::check_path(file, &self.config.global.allowed_paths)?;
 
         // Validar nombre de tabla si no es SELECT
         if !query.to_uppercase().starts_with("SELECT ") {
@@ -703,18 +1476,17 @@ impl Repl {
         }
 
         // Ejecutar query para obtener datos
-        let result = if query.to_uppercase().starts_with("SELECT ") {
+        let select_query = if query.to_uppercase().starts_with("SELECT ") {
             // Es una query completa
-            let params = HashMap::new();
-            let rql_query = RqlQuery::new(query, params);
-            self.executor.execute_rql(&self.session, rql_query)?
+            query.to_string()
         } else {
             // Es un nombre de tabla, generar SELECT *
-            let select_query = format!("SELECT * FROM {}", query);
-            let params = HashMap::new();
-            let rql_query = RqlQuery::new(&select_query, params);
-            self.executor.execute_rql(&self.session, rql_query)?
+            format!("SELECT * FROM {}", query)
         };
+        // Todas las filas: `execute_rql` recorta a `max_rows`
+        let rql_query = RqlQuery::new(&select_query, Parameters::new());
+        let stream = self.executor.execute_rql_stream(&self.session, rql_query)?;
+        let result = ResultSet::from_stream(stream, None)?;
 
         match format {
             noctra_parser::ExportFormat::Csv => {
@@ -726,7 +1498,7 @@ impl Repl {
                     .unwrap_or(true);
 
                 let mut file_handle = File::create(file)
-                    .map_err(|e| NoctraError::Internal(format!("Error creando archivo: {}", e)))?;
+                    .map_err(|e| NoctraError::Io(format!("Error creando archivo: {}", e)))?;
 
                 // Escribir header si está habilitado
                 if has_header {
@@ -735,7 +1507,7 @@ impl Repl {
                         .collect();
                     let header_line = header_names.join(&delimiter.to_string());
                     writeln!(file_handle, "{}", header_line)
-                        .map_err(|e| NoctraError::Internal(format!("Error escribiendo header: {}", e)))?;
+                        .map_err(|e| NoctraError::Io(format!("Error escribiendo header: {}", e)))?;
                 }
 
                 // Escribir filas
@@ -755,13 +1527,14 @@ impl Repl {
                                 noctra_core::Value::Float(f) => f.to_string(),
                                 noctra_core::Value::Boolean(b) => b.to_string(),
                                 noctra_core::Value::Null => String::new(),
-                                _ => format!("{:?}", v),
+                                // Decimales (feature `decimal`), fechas, ... en su forma textual exacta
+                                _ => v.to_string(),
                             }
                         })
                         .collect();
 
                     writeln!(file_handle, "{}", row_values.join(&delimiter.to_string()))
-                        .map_err(|e| NoctraError::Internal(format!("Error escribiendo fila: {}", e)))?;
+                        .map_err(|e| NoctraError::Io(format!("Error escribiendo fila: {}", e)))?;
                 }
 
                 println!("✅ Exportadas {} filas a '{}'", result.rows.len(), file);
@@ -770,7 +1543,7 @@ impl Repl {
                 use serde_json::{json, Value as JsonValue};
 
                 let mut file_handle = File::create(file)
-                    .map_err(|e| NoctraError::Internal(format!("Error creando archivo: {}", e)))?;
+                    .map_err(|e| NoctraError::Io(format!("Error creando archivo: {}", e)))?;
 
                 // Convertir ResultSet a JSON array
                 let rows_json: Vec<JsonValue> = result.rows.iter()
@@ -790,7 +1563,11 @@ impl Repl {
                                 }
                                 noctra_core::Value::Boolean(b) => JsonValue::Bool(*b),
                                 noctra_core::Value::Null => JsonValue::Null,
-                                _ => JsonValue::String(format!("{:?}", value)),
+                                // Decimales como número si no pierden dígitos; el resto como texto
+                                _ => value
+                                    .to_json_number()
+                                    .map(JsonValue::Number)
+                                    .unwrap_or_else(|| JsonValue::String(value.to_string())),
                             };
                             obj.insert(col.name.clone(), json_val);
                         }
@@ -800,93 +1577,153 @@ impl Repl {
 
                 let json_output = json!(rows_json);
                 writeln!(file_handle, "{}", serde_json::to_string_pretty(&json_output)
-                    .map_err(|e| NoctraError::Internal(format!("Error serializando JSON: {}", e)))?)
-                    .map_err(|e| NoctraError::Internal(format!("Error escribiendo JSON: {}", e)))?;
+                    .map_err(|e| NoctraError::Serialization(format!("Error serializando JSON: {}", e)))?)
+                    .map_err(|e| NoctraError::Io(format!("Error escribiendo JSON: {}", e)))?;
 
                 println!("✅ Exportadas {} filas a '{}'", result.rows.len(), file);
             }
             noctra_parser::ExportFormat::Xlsx => {
-                println!("⚠️  Exportación a XLSX no implementada en M4 (planeado para M5)");
+                let xlsx_options = noctra_core::export::XlsxOptions::from_options(options)?;
+                crate::output::XlsxFormatter::new(xlsx_options).write_to_path(&result, file)?;
+
+                println!("✅ Exportadas {} filas a '{}'", result.rows.len(), file);
             }
         }
 
         Ok(())
     }
 
-    /// Manejar comando MAP
-    /// Sintaxis: MAP expression1 AS alias1, expression2 AS alias2, ...
-    fn handle_map(&mut self, _expressions: &[noctra_parser::MapExpression]) -> Result<()> {
-        println!("⚠️  MAP: Transformaciones declarativas");
-        println!("No implementado completamente en M4.");
-        println!("Use SELECT para transformaciones simples.");
-        println!();
-        println!("Ejemplo:");
-        println!("  SELECT UPPER(nombre) AS nombre, precio * 1.1 AS precio_nuevo");
-        println!("  FROM productos;");
+    /// Manejar comando EXPORT SCHEMA
+    /// Sintaxis: EXPORT SCHEMA TO 'catalog.toml'
+    fn handle_export_schema(&self, file: &str) -> Result<()> {
+        noctra_core::fs_guard::check_path(file, &self.config.global.allowed_paths)?;
+
+        let registry = self.executor.source_registry();
+        if registry.aliases().is_empty() {
+            println!("⚠️  No hay fuentes registradas: el catálogo queda vacío");
+        }
+
+        match registry.export_schema(Path::new(file)) {
+            Ok(()) => println!(
+                "✅ Esquema de {} fuente(s) exportado a '{}'",
+                registry.aliases().len(),
+                file
+            ),
+            Err(e) => println!("❌ Error exportando esquema: {}", e),
+        }
+
         Ok(())
     }
 
-    /// Manejar comando FILTER
-    /// Sintaxis: FILTER condition
-    fn handle_filter(&mut self, _condition: &str) -> Result<()> {
-        println!("⚠️  FILTER: Filtrado declarativo");
-        println!("No implementado completamente en M4.");
-        println!("Use WHERE en SELECT.");
-        println!();
-        println!("Ejemplo:");
-        println!("  SELECT * FROM productos");
-        println!("  WHERE precio > 100;");
+    /// Manejar comando EXPORT SOURCE
+    /// Sintaxis: EXPORT SOURCE csv TO 'bundle/'
+    fn handle_export_source(&self, alias: &str, path: &str) -> Result<()> {
+        // Se valida el manifiesto: el directorio del paquete puede existir ya
+        noctra_core::fs_guard::check_path(
+            Path::new(path).join(noctra_core::bundle::MANIFEST_FILE),
+            &self.config.global.allowed_paths,
+        )?;
+
+        let manifest = self.executor.export_bundle(alias, Path::new(path))?;
+        let rows: u64 = manifest.tables.iter().map(|table| table.rows).sum();
+        println!(
+            "✅ Fuente '{}' exportada a '{}' ({} tablas, {} filas)",
+            alias,
+            path,
+            manifest.tables.len(),
+            rows
+        );
         Ok(())
     }
 
-    /// Validar ruta de archivo (sandboxing)
-    fn validate_file_path(file: &str) -> Result<()> {
-        use std::path::Path;
+    /// Manejar comando SAVE BLOB
+    /// Sintaxis: SAVE BLOB (SELECT doc FROM files WHERE id = :id) TO 'out.pdf'
+    fn handle_save_blob(&mut self, sql: &str, file: &str) -> Result<()> {
+        // Se escribe en la ruta ya validada, no en la que escribió el usuario
+        let path = noctra_core::fs_guard::check_path(file, &self.config.global.allowed_paths)?;
 
-        let path = Path::new(file);
-        let path_str = path.to_string_lossy();
-
-        // Directorios bloqueados
-        let blocked_dirs = [
-            "/etc/",
-            "/sys/",
-            "/proc/",
-            "/dev/",
-            "/root/",
-            "/boot/",
-            "C:\\Windows\\",
-            "C:\\Program Files\\",
-        ];
-
-        for blocked in &blocked_dirs {
-            if path_str.starts_with(blocked) {
-                return Err(NoctraError::Internal(format!(
-                    "Acceso denegado: No se puede acceder a directorio del sistema: {}",
-                    path_str
-                )));
-            }
-        }
+        let result = self
+            .executor
+            .execute_rql(&self.session, RqlQuery::new(sql, Parameters::new()))?;
+        let bytes = result.single_blob()?;
+        fs::write(&path, bytes)
+            .map_err(|e| NoctraError::Io(format!("Error escribiendo '{}': {}", file, e)))?;
 
-        // Prevenir path traversal
-        if path_str.contains("..") {
-            return Err(NoctraError::Internal(
-                "Acceso denegado: Path traversal no permitido".to_string(),
-            ));
-        }
+        println!("✅ {} bytes guardados en '{}'", bytes.len(), file);
+        Ok(())
+    }
 
-        // Validar que es un archivo regular
-        if path.exists() {
-            let metadata = std::fs::metadata(path)?;
-            if !metadata.is_file() {
-                return Err(NoctraError::Internal(
-                    "Acceso denegado: La ruta debe ser un archivo regular".to_string(),
-                ));
-            }
-        }
+    /// Manejar SELECT ... INTO 'file'
+    /// Sintaxis: SELECT * FROM ventas INTO 'ventas.parquet' FORMAT PARQUET OPTIONS (compression=zstd)
+    fn handle_select_into(
+        &mut self,
+        sql: &str,
+        file: &str,
+        format: noctra_parser::OutfileFormat,
+        options: &HashMap<String, String>,
+    ) -> Result<()> {
+        noctra_core::fs_guard::check_path(file, &self.config.global.allowed_paths)?;
+        let format = match format {
+            noctra_parser::OutfileFormat::Parquet => noctra_duckdb::WriteFormat::Parquet(
+                noctra_duckdb::ParquetWriteOptions::from_options(options)
+                    .map_err(NoctraError::from)?,
+            ),
+            noctra_parser::OutfileFormat::Csv => noctra_duckdb::WriteFormat::Csv(
+                noctra_duckdb::CsvWriteOptions::from_options(options).map_err(NoctraError::from)?,
+            ),
+        };
+
+        // Todas las filas: `execute_rql` recorta a `max_rows`
+        let stream = self
+            .executor
+            .execute_rql_stream(&self.session, RqlQuery::new(sql, Parameters::new()))?;
+        let result = ResultSet::from_stream(stream, None)?;
+        let written = noctra_duckdb::DuckDBSource::new_in_memory()
+            .and_then(|mut writer| writer.write_table(&result, file, format))
+            .map_err(NoctraError::from)?;
 
+        println!("✅ {} filas escritas en '{}'", written, file);
         Ok(())
     }
 
+    /// Manejar comando MAP
+    /// Sintaxis: MAP expression1 AS alias1, expression2 AS alias2, ...
+    ///
+    /// Transforma el último resultado; la salida pasa a ser el último
+    /// resultado, así que MAP y FILTER se pueden encadenar.
+    fn handle_map(&mut self, expressions: &[noctra_parser::MapExpression]) -> Result<()> {
+        let expressions: Vec<(&str, Option<&str>)> = expressions
+            .iter()
+            .map(|e| (e.expression.as_str(), e.alias.as_deref()))
+            .collect();
+        let result = self
+            .last_result_for("MAP")
+            .and_then(|last| self.session.map_result(&last, &expressions));
+        self.show_execution_result(result)
+    }
+
+    /// Manejar comando FILTER
+    /// Sintaxis: FILTER condition
+    ///
+    /// Se queda con las filas del último resultado que cumplen la condición.
+    fn handle_filter(&mut self, condition: &str) -> Result<()> {
+        let result = self
+            .last_result_for("FILTER")
+            .and_then(|last| self.session.filter_result(&last, condition));
+        self.show_execution_result(result)
+    }
+
+    /// Último resultado para MAP/FILTER; sin resultado previo es un error
+    /// (lo muestra `show_execution_result`)
+    fn last_result_for(&self, command: &str) -> Result<ResultSet> {
+        self.last_result.clone().ok_or_else(|| {
+            NoctraError::Validation(format!(
+                "{} se aplica al último resultado; ejecute antes una consulta",
+                command
+            ))
+        })
+    }
+
     /// Validar nombre de tabla (SQL injection prevention)
     fn validate_table_name(name: &str) -> Result<()> {
         // Solo permitir alfanuméricos, guión bajo y guión
@@ -896,7 +1733,7 @@ impl Repl {
         {
             Ok(())
         } else {
-            Err(NoctraError::Internal(format!(
+            Err(NoctraError::Validation(format!(
                 "Nombre de tabla inválido: '{}' (solo alfanuméricos, _, - permitidos)",
                 name
             )))
@@ -911,8 +1748,12 @@ impl Repl {
         println!("  quit, exit, q    - Salir del REPL");
         println!("  :version, :ver   - Mostrar versión");
         println!("  :config          - Mostrar configuración");
-        println!("  :status, :stats  - Mostrar estado");
+        println!("  :status          - Mostrar estado");
+        println!("  :stats           - Métricas de las consultas por backend y sentencia");
         println!("  :set KEY=VALUE   - Configurar variable");
+        println!("  :set timeout=5s  - Timeout de consultas (500ms, 2m, off)");
+        println!("  :set max_rows=N  - Máximo de filas por consulta (off = sin límite)");
+        println!("  Ctrl+R           - Buscar en el historial");
         println!();
         println!("📋 Comandos SQL/RQL:");
         println!("  SELECT * FROM employees WHERE dept = 'IT';");
@@ -925,6 +1766,9 @@ impl Repl {
         println!("  SHOW TABLES;                        - Listar tablas de todas las fuentes");
         println!("  SHOW TABLES FROM csv;               - Listar tablas de fuente específica");
         println!("  DESCRIBE csv.clientes;              - Describir estructura de tabla");
+        println!("  PROFILE csv.clientes;               - Estadísticas por columna (DuckDB)");
+        println!("  SAMPLE csv.clientes LIMIT 10;       - Muestra de filas sin recorrer la tabla");
+        println!("  CHECKPOINT;                         - Volcar escrituras pendientes (DuckDB)");
         println!("  UNSET variable;                     - Eliminar variable de sesión");
         println!();
     }
@@ -952,17 +1796,68 @@ impl Repl {
         println!("  Estado: {:?}", self.handler.state);
     }
 
+    /// Mostrar las métricas de las consultas de la sesión
+    fn show_stats(&self) {
+        let series = self.metrics.snapshot();
+        if series.is_empty() {
+            println!("📈 Sin consultas registradas");
+            return;
+        }
+        println!("📈 Métricas de consultas:");
+        for s in series {
+            let mean = s.mean_latency().unwrap_or_default();
+            println!(
+                "  {:<10} {:<8} consultas: {:>5}  errores: {:>4}  filas: {:>7}  media: {:?}",
+                s.backend, s.statement, s.queries, s.errors, s.rows, mean
+            );
+        }
+    }
+
     /// Manejar comando SET
     fn handle_set_command(&mut self, cmd: &str) {
         let parts: Vec<&str> = cmd.splitn(2, ' ').collect();
         if parts.len() == 2 {
             let key_value = parts[1];
             if let Some((key, value)) = key_value.split_once('=') {
-                println!(
-                    "📝 Variable '{}' configurada a '{}'",
-                    key.trim(),
-                    value.trim()
-                );
+                let (key, value) = (key.trim(), value.trim());
+                let mut options = self.executor.options();
+                match key.to_lowercase().as_str() {
+                    "timeout" => match parse_timeout(value) {
+                        Some(timeout) => {
+                            options.timeout = timeout;
+                            self.executor.set_options(options);
+                            match timeout {
+                                Some(t) => println!("⏱️  Timeout de consultas: {:?}", t),
+                                None => println!("⏱️  Timeout de consultas desactivado"),
+                            }
+                        }
+                        None => println!(
+                            "❌ Timeout inválido '{}'. Usa por ejemplo 5s, 500ms, 2m u off",
+                            value
+                        ),
+                    },
+                    "max_rows" => {
+                        let max_rows = match value.to_lowercase().as_str() {
+                            "off" | "0" | "none" => Some(None),
+                            n => n.parse::<usize>().ok().map(Some),
+                        };
+                        match max_rows {
+                            Some(max_rows) => {
+                                options.max_rows = max_rows;
+                                self.executor.set_options(options);
+                                match max_rows {
+                                    Some(n) => println!("📏 Máximo de filas por consulta: {}", n),
+                                    None => println!("📏 Sin límite de filas por consulta"),
+                                }
+                            }
+                            None => println!(
+                                "❌ Límite de filas inválido '{}'. Usa un número u off",
+                                value
+                            ),
+                        }
+                    }
+                    _ => println!("📝 Variable '{}' configurada a '{}'", key, value),
+                }
             } else {
                 println!("❌ Formato inválido. Usa: :set KEY=VALUE");
             }
@@ -974,11 +1869,19 @@ impl Repl {
 
 impl ReplHandler {
     /// Crear nuevo handler
-    fn new(config: CliConfig, _args: ReplArgs) -> Result<Self> {
+    fn new(config: CliConfig, args: ReplArgs) -> Result<Self> {
+        let history_file = if args.no_history {
+            None
+        } else {
+            args.history.or_else(|| config.global.history_file.clone())
+        };
+
         Ok(Self {
             _config: config,
             state: ReplState::Ready,
             history: Vec::new(),
+            saved_history: 0,
+            history_file,
             line_count: 0,
         })
     }
@@ -1001,3 +1904,213 @@ fn read_input(prompt: &str) -> Result<String> {
 
 /// Resultado de comando
 pub type CommandResult = Result<bool>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn test_repl(history_size: usize) -> Repl {
+        let mut config = CliConfig::default();
+        config.database.connection_string = ":memory:".to_string();
+        config.repl.history_size = history_size;
+        Repl::new(config, ReplArgs::default()).unwrap()
+    }
+
+    #[test]
+    fn test_history_round_trip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(".noctra_history");
+        fs::write(&path, "SELECT 1;\nSHOW SOURCES;\n").unwrap();
+
+        let mut repl = test_repl(1000);
+        let loaded = repl.load_history(&path).unwrap();
+        assert_eq!(loaded, vec!["SELECT 1;", "SHOW SOURCES;"]);
+
+        repl.handler.history.push("SELECT 2;".to_string());
+        repl.handler.history.push("SHOW VARS;".to_string());
+        repl.save_history(&path).unwrap();
+
+        // Guardar de nuevo no duplica entradas ya escritas
+        repl.save_history(&path).unwrap();
+
+        let mut next_session = test_repl(1000);
+        let loaded = next_session.load_history(&path).unwrap();
+        assert_eq!(
+            loaded,
+            vec!["SELECT 1;", "SHOW SOURCES;", "SELECT 2;", "SHOW VARS;"]
+        );
+        assert_eq!(next_session.handler.history, loaded);
+    }
+
+    #[test]
+    fn test_history_is_capped() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join(".noctra_history");
+
+        let mut repl = test_repl(3);
+        for i in 0..5 {
+            repl.handler.history.push(format!("SELECT {};", i));
+        }
+        repl.save_history(&path).unwrap();
+
+        let loaded = test_repl(3).load_history(&path).unwrap();
+        assert_eq!(loaded, vec!["SELECT 2;", "SELECT 3;", "SELECT 4;"]);
+    }
+
+    #[test]
+    fn test_missing_history_file_is_empty() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut repl = test_repl(1000);
+        assert!(repl
+            .load_history(&dir.path().join("missing"))
+            .unwrap()
+            .is_empty());
+    }
+
+    #[test]
+    fn test_set_timeout_and_max_rows() {
+        let mut repl = test_repl(1000);
+        assert_eq!(
+            repl.executor.options().timeout,
+            Some(Duration::from_secs(30))
+        );
+
+        repl.handle_set_command(":set timeout=500ms");
+        assert_eq!(
+            repl.executor.options().timeout,
+            Some(Duration::from_millis(500))
+        );
+        repl.handle_set_command(":set timeout=2m");
+        assert_eq!(
+            repl.executor.options().timeout,
+            Some(Duration::from_secs(120))
+        );
+        repl.handle_set_command(":set timeout=pronto");
+        assert_eq!(
+            repl.executor.options().timeout,
+            Some(Duration::from_secs(120))
+        );
+        repl.handle_set_command(":set timeout=off");
+        assert_eq!(repl.executor.options().timeout, None);
+
+        repl.handle_set_command(":set max_rows=10");
+        assert_eq!(repl.executor.options().max_rows, Some(10));
+        repl.handle_set_command(":set max_rows=off");
+        assert_eq!(repl.executor.options().max_rows, None);
+    }
+
+    #[test]
+    fn test_import_outside_allowed_paths_is_rejected() {
+        let root = tempfile::tempdir().unwrap();
+        let outside = tempfile::tempdir().unwrap();
+        let file = outside.path().join("datos.csv");
+        fs::write(&file, "id\n1\n").unwrap();
+
+        let mut config = CliConfig::default();
+        config.database.connection_string = ":memory:".to_string();
+        config.global.allowed_paths = vec![root.path().to_path_buf()];
+        let mut repl = Repl::new(config, ReplArgs::default()).unwrap();
+
+        let error = repl
+            .handle_import(&file.to_string_lossy(), "datos", &HashMap::new())
+            .unwrap_err();
+        assert!(matches!(error, NoctraError::Permission { .. }));
+    }
+
+    #[test]
+    fn test_save_blob_writes_the_selected_blob() {
+        let root = tempfile::tempdir().unwrap();
+        let mut config = CliConfig::default();
+        config.database.connection_string = ":memory:".to_string();
+        config.global.allowed_paths = vec![root.path().to_path_buf()];
+        let mut repl = Repl::new(config, ReplArgs::default()).unwrap();
+        repl.execute_sql_statement("CREATE TABLE files (id INTEGER, doc BLOB)", false)
+            .unwrap();
+        repl.execute_sql_statement("INSERT INTO files VALUES (1, x'25504446ff00')", false)
+            .unwrap();
+
+        let file = root.path().join("out.pdf");
+        repl.handle_save_blob(
+            "SELECT doc FROM files WHERE id = 1",
+            &file.to_string_lossy(),
+        )
+        .unwrap();
+        assert_eq!(fs::read(&file).unwrap(), b"%PDF\xff\x00");
+
+        // Fuera de las rutas permitidas no se escribe nada
+        let outside = tempfile::tempdir().unwrap();
+        let file = outside.path().join("out.pdf");
+        let error = repl
+            .handle_save_blob(
+                "SELECT doc FROM files WHERE id = 1",
+                &file.to_string_lossy(),
+            )
+            .unwrap_err();
+        assert!(matches!(error, NoctraError::Permission { .. }));
+        assert!(!file.exists());
+    }
+
+    #[test]
+    fn test_export_writes_rows_beyond_max_rows() {
+        let dir = tempfile::tempdir().unwrap();
+        let file = dir.path().join("numeros.csv");
+        let mut repl = test_repl(1000);
+        repl.handle_set_command(":set max_rows=10");
+
+        repl.handle_export(
+            "SELECT x FROM (WITH RECURSIVE c(x) AS (SELECT 1 UNION ALL SELECT x + 1 FROM c WHERE x < 25) SELECT x FROM c)",
+            &file.to_string_lossy(),
+            &noctra_parser::ExportFormat::Csv,
+            &HashMap::new(),
+        )
+        .unwrap();
+        let content = fs::read_to_string(&file).unwrap();
+        assert_eq!(content.lines().count(), 26);
+    }
+
+    #[test]
+    fn test_run_script_parses_multiline_statements_as_rql() {
+        let mut repl = test_repl(1000);
+        let script = "CREATE TABLE t (n INTEGER, tipo TEXT);\n\
+                      CREATE TRIGGER clasificar AFTER INSERT ON t BEGIN\n\
+                        UPDATE t SET tipo = CASE WHEN n > 0 THEN 'pos' ELSE 'neg' END;\n\
+                      END;\n\
+                      INSERT INTO t (n) VALUES (5);\n\
+                      ASSERT (SELECT count(*) FROM t\n  WHERE tipo = 'pos') = 1;\n\
+                      SELECT tipo\n  FROM t;";
+
+        let failed = repl
+            .run_script(script, true, ScriptOptions::default())
+            .unwrap();
+
+        assert_eq!(failed, 0);
+        let last = repl.last_result.as_ref().unwrap();
+        assert_eq!(
+            last.rows[0].values[0],
+            noctra_core::Value::Text("pos".to_string())
+        );
+    }
+
+    #[test]
+    fn test_map_and_filter_chain_on_last_result() {
+        let mut repl = test_repl(1000);
+        assert!(repl.handle_filter("n > 1").is_err());
+
+        repl.execute_sql_statement(
+            "SELECT 1 AS n, 'a' AS letra UNION ALL SELECT 2, 'b' UNION ALL SELECT 3, 'c'",
+            false,
+        )
+        .unwrap();
+        repl.handle_filter("n >= 2").unwrap();
+        repl.handle_map(&[noctra_parser::MapExpression {
+            expression: "n * 10".to_string(),
+            alias: Some("decenas".to_string()),
+        }])
+        .unwrap();
+
+        let last = repl.last_result.as_ref().unwrap();
+        assert_eq!(last.columns[0].name, "decenas");
+        assert_eq!(last.rows.len(), 2);
+        assert_eq!(last.rows[1].values[0], noctra_core::Value::Integer(30));
+    }
+}
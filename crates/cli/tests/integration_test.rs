@@ -91,6 +91,7 @@ fn test_query_formatting() {
         ],
         rows_affected: None,
         last_insert_rowid: None,
+        truncated: false,
     };
 
     let table = format_result_set(&result);
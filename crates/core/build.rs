@@ -0,0 +1,42 @@
+//! Genera `docs/ERROR_CODES.md` a partir del registro de códigos de error
+//!
+//! El registro (`src/error_codes.rs`) es la única fuente de verdad; el
+//! documento sólo se reescribe si cambió, y sólo dentro del workspace (en un
+//! paquete publicado no existe `docs/` y no se genera nada).
+
+use std::fmt::Write as _;
+use std::path::Path;
+
+include!("src/error_codes.rs");
+
+fn main() {
+    println!("cargo:rerun-if-changed=src/error_codes.rs");
+
+    let manifest_dir = std::env::var("CARGO_MANIFEST_DIR").unwrap_or_default();
+    let docs_dir = Path::new(&manifest_dir).join("../../docs");
+    if !docs_dir.is_dir() {
+        return;
+    }
+
+    let mut markdown = String::from(
+        "# Códigos de error de Noctra\n\
+         \n\
+         > Generado por `crates/core/build.rs` a partir de \
+         `crates/core/src/error_codes.rs`; no editar a mano.\n\
+         \n\
+         `NoctraError::error_code()` devuelve uno de estos códigos y el \
+         servidor lo expone en el campo `code` de `ServerError`.\n\
+         \n\
+         | Código | Origen | Descripción |\n\
+         |--------|--------|-------------|\n",
+    );
+    for (code, origin, description) in ERROR_CODES {
+        let _ = writeln!(markdown, "| `{}` | `{}` | {} |", code, origin, description);
+    }
+
+    let path = docs_dir.join("ERROR_CODES.md");
+    if std::fs::read_to_string(&path).ok().as_deref() != Some(markdown.as_str()) {
+        // Un docs/ de sólo lectura no debe romper la compilación
+        let _ = std::fs::write(&path, markdown);
+    }
+}
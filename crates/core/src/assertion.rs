@@ -0,0 +1,219 @@
+//! Validación de resultados para comandos ASSERT
+//!
+//! Compara el resultado de la query interna de un `ASSERT` contra el valor
+//! o la cantidad de filas esperada, produciendo
+//! `NoctraError::AssertionFailed` cuando no coinciden.
+
+use crate::error::{NoctraError, Result};
+use crate::types::{parse_timestamp, ResultSet, Value};
+use chrono::{NaiveDate, NaiveTime};
+
+/// Tolerancia al comparar valores de punto flotante
+const FLOAT_EPSILON: f64 = 1e-9;
+
+/// Verificar un resultado contra lo esperado por un ASSERT
+///
+/// Si `expected_count` está presente se compara la cantidad de filas; si no,
+/// se compara el valor escalar (primera columna de la primera fila) contra
+/// `expected_value`.
+pub fn check_assertion(
+    result: &ResultSet,
+    expected_count: Option<usize>,
+    expected_value: Option<&serde_json::Value>,
+    label: Option<&str>,
+) -> Result<()> {
+    if let Some(expected) = expected_count {
+        let actual = result.row_count();
+        if actual != expected {
+            return Err(assertion_failed(
+                label,
+                format!("{} filas", expected),
+                format!("{} filas", actual),
+            ));
+        }
+    }
+
+    if let Some(expected) = expected_value {
+        let actual = result.rows.first().and_then(|row| row.values.first());
+        match actual {
+            Some(actual) if value_matches(actual, expected) => {}
+            Some(actual) => {
+                return Err(assertion_failed(
+                    label,
+                    expected.to_string(),
+                    actual.to_string(),
+                ));
+            }
+            None => {
+                return Err(assertion_failed(
+                    label,
+                    expected.to_string(),
+                    "(sin filas)".to_string(),
+                ));
+            }
+        }
+    }
+
+    Ok(())
+}
+
+/// Comparar un valor de resultado contra un literal esperado
+pub fn value_matches(actual: &Value, expected: &serde_json::Value) -> bool {
+    match (actual, expected) {
+        (Value::Null, serde_json::Value::Null) => true,
+        (Value::Boolean(a), serde_json::Value::Bool(e)) => a == e,
+        // SQLite representa booleanos como 0/1
+        (Value::Integer(a), serde_json::Value::Bool(e)) => *a == i64::from(*e),
+        (Value::Integer(a), serde_json::Value::Number(e)) => match e.as_i64() {
+            Some(e) => *a == e,
+            None => e.as_f64().is_some_and(|e| floats_equal(*a as f64, e)),
+        },
+        (Value::Float(a), serde_json::Value::Number(e)) => {
+            e.as_f64().is_some_and(|e| floats_equal(*a, e))
+        }
+        // Los decimales se comparan exactamente
+        #[cfg(feature = "decimal")]
+        (Value::Decimal(a), serde_json::Value::Number(e)) => e
+            .to_string()
+            .parse::<rust_decimal::Decimal>()
+            .is_ok_and(|e| *a == e),
+        (Value::Text(a), serde_json::Value::String(e)) => a == e,
+        // Fechas y horas: se compara el valor, no el formato del literal
+        (Value::Date(a), serde_json::Value::String(e)) => {
+            e.trim().parse::<NaiveDate>().is_ok_and(|e| *a == e)
+        }
+        (Value::Time(a), serde_json::Value::String(e)) => {
+            e.trim().parse::<NaiveTime>().is_ok_and(|e| *a == e)
+        }
+        (Value::Timestamp(a), serde_json::Value::String(e)) => parse_timestamp(e) == Some(*a),
+        (Value::Json(a), e) => a == e,
+        _ => false,
+    }
+}
+
+fn floats_equal(a: f64, b: f64) -> bool {
+    (a - b).abs() <= FLOAT_EPSILON * a.abs().max(b.abs()).max(1.0)
+}
+
+fn assertion_failed(label: Option<&str>, expected: String, actual: String) -> NoctraError {
+    NoctraError::AssertionFailed {
+        label: label.map(str::to_string),
+        expected,
+        actual,
+    }
+}
+
+#[cfg(all(test, feature = "sqlite"))]
+mod tests {
+    use super::*;
+    use crate::executor::{Executor, RqlQuery};
+    use crate::session::Session;
+    use crate::types::Parameters;
+
+    fn orders_executor() -> (Executor, Session) {
+        let executor = Executor::new_sqlite_memory().unwrap();
+        let session = Session::new();
+        executor
+            .execute_rql(
+                &session,
+                RqlQuery::new(
+                    "CREATE TABLE orders (id INTEGER, status TEXT, total REAL)",
+                    Parameters::new(),
+                ),
+            )
+            .unwrap();
+        executor
+            .execute_rql(
+                &session,
+                RqlQuery::new(
+                    "INSERT INTO orders VALUES (1, 'paid', 10.5), (2, 'paid', 4.5), (3, 'open', 1.0)",
+                    Parameters::new(),
+                ),
+            )
+            .unwrap();
+        (executor, session)
+    }
+
+    fn run(executor: &Executor, session: &Session, sql: &str) -> ResultSet {
+        executor
+            .execute_rql(session, RqlQuery::new(sql, Parameters::new()))
+            .unwrap()
+    }
+
+    #[test]
+    fn test_passing_assertions() {
+        let (executor, session) = orders_executor();
+
+        let count = run(&executor, &session, "SELECT COUNT(*) FROM orders");
+        assert!(check_assertion(&count, None, Some(&serde_json::json!(3)), None).is_ok());
+
+        let total = run(&executor, &session, "SELECT SUM(total) FROM orders");
+        assert!(check_assertion(&total, None, Some(&serde_json::json!(16.0)), None).is_ok());
+
+        let status = run(
+            &executor,
+            &session,
+            "SELECT status FROM orders WHERE id = 3",
+        );
+        assert!(check_assertion(&status, None, Some(&serde_json::json!("open")), None).is_ok());
+
+        let paid = run(
+            &executor,
+            &session,
+            "SELECT * FROM orders WHERE status = 'paid'",
+        );
+        assert!(check_assertion(&paid, Some(2), None, None).is_ok());
+    }
+
+    #[test]
+    fn test_failing_assertions() {
+        let (executor, session) = orders_executor();
+
+        let count = run(&executor, &session, "SELECT COUNT(*) FROM orders");
+        let err = check_assertion(
+            &count,
+            None,
+            Some(&serde_json::json!(100)),
+            Some("order count check"),
+        )
+        .unwrap_err();
+        match &err {
+            NoctraError::AssertionFailed {
+                label,
+                expected,
+                actual,
+            } => {
+                assert_eq!(label.as_deref(), Some("order count check"));
+                assert_eq!(expected, "100");
+                assert_eq!(actual, "3");
+            }
+            other => panic!("Expected AssertionFailed, got {:?}", other),
+        }
+        assert!(err.to_string().contains("order count check"));
+
+        let paid = run(
+            &executor,
+            &session,
+            "SELECT * FROM orders WHERE status = 'paid'",
+        );
+        assert!(matches!(
+            check_assertion(&paid, Some(5), None, None),
+            Err(NoctraError::AssertionFailed { .. })
+        ));
+
+        let empty = run(&executor, &session, "SELECT id FROM orders WHERE id > 10");
+        assert!(check_assertion(&empty, None, Some(&serde_json::json!(1)), None).is_err());
+    }
+
+    #[cfg(feature = "decimal")]
+    #[test]
+    fn test_decimal_values_match_exactly() {
+        let total = Value::Decimal("20.99".parse().unwrap());
+        assert!(value_matches(&total, &serde_json::json!(20.99)));
+        assert!(!value_matches(&total, &serde_json::json!(20.990000001)));
+        assert_eq!(
+            total.to_json_number(),
+            Some(serde_json::Number::from_f64(20.99).unwrap())
+        );
+    }
+}
@@ -0,0 +1,254 @@
+//! Paquetes portables de una fuente (`EXPORT SOURCE alias TO 'dir/'`)
+//!
+//! Un paquete es un directorio con:
+//!
+//! - `manifest.json`: versión de Noctra, fecha, fuente y, por tabla, el
+//!   archivo de datos y las filas exportadas (`BundleManifest`)
+//! - `schema.json`: tablas y columnas con sus tipos, en el formato de
+//!   `SchemaEntry` (el mismo que `EXPORT SCHEMA`)
+//! - `data/<tabla>.csv`: los datos de cada tabla, CSV RFC 4180 con cabecera
+//!
+//! En los CSV, NULL es un campo vacío sin comillas, el texto va siempre
+//! entre comillas y los BLOB byte a byte como `\xHH` (la forma que DuckDB
+//! convierte a BLOB; la versión 1 del formato los escribía como `0x…`).
+//! `Executor::export_bundle` escribe el paquete con todas las filas de cada
+//! tabla; `Executor::import_bundle` lo vuelve a cargar con la fábrica del
+//! esquema `bundle://` (la de noctra-duckdb), que lee los CSV con los tipos
+//! de `schema.json`.
+
+use crate::datasource::{DataSource, SchemaEntry, SchemaTable};
+use crate::error::{NoctraError, Result};
+use crate::types::{Column, Parameters, Row, Value};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::HashSet;
+use std::io::Write;
+use std::path::Path;
+
+/// Manifiesto del paquete
+pub const MANIFEST_FILE: &str = "manifest.json";
+
+/// Esquema de las tablas del paquete
+pub const SCHEMA_FILE: &str = "schema.json";
+
+/// Directorio de los datos, relativo al paquete
+pub const DATA_DIR: &str = "data";
+
+/// Versión del formato de paquete que se escribe y se sabe leer
+pub const BUNDLE_FORMAT_VERSION: u32 = 2;
+
+/// Contenido de `manifest.json`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct BundleManifest {
+    /// Versión del formato (`BUNDLE_FORMAT_VERSION`)
+    pub format_version: u32,
+
+    /// Versión de Noctra que escribió el paquete
+    pub noctra_version: String,
+
+    /// Momento de la exportación
+    pub created_at: DateTime<Utc>,
+
+    /// Alias de la fuente exportada
+    pub source: String,
+
+    /// Tipo de la fuente exportada (`csv`, `duckdb`...)
+    pub source_type: String,
+
+    /// Tablas exportadas, en el orden del esquema
+    pub tables: Vec<BundleTable>,
+}
+
+/// Tabla de un paquete
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct BundleTable {
+    /// Nombre de la tabla
+    pub name: String,
+
+    /// Archivo de datos, relativo al paquete
+    pub file: String,
+
+    /// Filas exportadas
+    pub rows: u64,
+}
+
+impl BundleManifest {
+    /// Leer el manifiesto del paquete `dir`
+    ///
+    /// Falla si no es un paquete o si lo escribió una versión del formato
+    /// más nueva.
+    pub fn read(dir: &Path) -> Result<Self> {
+        let path = dir.join(MANIFEST_FILE);
+        let content = std::fs::read_to_string(&path).map_err(|e| {
+            NoctraError::Io(format!(
+                "'{}' no es un paquete de Noctra: {}",
+                dir.display(),
+                e
+            ))
+        })?;
+        let manifest: Self = serde_json::from_str(&content)
+            .map_err(|e| NoctraError::Serialization(format!("{}: {}", path.display(), e)))?;
+        if manifest.format_version > BUNDLE_FORMAT_VERSION {
+            return Err(NoctraError::Validation(format!(
+                "El paquete '{}' usa el formato {} y esta versión sólo lee hasta el {}",
+                dir.display(),
+                manifest.format_version,
+                BUNDLE_FORMAT_VERSION
+            )));
+        }
+        Ok(manifest)
+    }
+
+    /// Leer `schema.json` del paquete `dir`
+    pub fn read_schema(dir: &Path) -> Result<SchemaEntry> {
+        let path = dir.join(SCHEMA_FILE);
+        let content = std::fs::read_to_string(&path)?;
+        serde_json::from_str(&content)
+            .map_err(|e| NoctraError::Serialization(format!("{}: {}", path.display(), e)))
+    }
+}
+
+/// Escribir en `dir` el paquete de `source` (registrada como `alias`)
+///
+/// Cada tabla se lee con `DataSource::query_stream`, sin el límite de filas
+/// de `query`; si la fuente sólo puede dar un resultado recortado, falla.
+pub fn write_bundle(dir: &Path, alias: &str, source: &dyn DataSource) -> Result<BundleManifest> {
+    let tables = source.schema()?;
+    std::fs::create_dir_all(dir.join(DATA_DIR))?;
+
+    let mut used_files = HashSet::new();
+    let mut manifest = BundleManifest {
+        format_version: BUNDLE_FORMAT_VERSION,
+        noctra_version: env!("CARGO_PKG_VERSION").to_string(),
+        created_at: Utc::now(),
+        source: alias.to_string(),
+        source_type: source.source_type().type_name().to_string(),
+        tables: Vec::with_capacity(tables.len()),
+    };
+    for table in &tables {
+        let sql = format!("SELECT * FROM \"{}\"", table.name.replace('"', "\"\""));
+        let mut stream = source.query_stream("", &sql, &Parameters::new())?;
+        if stream.is_truncated() {
+            return Err(NoctraError::Validation(format!(
+                "La fuente '{}' no puede leer la tabla '{}' entera",
+                alias, table.name
+            )));
+        }
+        let columns = stream.columns().to_vec();
+        let file = data_file_name(&table.name, &mut used_files);
+        let rows = write_csv(&columns, &mut stream, &dir.join(&file))?;
+        manifest.tables.push(BundleTable {
+            name: table.name.clone(),
+            file,
+            rows,
+        });
+    }
+
+    let schema = SchemaEntry {
+        alias: alias.to_string(),
+        source_type: manifest.source_type.clone(),
+        tables: tables.iter().map(SchemaTable::from).collect(),
+    };
+    write_json(&dir.join(SCHEMA_FILE), &schema)?;
+    write_json(&dir.join(MANIFEST_FILE), &manifest)?;
+    Ok(manifest)
+}
+
+/// `data/<tabla>.csv` con los caracteres problemáticos cambiados por `_`
+/// (y un sufijo si dos tablas quedan con el mismo nombre)
+fn data_file_name(table: &str, used: &mut HashSet<String>) -> String {
+    let stem: String = table
+        .chars()
+        .map(|c| {
+            if c.is_ascii_alphanumeric() || c == '_' || c == '-' {
+                c
+            } else {
+                '_'
+            }
+        })
+        .collect();
+    let mut file = format!("{}/{}.csv", DATA_DIR, stem);
+    let mut n = 2;
+    while !used.insert(file.to_lowercase()) {
+        file = format!("{}/{}_{}.csv", DATA_DIR, stem, n);
+        n += 1;
+    }
+    file
+}
+
+fn write_json<T: Serialize>(path: &Path, value: &T) -> Result<()> {
+    let content = serde_json::to_string_pretty(value)
+        .map_err(|e| NoctraError::Serialization(e.to_string()))?;
+    std::fs::write(path, content)?;
+    Ok(())
+}
+
+/// Campo CSV de un valor (ver la documentación del módulo)
+fn csv_field(value: &Value) -> String {
+    match value {
+        Value::Null => String::new(),
+        Value::Text(text) => format!("\"{}\"", text.replace('"', "\"\"")),
+        Value::Blob(bytes) => bytes.iter().map(|b| format!("\\x{:02x}", b)).collect(),
+        other => {
+            let text = other.to_string();
+            if text.contains([',', '"', '\n', '\r']) {
+                format!("\"{}\"", text.replace('"', "\"\""))
+            } else {
+                text
+            }
+        }
+    }
+}
+
+/// Escribir las filas según llegan; devuelve cuántas se escribieron
+fn write_csv(
+    columns: &[Column],
+    rows: impl Iterator<Item = Result<Row>>,
+    path: &Path,
+) -> Result<u64> {
+    let mut out = std::io::BufWriter::new(std::fs::File::create(path)?);
+    let header: Vec<String> = columns
+        .iter()
+        .map(|column| csv_field(&Value::Text(column.name.clone())))
+        .collect();
+    writeln!(out, "{}", header.join(","))?;
+    let mut written = 0;
+    for row in rows {
+        let fields: Vec<String> = row?.values.iter().map(csv_field).collect();
+        writeln!(out, "{}", fields.join(","))?;
+        written += 1;
+    }
+    out.flush()?;
+    Ok(written)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_csv_field_quoting() {
+        assert_eq!(csv_field(&Value::Null), "");
+        assert_eq!(csv_field(&Value::Text(String::new())), "\"\"");
+        assert_eq!(
+            csv_field(&Value::Text("a,\"b\"".to_string())),
+            "\"a,\"\"b\"\"\""
+        );
+        assert_eq!(csv_field(&Value::Integer(7)), "7");
+        assert_eq!(csv_field(&Value::Blob(vec![0, 255])), "\\x00\\xff");
+    }
+
+    #[test]
+    fn test_data_file_names_are_unique() {
+        let mut used = HashSet::new();
+        assert_eq!(data_file_name("ventas", &mut used), "data/ventas.csv");
+        assert_eq!(
+            data_file_name("ventas 2024", &mut used),
+            "data/ventas_2024.csv"
+        );
+        assert_eq!(
+            data_file_name("ventas/2024", &mut used),
+            "data/ventas_2024_2.csv"
+        );
+    }
+}
@@ -0,0 +1,129 @@
+//! Catálogo de esquemas del executor
+//!
+//! Reúne las tablas de todas las fuentes registradas y de la base del
+//! backend en una sola vista, para autocompletado y `DESCRIBE` sin tener
+//! que preguntar a cada fuente en cada tecla. `Executor::catalog` lo guarda
+//! en caché hasta el próximo DDL o `Executor::invalidate_catalog`.
+
+use crate::datasource::{SourceRegistry, TableInfo};
+use crate::error::{NoctraError, Result};
+
+/// Tabla del catálogo y fuente a la que pertenece
+#[derive(Debug, Clone)]
+pub struct CatalogEntry {
+    /// Alias de la fuente (None = base del backend)
+    pub source: Option<String>,
+
+    /// Tabla con sus columnas
+    pub table: TableInfo,
+}
+
+impl CatalogEntry {
+    /// Nombre cualificado (`fuente.tabla`, o sólo `tabla` en el backend)
+    pub fn qualified_name(&self) -> String {
+        match &self.source {
+            Some(source) => format!("{}.{}", source, self.table.name),
+            None => self.table.name.clone(),
+        }
+    }
+}
+
+/// Tablas de todas las fuentes y del backend
+#[derive(Debug, Clone, Default)]
+pub struct Catalog {
+    entries: Vec<CatalogEntry>,
+
+    /// Alias de las fuentes registradas (también las que no tienen tablas)
+    sources: Vec<String>,
+}
+
+impl Catalog {
+    /// Construir el catálogo a partir del registro y las tablas del backend
+    ///
+    /// Las fuentes cuyo esquema no se puede leer se omiten.
+    pub fn build(registry: &SourceRegistry, backend_tables: Vec<TableInfo>) -> Self {
+        let mut entries: Vec<CatalogEntry> = backend_tables
+            .into_iter()
+            .map(|table| CatalogEntry {
+                source: None,
+                table,
+            })
+            .collect();
+
+        let sources = registry.aliases();
+        for alias in &sources {
+            let Some(source) = registry.get(alias) else {
+                continue;
+            };
+            for table in source.schema().unwrap_or_default() {
+                entries.push(CatalogEntry {
+                    source: Some(alias.clone()),
+                    table,
+                });
+            }
+        }
+
+        Self { entries, sources }
+    }
+
+    /// Todas las tablas del catálogo (primero las del backend)
+    pub fn entries(&self) -> &[CatalogEntry] {
+        &self.entries
+    }
+
+    /// Buscar una tabla por nombre (`tabla` o `fuente.tabla`)
+    ///
+    /// Los nombres no distinguen mayúsculas. Si un nombre sin cualificar
+    /// existe en varias fuentes se elige el de `preferred_source` (la fuente
+    /// contra la que se ejecutan las consultas; None = backend); si tampoco
+    /// así hay una única tabla, el nombre es ambiguo.
+    pub fn lookup(&self, name: &str, preferred_source: Option<&str>) -> Result<&CatalogEntry> {
+        if let Some((source, table)) = name.split_once('.') {
+            if self.sources.iter().any(|alias| alias == source) {
+                return self
+                    .entries
+                    .iter()
+                    .find(|entry| {
+                        entry.source.as_deref() == Some(source)
+                            && entry.table.name.eq_ignore_ascii_case(table)
+                    })
+                    .ok_or_else(|| {
+                        NoctraError::Validation(format!(
+                            "tabla '{}' no encontrada en '{}'",
+                            table, source
+                        ))
+                    });
+            }
+        }
+
+        let matches: Vec<&CatalogEntry> = self
+            .entries
+            .iter()
+            .filter(|entry| entry.table.name.eq_ignore_ascii_case(name))
+            .collect();
+        match matches.as_slice() {
+            [] => Err(NoctraError::Validation(format!(
+                "tabla '{}' no encontrada",
+                name
+            ))),
+            [entry] => Ok(entry),
+            _ => {
+                let mut preferred = matches
+                    .iter()
+                    .filter(|entry| entry.source.as_deref() == preferred_source);
+                match (preferred.next(), preferred.next()) {
+                    (Some(entry), None) => Ok(entry),
+                    _ => Err(NoctraError::Validation(format!(
+                        "tabla '{}' ambigua: existe en {}; usa fuente.tabla",
+                        name,
+                        matches
+                            .iter()
+                            .map(|entry| entry.qualified_name())
+                            .collect::<Vec<_>>()
+                            .join(", ")
+                    ))),
+                }
+            }
+        }
+    }
+}
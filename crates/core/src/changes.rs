@@ -0,0 +1,250 @@
+//! Notificaciones de cambios por fila
+//!
+//! `SqliteBackend` engancha los hooks de SQLite (update, commit y rollback)
+//! a un `ChangeFeed`: cada fila insertada, modificada o borrada queda
+//! pendiente hasta que su transacción se confirma; un rollback la descarta.
+//! El commit hook corre antes de que el commit termine (y puede fallar, p.
+//! ej. con SQLITE_BUSY), así que sólo aparta los cambios: el backend los
+//! publica con `publish_committed` cuando la sentencia ha vuelto y la
+//! conexión ya no tiene transacción abierta. `Executor::subscribe_changes`
+//! devuelve un receptor del canal, que el servidor usa para volver a
+//! ejecutar las consultas en vivo de los clientes WebSocket.
+//!
+//! SQLite no avisa de un `ROLLBACK TO SAVEPOINT`: las filas que deshace se
+//! notifican igualmente al confirmar la transacción.
+
+use serde::{Deserialize, Serialize};
+use std::cell::RefCell;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, Mutex};
+use tokio::sync::broadcast;
+
+/// Eventos que guarda el canal; un receptor más lento pierde los más antiguos
+pub const CHANGE_CHANNEL_CAPACITY: usize = 1024;
+
+/// Operación que cambió una fila
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum ChangeOp {
+    Insert,
+    Update,
+    Delete,
+}
+
+/// Fila cambiada por una transacción confirmada
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+pub struct ChangeEvent {
+    /// Tabla de la fila; con el prefijo de su base si no es `main`
+    pub table: String,
+
+    /// Operación neta de la transacción sobre la fila
+    pub op: ChangeOp,
+
+    /// Rowid de la fila
+    pub rowid: i64,
+}
+
+/// Cambios de la transacción en curso, uno por fila
+#[derive(Debug, Default)]
+struct PendingChanges {
+    /// Eventos en orden de llegada (`None` si se anularon entre sí)
+    events: Vec<Option<ChangeEvent>>,
+
+    /// Posición en `events` de cada (tabla, rowid)
+    index: HashMap<(String, i64), usize>,
+}
+
+impl PendingChanges {
+    /// Combinar el cambio con el anterior de la misma fila
+    ///
+    /// INSERT + UPDATE sigue siendo INSERT, INSERT + DELETE se anula,
+    /// DELETE + INSERT (rowid reutilizado) es un UPDATE y en el resto manda
+    /// la última operación.
+    fn push(&mut self, event: ChangeEvent) {
+        let key = (event.table.clone(), event.rowid);
+        let Some(&position) = self.index.get(&key) else {
+            self.index.insert(key, self.events.len());
+            self.events.push(Some(event));
+            return;
+        };
+
+        let previous = self.events[position].as_ref().map(|e| e.op);
+        let op = match (previous, event.op) {
+            (Some(ChangeOp::Insert), ChangeOp::Update) => ChangeOp::Insert,
+            (Some(ChangeOp::Insert), ChangeOp::Delete) => {
+                self.events[position] = None;
+                self.index.remove(&key);
+                return;
+            }
+            (Some(ChangeOp::Delete), ChangeOp::Insert) => ChangeOp::Update,
+            (_, op) => op,
+        };
+        self.events[position] = Some(ChangeEvent { op, ..event });
+    }
+
+    /// Vaciar los cambios, en orden de llegada
+    fn take(&mut self) -> Vec<ChangeEvent> {
+        self.index.clear();
+        std::mem::take(&mut self.events)
+            .into_iter()
+            .flatten()
+            .collect()
+    }
+}
+
+thread_local! {
+    /// Cambios cuyo commit hook ya corrió en este hilo, a la espera de que
+    /// la sentencia que confirma vuelva (ver `publish_committed`)
+    static COMMITTING: RefCell<Vec<(Arc<ChangeFeed>, Vec<ChangeEvent>)>> =
+        const { RefCell::new(Vec::new()) };
+}
+
+/// Publicar los cambios que el commit hook apartó en este hilo
+///
+/// Se llama al volver cada sentencia con `committed = conn.is_autocommit()`:
+/// si la conexión sigue en una transacción el commit no terminó y los
+/// cambios vuelven a quedar pendientes hasta el siguiente.
+pub(crate) fn publish_committed(committed: bool) {
+    let batches = COMMITTING.with(|committing| std::mem::take(&mut *committing.borrow_mut()));
+    for (feed, events) in batches {
+        if committed {
+            for event in events {
+                // Sin suscriptores el envío falla y el evento se pierde
+                let _ = feed.sender.send(event);
+            }
+        } else {
+            feed.restore(events);
+        }
+    }
+}
+
+/// Canal de cambios de un backend
+#[derive(Debug)]
+pub(crate) struct ChangeFeed {
+    sender: broadcast::Sender<ChangeEvent>,
+    pending: Mutex<PendingChanges>,
+    include_attached: AtomicBool,
+}
+
+impl ChangeFeed {
+    /// Canal sin suscriptores que sólo notifica la base `main`
+    pub(crate) fn new() -> Self {
+        let (sender, _) = broadcast::channel(CHANGE_CHANNEL_CAPACITY);
+        Self {
+            sender,
+            pending: Mutex::new(PendingChanges::default()),
+            include_attached: AtomicBool::new(false),
+        }
+    }
+
+    /// Canal que publica en el mismo broadcast con sus propios cambios
+    /// pendientes (una conexión más del pool de `SqlitePool`)
+    pub(crate) fn sibling(&self) -> Self {
+        Self {
+            sender: self.sender.clone(),
+            pending: Mutex::new(PendingChanges::default()),
+            include_attached: AtomicBool::new(self.include_attached.load(Ordering::Relaxed)),
+        }
+    }
+
+    /// Nuevo receptor de los cambios confirmados a partir de ahora
+    pub(crate) fn subscribe(&self) -> broadcast::Receiver<ChangeEvent> {
+        self.sender.subscribe()
+    }
+
+    /// Notificar también los cambios en bases adjuntas (`ATTACH`) y `temp`
+    pub(crate) fn set_include_attached(&self, include: bool) {
+        self.include_attached.store(include, Ordering::Relaxed);
+    }
+
+    /// Registrar una fila cambiada en la base `database`
+    pub(crate) fn record(&self, database: &str, table: &str, op: ChangeOp, rowid: i64) {
+        let table = if database == "main" {
+            table.to_string()
+        } else if self.include_attached.load(Ordering::Relaxed) {
+            format!("{}.{}", database, table)
+        } else {
+            return;
+        };
+        if let Ok(mut pending) = self.pending.lock() {
+            pending.push(ChangeEvent { table, op, rowid });
+        }
+    }
+
+    /// Apartar los cambios de la transacción que se está confirmando
+    ///
+    /// Lo llama el commit hook; se publican con `publish_committed`.
+    pub(crate) fn stash(self: &Arc<Self>) {
+        let events = match self.pending.lock() {
+            Ok(mut pending) => pending.take(),
+            Err(_) => return,
+        };
+        if !events.is_empty() {
+            COMMITTING.with(|committing| committing.borrow_mut().push((Arc::clone(self), events)));
+        }
+    }
+
+    /// Devolver a pendientes los cambios de un commit que no terminó,
+    /// delante de los que hayan llegado después
+    fn restore(&self, events: Vec<ChangeEvent>) {
+        if let Ok(mut pending) = self.pending.lock() {
+            let later = pending.take();
+            for event in events.into_iter().chain(later) {
+                pending.push(event);
+            }
+        }
+    }
+
+    /// Descartar los cambios de la transacción revertida
+    pub(crate) fn rollback(self: &Arc<Self>) {
+        if let Ok(mut pending) = self.pending.lock() {
+            pending.take();
+        }
+        COMMITTING.with(|committing| {
+            committing
+                .borrow_mut()
+                .retain(|(feed, _)| !Arc::ptr_eq(feed, self))
+        });
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_changes_published_only_after_commit_finishes() {
+        let feed = Arc::new(ChangeFeed::new());
+        let mut receiver = feed.subscribe();
+        let received = |receiver: &mut broadcast::Receiver<ChangeEvent>| {
+            std::iter::from_fn(|| receiver.try_recv().ok()).collect::<Vec<_>>()
+        };
+
+        feed.record("main", "t", ChangeOp::Insert, 1);
+        feed.stash();
+        assert!(received(&mut receiver).is_empty());
+
+        // El commit falló y la transacción sigue abierta: vuelven a pendientes
+        publish_committed(false);
+        assert!(received(&mut receiver).is_empty());
+        feed.record("main", "t", ChangeOp::Update, 1);
+        feed.stash();
+        publish_committed(true);
+        assert_eq!(
+            received(&mut receiver),
+            vec![ChangeEvent {
+                table: "t".to_string(),
+                op: ChangeOp::Insert,
+                rowid: 1
+            }]
+        );
+
+        // Un rollback tras el commit hook descarta también lo apartado
+        feed.record("main", "t", ChangeOp::Delete, 1);
+        feed.stash();
+        feed.rollback();
+        publish_committed(true);
+        assert!(received(&mut receiver).is_empty());
+    }
+}
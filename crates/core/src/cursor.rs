@@ -0,0 +1,173 @@
+//! Cursores sobre resultados en streaming
+//!
+//! `Executor::open_cursor` deja abierto el `RowStream` de una consulta y
+//! `Executor::fetch_cursor` lee sus filas por bloques, así que el servidor
+//! y el TUI pueden paginar sin materializar el resultado. Cada cursor
+//! pertenece a la sesión que lo abrió y se cierra al agotarse, con
+//! `Executor::close_cursor` o tras `ExecutorOptions::cursor_idle_timeout`
+//! sin uso.
+
+use serde::{Deserialize, Serialize};
+use std::collections::HashMap;
+use std::fmt;
+use std::sync::Mutex;
+use std::time::{Duration, Instant};
+
+use crate::error::{NoctraError, Result};
+use crate::types::{ResultSet, Row, RowStream};
+
+/// Tiempo sin uso tras el que se cierra un cursor si las opciones no
+/// indican otro
+pub const DEFAULT_CURSOR_IDLE_TIMEOUT: Duration = Duration::from_secs(300);
+
+/// Identificador de un cursor abierto
+///
+/// Es aleatorio para que no se pueda adivinar el cursor de otro cliente.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[serde(transparent)]
+pub struct CursorId(pub u64);
+
+impl fmt::Display for CursorId {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.0)
+    }
+}
+
+/// Bloque de filas leído de un cursor
+#[derive(Debug, Clone)]
+pub struct CursorPage {
+    /// Columnas y filas del bloque
+    pub result_set: ResultSet,
+
+    /// Si quedan filas; con `false` el cursor ya se cerró
+    pub has_more: bool,
+}
+
+/// Cursor abierto: el stream y la primera fila sin entregar
+#[derive(Debug)]
+struct Cursor {
+    session_id: String,
+    stream: RowStream,
+    /// Fila (o error) leída para saber si quedaban más
+    lookahead: Option<Result<Row>>,
+    last_used: Instant,
+}
+
+/// Cursores abiertos del executor
+#[derive(Debug, Default)]
+pub(crate) struct CursorRegistry {
+    cursors: Mutex<HashMap<CursorId, Cursor>>,
+}
+
+impl CursorRegistry {
+    /// Registrar el stream de `session_id` como cursor
+    pub(crate) fn open(&self, session_id: &str, stream: RowStream) -> Result<CursorId> {
+        let mut cursors = self.lock()?;
+        let id = loop {
+            let id = CursorId(uuid::Uuid::new_v4().as_u64_pair().0);
+            if !cursors.contains_key(&id) {
+                break id;
+            }
+        };
+        cursors.insert(
+            id,
+            Cursor {
+                session_id: session_id.to_string(),
+                stream,
+                lookahead: None,
+                last_used: Instant::now(),
+            },
+        );
+        Ok(id)
+    }
+
+    /// Leer hasta `count` filas del cursor
+    ///
+    /// El cursor sale del registro mientras se lee, para no bloquear al
+    /// resto; si se agota (o falla) ya no vuelve y su stream se suelta.
+    pub(crate) fn fetch(
+        &self,
+        session_id: &str,
+        id: CursorId,
+        count: usize,
+        idle_timeout: Duration,
+    ) -> Result<CursorPage> {
+        let mut cursor = {
+            let mut cursors = self.lock()?;
+            match cursors.remove(&id) {
+                Some(cursor) if cursor.session_id != session_id => {
+                    cursors.insert(id, cursor);
+                    return Err(cursor_not_found(id));
+                }
+                Some(cursor) if cursor.last_used.elapsed() <= idle_timeout => cursor,
+                _ => return Err(cursor_not_found(id)),
+            }
+        };
+
+        let mut result_set = ResultSet::new(cursor.stream.columns().to_vec());
+        result_set.rows_affected = cursor.stream.rows_affected();
+        while result_set.rows.len() < count {
+            match cursor.lookahead.take().or_else(|| cursor.stream.next()) {
+                Some(row) => result_set.add_row(row?),
+                None => break,
+            }
+        }
+
+        if cursor.lookahead.is_none() {
+            cursor.lookahead = cursor.stream.next();
+        }
+        let has_more = cursor.lookahead.is_some();
+        if has_more {
+            cursor.last_used = Instant::now();
+            self.lock()?.insert(id, cursor);
+        }
+
+        Ok(CursorPage {
+            result_set,
+            has_more,
+        })
+    }
+
+    /// Cerrar un cursor de `session_id`; `false` si no estaba abierto
+    pub(crate) fn close(&self, session_id: &str, id: CursorId) -> bool {
+        let Ok(mut cursors) = self.lock() else {
+            return false;
+        };
+        if cursors
+            .get(&id)
+            .is_some_and(|cursor| cursor.session_id == session_id)
+        {
+            cursors.remove(&id);
+            return true;
+        }
+        false
+    }
+
+    /// Cerrar todos los cursores de `session_id`; devuelve cuántos había
+    pub(crate) fn close_session(&self, session_id: &str) -> usize {
+        let Ok(mut cursors) = self.lock() else {
+            return 0;
+        };
+        let before = cursors.len();
+        cursors.retain(|_, cursor| cursor.session_id != session_id);
+        before - cursors.len()
+    }
+
+    /// Cerrar los cursores que llevan más de `idle_timeout` sin uso
+    pub(crate) fn purge_expired(&self, idle_timeout: Duration) {
+        if let Ok(mut cursors) = self.lock() {
+            cursors.retain(|_, cursor| cursor.last_used.elapsed() <= idle_timeout);
+        }
+    }
+
+    fn lock(&self) -> Result<std::sync::MutexGuard<'_, HashMap<CursorId, Cursor>>> {
+        self.cursors
+            .lock()
+            .map_err(|_| NoctraError::Internal("registro de cursores envenenado".to_string()))
+    }
+}
+
+/// Error de un cursor cerrado, caducado o de otra sesión
+fn cursor_not_found(id: CursorId) -> NoctraError {
+    NoctraError::not_found("cursor", id.to_string())
+}
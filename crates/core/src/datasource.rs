@@ -4,9 +4,15 @@
 //! data sources (SQLite, CSV, JSON, Memory) using a unified interface.
 
 use crate::error::{NoctraError, Result};
-use crate::types::{Parameters, ResultSet};
+use crate::executor::QueryPlan;
+use crate::session::Session;
+use crate::types::{Parameters, ResultSet, RowStream};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
 use std::fmt::Debug;
-use std::path::PathBuf;
+use std::path::Path;
+use std::sync::{Arc, RwLock};
+use std::time::{Duration, SystemTime};
 
 /// Trait for data sources in NQL
 ///
@@ -16,6 +22,193 @@ pub trait DataSource: Send + Sync + Debug {
     /// Execute a query against the data source
     fn query(&self, sql: &str, parameters: &Parameters) -> Result<ResultSet>;
 
+    /// Execute a query on behalf of a session
+    ///
+    /// Sources with session-scoped tables resolve unqualified names against
+    /// that session's tables first. Defaults to `query`.
+    fn query_in_session(
+        &self,
+        _session_id: &str,
+        sql: &str,
+        parameters: &Parameters,
+    ) -> Result<ResultSet> {
+        self.query(sql, parameters)
+    }
+
+    /// Execute a query on behalf of a session, cancelling it after `timeout`
+    ///
+    /// Returns `NoctraError::Timeout` when the query was interrupted. Sources
+    /// that cannot interrupt a running query ignore the timeout (the default).
+    fn query_with_timeout(
+        &self,
+        session_id: &str,
+        sql: &str,
+        parameters: &Parameters,
+        _timeout: Duration,
+    ) -> Result<ResultSet> {
+        self.query_in_session(session_id, sql, parameters)
+    }
+
+    /// Execute a query on behalf of a session, yielding rows as they are read
+    ///
+    /// Defaults to the materialized `query_in_session` result.
+    fn query_stream(
+        &self,
+        session_id: &str,
+        sql: &str,
+        parameters: &Parameters,
+    ) -> Result<RowStream> {
+        self.query_in_session(session_id, sql, parameters)
+            .map(RowStream::from_result_set)
+    }
+
+    /// Execute an INSERT, UPDATE or DELETE against the data source
+    ///
+    /// Read-only sources (the default) fail with `NoctraError::Validation`.
+    fn execute_write(&self, sql: &str, _parameters: &Parameters) -> Result<WriteResult> {
+        Err(NoctraError::Validation(format!(
+            "cannot run '{}': source '{}' ({}) is read-only",
+            sql.split_whitespace().next().unwrap_or(sql),
+            self.name(),
+            self.source_type().type_name()
+        )))
+    }
+
+    /// Execute a write on behalf of a session
+    ///
+    /// Sources with session-scoped tables resolve unqualified names against
+    /// that session's tables first. Defaults to `execute_write`.
+    fn execute_write_in_session(
+        &self,
+        _session_id: &str,
+        sql: &str,
+        parameters: &Parameters,
+    ) -> Result<WriteResult> {
+        self.execute_write(sql, parameters)
+    }
+
+    /// Execute a write on behalf of a session, cancelling it after `timeout`
+    ///
+    /// Returns `NoctraError::Timeout` when the write was interrupted. Sources
+    /// that cannot interrupt a running statement ignore the timeout (the default).
+    fn execute_write_with_timeout(
+        &self,
+        session_id: &str,
+        sql: &str,
+        parameters: &Parameters,
+        _timeout: Duration,
+    ) -> Result<WriteResult> {
+        self.execute_write_in_session(session_id, sql, parameters)
+    }
+
+    /// Whether `query` binds `$1`, `$2`, ... placeholders from its parameters
+    ///
+    /// When false, the executor substitutes the values into the SQL as quoted
+    /// literals and passes no parameters. Defaults to false.
+    fn binds_parameters(&self) -> bool {
+        false
+    }
+
+    /// Make the session's state (e.g. variables) visible to SQL run in this source
+    ///
+    /// Called by the executor before each query. Defaults to doing nothing.
+    fn sync_session(&self, _session: &Session) -> Result<()> {
+        Ok(())
+    }
+
+    /// Validate a query without executing it
+    ///
+    /// Sources that can't plan queries fail with `NoctraError::DryRunFailed`.
+    fn dry_run(&self, _sql: &str) -> Result<QueryPlan> {
+        Err(NoctraError::DryRunFailed(format!(
+            "source '{}' does not support dry runs",
+            self.name()
+        )))
+    }
+
+    /// Run a read-only query and return its plan with runtime statistics
+    ///
+    /// The plan's `root` holds the operator tree with actual rows and
+    /// timings. Sources without a profiler fail with `NoctraError::Validation`.
+    fn explain_analyze(&self, _sql: &str) -> Result<QueryPlan> {
+        Err(NoctraError::Validation(format!(
+            "source '{}' ({}) does not support EXPLAIN ANALYZE, use a DuckDB source",
+            self.name(),
+            self.source_type().type_name()
+        )))
+    }
+
+    /// Per-column statistics for a table (type, min/max, distinct count, nulls)
+    ///
+    /// Only sources with a profiling engine support this; the rest fail with
+    /// `NoctraError::Validation`.
+    fn profile(&self, table: &str) -> Result<ResultSet> {
+        Err(NoctraError::Validation(format!(
+            "cannot profile '{}': source '{}' ({}) does not support PROFILE, use a DuckDB source",
+            table,
+            self.name(),
+            self.source_type().type_name()
+        )))
+    }
+
+    /// Up to `n` rows of a table, to preview its data
+    ///
+    /// Defaults to `SELECT * FROM "table" LIMIT n`, which stops after the
+    /// first rows; sources with a sampling clause can return rows from
+    /// anywhere in the table instead, at the cost of reading all of it.
+    fn sample(&self, table: &str, n: usize) -> Result<ResultSet> {
+        self.query(
+            &format!(
+                "SELECT * FROM \"{}\" LIMIT {}",
+                table.replace('"', "\"\""),
+                n
+            ),
+            &Parameters::new(),
+        )
+    }
+
+    /// Persist pending writes to the underlying file (e.g. flush a WAL)
+    ///
+    /// Sources without a write-ahead log fail with `NoctraError::Validation`.
+    fn checkpoint(&self) -> Result<()> {
+        Err(NoctraError::Validation(format!(
+            "source '{}' ({}) does not support CHECKPOINT",
+            self.name(),
+            self.source_type().type_name()
+        )))
+    }
+
+    /// Create (or replace) a view over `sql` that lasts as long as the source
+    ///
+    /// Sources without persistent views fail with `NoctraError::Validation`.
+    fn create_view(&mut self, name: &str, _sql: &str) -> Result<()> {
+        Err(NoctraError::Validation(format!(
+            "cannot create view '{}': source '{}' ({}) does not support CREATE VIEW",
+            name,
+            self.name(),
+            self.source_type().type_name()
+        )))
+    }
+
+    /// Register a file as table `table` visible only to one session
+    ///
+    /// It lasts until `release_session`. Sources without per-session
+    /// tables fail with `NoctraError::Validation`.
+    fn register_session_file(&self, session_id: &str, _path: &str, table: &str) -> Result<()> {
+        Err(NoctraError::Validation(format!(
+            "cannot register '{}' for session '{}': source '{}' ({}) does not support per-session tables",
+            table,
+            session_id,
+            self.name(),
+            self.source_type().type_name()
+        )))
+    }
+
+    /// Drop whatever a session registered in this source (called when it ends)
+    fn release_session(&self, _session_id: &str) -> Result<()> {
+        Ok(())
+    }
+
     /// Get schema information (tables/columns)
     fn schema(&self) -> Result<Vec<TableInfo>>;
 
@@ -34,12 +227,47 @@ pub trait DataSource: Send + Sync + Debug {
         }
     }
 
+    /// Attach a SQLite database file so its tables can be queried from this source
+    ///
+    /// Returns the names under which the linked tables can be referenced.
+    /// Sources without cross-database support link nothing.
+    fn link_sqlite(&mut self, _path: &str) -> Result<Vec<String>> {
+        Ok(Vec::new())
+    }
+
     /// Close the data source (optional)
     fn close(&mut self) -> Result<()> {
         Ok(())
     }
 }
 
+/// Outcome of `DataSource::execute_write`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct WriteResult {
+    /// Rows inserted, updated or deleted
+    pub rows_affected: u64,
+    /// Rowid of the last inserted row, for sources that have rowids
+    pub last_insert_rowid: Option<i64>,
+}
+
+impl From<ResultSet> for WriteResult {
+    fn from(result: ResultSet) -> Self {
+        WriteResult {
+            rows_affected: result.rows_affected.unwrap_or(0),
+            last_insert_rowid: result.last_insert_rowid,
+        }
+    }
+}
+
+impl From<WriteResult> for ResultSet {
+    fn from(write: WriteResult) -> Self {
+        let mut result_set = ResultSet::empty();
+        result_set.rows_affected = Some(write.rows_affected);
+        result_set.last_insert_rowid = write.last_insert_rowid;
+        result_set
+    }
+}
+
 /// Type of data source
 #[derive(Debug, Clone, PartialEq, Eq)]
 pub enum SourceType {
@@ -59,12 +287,16 @@ pub enum SourceType {
         has_header: bool,
         /// Encoding (e.g., "utf-8", "latin1")
         encoding: String,
+        /// Compression codec (e.g., "gzip", "zstd"), if any
+        compression: Option<String>,
     },
 
     /// JSON file
     JSON {
         /// Path to JSON file
         path: String,
+        /// Compression codec (e.g., "gzip", "zstd"), if any
+        compression: Option<String>,
     },
 
     /// In-memory dataset
@@ -90,10 +322,20 @@ impl SourceType {
         match self {
             SourceType::SQLite { path } => path.clone(),
             SourceType::CSV { path, .. } => path.clone(),
-            SourceType::JSON { path } => path.clone(),
+            SourceType::JSON { path, .. } => path.clone(),
             SourceType::Memory { .. } => "(in-memory)".to_string(),
         }
     }
+
+    /// Get the compression codec of file-based sources, if any
+    pub fn compression(&self) -> Option<&str> {
+        match self {
+            SourceType::CSV { compression, .. } | SourceType::JSON { compression, .. } => {
+                compression.as_deref()
+            }
+            _ => None,
+        }
+    }
 }
 
 /// Information about a table in a data source
@@ -103,8 +345,12 @@ pub struct TableInfo {
     pub name: String,
     /// Columns in the table
     pub columns: Vec<ColumnInfo>,
-    /// Number of rows (if known)
+    /// Number of rows (if known; may be a cached approximation)
     pub row_count: Option<usize>,
+    /// When `row_count` was computed (None if not cached/known)
+    pub row_count_updated_at: Option<SystemTime>,
+    /// Whether this is a user-created view (`CREATE VIEW`)
+    pub is_view: bool,
 }
 
 /// Information about a column
@@ -118,6 +364,26 @@ pub struct ColumnInfo {
     pub nullable: bool,
     /// Default value (if any)
     pub default_value: Option<String>,
+    /// Whether the column is part of the primary key
+    pub primary_key: bool,
+}
+
+impl ColumnInfo {
+    /// Constraints as written in a table definition
+    /// (`PRIMARY KEY NOT NULL DEFAULT 0`), empty if there are none
+    pub fn constraints(&self) -> String {
+        let mut parts = Vec::new();
+        if self.primary_key {
+            parts.push("PRIMARY KEY".to_string());
+        }
+        if !self.nullable {
+            parts.push("NOT NULL".to_string());
+        }
+        if let Some(default) = &self.default_value {
+            parts.push(format!("DEFAULT {}", default));
+        }
+        parts.join(" ")
+    }
 }
 
 /// Metadata about a data source
@@ -138,12 +404,14 @@ pub struct CsvOptions {
     pub delimiter: Option<char>,
     /// Whether the file has a header row
     pub has_header: bool,
-    /// Encoding (None = auto-detect)
+    /// Encoding of the file, e.g. `latin-1` (None = UTF-8)
     pub encoding: Option<String>,
     /// Quote character
     pub quote: char,
     /// Skip N rows at the beginning
     pub skip_rows: usize,
+    /// Column names and types, in file order (empty = sniff them)
+    pub columns: Vec<(String, String)>,
 }
 
 impl Default for CsvOptions {
@@ -151,18 +419,218 @@ impl Default for CsvOptions {
         Self {
             delimiter: None, // Auto-detect
             has_header: true,
-            encoding: None, // Auto-detect
+            encoding: None, // UTF-8
             quote: '"',
             skip_rows: 0,
+            columns: Vec::new(),
+        }
+    }
+}
+
+impl CsvOptions {
+    /// Read `delimiter`, `quote`, `header`, `skip` and `encoding` from `USE ... OPTIONS (...)`
+    ///
+    /// Values may be quoted (`quote='"'`, `delimiter=';'`), and a single quote
+    /// is written doubled (`quote=''''`). Keys that are not CSV options
+    /// (`hive_partitioning`, `sheet`...) are ignored.
+    pub fn from_use_options(options: &HashMap<String, String>) -> Result<Self> {
+        let mut parsed = Self::default();
+        for (key, value) in options {
+            let value = unquote_option(value);
+            let value = value.as_str();
+            match key.to_lowercase().as_str() {
+                "delimiter" | "delim" | "sep" => {
+                    parsed.delimiter = Some(single_char(key, value)?);
+                }
+                "quote" => parsed.quote = single_char(key, value)?,
+                "header" | "has_header" => {
+                    parsed.has_header =
+                        !matches!(value.to_lowercase().as_str(), "false" | "no" | "0");
+                }
+                "skip" | "skip_rows" => {
+                    parsed.skip_rows = value.parse().map_err(|_| {
+                        NoctraError::Validation(format!(
+                            "CSV option '{}' must be a row count, got '{}'",
+                            key, value
+                        ))
+                    })?;
+                }
+                "encoding" => parsed.encoding = Some(value.to_string()),
+                _ => {}
+            }
+        }
+        Ok(parsed)
+    }
+}
+
+/// Option value without its surrounding quotes, with `''` read as `'`
+///
+/// The NQL parser already strips the outer quotes of `OPTIONS (...)` values,
+/// so a lone `"` or `''` may arrive here.
+fn unquote_option(value: &str) -> String {
+    let mut value = value.trim();
+    for quote in ['\'', '"'] {
+        if value.len() > 2 && value.starts_with(quote) && value.ends_with(quote) {
+            value = &value[1..value.len() - 1];
+            break;
+        }
+    }
+    value.replace("''", "'")
+}
+
+fn single_char(key: &str, value: &str) -> Result<char> {
+    let mut chars = value.chars();
+    match (chars.next(), chars.next()) {
+        (Some(c), None) => Ok(c),
+        _ => Err(NoctraError::Validation(format!(
+            "CSV option '{}' must be one character, got '{}'",
+            key, value
+        ))),
+    }
+}
+
+/// Schema of one registered source, as stored in a catalog file
+///
+/// See `SourceRegistry::export_schema` and `SourceRegistry::import_schema`.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SchemaEntry {
+    /// Alias the source was registered under (the key in the file)
+    #[serde(skip)]
+    pub alias: String,
+    /// Source type name (e.g., "sqlite", "csv")
+    #[serde(rename = "type")]
+    pub source_type: String,
+    /// Tables in the source
+    pub tables: Vec<SchemaTable>,
+}
+
+/// Table entry of a catalog file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SchemaTable {
+    /// Table name
+    pub name: String,
+    /// Number of rows (if known when exported)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub row_count: Option<usize>,
+    /// Columns in the table
+    pub columns: Vec<SchemaColumn>,
+}
+
+/// Column entry of a catalog file
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SchemaColumn {
+    /// Column name
+    pub name: String,
+    /// Column type (e.g., "TEXT", "INTEGER")
+    #[serde(rename = "type")]
+    pub data_type: String,
+    /// Whether the column can be null
+    pub nullable: bool,
+}
+
+/// Top-level layout of a catalog file (`[sources.<alias>]`)
+#[derive(Debug, Default, Serialize, Deserialize)]
+struct SchemaFile {
+    #[serde(default)]
+    sources: BTreeMap<String, SchemaEntry>,
+}
+
+impl From<&TableInfo> for SchemaTable {
+    fn from(table: &TableInfo) -> Self {
+        Self {
+            name: table.name.clone(),
+            row_count: table.row_count,
+            columns: table
+                .columns
+                .iter()
+                .map(|column| SchemaColumn {
+                    name: column.name.clone(),
+                    data_type: column.data_type.clone(),
+                    nullable: column.nullable,
+                })
+                .collect(),
         }
     }
 }
 
+/// Creates data sources for `USE '<uri>' AS alias`
+///
+/// A factory claims URI schemes (`myapi` for `myapi://host/resource`) and,
+/// optionally, plain paths without a scheme. Factories are added to a
+/// `SourceRegistry` with `register_factory`, or process-wide with
+/// `register_source_factory`, so other crates can provide new kinds of
+/// sources without changing noctra.
+pub trait SourceFactory: Send + Sync + Debug {
+    /// URI schemes handled by this factory, lowercase and without `://`
+    fn schemes(&self) -> &[&str];
+
+    /// Whether this factory opens `path`, a URI without a scheme
+    fn handles_path(&self, _path: &str) -> bool {
+        false
+    }
+
+    /// Create the source for `uri`, whose tables are exposed under `alias`
+    fn create(
+        &self,
+        uri: &str,
+        alias: &str,
+        options: &HashMap<String, String>,
+    ) -> Result<CreatedSource>;
+}
+
+/// Source created by a `SourceFactory`
+#[derive(Debug)]
+pub struct CreatedSource {
+    /// The new source, ready to register
+    pub source: Box<dyn DataSource>,
+
+    /// Non-fatal problems found while loading it (e.g. generated column names)
+    pub warnings: Vec<String>,
+}
+
+impl CreatedSource {
+    /// Source without warnings
+    pub fn new(source: Box<dyn DataSource>) -> Self {
+        Self {
+            source,
+            warnings: Vec::new(),
+        }
+    }
+}
+
+/// Factories available to every registry
+static GLOBAL_FACTORIES: RwLock<Vec<Arc<dyn SourceFactory>>> = RwLock::new(Vec::new());
+
+/// Make `factory` available to every `SourceRegistry` in the process
+///
+/// Registry-level factories (`SourceRegistry::register_factory`) take
+/// precedence over these.
+pub fn register_source_factory(factory: Arc<dyn SourceFactory>) {
+    if let Ok(mut factories) = GLOBAL_FACTORIES.write() {
+        factories.push(factory);
+    }
+}
+
+/// Scheme of a URI (`myapi` for `myapi://host`), lowercased
+///
+/// Plain paths, including Windows drive letters like `C:\data.csv`, have
+/// no scheme.
+pub fn uri_scheme(uri: &str) -> Option<String> {
+    let (scheme, _) = uri.split_once("://")?;
+    let valid = scheme.len() > 1
+        && scheme.starts_with(|c: char| c.is_ascii_alphabetic())
+        && scheme
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || matches!(c, '+' | '-' | '.'));
+    valid.then(|| scheme.to_ascii_lowercase())
+}
+
 /// Registry of named data sources
 #[derive(Debug, Default)]
 pub struct SourceRegistry {
     sources: std::collections::HashMap<String, Box<dyn DataSource>>,
     active_source: Option<String>,
+    factories: Vec<Arc<dyn SourceFactory>>,
 }
 
 impl SourceRegistry {
@@ -202,18 +670,44 @@ impl SourceRegistry {
             .and_then(|alias| self.get(alias))
     }
 
+    /// Get a mutable reference to the active data source
+    pub fn active_mut(&mut self) -> Option<&mut (dyn DataSource + '_)> {
+        let alias = self.active_source.clone()?;
+        self.get_mut(&alias)
+    }
+
+    /// Alias of the active data source
+    pub fn active_alias(&self) -> Option<&str> {
+        self.active_source.as_deref()
+    }
+
     /// Set the active data source
+    ///
+    /// Unknown aliases fail with the list of registered aliases.
     pub fn set_active(&mut self, alias: &str) -> Result<()> {
         if !self.sources.contains_key(alias) {
-            return Err(NoctraError::Internal(format!(
-                "Data source '{}' not found",
-                alias
+            let aliases = self.aliases();
+            return Err(NoctraError::Validation(format!(
+                "Data source '{}' not found (available: {})",
+                alias,
+                if aliases.is_empty() {
+                    "none".to_string()
+                } else {
+                    aliases.join(", ")
+                }
             )));
         }
         self.active_source = Some(alias.to_string());
         Ok(())
     }
 
+    /// Registered aliases, sorted
+    pub fn aliases(&self) -> Vec<String> {
+        let mut aliases: Vec<String> = self.sources.keys().cloned().collect();
+        aliases.sort();
+        aliases
+    }
+
     /// List all registered sources
     pub fn list_sources(&self) -> Vec<(String, SourceType)> {
         self.sources
@@ -222,11 +716,124 @@ impl SourceRegistry {
             .collect()
     }
 
+    /// Release a session's registrations in every source
+    ///
+    /// All sources are released even if one fails; the first error is returned.
+    pub fn release_session(&self, session_id: &str) -> Result<()> {
+        let mut first_error = None;
+        for source in self.sources.values() {
+            if let Err(e) = source.release_session(session_id) {
+                first_error.get_or_insert(e);
+            }
+        }
+        first_error.map_or(Ok(()), Err)
+    }
+
+    /// Write the schema of every registered source to a TOML catalog file
+    ///
+    /// Sources are written in alias order. Fails if any source cannot
+    /// report its schema.
+    pub fn export_schema(&self, path: &Path) -> Result<()> {
+        let mut file = SchemaFile::default();
+        for alias in self.aliases() {
+            let source = &self.sources[&alias];
+            let tables = source.schema()?.iter().map(SchemaTable::from).collect();
+            file.sources.insert(
+                alias.clone(),
+                SchemaEntry {
+                    alias,
+                    source_type: source.source_type().type_name().to_string(),
+                    tables,
+                },
+            );
+        }
+
+        let content =
+            toml::to_string(&file).map_err(|e| NoctraError::Serialization(e.to_string()))?;
+        std::fs::write(path, content)?;
+        Ok(())
+    }
+
+    /// Read a catalog file written by `export_schema`
+    ///
+    /// Entries are returned in alias order.
+    pub fn import_schema(path: &Path) -> Result<Vec<SchemaEntry>> {
+        let content = std::fs::read_to_string(path)?;
+        let file: SchemaFile = toml::from_str(&content)
+            .map_err(|e| NoctraError::Serialization(format!("{}: {}", path.display(), e)))?;
+        Ok(file
+            .sources
+            .into_iter()
+            .map(|(alias, entry)| SchemaEntry { alias, ..entry })
+            .collect())
+    }
+
+    /// Add a factory for `create_source`
+    ///
+    /// Factories registered later take precedence when two claim the same
+    /// scheme or path.
+    pub fn register_factory(&mut self, factory: Arc<dyn SourceFactory>) {
+        self.factories.insert(0, factory);
+    }
+
+    /// Factory that opens `uri`: by scheme, or by `handles_path` when the
+    /// URI has none
+    ///
+    /// Registry-level factories are tried before global ones.
+    pub fn factory_for(&self, uri: &str) -> Result<Arc<dyn SourceFactory>> {
+        let global: Vec<Arc<dyn SourceFactory>> = GLOBAL_FACTORIES
+            .read()
+            .map(|factories| factories.iter().rev().cloned().collect())
+            .unwrap_or_default();
+        let mut candidates = self.factories.iter().chain(&global);
+
+        match uri_scheme(uri) {
+            Some(scheme) => candidates
+                .find(|factory| factory.schemes().contains(&scheme.as_str()))
+                .cloned()
+                .ok_or_else(|| {
+                    let mut schemes: Vec<&str> = self
+                        .factories
+                        .iter()
+                        .chain(&global)
+                        .flat_map(|factory| factory.schemes().iter().copied())
+                        .collect();
+                    schemes.sort_unstable();
+                    schemes.dedup();
+                    NoctraError::Validation(format!(
+                        "No data source factory for scheme '{}' (registered: {})",
+                        scheme,
+                        if schemes.is_empty() {
+                            "none".to_string()
+                        } else {
+                            schemes.join(", ")
+                        }
+                    ))
+                }),
+            None => candidates
+                .find(|factory| factory.handles_path(uri))
+                .cloned()
+                .ok_or_else(|| {
+                    NoctraError::Validation(format!("Unsupported data source: {}", uri))
+                }),
+        }
+    }
+
+    /// Create (without registering) the source for `USE '<uri>' AS alias`
+    pub fn create_source(
+        &self,
+        uri: &str,
+        alias: &str,
+        options: &HashMap<String, String>,
+    ) -> Result<CreatedSource> {
+        self.factory_for(uri)?.create(uri, alias, options)
+    }
+
     /// Remove a data source
     pub fn remove(&mut self, alias: &str) -> Result<()> {
         self.sources
             .remove(alias)
-            .ok_or_else(|| NoctraError::Internal(format!("Data source '{}' not found", alias)))?;
+            .ok_or_else(|| NoctraError::not_found("fuente", alias))?;
 
         // If we removed the active source, clear it
         if self.active_source.as_deref() == Some(alias) {
@@ -258,9 +865,18 @@ mod tests {
             delimiter: ',',
             has_header: true,
             encoding: "utf-8".to_string(),
+            compression: None,
         };
         assert_eq!(csv.type_name(), "csv");
         assert_eq!(csv.display_path(), "data.csv");
+        assert_eq!(csv.compression(), None);
+
+        let json = SourceType::JSON {
+            path: "events.ndjson.gz".to_string(),
+            compression: Some("gzip".to_string()),
+        };
+        assert_eq!(json.type_name(), "json");
+        assert_eq!(json.compression(), Some("gzip"));
     }
 
     #[test]
@@ -271,4 +887,229 @@ mod tests {
         assert_eq!(opts.quote, '"');
         assert_eq!(opts.skip_rows, 0);
     }
+
+    #[test]
+    fn test_csv_options_from_use_options() {
+        let options: HashMap<String, String> = [
+            ("quote", "''''"),
+            ("delimiter", "';'"),
+            ("has_header", "false"),
+            ("skip", "2"),
+            ("encoding", "'latin-1'"),
+            ("hive_partitioning", "true"),
+        ]
+        .into_iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+        let opts = CsvOptions::from_use_options(&options).unwrap();
+        assert_eq!(opts.quote, '\'');
+        assert_eq!(opts.delimiter, Some(';'));
+        assert!(!opts.has_header);
+        assert_eq!(opts.skip_rows, 2);
+        assert_eq!(opts.encoding.as_deref(), Some("latin-1"));
+
+        // As the NQL parser leaves them, with the outer quotes stripped
+        for (value, quote) in [("\"", '"'), ("''", '\''), ("'\"'", '"')] {
+            let options = HashMap::from([("quote".to_string(), value.to_string())]);
+            assert_eq!(CsvOptions::from_use_options(&options).unwrap().quote, quote);
+        }
+
+        let options = HashMap::from([("quote".to_string(), "'ab'".to_string())]);
+        assert!(CsvOptions::from_use_options(&options).is_err());
+    }
+
+    /// Source that only reports a fixed schema
+    #[derive(Debug)]
+    struct FixedSchema {
+        source_type: SourceType,
+        tables: Vec<TableInfo>,
+    }
+
+    impl DataSource for FixedSchema {
+        fn query(&self, _sql: &str, _parameters: &Parameters) -> Result<ResultSet> {
+            Ok(ResultSet::new(Vec::new()))
+        }
+
+        fn schema(&self) -> Result<Vec<TableInfo>> {
+            Ok(self.tables.clone())
+        }
+
+        fn source_type(&self) -> SourceType {
+            self.source_type.clone()
+        }
+
+        fn name(&self) -> &str {
+            "fixed"
+        }
+    }
+
+    fn table(name: &str, row_count: Option<usize>, columns: &[(&str, &str, bool)]) -> TableInfo {
+        TableInfo {
+            name: name.to_string(),
+            columns: columns
+                .iter()
+                .map(|&(name, data_type, nullable)| ColumnInfo {
+                    name: name.to_string(),
+                    data_type: data_type.to_string(),
+                    nullable,
+                    default_value: None,
+                    primary_key: false,
+                })
+                .collect(),
+            row_count,
+            row_count_updated_at: None,
+            is_view: false,
+        }
+    }
+
+    #[test]
+    fn test_export_and_import_schema() {
+        let mut registry = SourceRegistry::new();
+        registry
+            .register(
+                "ventas".to_string(),
+                Box::new(FixedSchema {
+                    source_type: SourceType::CSV {
+                        path: "ventas.csv".to_string(),
+                        delimiter: ',',
+                        has_header: true,
+                        encoding: "utf-8".to_string(),
+                        compression: None,
+                    },
+                    tables: vec![table(
+                        "ventas",
+                        Some(120),
+                        &[("id", "INTEGER", false), ("total", "REAL", true)],
+                    )],
+                }),
+            )
+            .unwrap();
+        registry
+            .register(
+                "erp".to_string(),
+                Box::new(FixedSchema {
+                    source_type: SourceType::SQLite {
+                        path: "erp.db".to_string(),
+                    },
+                    tables: vec![
+                        table("clientes", None, &[("id", "INTEGER", false)]),
+                        table(
+                            "pedidos",
+                            Some(3),
+                            &[("id", "INTEGER", false), ("cliente_id", "INTEGER", true)],
+                        ),
+                    ],
+                }),
+            )
+            .unwrap();
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("catalog.toml");
+        registry.export_schema(&path).unwrap();
+        assert!(std::fs::read_to_string(&path)
+            .unwrap()
+            .contains("[sources.ventas]"));
+
+        let entries = SourceRegistry::import_schema(&path).unwrap();
+        assert_eq!(entries.len(), 2);
+        for entry in &entries {
+            let source = registry.get(&entry.alias).unwrap();
+            assert_eq!(entry.source_type, source.source_type().type_name());
+            let expected: Vec<SchemaTable> = source
+                .schema()
+                .unwrap()
+                .iter()
+                .map(SchemaTable::from)
+                .collect();
+            assert_eq!(entry.tables, expected);
+        }
+        assert_eq!(entries[0].alias, "erp");
+        assert_eq!(entries[0].tables[0].row_count, None);
+        assert_eq!(entries[1].tables[0].columns[1].data_type, "REAL");
+    }
+
+    /// Factory for `mock://` URIs and `.mock` files
+    #[derive(Debug)]
+    struct MockFactory;
+
+    impl SourceFactory for MockFactory {
+        fn schemes(&self) -> &[&str] {
+            &["mock"]
+        }
+
+        fn handles_path(&self, path: &str) -> bool {
+            path.ends_with(".mock")
+        }
+
+        fn create(
+            &self,
+            uri: &str,
+            alias: &str,
+            options: &HashMap<String, String>,
+        ) -> Result<CreatedSource> {
+            let columns: Vec<(&str, &str, bool)> = options
+                .get("columns")
+                .map(|columns| columns.split(',').map(|c| (c, "TEXT", true)).collect())
+                .unwrap_or_default();
+            Ok(CreatedSource {
+                source: Box::new(FixedSchema {
+                    source_type: SourceType::JSON {
+                        path: uri.to_string(),
+                        compression: None,
+                    },
+                    tables: vec![table(alias, None, &columns)],
+                }),
+                warnings: vec![format!("mock source for {}", uri)],
+            })
+        }
+    }
+
+    #[test]
+    fn test_source_factory_resolves_schemes_and_paths() {
+        assert_eq!(uri_scheme("MyApi://host/r"), Some("myapi".to_string()));
+        assert_eq!(uri_scheme("C://data.csv"), None);
+        assert_eq!(uri_scheme("data/ventas.csv"), None);
+
+        let mut registry = SourceRegistry::new();
+        assert!(registry
+            .create_source("mock://host/clientes", "c", &HashMap::new())
+            .is_err());
+
+        registry.register_factory(Arc::new(MockFactory));
+        let options = HashMap::from([("columns".to_string(), "id,nombre".to_string())]);
+        let created = registry
+            .create_source("mock://host/clientes", "clientes", &options)
+            .unwrap();
+        assert_eq!(
+            created.warnings,
+            vec!["mock source for mock://host/clientes"]
+        );
+        let tables = created.source.schema().unwrap();
+        assert_eq!(tables[0].name, "clientes");
+        assert_eq!(tables[0].columns.len(), 2);
+        registry
+            .register("clientes".to_string(), created.source)
+            .unwrap();
+        assert_eq!(
+            registry
+                .get("clientes")
+                .unwrap()
+                .source_type()
+                .display_path(),
+            "mock://host/clientes"
+        );
+
+        assert!(registry
+            .create_source("datos.mock", "d", &HashMap::new())
+            .is_ok());
+        let error = registry
+            .create_source("otro://host", "o", &HashMap::new())
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("'otro'"), "{}", error);
+        assert!(error.contains("mock"), "{}", error);
+        assert!(registry
+            .create_source("datos.txt", "d", &HashMap::new())
+            .is_err());
+    }
 }
@@ -1,14 +1,53 @@
 //! Manejo de errores para Noctra Core
+//!
+//! Cada variante tiene un código estable (`NoctraError::code`) para que el
+//! servidor elija el status HTTP y las interfaces den mensajes accionables
+//! (`NoctraError::hint`). `Internal` queda como último recurso para fallos
+//! que no son culpa del usuario (locks envenenados, hilos caídos, ...).
+//!
+//! Para consumidores de la API cada variante tiene además un código
+//! numerado (`NoctraError::error_code`, p. ej. "NOCTRA_E030") registrado en
+//! `ERROR_CODES`; `docs/ERROR_CODES.md` se genera a partir de ese registro.
 
 use std::fmt;
 use thiserror::Error;
 
+include!("error_codes.rs");
+
 /// Tipos de errores en Noctra
 #[derive(Error, Debug, Clone)]
 pub enum NoctraError {
     #[error("Error de conexión a base de datos: {0}")]
     Database(String),
 
+    /// Error de sintaxis RQL con su posición (1-based)
+    #[error("Error de sintaxis en línea {line}, columna {column}: {message}")]
+    Parse {
+        line: usize,
+        column: usize,
+        message: String,
+        /// Línea del texto con un `^` bajo la columna, si se conoce
+        snippet: Option<String>,
+    },
+
+    /// Objeto inexistente (`kind`: "fuente", "tabla", "archivo", ...)
+    #[error("No encontrado: {kind} '{name}'")]
+    NotFound { kind: String, name: String },
+
+    /// Acceso denegado (rutas fuera de los directorios permitidos, solo lectura, ...)
+    ///
+    /// En modo solo lectura `statement_kind` es la sentencia rechazada
+    /// ("INSERT", "GRANT", "load_extension()", ...); ver `NoctraError::read_only`.
+    #[error("Acceso denegado: {message}")]
+    Permission {
+        message: String,
+        statement_kind: Option<String>,
+    },
+
+    /// Conflicto con el estado actual (restricciones, bloqueos, duplicados)
+    #[error("Conflicto: {0}")]
+    Conflict(String),
+
     #[error("Error de sintaxis SQL: {0}")]
     SqlSyntax(String),
 
@@ -18,6 +57,15 @@ pub enum NoctraError {
     #[error("Parámetro no encontrado: {0}")]
     ParameterNotFound(String),
 
+    /// Placeholders de la consulta sin valor (todos, separados por comas)
+    #[error("Parámetros sin valor: {0}")]
+    MissingParameter(String),
+
+    /// Parámetros mal formados (p. ej. un objeto JSON que mezcla claves
+    /// numéricas y nombres)
+    #[error("Parámetros inválidos: {0}")]
+    InvalidParameters(String),
+
     #[error("Variable de sesión no encontrada: {0}")]
     SessionVariableNotFound(String),
 
@@ -33,13 +81,69 @@ pub enum NoctraError {
     #[error("Error de serialización: {0}")]
     Serialization(String),
 
+    #[error("Error de backend [{code}]: {message}")]
+    Backend {
+        code: String,
+        message: String,
+        /// Sugerencia del backend (p. ej. "Did you mean ...?")
+        hint: Option<String>,
+    },
+
+    #[error(
+        "Aserción fallida{}: esperado {expected}, obtenido {actual}",
+        label.as_ref().map(|l| format!(" '{}'", l)).unwrap_or_default()
+    )]
+    AssertionFailed {
+        label: Option<String>,
+        expected: String,
+        actual: String,
+    },
+
+    #[error("Validación de consulta fallida: {0}")]
+    DryRunFailed(String),
+
+    /// Sintaxis que el backend no entiende (p. ej. `PIVOT` en SQLite)
+    #[error("{feature} no está soportado por {backend}")]
+    UnsupportedFeature { feature: String, backend: String },
+
+    /// Consulta cancelada al superar `ExecutorOptions::timeout`
+    #[error("Consulta cancelada por timeout tras {elapsed:.1?}")]
+    Timeout { elapsed: std::time::Duration },
+
     #[error("Error interno: {0}")]
     Internal(String),
 }
 
 impl From<rusqlite::Error> for NoctraError {
     fn from(error: rusqlite::Error) -> Self {
-        NoctraError::Database(format!("SQLite error: {}", error))
+        use rusqlite::ErrorCode;
+
+        // Los errores ligados a un token del SQL llegan como `SqlInputError`
+        let (failure, message) = match &error {
+            rusqlite::Error::SqliteFailure(failure, message) => (
+                failure,
+                message.clone().unwrap_or_else(|| failure.to_string()),
+            ),
+            rusqlite::Error::SqlInputError { error, msg, .. } => (error, msg.clone()),
+            _ => return NoctraError::Database(format!("SQLite error: {}", error)),
+        };
+        match failure.code {
+            ErrorCode::ConstraintViolation
+            | ErrorCode::DatabaseBusy
+            | ErrorCode::DatabaseLocked => NoctraError::Conflict(message),
+            ErrorCode::PermissionDenied
+            | ErrorCode::ReadOnly
+            | ErrorCode::AuthorizationForStatementDenied => NoctraError::permission(message),
+            _ => {
+                if let Some(table) = message.strip_prefix("no such table: ") {
+                    NoctraError::not_found("tabla", table)
+                } else if message.contains("syntax error") {
+                    NoctraError::SqlSyntax(message)
+                } else {
+                    NoctraError::backend(format!("SQLITE_{}", failure.extended_code), message)
+                }
+            }
+        }
     }
 }
 
@@ -74,6 +178,195 @@ impl NoctraError {
     pub fn session_variable_not_found<T: fmt::Display>(var: T) -> Self {
         Self::SessionVariableNotFound(var.to_string())
     }
+
+    /// Crear error de sintaxis con posición
+    pub fn parse<T: fmt::Display>(line: usize, column: usize, msg: T) -> Self {
+        Self::Parse {
+            line,
+            column,
+            message: msg.to_string(),
+            snippet: None,
+        }
+    }
+
+    /// Crear error de objeto no encontrado
+    pub fn not_found<K: fmt::Display, T: fmt::Display>(kind: K, name: T) -> Self {
+        Self::NotFound {
+            kind: kind.to_string(),
+            name: name.to_string(),
+        }
+    }
+
+    /// Crear error de acceso denegado
+    pub fn permission<T: fmt::Display>(msg: T) -> Self {
+        Self::Permission {
+            message: msg.to_string(),
+            statement_kind: None,
+        }
+    }
+
+    /// Crear error de sentencia rechazada en modo solo lectura
+    pub fn read_only<K: fmt::Display>(statement_kind: K) -> Self {
+        Self::Permission {
+            message: format!("modo solo lectura, {} no está permitido", statement_kind),
+            statement_kind: Some(statement_kind.to_string()),
+        }
+    }
+
+    /// Crear error de backend con código (SQLSTATE o similar)
+    pub fn backend<C: fmt::Display, T: fmt::Display>(code: C, msg: T) -> Self {
+        Self::Backend {
+            code: code.to_string(),
+            message: msg.to_string(),
+            hint: None,
+        }
+    }
+
+    /// Crear error de backend con código y sugerencia
+    pub fn backend_with_hint<C: fmt::Display, T: fmt::Display>(
+        code: C,
+        msg: T,
+        hint: Option<String>,
+    ) -> Self {
+        Self::Backend {
+            code: code.to_string(),
+            message: msg.to_string(),
+            hint,
+        }
+    }
+
+    /// Obtener código del backend, si el error proviene de uno
+    pub fn backend_code(&self) -> Option<&str> {
+        match self {
+            Self::Backend { code, .. } => Some(code),
+            _ => None,
+        }
+    }
+
+    /// Sentencia rechazada en modo solo lectura, si el error es de ese tipo
+    pub fn read_only_statement(&self) -> Option<&str> {
+        match self {
+            Self::Permission { statement_kind, .. } => statement_kind.as_deref(),
+            _ => None,
+        }
+    }
+
+    /// Código estable del error (p. ej. "not_found", "timeout")
+    ///
+    /// Los errores de backend conservan además su propio código en
+    /// `backend_code`.
+    pub fn code(&self) -> &'static str {
+        match self {
+            Self::Database(_) => "database",
+            Self::Parse { .. } => "parse",
+            Self::NotFound { .. } => "not_found",
+            Self::Permission { .. } => "permission",
+            Self::Conflict(_) => "conflict",
+            Self::SqlSyntax(_) => "sql_syntax",
+            Self::SqlExecution(_) => "sql_execution",
+            Self::ParameterNotFound(_) => "parameter_not_found",
+            Self::MissingParameter(_) => "missing_parameter",
+            Self::InvalidParameters(_) => "invalid_parameters",
+            Self::SessionVariableNotFound(_) => "variable_not_found",
+            Self::Validation(_) => "validation",
+            Self::Configuration(_) => "configuration",
+            Self::Io(_) => "io",
+            Self::Serialization(_) => "serialization",
+            Self::Backend { .. } => "backend",
+            Self::AssertionFailed { .. } => "assertion_failed",
+            Self::DryRunFailed(_) => "dry_run_failed",
+            Self::UnsupportedFeature { .. } => "unsupported_feature",
+            Self::Timeout { .. } => "timeout",
+            Self::Internal(_) => "internal",
+        }
+    }
+
+    /// Código numerado del error para consumidores de la API
+    ///
+    /// Uno por variante y estable entre versiones; ver `ERROR_CODES`.
+    pub fn error_code(&self) -> &'static str {
+        match self {
+            Self::Io(_) => "NOCTRA_E001",
+            Self::Serialization(_) => "NOCTRA_E002",
+            Self::Configuration(_) => "NOCTRA_E003",
+            Self::Parse { .. } => "NOCTRA_E010",
+            Self::SqlSyntax(_) => "NOCTRA_E011",
+            Self::Validation(_) => "NOCTRA_E012",
+            Self::DryRunFailed(_) => "NOCTRA_E013",
+            Self::InvalidParameters(_) => "NOCTRA_E014",
+            Self::SqlExecution(_) => "NOCTRA_E020",
+            Self::Database(_) => "NOCTRA_E021",
+            Self::Backend { .. } => "NOCTRA_E022",
+            Self::Timeout { .. } => "NOCTRA_E023",
+            Self::Conflict(_) => "NOCTRA_E024",
+            Self::AssertionFailed { .. } => "NOCTRA_E025",
+            Self::UnsupportedFeature { .. } => "NOCTRA_E026",
+            Self::NotFound { .. } => "NOCTRA_E030",
+            Self::ParameterNotFound(_) => "NOCTRA_E031",
+            Self::MissingParameter(_) => "NOCTRA_E032",
+            Self::SessionVariableNotFound(_) => "NOCTRA_E033",
+            Self::Permission { .. } => "NOCTRA_E040",
+            Self::Internal(_) => "NOCTRA_E099",
+        }
+    }
+
+    /// Sugerencia para resolver el error, si la hay
+    ///
+    /// En los errores de backend es la sugerencia del propio backend.
+    pub fn hint(&self) -> Option<&str> {
+        match self {
+            Self::Backend { hint, .. } => hint.as_deref(),
+            Self::NotFound { kind, .. } => match kind.as_str() {
+                "fuente" => Some("SHOW SOURCES lista las fuentes registradas"),
+                "tabla" => Some("SHOW TABLES lista las tablas disponibles"),
+                "cursor" => Some(
+                    "los cursores se cierran al agotarse o tras un tiempo sin uso; vuelve a abrirlo",
+                ),
+                _ => None,
+            },
+            Self::Timeout { .. } => {
+                Some("acota la consulta (WHERE, LIMIT) o sube el timeout configurado")
+            }
+            Self::Permission {
+                statement_kind: Some(_),
+                ..
+            } => Some("sólo se admiten consultas: SELECT, EXPLAIN, SHOW y PRAGMA de lectura"),
+            Self::Permission { .. } => {
+                Some("revisa los directorios permitidos en la configuración")
+            }
+            Self::MissingParameter(_) => Some("asigna los parámetros con LET antes de ejecutar"),
+            Self::UnsupportedFeature { .. } => {
+                Some("ejecútalo sobre una fuente DuckDB (USE 'archivo' AS alias)")
+            }
+            _ => None,
+        }
+    }
+
+    /// Fragmento del texto que señala un error de sintaxis, si se conoce
+    pub fn snippet(&self) -> Option<&str> {
+        match self {
+            Self::Parse { snippet, .. } => snippet.as_deref(),
+            _ => None,
+        }
+    }
+
+    /// Si el error es un fallo interno (no atribuible a la consulta ni al usuario)
+    pub fn is_internal(&self) -> bool {
+        matches!(self, Self::Internal(_))
+    }
+
+    /// Mensaje apto para mostrar al usuario
+    ///
+    /// Los errores internos no exponen su detalle (que puede incluir rutas o
+    /// estado del proceso); el resto se muestra tal cual.
+    pub fn user_message(&self) -> String {
+        match self {
+            Self::Internal(_) => {
+                "Error interno de Noctra; revisa el log para más detalles".to_string()
+            }
+            _ => self.to_string(),
+        }
+    }
 }
 
 /// Result type para operaciones de Noctra
@@ -83,3 +376,132 @@ pub type Result<T> = std::result::Result<T, NoctraError>;
 pub trait IntoNoctraError {
     fn into_noctra_error(self) -> NoctraError;
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sqlite_error(sql: &str) -> NoctraError {
+        let conn = rusqlite::Connection::open_in_memory().unwrap();
+        conn.execute_batch("CREATE TABLE t (id INTEGER PRIMARY KEY); INSERT INTO t VALUES (1);")
+            .unwrap();
+        conn.execute_batch(sql).unwrap_err().into()
+    }
+
+    #[test]
+    fn test_sqlite_errors_map_to_variants() {
+        let error = sqlite_error("SELECT * FROM ventas");
+        assert!(
+            matches!(&error, NoctraError::NotFound { kind, name } if kind == "tabla" && name == "ventas")
+        );
+        assert_eq!(error.code(), "not_found");
+        assert!(error.hint().is_some());
+
+        let error = sqlite_error("INSERT INTO t VALUES (1)");
+        assert_eq!(error.code(), "conflict");
+
+        let error = sqlite_error("SELEC 1");
+        assert_eq!(error.code(), "sql_syntax");
+
+        let error = sqlite_error("SELECT nope FROM t");
+        assert_eq!(error.code(), "backend");
+        assert_eq!(error.backend_code(), Some("SQLITE_1"));
+    }
+
+    #[test]
+    fn test_internal_error_hides_details() {
+        let error = NoctraError::Internal("lock envenenado".to_string());
+        assert!(error.is_internal());
+        assert!(!error.user_message().contains("lock envenenado"));
+
+        let error = NoctraError::permission("/etc/passwd");
+        assert_eq!(error.user_message(), "Acceso denegado: /etc/passwd");
+        assert_eq!(error.code(), "permission");
+        assert_eq!(error.read_only_statement(), None);
+
+        let error = NoctraError::read_only("INSERT");
+        assert_eq!(
+            error.user_message(),
+            "Acceso denegado: modo solo lectura, INSERT no está permitido"
+        );
+        assert_eq!(error.error_code(), "NOCTRA_E040");
+        assert_eq!(error.read_only_statement(), Some("INSERT"));
+    }
+
+    #[test]
+    fn test_error_codes_unique_and_registered() {
+        let text = || "x".to_string();
+        let errors = vec![
+            (NoctraError::Database(text()), "Database"),
+            (
+                NoctraError::Parse {
+                    line: 1,
+                    column: 1,
+                    message: text(),
+                    snippet: None,
+                },
+                "Parse",
+            ),
+            (NoctraError::not_found("tabla", "t"), "NotFound"),
+            (NoctraError::permission(text()), "Permission"),
+            (NoctraError::Conflict(text()), "Conflict"),
+            (NoctraError::SqlSyntax(text()), "SqlSyntax"),
+            (NoctraError::SqlExecution(text()), "SqlExecution"),
+            (NoctraError::ParameterNotFound(text()), "ParameterNotFound"),
+            (NoctraError::MissingParameter(text()), "MissingParameter"),
+            (NoctraError::InvalidParameters(text()), "InvalidParameters"),
+            (
+                NoctraError::SessionVariableNotFound(text()),
+                "SessionVariableNotFound",
+            ),
+            (NoctraError::Validation(text()), "Validation"),
+            (NoctraError::Configuration(text()), "Configuration"),
+            (NoctraError::Io(text()), "Io"),
+            (NoctraError::Serialization(text()), "Serialization"),
+            (NoctraError::backend("SQLITE_1", text()), "Backend"),
+            (
+                NoctraError::AssertionFailed {
+                    label: None,
+                    expected: text(),
+                    actual: text(),
+                },
+                "AssertionFailed",
+            ),
+            (NoctraError::DryRunFailed(text()), "DryRunFailed"),
+            (
+                NoctraError::UnsupportedFeature {
+                    feature: text(),
+                    backend: text(),
+                },
+                "UnsupportedFeature",
+            ),
+            (
+                NoctraError::Timeout {
+                    elapsed: std::time::Duration::from_secs(1),
+                },
+                "Timeout",
+            ),
+            (NoctraError::Internal(text()), "Internal"),
+        ];
+
+        let mut seen = std::collections::HashSet::new();
+        for (error, variant) in &errors {
+            let code = error.error_code();
+            assert!(!code.is_empty());
+            assert!(seen.insert(code), "código repetido: {}", code);
+            assert!(
+                ERROR_CODES
+                    .iter()
+                    .any(|(registered, origin, _)| *registered == code && origin == variant),
+                "{} no registrado para {}",
+                code,
+                variant
+            );
+        }
+
+        let mut registered = std::collections::HashSet::new();
+        assert!(ERROR_CODES
+            .iter()
+            .all(|(code, _, _)| registered.insert(code)));
+    }
+}
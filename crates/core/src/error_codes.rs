@@ -0,0 +1,42 @@
+// Registro de códigos de error de Noctra
+//
+// Lo incluyen (`include!`) tanto `error.rs` como `build.rs`, que genera
+// `docs/ERROR_CODES.md` a partir de esta tabla; por eso no lleva atributos
+// internos ni comentarios de módulo (`//!`). Los códigos son estables: no
+// se reutilizan ni se renumeran, sólo se añaden.
+//
+// Rangos: E00x entrada/salida y configuración, E01x parseo y validación,
+// E02x ejecución, E03x objetos inexistentes, E04x permisos, E05x errores del
+// servidor HTTP, E099 fallos internos.
+
+/// Códigos de error: (código, origen, descripción)
+#[rustfmt::skip]
+pub const ERROR_CODES: &[(&str, &str, &str)] = &[
+    ("NOCTRA_E001", "Io", "Error de entrada/salida"),
+    ("NOCTRA_E002", "Serialization", "Error de serialización o deserialización"),
+    ("NOCTRA_E003", "Configuration", "Configuración inválida"),
+    ("NOCTRA_E010", "Parse", "Error de sintaxis RQL"),
+    ("NOCTRA_E011", "SqlSyntax", "Error de sintaxis SQL"),
+    ("NOCTRA_E012", "Validation", "Validación fallida"),
+    ("NOCTRA_E013", "DryRunFailed", "Falló la validación en modo dry-run"),
+    ("NOCTRA_E014", "InvalidParameters", "Parámetros mal formados (JSON que mezcla posicionales y nombrados)"),
+    ("NOCTRA_E020", "SqlExecution", "Error al ejecutar la sentencia"),
+    ("NOCTRA_E021", "Database", "Error de conexión a base de datos"),
+    ("NOCTRA_E022", "Backend", "Error propio del backend (con su código)"),
+    ("NOCTRA_E023", "Timeout", "La consulta superó el tiempo máximo"),
+    ("NOCTRA_E024", "Conflict", "Conflicto con el estado actual (restricciones, bloqueos)"),
+    ("NOCTRA_E025", "AssertionFailed", "Falló un ASSERT"),
+    ("NOCTRA_E026", "UnsupportedFeature", "Sintaxis no soportada por el backend (p. ej. PIVOT en SQLite)"),
+    ("NOCTRA_E030", "NotFound", "Fuente, tabla o archivo inexistente"),
+    ("NOCTRA_E031", "ParameterNotFound", "Parámetro no encontrado"),
+    ("NOCTRA_E032", "MissingParameter", "Parámetro requerido sin valor"),
+    ("NOCTRA_E033", "SessionVariableNotFound", "Variable de sesión no encontrada"),
+    ("NOCTRA_E040", "Permission", "Acceso denegado"),
+    ("NOCTRA_E050", "ServerError::bad_request", "Petición HTTP inválida"),
+    ("NOCTRA_E051", "ServerError::unauthorized", "Falta la API key o no es válida"),
+    ("NOCTRA_E052", "ServerError::not_found", "Recurso HTTP inexistente"),
+    ("NOCTRA_E053", "ServerError::service_unavailable", "Servidor no disponible"),
+    ("NOCTRA_E054", "ServerError::internal_error", "Error interno del servidor"),
+    ("NOCTRA_E055", "ServerError::payload_too_large", "Cuerpo de la petición demasiado grande"),
+    ("NOCTRA_E099", "Internal", "Error interno de Noctra"),
+];
@@ -0,0 +1,232 @@
+//! Exportación de resultados a formatos binarios
+//!
+//! Actualmente soporta XLSX (Excel) mediante `rust_xlsxwriter`, habilitado
+//! con el feature `xlsx`.
+
+use crate::error::{NoctraError, Result};
+use crate::types::{ResultSet, Value};
+use rust_xlsxwriter::{Format, Workbook, Worksheet, XlsxError};
+use std::collections::HashMap;
+use std::path::Path;
+
+/// Ancho máximo de columna (en caracteres) al auto-ajustar
+const MAX_COLUMN_WIDTH: usize = 50;
+
+/// Opciones de exportación XLSX
+#[derive(Debug, Clone, PartialEq)]
+pub struct XlsxOptions {
+    /// Nombre de la hoja
+    pub sheet_name: String,
+
+    /// Filas congeladas en la parte superior (0 = sin congelar)
+    pub freeze_rows: u32,
+}
+
+impl Default for XlsxOptions {
+    fn default() -> Self {
+        Self {
+            sheet_name: "Results".to_string(),
+            freeze_rows: 1,
+        }
+    }
+}
+
+impl XlsxOptions {
+    /// Construir opciones desde `OPTIONS (sheet_name=..., freeze_rows=...)`
+    pub fn from_options(options: &HashMap<String, String>) -> Result<Self> {
+        let mut xlsx_options = Self::default();
+
+        if let Some(name) = options.get("sheet_name") {
+            let name = name.trim_matches('\'').trim_matches('"');
+            if name.is_empty() {
+                return Err(NoctraError::Validation(
+                    "sheet_name no puede estar vacío".to_string(),
+                ));
+            }
+            xlsx_options.sheet_name = name.to_string();
+        }
+
+        if let Some(rows) = options.get("freeze_rows") {
+            xlsx_options.freeze_rows = rows.trim().parse().map_err(|_| {
+                NoctraError::Validation(format!("freeze_rows inválido: '{}'", rows))
+            })?;
+        }
+
+        Ok(xlsx_options)
+    }
+}
+
+/// Exportar result set a un archivo XLSX
+pub fn write_xlsx<P: AsRef<Path>>(
+    result: &ResultSet,
+    path: P,
+    options: &XlsxOptions,
+) -> Result<()> {
+    let mut workbook = build_workbook(result, options)?;
+    workbook.save(path.as_ref()).map_err(xlsx_error)
+}
+
+/// Exportar result set a un buffer XLSX en memoria
+pub fn xlsx_to_buffer(result: &ResultSet, options: &XlsxOptions) -> Result<Vec<u8>> {
+    let mut workbook = build_workbook(result, options)?;
+    workbook.save_to_buffer().map_err(xlsx_error)
+}
+
+/// Construir workbook con headers, filas y formato
+fn build_workbook(result: &ResultSet, options: &XlsxOptions) -> Result<Workbook> {
+    let mut workbook = Workbook::new();
+    let worksheet = workbook.add_worksheet();
+    worksheet
+        .set_name(&options.sheet_name)
+        .map_err(xlsx_error)?;
+
+    write_sheet(worksheet, result, options).map_err(xlsx_error)?;
+
+    Ok(workbook)
+}
+
+/// Escribir contenido del result set en la hoja
+fn write_sheet(
+    worksheet: &mut Worksheet,
+    result: &ResultSet,
+    options: &XlsxOptions,
+) -> std::result::Result<(), XlsxError> {
+    let header_format = Format::new().set_bold();
+    let mut widths: Vec<usize> = result
+        .columns
+        .iter()
+        .map(|col| col.name.chars().count())
+        .collect();
+
+    // Headers en negrita
+    for (col_idx, column) in result.columns.iter().enumerate() {
+        worksheet.write_string_with_format(0, col_idx as u16, &column.name, &header_format)?;
+    }
+
+    // Filas de datos
+    for (row_idx, row) in result.rows.iter().enumerate() {
+        let xlsx_row = row_idx as u32 + 1;
+
+        for (col_idx, value) in row.values.iter().enumerate() {
+            let xlsx_col = col_idx as u16;
+
+            match value {
+                Value::Null => {}
+                Value::Integer(i) => {
+                    worksheet.write_number(xlsx_row, xlsx_col, *i as f64)?;
+                }
+                Value::Float(f) => {
+                    worksheet.write_number(xlsx_row, xlsx_col, *f)?;
+                }
+                #[cfg(feature = "decimal")]
+                Value::Decimal(d) => {
+                    // Excel guarda números como f64; el valor mostrado coincide
+                    use rust_decimal::prelude::ToPrimitive;
+                    match d.to_f64() {
+                        Some(f) => worksheet.write_number(xlsx_row, xlsx_col, f)?,
+                        None => worksheet.write_string(xlsx_row, xlsx_col, d.to_string())?,
+                    };
+                }
+                Value::Boolean(b) => {
+                    worksheet.write_boolean(xlsx_row, xlsx_col, *b)?;
+                }
+                other => {
+                    worksheet.write_string(xlsx_row, xlsx_col, other.to_string())?;
+                }
+            }
+
+            if let Some(width) = widths.get_mut(col_idx) {
+                *width = (*width).max(value_width(value));
+            }
+        }
+    }
+
+    // Auto-ajustar anchos de columna
+    for (col_idx, width) in widths.iter().enumerate() {
+        let width = (*width + 2).min(MAX_COLUMN_WIDTH);
+        worksheet.set_column_width(col_idx as u16, width as f64)?;
+    }
+
+    if options.freeze_rows > 0 {
+        worksheet.set_freeze_panes(options.freeze_rows, 0)?;
+    }
+
+    Ok(())
+}
+
+/// Longitud visible de un valor en la hoja
+fn value_width(value: &Value) -> usize {
+    match value {
+        Value::Null => 0,
+        other => other.to_string().chars().count(),
+    }
+}
+
+fn xlsx_error(error: XlsxError) -> NoctraError {
+    NoctraError::Io(format!("Error escribiendo XLSX: {}", error))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::types::{Column, Row};
+    use calamine::{open_workbook, Data, Reader, Xlsx};
+
+    fn sample_result() -> ResultSet {
+        let mut result = ResultSet::new(vec![
+            Column::new("id", "INTEGER", 0),
+            Column::new("name", "TEXT", 1),
+            Column::new("salary", "REAL", 2),
+            Column::new("active", "BOOLEAN", 3),
+        ]);
+        result.add_row(Row::new(vec![
+            Value::Integer(1),
+            Value::Text("Ana".to_string()),
+            Value::Float(1500.5),
+            Value::Boolean(true),
+        ]));
+        result.add_row(Row::new(vec![
+            Value::Integer(2),
+            Value::Text("Luis".to_string()),
+            Value::Null,
+            Value::Boolean(false),
+        ]));
+        result
+    }
+
+    #[test]
+    fn test_write_xlsx_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("export.xlsx");
+        let options = XlsxOptions {
+            sheet_name: "Empleados".to_string(),
+            freeze_rows: 1,
+        };
+
+        write_xlsx(&sample_result(), &path, &options).unwrap();
+
+        let mut workbook: Xlsx<_> = open_workbook(&path).unwrap();
+        let range = workbook.worksheet_range("Empleados").unwrap();
+
+        // Header + 2 filas
+        assert_eq!(range.height(), 3);
+        assert_eq!(range.get((0, 1)), Some(&Data::String("name".to_string())));
+        assert_eq!(range.get((1, 0)), Some(&Data::Float(1.0)));
+        assert_eq!(range.get((1, 3)), Some(&Data::Bool(true)));
+        assert_eq!(range.get((2, 2)), Some(&Data::Empty));
+    }
+
+    #[test]
+    fn test_xlsx_options_from_options() {
+        let mut options = HashMap::new();
+        options.insert("sheet_name".to_string(), "Results".to_string());
+        options.insert("freeze_rows".to_string(), "0".to_string());
+
+        let parsed = XlsxOptions::from_options(&options).unwrap();
+        assert_eq!(parsed.sheet_name, "Results");
+        assert_eq!(parsed.freeze_rows, 0);
+
+        options.insert("freeze_rows".to_string(), "abc".to_string());
+        assert!(XlsxOptions::from_options(&options).is_err());
+    }
+}
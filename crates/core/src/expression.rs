@@ -0,0 +1,554 @@
+//! Evaluación de expresiones de `LET`, `MAP` y `FILTER`
+//!
+//! Soporta aritmética entera y flotante (`+ - * / %`), concatenación con
+//! `||`, comparaciones (`= <> != < <= > >=`), `IN (...)`, `AND`, `OR`,
+//! `NOT`, paréntesis, las funciones `NOW()`, `UPPER()` y `LOWER()` y
+//! referencias a otras variables de sesión (`#nombre` o `nombre`). En `MAP`
+//! y `FILTER` un nombre suelto es primero una columna de la fila. Como en
+//! SQL, la división entre enteros es entera, cualquier operación con `NULL`
+//! da `NULL` y la lógica es de tres valores.
+
+use crate::error::{NoctraError, Result};
+use crate::session::Session;
+use crate::types::coerce::{self, Collation};
+use crate::types::{Column, Row, Value};
+use chrono::Utc;
+use std::cmp::Ordering;
+
+/// Evaluar una expresión con las variables de `session`
+pub(crate) fn evaluate(expression: &str, session: &Session) -> Result<Value> {
+    evaluate_in(expression, session, None)
+}
+
+/// Evaluar una expresión sobre una fila de un resultado
+pub(crate) fn evaluate_row(
+    expression: &str,
+    session: &Session,
+    columns: &[Column],
+    row: &Row,
+) -> Result<Value> {
+    evaluate_in(expression, session, Some((columns, row)))
+}
+
+fn evaluate_in(
+    expression: &str,
+    session: &Session,
+    row: Option<(&[Column], &Row)>,
+) -> Result<Value> {
+    let tokens = tokenize(expression)?;
+    let mut parser = Parser {
+        tokens,
+        pos: 0,
+        session,
+        row,
+    };
+    let value = parser.disjunction()?;
+    match parser.peek() {
+        None => Ok(value),
+        Some(token) => Err(invalid(format!("token inesperado '{}'", token))),
+    }
+}
+
+fn invalid(message: impl Into<String>) -> NoctraError {
+    NoctraError::Validation(format!("expresión inválida: {}", message.into()))
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum Token {
+    Number(String),
+    Text(String),
+    Ident(String),
+    Variable(String),
+    Op(&'static str),
+    LParen,
+    RParen,
+    Comma,
+}
+
+impl std::fmt::Display for Token {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Token::Number(n) | Token::Ident(n) => f.write_str(n),
+            Token::Text(s) => write!(f, "'{}'", s),
+            Token::Variable(name) => write!(f, "#{}", name),
+            Token::Op(op) => f.write_str(op),
+            Token::LParen => f.write_str("("),
+            Token::RParen => f.write_str(")"),
+            Token::Comma => f.write_str(","),
+        }
+    }
+}
+
+/// Operadores, los de dos caracteres primero
+const OPERATORS: &[&str] = &[
+    "||", "<=", ">=", "<>", "!=", "+", "-", "*", "/", "%", "=", "<", ">",
+];
+
+fn tokenize(expression: &str) -> Result<Vec<Token>> {
+    let mut tokens = Vec::new();
+    let mut rest = expression.trim_start();
+
+    while let Some(c) = rest.chars().next() {
+        let consumed = if c == '\'' || c == '"' {
+            // '' (o "") dentro del literal es una comilla escapada
+            let mut text = String::new();
+            let mut chars = rest.char_indices().skip(1).peekable();
+            let mut end = None;
+            while let Some((i, ch)) = chars.next() {
+                if ch != c {
+                    text.push(ch);
+                } else if chars.peek().is_some_and(|&(_, next)| next == c) {
+                    text.push(c);
+                    chars.next();
+                } else {
+                    end = Some(i + 1);
+                    break;
+                }
+            }
+            tokens.push(Token::Text(text));
+            end.ok_or_else(|| invalid("literal de texto sin cerrar"))?
+        } else if c.is_ascii_digit()
+            || (c == '.' && rest[1..].starts_with(|d: char| d.is_ascii_digit()))
+        {
+            let mut len = rest
+                .find(|ch: char| !(ch.is_ascii_digit() || ch == '.'))
+                .unwrap_or(rest.len());
+            len += exponent_len(&rest[len..]);
+            tokens.push(Token::Number(rest[..len].to_string()));
+            len
+        } else if c == '#' || c.is_alphabetic() || c == '_' {
+            let start = usize::from(c == '#');
+            let len = rest[start..]
+                .find(|ch: char| !(ch.is_alphanumeric() || ch == '_'))
+                .map_or(rest.len(), |i| i + start);
+            let name = rest[start..len].to_string();
+            if name.is_empty() {
+                return Err(invalid("'#' sin nombre de variable"));
+            }
+            tokens.push(if start == 1 {
+                Token::Variable(name)
+            } else {
+                Token::Ident(name)
+            });
+            len
+        } else if c == '(' {
+            tokens.push(Token::LParen);
+            1
+        } else if c == ')' {
+            tokens.push(Token::RParen);
+            1
+        } else if c == ',' {
+            tokens.push(Token::Comma);
+            1
+        } else if let Some(op) = OPERATORS.iter().find(|op| rest.starts_with(**op)) {
+            tokens.push(Token::Op(op));
+            op.len()
+        } else {
+            return Err(invalid(format!("carácter inesperado '{}'", c)));
+        };
+        rest = rest[consumed..].trim_start();
+    }
+
+    if tokens.is_empty() {
+        return Err(invalid("expresión vacía"));
+    }
+    Ok(tokens)
+}
+
+/// Largo del exponente (`e5`, `E-3`) al comienzo de `rest`, 0 si no hay
+fn exponent_len(rest: &str) -> usize {
+    let Some(after) = rest.strip_prefix(['e', 'E']) else {
+        return 0;
+    };
+    let sign = usize::from(after.starts_with(['+', '-']));
+    let digits = after[sign..]
+        .find(|ch: char| !ch.is_ascii_digit())
+        .unwrap_or(after.len() - sign);
+    if digits == 0 {
+        0
+    } else {
+        1 + sign + digits
+    }
+}
+
+/// Verificar si `expression` se puede leer como expresión (sin evaluarla)
+pub(crate) fn tokenizes(expression: &str) -> bool {
+    tokenize(expression).is_ok()
+}
+
+/// Parser descendente recursivo que evalúa sobre la marcha
+struct Parser<'a> {
+    tokens: Vec<Token>,
+    pos: usize,
+    session: &'a Session,
+    row: Option<(&'a [Column], &'a Row)>,
+}
+
+impl Parser<'_> {
+    fn peek(&self) -> Option<&Token> {
+        self.tokens.get(self.pos)
+    }
+
+    fn next(&mut self) -> Option<Token> {
+        let token = self.tokens.get(self.pos).cloned();
+        self.pos += 1;
+        token
+    }
+
+    /// Consumir el operador si es uno de `ops`
+    fn operator(&mut self, ops: &[&str]) -> Option<&'static str> {
+        match self.peek() {
+            Some(Token::Op(op)) if ops.contains(op) => {
+                let op = *op;
+                self.pos += 1;
+                Some(op)
+            }
+            _ => None,
+        }
+    }
+
+    /// Consumir la palabra clave `keyword` (sin distinguir mayúsculas)
+    fn keyword(&mut self, keyword: &str) -> bool {
+        match self.peek() {
+            Some(Token::Ident(name)) if name.eq_ignore_ascii_case(keyword) => {
+                self.pos += 1;
+                true
+            }
+            _ => false,
+        }
+    }
+
+    fn expect(&mut self, expected: Token) -> Result<()> {
+        match self.next() {
+            Some(token) if token == expected => Ok(()),
+            Some(token) => Err(invalid(format!(
+                "se esperaba '{}', se obtuvo '{}'",
+                expected, token
+            ))),
+            None => Err(invalid(format!("se esperaba '{}'", expected))),
+        }
+    }
+
+    fn disjunction(&mut self) -> Result<Value> {
+        let mut value = self.conjunction()?;
+        while self.keyword("OR") {
+            let right = self.conjunction()?;
+            value = match (truth(&value)?, truth(&right)?) {
+                (Some(true), _) | (_, Some(true)) => Value::Boolean(true),
+                (Some(false), Some(false)) => Value::Boolean(false),
+                _ => Value::Null,
+            };
+        }
+        Ok(value)
+    }
+
+    fn conjunction(&mut self) -> Result<Value> {
+        let mut value = self.negation()?;
+        while self.keyword("AND") {
+            let right = self.negation()?;
+            value = match (truth(&value)?, truth(&right)?) {
+                (Some(false), _) | (_, Some(false)) => Value::Boolean(false),
+                (Some(true), Some(true)) => Value::Boolean(true),
+                _ => Value::Null,
+            };
+        }
+        Ok(value)
+    }
+
+    fn negation(&mut self) -> Result<Value> {
+        if self.keyword("NOT") {
+            return Ok(match truth(&self.negation()?)? {
+                Some(flag) => Value::Boolean(!flag),
+                None => Value::Null,
+            });
+        }
+        self.comparison()
+    }
+
+    fn comparison(&mut self) -> Result<Value> {
+        let left = self.concat()?;
+        let negated = self.keyword("NOT");
+        if negated || self.keyword("IN") {
+            if negated && !self.keyword("IN") {
+                return Err(invalid("se esperaba IN después de NOT"));
+            }
+            let found = self.in_list(&left)?;
+            return Ok(match found {
+                Some(found) => Value::Boolean(found != negated),
+                None => Value::Null,
+            });
+        }
+        match self.operator(&["=", "<>", "!=", "<", "<=", ">", ">="]) {
+            Some(op) => {
+                let right = self.concat()?;
+                compare(op, &left, &right)
+            }
+            None => Ok(left),
+        }
+    }
+
+    /// `(valor, ...)` de un IN: si `value` está en la lista (`None` si no
+    /// está y hubo algún NULL)
+    fn in_list(&mut self, value: &Value) -> Result<Option<bool>> {
+        self.expect(Token::LParen)?;
+        let (mut found, mut unknown) = (false, false);
+        loop {
+            let item = self.concat()?;
+            match coerce::equals_sql(value, &item) {
+                Some(equal) => found |= equal,
+                None => unknown = true,
+            }
+            if self.peek() != Some(&Token::Comma) {
+                break;
+            }
+            self.pos += 1;
+        }
+        self.expect(Token::RParen)?;
+        Ok(if found {
+            Some(true)
+        } else if unknown {
+            None
+        } else {
+            Some(false)
+        })
+    }
+
+    fn concat(&mut self) -> Result<Value> {
+        let mut value = self.additive()?;
+        while self.operator(&["||"]).is_some() {
+            let right = self.additive()?;
+            value = match (value, right) {
+                (Value::Null, _) | (_, Value::Null) => Value::Null,
+                (left, right) => Value::Text(format!("{}{}", left, right)),
+            };
+        }
+        Ok(value)
+    }
+
+    fn additive(&mut self) -> Result<Value> {
+        let mut value = self.term()?;
+        while let Some(op) = self.operator(&["+", "-"]) {
+            let right = self.term()?;
+            value = arithmetic(op, value, right)?;
+        }
+        Ok(value)
+    }
+
+    fn term(&mut self) -> Result<Value> {
+        let mut value = self.unary()?;
+        while let Some(op) = self.operator(&["*", "/", "%"]) {
+            let right = self.unary()?;
+            value = arithmetic(op, value, right)?;
+        }
+        Ok(value)
+    }
+
+    fn unary(&mut self) -> Result<Value> {
+        if self.operator(&["-"]).is_some() {
+            return arithmetic("-", Value::Integer(0), self.unary()?);
+        }
+        if self.operator(&["+"]).is_some() {
+            return self.unary();
+        }
+        self.primary()
+    }
+
+    fn primary(&mut self) -> Result<Value> {
+        match self.next() {
+            Some(Token::Number(n)) => match Value::from_literal(&n) {
+                value @ (Value::Integer(_) | Value::Float(_)) => Ok(value),
+                _ => Err(invalid(format!("número inválido '{}'", n))),
+            },
+            Some(Token::Text(s)) => Ok(Value::Text(s)),
+            Some(Token::LParen) => {
+                let value = self.disjunction()?;
+                self.expect(Token::RParen)?;
+                Ok(value)
+            }
+            Some(Token::Variable(name)) => self.variable(&name),
+            Some(Token::Ident(name)) if self.peek() == Some(&Token::LParen) => {
+                self.pos += 1;
+                let mut args = Vec::new();
+                if self.peek() != Some(&Token::RParen) {
+                    loop {
+                        args.push(self.comparison()?);
+                        if self.peek() != Some(&Token::Comma) {
+                            break;
+                        }
+                        self.pos += 1;
+                    }
+                }
+                self.expect(Token::RParen)?;
+                call(&name, args)
+            }
+            Some(Token::Ident(name)) => match name.to_uppercase().as_str() {
+                "TRUE" => Ok(Value::Boolean(true)),
+                "FALSE" => Ok(Value::Boolean(false)),
+                "NULL" => Ok(Value::Null),
+                _ => match self.column(&name) {
+                    Some(value) => Ok(value),
+                    None => self.variable(&name),
+                },
+            },
+            Some(token) => Err(invalid(format!("token inesperado '{}'", token))),
+            None => Err(invalid("expresión incompleta")),
+        }
+    }
+
+    /// Valor de la columna `name` en la fila que se evalúa
+    fn column(&self, name: &str) -> Option<Value> {
+        let (columns, row) = self.row?;
+        let column = columns
+            .iter()
+            .find(|column| column.name == name)
+            .or_else(|| {
+                columns
+                    .iter()
+                    .find(|column| column.name.eq_ignore_ascii_case(name))
+            })?;
+        Some(row.get(column.ordinal).cloned().unwrap_or(Value::Null))
+    }
+
+    fn variable(&self, name: &str) -> Result<Value> {
+        self.session
+            .get_variable(name)
+            .cloned()
+            .ok_or_else(|| NoctraError::SessionVariableNotFound(name.to_string()))
+    }
+}
+
+/// Valor numérico para operar
+enum Number {
+    Integer(i64),
+    Float(f64),
+}
+
+fn number(value: &Value, op: &str) -> Result<Number> {
+    match value {
+        Value::Integer(i) => Ok(Number::Integer(*i)),
+        Value::Float(f) => Ok(Number::Float(*f)),
+        #[cfg(feature = "decimal")]
+        Value::Decimal(d) => d
+            .to_string()
+            .parse()
+            .map(Number::Float)
+            .map_err(|_| invalid(format!("decimal fuera de rango: {}", d))),
+        other => Err(invalid(format!(
+            "'{}' no admite operandos {} ('{}')",
+            op,
+            other.type_name(),
+            other
+        ))),
+    }
+}
+
+fn arithmetic(op: &str, left: Value, right: Value) -> Result<Value> {
+    if left.is_null() || right.is_null() {
+        return Ok(Value::Null);
+    }
+    if matches!(op, "/" | "%") && matches!(right, Value::Integer(0)) {
+        return Err(invalid("división por cero"));
+    }
+
+    match (number(&left, op)?, number(&right, op)?) {
+        (Number::Integer(a), Number::Integer(b)) => {
+            let result = match op {
+                "+" => a.checked_add(b),
+                "-" => a.checked_sub(b),
+                "*" => a.checked_mul(b),
+                "/" => a.checked_div(b),
+                _ => a.checked_rem(b),
+            };
+            result
+                .map(Value::Integer)
+                .ok_or_else(|| invalid(format!("desbordamiento en {} {} {}", a, op, b)))
+        }
+        (a, b) => {
+            let as_f64 = |n: Number| match n {
+                Number::Integer(i) => i as f64,
+                Number::Float(f) => f,
+            };
+            let (a, b) = (as_f64(a), as_f64(b));
+            if matches!(op, "/" | "%") && b == 0.0 {
+                return Err(invalid("división por cero"));
+            }
+            let result = match op {
+                "+" => a + b,
+                "-" => a - b,
+                "*" => a * b,
+                "/" => a / b,
+                _ => a % b,
+            };
+            if result.is_finite() {
+                Ok(Value::Float(result))
+            } else {
+                Err(invalid(format!(
+                    "resultado no finito en {} {} {}",
+                    a, op, b
+                )))
+            }
+        }
+    }
+}
+
+/// Valor de verdad de un operando de AND/OR/NOT (`None` si es NULL)
+fn truth(value: &Value) -> Result<Option<bool>> {
+    match value {
+        Value::Null => Ok(None),
+        Value::Boolean(flag) => Ok(Some(*flag)),
+        Value::Integer(i) => Ok(Some(*i != 0)),
+        other => Err(invalid(format!(
+            "se esperaba un booleano, se obtuvo {} ('{}')",
+            other.type_name(),
+            other
+        ))),
+    }
+}
+
+fn compare(op: &str, left: &Value, right: &Value) -> Result<Value> {
+    if left.is_null() || right.is_null() {
+        return Ok(Value::Null);
+    }
+    let ordering = coerce::compare_sql(left, right, Collation::Binary).ok_or_else(|| {
+        invalid(format!(
+            "no se puede comparar {} con {}",
+            left.type_name(),
+            right.type_name()
+        ))
+    })?;
+
+    let result = match op {
+        "=" => ordering == Ordering::Equal,
+        "<>" | "!=" => ordering != Ordering::Equal,
+        "<" => ordering == Ordering::Less,
+        "<=" => ordering != Ordering::Greater,
+        ">" => ordering == Ordering::Greater,
+        _ => ordering != Ordering::Less,
+    };
+    Ok(Value::Boolean(result))
+}
+
+fn call(name: &str, args: Vec<Value>) -> Result<Value> {
+    let function = name.to_uppercase();
+    let expected = match function.as_str() {
+        "NOW" => 0,
+        "UPPER" | "LOWER" => 1,
+        _ => return Err(invalid(format!("función desconocida '{}'", name))),
+    };
+    if args.len() != expected {
+        return Err(invalid(format!(
+            "{}() espera {} argumento(s), recibió {}",
+            function,
+            expected,
+            args.len()
+        )));
+    }
+
+    Ok(match (function.as_str(), args.into_iter().next()) {
+        ("NOW", _) => Value::Timestamp(Utc::now()),
+        (_, Some(Value::Null)) => Value::Null,
+        ("UPPER", Some(value)) => Value::Text(value.to_string().to_uppercase()),
+        (_, Some(value)) => Value::Text(value.to_string().to_lowercase()),
+        (_, None) => Value::Null,
+    })
+}
@@ -0,0 +1,955 @@
+//! JOIN virtual entre fuentes
+//!
+//! Cuando un SELECT une tablas de fuentes distintas (p. ej. un CSV
+//! registrado y una tabla de la base SQLite) ninguna fuente puede resolverlo
+//! entera. El executor lee entonces de cada fuente sólo las columnas que
+//! necesita, con los predicados del WHERE que afectan a una sola tabla
+//! empujados a esa fuente, y hace el JOIN en memoria con una tabla hash.
+//!
+//! Se admite el subconjunto habitual: `[INNER | LEFT] JOIN ... ON a.x = b.y
+//! [AND ...]`, columnas y `*` en el SELECT, y `ORDER BY`, `LIMIT` y `OFFSET`
+//! sobre columnas. Los predicados que quedan para después del JOIN admiten
+//! comparaciones, `AND`/`OR`/`NOT`, `IS [NOT] NULL`, `IN` y `BETWEEN`.
+//! Agregaciones, `DISTINCT` o expresiones en el SELECT se rechazan.
+
+use crate::catalog::Catalog;
+use crate::error::{NoctraError, Result};
+use crate::types::coerce::{self, Collation};
+use crate::types::{Column, ResultSet, Row, Value};
+use sqlparser::ast::{
+    BinaryOperator, Expr, FunctionArg, FunctionArgExpr, GroupByExpr, Ident, JoinConstraint,
+    JoinOperator, ObjectName, Query, SelectItem, SetExpr, Statement, TableFactor, UnaryOperator,
+};
+use sqlparser::dialect::GenericDialect;
+use sqlparser::parser::Parser;
+use std::cmp::Ordering;
+use std::collections::HashMap;
+
+/// Filas máximas que se leen de cada tabla si `ExecutorOptions::join_row_limit` es None
+pub const DEFAULT_JOIN_ROW_LIMIT: usize = 1_000_000;
+
+/// Plan de un JOIN entre fuentes (ver `Executor::federated_plan`)
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FederatedPlan {
+    /// Lectura de cada tabla, en el orden del FROM
+    pub scans: Vec<FederatedScan>,
+
+    /// Claves de cada JOIN (`izquierda = derecha`), en el orden de los JOIN
+    pub join_keys: Vec<Vec<String>>,
+
+    /// Predicados que se evalúan en memoria después del JOIN
+    pub residual_predicates: Vec<String>,
+}
+
+/// Lectura de una tabla en su fuente
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct FederatedScan {
+    /// Alias de la fuente (None = base del backend)
+    pub source: Option<String>,
+
+    /// Tabla en la fuente
+    pub table: String,
+
+    /// Alias de la tabla en la consulta
+    pub alias: String,
+
+    /// Columnas leídas
+    pub columns: Vec<String>,
+
+    /// Predicados del WHERE (u ON) empujados a la fuente
+    pub pushed_predicates: Vec<String>,
+
+    /// SQL que se envía a la fuente
+    pub sql: String,
+}
+
+/// Columna de una tabla del JOIN (índices en `scans` y en `columns`)
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+struct ColumnRef {
+    table: usize,
+    column: usize,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum JoinKind {
+    Inner,
+    Left,
+}
+
+/// JOIN de la tabla `table` con las anteriores
+#[derive(Debug, Clone)]
+struct JoinStep {
+    table: usize,
+    kind: JoinKind,
+    /// Pares (columna de las tablas anteriores, columna de `table`)
+    keys: Vec<(ColumnRef, ColumnRef)>,
+}
+
+#[derive(Debug, Clone)]
+enum Operand {
+    Column(ColumnRef),
+    Literal(Value),
+}
+
+/// Predicado evaluable sobre una fila ya unida
+#[derive(Debug, Clone)]
+enum Predicate {
+    Compare(Operand, BinaryOperator, Operand),
+    And(Box<Predicate>, Box<Predicate>),
+    Or(Box<Predicate>, Box<Predicate>),
+    Not(Box<Predicate>),
+    IsNull(Operand, bool),
+    InList(Operand, Vec<Operand>, bool),
+    Between(Operand, Operand, Operand, bool),
+}
+
+/// Tabla del FROM ya resuelta a su fuente
+#[derive(Debug, Clone)]
+struct TableRef {
+    source: Option<String>,
+    table: String,
+    alias: String,
+    /// Columnas conocidas por el catálogo
+    known_columns: Vec<String>,
+    /// Columnas que hay que leer
+    columns: Vec<String>,
+    pushed: Vec<String>,
+}
+
+/// JOIN entre fuentes listo para ejecutar
+#[derive(Debug, Clone)]
+pub(crate) struct FederatedQuery {
+    plan: FederatedPlan,
+    joins: Vec<JoinStep>,
+    residual: Vec<Predicate>,
+    projection: Vec<(String, ColumnRef)>,
+    order_by: Vec<(ColumnRef, bool)>,
+    limit: Option<usize>,
+    offset: usize,
+    row_limit: usize,
+}
+
+fn unsupported(what: impl std::fmt::Display) -> NoctraError {
+    NoctraError::Validation(format!("JOIN entre fuentes: {} no soportado", what))
+}
+
+impl FederatedQuery {
+    /// Planificar `sql` si une tablas de fuentes distintas
+    ///
+    /// Devuelve None si el SQL no es un SELECT con JOIN que el planificador
+    /// entienda o si todas sus tablas están en la misma fuente: entonces la
+    /// consulta sigue el camino normal. Los nombres sin cualificar se
+    /// buscan en el catálogo; `alias.tabla` con un alias registrado va a esa
+    /// fuente y el resto, a la fuente activa (o al backend).
+    pub(crate) fn plan(
+        sql: &str,
+        catalog: &Catalog,
+        registered: &[String],
+        active: Option<&str>,
+        row_limit: usize,
+    ) -> Result<Option<Self>> {
+        let Ok(mut statements) = Parser::parse_sql(&GenericDialect {}, sql) else {
+            return Ok(None);
+        };
+        let Some(Statement::Query(query)) = statements.pop().filter(|_| statements.is_empty())
+        else {
+            return Ok(None);
+        };
+        let SetExpr::Select(select) = query.body.as_ref() else {
+            return Ok(None);
+        };
+        let [from] = select.from.as_slice() else {
+            return Ok(None);
+        };
+        if from.joins.is_empty() {
+            return Ok(None);
+        }
+
+        let mut tables = Vec::new();
+        for relation in
+            std::iter::once(&from.relation).chain(from.joins.iter().map(|j| &j.relation))
+        {
+            let TableFactor::Table {
+                name,
+                alias,
+                args: None,
+                ..
+            } = relation
+            else {
+                return Ok(None);
+            };
+            tables.push(resolve_table(
+                name,
+                alias.as_ref().map(|a| &a.name),
+                catalog,
+                registered,
+                active,
+            ));
+        }
+        let first_source = &tables[0].source;
+        if tables.iter().all(|table| &table.source == first_source) {
+            return Ok(None);
+        }
+
+        if select.distinct.is_some() || select.top.is_some() {
+            return Err(unsupported("DISTINCT"));
+        }
+        if select.having.is_some()
+            || !matches!(&select.group_by, GroupByExpr::Expressions(exprs) if exprs.is_empty())
+        {
+            return Err(unsupported("GROUP BY"));
+        }
+        if query.with.is_some() || query.fetch.is_some() {
+            return Err(unsupported("WITH / FETCH"));
+        }
+
+        let mut planner = Planner { tables };
+
+        let projection = planner.projection(&select.projection)?;
+
+        let mut joins = Vec::new();
+        let mut residual = Vec::new();
+        let mut residual_sql = Vec::new();
+        for (index, join) in from.joins.iter().enumerate() {
+            let table = index + 1;
+            let (kind, constraint) = match &join.join_operator {
+                JoinOperator::Inner(constraint) => (JoinKind::Inner, constraint),
+                JoinOperator::LeftOuter(constraint) => (JoinKind::Left, constraint),
+                _ => return Err(unsupported("RIGHT, FULL o CROSS JOIN")),
+            };
+            let JoinConstraint::On(on) = constraint else {
+                return Err(unsupported("JOIN sin ON"));
+            };
+
+            let mut keys = Vec::new();
+            for conjunct in conjuncts(on) {
+                if let Some(key) = planner.join_key(conjunct, table)? {
+                    keys.push(key);
+                    continue;
+                }
+                match planner.single_table(conjunct)? {
+                    // Filtrar la tabla de la derecha antes del JOIN equivale al ON
+                    Some(only) if only == table => {
+                        planner.tables[table].pushed.push(conjunct.to_string())
+                    }
+                    _ if kind == JoinKind::Inner => {
+                        residual.push(planner.predicate(conjunct)?);
+                        residual_sql.push(conjunct.to_string());
+                    }
+                    _ => return Err(unsupported(format!("condición ON '{}'", conjunct))),
+                }
+            }
+            if keys.is_empty() {
+                return Err(unsupported("JOIN sin igualdad entre columnas"));
+            }
+            joins.push(JoinStep { table, kind, keys });
+        }
+
+        // La tabla derecha de un LEFT JOIN no recibe el WHERE: filtraría
+        // también las filas completadas con NULL
+        let outer: Vec<usize> = joins
+            .iter()
+            .filter(|join| join.kind == JoinKind::Left)
+            .map(|join| join.table)
+            .collect();
+        if let Some(selection) = &select.selection {
+            for conjunct in conjuncts(selection) {
+                match planner.single_table(conjunct)? {
+                    Some(table) if !outer.contains(&table) => {
+                        planner.tables[table].pushed.push(conjunct.to_string())
+                    }
+                    _ => {
+                        residual.push(planner.predicate(conjunct)?);
+                        residual_sql.push(conjunct.to_string());
+                    }
+                }
+            }
+        }
+
+        let order_by = planner.order_by(&query, &projection)?;
+        let limit = query.limit.as_ref().map(literal_count).transpose()?;
+        let offset = query
+            .offset
+            .as_ref()
+            .map(|offset| literal_count(&offset.value))
+            .transpose()?
+            .unwrap_or(0);
+
+        let join_keys = joins
+            .iter()
+            .map(|join| {
+                join.keys
+                    .iter()
+                    .map(|(left, right)| {
+                        format!(
+                            "{} = {}",
+                            planner.column_name(*left),
+                            planner.column_name(*right)
+                        )
+                    })
+                    .collect()
+            })
+            .collect();
+        let scans = planner
+            .tables
+            .iter()
+            .map(|table| FederatedScan {
+                source: table.source.clone(),
+                table: table.table.clone(),
+                alias: table.alias.clone(),
+                columns: table.columns.clone(),
+                pushed_predicates: table.pushed.clone(),
+                sql: scan_sql(table, row_limit),
+            })
+            .collect();
+
+        Ok(Some(Self {
+            plan: FederatedPlan {
+                scans,
+                join_keys,
+                residual_predicates: residual_sql,
+            },
+            joins,
+            residual,
+            projection,
+            order_by,
+            limit,
+            offset,
+            row_limit,
+        }))
+    }
+
+    /// Plan de la consulta
+    pub(crate) fn plan_info(&self) -> &FederatedPlan {
+        &self.plan
+    }
+
+    /// Ejecutar el JOIN leyendo cada tabla con `scan`
+    pub(crate) fn execute<F>(&self, mut scan: F) -> Result<ResultSet>
+    where
+        F: FnMut(&FederatedScan) -> Result<ResultSet>,
+    {
+        let mut results = Vec::with_capacity(self.plan.scans.len());
+        for federated_scan in &self.plan.scans {
+            let result = scan(federated_scan)?;
+            if result.rows.len() > self.row_limit {
+                return Err(NoctraError::Validation(format!(
+                    "JOIN entre fuentes: '{}' devuelve más de {} filas (ajusta join_row_limit o filtra la consulta)",
+                    federated_scan.alias, self.row_limit
+                )));
+            }
+            results.push(result);
+        }
+
+        let mut offsets = Vec::with_capacity(results.len());
+        let mut width = 0;
+        for federated_scan in &self.plan.scans {
+            offsets.push(width);
+            width += federated_scan.columns.len();
+        }
+        let position = |column: ColumnRef| offsets[column.table] + column.column;
+
+        let mut rows: Vec<Vec<Value>> = results[0]
+            .rows
+            .iter()
+            .map(|row| row.values.clone())
+            .collect();
+        for join in &self.joins {
+            let right = &results[join.table].rows;
+            let mut index: HashMap<Vec<String>, Vec<usize>> = HashMap::new();
+            for (i, row) in right.iter().enumerate() {
+                let key: Option<Vec<String>> = join
+                    .keys
+                    .iter()
+                    .map(|(_, column)| join_key(row.values.get(column.column)?))
+                    .collect();
+                if let Some(key) = key {
+                    index.entry(key).or_default().push(i);
+                }
+            }
+
+            let padding = self.plan.scans[join.table].columns.len();
+            let mut joined = Vec::new();
+            for row in rows {
+                let key: Option<Vec<String>> = join
+                    .keys
+                    .iter()
+                    .map(|(column, _)| join_key(&row[position(*column)]))
+                    .collect();
+                match key.and_then(|key| index.get(&key)) {
+                    Some(matches) => {
+                        for &i in matches {
+                            let mut values = row.clone();
+                            values.extend(right[i].values.iter().cloned());
+                            joined.push(values);
+                        }
+                    }
+                    None if join.kind == JoinKind::Left => {
+                        let mut values = row;
+                        values.resize(values.len() + padding, Value::Null);
+                        joined.push(values);
+                    }
+                    None => {}
+                }
+            }
+            rows = joined;
+        }
+
+        rows.retain(|row| {
+            self.residual
+                .iter()
+                .all(|predicate| predicate.evaluate(row, &position))
+        });
+        if !self.order_by.is_empty() {
+            rows.sort_by(|a, b| {
+                self.order_by
+                    .iter()
+                    .map(|&(column, ascending)| {
+                        let ordering = coerce::compare(&a[position(column)], &b[position(column)]);
+                        if ascending {
+                            ordering
+                        } else {
+                            ordering.reverse()
+                        }
+                    })
+                    .find(|ordering| ordering.is_ne())
+                    .unwrap_or(Ordering::Equal)
+            });
+        }
+
+        let columns = self
+            .projection
+            .iter()
+            .enumerate()
+            .map(|(ordinal, (name, column))| {
+                let data_type = results[column.table]
+                    .columns
+                    .get(column.column)
+                    .map(|c| c.data_type.clone())
+                    .unwrap_or_default();
+                Column::new(name.clone(), data_type, ordinal)
+            })
+            .collect();
+        let mut result = ResultSet::new(columns);
+        let rows = rows
+            .into_iter()
+            .skip(self.offset)
+            .take(self.limit.unwrap_or(usize::MAX));
+        for row in rows {
+            result.add_row(Row::new(
+                self.projection
+                    .iter()
+                    .map(|(_, column)| row[position(*column)].clone())
+                    .collect(),
+            ));
+        }
+        Ok(result)
+    }
+}
+
+/// Resolver una tabla del FROM a su fuente
+fn resolve_table(
+    name: &ObjectName,
+    alias: Option<&Ident>,
+    catalog: &Catalog,
+    registered: &[String],
+    active: Option<&str>,
+) -> TableRef {
+    let parts = &name.0;
+    let (source, table) = match parts.as_slice() {
+        [source, table] if registered.iter().any(|alias| alias == &source.value) => {
+            (Some(source.value.clone()), table.to_string())
+        }
+        [table] => match catalog.lookup(&table.value, active) {
+            Ok(entry) => (entry.source.clone(), table.to_string()),
+            Err(_) => (active.map(str::to_string), table.to_string()),
+        },
+        _ => (active.map(str::to_string), name.to_string()),
+    };
+
+    let bare = parts
+        .last()
+        .map(|part| part.value.clone())
+        .unwrap_or_default();
+    let known_columns = catalog
+        .entries()
+        .iter()
+        .find(|entry| entry.source == source && entry.table.name.eq_ignore_ascii_case(&bare))
+        .map(|entry| entry.table.columns.iter().map(|c| c.name.clone()).collect())
+        .unwrap_or_default();
+
+    TableRef {
+        source,
+        table,
+        alias: alias.map_or(bare, |alias| alias.value.clone()),
+        known_columns,
+        columns: Vec::new(),
+        pushed: Vec::new(),
+    }
+}
+
+/// SQL de la lectura de una tabla (una fila de más para detectar el límite)
+fn scan_sql(table: &TableRef, row_limit: usize) -> String {
+    let columns = table
+        .columns
+        .iter()
+        .map(|column| format!("\"{}\"", column.replace('"', "\"\"")))
+        .collect::<Vec<_>>()
+        .join(", ");
+    let mut sql = format!(
+        "SELECT {} FROM {} AS \"{}\"",
+        columns,
+        table.table,
+        table.alias.replace('"', "\"\"")
+    );
+    if !table.pushed.is_empty() {
+        sql.push_str(" WHERE ");
+        sql.push_str(&table.pushed.join(" AND "));
+    }
+    sql.push_str(&format!(" LIMIT {}", row_limit.saturating_add(1)));
+    sql
+}
+
+/// Partes de una conjunción `a AND b AND ...`
+fn conjuncts(expr: &Expr) -> Vec<&Expr> {
+    match expr {
+        Expr::BinaryOp {
+            left,
+            op: BinaryOperator::And,
+            right,
+        } => {
+            let mut parts = conjuncts(left);
+            parts.extend(conjuncts(right));
+            parts
+        }
+        Expr::Nested(inner) => conjuncts(inner),
+        _ => vec![expr],
+    }
+}
+
+/// `LIMIT`/`OFFSET` con un número literal
+fn literal_count(expr: &Expr) -> Result<usize> {
+    match expr {
+        Expr::Value(sqlparser::ast::Value::Number(number, _)) => number
+            .parse()
+            .map_err(|_| unsupported(format!("LIMIT '{}'", number))),
+        other => Err(unsupported(format!("LIMIT '{}'", other))),
+    }
+}
+
+struct Planner {
+    tables: Vec<TableRef>,
+}
+
+impl Planner {
+    /// Resolver una columna (`col`, `alias.col` o `fuente.tabla.col`)
+    fn resolve(&self, idents: &[Ident]) -> Result<(usize, String)> {
+        let (column, qualifier) = idents
+            .split_last()
+            .ok_or_else(|| unsupported("columna vacía"))?;
+        let canonical = |table: &TableRef| {
+            table
+                .known_columns
+                .iter()
+                .find(|known| known.eq_ignore_ascii_case(&column.value))
+                .cloned()
+        };
+
+        if qualifier.is_empty() {
+            let candidates: Vec<(usize, String)> = self
+                .tables
+                .iter()
+                .enumerate()
+                .filter_map(|(index, table)| canonical(table).map(|name| (index, name)))
+                .collect();
+            return match candidates.as_slice() {
+                [(index, name)] => Ok((*index, name.clone())),
+                [] => Err(NoctraError::not_found("columna", &column.value)),
+                _ => Err(NoctraError::Validation(format!(
+                    "columna '{}' ambigua; usa tabla.columna",
+                    column.value
+                ))),
+            };
+        }
+
+        let qualifier: Vec<&str> = qualifier.iter().map(|ident| ident.value.as_str()).collect();
+        let index = self
+            .tables
+            .iter()
+            .position(|table| match qualifier.as_slice() {
+                [alias] => table.alias.eq_ignore_ascii_case(alias),
+                [source, name] => {
+                    table.source.as_deref() == Some(*source)
+                        && table.alias.eq_ignore_ascii_case(name)
+                }
+                _ => false,
+            })
+            .ok_or_else(|| NoctraError::not_found("tabla", qualifier.join(".")))?;
+        let table = &self.tables[index];
+        if !table.known_columns.is_empty() && canonical(table).is_none() {
+            return Err(NoctraError::not_found(
+                "columna",
+                format!("{}.{}", table.alias, column.value),
+            ));
+        }
+        Ok((
+            index,
+            canonical(table).unwrap_or_else(|| column.value.clone()),
+        ))
+    }
+
+    /// Columna que hay que leer de su tabla
+    fn column(&mut self, idents: &[Ident]) -> Result<ColumnRef> {
+        let (table, name) = self.resolve(idents)?;
+        let columns = &mut self.tables[table].columns;
+        let column = match columns.iter().position(|c| c == &name) {
+            Some(position) => position,
+            None => {
+                columns.push(name);
+                columns.len() - 1
+            }
+        };
+        Ok(ColumnRef { table, column })
+    }
+
+    fn column_name(&self, column: ColumnRef) -> String {
+        let table = &self.tables[column.table];
+        format!("{}.{}", table.alias, table.columns[column.column])
+    }
+
+    fn projection(&mut self, items: &[SelectItem]) -> Result<Vec<(String, ColumnRef)>> {
+        let mut projection = Vec::new();
+        for item in items {
+            match item {
+                SelectItem::UnnamedExpr(expr) => {
+                    let idents = column_idents(expr)
+                        .ok_or_else(|| unsupported(format!("expresión '{}' en el SELECT", expr)))?;
+                    let column = self.column(&idents)?;
+                    let name = self.tables[column.table].columns[column.column].clone();
+                    projection.push((name, column));
+                }
+                SelectItem::ExprWithAlias { expr, alias } => {
+                    let idents = column_idents(expr)
+                        .ok_or_else(|| unsupported(format!("expresión '{}' en el SELECT", expr)))?;
+                    projection.push((alias.value.clone(), self.column(&idents)?));
+                }
+                SelectItem::Wildcard(_) => {
+                    for table in 0..self.tables.len() {
+                        projection.extend(self.all_columns(table)?);
+                    }
+                }
+                SelectItem::QualifiedWildcard(name, _) => {
+                    let qualifier = name
+                        .0
+                        .last()
+                        .map(|ident| ident.value.as_str())
+                        .unwrap_or("");
+                    let table = self
+                        .tables
+                        .iter()
+                        .position(|table| table.alias.eq_ignore_ascii_case(qualifier))
+                        .ok_or_else(|| NoctraError::not_found("tabla", name.to_string()))?;
+                    projection.extend(self.all_columns(table)?);
+                }
+            }
+        }
+        Ok(projection)
+    }
+
+    /// Todas las columnas de una tabla (para `*` y `alias.*`)
+    fn all_columns(&mut self, table: usize) -> Result<Vec<(String, ColumnRef)>> {
+        let known = self.tables[table].known_columns.clone();
+        if known.is_empty() {
+            return Err(unsupported(format!(
+                "'*' sobre '{}' (esquema desconocido)",
+                self.tables[table].alias
+            )));
+        }
+        known
+            .into_iter()
+            .map(|name| {
+                let alias = Ident::new(self.tables[table].alias.clone());
+                let column = self.column(&[alias, Ident::new(name.clone())])?;
+                Ok((name, column))
+            })
+            .collect()
+    }
+
+    /// Clave de JOIN `a.x = b.y` entre `table` y una tabla anterior
+    fn join_key(&mut self, expr: &Expr, table: usize) -> Result<Option<(ColumnRef, ColumnRef)>> {
+        let Expr::BinaryOp {
+            left,
+            op: BinaryOperator::Eq,
+            right,
+        } = expr
+        else {
+            return Ok(None);
+        };
+        let (Some(left), Some(right)) = (column_idents(left), column_idents(right)) else {
+            return Ok(None);
+        };
+        let (left_table, _) = self.resolve(&left)?;
+        let (right_table, _) = self.resolve(&right)?;
+        let (earlier, current) = if right_table == table && left_table < table {
+            (left, right)
+        } else if left_table == table && right_table < table {
+            (right, left)
+        } else {
+            return Ok(None);
+        };
+        Ok(Some((self.column(&earlier)?, self.column(&current)?)))
+    }
+
+    /// Tabla a la que se refieren todas las columnas de `expr`, si es una sola
+    ///
+    /// Las expresiones con construcciones desconocidas no se empujan.
+    fn single_table(&self, expr: &Expr) -> Result<Option<usize>> {
+        let mut idents = Vec::new();
+        if !collect_columns(expr, &mut idents) {
+            return Ok(None);
+        }
+        let mut tables = Vec::new();
+        for column in &idents {
+            let (table, _) = self.resolve(column)?;
+            if !tables.contains(&table) {
+                tables.push(table);
+            }
+        }
+        Ok(match tables.as_slice() {
+            [table] => Some(*table),
+            _ => None,
+        })
+    }
+
+    fn operand(&mut self, expr: &Expr) -> Result<Operand> {
+        if let Some(idents) = column_idents(expr) {
+            return Ok(Operand::Column(self.column(&idents)?));
+        }
+        literal(expr)
+            .map(Operand::Literal)
+            .ok_or_else(|| unsupported(format!("expresión '{}' tras el JOIN", expr)))
+    }
+
+    /// Traducir un predicado que se evalúa en memoria
+    fn predicate(&mut self, expr: &Expr) -> Result<Predicate> {
+        Ok(match expr {
+            Expr::Nested(inner) => self.predicate(inner)?,
+            Expr::BinaryOp { left, op, right } => match op {
+                BinaryOperator::And => Predicate::And(
+                    Box::new(self.predicate(left)?),
+                    Box::new(self.predicate(right)?),
+                ),
+                BinaryOperator::Or => Predicate::Or(
+                    Box::new(self.predicate(left)?),
+                    Box::new(self.predicate(right)?),
+                ),
+                BinaryOperator::Eq
+                | BinaryOperator::NotEq
+                | BinaryOperator::Lt
+                | BinaryOperator::LtEq
+                | BinaryOperator::Gt
+                | BinaryOperator::GtEq => {
+                    Predicate::Compare(self.operand(left)?, op.clone(), self.operand(right)?)
+                }
+                _ => return Err(unsupported(format!("operador '{}' tras el JOIN", op))),
+            },
+            Expr::UnaryOp {
+                op: UnaryOperator::Not,
+                expr,
+            } => Predicate::Not(Box::new(self.predicate(expr)?)),
+            Expr::IsNull(inner) => Predicate::IsNull(self.operand(inner)?, false),
+            Expr::IsNotNull(inner) => Predicate::IsNull(self.operand(inner)?, true),
+            Expr::InList {
+                expr,
+                list,
+                negated,
+            } => Predicate::InList(
+                self.operand(expr)?,
+                list.iter()
+                    .map(|item| self.operand(item))
+                    .collect::<Result<_>>()?,
+                *negated,
+            ),
+            Expr::Between {
+                expr,
+                negated,
+                low,
+                high,
+            } => Predicate::Between(
+                self.operand(expr)?,
+                self.operand(low)?,
+                self.operand(high)?,
+                *negated,
+            ),
+            other => return Err(unsupported(format!("predicado '{}' tras el JOIN", other))),
+        })
+    }
+
+    /// `ORDER BY` sobre columnas, alias del SELECT o posiciones (`ORDER BY 2`)
+    fn order_by(
+        &mut self,
+        query: &Query,
+        projection: &[(String, ColumnRef)],
+    ) -> Result<Vec<(ColumnRef, bool)>> {
+        let mut order_by = Vec::new();
+        for item in &query.order_by {
+            let ascending = item.asc.unwrap_or(true);
+            let by_alias = match &item.expr {
+                Expr::Identifier(ident) => projection
+                    .iter()
+                    .find(|(name, _)| name.eq_ignore_ascii_case(&ident.value))
+                    .map(|(_, column)| *column),
+                Expr::Value(sqlparser::ast::Value::Number(number, _)) => number
+                    .parse::<usize>()
+                    .ok()
+                    .and_then(|position| projection.get(position.checked_sub(1)?))
+                    .map(|(_, column)| *column),
+                _ => None,
+            };
+            let column = match by_alias {
+                Some(column) => column,
+                None => {
+                    let idents = column_idents(&item.expr)
+                        .ok_or_else(|| unsupported(format!("ORDER BY '{}'", item.expr)))?;
+                    self.column(&idents)?
+                }
+            };
+            order_by.push((column, ascending));
+        }
+        Ok(order_by)
+    }
+}
+
+/// Identificadores de una referencia a columna
+fn column_idents(expr: &Expr) -> Option<Vec<Ident>> {
+    match expr {
+        Expr::Identifier(ident) => Some(vec![ident.clone()]),
+        Expr::CompoundIdentifier(idents) => Some(idents.clone()),
+        Expr::Nested(inner) => column_idents(inner),
+        _ => None,
+    }
+}
+
+/// Reunir las columnas de `expr`; false si hay construcciones desconocidas
+fn collect_columns(expr: &Expr, out: &mut Vec<Vec<Ident>>) -> bool {
+    match expr {
+        Expr::Identifier(_) | Expr::CompoundIdentifier(_) => {
+            out.extend(column_idents(expr));
+            true
+        }
+        Expr::Value(_) => true,
+        Expr::Nested(inner)
+        | Expr::IsNull(inner)
+        | Expr::IsNotNull(inner)
+        | Expr::UnaryOp { expr: inner, .. }
+        | Expr::Cast { expr: inner, .. } => collect_columns(inner, out),
+        Expr::BinaryOp { left, right, .. } => {
+            collect_columns(left, out) && collect_columns(right, out)
+        }
+        Expr::Like { expr, pattern, .. } | Expr::ILike { expr, pattern, .. } => {
+            collect_columns(expr, out) && collect_columns(pattern, out)
+        }
+        Expr::InList { expr, list, .. } => {
+            collect_columns(expr, out) && list.iter().all(|item| collect_columns(item, out))
+        }
+        Expr::Between {
+            expr, low, high, ..
+        } => collect_columns(expr, out) && collect_columns(low, out) && collect_columns(high, out),
+        Expr::Function(function) if function.over.is_none() && function.filter.is_none() => {
+            function.args.iter().all(|arg| match arg {
+                FunctionArg::Named {
+                    arg: FunctionArgExpr::Expr(expr),
+                    ..
+                }
+                | FunctionArg::Unnamed(FunctionArgExpr::Expr(expr)) => collect_columns(expr, out),
+                _ => false,
+            })
+        }
+        _ => false,
+    }
+}
+
+/// Valor de un literal SQL (números, texto, booleanos y NULL)
+fn literal(expr: &Expr) -> Option<Value> {
+    use sqlparser::ast::Value as SqlValue;
+    match expr {
+        Expr::Value(SqlValue::Number(number, _)) => Some(Value::from_literal(number)),
+        Expr::Value(SqlValue::SingleQuotedString(text)) => Some(Value::Text(text.clone())),
+        Expr::Value(SqlValue::Boolean(value)) => Some(Value::Boolean(*value)),
+        Expr::Value(SqlValue::Null) => Some(Value::Null),
+        Expr::UnaryOp {
+            op: UnaryOperator::Minus,
+            expr,
+        } => match literal(expr)? {
+            Value::Integer(value) => Some(Value::Integer(-value)),
+            Value::Float(value) => Some(Value::Float(-value)),
+            _ => None,
+        },
+        Expr::Nested(inner) => literal(inner),
+        _ => None,
+    }
+}
+
+/// Clave de JOIN de un valor (None = NULL, que no une con nada)
+///
+/// Se compara la representación textual, así que un CSV leído como texto
+/// une con una columna INTEGER (`'7'` con `7`, `7.0` con `7`).
+fn join_key(value: &Value) -> Option<String> {
+    match value {
+        Value::Null => None,
+        Value::Integer(value) => Some(value.to_string()),
+        Value::Float(value) if value.fract() == 0.0 && value.abs() < 1e15 => {
+            Some((*value as i64).to_string())
+        }
+        Value::Text(text) => Some(text.clone()),
+        other => Some(other.to_string()),
+    }
+}
+
+impl Predicate {
+    fn evaluate(&self, row: &[Value], position: &dyn Fn(ColumnRef) -> usize) -> bool {
+        let value = |operand: &Operand| match operand {
+            Operand::Column(column) => row[position(*column)].clone(),
+            Operand::Literal(value) => value.clone(),
+        };
+        // Comparar con NULL nunca es verdadero
+        let compare = |left: &Operand, right: &Operand| {
+            coerce::compare_sql(&value(left), &value(right), Collation::Binary)
+        };
+
+        match self {
+            Predicate::Compare(left, op, right) => {
+                let Some(ordering) = compare(left, right) else {
+                    return false;
+                };
+                match op {
+                    BinaryOperator::Eq => ordering.is_eq(),
+                    BinaryOperator::NotEq => ordering.is_ne(),
+                    BinaryOperator::Lt => ordering.is_lt(),
+                    BinaryOperator::LtEq => ordering.is_le(),
+                    BinaryOperator::Gt => ordering.is_gt(),
+                    BinaryOperator::GtEq => ordering.is_ge(),
+                    _ => false,
+                }
+            }
+            Predicate::And(left, right) => {
+                left.evaluate(row, position) && right.evaluate(row, position)
+            }
+            Predicate::Or(left, right) => {
+                left.evaluate(row, position) || right.evaluate(row, position)
+            }
+            Predicate::Not(inner) => !inner.evaluate(row, position),
+            Predicate::IsNull(operand, negated) => value(operand).is_null() != *negated,
+            Predicate::InList(operand, list, negated) => {
+                list.iter()
+                    .any(|item| compare(operand, item).is_some_and(Ordering::is_eq))
+                    != *negated
+            }
+            Predicate::Between(operand, low, high, negated) => {
+                let inside = compare(operand, low).is_some_and(Ordering::is_ge)
+                    && compare(operand, high).is_some_and(Ordering::is_le);
+                inside != *negated
+            }
+        }
+    }
+}
@@ -0,0 +1,220 @@
+//! Sandbox de rutas de archivo
+//!
+//! Valida las rutas que usan IMPORT, EXPORT y el registro de archivos.
+//! Con raíces permitidas, la ruta se canonicaliza (resolviendo `..` y
+//! enlaces simbólicos de los directorios) y debe quedar dentro de alguna de
+//! ellas; el archivo final no puede ser un enlace simbólico. Sin raíces
+//! se aplica la lista de directorios del sistema bloqueados.
+
+use crate::error::{NoctraError, Result};
+use std::path::{Component, Path, PathBuf};
+
+/// Directorios del sistema rechazados cuando no hay raíces permitidas
+pub const BLOCKED_DIRS: &[&str] = &[
+    "/etc/",
+    "/sys/",
+    "/proc/",
+    "/dev/",
+    "/root/",
+    "/boot/",
+    "C:\\Windows\\",
+    "C:\\Program Files\\",
+];
+
+/// Validar una ruta de archivo
+///
+/// Con `allowed_roots` vacío se aplica la lista de bloqueo: se rechazan los
+/// directorios de `BLOCKED_DIRS`, las rutas con `..` y las que existen pero no
+/// son archivos regulares. En otro caso la ruta (que puede no existir todavía,
+/// p. ej. el destino de un EXPORT) debe quedar bajo alguna raíz una vez
+/// canonicalizada, y no puede ser un enlace simbólico (aunque esté roto: un
+/// EXPORT lo seguiría al escribir). En los patrones glob se valida la parte
+/// fija inicial y el resto no puede contener `..`.
+///
+/// Devuelve la ruta canonicalizada, o la original con la lista de bloqueo.
+pub fn check_path(path: impl AsRef<Path>, allowed_roots: &[PathBuf]) -> Result<PathBuf> {
+    let path = path.as_ref();
+    if allowed_roots.is_empty() {
+        check_blocklist(path)?;
+        return Ok(path.to_path_buf());
+    }
+
+    let glob = glob_base(path);
+    if let Some(base) = glob {
+        let pattern = path.strip_prefix(base).unwrap_or(path);
+        if pattern.components().any(|c| c == Component::ParentDir) {
+            return Err(NoctraError::permission(format!(
+                "el patrón {} no puede contener '..' tras el comodín",
+                path.display()
+            )));
+        }
+    }
+    let target = canonicalize_target(glob.unwrap_or(path))?;
+
+    let allowed = allowed_roots
+        .iter()
+        .filter_map(|root| root.canonicalize().ok())
+        .any(|root| target.starts_with(root));
+    if !allowed {
+        return Err(NoctraError::permission(format!(
+            "{} está fuera de los directorios permitidos",
+            path.display()
+        )));
+    }
+
+    // En un glob se devuelve el patrón con su base canonicalizada
+    match glob {
+        Some(base) => Ok(target.join(path.strip_prefix(base).unwrap_or(path))),
+        None => Ok(target),
+    }
+}
+
+/// Comprobación heredada: directorios del sistema, `..` y archivos regulares
+fn check_blocklist(path: &Path) -> Result<()> {
+    let path_str = path.to_string_lossy();
+
+    for blocked in BLOCKED_DIRS {
+        if path_str.starts_with(blocked) {
+            return Err(NoctraError::permission(format!(
+                "No se puede acceder a directorio del sistema: {}",
+                path_str
+            )));
+        }
+    }
+
+    // Prevenir path traversal
+    if path_str.contains("..") {
+        return Err(NoctraError::permission("Path traversal no permitido"));
+    }
+
+    // Validar que es un archivo regular
+    if path.exists() && !std::fs::metadata(path)?.is_file() {
+        return Err(NoctraError::permission(
+            "La ruta debe ser un archivo regular",
+        ));
+    }
+
+    Ok(())
+}
+
+/// Canonicalizar una ruta que puede no existir (se resuelve su directorio)
+///
+/// Un enlace simbólico como destino se rechaza: si está roto no se puede
+/// canonicalizar, pero escribir en él crearía el archivo al que apunta.
+fn canonicalize_target(path: &Path) -> Result<PathBuf> {
+    let unresolved =
+        || NoctraError::permission(format!("no se puede resolver la ruta {}", path.display()));
+    if path
+        .symlink_metadata()
+        .is_ok_and(|metadata| metadata.file_type().is_symlink())
+    {
+        return Err(NoctraError::permission(format!(
+            "{} es un enlace simbólico",
+            path.display()
+        )));
+    }
+    if let Ok(canonical) = path.canonicalize() {
+        return Ok(canonical);
+    }
+
+    let name = path.file_name().ok_or_else(unresolved)?;
+    let parent = match path.parent() {
+        Some(parent) if !parent.as_os_str().is_empty() => parent,
+        _ => Path::new("."),
+    };
+    parent
+        .canonicalize()
+        .map(|dir| dir.join(name))
+        .map_err(|_| unresolved())
+}
+
+/// Parte fija de un patrón glob (el directorio previo al primer comodín)
+fn glob_base(path: &Path) -> Option<&Path> {
+    let pattern = path.to_str()?;
+    let wildcard = pattern.find(['*', '?', '['])?;
+    let base = match pattern[..wildcard].rfind(std::path::is_separator) {
+        Some(0) => "/",
+        Some(separator) => &pattern[..separator],
+        None => ".",
+    };
+    Some(Path::new(base))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_blocklist_fallback() {
+        assert!(check_path("/etc/passwd", &[]).is_err());
+        assert!(check_path("datos/../../secreto.csv", &[]).is_err());
+
+        let dir = tempfile::tempdir().unwrap();
+        assert!(check_path(dir.path(), &[]).is_err());
+
+        let file = dir.path().join("datos.csv");
+        std::fs::write(&file, "id\n1\n").unwrap();
+        assert_eq!(check_path(&file, &[]).unwrap(), file);
+    }
+
+    #[test]
+    fn test_allowed_roots_normalize_relative_segments() {
+        let root = tempfile::tempdir().unwrap();
+        let roots = vec![root.path().to_path_buf()];
+        std::fs::create_dir(root.path().join("sub")).unwrap();
+        std::fs::write(root.path().join("datos.csv"), "id\n").unwrap();
+
+        let inside = root.path().join("sub").join("..").join("datos.csv");
+        assert_eq!(
+            check_path(&inside, &roots).unwrap(),
+            root.path().canonicalize().unwrap().join("datos.csv")
+        );
+
+        // Destino de EXPORT que todavía no existe
+        assert!(check_path(root.path().join("sub").join("salida.csv"), &roots).is_ok());
+        // Glob bajo la raíz
+        assert!(check_path(root.path().join("sub").join("*.parquet"), &roots).is_ok());
+
+        let outside = root.path().join("..").join("fuera.csv");
+        assert!(check_path(outside, &roots).is_err());
+        assert!(check_path("/etc/passwd", &roots).is_err());
+    }
+
+    #[test]
+    fn test_allowed_roots_reject_parent_dir_after_wildcard() {
+        let root = tempfile::tempdir().unwrap();
+        let roots = vec![root.path().to_path_buf()];
+        std::fs::create_dir(root.path().join("sub")).unwrap();
+
+        let escape = root.path().join("sub/*/../../../etc/*.csv");
+        assert!(matches!(
+            check_path(escape, &roots),
+            Err(NoctraError::Permission { .. })
+        ));
+        assert!(check_path(root.path().join("sub/*/datos.csv"), &roots).is_ok());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn test_allowed_roots_reject_symlink_escape() {
+        let root = tempfile::tempdir().unwrap();
+        let outside = tempfile::tempdir().unwrap();
+        let secret = outside.path().join("secreto.csv");
+        std::fs::write(&secret, "clave\n").unwrap();
+
+        let link = root.path().join("enlace.csv");
+        std::os::unix::fs::symlink(&secret, &link).unwrap();
+        let linked_dir = root.path().join("otro");
+        std::os::unix::fs::symlink(outside.path(), &linked_dir).unwrap();
+
+        let roots = vec![root.path().to_path_buf()];
+        assert!(check_path(&link, &roots).is_err());
+        assert!(check_path(linked_dir.join("nuevo.csv"), &roots).is_err());
+        assert!(check_path(linked_dir.join("*.csv"), &roots).is_err());
+
+        // Enlace roto: el EXPORT crearía el archivo fuera de la raíz
+        let dangling = root.path().join("salida.csv");
+        std::os::unix::fs::symlink(outside.path().join("nuevo.csv"), &dangling).unwrap();
+        assert!(check_path(&dangling, &roots).is_err());
+    }
+}
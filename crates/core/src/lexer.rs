@@ -0,0 +1,353 @@
+//! Tramos léxicos del SQL
+//!
+//! `segments` divide un texto SQL en código, literales, identificadores
+//! entre comillas, cadenas dollar-quoted y comentarios. Lo usan la división
+//! en sentencias (scripts y parser RQL), los placeholders y las variables
+//! `#nombre`, para que todos ignoren lo mismo; `statements` agrupa esos
+//! tramos en sentencias.
+
+use std::ops::Range;
+
+/// Tipo de un tramo del SQL
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum SegmentKind {
+    /// Código SQL fuera de literales y comentarios
+    Code,
+    /// Literal de texto `'...'`
+    String,
+    /// Identificador `"..."`
+    QuotedIdentifier,
+    /// Cadena `$$...$$` o `$tag$...$tag$`
+    DollarQuoted,
+    /// Comentario `-- ...`, sin el salto de línea final
+    LineComment,
+    /// Comentario `/* ... */`
+    BlockComment,
+}
+
+/// Tramo del SQL con su posición en bytes
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Segment {
+    /// Tipo del tramo
+    pub kind: SegmentKind,
+
+    /// Posición en el texto, delimitadores incluidos
+    pub range: Range<usize>,
+}
+
+/// Dividir `sql` en tramos consecutivos que lo cubren entero
+///
+/// Las comillas dobladas (`'it''s'`) quedan dentro del literal. Un literal o
+/// comentario sin cerrar llega hasta el final del texto.
+pub fn segments(sql: &str) -> Vec<Segment> {
+    let bytes = sql.as_bytes();
+    let mut segments = Vec::new();
+    let mut code_start = 0;
+    let mut pos = 0;
+
+    while pos < bytes.len() {
+        if !matches!(bytes[pos], b'\'' | b'"' | b'-' | b'/' | b'$') {
+            pos += 1;
+            continue;
+        }
+        // Los delimitadores son ASCII: `pos` cae en el inicio de un carácter
+        let rest = &sql[pos..];
+        let (kind, len) = match bytes[pos] {
+            b'\'' => (SegmentKind::String, quoted_len(rest, b'\'')),
+            b'"' => (SegmentKind::QuotedIdentifier, quoted_len(rest, b'"')),
+            b'-' if rest.starts_with("--") => (
+                SegmentKind::LineComment,
+                rest.find('\n').unwrap_or(rest.len()),
+            ),
+            b'/' if rest.starts_with("/*") => (
+                SegmentKind::BlockComment,
+                rest[2..].find("*/").map_or(rest.len(), |end| end + 4),
+            ),
+            b'$' if dollar_tag_len(rest).is_some() => {
+                let tag_len = dollar_tag_len(rest).unwrap_or(1);
+                let tag = &rest[..tag_len];
+                let len = rest[tag_len..]
+                    .find(tag)
+                    .map_or(rest.len(), |close| tag_len + close + tag_len);
+                (SegmentKind::DollarQuoted, len)
+            }
+            _ => {
+                pos += 1;
+                continue;
+            }
+        };
+
+        if code_start < pos {
+            segments.push(Segment {
+                kind: SegmentKind::Code,
+                range: code_start..pos,
+            });
+        }
+        segments.push(Segment {
+            kind,
+            range: pos..pos + len,
+        });
+        pos += len;
+        code_start = pos;
+    }
+    if code_start < sql.len() {
+        segments.push(Segment {
+            kind: SegmentKind::Code,
+            range: code_start..sql.len(),
+        });
+    }
+
+    segments
+}
+
+/// Tramos de código de `sql` (sin literales, identificadores ni comentarios)
+pub fn code_ranges(sql: &str) -> impl Iterator<Item = Range<usize>> {
+    segments(sql)
+        .into_iter()
+        .filter(|segment| segment.kind == SegmentKind::Code)
+        .map(|segment| segment.range)
+}
+
+/// Sentencia de `statements`: sus tramos y el `;` que la termina
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct StatementSegments {
+    /// Tramos de la sentencia, sin los comentarios `--` ni el `;` final
+    pub segments: Vec<Segment>,
+
+    /// Posición del `;` que la termina (la última puede no llevarlo)
+    pub terminator: Option<usize>,
+}
+
+/// Dividir `sql` en sentencias terminadas en `;`
+///
+/// Es la única división en sentencias: la usan los scripts
+/// (`script::split_statements`) y el parser RQL. Los `;` de literales y
+/// comentarios no cuentan, y los de código sólo si terminan la sentencia
+/// (ver `StatementEnd`). Las sentencias vacías (`;;`) también se devuelven.
+pub fn statements(sql: &str) -> Vec<StatementSegments> {
+    let mut statements = Vec::new();
+    let mut current = Vec::new();
+    let mut end = StatementEnd::default();
+
+    for segment in segments(sql) {
+        match segment.kind {
+            SegmentKind::Code => {
+                let mut start = segment.range.start;
+                for (offset, c) in sql[segment.range.clone()].char_indices() {
+                    if !end.is_end(c) {
+                        continue;
+                    }
+                    let pos = segment.range.start + offset;
+                    if start < pos {
+                        current.push(Segment {
+                            kind: SegmentKind::Code,
+                            range: start..pos,
+                        });
+                    }
+                    statements.push(StatementSegments {
+                        segments: std::mem::take(&mut current),
+                        terminator: Some(pos),
+                    });
+                    start = pos + 1;
+                }
+                if start < segment.range.end {
+                    current.push(Segment {
+                        kind: SegmentKind::Code,
+                        range: start..segment.range.end,
+                    });
+                }
+            }
+            SegmentKind::LineComment => end.break_word(),
+            _ => {
+                end.break_word();
+                current.push(segment);
+            }
+        }
+    }
+    if !current.is_empty() {
+        statements.push(StatementSegments {
+            segments: current,
+            terminator: None,
+        });
+    }
+
+    statements
+}
+
+/// Qué `;` de código terminan una sentencia
+///
+/// Los `;` dentro de paréntesis (`OPTIONS (delimiter=;)`) o del cuerpo
+/// `BEGIN ... END` de un `CREATE TRIGGER` no la terminan; dentro del cuerpo
+/// se cuentan los `CASE ... END` anidados. Se alimenta en orden con los
+/// caracteres de los tramos de código (`is_end`) y con `break_word` en los
+/// demás tramos, y vuelve al estado inicial en cada fin de sentencia.
+#[derive(Debug, Default)]
+struct StatementEnd {
+    parens: usize,
+    word: String,
+    words: usize,
+    create: bool,
+    trigger: bool,
+    blocks: usize,
+}
+
+impl StatementEnd {
+    /// Procesar un carácter de código; `true` si es el `;` que termina la sentencia
+    fn is_end(&mut self, c: char) -> bool {
+        if c.is_alphanumeric() || c == '_' {
+            self.word.push(c.to_ascii_uppercase());
+            return false;
+        }
+        self.break_word();
+        match c {
+            '(' => self.parens += 1,
+            ')' => self.parens = self.parens.saturating_sub(1),
+            ';' if self.parens == 0 && self.blocks == 0 => {
+                *self = Self::default();
+                return true;
+            }
+            _ => {}
+        }
+        false
+    }
+
+    /// Cerrar la palabra en curso (un literal o comentario la corta)
+    fn break_word(&mut self) {
+        if self.word.is_empty() {
+            return;
+        }
+        match self.word.as_str() {
+            "CREATE" if self.words == 0 => self.create = true,
+            "TRIGGER" if self.create => self.trigger = true,
+            "BEGIN" | "CASE" if self.trigger => self.blocks += 1,
+            "END" if self.trigger => self.blocks = self.blocks.saturating_sub(1),
+            _ => {}
+        }
+        self.words += 1;
+        self.word.clear();
+    }
+}
+
+/// Longitud de un literal que empieza por `quote`, comilla de cierre incluida
+fn quoted_len(text: &str, quote: u8) -> usize {
+    let bytes = text.as_bytes();
+    let mut pos = 1;
+    while pos < bytes.len() {
+        if bytes[pos] == quote {
+            // Comilla doblada: sigue el literal
+            if bytes.get(pos + 1) == Some(&quote) {
+                pos += 2;
+                continue;
+            }
+            return pos + 1;
+        }
+        pos += 1;
+    }
+    bytes.len()
+}
+
+/// Longitud de la etiqueta dollar-quoted (`$$`, `$tag$`) al inicio de `text`
+///
+/// `$1` y `$name` son parámetros, no etiquetas: la etiqueta no empieza por
+/// un dígito y tiene que cerrarse con otro `$`.
+pub fn dollar_tag_len(text: &str) -> Option<usize> {
+    let rest = text.strip_prefix('$')?;
+    let tag_len = rest
+        .find(|c: char| !(c.is_alphanumeric() || c == '_'))
+        .unwrap_or(rest.len());
+    let tag = &rest[..tag_len];
+    if tag.starts_with(|c: char| c.is_ascii_digit()) || !rest[tag_len..].starts_with('$') {
+        return None;
+    }
+    Some(tag_len + 2)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn kinds(sql: &str) -> Vec<(SegmentKind, &str)> {
+        segments(sql)
+            .into_iter()
+            .map(|segment| (segment.kind, &sql[segment.range]))
+            .collect()
+    }
+
+    #[test]
+    fn test_segments_cover_quotes_comments_and_dollar_strings() {
+        use SegmentKind::*;
+
+        assert_eq!(
+            kinds("SELECT 'it''s', \"a\"\"b\" -- don't\nFROM t /* :x */ WHERE $$ ? $$ = $1"),
+            vec![
+                (Code, "SELECT "),
+                (String, "'it''s'"),
+                (Code, ", "),
+                (QuotedIdentifier, "\"a\"\"b\""),
+                (Code, " "),
+                (LineComment, "-- don't"),
+                (Code, "\nFROM t "),
+                (BlockComment, "/* :x */"),
+                (Code, " WHERE "),
+                (DollarQuoted, "$$ ? $$"),
+                (Code, " = $1"),
+            ]
+        );
+        assert_eq!(
+            kinds("SELECT $fn$ a; b $fn$, 'ñ"),
+            vec![
+                (Code, "SELECT "),
+                (DollarQuoted, "$fn$ a; b $fn$"),
+                (Code, ", "),
+                (String, "'ñ"),
+            ]
+        );
+        assert!(segments("").is_empty());
+    }
+
+    #[test]
+    fn test_dollar_tag_len() {
+        assert_eq!(dollar_tag_len("$$ x $$"), Some(2));
+        assert_eq!(dollar_tag_len("$body$ x $body$"), Some(6));
+        assert_eq!(dollar_tag_len("$1"), None);
+        assert_eq!(dollar_tag_len("$name = 1"), None);
+    }
+
+    #[test]
+    fn test_statements_cut_code_at_terminators() {
+        let sql = "SELECT 'a;b'; -- fin;\nSELECT 2 /* ; */";
+        let statements = statements(sql);
+
+        assert_eq!(statements.len(), 2);
+        assert_eq!(statements[0].terminator, Some(12));
+        let texts: Vec<&str> = statements[1]
+            .segments
+            .iter()
+            .map(|segment| &sql[segment.range.clone()])
+            .collect();
+        assert_eq!(texts, vec![" ", "\nSELECT 2 ", "/* ; */"]);
+        assert_eq!(statements[1].terminator, None);
+    }
+
+    fn ends(code: &str) -> Vec<usize> {
+        let mut end = StatementEnd::default();
+        code.char_indices()
+            .filter(|&(_, c)| end.is_end(c))
+            .map(|(pos, _)| pos)
+            .collect()
+    }
+
+    #[test]
+    fn test_statement_end_skips_parens_and_trigger_bodies() {
+        assert_eq!(ends("SELECT 1; SELECT 2;"), vec![8, 18]);
+        assert_eq!(ends("USE x OPTIONS (delimiter=;); SELECT 1"), vec![27]);
+
+        let trigger = "CREATE TRIGGER t AFTER INSERT ON a BEGIN \
+                       UPDATE a SET n = CASE WHEN n > 0 THEN 1 ELSE 0 END; \
+                       INSERT INTO b VALUES (1); END; SELECT 1;";
+        let ends = ends(trigger);
+        assert_eq!(ends.len(), 2);
+        assert_eq!(&trigger[ends[0] - 4..=ends[0]], " END;");
+        assert_eq!(ends[1], trigger.len() - 1);
+    }
+}
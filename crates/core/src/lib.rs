@@ -3,14 +3,35 @@
 //! El núcleo del sistema Noctra que proporciona tipos base,
 //! execution engine y adaptadores de backend.
 
+pub mod assertion;
+pub mod bundle;
+pub mod catalog;
+pub mod changes;
+pub mod cursor;
 pub mod datasource;
 pub mod error;
 pub mod executor;
+#[cfg(feature = "xlsx")]
+pub mod export;
+mod expression;
+pub mod federation;
+pub mod fs_guard;
+pub mod lexer;
+pub mod metrics;
+pub mod query_engine;
+pub mod query_log;
+mod row_de;
+pub mod script;
 pub mod session;
 pub mod types;
 
+pub use catalog::{Catalog, CatalogEntry};
+pub use changes::{ChangeEvent, ChangeOp};
+pub use cursor::{CursorId, CursorPage};
 pub use datasource::{
-    ColumnInfo, CsvOptions, DataSource, SourceMetadata, SourceRegistry, SourceType, TableInfo,
+    register_source_factory, uri_scheme, ColumnInfo, CreatedSource, CsvOptions, DataSource,
+    SchemaColumn, SchemaEntry, SchemaTable, SourceFactory, SourceMetadata, SourceRegistry,
+    SourceType, TableInfo, WriteResult,
 };
 
 #[deprecated(since = "0.6.0", note = "Use noctra-duckdb instead")]
@@ -25,7 +46,25 @@ pub mod csv_backend {
     //! - Use `USE 'file.csv' AS alias` instead of manual CSV loading
     //! - DuckDB provides automatic type inference and better performance
 }
+pub use bundle::{BundleManifest, BundleTable};
 pub use error::{NoctraError, Result};
-pub use executor::{Backend, Executor, RqlQuery, SqliteBackend};
-pub use session::{Session, SessionManager};
-pub use types::{Column, ResultSet, Row, Value};
+pub use executor::{
+    Backend, Executor, ExecutorOptions, PlanNode, PoolStatus, QueryPlan, RqlQuery, SqliteBackend,
+    SqlitePool,
+};
+#[cfg(feature = "postgres")]
+pub use executor::{PostgresBackend, PostgresConfig};
+pub use federation::{FederatedPlan, FederatedScan};
+pub use metrics::{InMemoryRecorder, NoopRecorder, Recorder};
+pub use query_engine::{CostModel, QueryEngine, RoutingStrategy};
+pub use query_log::{
+    redact_error, redact_sql, JsonlQueryLogger, QueryLogEntry, QueryLogger, QueryOutcome,
+};
+pub use script::{
+    run_script, split_statements, ScriptOptions, ScriptStatement, StatementOutcome, StatementResult,
+};
+pub use session::{
+    FileSessionStore, Session, SessionConfig, SessionHandle, SessionHook, SessionManager,
+    SessionPersistence, SourceDescriptor,
+};
+pub use types::{Column, Parameters, ResultSet, Row, RowSink, RowStream, SortOrder, Value};
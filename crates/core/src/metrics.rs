@@ -0,0 +1,356 @@
+//! Métricas del executor
+//!
+//! `Executor` informa de cada consulta (también las de streams y cursores)
+//! a un `Recorder`: backend que la ejecuta (o tipo de la fuente activa),
+//! clase de sentencia (`metric_statement`), latencia y filas devueltas o
+//! error. Por defecto usa `NoopRecorder`, que no guarda nada; con
+//! `Executor::set_metrics_recorder` se instala otro, como
+//! `InMemoryRecorder`, que acumula contadores e histogramas de latencia por
+//! (backend, sentencia) y los exporta en el formato de texto de Prometheus
+//! (el servidor lo sirve en `/metrics` y el CLI lo resume con `:stats`).
+
+use crate::lexer::code_ranges;
+use serde::{Deserialize, Serialize};
+use std::collections::BTreeMap;
+use std::fmt::{self, Write as _};
+use std::sync::Mutex;
+use std::time::Duration;
+
+/// Límites superiores (en segundos) de los buckets de latencia
+pub const LATENCY_BUCKETS: [f64; 12] = [
+    0.001, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0,
+];
+
+/// Destino de las métricas de consultas
+pub trait Recorder: Send + Sync + fmt::Debug {
+    /// Consulta terminada con `rows` filas devueltas
+    fn record_query(&self, backend: &str, statement: &str, elapsed: Duration, rows: usize);
+
+    /// Consulta que terminó en error
+    fn record_error(&self, backend: &str, statement: &str, elapsed: Duration);
+}
+
+/// Recorder que descarta las métricas
+#[derive(Debug, Default, Clone, Copy)]
+pub struct NoopRecorder;
+
+impl Recorder for NoopRecorder {
+    fn record_query(&self, _backend: &str, _statement: &str, _elapsed: Duration, _rows: usize) {}
+
+    fn record_error(&self, _backend: &str, _statement: &str, _elapsed: Duration) {}
+}
+
+/// Métricas acumuladas de un (backend, tipo de sentencia)
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SeriesMetrics {
+    /// Backend o tipo de la fuente activa (`sqlite`, `postgresql`, `csv`...)
+    pub backend: String,
+
+    /// Clase de la sentencia (ver `metric_statement`)
+    pub statement: String,
+
+    /// Consultas ejecutadas, con error o sin él
+    pub queries: u64,
+
+    /// Consultas que terminaron en error
+    pub errors: u64,
+
+    /// Filas devueltas por las consultas correctas (las de streams y
+    /// cursores no se cuentan: se leen después de registrar la consulta)
+    pub rows: u64,
+
+    /// Consultas por bucket de `LATENCY_BUCKETS` (no acumulado); la última
+    /// posición cuenta las que superan el mayor límite
+    pub buckets: Vec<u64>,
+
+    /// Suma de latencias en segundos
+    pub latency_sum: f64,
+}
+
+impl SeriesMetrics {
+    fn new(backend: &str, statement: &str) -> Self {
+        Self {
+            backend: backend.to_string(),
+            statement: statement.to_string(),
+            queries: 0,
+            errors: 0,
+            rows: 0,
+            buckets: vec![0; LATENCY_BUCKETS.len() + 1],
+            latency_sum: 0.0,
+        }
+    }
+
+    fn observe(&mut self, elapsed: Duration) {
+        let seconds = elapsed.as_secs_f64();
+        let bucket = LATENCY_BUCKETS
+            .iter()
+            .position(|bound| seconds <= *bound)
+            .unwrap_or(LATENCY_BUCKETS.len());
+        self.buckets[bucket] += 1;
+        self.latency_sum += seconds;
+        self.queries += 1;
+    }
+
+    /// Latencia media; `None` sin consultas
+    pub fn mean_latency(&self) -> Option<Duration> {
+        (self.queries > 0).then(|| Duration::from_secs_f64(self.latency_sum / self.queries as f64))
+    }
+}
+
+/// Recorder que acumula las métricas en memoria
+#[derive(Debug, Default)]
+pub struct InMemoryRecorder {
+    series: Mutex<BTreeMap<(String, String), SeriesMetrics>>,
+}
+
+impl InMemoryRecorder {
+    /// Recorder vacío
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Copia de las métricas, ordenadas por backend y sentencia
+    pub fn snapshot(&self) -> Vec<SeriesMetrics> {
+        match self.series.lock() {
+            Ok(series) => series.values().cloned().collect(),
+            Err(_) => Vec::new(),
+        }
+    }
+
+    /// Volver a empezar de cero
+    pub fn reset(&self) {
+        if let Ok(mut series) = self.series.lock() {
+            series.clear();
+        }
+    }
+
+    /// Métricas en el formato de texto de Prometheus
+    pub fn render_prometheus(&self) -> String {
+        let series = self.snapshot();
+        let mut out = String::new();
+
+        write_counter(
+            &mut out,
+            "noctra_queries_total",
+            "Consultas ejecutadas",
+            &series,
+            |s| s.queries,
+        );
+        write_counter(
+            &mut out,
+            "noctra_query_errors_total",
+            "Consultas con error",
+            &series,
+            |s| s.errors,
+        );
+        write_counter(
+            &mut out,
+            "noctra_rows_returned_total",
+            "Filas devueltas",
+            &series,
+            |s| s.rows,
+        );
+
+        let name = "noctra_query_duration_seconds";
+        let _ = writeln!(out, "# HELP {} Latencia de las consultas", name);
+        let _ = writeln!(out, "# TYPE {} histogram", name);
+        for s in &series {
+            let labels = labels(s);
+            let mut cumulative = 0;
+            for (bound, count) in LATENCY_BUCKETS.iter().zip(&s.buckets) {
+                cumulative += count;
+                let _ = writeln!(
+                    out,
+                    "{}_bucket{{{},le=\"{}\"}} {}",
+                    name, labels, bound, cumulative
+                );
+            }
+            let _ = writeln!(
+                out,
+                "{}_bucket{{{},le=\"+Inf\"}} {}",
+                name, labels, s.queries
+            );
+            let _ = writeln!(out, "{}_sum{{{}}} {}", name, labels, s.latency_sum);
+            let _ = writeln!(out, "{}_count{{{}}} {}", name, labels, s.queries);
+        }
+        out
+    }
+
+    fn with_series(&self, backend: &str, statement: &str, f: impl FnOnce(&mut SeriesMetrics)) {
+        if let Ok(mut series) = self.series.lock() {
+            let entry = series
+                .entry((backend.to_string(), statement.to_string()))
+                .or_insert_with(|| SeriesMetrics::new(backend, statement));
+            f(entry);
+        }
+    }
+}
+
+impl Recorder for InMemoryRecorder {
+    fn record_query(&self, backend: &str, statement: &str, elapsed: Duration, rows: usize) {
+        self.with_series(backend, statement, |s| {
+            s.observe(elapsed);
+            s.rows += rows as u64;
+        });
+    }
+
+    fn record_error(&self, backend: &str, statement: &str, elapsed: Duration) {
+        self.with_series(backend, statement, |s| {
+            s.observe(elapsed);
+            s.errors += 1;
+        });
+    }
+}
+
+/// Escribir un contador Prometheus con un valor por serie
+fn write_counter(
+    out: &mut String,
+    name: &str,
+    help: &str,
+    series: &[SeriesMetrics],
+    value: impl Fn(&SeriesMetrics) -> u64,
+) {
+    let _ = writeln!(out, "# HELP {} {}", name, help);
+    let _ = writeln!(out, "# TYPE {} counter", name);
+    for s in series {
+        let _ = writeln!(out, "{}{{{}}} {}", name, labels(s), value(s));
+    }
+}
+
+/// Etiquetas Prometheus de una serie
+fn labels(series: &SeriesMetrics) -> String {
+    format!(
+        "backend=\"{}\",statement=\"{}\"",
+        escape_label(&series.backend),
+        escape_label(&series.statement)
+    )
+}
+
+fn escape_label(value: &str) -> String {
+    value
+        .replace('\\', "\\\\")
+        .replace('"', "\\\"")
+        .replace('\n', "\\n")
+}
+
+/// Tipo de sentencia para el log de consultas: su primera palabra clave
+pub fn statement_type(sql: &str) -> String {
+    let keyword = sql
+        .trim_start_matches(|c: char| c.is_whitespace() || c == '(')
+        .split(|c: char| !c.is_ascii_alphabetic())
+        .next()
+        .unwrap_or("");
+    if keyword.is_empty() {
+        "OTHER".to_string()
+    } else {
+        keyword.to_uppercase()
+    }
+}
+
+/// Clase de sentencia para las métricas: `select`, `insert`, `update`,
+/// `delete`, `ddl` u `other`
+///
+/// Es la etiqueta `statement` de Prometheus, que tiene que tomar pocos
+/// valores. En un `WITH` cuenta la sentencia que sigue a los CTE.
+pub fn metric_statement(sql: &str) -> &'static str {
+    let mut keyword = statement_type(sql);
+    if keyword == "WITH" {
+        keyword = with_body_keyword(sql).unwrap_or_default();
+    }
+    match keyword.as_str() {
+        "SELECT" | "VALUES" | "TABLE" | "FROM" => "select",
+        "INSERT" | "REPLACE" => "insert",
+        "UPDATE" => "update",
+        "DELETE" => "delete",
+        "CREATE" | "ALTER" | "DROP" | "TRUNCATE" => "ddl",
+        _ => "other",
+    }
+}
+
+/// Primera palabra clave fuera de paréntesis tras los CTE de un `WITH`
+fn with_body_keyword(sql: &str) -> Option<String> {
+    const BODY_KEYWORDS: [&str; 6] = ["SELECT", "VALUES", "INSERT", "REPLACE", "UPDATE", "DELETE"];
+    let mut depth = 0usize;
+    let mut word = String::new();
+    for range in code_ranges(sql) {
+        // Un literal o comentario corta la palabra
+        for c in sql[range].chars().chain(std::iter::once(' ')) {
+            if c.is_ascii_alphabetic() {
+                word.push(c.to_ascii_uppercase());
+                continue;
+            }
+            if depth == 0 && BODY_KEYWORDS.contains(&word.as_str()) {
+                return Some(word);
+            }
+            word.clear();
+            match c {
+                '(' => depth += 1,
+                ')' => depth = depth.saturating_sub(1),
+                _ => {}
+            }
+        }
+    }
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_in_memory_recorder_counts_and_buckets() {
+        let recorder = InMemoryRecorder::new();
+        recorder.record_query("sqlite", "SELECT", Duration::from_micros(500), 3);
+        recorder.record_query("sqlite", "SELECT", Duration::from_millis(30), 2);
+        recorder.record_error("sqlite", "SELECT", Duration::from_secs(20));
+        recorder.record_query("csv", "SELECT", Duration::from_millis(2), 7);
+
+        let series = recorder.snapshot();
+        assert_eq!(series.len(), 2);
+        assert_eq!(series[0].backend, "csv");
+        let sqlite = &series[1];
+        assert_eq!((sqlite.queries, sqlite.errors, sqlite.rows), (3, 1, 5));
+        assert_eq!(sqlite.buckets[0], 1);
+        assert_eq!(sqlite.buckets[4], 1);
+        assert_eq!(sqlite.buckets[LATENCY_BUCKETS.len()], 1);
+        assert_eq!(sqlite.buckets.iter().sum::<u64>(), 3);
+
+        let text = recorder.render_prometheus();
+        assert!(text.contains("noctra_queries_total{backend=\"sqlite\",statement=\"SELECT\"} 3"));
+        assert!(text.contains(
+            "noctra_query_duration_seconds_bucket{backend=\"sqlite\",statement=\"SELECT\",le=\"0.05\"} 2"
+        ));
+        assert!(text.contains(
+            "noctra_query_duration_seconds_bucket{backend=\"sqlite\",statement=\"SELECT\",le=\"+Inf\"} 3"
+        ));
+
+        recorder.reset();
+        assert!(recorder.snapshot().is_empty());
+    }
+
+    #[test]
+    fn test_statement_type() {
+        assert_eq!(statement_type("  select * from t"), "SELECT");
+        assert_eq!(statement_type("(SELECT 1)"), "SELECT");
+        assert_eq!(statement_type("insert into t values (1)"), "INSERT");
+        assert_eq!(statement_type(""), "OTHER");
+    }
+
+    #[test]
+    fn test_metric_statement() {
+        assert_eq!(metric_statement("select * from t"), "select");
+        assert_eq!(metric_statement("INSERT INTO t VALUES (1)"), "insert");
+        assert_eq!(metric_statement("update t set n = 1"), "update");
+        assert_eq!(metric_statement("DELETE FROM t"), "delete");
+        assert_eq!(metric_statement("CREATE TABLE t (n INTEGER)"), "ddl");
+        assert_eq!(metric_statement("PRAGMA table_info(t)"), "other");
+        assert_eq!(
+            metric_statement("WITH x AS (SELECT 1) INSERT INTO t SELECT * FROM x"),
+            "insert"
+        );
+        assert_eq!(
+            metric_statement("WITH RECURSIVE c(n) AS (VALUES (1)) SELECT n FROM c"),
+            "select"
+        );
+    }
+}
@@ -0,0 +1,738 @@
+//! Motor de consultas con enrutamiento entre múltiples backends
+//!
+//! `QueryEngine` mantiene varios backends con nombre y decide en cuál
+//! ejecutar cada query según una `RoutingStrategy`. La estrategia
+//! `CostBased` elige entre los backends que tienen las tablas de la query:
+//! envía las búsquedas puntuales y las tablas chicas al backend
+//! transaccional (SQLite) y los scans grandes al backend columnar (DuckDB),
+//! según las estadísticas cacheadas de cada tabla. Sin estadísticas recurre a
+//! un `CostModel` con tiempos históricos de ejecución.
+//!
+//! Un comentario `/*+ engine(duckdb) */` en la query fuerza el backend.
+
+use crate::error::{NoctraError, Result};
+use crate::executor::{Backend, QueryPlan};
+use crate::types::{Parameters, ResultSet};
+use std::collections::{HashMap, VecDeque};
+use std::sync::{Arc, Mutex};
+use std::time::{Duration, Instant};
+
+/// Máximo de observaciones guardadas por (tipo de query, backend)
+const MAX_OBSERVATIONS: usize = 100;
+
+/// Filas a partir de las cuales un scan se considera analítico
+pub const DEFAULT_ANALYTICAL_ROW_THRESHOLD: usize = 100_000;
+
+/// Feature que anuncian los backends columnares en `BackendInfo::features`
+pub const COLUMNAR_FEATURE: &str = "columnar";
+
+/// Estrategia de enrutamiento de queries
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum RoutingStrategy {
+    /// Usar siempre el primer backend registrado
+    #[default]
+    Primary,
+
+    /// Elegir backend según estadísticas de tablas y forma de la query;
+    /// sin estadísticas, según el historial de tiempos
+    CostBased,
+}
+
+/// Tipo de query, usado para agrupar observaciones de costo
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum QueryKind {
+    /// SELECT simple
+    Select,
+    /// Escritura (INSERT/UPDATE/DELETE)
+    Insert,
+    /// SELECT con JOIN
+    Join,
+    /// SELECT con agregaciones (GROUP BY, COUNT, SUM, ...)
+    Aggregate,
+    /// DDL u otros statements
+    Other,
+}
+
+/// Forma de una query: tipo y tamaño (variable de la regresión)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub struct QueryShape {
+    /// Tipo de query
+    pub kind: QueryKind,
+    /// Tamaño del SQL en bytes (aproximación de su complejidad)
+    pub size: usize,
+}
+
+impl QueryShape {
+    /// Clasificar una query SQL
+    pub fn of(sql: &str) -> Self {
+        let upper = sql.trim().to_uppercase();
+
+        let kind = if upper.starts_with("INSERT")
+            || upper.starts_with("UPDATE")
+            || upper.starts_with("DELETE")
+        {
+            QueryKind::Insert
+        } else if upper.starts_with("SELECT") || upper.starts_with("WITH") {
+            if upper.contains(" JOIN ") {
+                QueryKind::Join
+            } else if upper.contains("GROUP BY")
+                || ["COUNT(", "SUM(", "AVG(", "MIN(", "MAX("]
+                    .iter()
+                    .any(|agg| upper.contains(agg))
+            {
+                QueryKind::Aggregate
+            } else {
+                QueryKind::Select
+            }
+        } else {
+            QueryKind::Other
+        };
+
+        Self {
+            kind,
+            size: sql.trim().len(),
+        }
+    }
+
+    /// Verificar si la query modifica datos
+    pub fn is_write(&self) -> bool {
+        self.kind == QueryKind::Insert
+    }
+}
+
+/// Backend forzado con `/*+ engine(nombre) */`
+pub fn engine_hint(sql: &str) -> Option<String> {
+    let start = sql.find("/*+")? + 3;
+    let end = start + sql[start..].find("*/")?;
+    let hint = sql[start..end].trim();
+
+    let args = hint
+        .strip_prefix("engine")?
+        .trim_start()
+        .strip_prefix('(')?;
+    let name = args[..args.find(')')?].trim();
+    (!name.is_empty()).then(|| name.to_lowercase())
+}
+
+/// Verificar si la query es una búsqueda puntual (`WHERE col = valor [AND ...]`)
+fn is_point_lookup(sql: &str, shape: QueryShape) -> bool {
+    if shape.kind != QueryKind::Select {
+        return false;
+    }
+
+    let upper = sql.to_uppercase();
+    let Some(start) = upper.find(" WHERE ") else {
+        return false;
+    };
+    let clause = &upper[start + 7..];
+    let clause = ["GROUP BY", "ORDER BY", "LIMIT"]
+        .iter()
+        .filter_map(|kw| clause.find(kw))
+        .min()
+        .map_or(clause, |end| &clause[..end]);
+
+    clause.split(" AND ").all(|predicate| {
+        predicate.contains('=')
+            && !predicate.contains(['<', '>', '!'])
+            && ![" OR ", " LIKE ", " IN ", " BETWEEN ", "SELECT"]
+                .iter()
+                .any(|op| predicate.contains(op))
+    })
+}
+
+/// Modelo de costos con tiempos históricos por backend
+///
+/// Para cada (tipo de query, backend) ajusta una regresión lineal simple
+/// `duración = a + b * tamaño` sobre las últimas observaciones.
+#[derive(Debug, Default)]
+pub struct CostModel {
+    observations: HashMap<(QueryKind, String), VecDeque<(usize, Duration)>>,
+}
+
+impl CostModel {
+    /// Crear modelo vacío
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Registrar el tiempo real de ejecución de una query
+    pub fn record(&mut self, shape: QueryShape, backend: &str, actual: Duration) {
+        let samples = self
+            .observations
+            .entry((shape.kind, backend.to_string()))
+            .or_default();
+
+        if samples.len() == MAX_OBSERVATIONS {
+            samples.pop_front();
+        }
+        samples.push_back((shape.size, actual));
+    }
+
+    /// Estimar el costo de ejecutar `sql` en `backend`
+    ///
+    /// Sin observaciones previas el costo estimado es cero, de modo que los
+    /// backends nuevos se prueban antes de descartarlos.
+    pub fn estimate(&self, sql: &str, backend: &str) -> Duration {
+        let shape = QueryShape::of(sql);
+        self.observations
+            .get(&(shape.kind, backend.to_string()))
+            .map(|samples| predict(samples, shape.size))
+            .unwrap_or(Duration::ZERO)
+    }
+
+    /// Cantidad de observaciones para un tipo de query y backend
+    pub fn observation_count(&self, kind: QueryKind, backend: &str) -> usize {
+        self.observations
+            .get(&(kind, backend.to_string()))
+            .map_or(0, VecDeque::len)
+    }
+}
+
+/// Predecir duración por regresión lineal (o promedio si no hay varianza)
+fn predict(samples: &VecDeque<(usize, Duration)>, size: usize) -> Duration {
+    if samples.is_empty() {
+        return Duration::ZERO;
+    }
+
+    let n = samples.len() as f64;
+    let mean_x = samples.iter().map(|(x, _)| *x as f64).sum::<f64>() / n;
+    let mean_y = samples.iter().map(|(_, y)| y.as_secs_f64()).sum::<f64>() / n;
+
+    let variance: f64 = samples
+        .iter()
+        .map(|(x, _)| (*x as f64 - mean_x).powi(2))
+        .sum();
+
+    let predicted = if variance == 0.0 {
+        mean_y
+    } else {
+        let covariance: f64 = samples
+            .iter()
+            .map(|(x, y)| (*x as f64 - mean_x) * (y.as_secs_f64() - mean_y))
+            .sum();
+        let slope = covariance / variance;
+        mean_y + slope * (size as f64 - mean_x)
+    };
+
+    Duration::from_secs_f64(predicted.max(0.0))
+}
+
+/// Motor de consultas multi-backend
+#[derive(Debug)]
+pub struct QueryEngine {
+    /// Backends registrados (nombre, backend), en orden de registro
+    backends: Vec<(String, Arc<dyn Backend>)>,
+
+    /// Estrategia de enrutamiento
+    strategy: RoutingStrategy,
+
+    /// Modelo de costos compartido entre requests concurrentes
+    cost_model: Arc<Mutex<CostModel>>,
+
+    /// Filas estimadas a partir de las cuales se prefiere el backend columnar
+    analytical_row_threshold: usize,
+}
+
+impl QueryEngine {
+    /// Crear motor sin backends
+    pub fn new(strategy: RoutingStrategy) -> Self {
+        Self {
+            backends: Vec::new(),
+            strategy,
+            cost_model: Arc::new(Mutex::new(CostModel::new())),
+            analytical_row_threshold: DEFAULT_ANALYTICAL_ROW_THRESHOLD,
+        }
+    }
+
+    /// Cambiar el umbral de filas para considerar analítico un scan
+    pub fn with_analytical_row_threshold(mut self, rows: usize) -> Self {
+        self.analytical_row_threshold = rows;
+        self
+    }
+
+    /// Usar un modelo de costos existente (p. ej. compartido con otro motor)
+    pub fn with_cost_model(mut self, cost_model: Arc<Mutex<CostModel>>) -> Self {
+        self.cost_model = cost_model;
+        self
+    }
+
+    /// Registrar backend con nombre
+    pub fn add_backend<T: Into<String>>(&mut self, name: T, backend: Arc<dyn Backend>) {
+        self.backends.push((name.into(), backend));
+    }
+
+    /// Obtener estrategia de enrutamiento
+    pub fn strategy(&self) -> RoutingStrategy {
+        self.strategy
+    }
+
+    /// Cambiar estrategia de enrutamiento
+    pub fn set_strategy(&mut self, strategy: RoutingStrategy) {
+        self.strategy = strategy;
+    }
+
+    /// Obtener modelo de costos compartido
+    pub fn cost_model(&self) -> Arc<Mutex<CostModel>> {
+        Arc::clone(&self.cost_model)
+    }
+
+    /// Elegir backend para una query según la estrategia
+    ///
+    /// Un hint `/*+ engine(nombre) */` con un backend registrado tiene
+    /// prioridad sobre la estrategia.
+    pub fn select_backend(&self, sql: &str) -> Result<&str> {
+        let (first, _) = self
+            .backends
+            .first()
+            .ok_or_else(|| NoctraError::Configuration("No hay backends registrados".to_string()))?;
+
+        if let Some(hint) = engine_hint(sql) {
+            match self
+                .backends
+                .iter()
+                .find(|(name, _)| name.eq_ignore_ascii_case(&hint))
+            {
+                Some((name, _)) => {
+                    log::debug!("Query enrutada a '{}' (hint)", name);
+                    return Ok(name);
+                }
+                None => log::warn!("Hint engine({}) ignorado: backend no registrado", hint),
+            }
+        }
+
+        let (name, reason) = match self.strategy {
+            RoutingStrategy::Primary => (first.as_str(), "backend primario"),
+            RoutingStrategy::CostBased => self.cost_based_choice(sql)?,
+        };
+        log::debug!("Query enrutada a '{}' ({})", name, reason);
+        Ok(name)
+    }
+
+    /// Elección de `CostBased` y su motivo
+    ///
+    /// Sólo compiten los backends que tienen todas las tablas de la query
+    /// (ver `has_table`); si ninguno las tiene todas, o la query no nombra
+    /// tablas, compiten todos.
+    fn cost_based_choice(&self, sql: &str) -> Result<(&str, &'static str)> {
+        let shape = QueryShape::of(sql);
+        let first = self.backends[0].0.as_str();
+
+        if shape.is_write() {
+            return Ok((first, "escritura en backend primario"));
+        }
+
+        let tables = QueryPlan::from_sql(sql).referenced_tables;
+        let holding: Vec<&(String, Arc<dyn Backend>)> = self
+            .backends
+            .iter()
+            .filter(|(_, backend)| {
+                tables
+                    .iter()
+                    .all(|table| has_table(backend.as_ref(), table))
+            })
+            .collect();
+        let candidates: Vec<&(String, Arc<dyn Backend>)> = if holding.is_empty() {
+            self.backends.iter().collect()
+        } else {
+            holding
+        };
+        if let [(name, _)] = candidates.as_slice() {
+            return Ok((name, "único backend candidato"));
+        }
+
+        let columnar = candidates
+            .iter()
+            .find(|(_, backend)| is_columnar(backend.as_ref()));
+        let transactional = candidates
+            .iter()
+            .find(|(_, backend)| !is_columnar(backend.as_ref()));
+
+        if let (Some((columnar, _)), Some((transactional, _))) = (columnar, transactional) {
+            if is_point_lookup(sql, shape) {
+                return Ok((transactional, "búsqueda puntual"));
+            }
+            if let Some(rows) = self.estimate_rows(&tables) {
+                return Ok(if rows >= self.analytical_row_threshold {
+                    (columnar, "scan analítico sobre tablas grandes")
+                } else {
+                    (transactional, "tablas chicas")
+                });
+            }
+        }
+
+        let model = self.lock_cost_model()?;
+        let (name, _) = candidates
+            .iter()
+            .min_by_key(|(name, _)| model.estimate(sql, name))
+            .ok_or_else(|| NoctraError::Configuration("No hay backends registrados".to_string()))?;
+        Ok((name, "historial de tiempos"))
+    }
+
+    /// Filas de las tablas referenciadas según las estadísticas cacheadas
+    ///
+    /// None si ningún backend conoce alguna de las tablas.
+    fn estimate_rows(&self, tables: &[String]) -> Option<usize> {
+        let counts: Vec<usize> = tables
+            .iter()
+            .filter_map(|table| {
+                self.backends
+                    .iter()
+                    .filter_map(|(_, backend)| backend.table_row_count(table))
+                    .max()
+            })
+            .collect();
+
+        (!counts.is_empty()).then(|| counts.iter().sum())
+    }
+
+    /// Ejecutar query en el backend elegido
+    ///
+    /// Con `CostBased` el tiempo real de ejecución se registra en el modelo.
+    pub fn execute(&self, sql: &str, parameters: &Parameters) -> Result<ResultSet> {
+        let name = self.select_backend(sql)?;
+        let backend = self
+            .backends
+            .iter()
+            .find(|(candidate, _)| candidate == name)
+            .map(|(_, backend)| Arc::clone(backend))
+            .ok_or_else(|| NoctraError::not_found("backend", name))?;
+
+        let shape = QueryShape::of(sql);
+        let start = Instant::now();
+        let result = if shape.is_write() {
+            backend.execute_statement(sql, parameters)
+        } else {
+            backend.execute_query(sql, parameters)
+        };
+
+        if self.strategy == RoutingStrategy::CostBased && result.is_ok() {
+            self.lock_cost_model()?.record(shape, name, start.elapsed());
+        }
+
+        result
+    }
+
+    fn lock_cost_model(&self) -> Result<std::sync::MutexGuard<'_, CostModel>> {
+        self.cost_model
+            .lock()
+            .map_err(|_| NoctraError::Internal("Modelo de costos envenenado".to_string()))
+    }
+}
+
+/// Verificar si un backend tiene una tabla
+///
+/// Primero mira las estadísticas cacheadas y, si no las hay, el esquema de
+/// la base.
+fn has_table(backend: &dyn Backend, table: &str) -> bool {
+    backend.table_row_count(table).is_some()
+        || backend.database_schema().is_ok_and(|tables| {
+            tables
+                .iter()
+                .any(|info| info.name.eq_ignore_ascii_case(table))
+        })
+}
+
+/// Verificar si un backend es columnar (apto para scans analíticos)
+fn is_columnar(backend: &dyn Backend) -> bool {
+    backend
+        .backend_info()
+        .features
+        .iter()
+        .any(|feature| feature == COLUMNAR_FEATURE)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::executor::BackendInfo;
+
+    /// Backend de prueba que no ejecuta nada
+    #[derive(Debug)]
+    struct NullBackend;
+
+    impl Backend for NullBackend {
+        fn execute_query(&self, _sql: &str, _parameters: &Parameters) -> Result<ResultSet> {
+            Ok(ResultSet::empty())
+        }
+
+        fn execute_statement(&self, _sql: &str, _parameters: &Parameters) -> Result<ResultSet> {
+            Ok(ResultSet::empty())
+        }
+
+        fn ping(&self) -> Result<()> {
+            Ok(())
+        }
+
+        fn backend_info(&self) -> BackendInfo {
+            BackendInfo {
+                name: "null".to_string(),
+                version: "0".to_string(),
+                url: "null://".to_string(),
+                features: Vec::new(),
+            }
+        }
+    }
+
+    fn two_backend_engine(strategy: RoutingStrategy) -> QueryEngine {
+        let mut engine = QueryEngine::new(strategy);
+        engine.add_backend("sqlite", Arc::new(NullBackend));
+        engine.add_backend("duckdb", Arc::new(NullBackend));
+        engine
+    }
+
+    #[test]
+    fn test_query_shape_classification() {
+        assert_eq!(QueryShape::of("SELECT * FROM t").kind, QueryKind::Select);
+        assert_eq!(
+            QueryShape::of("SELECT * FROM a JOIN b ON a.id = b.id").kind,
+            QueryKind::Join
+        );
+        assert_eq!(
+            QueryShape::of("select dept, count(*) from emp group by dept").kind,
+            QueryKind::Aggregate
+        );
+        assert_eq!(
+            QueryShape::of("INSERT INTO t VALUES (1)").kind,
+            QueryKind::Insert
+        );
+        assert_eq!(
+            QueryShape::of("CREATE TABLE t (id INT)").kind,
+            QueryKind::Other
+        );
+    }
+
+    #[test]
+    fn test_cost_model_linear_estimate() {
+        let mut model = CostModel::new();
+        let shape = |size| QueryShape {
+            kind: QueryKind::Select,
+            size,
+        };
+        model.record(shape(100), "sqlite", Duration::from_millis(10));
+        model.record(shape(200), "sqlite", Duration::from_millis(20));
+        model.record(shape(300), "sqlite", Duration::from_millis(30));
+
+        let sql = format!("SELECT {}", "x".repeat(393)); // 400 bytes
+        let estimate = model.estimate(&sql, "sqlite");
+        assert!((estimate.as_secs_f64() - 0.040).abs() < 1e-6);
+
+        assert_eq!(model.estimate(&sql, "duckdb"), Duration::ZERO);
+    }
+
+    #[test]
+    fn test_cost_based_routing_picks_faster_backend() {
+        let engine = two_backend_engine(RoutingStrategy::CostBased);
+        let join = QueryShape::of("SELECT * FROM a JOIN b ON a.id = b.id");
+        let agg = QueryShape::of("SELECT COUNT(*) FROM events");
+        {
+            let model = engine.cost_model();
+            let mut model = model.lock().unwrap();
+            for _ in 0..5 {
+                model.record(join, "sqlite", Duration::from_millis(120));
+                model.record(join, "duckdb", Duration::from_millis(15));
+                model.record(agg, "sqlite", Duration::from_millis(2));
+                model.record(agg, "duckdb", Duration::from_millis(9));
+            }
+        }
+
+        assert_eq!(
+            engine
+                .select_backend("SELECT * FROM a JOIN b ON a.id = b.id")
+                .unwrap(),
+            "duckdb"
+        );
+        assert_eq!(
+            engine
+                .select_backend("SELECT COUNT(*) FROM events")
+                .unwrap(),
+            "sqlite"
+        );
+
+        // Primary ignora el modelo
+        let primary = two_backend_engine(RoutingStrategy::Primary);
+        assert_eq!(
+            primary
+                .select_backend("SELECT * FROM a JOIN b ON a.id = b.id")
+                .unwrap(),
+            "sqlite"
+        );
+    }
+
+    #[test]
+    fn test_execute_records_observations() {
+        let engine = two_backend_engine(RoutingStrategy::CostBased);
+        engine
+            .execute("SELECT * FROM t", &Parameters::new())
+            .unwrap();
+
+        let model = engine.cost_model();
+        let model = model.lock().unwrap();
+        let total = model.observation_count(QueryKind::Select, "sqlite")
+            + model.observation_count(QueryKind::Select, "duckdb");
+        assert_eq!(total, 1);
+    }
+
+    /// Backend de prueba con estadísticas fijas
+    #[derive(Debug)]
+    struct StatsBackend {
+        columnar: bool,
+        rows: HashMap<String, usize>,
+    }
+
+    impl Backend for StatsBackend {
+        fn execute_query(&self, _sql: &str, _parameters: &Parameters) -> Result<ResultSet> {
+            Ok(ResultSet::empty())
+        }
+
+        fn execute_statement(&self, _sql: &str, _parameters: &Parameters) -> Result<ResultSet> {
+            Ok(ResultSet::empty())
+        }
+
+        fn ping(&self) -> Result<()> {
+            Ok(())
+        }
+
+        fn backend_info(&self) -> BackendInfo {
+            BackendInfo {
+                name: "stats".to_string(),
+                version: "0".to_string(),
+                url: "stats://".to_string(),
+                features: if self.columnar {
+                    vec![COLUMNAR_FEATURE.to_string()]
+                } else {
+                    Vec::new()
+                },
+            }
+        }
+
+        fn table_row_count(&self, table: &str) -> Option<usize> {
+            self.rows.get(table).copied()
+        }
+    }
+
+    fn stats_engine() -> QueryEngine {
+        let mut engine = QueryEngine::new(RoutingStrategy::CostBased);
+        engine.add_backend(
+            "sqlite",
+            Arc::new(StatsBackend {
+                columnar: false,
+                rows: HashMap::from([("users".to_string(), 500), ("logs".to_string(), 2_000_000)]),
+            }),
+        );
+        engine.add_backend(
+            "duckdb",
+            Arc::new(StatsBackend {
+                columnar: true,
+                rows: HashMap::from([
+                    ("events".to_string(), 2_000_000),
+                    ("logs".to_string(), 2_000_000),
+                ]),
+            }),
+        );
+        engine
+    }
+
+    #[test]
+    fn test_cost_based_routing_uses_table_statistics() {
+        let engine = stats_engine();
+
+        assert_eq!(
+            engine
+                .select_backend("SELECT kind, COUNT(*) FROM logs GROUP BY kind")
+                .unwrap(),
+            "duckdb"
+        );
+        assert_eq!(
+            engine.select_backend("SELECT * FROM users").unwrap(),
+            "sqlite"
+        );
+        // Búsqueda puntual aunque la tabla sea grande
+        assert_eq!(
+            engine
+                .select_backend("SELECT * FROM logs WHERE id = 42")
+                .unwrap(),
+            "sqlite"
+        );
+        assert_eq!(
+            engine
+                .select_backend("SELECT * FROM logs WHERE id > 42")
+                .unwrap(),
+            "duckdb"
+        );
+
+        let engine = stats_engine().with_analytical_row_threshold(usize::MAX);
+        assert_eq!(
+            engine
+                .select_backend("SELECT kind, COUNT(*) FROM logs GROUP BY kind")
+                .unwrap(),
+            "sqlite"
+        );
+    }
+
+    #[test]
+    fn test_cost_based_routing_only_uses_backends_with_the_tables() {
+        let engine = stats_engine();
+
+        // events sólo está en DuckDB: ni una búsqueda puntual va a SQLite
+        assert_eq!(
+            engine
+                .select_backend("SELECT * FROM events WHERE id = 42")
+                .unwrap(),
+            "duckdb"
+        );
+        assert_eq!(
+            engine
+                .select_backend("SELECT * FROM users WHERE id = 42")
+                .unwrap(),
+            "sqlite"
+        );
+        // Con el umbral al máximo, una tabla sólo columnar sigue en DuckDB
+        let engine = stats_engine().with_analytical_row_threshold(usize::MAX);
+        assert_eq!(
+            engine
+                .select_backend("SELECT kind, COUNT(*) FROM events GROUP BY kind")
+                .unwrap(),
+            "duckdb"
+        );
+        // Ningún backend tiene todas las tablas: compiten todos
+        assert_eq!(
+            engine
+                .select_backend("SELECT * FROM users JOIN events ON users.id = events.user_id")
+                .unwrap(),
+            "sqlite"
+        );
+    }
+
+    #[test]
+    fn test_engine_hint_overrides_strategy() {
+        assert_eq!(
+            engine_hint("SELECT /*+ engine(DuckDB) */ * FROM t"),
+            Some("duckdb".to_string())
+        );
+        assert_eq!(engine_hint("SELECT /* engine(duckdb) */ 1"), None);
+        assert_eq!(engine_hint("SELECT /*+ parallel(4) */ 1"), None);
+
+        let mut engine = stats_engine();
+        engine.set_strategy(RoutingStrategy::Primary);
+        assert_eq!(engine.strategy(), RoutingStrategy::Primary);
+        assert_eq!(
+            engine
+                .select_backend("/*+ engine(duckdb) */ SELECT * FROM users WHERE id = 1")
+                .unwrap(),
+            "duckdb"
+        );
+        // Un backend desconocido en el hint se ignora
+        assert_eq!(
+            engine
+                .select_backend("/*+ engine(postgres) */ SELECT * FROM users")
+                .unwrap(),
+            "sqlite"
+        );
+    }
+
+    #[test]
+    fn test_no_backends() {
+        let engine = QueryEngine::new(RoutingStrategy::CostBased);
+        assert!(engine.select_backend("SELECT 1").is_err());
+    }
+}
@@ -0,0 +1,322 @@
+//! Registro de consultas para auditoría
+//!
+//! Con `Executor::set_query_logger` cada sentencia ejecutada se informa a un
+//! `QueryLogger` como una `QueryLogEntry`: fecha, sesión, SQL, duración,
+//! filas y resultado. El SQL se guarda redactado con `redact_sql`, que
+//! sustituye los literales de texto y números por `?`; el mensaje de error
+//! pasa por `redact_error`, porque los backends suelen repetir en él los
+//! valores de la consulta. `JsonlQueryLogger` añade una línea JSON por
+//! consulta a un archivo.
+//!
+//! Un fallo del logger nunca hace fallar la consulta: el executor sólo lo
+//! anota con `log::warn!`.
+
+use crate::error::{NoctraError, Result};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sqlparser::ast::{visit_expressions_mut, Expr, Value};
+use sqlparser::dialect::GenericDialect;
+use sqlparser::tokenizer::{Token, Tokenizer};
+use std::fmt;
+use std::fs::{File, OpenOptions};
+use std::io::Write;
+use std::ops::ControlFlow;
+use std::path::{Path, PathBuf};
+use std::sync::Mutex;
+
+/// SQL registrado cuando ni siquiera se puede tokenizar
+pub const REDACTED_SQL: &str = "<sql redactado>";
+
+/// Resultado de una consulta registrada
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum QueryOutcome {
+    /// Terminó correctamente
+    Ok,
+    /// Terminó con error
+    Error,
+}
+
+/// Una consulta ejecutada, tal como se registra
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct QueryLogEntry {
+    /// Momento en que terminó la consulta
+    pub timestamp: DateTime<Utc>,
+
+    /// ID de la sesión que la ejecutó
+    pub session_id: String,
+
+    /// SQL con los literales redactados
+    pub sql: String,
+
+    /// Primera palabra clave de la sentencia (`SELECT`, `INSERT`...)
+    pub statement: String,
+
+    /// Duración en milisegundos
+    pub duration_ms: f64,
+
+    /// Filas devueltas o afectadas (`None` si falló o si el resultado es un
+    /// stream, cuyas filas aún no se han leído)
+    pub rows: Option<u64>,
+
+    /// Resultado
+    pub outcome: QueryOutcome,
+
+    /// Mensaje del error redactado con `redact_error`, si lo hubo
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub error: Option<String>,
+}
+
+/// Destino del registro de consultas
+pub trait QueryLogger: Send + Sync + fmt::Debug {
+    /// Registrar una consulta terminada
+    fn log(&self, entry: &QueryLogEntry) -> Result<()>;
+}
+
+/// Logger que añade cada consulta como una línea JSON a un archivo
+#[derive(Debug)]
+pub struct JsonlQueryLogger {
+    path: PathBuf,
+    file: Mutex<File>,
+}
+
+impl JsonlQueryLogger {
+    /// Abrir (o crear) `path` para añadir entradas al final
+    pub fn open<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref().to_path_buf();
+        let file = OpenOptions::new()
+            .create(true)
+            .append(true)
+            .open(&path)
+            .map_err(|e| {
+                NoctraError::Io(format!(
+                    "No se pudo abrir el registro de consultas '{}': {}",
+                    path.display(),
+                    e
+                ))
+            })?;
+        Ok(Self {
+            path,
+            file: Mutex::new(file),
+        })
+    }
+
+    /// Archivo del registro
+    pub fn path(&self) -> &Path {
+        &self.path
+    }
+}
+
+impl QueryLogger for JsonlQueryLogger {
+    fn log(&self, entry: &QueryLogEntry) -> Result<()> {
+        let mut line = serde_json::to_string(entry)
+            .map_err(|e| NoctraError::Internal(format!("Entrada de registro inválida: {}", e)))?;
+        line.push('\n');
+        let mut file = self
+            .file
+            .lock()
+            .map_err(|_| NoctraError::Internal("Registro de consultas bloqueado".to_string()))?;
+        file.write_all(line.as_bytes())
+            .and_then(|_| file.flush())
+            .map_err(|e| NoctraError::Io(e.to_string()))
+    }
+}
+
+/// SQL con los literales de texto y números sustituidos por `?`
+///
+/// Se analiza con sqlparser y se vuelve a escribir desde el AST; si no se
+/// puede analizar (SQL propio de un backend, NQL...) se redactan los tokens
+/// literales, y si tampoco se puede tokenizar se devuelve `REDACTED_SQL`.
+pub fn redact_sql(sql: &str) -> String {
+    let dialect = GenericDialect {};
+    if let Ok(mut statements) = sqlparser::parser::Parser::parse_sql(&dialect, sql) {
+        let _ = visit_expressions_mut(&mut statements, |expr| {
+            match expr {
+                Expr::Value(value) if is_literal(value) => {
+                    *value = Value::Placeholder("?".to_string());
+                }
+                Expr::TypedString { .. } => {
+                    *expr = Expr::Value(Value::Placeholder("?".to_string()));
+                }
+                _ => {}
+            }
+            ControlFlow::<()>::Continue(())
+        });
+        return statements
+            .iter()
+            .map(|statement| statement.to_string())
+            .collect::<Vec<_>>()
+            .join("; ");
+    }
+
+    match Tokenizer::new(&dialect, sql).tokenize() {
+        Ok(tokens) => tokens
+            .into_iter()
+            .map(|token| match token {
+                Token::Number(..)
+                | Token::SingleQuotedString(_)
+                | Token::DollarQuotedString(_)
+                | Token::SingleQuotedByteStringLiteral(_)
+                | Token::DoubleQuotedByteStringLiteral(_)
+                | Token::RawStringLiteral(_)
+                | Token::NationalStringLiteral(_)
+                | Token::EscapedStringLiteral(_)
+                | Token::HexStringLiteral(_) => "?".to_string(),
+                token => token.to_string(),
+            })
+            .collect(),
+        Err(_) => REDACTED_SQL.to_string(),
+    }
+}
+
+/// Mensaje de un error con los valores entre comillas y los números
+/// sustituidos por `?`
+///
+/// Los backends repiten en sus mensajes los datos de la consulta (DuckDB:
+/// "Could not convert string 'x' to INT32", claves duplicadas, `CHECK`
+/// con su literal...). Los errores que sólo nombran objetos o parámetros
+/// se conservan tal cual.
+pub fn redact_error(error: &NoctraError) -> String {
+    match error {
+        NoctraError::NotFound { .. }
+        | NoctraError::ParameterNotFound(_)
+        | NoctraError::MissingParameter(_)
+        | NoctraError::SessionVariableNotFound(_)
+        | NoctraError::UnsupportedFeature { .. }
+        | NoctraError::Timeout { .. } => error.to_string(),
+        _ => redact_message(&error.to_string()),
+    }
+}
+
+/// Texto con lo entrecomillado (`'...'`, `"..."`) y los números por `?`
+///
+/// Una comilla sin cerrar redacta el resto del mensaje.
+fn redact_message(message: &str) -> String {
+    let mut redacted = String::with_capacity(message.len());
+    let mut chars = message.chars().peekable();
+    let mut previous: Option<char> = None;
+    while let Some(c) = chars.next() {
+        match c {
+            '\'' | '"' => {
+                while let Some(next) = chars.next() {
+                    if next == c {
+                        // Comilla doblada: sigue el literal
+                        if chars.peek() == Some(&c) {
+                            chars.next();
+                            continue;
+                        }
+                        break;
+                    }
+                }
+                redacted.push('?');
+            }
+            c if c.is_ascii_digit()
+                && !previous.is_some_and(|p| p.is_alphanumeric() || p == '_') =>
+            {
+                while chars
+                    .peek()
+                    .is_some_and(|next| next.is_alphanumeric() || *next == '.' || *next == '_')
+                {
+                    chars.next();
+                }
+                redacted.push('?');
+            }
+            c => redacted.push(c),
+        }
+        previous = Some(c);
+    }
+    redacted
+}
+
+/// Valores que pueden contener datos (no `NULL`, booleanos ni placeholders)
+fn is_literal(value: &Value) -> bool {
+    !matches!(
+        value,
+        Value::Boolean(_) | Value::Null | Value::Placeholder(_)
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_redact_sql_replaces_literals() {
+        let sql =
+            "SELECT name FROM users WHERE email = 'ana@example.com' AND age > 30 AND active = true";
+        let redacted = redact_sql(sql);
+        assert_eq!(
+            redacted,
+            "SELECT name FROM users WHERE email = ? AND age > ? AND active = true"
+        );
+
+        let redacted = redact_sql("INSERT INTO t (a, b) VALUES ('secreto', 42)");
+        assert!(!redacted.contains("secreto"));
+        assert!(!redacted.contains("42"));
+
+        // Sin AST se redactan los tokens
+        let redacted = redact_sql("PIVOT ventas ON mes USING sum(total) WHERE id = 'x-77'");
+        assert!(!redacted.contains("x-77"));
+        assert!(redacted.contains("PIVOT ventas"));
+
+        assert_eq!(redact_sql("SELECT 'sin cerrar"), REDACTED_SQL);
+    }
+
+    #[test]
+    fn test_redact_error_hides_values() {
+        let error = NoctraError::backend(
+            "Conversion",
+            "Could not convert string 'ana@example.com' to INT32",
+        );
+        assert_eq!(
+            redact_error(&error),
+            "Error de backend [Conversion]: Could not convert string ? to INT32"
+        );
+
+        let error = NoctraError::Conflict(
+            "Duplicate key \"id: 5\" violates primary key constraint".to_string(),
+        );
+        assert!(!redact_error(&error).contains('5'));
+
+        let error = NoctraError::Conflict("CHECK constraint failed: pin <> 1234".to_string());
+        assert_eq!(
+            redact_error(&error),
+            "Conflicto: CHECK constraint failed: pin <> ?"
+        );
+
+        // Sin cerrar: se redacta el resto
+        let error = NoctraError::SqlExecution("valor 'it''s secreto".to_string());
+        assert_eq!(redact_error(&error), "Error de ejecución SQL: valor ?");
+
+        // Los nombres de objetos se conservan
+        let error = NoctraError::not_found("tabla", "clientes_2024");
+        assert_eq!(redact_error(&error), error.to_string());
+    }
+
+    #[test]
+    fn test_jsonl_logger_appends_lines() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("queries.jsonl");
+        let logger = JsonlQueryLogger::open(&path).unwrap();
+        let entry = QueryLogEntry {
+            timestamp: Utc::now(),
+            session_id: "s1".to_string(),
+            sql: redact_sql("SELECT 1"),
+            statement: "SELECT".to_string(),
+            duration_ms: 1.5,
+            rows: Some(1),
+            outcome: QueryOutcome::Ok,
+            error: None,
+        };
+        logger.log(&entry).unwrap();
+        logger.log(&entry).unwrap();
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        let lines: Vec<_> = content.lines().collect();
+        assert_eq!(lines.len(), 2);
+        let parsed: QueryLogEntry = serde_json::from_str(lines[0]).unwrap();
+        assert_eq!(parsed.sql, "SELECT ?");
+        assert_eq!(parsed.outcome, QueryOutcome::Ok);
+        assert!(!lines[0].contains("\"error\""));
+    }
+}
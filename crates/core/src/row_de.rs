@@ -0,0 +1,287 @@
+//! Deserialización de filas a tipos propios con serde
+//!
+//! Las columnas se asignan a los campos por nombre. `NULL` se convierte en
+//! `None` (los campos `Option` ausentes también), los enteros se amplían a
+//! flotante y los booleanos de SQLite (`0`/`1`) se aceptan como `bool`. Las
+//! fechas se entregan en el formato que espera chrono (RFC 3339 para
+//! `DateTime<Utc>`).
+
+use crate::error::NoctraError;
+use crate::types::{Column, Row, Value};
+use serde::de::{self, DeserializeSeed, IntoDeserializer, MapAccess, SeqAccess, Visitor};
+use serde::forward_to_deserialize_any;
+use std::fmt;
+
+/// Error interno de deserialización (se convierte en `NoctraError`)
+#[derive(Debug)]
+pub(crate) struct DeError(String);
+
+impl DeError {
+    /// Anteponer el nombre de la columna al mensaje
+    fn in_column(self, column: &str) -> Self {
+        Self(format!("columna '{}': {}", column, self.0))
+    }
+}
+
+impl fmt::Display for DeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.write_str(&self.0)
+    }
+}
+
+impl std::error::Error for DeError {}
+
+impl de::Error for DeError {
+    fn custom<T: fmt::Display>(msg: T) -> Self {
+        Self(msg.to_string())
+    }
+
+    fn invalid_type(unexpected: de::Unexpected, expected: &dyn de::Expected) -> Self {
+        Self(format!(
+            "se esperaba {}, se obtuvo {}",
+            expected, unexpected
+        ))
+    }
+
+    fn invalid_value(unexpected: de::Unexpected, expected: &dyn de::Expected) -> Self {
+        Self(format!(
+            "valor inválido {}, se esperaba {}",
+            unexpected, expected
+        ))
+    }
+
+    fn missing_field(field: &'static str) -> Self {
+        Self(format!("falta la columna '{}'", field))
+    }
+}
+
+impl From<DeError> for NoctraError {
+    fn from(error: DeError) -> Self {
+        NoctraError::Serialization(error.0)
+    }
+}
+
+/// Deserializar una fila completa (columnas por nombre, o por posición en tuplas)
+pub(crate) fn from_row<T: de::DeserializeOwned>(
+    columns: &[Column],
+    row: &Row,
+) -> Result<T, DeError> {
+    T::deserialize(RowDeserializer { columns, row })
+}
+
+/// Deserializar un valor suelto de la columna `column`
+pub(crate) fn from_value<T: de::DeserializeOwned>(
+    value: &Value,
+    column: &str,
+) -> Result<T, DeError> {
+    T::deserialize(ValueDeserializer { value }).map_err(|e| e.in_column(column))
+}
+
+struct RowDeserializer<'a> {
+    columns: &'a [Column],
+    row: &'a Row,
+}
+
+impl<'de> de::Deserializer<'de> for RowDeserializer<'de> {
+    type Error = DeError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        visitor.visit_map(RowMap {
+            columns: self.columns.iter(),
+            row: self.row,
+            current: None,
+        })
+    }
+
+    fn deserialize_seq<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        visitor.visit_seq(ValueSeq {
+            values: self.row.values.iter(),
+            columns: Some(self.columns),
+            index: 0,
+        })
+    }
+
+    fn deserialize_tuple<V: Visitor<'de>>(
+        self,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, DeError> {
+        self.deserialize_seq(visitor)
+    }
+
+    fn deserialize_tuple_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        _len: usize,
+        visitor: V,
+    ) -> Result<V::Value, DeError> {
+        self.deserialize_seq(visitor)
+    }
+
+    forward_to_deserialize_any! {
+        bool i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 f32 f64 char str string
+        bytes byte_buf option unit unit_struct newtype_struct map struct enum
+        identifier ignored_any
+    }
+}
+
+/// Columnas de una fila como mapa `nombre -> valor`
+struct RowMap<'a> {
+    columns: std::slice::Iter<'a, Column>,
+    row: &'a Row,
+    current: Option<&'a Column>,
+}
+
+impl<'de> MapAccess<'de> for RowMap<'de> {
+    type Error = DeError;
+
+    fn next_key_seed<K: DeserializeSeed<'de>>(
+        &mut self,
+        seed: K,
+    ) -> Result<Option<K::Value>, DeError> {
+        let Some(column) = self.columns.next() else {
+            return Ok(None);
+        };
+        self.current = Some(column);
+        seed.deserialize(column.name.as_str().into_deserializer())
+            .map(Some)
+    }
+
+    fn next_value_seed<V: DeserializeSeed<'de>>(&mut self, seed: V) -> Result<V::Value, DeError> {
+        let column = self
+            .current
+            .take()
+            .ok_or_else(|| DeError("valor pedido antes que su columna".to_string()))?;
+        let value = self.row.values.get(column.ordinal).unwrap_or(&Value::Null);
+        seed.deserialize(ValueDeserializer { value })
+            .map_err(|e| e.in_column(&column.name))
+    }
+}
+
+/// Valores en orden (tuplas o `Value::Array`)
+struct ValueSeq<'a> {
+    values: std::slice::Iter<'a, Value>,
+    /// Columnas de la fila, para nombrarlas en los errores
+    columns: Option<&'a [Column]>,
+    index: usize,
+}
+
+impl<'de> SeqAccess<'de> for ValueSeq<'de> {
+    type Error = DeError;
+
+    fn next_element_seed<T: DeserializeSeed<'de>>(
+        &mut self,
+        seed: T,
+    ) -> Result<Option<T::Value>, DeError> {
+        let Some(value) = self.values.next() else {
+            return Ok(None);
+        };
+        let index = self.index;
+        self.index += 1;
+        seed.deserialize(ValueDeserializer { value })
+            .map(Some)
+            .map_err(|e| match self.columns.and_then(|c| c.get(index)) {
+                Some(column) => e.in_column(&column.name),
+                None => DeError(format!("elemento {}: {}", index, e)),
+            })
+    }
+
+    fn size_hint(&self) -> Option<usize> {
+        Some(self.values.len())
+    }
+}
+
+struct ValueDeserializer<'a> {
+    value: &'a Value,
+}
+
+impl<'de> de::Deserializer<'de> for ValueDeserializer<'de> {
+    type Error = DeError;
+
+    fn deserialize_any<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        match self.value {
+            Value::Null => visitor.visit_unit(),
+            Value::Integer(i) => visitor.visit_i64(*i),
+            Value::Float(f) => visitor.visit_f64(*f),
+            #[cfg(feature = "decimal")]
+            Value::Decimal(d) => visitor.visit_string(d.to_string()),
+            Value::Text(s) => visitor.visit_borrowed_str(s),
+            Value::Boolean(b) => visitor.visit_bool(*b),
+            Value::Date(d) => visitor.visit_string(d.to_string()),
+            Value::Time(t) => visitor.visit_string(t.to_string()),
+            Value::Timestamp(ts) => visitor.visit_string(ts.to_rfc3339()),
+            Value::Blob(bytes) => visitor.visit_borrowed_bytes(bytes),
+            Value::Array(values) => visitor.visit_seq(ValueSeq {
+                values: values.iter(),
+                columns: None,
+                index: 0,
+            }),
+            Value::Json(json) => json
+                .clone()
+                .deserialize_any(visitor)
+                .map_err(|e| DeError(e.to_string())),
+        }
+    }
+
+    fn deserialize_option<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        match self.value {
+            Value::Null => visitor.visit_none(),
+            _ => visitor.visit_some(self),
+        }
+    }
+
+    fn deserialize_bool<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        // SQLite guarda los booleanos como 0/1
+        match self.value {
+            Value::Integer(0) => visitor.visit_bool(false),
+            Value::Integer(1) => visitor.visit_bool(true),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_f64<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        match self.value {
+            Value::Integer(i) => visitor.visit_f64(*i as f64),
+            #[cfg(feature = "decimal")]
+            Value::Decimal(d) => match d.to_string().parse::<f64>() {
+                Ok(f) => visitor.visit_f64(f),
+                Err(_) => self.deserialize_any(visitor),
+            },
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    fn deserialize_f32<V: Visitor<'de>>(self, visitor: V) -> Result<V::Value, DeError> {
+        self.deserialize_f64(visitor)
+    }
+
+    fn deserialize_newtype_struct<V: Visitor<'de>>(
+        self,
+        _name: &'static str,
+        visitor: V,
+    ) -> Result<V::Value, DeError> {
+        visitor.visit_newtype_struct(self)
+    }
+
+    fn deserialize_enum<V: Visitor<'de>>(
+        self,
+        name: &'static str,
+        variants: &'static [&'static str],
+        visitor: V,
+    ) -> Result<V::Value, DeError> {
+        match self.value {
+            // Enums unitarios guardados como texto
+            Value::Text(s) => visitor.visit_enum(s.as_str().into_deserializer()),
+            Value::Json(json) => json
+                .clone()
+                .deserialize_enum(name, variants, visitor)
+                .map_err(|e| DeError(e.to_string())),
+            _ => self.deserialize_any(visitor),
+        }
+    }
+
+    forward_to_deserialize_any! {
+        i8 i16 i32 i64 i128 u8 u16 u32 u64 u128 char str string bytes byte_buf
+        unit unit_struct seq tuple tuple_struct map struct identifier ignored_any
+    }
+}
@@ -0,0 +1,258 @@
+//! Scripts de varias sentencias
+//!
+//! `split_statements` divide un script en sentencias terminadas en `;`
+//! (respetando literales, identificadores entre comillas, comentarios y
+//! cuerpos de trigger, ver `lexer::statements`) y `run_script` las ejecuta en
+//! orden devolviendo un `StatementResult` por sentencia. `Executor::execute_script`
+//! ejecuta así SQL; los frontends (REPL, `--file`) pasan cada sentencia por
+//! el procesador RQL.
+
+use crate::error::{NoctraError, Result};
+use crate::lexer::{statements, SegmentKind};
+use crate::types::ResultSet;
+
+/// Opciones de `Executor::execute_script`
+#[derive(Debug, Clone, Copy)]
+pub struct ScriptOptions {
+    /// Detener el script en la primera sentencia con error
+    pub stop_on_error: bool,
+
+    /// Ejecutar el script en una transacción del backend
+    ///
+    /// Si alguna sentencia falla se revierte el script entero, también en
+    /// modo `stop_on_error = false`.
+    pub transaction: bool,
+}
+
+impl Default for ScriptOptions {
+    fn default() -> Self {
+        Self {
+            stop_on_error: true,
+            transaction: false,
+        }
+    }
+}
+
+/// Sentencia de un script, sin el `;` final ni comentarios
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ScriptStatement {
+    /// Línea del script donde empieza la sentencia (desde 1)
+    pub line: usize,
+
+    /// Texto SQL de la sentencia
+    pub sql: String,
+}
+
+/// Resultado de ejecutar una sentencia
+#[derive(Debug, Clone)]
+pub enum StatementOutcome {
+    /// Consulta con filas (SELECT, PRAGMA, ...)
+    Rows(ResultSet),
+
+    /// DDL o DML: filas afectadas
+    RowsAffected(u64),
+
+    /// La sentencia falló
+    Error(NoctraError),
+}
+
+/// Resultado de una sentencia del script
+#[derive(Debug, Clone)]
+pub struct StatementResult {
+    /// Posición de la sentencia en el script (desde 0)
+    pub index: usize,
+
+    /// Línea del script donde empieza la sentencia (desde 1)
+    pub line: usize,
+
+    /// Texto SQL ejecutado
+    pub sql: String,
+
+    /// Filas, filas afectadas o error
+    pub outcome: StatementOutcome,
+}
+
+impl StatementResult {
+    /// Si la sentencia falló
+    pub fn is_error(&self) -> bool {
+        matches!(self.outcome, StatementOutcome::Error(_))
+    }
+
+    /// Error de la sentencia, si falló
+    pub fn error(&self) -> Option<&NoctraError> {
+        match &self.outcome {
+            StatementOutcome::Error(e) => Some(e),
+            _ => None,
+        }
+    }
+}
+
+impl StatementOutcome {
+    /// Clasificar el resultado de `Executor::execute_rql`
+    pub(crate) fn from_result_set(result: ResultSet) -> Self {
+        if result.columns.is_empty() {
+            StatementOutcome::RowsAffected(result.rows_affected.unwrap_or(0))
+        } else {
+            StatementOutcome::Rows(result)
+        }
+    }
+}
+
+/// Dividir un script en sentencias terminadas en `;`
+///
+/// Los `;` dentro de literales (`'...'`), identificadores (`"..."`),
+/// cadenas dollar-quoted (`$$...$$`), comentarios o el cuerpo
+/// `BEGIN ... END` de un `CREATE TRIGGER` no cortan la sentencia. Los
+/// comentarios `--` y `/* */` se descartan y las sentencias vacías se
+/// omiten; la última puede no llevar `;`.
+pub fn split_statements(script: &str) -> Vec<ScriptStatement> {
+    let mut result = Vec::new();
+    let (mut line, mut counted) = (1, 0);
+
+    for statement in statements(script) {
+        let mut sql = String::new();
+        let mut first = None;
+        for segment in statement.segments {
+            let text = &script[segment.range.clone()];
+            if segment.kind == SegmentKind::BlockComment {
+                sql.push(' ');
+                continue;
+            }
+            if first.is_none() {
+                first = text
+                    .find(|c: char| !c.is_whitespace())
+                    .map(|pos| segment.range.start + pos);
+            }
+            sql.push_str(text);
+        }
+        // Sin nada más que espacios y comentarios no hay sentencia
+        let Some(first) = first else { continue };
+        line += script[counted..first].matches('\n').count();
+        counted = first;
+        result.push(ScriptStatement {
+            line,
+            sql: sql.trim().to_string(),
+        });
+    }
+
+    result
+}
+
+/// Ejecutar en orden las sentencias de `script`
+///
+/// `run` ejecuta una sentencia y devuelve su resultado
+/// (`StatementOutcome::Error` si falló); un `Err` aborta el script y se
+/// propaga. Con `stop_on_error` el script se detiene en la primera
+/// sentencia con error.
+pub fn run_script<F>(script: &str, stop_on_error: bool, mut run: F) -> Result<Vec<StatementResult>>
+where
+    F: FnMut(&ScriptStatement) -> Result<StatementOutcome>,
+{
+    let statements = split_statements(script);
+    let mut results = Vec::with_capacity(statements.len());
+    for (index, statement) in statements.into_iter().enumerate() {
+        let outcome = run(&statement)?;
+        let failed = matches!(outcome, StatementOutcome::Error(_));
+        results.push(StatementResult {
+            index,
+            line: statement.line,
+            sql: statement.sql,
+            outcome,
+        });
+        if failed && stop_on_error {
+            break;
+        }
+    }
+    Ok(results)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn sqls(script: &str) -> Vec<String> {
+        split_statements(script)
+            .into_iter()
+            .map(|statement| statement.sql)
+            .collect()
+    }
+
+    #[test]
+    fn test_split_statements_respects_quotes_and_comments() {
+        let script = "-- cabecera; con punto y coma\n\
+                      CREATE TABLE t (nombre TEXT);\n\
+                      INSERT INTO t VALUES ('a;b'), ('O''Brien; c');\n\
+                      /* bloque; */ SELECT \"x;y\" FROM t\n\
+                      ;;\n\
+                      SELECT 1";
+
+        assert_eq!(
+            sqls(script),
+            vec![
+                "CREATE TABLE t (nombre TEXT)",
+                "INSERT INTO t VALUES ('a;b'), ('O''Brien; c')",
+                "SELECT \"x;y\" FROM t",
+                "SELECT 1",
+            ]
+        );
+        let lines: Vec<usize> = split_statements(script).iter().map(|s| s.line).collect();
+        assert_eq!(lines, vec![2, 3, 4, 6]);
+    }
+
+    #[test]
+    fn test_split_statements_keeps_trigger_body() {
+        let script = "CREATE TRIGGER log_t AFTER INSERT ON t BEGIN\n\
+                        INSERT INTO log VALUES (new.id);\n\
+                        UPDATE t SET n = n + 1;\n\
+                      END;\n\
+                      SELECT * FROM log;";
+
+        let statements = sqls(script);
+        assert_eq!(statements.len(), 2);
+        assert!(statements[0].starts_with("CREATE TRIGGER"));
+        assert!(statements[0].ends_with("END"));
+        assert_eq!(statements[1], "SELECT * FROM log");
+
+        let script = "CREATE TRIGGER clasificar AFTER INSERT ON t BEGIN\n\
+                        UPDATE t SET tipo = CASE WHEN n > 0 THEN 'pos' ELSE 'neg' END;\n\
+                        INSERT INTO log VALUES (new.id);\n\
+                      END;\n\
+                      SELECT 1;";
+        let statements = sqls(script);
+        assert_eq!(statements.len(), 2);
+        assert!(statements[0].contains("INSERT INTO log VALUES (new.id);\nEND"));
+        assert_eq!(statements[1], "SELECT 1");
+    }
+
+    #[test]
+    fn test_run_script_stops_on_error_and_propagates_aborts() {
+        let script = "SELECT 1; FALLA; SELECT 2";
+        let run = |statement: &ScriptStatement| {
+            Ok(if statement.sql == "FALLA" {
+                StatementOutcome::Error(NoctraError::Internal("falla".into()))
+            } else {
+                StatementOutcome::RowsAffected(0)
+            })
+        };
+
+        let results = run_script(script, true, run).unwrap();
+        assert_eq!(results.len(), 2);
+        assert!(results[1].is_error());
+        assert_eq!(run_script(script, false, run).unwrap().len(), 3);
+
+        let aborted = run_script(script, false, |statement| match statement.sql.as_str() {
+            "FALLA" => Err(NoctraError::Internal("abortado".into())),
+            _ => Ok(StatementOutcome::RowsAffected(0)),
+        });
+        assert!(aborted.is_err());
+    }
+
+    #[test]
+    fn test_split_statements_keeps_dollar_quoted_body() {
+        let script = "CREATE MACRO m() AS $$ SELECT 1; $$;\nSELECT $t$ a;b $t$";
+        assert_eq!(
+            sqls(script),
+            vec!["CREATE MACRO m() AS $$ SELECT 1; $$", "SELECT $t$ a;b $t$"]
+        );
+    }
+}
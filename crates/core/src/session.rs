@@ -1,12 +1,20 @@
 //! Gestión de sesiones para Noctra
 
 use crate::error::{NoctraError, Result};
-use crate::types::{Parameters, SessionVariables, Value};
+use crate::expression;
+use crate::types::{Column, Parameters, ResultSet, Row, SessionVariables, Value};
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::sync::{Arc, Mutex, MutexGuard};
+use std::time::{Duration, Instant};
+
+/// Entradas de historial que conserva una sesión
+const MAX_SESSION_HISTORY: usize = 1000;
 
 /// Una sesión de trabajo de Noctra
-#[derive(Debug, Clone)]
+#[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct Session {
     /// Variables de sesión
     variables: SessionVariables,
@@ -22,28 +30,72 @@ pub struct Session {
 
     /// ID único de la sesión
     id: String,
+
+    /// Comandos ejecutados, del más antiguo al más reciente
+    #[serde(default)]
+    history: Vec<String>,
+
+    /// Fuentes registradas con USE, en orden de registro
+    #[serde(default)]
+    sources: Vec<SourceDescriptor>,
+
+    /// Alias de la fuente activa
+    #[serde(default)]
+    active_source: Option<String>,
+}
+
+/// Fuente registrada con `USE 'path' AS alias OPTIONS (...)`
+///
+/// Permite volver a registrar las fuentes al restaurar una sesión.
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct SourceDescriptor {
+    /// Ruta del archivo tal como se escribió
+    pub path: String,
+
+    /// Alias de la fuente (None: se usa la ruta)
+    #[serde(default)]
+    pub alias: Option<String>,
+
+    /// Opciones de carga
+    #[serde(default)]
+    pub options: HashMap<String, String>,
+}
+
+impl SourceDescriptor {
+    /// Nombre con el que queda registrada la fuente
+    pub fn name(&self) -> &str {
+        self.alias.as_deref().unwrap_or(&self.path)
+    }
 }
 
 impl Session {
     /// Crear nueva sesión
     pub fn new() -> Self {
-        Self {
-            variables: HashMap::new(),
-            parameters: HashMap::new(),
-            default_schema: "main".to_string(),
-            state: SessionState::Active,
-            id: uuid::Uuid::new_v4().to_string(),
-        }
+        Self::with_schema("main")
     }
 
     /// Crear sesión con configuración específica
     pub fn with_schema<T: Into<String>>(schema: T) -> Self {
         Self {
             variables: HashMap::new(),
-            parameters: HashMap::new(),
+            parameters: Parameters::new(),
             default_schema: schema.into(),
             state: SessionState::Active,
             id: uuid::Uuid::new_v4().to_string(),
+            history: Vec::new(),
+            sources: Vec::new(),
+            active_source: None,
+        }
+    }
+
+    /// Crear sesión con un ID conocido (p. ej. el que envía un cliente HTTP)
+    ///
+    /// Sirve para volver a encontrar lo que el executor guarda por sesión,
+    /// como los cursores de `Executor::open_cursor`.
+    pub fn with_id<T: Into<String>>(id: T) -> Self {
+        Self {
+            id: id.into(),
+            ..Self::new()
         }
     }
 
@@ -99,11 +151,114 @@ impl Session {
             Value::Text(text) => T::from_str(text).ok(),
             Value::Integer(int) => T::from_str(&int.to_string()).ok(),
             Value::Float(float) => T::from_str(&float.to_string()).ok(),
+            #[cfg(feature = "decimal")]
+            Value::Decimal(decimal) => T::from_str(&decimal.to_string()).ok(),
             Value::Boolean(bool) => T::from_str(&bool.to_string()).ok(),
             _ => None,
         }))
     }
 
+    /// Evaluar la expresión de un `LET` con las variables actuales
+    ///
+    /// Admite aritmética, `||`, comparaciones, `NOW()`, `UPPER()`, `LOWER()`
+    /// y referencias a otras variables. Lo que no es una expresión se toma
+    /// con `Value::from_literal`, como hacía `LET`: números como `1e5`,
+    /// fechas y horas (`2024-01-01` no es una resta), rutas, correos y
+    /// palabras sueltas que no son variables (`LET dept = ventas`).
+    pub fn evaluate(&self, expression: &str) -> Result<Value> {
+        if let Some(value) = literal_value(expression) {
+            return Ok(value);
+        }
+        let bare = expression.trim();
+        match expression::evaluate(expression, self) {
+            Err(NoctraError::SessionVariableNotFound(_))
+                if !bare.contains(|c: char| c.is_whitespace() || c == '#') =>
+            {
+                Ok(Value::from_literal(bare))
+            }
+            result => result,
+        }
+    }
+
+    /// Filas de `result` que cumplen `condition` (`FILTER`)
+    ///
+    /// La condición es una expresión como las de `LET` en la que los nombres
+    /// sueltos son columnas de la fila; una fila con condición NULL no pasa.
+    pub fn filter_result(&self, result: &ResultSet, condition: &str) -> Result<ResultSet> {
+        let mut error = None;
+        let filtered = result.filter(|row| {
+            if error.is_some() {
+                return false;
+            }
+            match expression::evaluate_row(condition, self, &result.columns, row) {
+                Ok(Value::Boolean(flag)) => flag,
+                Ok(Value::Null) => false,
+                Ok(other) => {
+                    error = Some(NoctraError::Validation(format!(
+                        "FILTER: la condición da {} ('{}'), no un booleano",
+                        other.type_name(),
+                        other
+                    )));
+                    false
+                }
+                Err(e) => {
+                    error = Some(e);
+                    false
+                }
+            }
+        });
+        match error {
+            Some(e) => Err(e),
+            None => Ok(filtered),
+        }
+    }
+
+    /// Resultado con una columna por expresión de `expressions` (`MAP`)
+    ///
+    /// Cada expresión lleva su alias (o se nombra con su texto). Una columna
+    /// tomada tal cual conserva su tipo; el de las calculadas es el del
+    /// primer valor no nulo.
+    pub fn map_result(
+        &self,
+        result: &ResultSet,
+        expressions: &[(&str, Option<&str>)],
+    ) -> Result<ResultSet> {
+        let mut columns: Vec<Column> = Vec::with_capacity(expressions.len());
+        let mut values: Vec<Vec<Value>> = vec![Vec::new(); result.rows.len()];
+
+        for (ordinal, (expression, alias)) in expressions.iter().enumerate() {
+            let name = alias.unwrap_or(expression.trim()).to_string();
+            if let Ok(index) = result.column_index(expression.trim()) {
+                for (row, out) in result.rows.iter().zip(&mut values) {
+                    out.push(row.get(index).cloned().unwrap_or(Value::Null));
+                }
+                columns.push(Column {
+                    name,
+                    ordinal,
+                    ..result.columns[index].clone()
+                });
+                continue;
+            }
+
+            for (row, out) in result.rows.iter().zip(&mut values) {
+                out.push(expression::evaluate_row(
+                    expression,
+                    self,
+                    &result.columns,
+                    row,
+                )?);
+            }
+            let data_type = values
+                .iter()
+                .map(|row| &row[ordinal])
+                .find(|value| !value.is_null())
+                .map_or("", |value| value.type_name());
+            columns.push(Column::new(name, data_type.to_string(), ordinal));
+        }
+
+        Ok(result.derive(columns, values.into_iter().map(Row::new).collect()))
+    }
+
     /// Remover variable de sesión
     pub fn remove_variable(&mut self, name: &str) -> Option<Value> {
         self.variables.remove(name)
@@ -128,8 +283,7 @@ impl Session {
 
     /// Obtener parámetro posicionado ($1, $2, etc.)
     pub fn get_positional_parameter(&self, index: usize) -> Option<&Value> {
-        let key = format!("${}", index + 1);
-        self.parameters.get(&key)
+        self.parameters.positional_values().get(index)
     }
 
     /// Obtener parámetro nombrado (:name)
@@ -140,8 +294,7 @@ impl Session {
 
     /// Establecer parámetro posicionado
     pub fn set_positional_parameter(&mut self, index: usize, value: impl Into<Value>) {
-        let key = format!("${}", index + 1);
-        self.parameters.insert(key, value.into());
+        self.parameters.set_positional(index, value);
     }
 
     /// Establecer parámetro nombrado
@@ -160,17 +313,107 @@ impl Session {
         &self.parameters
     }
 
+    // === HISTORIAL Y FUENTES ===
+
+    /// Agregar un comando al historial (se conservan los más recientes)
+    pub fn push_history<T: Into<String>>(&mut self, command: T) {
+        self.history.push(command.into());
+        if self.history.len() > MAX_SESSION_HISTORY {
+            self.history
+                .drain(..self.history.len() - MAX_SESSION_HISTORY);
+        }
+    }
+
+    /// Historial de comandos
+    pub fn history(&self) -> &[String] {
+        &self.history
+    }
+
+    /// Recordar una fuente registrada (reemplaza la del mismo nombre)
+    pub fn record_source(&mut self, source: SourceDescriptor) {
+        self.sources.retain(|s| s.name() != source.name());
+        self.sources.push(source);
+    }
+
+    /// Olvidar una fuente
+    pub fn forget_source(&mut self, name: &str) {
+        self.sources.retain(|s| s.name() != name);
+        if self.active_source.as_deref() == Some(name) {
+            self.active_source = None;
+        }
+    }
+
+    /// Fuentes registradas
+    pub fn sources(&self) -> &[SourceDescriptor] {
+        &self.sources
+    }
+
+    /// Alias de la fuente activa
+    pub fn active_source(&self) -> Option<&str> {
+        self.active_source.as_deref()
+    }
+
+    /// Cambiar la fuente activa
+    pub fn set_active_source(&mut self, alias: Option<String>) {
+        self.active_source = alias;
+    }
+
+    // === PERSISTENCIA ===
+
+    /// Guardar la sesión en un archivo
+    ///
+    /// Con extensión `.toml` se escribe TOML y en otro caso JSON. Las
+    /// variables conservan su tipo. Se escribe en un temporal que luego se
+    /// renombra, así que un corte no deja el archivo a medias.
+    pub fn save_to<P: AsRef<Path>>(&self, path: P) -> Result<()> {
+        let path = path.as_ref();
+        let content = if is_toml(path) {
+            toml::to_string(self).map_err(|e| NoctraError::Serialization(e.to_string()))?
+        } else {
+            serde_json::to_string_pretty(self)
+                .map_err(|e| NoctraError::Serialization(e.to_string()))?
+        };
+
+        let mut tmp = path.as_os_str().to_owned();
+        tmp.push(".tmp");
+        std::fs::write(&tmp, content)?;
+        std::fs::rename(&tmp, path)?;
+        Ok(())
+    }
+
+    /// Cargar una sesión guardada con `save_to`
+    pub fn load_from<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let path = path.as_ref();
+        let content = std::fs::read_to_string(path)?;
+        let session = if is_toml(path) {
+            toml::from_str(&content).map_err(|e| e.to_string())
+        } else {
+            serde_json::from_str(&content).map_err(|e| e.to_string())
+        };
+        session.map_err(|e| NoctraError::Serialization(format!("{}: {}", path.display(), e)))
+    }
+
+    /// Restaurar la sesión de `path`, o crear una nueva
+    ///
+    /// Un archivo inexistente da una sesión nueva sin error. Si el archivo
+    /// no se puede leer o está dañado también se devuelve una sesión nueva,
+    /// junto con el error para que el llamador pueda avisar.
+    pub fn restore<P: AsRef<Path>>(path: P) -> (Self, Option<NoctraError>) {
+        let path = path.as_ref();
+        if !path.exists() {
+            return (Self::new(), None);
+        }
+        match Self::load_from(path) {
+            Ok(session) => (session, None),
+            Err(e) => (Self::new(), Some(e)),
+        }
+    }
+
     // === UTILIDADES ===
 
     /// Clonar sesión para operaciones seguras
     pub fn clone_for_operation(&self) -> Self {
-        Session {
-            variables: self.variables.clone(),
-            parameters: self.parameters.clone(),
-            default_schema: self.default_schema.clone(),
-            state: self.state.clone(),
-            id: self.id.clone(),
-        }
+        self.clone()
     }
 
     /// Resetear sesión (mantener ID)
@@ -179,6 +422,9 @@ impl Session {
         self.parameters.clear();
         self.default_schema = "main".to_string();
         self.state = SessionState::Active;
+        self.history.clear();
+        self.sources.clear();
+        self.active_source = None;
     }
 
     /// Obtener información de debug
@@ -193,6 +439,40 @@ impl Session {
     }
 }
 
+/// Valor de un `LET` que es un literal y no una expresión (ver
+/// `Session::evaluate`)
+fn literal_value(expression: &str) -> Option<Value> {
+    let literal = expression.trim();
+    let value = Value::from_literal(literal);
+    let is_literal = !matches!(value, Value::Text(_))
+        || is_temporal(literal)
+        || (!literal.contains(char::is_whitespace) && !expression::tokenizes(literal));
+    is_literal.then_some(value)
+}
+
+/// Verificar si `text` es una fecha, hora o fecha y hora ISO
+fn is_temporal(text: &str) -> bool {
+    use chrono::{NaiveDate, NaiveDateTime, NaiveTime};
+
+    NaiveDate::parse_from_str(text, "%Y-%m-%d").is_ok()
+        || [
+            "%Y-%m-%d %H:%M:%S%.f",
+            "%Y-%m-%dT%H:%M:%S%.f",
+            "%Y-%m-%d %H:%M",
+        ]
+        .iter()
+        .any(|format| NaiveDateTime::parse_from_str(text, format).is_ok())
+        || ["%H:%M:%S%.f", "%H:%M"]
+            .iter()
+            .any(|format| NaiveTime::parse_from_str(text, format).is_ok())
+}
+
+/// Si el archivo de sesión es TOML (según su extensión)
+fn is_toml(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|ext| ext.eq_ignore_ascii_case("toml"))
+}
+
 /// Estados posibles de una sesión
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
 pub enum SessionState {
@@ -223,79 +503,409 @@ pub struct SessionDebugInfo {
     pub parameters_count: usize,
 }
 
-/// Gestor de sesiones múltiples
+/// Almacenamiento persistente de sesiones
+///
+/// Permite que una sesión sobreviva al proceso o se comparta entre
+/// peticiones HTTP independientes.
+pub trait SessionPersistence: Send + Sync {
+    /// Guardar la sesión (reemplaza la versión anterior)
+    fn save(&self, session: &Session) -> Result<()>;
+
+    /// Cargar una sesión; None si no existe
+    fn load(&self, session_id: &str) -> Result<Option<Session>>;
+
+    /// Eliminar una sesión (no es error si no existe)
+    fn delete(&self, session_id: &str) -> Result<()>;
+}
+
+/// Sesiones guardadas como JSON, un archivo `<id>.json` por sesión
+#[derive(Debug, Clone)]
+pub struct FileSessionStore {
+    dir: PathBuf,
+}
+
+impl FileSessionStore {
+    /// Usar `dir` como almacén (se crea si no existe)
+    pub fn new<P: Into<PathBuf>>(dir: P) -> Result<Self> {
+        let dir = dir.into();
+        std::fs::create_dir_all(&dir)?;
+        Ok(Self { dir })
+    }
+
+    /// Directorio del almacén
+    pub fn dir(&self) -> &std::path::Path {
+        &self.dir
+    }
+
+    /// Archivo de una sesión
+    ///
+    /// Los IDs pueden venir de una cookie, así que sólo se aceptan letras,
+    /// dígitos, `-` y `_` para que no puedan salir del directorio.
+    fn path_for(&self, session_id: &str) -> Option<PathBuf> {
+        let valid = !session_id.is_empty()
+            && session_id.len() <= 128
+            && session_id
+                .chars()
+                .all(|c| c.is_ascii_alphanumeric() || matches!(c, '-' | '_'));
+        valid.then(|| self.dir.join(format!("{}.json", session_id)))
+    }
+
+    fn invalid_id(session_id: &str) -> NoctraError {
+        NoctraError::Validation(format!("ID de sesión inválido: {:?}", session_id))
+    }
+}
+
+impl SessionPersistence for FileSessionStore {
+    fn save(&self, session: &Session) -> Result<()> {
+        let path = self
+            .path_for(session.id())
+            .ok_or_else(|| Self::invalid_id(session.id()))?;
+        let json = serde_json::to_vec_pretty(session)
+            .map_err(|e| NoctraError::Serialization(e.to_string()))?;
+
+        // Escribir en un temporal y renombrar: nunca queda un JSON a medias
+        let tmp = path.with_extension("json.tmp");
+        std::fs::write(&tmp, json)?;
+        std::fs::rename(&tmp, &path)?;
+        Ok(())
+    }
+
+    fn load(&self, session_id: &str) -> Result<Option<Session>> {
+        let Some(path) = self.path_for(session_id) else {
+            return Ok(None);
+        };
+        let json = match std::fs::read(&path) {
+            Ok(json) => json,
+            Err(e) if e.kind() == std::io::ErrorKind::NotFound => return Ok(None),
+            Err(e) => return Err(e.into()),
+        };
+        serde_json::from_slice(&json)
+            .map(Some)
+            .map_err(|e| NoctraError::Serialization(format!("{}: {}", path.display(), e)))
+    }
+
+    fn delete(&self, session_id: &str) -> Result<()> {
+        let path = self
+            .path_for(session_id)
+            .ok_or_else(|| Self::invalid_id(session_id))?;
+        match std::fs::remove_file(path) {
+            Err(e) if e.kind() != std::io::ErrorKind::NotFound => Err(e.into()),
+            _ => Ok(()),
+        }
+    }
+}
+
+/// Callback invocado con el ID de cada sesión eliminada
+pub type SessionHook = Arc<dyn Fn(&str) + Send + Sync>;
+
+/// Sesión compartida entre los hilos que atienden a un mismo cliente
+pub type SessionHandle = Arc<Mutex<Session>>;
+
+/// Sesión en memoria del gestor
 #[derive(Debug)]
+struct ManagedSession {
+    session: SessionHandle,
+
+    /// Último `create`, `get` o `touch` de la sesión
+    last_activity: Instant,
+}
+
+/// Gestor de sesiones múltiples
+///
+/// Se puede compartir entre hilos (p. ej. en un `Arc`): cada sesión tiene
+/// sus propias variables y fuente activa, y se entrega como un
+/// `SessionHandle`. Las sesiones sin actividad durante
+/// `SessionConfig::session_timeout` caducan: `get` ya no las encuentra y
+/// `purge_expired` (o la tarea de `spawn_cleanup`) las elimina.
 pub struct SessionManager {
     /// Sesiones activas
-    sessions: HashMap<String, Session>,
+    sessions: Mutex<HashMap<String, ManagedSession>>,
 
     /// Configuración global
     config: SessionConfig,
+
+    /// Callbacks de limpieza al eliminar sesiones
+    removal_hooks: Vec<SessionHook>,
+
+    /// Almacén persistente (None: sólo en memoria)
+    store: Option<Box<dyn SessionPersistence>>,
+}
+
+impl fmt::Debug for SessionManager {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("SessionManager")
+            .field("sessions", &self.sessions)
+            .field("config", &self.config)
+            .field("removal_hooks", &self.removal_hooks.len())
+            .field("persistent", &self.store.is_some())
+            .finish()
+    }
 }
 
 impl SessionManager {
     /// Crear nuevo gestor de sesiones
     pub fn new(config: SessionConfig) -> Self {
         Self {
-            sessions: HashMap::new(),
+            sessions: Mutex::new(HashMap::new()),
             config,
+            removal_hooks: Vec::new(),
+            store: None,
+        }
+    }
+
+    /// Crear gestor que guarda las sesiones en `store`
+    ///
+    /// Las sesiones creadas se guardan al momento; las modificaciones se
+    /// guardan con `save_session`. `load_session` recupera las que no
+    /// están en memoria.
+    pub fn new_persistent(store: Box<dyn SessionPersistence>) -> Self {
+        Self {
+            store: Some(store),
+            ..Self::new(SessionConfig::default())
+        }
+    }
+
+    /// Guardar desde ahora las sesiones en `store` (ver `new_persistent`)
+    pub fn set_store(&mut self, store: Box<dyn SessionPersistence>) {
+        self.store = Some(store);
+    }
+
+    /// Registrar un callback para cuando se elimine una sesión
+    ///
+    /// Permite liberar recursos asociados a la sesión, p. ej. con
+    /// `Executor::release_session` (cursores, transacción abierta y tablas
+    /// registradas en las fuentes). Se llama tanto con `remove_session` como
+    /// al caducar la sesión.
+    pub fn on_session_removed<F>(&mut self, hook: F)
+    where
+        F: Fn(&str) + Send + Sync + 'static,
+    {
+        self.removal_hooks.push(Arc::new(hook));
+    }
+
+    /// Ejecutar los callbacks de limpieza para una sesión
+    fn notify_removed(&self, id: &str) {
+        for hook in &self.removal_hooks {
+            hook(id);
         }
     }
 
     /// Crear nueva sesión
-    pub fn create_session(&mut self) -> Result<Session> {
+    pub fn create(&self) -> Result<SessionHandle> {
         let session = Session::new();
-        let id = session.id().to_string();
+        let mut sessions = self.lock()?;
 
-        if self.sessions.len() >= self.config.max_sessions {
+        if sessions.len() >= self.config.max_sessions {
             return Err(NoctraError::Configuration(format!(
                 "Máximo de sesiones alcanzado: {}",
                 self.config.max_sessions
             )));
         }
 
-        self.sessions.insert(id, session.clone());
-        Ok(session)
+        if let Some(store) = &self.store {
+            store.save(&session)?;
+        }
+        let id = session.id().to_string();
+        let handle = Arc::new(Mutex::new(session));
+        sessions.insert(
+            id,
+            ManagedSession {
+                session: handle.clone(),
+                last_activity: Instant::now(),
+            },
+        );
+        Ok(handle)
+    }
+
+    /// Obtener una sesión en memoria y renovar su actividad
+    ///
+    /// Una sesión caducada se elimina y da `NoctraError::NotFound`, igual
+    /// que una que no existe.
+    pub fn get(&self, id: &str) -> Result<SessionHandle> {
+        let now = Instant::now();
+        let mut sessions = self.lock()?;
+        match sessions.get_mut(id) {
+            Some(managed) if !self.is_expired(managed, now) => {
+                managed.last_activity = now;
+                return Ok(managed.session.clone());
+            }
+            Some(_) => {}
+            None => return Err(NoctraError::not_found("sesión", id)),
+        }
+        drop(sessions);
+
+        self.remove_session(id);
+        Err(NoctraError::not_found("sesión", id))
     }
 
-    /// Obtener sesión por ID
-    pub fn get_session(&self, id: &str) -> Option<&Session> {
-        self.sessions.get(id)
+    /// Renovar la actividad de una sesión sin obtenerla
+    pub fn touch(&self, id: &str) -> Result<()> {
+        self.get(id).map(|_| ())
     }
 
-    /// Obtener sesión mutable por ID
-    pub fn get_session_mut(&mut self, id: &str) -> Option<&mut Session> {
-        self.sessions.get_mut(id)
+    /// Obtener sesión, cargándola del almacén si no está en memoria
+    pub fn load_session(&self, id: &str) -> Result<Option<SessionHandle>> {
+        match self.get(id) {
+            Ok(handle) => return Ok(Some(handle)),
+            Err(NoctraError::NotFound { .. }) => {}
+            Err(e) => return Err(e),
+        }
+        let Some(session) = self
+            .store
+            .as_ref()
+            .map(|s| s.load(id))
+            .transpose()?
+            .flatten()
+        else {
+            return Ok(None);
+        };
+
+        let handle = Arc::new(Mutex::new(session));
+        self.lock()?.insert(
+            id.to_string(),
+            ManagedSession {
+                session: handle.clone(),
+                last_activity: Instant::now(),
+            },
+        );
+        Ok(Some(handle))
+    }
+
+    /// Guardar en el almacén el estado actual de una sesión en memoria
+    pub fn save_session(&self, id: &str) -> Result<()> {
+        let Some(handle) = self.lock()?.get(id).map(|m| m.session.clone()) else {
+            return Ok(());
+        };
+        let session = lock_session(&handle)?;
+        if let Some(store) = &self.store {
+            store.save(&session)?;
+        }
+        Ok(())
     }
 
     /// Remover sesión
-    pub fn remove_session(&mut self, id: &str) -> Option<Session> {
-        self.sessions.remove(id)
+    pub fn remove_session(&self, id: &str) -> Option<SessionHandle> {
+        let removed = self
+            .sessions
+            .lock()
+            .ok()
+            .and_then(|mut sessions| sessions.remove(id));
+        if let Some(store) = &self.store {
+            if let Err(e) = store.delete(id) {
+                log::warn!("No se pudo eliminar la sesión {} del almacén: {}", id, e);
+            }
+        }
+        if removed.is_some() {
+            self.notify_removed(id);
+        }
+        removed.map(|managed| managed.session)
     }
 
     /// Limpiar sesiones finalizadas
-    pub fn cleanup_finished_sessions(&mut self) {
-        let finished: Vec<String> = self
-            .sessions
-            .iter()
-            .filter(|(_, session)| matches!(session.state(), SessionState::Finished))
-            .map(|(id, _)| id.clone())
-            .collect();
+    pub fn cleanup_finished_sessions(&self) {
+        let finished: Vec<String> = match self.lock() {
+            Ok(sessions) => sessions
+                .iter()
+                .filter(|(_, managed)| {
+                    lock_session(&managed.session)
+                        .map(|session| matches!(session.state(), SessionState::Finished))
+                        .unwrap_or(false)
+                })
+                .map(|(id, _)| id.clone())
+                .collect(),
+            Err(_) => return,
+        };
 
         for id in finished {
-            self.sessions.remove(&id);
+            self.remove_session(&id);
         }
     }
 
+    /// Eliminar las sesiones caducadas; devuelve cuántas se eliminaron
+    pub fn purge_expired(&self) -> usize {
+        self.purge_expired_at(Instant::now())
+    }
+
+    /// `purge_expired` tomando `now` como instante actual
+    fn purge_expired_at(&self, now: Instant) -> usize {
+        let expired: Vec<String> = match self.lock() {
+            Ok(sessions) => sessions
+                .iter()
+                .filter(|(_, managed)| self.is_expired(managed, now))
+                .map(|(id, _)| id.clone())
+                .collect(),
+            Err(_) => return 0,
+        };
+
+        for id in &expired {
+            self.remove_session(id);
+        }
+        expired.len()
+    }
+
+    /// Tarea que cada `every` elimina las sesiones caducadas (y las
+    /// finalizadas si `auto_cleanup` está activo)
+    ///
+    /// Debe llamarse dentro de un runtime de Tokio. La tarea termina sola
+    /// cuando se libera el gestor.
+    pub fn spawn_cleanup(self: &Arc<Self>, every: Duration) -> tokio::task::JoinHandle<()> {
+        let manager = Arc::downgrade(self);
+        tokio::spawn(async move {
+            let mut ticker = tokio::time::interval(every);
+            ticker.tick().await;
+            loop {
+                ticker.tick().await;
+                let Some(manager) = manager.upgrade() else {
+                    break;
+                };
+                let purged = manager.purge_expired();
+                if purged > 0 {
+                    log::debug!("{} sesiones caducadas eliminadas", purged);
+                }
+                if manager.config.auto_cleanup {
+                    manager.cleanup_finished_sessions();
+                }
+            }
+        })
+    }
+
+    /// IDs de las sesiones en memoria
+    pub fn session_ids(&self) -> Vec<String> {
+        self.lock()
+            .map(|sessions| sessions.keys().cloned().collect())
+            .unwrap_or_default()
+    }
+
     /// Número de sesiones activas
     pub fn active_sessions_count(&self) -> usize {
-        self.sessions.len()
+        self.lock().map(|sessions| sessions.len()).unwrap_or(0)
     }
 
     /// Configuración del gestor
     pub fn config(&self) -> &SessionConfig {
         &self.config
     }
+
+    /// Si la sesión lleva más de `session_timeout` sin actividad en `now`
+    /// (0: nunca caduca)
+    fn is_expired(&self, managed: &ManagedSession, now: Instant) -> bool {
+        self.config.session_timeout > 0
+            && now.saturating_duration_since(managed.last_activity)
+                > Duration::from_secs(self.config.session_timeout)
+    }
+
+    fn lock(&self) -> Result<MutexGuard<'_, HashMap<String, ManagedSession>>> {
+        self.sessions
+            .lock()
+            .map_err(|_| NoctraError::Internal("gestor de sesiones envenenado".to_string()))
+    }
+}
+
+/// Bloquear una sesión compartida
+fn lock_session(handle: &SessionHandle) -> Result<MutexGuard<'_, Session>> {
+    handle
+        .lock()
+        .map_err(|_| NoctraError::Internal("sesión envenenada".to_string()))
 }
 
 /// Configuración del gestor de sesiones
@@ -304,7 +914,7 @@ pub struct SessionConfig {
     /// Máximo número de sesiones concurrentes
     pub max_sessions: usize,
 
-    /// Timeout de sesión en segundos
+    /// Segundos sin actividad tras los que caduca una sesión (0: nunca)
     pub session_timeout: u64,
 
     /// Auto-cleanup de sesiones finalizadas
@@ -326,3 +936,300 @@ impl Default for Session {
         Self::new()
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_file_session_store_roundtrip() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = FileSessionStore::new(dir.path().join("sesiones")).unwrap();
+
+        let mut session = Session::with_schema("ventas");
+        session.set_variable("pais", "AR");
+        session.set_variable("limite", 10);
+        store.save(&session).unwrap();
+
+        let loaded = store.load(session.id()).unwrap().unwrap();
+        assert_eq!(loaded.id(), session.id());
+        assert_eq!(loaded.default_schema(), "ventas");
+        assert_eq!(loaded.get_variable("pais"), Some(&Value::Text("AR".into())));
+        assert_eq!(loaded.get_variable("limite"), Some(&Value::Integer(10)));
+
+        store.delete(session.id()).unwrap();
+        assert!(store.load(session.id()).unwrap().is_none());
+        store.delete(session.id()).unwrap();
+
+        // IDs que saldrían del directorio
+        assert!(store.load("../secreto").unwrap().is_none());
+        assert!(store.delete("../secreto").is_err());
+    }
+
+    #[test]
+    fn test_session_file_roundtrip_keeps_types() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut session = Session::new();
+        session.set_variable("pais", "AR");
+        session.set_variable("limite", 10);
+        session.set_variable("tasa", 0.25);
+        session.set_variable("activo", true);
+        session.set_variable("vacio", Value::Null);
+        session.push_history("LET pais = 'AR'");
+        session.push_history("SELECT * FROM ventas");
+        session.record_source(SourceDescriptor {
+            path: "ventas.csv".to_string(),
+            alias: Some("ventas".to_string()),
+            options: HashMap::from([("delimiter".to_string(), ";".to_string())]),
+        });
+        session.record_source(SourceDescriptor {
+            path: "datos/ventas_2024.csv".to_string(),
+            alias: Some("ventas".to_string()),
+            options: HashMap::new(),
+        });
+        session.set_active_source(Some("ventas".to_string()));
+
+        for name in ["sesion.toml", "sesion.json"] {
+            let path = dir.path().join(name);
+            session.save_to(&path).unwrap();
+            let loaded = Session::load_from(&path).unwrap();
+
+            assert_eq!(loaded.id(), session.id());
+            assert_eq!(loaded.list_variables(), session.list_variables());
+            assert_eq!(loaded.get_variable("tasa"), Some(&Value::Float(0.25)));
+            assert_eq!(loaded.history(), session.history());
+            assert_eq!(loaded.sources().len(), 1);
+            assert_eq!(loaded.sources()[0].path, "datos/ventas_2024.csv");
+            assert_eq!(loaded.active_source(), Some("ventas"));
+        }
+        assert!(std::fs::read_to_string(dir.path().join("sesion.toml"))
+            .unwrap()
+            .contains("[variables.pais]"));
+    }
+
+    #[test]
+    fn test_restore_corrupted_session_starts_fresh() {
+        let dir = tempfile::tempdir().unwrap();
+
+        let (session, warning) = Session::restore(dir.path().join("nueva.toml"));
+        assert!(warning.is_none());
+        assert!(session.list_variables().is_empty());
+
+        let path = dir.path().join("rota.toml");
+        std::fs::write(&path, "variables = [[[").unwrap();
+        let (session, warning) = Session::restore(&path);
+        assert!(matches!(warning, Some(NoctraError::Serialization(_))));
+        assert!(session.history().is_empty());
+    }
+
+    #[test]
+    fn test_persistent_manager_survives_restart() {
+        let dir = tempfile::tempdir().unwrap();
+        let store = || Box::new(FileSessionStore::new(dir.path()).unwrap());
+
+        let manager = SessionManager::new_persistent(store());
+        let id = manager.create().unwrap().lock().unwrap().id().to_string();
+        manager
+            .get(&id)
+            .unwrap()
+            .lock()
+            .unwrap()
+            .set_variable("moneda", "EUR");
+        manager.save_session(&id).unwrap();
+
+        let restarted = SessionManager::new_persistent(store());
+        assert!(restarted.get(&id).is_err());
+        let session = restarted.load_session(&id).unwrap().unwrap();
+        assert_eq!(
+            session.lock().unwrap().get_variable("moneda"),
+            Some(&Value::Text("EUR".into()))
+        );
+
+        restarted.remove_session(&id);
+        assert!(SessionManager::new_persistent(store())
+            .load_session(&id)
+            .unwrap()
+            .is_none());
+    }
+
+    #[test]
+    fn test_manager_sessions_are_isolated_across_threads() {
+        let manager = Arc::new(SessionManager::new(SessionConfig::default()));
+        let ids: Vec<String> = (0..2)
+            .map(|_| manager.create().unwrap().lock().unwrap().id().to_string())
+            .collect();
+
+        let workers: Vec<_> = ids
+            .iter()
+            .enumerate()
+            .map(|(n, id)| {
+                let (manager, id) = (manager.clone(), id.clone());
+                std::thread::spawn(move || {
+                    for _ in 0..100 {
+                        let session = manager.get(&id).unwrap();
+                        let mut session = session.lock().unwrap();
+                        let value = session.evaluate(&format!("{} * 10", n + 1)).unwrap();
+                        session.set_variable("limite", value);
+                    }
+                })
+            })
+            .collect();
+        for worker in workers {
+            worker.join().unwrap();
+        }
+
+        let limite = |id: &str| {
+            let session = manager.get(id).unwrap();
+            let limite = session.lock().unwrap().get_variable("limite").cloned();
+            limite
+        };
+        assert_eq!(limite(&ids[0]), Some(Value::Integer(10)));
+        assert_eq!(limite(&ids[1]), Some(Value::Integer(20)));
+    }
+
+    #[test]
+    fn test_manager_expires_idle_sessions() {
+        let mut manager = SessionManager::new(SessionConfig {
+            session_timeout: 60,
+            ..SessionConfig::default()
+        });
+        let removed = Arc::new(Mutex::new(Vec::new()));
+        let hook_removed = removed.clone();
+        manager.on_session_removed(move |id| hook_removed.lock().unwrap().push(id.to_string()));
+
+        let id = manager.create().unwrap().lock().unwrap().id().to_string();
+        manager.touch(&id).unwrap();
+        assert_eq!(manager.purge_expired(), 0);
+
+        let later = Instant::now() + Duration::from_secs(61);
+        assert_eq!(manager.purge_expired_at(later), 1);
+        assert_eq!(manager.active_sessions_count(), 0);
+        assert_eq!(*removed.lock().unwrap(), vec![id.clone()]);
+        assert!(matches!(
+            manager.get(&id),
+            Err(NoctraError::NotFound { kind, .. }) if kind == "sesión"
+        ));
+        assert!(manager.touch(&id).is_err());
+    }
+
+    #[test]
+    fn test_evaluate_let_expressions() {
+        let mut session = Session::new();
+        assert_eq!(session.evaluate("10 * 5").unwrap(), Value::Integer(50));
+        assert_eq!(
+            session.evaluate("(1 + 2) * 1.5").unwrap(),
+            Value::Float(4.5)
+        );
+        assert_eq!(session.evaluate("7 / 2").unwrap(), Value::Integer(3));
+        assert_eq!(
+            session.evaluate("-3 % 2 = -1").unwrap(),
+            Value::Boolean(true)
+        );
+        assert_eq!(
+            session.evaluate("'Mar' || 'ía' || ' ' || 2024").unwrap(),
+            Value::Text("María 2024".into())
+        );
+        assert_eq!(
+            session.evaluate("UPPER('o''brien') || lower('X')").unwrap(),
+            Value::Text("O'BRIENx".into())
+        );
+        assert!(matches!(session.evaluate("NOW()"), Ok(Value::Timestamp(_))));
+
+        session.set_variable("limite", 10);
+        session.set_variable("pais", "AR");
+        assert_eq!(session.evaluate("#limite * 5").unwrap(), Value::Integer(50));
+        assert_eq!(
+            session.evaluate("pais || '-' || limite").unwrap(),
+            Value::Text("AR-10".into())
+        );
+        assert_eq!(
+            session.evaluate("limite >= 10").unwrap(),
+            Value::Boolean(true)
+        );
+        // Palabra suelta que no es variable: texto, como antes
+        assert_eq!(
+            session.evaluate("ventas").unwrap(),
+            Value::Text("ventas".into())
+        );
+        // Literales que no son expresiones
+        for (literal, expected) in [
+            ("2024-01-01", Value::Text("2024-01-01".into())),
+            (
+                "2024-01-01 10:30:00",
+                Value::Text("2024-01-01 10:30:00".into()),
+            ),
+            ("10:30", Value::Text("10:30".into())),
+            ("1e5", Value::Float(100000.0)),
+            ("/tmp/datos.csv", Value::Text("/tmp/datos.csv".into())),
+            ("ana@example.com", Value::Text("ana@example.com".into())),
+            ("datos/2024", Value::Text("datos/2024".into())),
+        ] {
+            assert_eq!(session.evaluate(literal).unwrap(), expected, "{}", literal);
+        }
+        assert_eq!(session.evaluate("1e3 * 2").unwrap(), Value::Float(2000.0));
+        assert_eq!(session.evaluate("10*5").unwrap(), Value::Integer(50));
+        assert!(matches!(
+            session.evaluate("#otra + 1"),
+            Err(NoctraError::SessionVariableNotFound(name)) if name == "otra"
+        ));
+    }
+
+    #[test]
+    fn test_filter_and_map_result() {
+        let mut result = ResultSet::new(vec![
+            Column::new("nombre", "TEXT", 0),
+            Column::new("pais", "TEXT", 1),
+            Column::new("ventas", "INTEGER", 2),
+        ]);
+        for (nombre, pais, ventas) in [
+            ("Ana", Value::Text("AR".into()), Value::Integer(120)),
+            ("Luis", Value::Text("UY".into()), Value::Integer(80)),
+            ("Eva", Value::Null, Value::Integer(300)),
+        ] {
+            result.add_row(Row::new(vec![Value::Text(nombre.into()), pais, ventas]));
+        }
+
+        let mut session = Session::new();
+        session.set_variable("minimo", 100);
+
+        let filtered = session
+            .filter_result(&result, "ventas >= #minimo AND pais IN ('AR', 'CL')")
+            .unwrap();
+        assert_eq!(filtered.row_count(), 1);
+        assert_eq!(filtered.rows[0].values[0], Value::Text("Ana".into()));
+        let filtered = session
+            .filter_result(&result, "NOT pais = 'AR' OR ventas < 100")
+            .unwrap();
+        assert_eq!(filtered.row_count(), 1);
+        assert!(session.filter_result(&result, "ventas * 2").is_err());
+
+        let mapped = session
+            .map_result(
+                &result,
+                &[("nombre", Some("cliente")), ("ventas * 2", None)],
+            )
+            .unwrap();
+        assert_eq!(mapped.columns[0].name, "cliente");
+        assert_eq!(mapped.columns[0].data_type, "TEXT");
+        assert_eq!(mapped.columns[1].name, "ventas * 2");
+        assert_eq!(mapped.rows[1].values[1], Value::Integer(160));
+        assert!(session
+            .map_result(&result, &[("ventas / 0", None)])
+            .is_err());
+    }
+
+    #[test]
+    fn test_evaluate_let_errors() {
+        let mut session = Session::new();
+        session.set_variable("cero", 0);
+
+        for expression in ["1 / 0", "5 % #cero", "2.5 / cero"] {
+            let error = session.evaluate(expression).unwrap_err();
+            assert!(error.to_string().contains("división por cero"), "{}", error);
+        }
+        assert!(session.evaluate("'a' * 2").is_err());
+        assert!(session.evaluate("(1 + 2").is_err());
+        assert!(session.evaluate("FOO(1)").is_err());
+        assert!(session.evaluate("9223372036854775807 + 1").is_err());
+    }
+}
@@ -1,9 +1,20 @@
 //! Tipos de datos fundamentales para Noctra
 
+use crate::error::{NoctraError, Result};
+use crate::row_de;
+use chrono::{DateTime, NaiveDate, NaiveDateTime, NaiveTime, SecondsFormat, TimeZone, Utc};
+use serde::de::DeserializeOwned;
 use serde::{Deserialize, Serialize};
+use std::borrow::Cow;
+use std::cmp::Ordering;
 use std::collections::HashMap;
 use std::fmt;
 
+pub mod coerce;
+
+/// Bytes mostrados en la vista previa de un `Value::Blob`
+const BLOB_PREVIEW_BYTES: usize = 16;
+
 /// Representa un valor en Noctra
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize, Default)]
 pub enum Value {
@@ -17,6 +28,10 @@ pub enum Value {
     /// Número de punto flotante
     Float(f64),
 
+    /// Decimal exacto (importes, sin errores de redondeo)
+    #[cfg(feature = "decimal")]
+    Decimal(rust_decimal::Decimal),
+
     /// Texto
     Text(String),
 
@@ -24,10 +39,16 @@ pub enum Value {
     Boolean(bool),
 
     /// Fecha
-    Date(String),
+    Date(NaiveDate),
 
-    /// Fecha y hora
-    DateTime(String),
+    /// Hora del día
+    Time(NaiveTime),
+
+    /// Fecha y hora (UTC)
+    Timestamp(DateTime<Utc>),
+
+    /// Datos binarios
+    Blob(Vec<u8>),
 
     /// Array de valores
     Array(Vec<Value>),
@@ -57,10 +78,91 @@ impl Value {
         Self::Boolean(val)
     }
 
+    /// Interpretar un literal (p. ej. el valor de `LET`)
+    ///
+    /// `'texto'` y `"texto"` son texto (con `''` como comilla escapada); los
+    /// números, `TRUE`/`FALSE` y `NULL` se reconocen por su forma. Cualquier
+    /// otra cosa se toma como texto sin comillas.
+    pub fn from_literal(literal: &str) -> Self {
+        let literal = literal.trim();
+        for quote in ['\'', '"'] {
+            if let Some(inner) = literal
+                .strip_prefix(quote)
+                .and_then(|rest| rest.strip_suffix(quote))
+            {
+                let escaped = format!("{}{}", quote, quote);
+                return Self::Text(inner.replace(&escaped, &quote.to_string()));
+            }
+        }
+
+        if let Ok(int) = literal.parse::<i64>() {
+            Self::Integer(int)
+        } else if let Some(float) = literal.parse::<f64>().ok().filter(|f| f.is_finite()) {
+            Self::Float(float)
+        } else if literal.eq_ignore_ascii_case("true") {
+            Self::Boolean(true)
+        } else if literal.eq_ignore_ascii_case("false") {
+            Self::Boolean(false)
+        } else if literal.eq_ignore_ascii_case("null") {
+            Self::Null
+        } else {
+            Self::Text(literal.to_string())
+        }
+    }
+
+    /// Nombre del tipo (p. ej. para `SHOW VARS`)
+    pub fn type_name(&self) -> &'static str {
+        match self {
+            Self::Null => "NULL",
+            Self::Integer(_) => "INTEGER",
+            Self::Float(_) => "FLOAT",
+            #[cfg(feature = "decimal")]
+            Self::Decimal(_) => "DECIMAL",
+            Self::Text(_) => "TEXT",
+            Self::Boolean(_) => "BOOLEAN",
+            Self::Date(_) => "DATE",
+            Self::Time(_) => "TIME",
+            Self::Timestamp(_) => "TIMESTAMP",
+            Self::Blob(_) => "BLOB",
+            Self::Array(_) => "ARRAY",
+            Self::Json(_) => "JSON",
+        }
+    }
+
+    /// Texto de una celda en las tablas del CLI y el TUI
+    ///
+    /// Igual que `Display` salvo los BLOB, que se resumen como
+    /// `<BLOB n bytes>` (los bytes se guardan con `SAVE BLOB`).
+    pub fn display_cell(&self) -> String {
+        match self {
+            Self::Blob(bytes) => format!("<BLOB {} bytes>", bytes.len()),
+            other => other.to_string(),
+        }
+    }
+
     /// Verificar si es nulo
     pub fn is_null(&self) -> bool {
         matches!(self, Self::Null)
     }
+
+    /// Número JSON para valores numéricos (usado por los exportadores)
+    ///
+    /// Los decimales sólo se convierten si el número JSON conserva todos sus
+    /// dígitos; si no, devuelve `None` y conviene exportarlos como texto.
+    pub fn to_json_number(&self) -> Option<serde_json::Number> {
+        match self {
+            Self::Integer(i) => Some((*i).into()),
+            Self::Float(f) => serde_json::Number::from_f64(*f),
+            #[cfg(feature = "decimal")]
+            Self::Decimal(d) => {
+                let text = d.normalize().to_string();
+                text.parse::<serde_json::Number>()
+                    .ok()
+                    .filter(|number| number.to_string() == text)
+            }
+            _ => None,
+        }
+    }
 }
 
 impl fmt::Display for Value {
@@ -69,9 +171,23 @@ impl fmt::Display for Value {
             Self::Null => write!(f, "NULL"),
             Self::Integer(v) => write!(f, "{}", v),
             Self::Float(v) => write!(f, "{}", v),
+            #[cfg(feature = "decimal")]
+            Self::Decimal(v) => write!(f, "{}", v),
             Self::Text(v) => write!(f, "{}", v),
             Self::Boolean(v) => write!(f, "{}", v),
-            Self::Date(v) | Self::DateTime(v) => write!(f, "{}", v),
+            Self::Date(v) => write!(f, "{}", v.format("%Y-%m-%d")),
+            Self::Time(v) => write!(f, "{}", v.format("%H:%M:%S%.f")),
+            Self::Timestamp(v) => write!(f, "{}", v.to_rfc3339_opts(SecondsFormat::AutoSi, true)),
+            Self::Blob(v) => {
+                write!(f, "0x")?;
+                for byte in v.iter().take(BLOB_PREVIEW_BYTES) {
+                    write!(f, "{:02x}", byte)?;
+                }
+                if v.len() > BLOB_PREVIEW_BYTES {
+                    write!(f, "… ({} bytes)", v.len())?;
+                }
+                Ok(())
+            }
             Self::Array(v) => {
                 write!(f, "[")?;
                 for (i, item) in v.iter().enumerate() {
@@ -99,6 +215,81 @@ impl From<f64> for Value {
     }
 }
 
+/// Orden entre valores del mismo tipo (NULL sólo es comparable con NULL)
+///
+/// Valores de tipos distintos no son comparables, igual que con `PartialEq`.
+impl PartialOrd for Value {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        match (self, other) {
+            (Self::Null, Self::Null) => Some(Ordering::Equal),
+            (Self::Integer(a), Self::Integer(b)) => a.partial_cmp(b),
+            (Self::Float(a), Self::Float(b)) => a.partial_cmp(b),
+            #[cfg(feature = "decimal")]
+            (Self::Decimal(a), Self::Decimal(b)) => a.partial_cmp(b),
+            (Self::Text(a), Self::Text(b)) => a.partial_cmp(b),
+            (Self::Boolean(a), Self::Boolean(b)) => a.partial_cmp(b),
+            (Self::Date(a), Self::Date(b)) => a.partial_cmp(b),
+            (Self::Time(a), Self::Time(b)) => a.partial_cmp(b),
+            (Self::Timestamp(a), Self::Timestamp(b)) => a.partial_cmp(b),
+            (Self::Blob(a), Self::Blob(b)) => a.partial_cmp(b),
+            (Self::Array(a), Self::Array(b)) => a.partial_cmp(b),
+            _ => None,
+        }
+    }
+}
+
+/// Interpretar una fecha y hora ISO-8601 (`2024-01-31T10:00:00Z`,
+/// `2024-01-31 10:00:00.5`, ...); sin zona horaria se asume UTC
+pub fn parse_timestamp(text: &str) -> Option<DateTime<Utc>> {
+    let text = text.trim();
+    if let Ok(timestamp) = DateTime::parse_from_rfc3339(text) {
+        return Some(timestamp.with_timezone(&Utc));
+    }
+    [
+        "%Y-%m-%d %H:%M:%S%.f",
+        "%Y-%m-%dT%H:%M:%S%.f",
+        "%Y-%m-%d %H:%M:%S%.f%#z",
+    ]
+    .iter()
+    .find_map(|format| {
+        NaiveDateTime::parse_from_str(text, format)
+            .map(|naive| Utc.from_utc_datetime(&naive))
+            .or_else(|_| DateTime::parse_from_str(text, format).map(|dt| dt.with_timezone(&Utc)))
+            .ok()
+    })
+}
+
+impl From<NaiveDate> for Value {
+    fn from(val: NaiveDate) -> Self {
+        Self::Date(val)
+    }
+}
+
+impl From<NaiveTime> for Value {
+    fn from(val: NaiveTime) -> Self {
+        Self::Time(val)
+    }
+}
+
+impl From<DateTime<Utc>> for Value {
+    fn from(val: DateTime<Utc>) -> Self {
+        Self::Timestamp(val)
+    }
+}
+
+impl From<Vec<u8>> for Value {
+    fn from(val: Vec<u8>) -> Self {
+        Self::Blob(val)
+    }
+}
+
+#[cfg(feature = "decimal")]
+impl From<rust_decimal::Decimal> for Value {
+    fn from(val: rust_decimal::Decimal) -> Self {
+        Self::Decimal(val)
+    }
+}
+
 impl From<String> for Value {
     fn from(val: String) -> Self {
         Self::Text(val)
@@ -151,6 +342,58 @@ impl Column {
             ordinal,
         }
     }
+
+    /// Si el tipo es numérico (ver `is_numeric_type`)
+    ///
+    /// Las interfaces alinean estas columnas a la derecha.
+    pub fn is_numeric(&self) -> bool {
+        is_numeric_type(&self.data_type)
+    }
+}
+
+/// Palabras que hacen numérico un tipo de columna, con los nombres de
+/// SQLite, PostgreSQL (`int4`, `double precision`...) y DuckDB (`HUGEINT`,
+/// `UBIGINT`...)
+const NUMERIC_TYPE_WORDS: &[&str] = &[
+    "INT",
+    "INTEGER",
+    "TINYINT",
+    "SMALLINT",
+    "MEDIUMINT",
+    "BIGINT",
+    "HUGEINT",
+    "UTINYINT",
+    "USMALLINT",
+    "UINTEGER",
+    "UBIGINT",
+    "UHUGEINT",
+    "INT1",
+    "INT2",
+    "INT4",
+    "INT8",
+    "SERIAL",
+    "SMALLSERIAL",
+    "BIGSERIAL",
+    "REAL",
+    "FLOAT",
+    "FLOAT4",
+    "FLOAT8",
+    "DOUBLE",
+    "NUMERIC",
+    "DECIMAL",
+    "NUMBER",
+];
+
+/// Si un tipo de columna es numérico (INTEGER, REAL, NUMERIC, DECIMAL, DOUBLE...)
+///
+/// Se comparan palabras completas, sin la precisión (`DECIMAL(10,2)`), para
+/// que INTERVAL o POINT no cuenten como enteros. Los arrays de PostgreSQL
+/// (`_int4`) no son numéricos.
+pub fn is_numeric_type(data_type: &str) -> bool {
+    data_type
+        .to_uppercase()
+        .split(|c: char| !(c.is_ascii_alphanumeric() || c == '_'))
+        .any(|word| NUMERIC_TYPE_WORDS.contains(&word))
 }
 
 /// Una fila de resultado
@@ -179,6 +422,17 @@ impl Row {
             .and_then(|col| self.get(col.ordinal))
     }
 
+    /// Obtener el valor de una columna convertido a `T`
+    ///
+    /// `NULL` se convierte en `None` si `T` es un `Option`; el error nombra la
+    /// columna y los tipos esperado y obtenido.
+    pub fn get_as<T: DeserializeOwned>(&self, columns: &[Column], name: &str) -> Result<T> {
+        let value = self.get_by_name(columns, name).ok_or_else(|| {
+            NoctraError::Serialization(format!("columna '{}' no encontrada", name))
+        })?;
+        Ok(row_de::from_value(value, name)?)
+    }
+
     /// Cantidad de columnas
     pub fn len(&self) -> usize {
         self.values.len()
@@ -190,6 +444,14 @@ impl Row {
     }
 }
 
+/// Sentido de un criterio de `ResultSet::sort_by`
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Serialize, Deserialize)]
+pub enum SortOrder {
+    #[default]
+    Ascending,
+    Descending,
+}
+
 /// Un conjunto de resultados
 #[derive(Debug, Clone, Serialize, Deserialize)]
 pub struct ResultSet {
@@ -204,6 +466,10 @@ pub struct ResultSet {
 
     /// Último ID insertado (para INSERT)
     pub last_insert_rowid: Option<i64>,
+
+    /// Si el backend recortó las filas por un límite de resultados
+    #[serde(default)]
+    pub truncated: bool,
 }
 
 impl ResultSet {
@@ -214,7 +480,69 @@ impl ResultSet {
             rows: Vec::new(),
             rows_affected: None,
             last_insert_rowid: None,
+            truncated: false,
+        }
+    }
+
+    /// Leer un stream hasta `limit` filas
+    ///
+    /// Si quedan filas sin leer se marca `truncated` y el resto del stream
+    /// se descarta sin recorrerlo.
+    pub fn from_stream(mut stream: RowStream, limit: Option<usize>) -> Result<Self> {
+        let mut result_set = Self::new(stream.columns.clone());
+        result_set.rows_affected = stream.rows_affected;
+        result_set.last_insert_rowid = stream.last_insert_rowid;
+        result_set.truncated = stream.truncated;
+        while let Some(row) = stream.next().transpose()? {
+            if limit.is_some_and(|max| result_set.rows.len() >= max) {
+                result_set.truncated = true;
+                break;
+            }
+            result_set.add_row(row);
         }
+        Ok(result_set)
+    }
+
+    /// Convertir cada fila en un `T`, asignando columnas a campos por nombre
+    ///
+    /// También admite tuplas, que se llenan por posición.
+    ///
+    /// ```
+    /// use noctra_core::{Executor, RqlQuery, Session};
+    /// use serde::Deserialize;
+    ///
+    /// #[derive(Deserialize)]
+    /// struct Cliente {
+    ///     id: i64,
+    ///     nombre: String,
+    ///     email: Option<String>,
+    /// }
+    ///
+    /// let executor = Executor::new_sqlite_memory().unwrap();
+    /// let session = Session::new();
+    /// let result = executor
+    ///     .execute_rql(
+    ///         &session,
+    ///         RqlQuery::sql("SELECT 1 AS id, 'Ana' AS nombre, NULL AS email"),
+    ///     )
+    ///     .unwrap();
+    ///
+    /// let clientes: Vec<Cliente> = result.deserialize().unwrap();
+    /// assert_eq!(clientes[0].nombre, "Ana");
+    /// assert!(clientes[0].email.is_none());
+    ///
+    /// let tuplas: Vec<(i64, String)> = result.deserialize().unwrap();
+    /// assert_eq!(tuplas[0], (1, "Ana".to_string()));
+    /// ```
+    pub fn deserialize<T: DeserializeOwned>(&self) -> Result<Vec<T>> {
+        self.rows
+            .iter()
+            .enumerate()
+            .map(|(index, row)| {
+                row_de::from_row(&self.columns, row)
+                    .map_err(|e| NoctraError::Serialization(format!("fila {}: {}", index + 1, e)))
+            })
+            .collect()
     }
 
     /// Crear ResultSet vacío
@@ -224,6 +552,7 @@ impl ResultSet {
             rows: Vec::new(),
             rows_affected: None,
             last_insert_rowid: None,
+            truncated: false,
         }
     }
 
@@ -232,11 +561,201 @@ impl ResultSet {
         self.rows.push(row);
     }
 
+    /// Copia con las filas `offset..offset + limit` (las que existan)
+    ///
+    /// Conserva columnas, filas afectadas y la marca de truncado del
+    /// resultado original.
+    pub fn page(&self, offset: usize, limit: usize) -> ResultSet {
+        let start = offset.min(self.rows.len());
+        let end = start.saturating_add(limit).min(self.rows.len());
+        ResultSet {
+            columns: self.columns.clone(),
+            rows: self.rows[start..end].to_vec(),
+            rows_affected: self.rows_affected,
+            last_insert_rowid: self.last_insert_rowid,
+            truncated: self.truncated,
+        }
+    }
+
     /// Agregar múltiples filas
     pub fn add_rows(&mut self, rows: Vec<Row>) {
         self.rows.extend(rows);
     }
 
+    /// Posición de la columna `name` (exacta o sin distinguir mayúsculas)
+    pub fn column_index(&self, name: &str) -> Result<usize> {
+        self.columns
+            .iter()
+            .position(|column| column.name == name)
+            .or_else(|| {
+                self.columns
+                    .iter()
+                    .position(|column| column.name.eq_ignore_ascii_case(name))
+            })
+            .ok_or_else(|| NoctraError::not_found("columna", name))
+    }
+
+    /// Copia con otras columnas y filas y los mismos contadores del original
+    pub(crate) fn derive(&self, columns: Vec<Column>, rows: Vec<Row>) -> ResultSet {
+        ResultSet {
+            columns,
+            rows,
+            rows_affected: self.rows_affected,
+            last_insert_rowid: self.last_insert_rowid,
+            truncated: self.truncated,
+        }
+    }
+
+    /// Copia sólo con las columnas `names`, en ese orden
+    pub fn select_columns(&self, names: &[&str]) -> Result<ResultSet> {
+        let indices = names
+            .iter()
+            .map(|name| self.column_index(name))
+            .collect::<Result<Vec<_>>>()?;
+        let columns = indices
+            .iter()
+            .enumerate()
+            .map(|(ordinal, &index)| Column {
+                ordinal,
+                ..self.columns[index].clone()
+            })
+            .collect();
+        let rows = self
+            .rows
+            .iter()
+            .map(|row| {
+                Row::new(
+                    indices
+                        .iter()
+                        .map(|&index| row.get(index).cloned().unwrap_or(Value::Null))
+                        .collect(),
+                )
+            })
+            .collect();
+        Ok(self.derive(columns, rows))
+    }
+
+    /// Copia con las filas que cumplen `predicate`
+    pub fn filter(&self, mut predicate: impl FnMut(&Row) -> bool) -> ResultSet {
+        let rows = self
+            .rows
+            .iter()
+            .filter(|row| predicate(row))
+            .cloned()
+            .collect();
+        self.derive(self.columns.clone(), rows)
+    }
+
+    /// Copia con los valores de la columna `name` transformados por `f`
+    pub fn map_column(&self, name: &str, mut f: impl FnMut(&Value) -> Value) -> Result<ResultSet> {
+        let index = self.column_index(name)?;
+        let mut result = self.clone();
+        for row in &mut result.rows {
+            if let Some(value) = row.values.get_mut(index) {
+                *value = f(value);
+            }
+        }
+        Ok(result)
+    }
+
+    /// Copia ordenada por las columnas `keys`, en orden de prioridad
+    ///
+    /// El orden es el de ORDER BY (`coerce::compare`: NULL primero) y
+    /// estable: las filas iguales conservan su orden.
+    pub fn sort_by(&self, keys: &[(&str, SortOrder)]) -> Result<ResultSet> {
+        let keys = keys
+            .iter()
+            .map(|(name, order)| Ok((self.column_index(name)?, *order)))
+            .collect::<Result<Vec<_>>>()?;
+        let mut rows = self.rows.clone();
+        rows.sort_by(|a, b| {
+            keys.iter()
+                .map(|&(index, order)| {
+                    let ordering = match (a.get(index), b.get(index)) {
+                        (Some(a), Some(b)) => coerce::compare(a, b),
+                        (a, b) => a.is_some().cmp(&b.is_some()),
+                    };
+                    match order {
+                        SortOrder::Ascending => ordering,
+                        SortOrder::Descending => ordering.reverse(),
+                    }
+                })
+                .find(|ordering| ordering.is_ne())
+                .unwrap_or(Ordering::Equal)
+        });
+        Ok(self.derive(self.columns.clone(), rows))
+    }
+
+    /// Filas de `self` seguidas de las de `other`
+    ///
+    /// Los esquemas deben coincidir: mismas columnas en el mismo orden y,
+    /// si ambos lo conocen, el mismo tipo.
+    pub fn concat(&self, other: &ResultSet) -> Result<ResultSet> {
+        let mismatch =
+            |detail: String| NoctraError::Validation(format!("esquemas incompatibles: {}", detail));
+        if self.columns.len() != other.columns.len() {
+            return Err(mismatch(format!(
+                "{} columnas frente a {}",
+                self.columns.len(),
+                other.columns.len()
+            )));
+        }
+        for (left, right) in self.columns.iter().zip(&other.columns) {
+            if !left.name.eq_ignore_ascii_case(&right.name) {
+                return Err(mismatch(format!(
+                    "columna '{}' frente a '{}'",
+                    left.name, right.name
+                )));
+            }
+            let known = !left.data_type.is_empty() && !right.data_type.is_empty();
+            if known && !left.data_type.eq_ignore_ascii_case(&right.data_type) {
+                return Err(mismatch(format!(
+                    "columna '{}' de tipo {} frente a {}",
+                    left.name, left.data_type, right.data_type
+                )));
+            }
+        }
+
+        let mut result = self.derive(self.columns.clone(), self.rows.clone());
+        result.rows.extend(other.rows.iter().cloned());
+        result.rows_affected = None;
+        result.last_insert_rowid = None;
+        result.truncated = self.truncated || other.truncated;
+        Ok(result)
+    }
+
+    /// Bytes del único valor del resultado (`SAVE BLOB`)
+    ///
+    /// El resultado debe tener exactamente una fila; se toma su primera
+    /// columna, que debe ser un BLOB (o texto, que se guarda como UTF-8).
+    pub fn single_blob(&self) -> Result<&[u8]> {
+        if self.rows.len() != 1 {
+            return Err(NoctraError::Validation(format!(
+                "SAVE BLOB espera una fila y la consulta devolvió {}",
+                self.rows.len()
+            )));
+        }
+        match self.rows[0].get(0) {
+            Some(Value::Blob(bytes)) => Ok(bytes),
+            Some(Value::Text(text)) => Ok(text.as_bytes()),
+            Some(other) => Err(NoctraError::Validation(format!(
+                "SAVE BLOB espera un BLOB y la columna es {}",
+                other.type_name()
+            ))),
+            None => Err(NoctraError::Validation(
+                "SAVE BLOB espera una columna y la consulta no devolvió ninguna".to_string(),
+            )),
+        }
+    }
+
+    /// Copia sin filas repetidas (se queda la primera de cada grupo)
+    pub fn distinct(&self) -> ResultSet {
+        // Value no es Hash (flotantes): se agrupa por la representación
+        // Debug, que distingue tipo y valor
+        let mut seen = std::collections::HashSet::new();
+        self.filter(|row| seen.insert(format!("{:?}", row.values)))
+    }
+
     /// Número de filas
     pub fn row_count(&self) -> usize {
         self.rows.len()
@@ -282,7 +801,7 @@ impl ResultSet {
                 .enumerate()
                 .map(|(i, _)| {
                     row.get(i)
-                        .map(|v| v.to_string())
+                        .map(Value::display_cell)
                         .unwrap_or_else(|| "NULL".to_string())
                 })
                 .collect();
@@ -297,8 +816,924 @@ impl ResultSet {
     }
 }
 
-/// Mapeo de parámetros
-pub type Parameters = HashMap<String, Value>;
+/// Mensajes del hilo productor de un `RowStream`
+enum StreamMessage {
+    Columns(Vec<Column>),
+    Row(Row),
+    Error(NoctraError),
+}
+
+/// Extremo productor de `RowStream::from_producer`
+#[derive(Debug)]
+pub struct RowSink {
+    sender: std::sync::mpsc::SyncSender<StreamMessage>,
+}
+
+impl RowSink {
+    /// Enviar las columnas; debe llamarse antes de la primera fila
+    ///
+    /// Devuelve false si el consumidor ya descartó el stream.
+    pub fn columns(&self, columns: Vec<Column>) -> bool {
+        self.sender.send(StreamMessage::Columns(columns)).is_ok()
+    }
+
+    /// Enviar una fila, esperando a que el consumidor la pida
+    ///
+    /// Devuelve false si el consumidor ya descartó el stream: el productor
+    /// debe terminar y liberar su sentencia.
+    pub fn send(&self, row: Row) -> bool {
+        self.sender.send(StreamMessage::Row(row)).is_ok()
+    }
+}
+
+/// Filas de una consulta producidas bajo demanda
+///
+/// Las columnas se conocen antes de leer la primera fila. Al descartar el
+/// stream (aunque no se haya leído entero) el backend libera la sentencia.
+pub struct RowStream {
+    columns: Vec<Column>,
+    rows: Box<dyn Iterator<Item = Result<Row>> + Send>,
+    /// Hilo productor (`from_producer`); se espera al descartar el stream
+    producer: Option<std::thread::JoinHandle<()>>,
+    /// Filas afectadas y último rowid de un resultado materializado
+    rows_affected: Option<u64>,
+    last_insert_rowid: Option<i64>,
+    /// El resultado materializado ya venía truncado
+    truncated: bool,
+}
+
+impl fmt::Debug for RowStream {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RowStream")
+            .field("columns", &self.columns)
+            .finish_non_exhaustive()
+    }
+}
+
+impl RowStream {
+    /// Stream sobre un iterador de filas
+    pub fn new<I>(columns: Vec<Column>, rows: I) -> Self
+    where
+        I: Iterator<Item = Result<Row>> + Send + 'static,
+    {
+        Self {
+            columns,
+            rows: Box::new(rows),
+            producer: None,
+            rows_affected: None,
+            last_insert_rowid: None,
+            truncated: false,
+        }
+    }
+
+    /// Stream sobre un resultado ya materializado
+    ///
+    /// Conserva las filas afectadas, el último rowid y la marca de truncado,
+    /// que `ResultSet::from_stream` vuelve a copiar.
+    pub fn from_result_set(result_set: ResultSet) -> Self {
+        let mut stream = Self::new(result_set.columns, result_set.rows.into_iter().map(Ok));
+        stream.rows_affected = result_set.rows_affected;
+        stream.last_insert_rowid = result_set.last_insert_rowid;
+        stream.truncated = result_set.truncated;
+        stream
+    }
+
+    /// Producir las filas en un hilo aparte
+    ///
+    /// Sirve a los backends cuyas filas toman prestada la sentencia (que a
+    /// su vez toma prestada la conexión): `producer` se queda con ambas en
+    /// su hilo, envía las columnas y después cada fila, bloqueándose hasta
+    /// que el consumidor la pide. Los errores previos a las columnas se
+    /// devuelven aquí; los posteriores, como un elemento `Err` del stream.
+    pub fn from_producer<F>(producer: F) -> Result<Self>
+    where
+        F: FnOnce(&RowSink) -> Result<()> + Send + 'static,
+    {
+        let (sender, receiver) = std::sync::mpsc::sync_channel(0);
+        let handle = std::thread::spawn(move || {
+            let sink = RowSink { sender };
+            if let Err(e) = producer(&sink) {
+                let _ = sink.sender.send(StreamMessage::Error(e));
+            }
+        });
+
+        let columns = match receiver.recv() {
+            Ok(StreamMessage::Columns(columns)) => columns,
+            Ok(StreamMessage::Error(e)) => {
+                let _ = handle.join();
+                return Err(e);
+            }
+            Ok(StreamMessage::Row(_)) | Err(_) => {
+                let _ = handle.join();
+                return Err(NoctraError::Internal(
+                    "El productor del stream no envió las columnas".to_string(),
+                ));
+            }
+        };
+
+        let rows = receiver.into_iter().filter_map(|message| match message {
+            StreamMessage::Row(row) => Some(Ok(row)),
+            StreamMessage::Error(e) => Some(Err(e)),
+            StreamMessage::Columns(_) => None,
+        });
+        let mut stream = Self::new(columns, rows);
+        stream.producer = Some(handle);
+        Ok(stream)
+    }
+
+    /// Columnas del resultado
+    pub fn columns(&self) -> &[Column] {
+        &self.columns
+    }
+
+    /// Filas afectadas, si el stream viene de una sentencia ya ejecutada
+    pub fn rows_affected(&self) -> Option<u64> {
+        self.rows_affected
+    }
+
+    /// Si el resultado materializado del que viene ya estaba truncado
+    pub fn is_truncated(&self) -> bool {
+        self.truncated
+    }
+}
+
+impl Iterator for RowStream {
+    type Item = Result<Row>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        self.rows.next()
+    }
+}
+
+impl Drop for RowStream {
+    fn drop(&mut self) {
+        // Cerrar el canal despierta al productor, que suelta la sentencia
+        self.rows = Box::new(std::iter::empty());
+        if let Some(handle) = self.producer.take() {
+            let _ = handle.join();
+        }
+    }
+}
+
+/// Parámetros de una consulta
+///
+/// Los posicionales (`?`, `?N`, `$N`) van en orden: el primero enlaza `$1`.
+/// Los nombrados (`:dept`, `$dept`, `@dept`) van en un mapa cuya clave puede
+/// llevar el prefijo o no. Una clave numérica nombrada (`"2"`, `"?2"`) sigue
+/// enlazando `$2` si no hay posicional en esa posición, como cuando los
+/// parámetros eran un `HashMap`.
+///
+/// En JSON se escribe como un objeto (sólo nombrados), un array (sólo
+/// posicionales) o `{"positional": [...], "named": {...}}`.
+#[derive(Debug, Clone, Default, PartialEq, Serialize, Deserialize)]
+#[serde(from = "ParametersRepr", into = "ParametersRepr")]
+pub struct Parameters {
+    positional: Vec<Value>,
+    named: HashMap<String, Value>,
+}
+
+impl Parameters {
+    /// Parámetros vacíos
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Parámetros posicionales, en orden (`$1`, `$2`, ...)
+    pub fn positional<I>(values: I) -> Self
+    where
+        I: IntoIterator,
+        I::Item: Into<Value>,
+    {
+        Self {
+            positional: values.into_iter().map(Into::into).collect(),
+            named: HashMap::new(),
+        }
+    }
+
+    /// Parámetros nombrados a partir de textos, como los valores de un
+    /// formulario: todos quedan como `Value::Text`
+    pub fn from_text_map(values: HashMap<String, String>) -> Self {
+        values
+            .into_iter()
+            .map(|(name, value)| (name, Value::Text(value)))
+            .collect()
+    }
+
+    /// Añadir un parámetro nombrado (builder)
+    pub fn with<N: Into<String>, V: Into<Value>>(mut self, name: N, value: V) -> Self {
+        self.insert(name, value);
+        self
+    }
+
+    /// Añadir un posicional al final
+    pub fn push<V: Into<Value>>(&mut self, value: V) {
+        self.positional.push(value.into());
+    }
+
+    /// Fijar el posicional `index` (desde 0); los huecos quedan a NULL
+    pub fn set_positional<V: Into<Value>>(&mut self, index: usize, value: V) {
+        if self.positional.len() <= index {
+            self.positional.resize(index + 1, Value::Null);
+        }
+        self.positional[index] = value.into();
+    }
+
+    /// Insertar un parámetro nombrado; devuelve el valor anterior
+    pub fn insert<N: Into<String>, V: Into<Value>>(&mut self, name: N, value: V) -> Option<Value> {
+        self.named.insert(name.into(), value.into())
+    }
+
+    /// Parámetro nombrado con la clave exacta
+    pub fn get(&self, name: &str) -> Option<&Value> {
+        self.named.get(name)
+    }
+
+    /// Valor para un placeholder del SQL
+    ///
+    /// `dept` se busca como `dept`, `:dept`, `$dept` o `@dept`; los
+    /// posicionales (`?2`, `$2`) toman el segundo posicional o, si no lo
+    /// hay, la clave `2`, `?2` o `$2`.
+    pub fn lookup(&self, name: &str) -> Option<&Value> {
+        let bare = name.trim_start_matches('?');
+        let positional = bare
+            .parse::<usize>()
+            .ok()
+            .and_then(|n| n.checked_sub(1))
+            .and_then(|index| self.positional.get(index));
+        positional.or_else(|| {
+            ["", ":", "$", "@", "?"]
+                .iter()
+                .find_map(|prefix| self.named.get(&format!("{}{}", prefix, bare)))
+        })
+    }
+
+    /// Posicionales, en orden
+    pub fn positional_values(&self) -> &[Value] {
+        &self.positional
+    }
+
+    /// Nombrados
+    pub fn named_values(&self) -> &HashMap<String, Value> {
+        &self.named
+    }
+
+    /// Número de parámetros (posicionales y nombrados)
+    pub fn len(&self) -> usize {
+        self.positional.len() + self.named.len()
+    }
+
+    /// Si no hay ningún parámetro
+    pub fn is_empty(&self) -> bool {
+        self.positional.is_empty() && self.named.is_empty()
+    }
+
+    /// Vaciar los parámetros
+    pub fn clear(&mut self) {
+        self.positional.clear();
+        self.named.clear();
+    }
+
+    /// Parámetros con su nombre: primero los posicionales (`1`, `2`, ...) en
+    /// orden y después los nombrados
+    pub fn iter(&self) -> impl Iterator<Item = (Cow<'_, str>, &Value)> {
+        self.positional
+            .iter()
+            .enumerate()
+            .map(|(index, value)| (Cow::Owned((index + 1).to_string()), value))
+            .chain(
+                self.named
+                    .iter()
+                    .map(|(name, value)| (Cow::Borrowed(name.as_str()), value)),
+            )
+    }
+
+    /// Leer parámetros de JSON (peticiones REST, FFI)
+    ///
+    /// Un objeto da parámetros nombrados (`:name`) y un array posicionales
+    /// (`$1`, `$2`, ...); también vale la forma mixta
+    /// `{"positional": [...], "named": {...}}`. Un objeto que mezcla claves
+    /// numéricas (`"1"`, `"$2"`) con nombres es ambiguo y devuelve
+    /// `NoctraError::InvalidParameters`.
+    ///
+    /// Los valores son JSON plano: un texto es `Value::Text`, un número
+    /// `Value::Integer` (o `Value::Float` si no es entero), `true`/`false`
+    /// `Value::Boolean`, `null` `Value::Null` y un array u objeto
+    /// `Value::Json`.
+    pub fn from_json(json: &str) -> Result<Self> {
+        let value: serde_json::Value = serde_json::from_str(json)
+            .map_err(|e| NoctraError::InvalidParameters(e.to_string()))?;
+        Self::from_json_value(value)
+    }
+
+    /// Como `from_json`, sobre un JSON ya leído
+    pub fn from_json_value(value: serde_json::Value) -> Result<Self> {
+        use serde_json::Value as Json;
+
+        let map = match value {
+            Json::Array(values) => {
+                return Ok(Self::positional(values.into_iter().map(json_parameter)));
+            }
+            Json::Object(map) => map,
+            other => {
+                return Err(NoctraError::InvalidParameters(format!(
+                    "se esperaba un objeto o un array de parámetros, no {}",
+                    other
+                )));
+            }
+        };
+
+        if map.len() == 2 {
+            if let (Some(Json::Array(positional)), Some(Json::Object(named))) =
+                (map.get("positional"), map.get("named"))
+            {
+                return Ok(Self {
+                    positional: positional.iter().cloned().map(json_parameter).collect(),
+                    named: named
+                        .iter()
+                        .map(|(name, value)| (name.clone(), json_parameter(value.clone())))
+                        .collect(),
+                });
+            }
+        }
+
+        let numeric = map
+            .keys()
+            .filter(|key| key.trim_start_matches(['?', '$']).parse::<usize>().is_ok())
+            .count();
+        if numeric > 0 && numeric < map.len() {
+            return Err(NoctraError::InvalidParameters(
+                "el objeto mezcla claves numéricas y nombres; usa {\"positional\": [...], \"named\": {...}}"
+                    .to_string(),
+            ));
+        }
+        Ok(map
+            .into_iter()
+            .map(|(name, value)| (name, json_parameter(value)))
+            .collect())
+    }
+
+    /// Escribir los parámetros como JSON plano (la forma que lee `from_json`)
+    ///
+    /// Fechas, horas y decimales se escriben como texto, los blobs como
+    /// arrays de bytes y los arrays como arrays JSON; al leerlos de nuevo
+    /// vuelven como `Value::Text` o `Value::Json`.
+    pub fn to_json(&self) -> Result<String> {
+        let positional =
+            || serde_json::Value::Array(self.positional.iter().map(parameter_json).collect());
+        let named = || {
+            serde_json::Value::Object(
+                self.named
+                    .iter()
+                    .map(|(name, value)| (name.clone(), parameter_json(value)))
+                    .collect(),
+            )
+        };
+        let json = if self.positional.is_empty() {
+            named()
+        } else if self.named.is_empty() {
+            positional()
+        } else {
+            serde_json::json!({ "positional": positional(), "named": named() })
+        };
+        serde_json::to_string(&json).map_err(|e| NoctraError::Serialization(e.to_string()))
+    }
+}
+
+/// Valor de un parámetro escrito en JSON plano
+fn json_parameter(value: serde_json::Value) -> Value {
+    match value {
+        serde_json::Value::Null => Value::Null,
+        serde_json::Value::Bool(b) => Value::Boolean(b),
+        serde_json::Value::Number(n) => match n.as_i64() {
+            Some(i) => Value::Integer(i),
+            None => n.as_f64().map(Value::Float).unwrap_or(Value::Null),
+        },
+        serde_json::Value::String(s) => Value::Text(s),
+        json @ (serde_json::Value::Array(_) | serde_json::Value::Object(_)) => Value::Json(json),
+    }
+}
+
+/// JSON plano de un parámetro (inverso de `json_parameter`)
+fn parameter_json(value: &Value) -> serde_json::Value {
+    match value {
+        Value::Null => serde_json::Value::Null,
+        Value::Boolean(b) => serde_json::Value::Bool(*b),
+        Value::Integer(_) | Value::Float(_) => value
+            .to_json_number()
+            .map(serde_json::Value::Number)
+            .unwrap_or(serde_json::Value::Null),
+        Value::Blob(bytes) => bytes.iter().copied().map(serde_json::Value::from).collect(),
+        Value::Array(values) => values.iter().map(parameter_json).collect(),
+        Value::Json(json) => json.clone(),
+        other => serde_json::Value::String(other.to_string()),
+    }
+}
+
+impl FromIterator<(String, Value)> for Parameters {
+    fn from_iter<I: IntoIterator<Item = (String, Value)>>(iter: I) -> Self {
+        Self {
+            positional: Vec::new(),
+            named: iter.into_iter().collect(),
+        }
+    }
+}
+
+impl Extend<(String, Value)> for Parameters {
+    fn extend<I: IntoIterator<Item = (String, Value)>>(&mut self, iter: I) {
+        self.named.extend(iter);
+    }
+}
+
+impl From<Vec<Value>> for Parameters {
+    fn from(values: Vec<Value>) -> Self {
+        Self::positional(values)
+    }
+}
+
+impl From<HashMap<String, Value>> for Parameters {
+    fn from(named: HashMap<String, Value>) -> Self {
+        Self {
+            positional: Vec::new(),
+            named,
+        }
+    }
+}
+
+/// Forma JSON de `Parameters`
+#[derive(Serialize, Deserialize)]
+#[serde(untagged)]
+enum ParametersRepr {
+    Positional(Vec<Value>),
+    Mixed(MixedParameters),
+    Named(HashMap<String, Value>),
+}
+
+#[derive(Serialize, Deserialize)]
+#[serde(deny_unknown_fields)]
+struct MixedParameters {
+    positional: Vec<Value>,
+    named: HashMap<String, Value>,
+}
+
+impl From<ParametersRepr> for Parameters {
+    fn from(repr: ParametersRepr) -> Self {
+        match repr {
+            ParametersRepr::Positional(positional) => Self::positional(positional),
+            ParametersRepr::Mixed(MixedParameters { positional, named }) => {
+                Self { positional, named }
+            }
+            ParametersRepr::Named(named) => Self::from(named),
+        }
+    }
+}
+
+impl From<Parameters> for ParametersRepr {
+    fn from(parameters: Parameters) -> Self {
+        let Parameters { positional, named } = parameters;
+        if positional.is_empty() {
+            ParametersRepr::Named(named)
+        } else if named.is_empty() {
+            ParametersRepr::Positional(positional)
+        } else {
+            ParametersRepr::Mixed(MixedParameters { positional, named })
+        }
+    }
+}
 
 /// Variables de sesión
 pub type SessionVariables = HashMap<String, Value>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_is_numeric_type_matches_whole_words() {
+        for numeric in [
+            "INTEGER",
+            "int4",
+            "BIGINT",
+            "UNSIGNED BIG INT",
+            "DECIMAL(10,2)",
+            "double precision",
+            "FLOAT8",
+            "HUGEINT",
+        ] {
+            assert!(is_numeric_type(numeric), "{}", numeric);
+        }
+        for other in ["INTERVAL", "POINT", "TEXT", "TIMESTAMP", "_int4", ""] {
+            assert!(!is_numeric_type(other), "{}", other);
+        }
+    }
+
+    #[test]
+    fn test_timestamp_ordering() {
+        let earlier = Value::Timestamp(parse_timestamp("2024-01-31 23:59:59").unwrap());
+        let later = Value::Timestamp(parse_timestamp("2024-02-01T00:00:00+01:00").unwrap());
+        let latest = Value::Timestamp(parse_timestamp("2024-02-01T00:00:00.5Z").unwrap());
+
+        // 00:00 en +01:00 son las 23:00 UTC del día anterior
+        assert!(later < earlier);
+        assert!(earlier < latest);
+        assert_eq!(earlier.partial_cmp(&Value::Text("x".to_string())), None);
+        assert_eq!(later.to_string(), "2024-01-31T23:00:00Z");
+        assert_eq!(latest.to_string(), "2024-02-01T00:00:00.500Z");
+    }
+
+    #[test]
+    fn test_temporal_and_blob_json_round_trip() {
+        let values = vec![
+            Value::Date(NaiveDate::from_ymd_opt(2024, 2, 29).unwrap()),
+            Value::Time(NaiveTime::from_hms_milli_opt(8, 30, 0, 250).unwrap()),
+            Value::Timestamp(parse_timestamp("2024-02-29T08:30:00Z").unwrap()),
+            Value::Blob(vec![0xde, 0xad, 0xbe, 0xef]),
+        ];
+
+        let json = serde_json::to_string(&values).unwrap();
+        let back: Vec<Value> = serde_json::from_str(&json).unwrap();
+        assert_eq!(back, values);
+
+        assert_eq!(values[0].to_string(), "2024-02-29");
+        assert_eq!(values[1].to_string(), "08:30:00.250");
+        assert_eq!(values[3].to_string(), "0xdeadbeef");
+        assert_eq!(
+            Value::Blob((0..20).collect()).to_string(),
+            "0x000102030405060708090a0b0c0d0e0f… (20 bytes)"
+        );
+    }
+
+    #[test]
+    fn test_parameters_mix_positional_and_named() {
+        let params = Parameters::positional([Value::Integer(5), Value::from("ana")])
+            .with("dept", "eng")
+            .with("3", true);
+
+        assert_eq!(params.len(), 4);
+        assert_eq!(params.lookup("?1"), Some(&Value::Integer(5)));
+        assert_eq!(params.lookup("2"), Some(&Value::Text("ana".to_string())));
+        // Sin tercer posicional vale la clave nombrada `3`
+        assert_eq!(params.lookup("?3"), Some(&Value::Boolean(true)));
+        assert_eq!(params.lookup("dept"), Some(&Value::Text("eng".to_string())));
+        assert_eq!(params.lookup("4"), None);
+
+        let names: Vec<_> = params.iter().map(|(name, _)| name.into_owned()).collect();
+        assert_eq!(&names[..2], ["1", "2"]);
+        assert_eq!(names.len(), 4);
+
+        let mut session_like = Parameters::new();
+        session_like.set_positional(2, 7i64);
+        assert_eq!(
+            session_like.positional_values(),
+            [Value::Null, Value::Null, Value::Integer(7)]
+        );
+    }
+
+    #[test]
+    fn test_parameters_from_legacy_maps() {
+        let form: HashMap<String, String> =
+            [("email".to_string(), "ana@example.com".to_string())].into();
+        let params = Parameters::from_text_map(form);
+        assert_eq!(
+            params.get("email"),
+            Some(&Value::Text("ana@example.com".to_string()))
+        );
+        assert!(params.positional_values().is_empty());
+
+        let mut values = HashMap::new();
+        values.insert(":min".to_string(), Value::Integer(4));
+        let params = Parameters::from(values);
+        assert_eq!(params.lookup("min"), Some(&Value::Integer(4)));
+
+        let collected: Parameters = vec![("x".to_string(), Value::Null)].into_iter().collect();
+        assert_eq!(collected.len(), 1);
+    }
+
+    #[test]
+    fn test_parameters_json_forms() {
+        let named: Parameters = serde_json::from_str(r#"{"dept": {"Text": "eng"}}"#).unwrap();
+        assert_eq!(named, Parameters::new().with("dept", "eng"));
+
+        let positional: Parameters = serde_json::from_str(r#"[{"Integer": 1}]"#).unwrap();
+        assert_eq!(positional, Parameters::positional([1i64]));
+
+        let mixed = Parameters::positional([1i64]).with("dept", "eng");
+        let json = serde_json::to_string(&mixed).unwrap();
+        assert!(json.starts_with(r#"{"positional":"#));
+        assert_eq!(serde_json::from_str::<Parameters>(&json).unwrap(), mixed);
+        assert_eq!(
+            serde_json::to_string(&named).unwrap(),
+            r#"{"dept":{"Text":"eng"}}"#
+        );
+    }
+
+    #[test]
+    fn test_parameters_json_round_trip_all_values() {
+        let values = vec![
+            Value::Null,
+            Value::Integer(-7),
+            Value::Float(2.5),
+            Value::Text("Pérez, \"Luis\"".to_string()),
+            Value::Boolean(true),
+            Value::Date(NaiveDate::from_ymd_opt(2024, 2, 29).unwrap()),
+            Value::Time(NaiveTime::from_hms_milli_opt(8, 30, 0, 250).unwrap()),
+            Value::Timestamp(parse_timestamp("2024-02-29T08:30:00Z").unwrap()),
+            Value::Blob(vec![0, 0xff]),
+            Value::Array(vec![Value::Integer(1), Value::Null]),
+            Value::Json(serde_json::json!({"a": [1, "b"]})),
+        ];
+        #[cfg(feature = "decimal")]
+        let values = [values, vec![Value::Decimal("12.34".parse().unwrap())]].concat();
+
+        let positional = Parameters::positional(values.clone());
+        let named: Parameters = values
+            .iter()
+            .enumerate()
+            .map(|(i, value)| (format!("p{}", i), value.clone()))
+            .collect();
+        let mixed = Parameters::positional(values.clone()).with("dept", "eng");
+        for params in [positional, named, mixed] {
+            let json = serde_json::to_string(&params).unwrap();
+            assert_eq!(serde_json::from_str::<Parameters>(&json).unwrap(), params);
+        }
+
+        // En JSON plano vuelven igual los valores que JSON representa
+        let plain = Parameters::positional(vec![
+            Value::Null,
+            Value::Integer(-7),
+            Value::Float(2.5),
+            Value::Text("Pérez, \"Luis\"".to_string()),
+            Value::Boolean(true),
+            Value::Json(serde_json::json!({"a": [1, "b"]})),
+        ])
+        .with("dept", "eng");
+        let json = plain.to_json().unwrap();
+        assert_eq!(Parameters::from_json(&json).unwrap(), plain);
+        let dated = Parameters::new().with(
+            "d",
+            Value::Date(NaiveDate::from_ymd_opt(2024, 2, 29).unwrap()),
+        );
+        assert_eq!(dated.to_json().unwrap(), r#"{"d":"2024-02-29"}"#);
+
+        let numeric = Parameters::from_json(r#"{"1": 5}"#).unwrap();
+        assert_eq!(numeric.lookup("?1"), Some(&Value::Integer(5)));
+        for json in [
+            r#"{"1": 5, "dept": "eng"}"#,
+            r#"{"$2": "Null", "name": "Null"}"#,
+            "42",
+            "{",
+        ] {
+            assert!(matches!(
+                Parameters::from_json(json),
+                Err(NoctraError::InvalidParameters(_))
+            ));
+        }
+    }
+
+    #[test]
+    fn test_parameters_from_plain_json() {
+        let params = Parameters::from_json(
+            r#"{"dept": "Ventas", "limit": 10, "ratio": 0.5, "activo": false, "nada": null, "tags": ["a"], "meta": {"k": 1}}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            params.lookup("dept"),
+            Some(&Value::Text("Ventas".to_string()))
+        );
+        assert_eq!(params.lookup("limit"), Some(&Value::Integer(10)));
+        assert_eq!(params.lookup("ratio"), Some(&Value::Float(0.5)));
+        assert_eq!(params.lookup("activo"), Some(&Value::Boolean(false)));
+        assert_eq!(params.lookup("nada"), Some(&Value::Null));
+        assert_eq!(
+            params.lookup("tags"),
+            Some(&Value::Json(serde_json::json!(["a"])))
+        );
+        assert_eq!(
+            params.lookup("meta"),
+            Some(&Value::Json(serde_json::json!({"k": 1})))
+        );
+
+        let positional = Parameters::from_json(r#"[3, "x"]"#).unwrap();
+        assert_eq!(
+            positional,
+            Parameters::positional([Value::Integer(3), Value::from("x")])
+        );
+
+        let mixed =
+            Parameters::from_json(r#"{"positional": [1], "named": {"dept": "eng"}}"#).unwrap();
+        assert_eq!(mixed, Parameters::positional([1i64]).with("dept", "eng"));
+    }
+
+    #[test]
+    fn test_value_from_literal() {
+        assert_eq!(Value::from_literal("'IT'"), Value::Text("IT".to_string()));
+        assert_eq!(
+            Value::from_literal("'O''Brien'"),
+            Value::Text("O'Brien".to_string())
+        );
+        assert_eq!(Value::from_literal("\"42\""), Value::Text("42".to_string()));
+        assert_eq!(Value::from_literal(" 42 "), Value::Integer(42));
+        assert_eq!(Value::from_literal("-1.5"), Value::Float(-1.5));
+        assert_eq!(Value::from_literal("true"), Value::Boolean(true));
+        assert_eq!(Value::from_literal("NULL"), Value::Null);
+        assert_eq!(Value::from_literal("nan"), Value::Text("nan".to_string()));
+        assert_eq!(
+            Value::from_literal("ventas"),
+            Value::Text("ventas".to_string())
+        );
+    }
+
+    #[derive(Debug, PartialEq, Deserialize)]
+    struct Pedido {
+        id: i64,
+        cliente: String,
+        importe: f64,
+        pagado: bool,
+        nota: Option<String>,
+        creado: DateTime<Utc>,
+        descuento: Option<f64>,
+    }
+
+    fn pedidos() -> ResultSet {
+        let columns = ["id", "cliente", "importe", "pagado", "nota", "creado"]
+            .iter()
+            .enumerate()
+            .map(|(ordinal, name)| Column::new(*name, "TEXT", ordinal))
+            .collect();
+        let mut result = ResultSet::new(columns);
+        let creado = parse_timestamp("2024-03-01T10:00:00Z").unwrap();
+        result.add_row(Row::new(vec![
+            Value::Integer(1),
+            Value::Text("ana".to_string()),
+            Value::Float(12.5),
+            Value::Boolean(true),
+            Value::Text("urgente".to_string()),
+            Value::Timestamp(creado),
+        ]));
+        // Entero en columna f64 y booleano guardado como 0 (SQLite)
+        result.add_row(Row::new(vec![
+            Value::Integer(2),
+            Value::Text("luis".to_string()),
+            Value::Integer(30),
+            Value::Integer(0),
+            Value::Null,
+            Value::Timestamp(creado),
+        ]));
+        result
+    }
+
+    #[test]
+    fn test_result_set_deserialize_by_column_name() {
+        let result = pedidos();
+        let pedidos: Vec<Pedido> = result.deserialize().unwrap();
+
+        assert_eq!(
+            pedidos[0],
+            Pedido {
+                id: 1,
+                cliente: "ana".to_string(),
+                importe: 12.5,
+                pagado: true,
+                nota: Some("urgente".to_string()),
+                creado: parse_timestamp("2024-03-01T10:00:00Z").unwrap(),
+                descuento: None,
+            }
+        );
+        assert_eq!(pedidos[1].importe, 30.0);
+        assert!(!pedidos[1].pagado);
+        assert_eq!(pedidos[1].nota, None);
+
+        let row = &result.rows[1];
+        assert_eq!(row.get_as::<i64>(&result.columns, "id").unwrap(), 2);
+        assert_eq!(
+            row.get_as::<Option<String>>(&result.columns, "nota")
+                .unwrap(),
+            None
+        );
+        assert_eq!(
+            row.get_as::<String>(&result.columns, "cliente").unwrap(),
+            "luis"
+        );
+    }
+
+    #[test]
+    fn test_result_set_deserialize_errors_name_column() {
+        let mut result = pedidos();
+        result.rows[1].values[0] = Value::Text("dos".to_string());
+
+        let error = result.deserialize::<Pedido>().unwrap_err().to_string();
+        assert!(error.contains("fila 2"), "{}", error);
+        assert!(error.contains("columna 'id'"), "{}", error);
+        assert!(error.contains("se esperaba i64"), "{}", error);
+        assert!(error.contains("\"dos\""), "{}", error);
+
+        // NULL en un campo no opcional
+        let row = &result.rows[1];
+        let error = row
+            .get_as::<String>(&result.columns, "nota")
+            .unwrap_err()
+            .to_string();
+        assert!(error.contains("columna 'nota'"), "{}", error);
+        assert!(row.get_as::<i64>(&result.columns, "otra").is_err());
+        assert!(row.get_as::<i64>(&result.columns, "importe").is_ok());
+        assert!(row.get_as::<String>(&result.columns, "importe").is_err());
+    }
+
+    #[test]
+    fn test_result_set_page() {
+        let mut result = ResultSet::new(vec![Column::new("n", "INTEGER", 0)]);
+        result.add_rows((1..=5).map(|n| Row::new(vec![Value::Integer(n)])).collect());
+
+        let values = |page: ResultSet| -> Vec<Value> {
+            page.rows
+                .into_iter()
+                .map(|row| row.values[0].clone())
+                .collect()
+        };
+        assert_eq!(
+            values(result.page(1, 2)),
+            vec![Value::Integer(2), Value::Integer(3)]
+        );
+        assert_eq!(values(result.page(4, 10)), vec![Value::Integer(5)]);
+        assert!(result.page(9, 2).rows.is_empty());
+        assert_eq!(result.page(0, 2).columns.len(), 1);
+    }
+
+    fn people() -> ResultSet {
+        let mut result = ResultSet::new(vec![
+            Column::new("id", "INTEGER", 0),
+            Column::new("nombre", "TEXT", 1),
+            Column::new("edad", "INTEGER", 2),
+        ]);
+        for (id, nombre, edad) in [(1, "Ana", 30), (2, "Luis", 25), (3, "Eva", 30)] {
+            result.add_row(Row::new(vec![
+                Value::Integer(id),
+                Value::Text(nombre.into()),
+                Value::Integer(edad),
+            ]));
+        }
+        result
+    }
+
+    #[test]
+    fn test_result_set_select_filter_map_and_sort() {
+        let result = people();
+
+        let selected = result.select_columns(&["EDAD", "nombre"]).unwrap();
+        assert_eq!(selected.columns[0].name, "edad");
+        assert_eq!(selected.columns[1].ordinal, 1);
+        assert_eq!(
+            selected.rows[1].values,
+            vec![Value::Integer(25), Value::Text("Luis".into())]
+        );
+        assert!(result.select_columns(&["otra"]).is_err());
+
+        let adults = result.filter(|row| row.values[2] == Value::Integer(30));
+        assert_eq!(adults.row_count(), 2);
+
+        let upper = result
+            .map_column("nombre", |value| {
+                Value::Text(value.to_string().to_uppercase())
+            })
+            .unwrap();
+        assert_eq!(upper.rows[0].values[1], Value::Text("ANA".into()));
+        assert_eq!(result.rows[0].values[1], Value::Text("Ana".into()));
+
+        let sorted = result
+            .sort_by(&[
+                ("edad", SortOrder::Descending),
+                ("nombre", SortOrder::Ascending),
+            ])
+            .unwrap();
+        let ids: Vec<Value> = sorted
+            .rows
+            .iter()
+            .map(|row| row.values[0].clone())
+            .collect();
+        assert_eq!(
+            ids,
+            vec![Value::Integer(1), Value::Integer(3), Value::Integer(2)]
+        );
+    }
+
+    #[test]
+    fn test_result_set_concat_and_distinct() {
+        let result = people();
+
+        let doubled = result.concat(&result).unwrap();
+        assert_eq!(doubled.row_count(), 6);
+        assert_eq!(doubled.distinct().row_count(), 3);
+
+        let error = result
+            .concat(&result.select_columns(&["id", "nombre"]).unwrap())
+            .unwrap_err();
+        assert!(
+            error.to_string().contains("esquemas incompatibles"),
+            "{}",
+            error
+        );
+
+        let mut other = people();
+        other.columns[2].data_type = "TEXT".to_string();
+        assert!(result.concat(&other).is_err());
+    }
+}
@@ -0,0 +1,580 @@
+//! Conversión y comparación de valores
+//!
+//! Las partes que evalúan SQL en memoria (el JOIN federado, las expresiones
+//! de `LET` y de los formularios) comparan con estas funciones para que
+//! WHERE y ORDER BY den lo mismo que en el backend:
+//!
+//! - `compare` es un orden total para ORDER BY: NULL primero, luego
+//!   booleanos, números (enteros, flotantes y decimales entre sí), fechas,
+//!   horas, texto, binarios, arrays y JSON. NaN va detrás de todos los números.
+//! - `compare_sql` es la comparación de un WHERE: con NULL no hay resultado y
+//!   un texto numérico (un CSV leído como texto) se compara como número.
+//! - `equals_sql` sigue la semántica de SQL: NULL no es igual ni a NULL.
+
+use std::cmp::Ordering;
+
+use chrono::{NaiveDate, NaiveTime};
+
+use super::{parse_timestamp, Value};
+use crate::error::{NoctraError, Result};
+
+/// Cómo se comparan los textos
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Collation {
+    /// Byte a byte, como `BINARY` en SQLite
+    #[default]
+    Binary,
+
+    /// Sin distinguir mayúsculas de minúsculas, como `NOCASE`
+    NoCase,
+}
+
+/// Orden total de dos valores (ORDER BY), con colación binaria
+pub fn compare(a: &Value, b: &Value) -> Ordering {
+    compare_collated(a, b, Collation::Binary)
+}
+
+/// Orden total de dos valores con la colación indicada para los textos
+pub fn compare_collated(a: &Value, b: &Value, collation: Collation) -> Ordering {
+    let (rank_a, rank_b) = (rank(a), rank(b));
+    if rank_a != rank_b {
+        return rank_a.cmp(&rank_b);
+    }
+    same_rank(a, b, collation).unwrap_or(Ordering::Equal)
+}
+
+/// Comparación de un WHERE: `None` si algún lado es NULL o no son comparables
+///
+/// Un texto frente a un número, una fecha o un booleano se convierte al tipo
+/// del otro lado antes de comparar (`'7' = 7`, `'2024-01-31' < fecha`).
+pub fn compare_sql(a: &Value, b: &Value, collation: Collation) -> Option<Ordering> {
+    if a.is_null() || b.is_null() {
+        return None;
+    }
+    if rank(a) == rank(b) {
+        return same_rank(a, b, collation);
+    }
+    match (a, b) {
+        (Value::Text(text), other) => text_against(text, other).map(Ordering::reverse),
+        (other, Value::Text(text)) => text_against(text, other),
+        (Value::Boolean(flag), other) => Some(compare_numbers(
+            &Number::Int(i64::from(*flag)),
+            &number(other)?,
+        )),
+        (other, Value::Boolean(flag)) => Some(compare_numbers(
+            &number(other)?,
+            &Number::Int(i64::from(*flag)),
+        )),
+        _ => None,
+    }
+}
+
+/// Igualdad de SQL: `None` (desconocido) si algún lado es NULL
+pub fn equals_sql(a: &Value, b: &Value) -> Option<bool> {
+    if a.is_null() || b.is_null() {
+        return None;
+    }
+    Some(compare_sql(a, b, Collation::Binary) == Some(Ordering::Equal))
+}
+
+/// Convertir un valor al tipo SQL `target_type` (`INTEGER`, `VARCHAR(20)`, ...)
+///
+/// NULL sigue siendo NULL. Falla con `NoctraError::Validation` si el valor no
+/// se puede representar en ese tipo (`'abc'` como INTEGER, 2.5 como
+/// INTEGER) o si el tipo no se conoce.
+pub fn try_coerce(value: &Value, target_type: &str) -> Result<Value> {
+    if value.is_null() {
+        return Ok(Value::Null);
+    }
+    let base = target_type
+        .split('(')
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_uppercase();
+    let coerced = match base.as_str() {
+        "INTEGER" | "INT" | "BIGINT" | "SMALLINT" | "TINYINT" | "HUGEINT" | "INT8" | "INT4" => {
+            to_integer(value)
+        }
+        "FLOAT" | "REAL" | "DOUBLE" | "DOUBLE PRECISION" | "FLOAT8" => to_float(value),
+        #[cfg(not(feature = "decimal"))]
+        "NUMERIC" | "DECIMAL" => to_float(value),
+        #[cfg(feature = "decimal")]
+        "NUMERIC" | "DECIMAL" => to_decimal(value),
+        "TEXT" | "VARCHAR" | "CHAR" | "STRING" | "CLOB" => Some(match value {
+            Value::Text(text) => Value::Text(text.clone()),
+            other => Value::Text(other.to_string()),
+        }),
+        "BOOLEAN" | "BOOL" => to_boolean(value),
+        "DATE" => to_date(value),
+        "TIME" => to_time(value),
+        "TIMESTAMP" | "DATETIME" | "TIMESTAMPTZ" | "TIMESTAMP WITH TIME ZONE" => {
+            to_timestamp(value)
+        }
+        "BLOB" | "BYTEA" | "BINARY" | "VARBINARY" => match value {
+            Value::Blob(bytes) => Some(Value::Blob(bytes.clone())),
+            Value::Text(text) => Some(Value::Blob(text.as_bytes().to_vec())),
+            _ => None,
+        },
+        _ => {
+            return Err(NoctraError::Validation(format!(
+                "tipo desconocido: {}",
+                target_type
+            )))
+        }
+    };
+    coerced.ok_or_else(|| {
+        NoctraError::Validation(format!(
+            "no se puede convertir {} ({}) a {}",
+            value,
+            value.type_name(),
+            target_type
+        ))
+    })
+}
+
+/// Posición de la familia de tipos en el orden total
+fn rank(value: &Value) -> u8 {
+    match value {
+        Value::Null => 0,
+        Value::Boolean(_) => 1,
+        Value::Integer(_) | Value::Float(_) => 2,
+        #[cfg(feature = "decimal")]
+        Value::Decimal(_) => 2,
+        Value::Date(_) | Value::Timestamp(_) => 3,
+        Value::Time(_) => 4,
+        Value::Text(_) => 5,
+        Value::Blob(_) => 6,
+        Value::Array(_) => 7,
+        Value::Json(_) => 8,
+    }
+}
+
+/// Comparar dos valores de la misma familia
+fn same_rank(a: &Value, b: &Value, collation: Collation) -> Option<Ordering> {
+    Some(match (a, b) {
+        (Value::Null, Value::Null) => Ordering::Equal,
+        (Value::Boolean(a), Value::Boolean(b)) => a.cmp(b),
+        (Value::Text(a), Value::Text(b)) => match collation {
+            Collation::Binary => a.cmp(b),
+            Collation::NoCase => a.to_lowercase().cmp(&b.to_lowercase()),
+        },
+        (Value::Time(a), Value::Time(b)) => a.cmp(b),
+        (Value::Blob(a), Value::Blob(b)) => a.cmp(b),
+        (Value::Array(a), Value::Array(b)) => a
+            .iter()
+            .zip(b)
+            .map(|(a, b)| compare_collated(a, b, collation))
+            .find(|ordering| ordering.is_ne())
+            .unwrap_or_else(|| a.len().cmp(&b.len())),
+        (Value::Json(a), Value::Json(b)) => a.to_string().cmp(&b.to_string()),
+        (Value::Date(_) | Value::Timestamp(_), Value::Date(_) | Value::Timestamp(_)) => {
+            let instant = |value: &Value| match value {
+                Value::Date(date) => date.and_time(NaiveTime::MIN).and_utc(),
+                Value::Timestamp(timestamp) => *timestamp,
+                _ => unreachable!("sólo fechas"),
+            };
+            instant(a).cmp(&instant(b))
+        }
+        _ => compare_numbers(&number(a)?, &number(b)?),
+    })
+}
+
+/// Comparar un texto con un valor de otro tipo: `text` frente a `other`,
+/// devuelto desde el punto de vista de `other`
+fn text_against(text: &str, other: &Value) -> Option<Ordering> {
+    if let Some(other_number) = number(other) {
+        let text_number = numeric_text(text)?;
+        return Some(compare_numbers(&other_number, &text_number));
+    }
+    let coerced = try_coerce(&Value::Text(text.to_string()), other.type_name()).ok()?;
+    same_rank(other, &coerced, Collation::Binary)
+}
+
+/// Número de cualquiera de los tipos numéricos
+enum Number {
+    Int(i64),
+    Float(f64),
+    #[cfg(feature = "decimal")]
+    Decimal(rust_decimal::Decimal),
+}
+
+fn number(value: &Value) -> Option<Number> {
+    match value {
+        Value::Integer(i) => Some(Number::Int(*i)),
+        Value::Float(f) => Some(Number::Float(*f)),
+        #[cfg(feature = "decimal")]
+        Value::Decimal(d) => Some(Number::Decimal(*d)),
+        _ => None,
+    }
+}
+
+/// Número escrito como texto (`' 42 '`, `'2.5'`)
+fn numeric_text(text: &str) -> Option<Number> {
+    let text = text.trim();
+    text.parse::<i64>()
+        .map(Number::Int)
+        .or_else(|_| text.parse::<f64>().map(Number::Float))
+        .ok()
+}
+
+fn compare_numbers(a: &Number, b: &Number) -> Ordering {
+    match (a, b) {
+        (Number::Int(a), Number::Int(b)) => a.cmp(b),
+        #[cfg(feature = "decimal")]
+        (Number::Decimal(a), Number::Decimal(b)) => a.cmp(b),
+        #[cfg(feature = "decimal")]
+        (Number::Decimal(a), Number::Int(b)) => a.cmp(&rust_decimal::Decimal::from(*b)),
+        #[cfg(feature = "decimal")]
+        (Number::Int(a), Number::Decimal(b)) => rust_decimal::Decimal::from(*a).cmp(b),
+        (Number::Int(a), Number::Float(b)) => compare_int_float(*a, *b),
+        (Number::Float(a), Number::Int(b)) => compare_int_float(*b, *a).reverse(),
+        _ => compare_floats(as_f64(a), as_f64(b)),
+    }
+}
+
+/// Comparar un entero con un flotante sin pasar el entero a f64, que pierde
+/// precisión por encima de 2^53
+fn compare_int_float(int: i64, float: f64) -> Ordering {
+    // 2^63 es el primer flotante fuera de i64; -2^63 es exacto
+    const LIMIT: f64 = 9_223_372_036_854_775_808.0;
+    if float.is_nan() || float >= LIMIT {
+        return Ordering::Less;
+    }
+    if float < -LIMIT {
+        return Ordering::Greater;
+    }
+    let whole = float.trunc();
+    int.cmp(&(whole as i64))
+        .then_with(|| compare_floats(0.0, float - whole))
+}
+
+fn as_f64(number: &Number) -> f64 {
+    match number {
+        Number::Int(i) => *i as f64,
+        Number::Float(f) => *f,
+        #[cfg(feature = "decimal")]
+        Number::Decimal(d) => rust_decimal::prelude::ToPrimitive::to_f64(d).unwrap_or(f64::NAN),
+    }
+}
+
+/// Orden total de flotantes: `-0.0 == 0.0` y NaN detrás de todo (igual a NaN)
+fn compare_floats(a: f64, b: f64) -> Ordering {
+    a.partial_cmp(&b)
+        .unwrap_or_else(|| a.is_nan().cmp(&b.is_nan()))
+}
+
+fn to_integer(value: &Value) -> Option<Value> {
+    let integral = |f: f64| (f.fract() == 0.0 && f.abs() < 9.2e18).then_some(f as i64);
+    match value {
+        Value::Integer(i) => Some(Value::Integer(*i)),
+        Value::Float(f) => integral(*f).map(Value::Integer),
+        #[cfg(feature = "decimal")]
+        Value::Decimal(d) if d.fract().is_zero() => {
+            rust_decimal::prelude::ToPrimitive::to_i64(d).map(Value::Integer)
+        }
+        Value::Boolean(b) => Some(Value::Integer(i64::from(*b))),
+        Value::Text(text) => match numeric_text(text)? {
+            Number::Int(i) => Some(Value::Integer(i)),
+            Number::Float(f) => integral(f).map(Value::Integer),
+            #[cfg(feature = "decimal")]
+            Number::Decimal(_) => None,
+        },
+        _ => None,
+    }
+}
+
+#[cfg(feature = "decimal")]
+fn to_decimal(value: &Value) -> Option<Value> {
+    use std::str::FromStr;
+    match value {
+        Value::Decimal(d) => Some(Value::Decimal(*d)),
+        Value::Integer(i) => Some(Value::Decimal((*i).into())),
+        Value::Float(f) => rust_decimal::Decimal::try_from(*f).ok().map(Value::Decimal),
+        Value::Text(text) => rust_decimal::Decimal::from_str(text.trim())
+            .ok()
+            .map(Value::Decimal),
+        _ => None,
+    }
+}
+
+fn to_float(value: &Value) -> Option<Value> {
+    match value {
+        Value::Boolean(b) => Some(Value::Float(f64::from(u8::from(*b)))),
+        Value::Text(text) => numeric_text(text).map(|n| Value::Float(as_f64(&n))),
+        other => number(other).map(|n| Value::Float(as_f64(&n))),
+    }
+}
+
+fn to_boolean(value: &Value) -> Option<Value> {
+    match value {
+        Value::Boolean(b) => Some(Value::Boolean(*b)),
+        Value::Integer(i) => Some(Value::Boolean(*i != 0)),
+        Value::Text(text) => match text.trim().to_lowercase().as_str() {
+            "true" | "t" | "1" | "yes" => Some(Value::Boolean(true)),
+            "false" | "f" | "0" | "no" => Some(Value::Boolean(false)),
+            _ => None,
+        },
+        _ => None,
+    }
+}
+
+fn to_date(value: &Value) -> Option<Value> {
+    match value {
+        Value::Date(date) => Some(Value::Date(*date)),
+        Value::Timestamp(timestamp) => Some(Value::Date(timestamp.date_naive())),
+        Value::Text(text) => NaiveDate::parse_from_str(text.trim(), "%Y-%m-%d")
+            .ok()
+            .or_else(|| parse_timestamp(text).map(|timestamp| timestamp.date_naive()))
+            .map(Value::Date),
+        _ => None,
+    }
+}
+
+fn to_time(value: &Value) -> Option<Value> {
+    match value {
+        Value::Time(time) => Some(Value::Time(*time)),
+        Value::Timestamp(timestamp) => Some(Value::Time(timestamp.time())),
+        Value::Text(text) => NaiveTime::parse_from_str(text.trim(), "%H:%M:%S%.f")
+            .or_else(|_| NaiveTime::parse_from_str(text.trim(), "%H:%M"))
+            .ok()
+            .map(Value::Time),
+        _ => None,
+    }
+}
+
+fn to_timestamp(value: &Value) -> Option<Value> {
+    match value {
+        Value::Timestamp(timestamp) => Some(Value::Timestamp(*timestamp)),
+        Value::Date(date) => Some(Value::Timestamp(date.and_time(NaiveTime::MIN).and_utc())),
+        Value::Text(text) => parse_timestamp(text)
+            .or_else(|| {
+                NaiveDate::parse_from_str(text.trim(), "%Y-%m-%d")
+                    .ok()
+                    .map(|date| date.and_time(NaiveTime::MIN).and_utc())
+            })
+            .map(Value::Timestamp),
+        _ => None,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Valores de todas las familias, con casos límite
+    fn samples() -> Vec<Value> {
+        vec![
+            Value::Null,
+            Value::Boolean(false),
+            Value::Boolean(true),
+            Value::Integer(-3),
+            Value::Integer(0),
+            Value::Integer(2),
+            Value::Float(-0.0),
+            Value::Float(0.0),
+            Value::Float(2.0),
+            Value::Float(2.5),
+            Value::Float(f64::NAN),
+            Value::Float(f64::INFINITY),
+            Value::Date(NaiveDate::from_ymd_opt(2024, 1, 31).unwrap()),
+            Value::Timestamp(parse_timestamp("2024-01-31 00:00:00").unwrap()),
+            Value::Timestamp(parse_timestamp("2024-01-31 10:00:00").unwrap()),
+            Value::Time(NaiveTime::from_hms_opt(8, 30, 0).unwrap()),
+            Value::Text(String::new()),
+            Value::Text("10".to_string()),
+            Value::Text("9".to_string()),
+            Value::Text("Ana".to_string()),
+            Value::Text("ana".to_string()),
+            Value::Blob(vec![0x01]),
+            Value::Array(vec![Value::Integer(1), Value::Null]),
+            Value::Array(vec![Value::Integer(1)]),
+        ]
+    }
+
+    #[test]
+    fn test_compare_is_a_total_order() {
+        let values = samples();
+        for collation in [Collation::Binary, Collation::NoCase] {
+            let cmp = |a: &Value, b: &Value| compare_collated(a, b, collation);
+            for a in &values {
+                assert_eq!(cmp(a, a), Ordering::Equal, "reflexiva: {:?}", a);
+                for b in &values {
+                    assert_eq!(
+                        cmp(a, b),
+                        cmp(b, a).reverse(),
+                        "antisimétrica: {:?} {:?}",
+                        a,
+                        b
+                    );
+                    for c in &values {
+                        if cmp(a, b).is_le() && cmp(b, c).is_le() {
+                            assert!(cmp(a, c).is_le(), "transitiva: {:?} {:?} {:?}", a, b, c);
+                        }
+                    }
+                }
+            }
+        }
+
+        // Ordenar no depende del orden de entrada
+        let mut forward = values.clone();
+        let mut backward: Vec<Value> = values.into_iter().rev().collect();
+        forward.sort_by(compare);
+        backward.sort_by(compare);
+        assert!(forward
+            .iter()
+            .zip(&backward)
+            .all(|(a, b)| compare(a, b).is_eq()));
+    }
+
+    #[test]
+    fn test_compare_places_nulls_first_and_nan_after_numbers() {
+        let mut values = samples();
+        values.sort_by(compare);
+        assert_eq!(values[0], Value::Null);
+        assert!(values[1..].iter().all(|value| !value.is_null()));
+
+        let numbers: Vec<&Value> = values
+            .iter()
+            .filter(|value| matches!(value, Value::Integer(_) | Value::Float(_)))
+            .collect();
+        assert!(matches!(numbers.last(), Some(Value::Float(f)) if f.is_nan()));
+        assert_eq!(
+            compare(&Value::Integer(2), &Value::Float(2.0)),
+            Ordering::Equal
+        );
+        assert_eq!(
+            compare(&Value::Integer(2), &Value::Float(2.5)),
+            Ordering::Less
+        );
+        assert_eq!(
+            compare(&Value::Float(-0.0), &Value::Integer(0)),
+            Ordering::Equal
+        );
+        assert_eq!(
+            compare(&Value::Integer(-3), &Value::Float(-2.5)),
+            Ordering::Less
+        );
+
+        // Por encima de 2^53 el entero no se redondea al flotante más próximo
+        let two_53 = 2_i64.pow(53);
+        assert_eq!(
+            compare(&Value::Integer(two_53 + 1), &Value::Float(two_53 as f64)),
+            Ordering::Greater
+        );
+        assert_eq!(
+            compare(&Value::Float(two_53 as f64), &Value::Integer(two_53 + 1)),
+            Ordering::Less
+        );
+        assert_eq!(
+            compare(&Value::Integer(i64::MAX), &Value::Float(i64::MAX as f64)),
+            Ordering::Less
+        );
+        assert_eq!(
+            compare(&Value::Integer(i64::MIN), &Value::Float(i64::MIN as f64)),
+            Ordering::Equal
+        );
+        assert_eq!(
+            compare(&Value::Integer(i64::MIN), &Value::Float(f64::NEG_INFINITY)),
+            Ordering::Greater
+        );
+    }
+
+    #[test]
+    fn test_sql_null_semantics() {
+        assert_eq!(equals_sql(&Value::Null, &Value::Null), None);
+        assert_eq!(equals_sql(&Value::Null, &Value::Integer(1)), None);
+        assert_eq!(
+            compare_sql(&Value::Integer(1), &Value::Null, Collation::Binary),
+            None
+        );
+        for value in samples().iter().filter(|value| !value.is_null()) {
+            assert!(equals_sql(value, &Value::Null).is_none());
+        }
+    }
+
+    #[test]
+    fn test_compare_sql_coerces_across_types() {
+        let binary = Collation::Binary;
+        assert_eq!(
+            equals_sql(&Value::Integer(7), &Value::Float(7.0)),
+            Some(true)
+        );
+        assert_eq!(
+            equals_sql(&Value::Text("7".to_string()), &Value::Integer(7)),
+            Some(true)
+        );
+        assert_eq!(
+            compare_sql(&Value::Text("10".to_string()), &Value::Integer(9), binary),
+            Some(Ordering::Greater)
+        );
+        assert_eq!(
+            compare_sql(&Value::Integer(9), &Value::Text("10".to_string()), binary),
+            Some(Ordering::Less)
+        );
+        let date = Value::Date(NaiveDate::from_ymd_opt(2024, 1, 31).unwrap());
+        assert_eq!(
+            compare_sql(&date, &Value::Text("2024-02-01".to_string()), binary),
+            Some(Ordering::Less)
+        );
+        assert_eq!(
+            equals_sql(&Value::Boolean(true), &Value::Integer(1)),
+            Some(true)
+        );
+        assert_eq!(
+            equals_sql(&Value::Text("abc".to_string()), &Value::Integer(1)),
+            Some(false)
+        );
+        assert_eq!(
+            compare_sql(&Value::Blob(vec![1]), &Value::Integer(1), binary),
+            None
+        );
+
+        let (upper, lower) = (
+            Value::Text("Ana".to_string()),
+            Value::Text("ana".to_string()),
+        );
+        assert_eq!(equals_sql(&upper, &lower), Some(false));
+        assert_eq!(
+            compare_sql(&upper, &lower, Collation::NoCase),
+            Some(Ordering::Equal)
+        );
+    }
+
+    #[test]
+    fn test_try_coerce() {
+        let text = |s: &str| Value::Text(s.to_string());
+        assert_eq!(
+            try_coerce(&text(" 42 "), "INTEGER").unwrap(),
+            Value::Integer(42)
+        );
+        assert_eq!(
+            try_coerce(&Value::Float(3.0), "BIGINT").unwrap(),
+            Value::Integer(3)
+        );
+        assert!(try_coerce(&Value::Float(2.5), "INTEGER").is_err());
+        assert!(try_coerce(&text("abc"), "INTEGER").is_err());
+        assert_eq!(
+            try_coerce(&Value::Integer(2), "DOUBLE").unwrap(),
+            Value::Float(2.0)
+        );
+        assert_eq!(
+            try_coerce(&Value::Integer(2), "VARCHAR(10)").unwrap(),
+            text("2")
+        );
+        assert_eq!(
+            try_coerce(&text("yes"), "BOOLEAN").unwrap(),
+            Value::Boolean(true)
+        );
+        assert_eq!(
+            try_coerce(&text("2024-01-31"), "TIMESTAMP").unwrap(),
+            Value::Timestamp(parse_timestamp("2024-01-31 00:00:00").unwrap())
+        );
+        assert_eq!(
+            try_coerce(&text("08:30"), "time").unwrap(),
+            Value::Time(NaiveTime::from_hms_opt(8, 30, 0).unwrap())
+        );
+        assert_eq!(try_coerce(&Value::Null, "INTEGER").unwrap(), Value::Null);
+        assert!(try_coerce(&Value::Integer(1), "GEOMETRY").is_err());
+    }
+}
@@ -3,8 +3,13 @@
 //! Esta crate proporciona una interfaz C para integrar Noctra
 //! con otros lenguajes y aplicaciones.
 
+use noctra_core::datasource::DataSource;
+use noctra_core::types::Parameters;
+use noctra_duckdb::DuckDBSource;
+use std::collections::BTreeMap;
 use std::ffi::{CStr, CString};
 use std::os::raw::{c_char, c_int};
+use std::sync::{Arc, Mutex, MutexGuard, OnceLock};
 
 /// Resultado de funciones FFI
 pub type FfiResult = c_int;
@@ -13,6 +18,55 @@ pub type FfiResult = c_int;
 pub const FFI_SUCCESS: c_int = 0;
 pub const FFI_ERROR: c_int = -1;
 pub const FFI_INVALID_INPUT: c_int = -2;
+pub const FFI_NOT_FOUND: c_int = -3;
+
+/// Fuentes registradas con `noctra_register_file` por alias (compartidas
+/// por todo el proceso)
+type Sources = BTreeMap<String, Arc<dyn DataSource>>;
+
+static SOURCES: OnceLock<Mutex<Sources>> = OnceLock::new();
+
+/// Registro global de fuentes
+///
+/// El bloqueo sólo cubre buscar o cambiar el registro: las consultas usan
+/// su propio `Arc` de la fuente, así que las de fuentes distintas corren
+/// en paralelo y ninguna espera a que otro hilo registre un archivo.
+fn sources() -> Option<MutexGuard<'static, Sources>> {
+    SOURCES
+        .get_or_init(|| Mutex::new(BTreeMap::new()))
+        .lock()
+        .ok()
+}
+
+/// Leer un string C como UTF-8 (None si es nulo o inválido)
+///
+/// # Safety
+/// `ptr` debe ser nulo o apuntar a un string C terminado en cero.
+unsafe fn c_str<'a>(ptr: *const c_char) -> Option<&'a str> {
+    if ptr.is_null() {
+        return None;
+    }
+    CStr::from_ptr(ptr).to_str().ok()
+}
+
+/// Entregar `json` al caller en `out_json` (se libera con `noctra_free`)
+///
+/// # Safety
+/// `out_json` debe apuntar a una posición válida para un puntero.
+unsafe fn write_json(out_json: *mut *mut c_char, json: String) -> FfiResult {
+    match CString::new(json) {
+        Ok(s) => {
+            *out_json = s.into_raw();
+            FFI_SUCCESS
+        }
+        Err(_) => FFI_ERROR,
+    }
+}
+
+/// JSON de error: `{"success": false, "error": "..."}`
+fn error_json(message: &str) -> String {
+    serde_json::json!({ "success": false, "error": message }).to_string()
+}
 
 /// Ejecutar consulta SQL y retornar resultado JSON
 ///
@@ -62,6 +116,150 @@ pub unsafe extern "C" fn noctra_exec(sql: *const c_char, out_json: *mut *mut c_c
     FFI_SUCCESS
 }
 
+/// Registrar un archivo (CSV, JSON, Parquet, Excel...) como fuente consultable
+///
+/// Crea una fuente DuckDB en memoria con el archivo como tabla `alias` y la
+/// guarda en el registro global con ese mismo alias. Si el alias ya existe,
+/// la fuente anterior se reemplaza.
+///
+/// # Safety
+/// This function dereferences raw pointers from C. The caller must ensure:
+/// * `path` and `alias` point to valid, null-terminated C strings
+///
+/// # Arguments
+/// * `path` - Ruta del archivo
+/// * `alias` - Nombre de la fuente y de su tabla
+///
+/// # Returns
+/// FFI_SUCCESS on success, FFI_INVALID_INPUT for null/invalid strings,
+/// FFI_ERROR if the file can't be registered
+#[no_mangle]
+pub unsafe extern "C" fn noctra_register_file(
+    path: *const c_char,
+    alias: *const c_char,
+) -> FfiResult {
+    let (Some(path), Some(alias)) = (c_str(path), c_str(alias)) else {
+        return FFI_INVALID_INPUT;
+    };
+    if alias.is_empty() {
+        return FFI_INVALID_INPUT;
+    }
+
+    // La fuente se prepara fuera del bloqueo: leer el archivo puede tardar
+    let mut source = match DuckDBSource::new_in_memory() {
+        Ok(source) => source,
+        Err(_) => return FFI_ERROR,
+    };
+    if source.register_file(path, alias, false).is_err() {
+        return FFI_ERROR;
+    }
+
+    let Some(mut registry) = sources() else {
+        return FFI_ERROR;
+    };
+    registry.insert(alias.to_string(), Arc::new(source));
+    FFI_SUCCESS
+}
+
+/// Consultar una fuente registrada y retornar el resultado JSON
+///
+/// El resultado es el `ResultSet` serializado (`columns`, `rows`, ...). Si
+/// la consulta falla, `out_json` recibe `{"success": false, "error": "..."}`.
+///
+/// # Safety
+/// This function dereferences raw pointers from C. The caller must ensure:
+/// * `alias` and `sql` point to valid, null-terminated C strings
+/// * `params_json` is null or points to a valid, null-terminated C string
+/// * `out_json` points to a valid mutable pointer location
+///
+/// # Arguments
+/// * `alias` - Alias usado en `noctra_register_file`
+/// * `sql` - Query SQL como string C
+/// * `params_json` - Parámetros en JSON (ver `Parameters::from_json`) o NULL
+/// * `out_json` - Buffer para resultado JSON (allocado por la función)
+///
+/// # Returns
+/// FFI_SUCCESS on success, FFI_NOT_FOUND for unknown aliases,
+/// FFI_INVALID_INPUT for invalid parameters, FFI_ERROR if the query fails
+#[no_mangle]
+pub unsafe extern "C" fn noctra_query_source(
+    alias: *const c_char,
+    sql: *const c_char,
+    params_json: *const c_char,
+    out_json: *mut *mut c_char,
+) -> FfiResult {
+    if out_json.is_null() {
+        return FFI_INVALID_INPUT;
+    }
+    let (Some(alias), Some(sql)) = (c_str(alias), c_str(sql)) else {
+        return FFI_INVALID_INPUT;
+    };
+    let parameters = if params_json.is_null() {
+        Parameters::new()
+    } else {
+        let Some(json) = c_str(params_json) else {
+            return FFI_INVALID_INPUT;
+        };
+        match Parameters::from_json(json) {
+            Ok(parameters) => parameters,
+            Err(e) => {
+                write_json(out_json, error_json(&e.to_string()));
+                return FFI_INVALID_INPUT;
+            }
+        }
+    };
+
+    // Se suelta el registro antes de consultar (ver `sources`)
+    let source = match sources() {
+        Some(registry) => registry.get(alias).cloned(),
+        None => return FFI_ERROR,
+    };
+    let Some(source) = source else {
+        write_json(
+            out_json,
+            error_json(&format!("Fuente '{}' no registrada", alias)),
+        );
+        return FFI_NOT_FOUND;
+    };
+
+    match source.query(sql, &parameters) {
+        Ok(result_set) => match serde_json::to_string(&result_set) {
+            Ok(json) => write_json(out_json, json),
+            Err(_) => FFI_ERROR,
+        },
+        Err(e) => {
+            write_json(out_json, error_json(&e.to_string()));
+            FFI_ERROR
+        }
+    }
+}
+
+/// Listar los alias registrados como array JSON (ordenado)
+///
+/// # Safety
+/// This function dereferences a raw pointer. The caller must ensure:
+/// * `out_json` points to a valid mutable pointer location
+///
+/// # Arguments
+/// * `out_json` - Buffer para el array JSON (allocado por la función)
+///
+/// # Returns
+/// FFI_SUCCESS on success, FFI_ERROR on failure
+#[no_mangle]
+pub unsafe extern "C" fn noctra_list_sources(out_json: *mut *mut c_char) -> FfiResult {
+    if out_json.is_null() {
+        return FFI_INVALID_INPUT;
+    }
+    let Some(registry) = sources() else {
+        return FFI_ERROR;
+    };
+    let aliases: Vec<&String> = registry.keys().collect();
+    match serde_json::to_string(&aliases) {
+        Ok(json) => write_json(out_json, json),
+        Err(_) => FFI_ERROR,
+    }
+}
+
 /// Obtener versión de la librería
 ///
 /// # Returns
@@ -109,6 +307,9 @@ pub extern "C" fn noctra_shutdown() {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use noctra_core::datasource::SourceType;
+    use noctra_core::types::ResultSet;
+    use noctra_core::TableInfo;
 
     #[test]
     fn test_version() {
@@ -122,4 +323,152 @@ mod tests {
         let result = unsafe { noctra_exec(std::ptr::null(), &mut out_json) };
         assert_eq!(result, FFI_INVALID_INPUT);
     }
+
+    /// Tomar un JSON retornado por la librería y liberarlo
+    fn take_json(ptr: *mut c_char) -> serde_json::Value {
+        let json = unsafe { CStr::from_ptr(ptr) }.to_str().unwrap().to_string();
+        unsafe { noctra_free(ptr) };
+        serde_json::from_str(&json).unwrap()
+    }
+
+    #[test]
+    fn test_register_and_query_sources_from_threads() {
+        let dir = tempfile::tempdir().unwrap();
+        for (name, rows) in [("ffi_ventas", "1,10\n2,20\n"), ("ffi_stock", "1,5\n")] {
+            let path = dir.path().join(format!("{}.csv", name));
+            std::fs::write(&path, format!("id,cantidad\n{}", rows)).unwrap();
+            let path = CString::new(path.to_str().unwrap()).unwrap();
+            let alias = CString::new(name).unwrap();
+            let result = unsafe { noctra_register_file(path.as_ptr(), alias.as_ptr()) };
+            assert_eq!(result, FFI_SUCCESS);
+        }
+
+        let handles: Vec<_> = [("ffi_ventas", 30), ("ffi_stock", 5)]
+            .into_iter()
+            .map(|(alias, expected)| {
+                std::thread::spawn(move || {
+                    let alias = CString::new(alias).unwrap();
+                    let sql = CString::new(format!(
+                        "SELECT CAST(SUM(cantidad) AS INTEGER) AS total FROM {}",
+                        alias.to_str().unwrap()
+                    ))
+                    .unwrap();
+                    let mut out_json: *mut c_char = std::ptr::null_mut();
+                    let result = unsafe {
+                        noctra_query_source(
+                            alias.as_ptr(),
+                            sql.as_ptr(),
+                            std::ptr::null(),
+                            &mut out_json,
+                        )
+                    };
+                    assert_eq!(result, FFI_SUCCESS);
+                    let json = take_json(out_json);
+                    assert_eq!(json["rows"][0]["values"][0]["Integer"], expected);
+                })
+            })
+            .collect();
+        for handle in handles {
+            handle.join().unwrap();
+        }
+
+        let mut out_json: *mut c_char = std::ptr::null_mut();
+        assert_eq!(unsafe { noctra_list_sources(&mut out_json) }, FFI_SUCCESS);
+        let aliases = take_json(out_json);
+        assert!(aliases.as_array().unwrap().contains(&"ffi_ventas".into()));
+
+        let missing = CString::new("ffi_inexistente").unwrap();
+        let sql = CString::new("SELECT 1").unwrap();
+        let result = unsafe {
+            noctra_query_source(
+                missing.as_ptr(),
+                sql.as_ptr(),
+                std::ptr::null(),
+                &mut out_json,
+            )
+        };
+        assert_eq!(result, FFI_NOT_FOUND);
+        assert_eq!(take_json(out_json)["success"], false);
+
+        // Parámetros en JSON plano
+        let alias = CString::new("ffi_ventas").unwrap();
+        let sql = CString::new("SELECT COUNT(*) AS n FROM ffi_ventas WHERE cantidad > ?").unwrap();
+        let params = CString::new("[15]").unwrap();
+        let result = unsafe {
+            noctra_query_source(alias.as_ptr(), sql.as_ptr(), params.as_ptr(), &mut out_json)
+        };
+        assert_eq!(result, FFI_SUCCESS);
+        assert_eq!(take_json(out_json)["rows"][0]["values"][0]["Integer"], 1);
+
+        let params = CString::new("42").unwrap();
+        let result = unsafe {
+            noctra_query_source(alias.as_ptr(), sql.as_ptr(), params.as_ptr(), &mut out_json)
+        };
+        assert_eq!(result, FFI_INVALID_INPUT);
+        assert_eq!(take_json(out_json)["success"], false);
+    }
+
+    /// Fuente que no termina su consulta hasta que el test lo indica
+    #[derive(Debug)]
+    struct BlockingSource {
+        started: std::sync::mpsc::Sender<()>,
+        release: Mutex<std::sync::mpsc::Receiver<()>>,
+    }
+
+    impl DataSource for BlockingSource {
+        fn query(&self, _sql: &str, _parameters: &Parameters) -> noctra_core::Result<ResultSet> {
+            self.started.send(()).unwrap();
+            self.release.lock().unwrap().recv().unwrap();
+            Ok(ResultSet::empty())
+        }
+
+        fn schema(&self) -> noctra_core::Result<Vec<TableInfo>> {
+            Ok(Vec::new())
+        }
+
+        fn source_type(&self) -> SourceType {
+            SourceType::Memory { capacity: 0 }
+        }
+
+        fn name(&self) -> &str {
+            "ffi_bloqueada"
+        }
+    }
+
+    #[test]
+    fn test_query_does_not_hold_the_registry_lock() {
+        let (started, wait_started) = std::sync::mpsc::channel();
+        let (release, wait_release) = std::sync::mpsc::channel();
+        sources().unwrap().insert(
+            "ffi_bloqueada".to_string(),
+            Arc::new(BlockingSource {
+                started,
+                release: Mutex::new(wait_release),
+            }),
+        );
+
+        let query = std::thread::spawn(|| {
+            let alias = CString::new("ffi_bloqueada").unwrap();
+            let sql = CString::new("SELECT 1").unwrap();
+            let mut out_json: *mut c_char = std::ptr::null_mut();
+            let result = unsafe {
+                noctra_query_source(
+                    alias.as_ptr(),
+                    sql.as_ptr(),
+                    std::ptr::null(),
+                    &mut out_json,
+                )
+            };
+            unsafe { noctra_free(out_json) };
+            result
+        });
+
+        // Con la consulta en curso el registro sigue libre
+        wait_started.recv().unwrap();
+        assert!(SOURCES.get().unwrap().try_lock().is_ok());
+
+        release.send(()).unwrap();
+        assert_eq!(query.join().unwrap(), FFI_SUCCESS);
+        sources().unwrap().remove("ffi_bloqueada");
+    }
 }
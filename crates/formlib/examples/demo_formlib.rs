@@ -30,7 +30,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                     );
                 }
                 println!("\n  ⚙️ Acciones ({} total):", form.actions.len());
-                for (name, _action) in &form.actions {
+                for name in form.actions.keys() {
                     println!("    - {}", name);
                 }
             }
@@ -62,7 +62,7 @@ fn main() -> Result<(), Box<dyn std::error::Error>> {
                 let mut by_type: HashMap<String, Vec<String>> = HashMap::new();
                 for (name, field) in &form.fields {
                     let type_str = format!("{:?}", field.field_type);
-                    by_type.entry(type_str).or_insert_with(Vec::new).push(name.clone());
+                    by_type.entry(type_str).or_default().push(name.clone());
                 }
 
                 for (field_type, fields) in &by_type {
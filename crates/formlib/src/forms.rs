@@ -26,6 +26,18 @@ pub struct FormField {
 
     /// Validaciones específicas del campo
     pub validations: Option<FieldValidations>,
+
+    /// Condición de visibilidad (p. ej. `tipo == 'empresa'`)
+    #[serde(default)]
+    pub visible_when: Option<String>,
+
+    /// Campos de los que se calcula este campo
+    #[serde(default)]
+    pub computed_from: Vec<String>,
+
+    /// Fórmula aritmética del campo calculado (p. ej. `cantidad * precio`)
+    #[serde(default)]
+    pub formula: Option<String>,
 }
 
 /// Tipo de campo
@@ -104,6 +116,29 @@ pub struct FormAction {
 
     /// Tipo de parámetros
     pub param_type: ParamType,
+
+    /// Cadena de consultas (en lugar de `sql`)
+    pub pipeline: Option<ActionPipeline>,
+}
+
+/// Cadena de consultas de una acción
+///
+/// Cada paso recibe como parámetros las columnas de la primera fila del
+/// paso anterior, renombradas según su `mapping`.
+#[derive(Debug, Clone, Default, Serialize, Deserialize)]
+pub struct ActionPipeline {
+    /// Pasos en orden de ejecución
+    pub steps: Vec<PipelineStep>,
+}
+
+/// Un paso de la cadena de consultas
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct PipelineStep {
+    /// Consulta SQL del paso
+    pub sql: String,
+
+    /// Columna del resultado -> parámetro del paso siguiente
+    pub mapping: HashMap<String, String>,
 }
 
 /// Tipo de acción
@@ -153,6 +188,10 @@ pub struct Form {
     /// Campos del formulario
     pub fields: HashMap<String, FormField>,
 
+    /// Nombres de los campos en el orden en que se declararon
+    #[serde(default)]
+    pub field_order: Vec<String>,
+
     /// Acciones disponibles
     pub actions: HashMap<String, FormAction>,
 
@@ -161,6 +200,51 @@ pub struct Form {
 
     /// Configuración de paginación
     pub pagination: Option<PaginationConfig>,
+
+    /// Reglas que relacionan varios campos
+    #[serde(default)]
+    pub cross_field_rules: Vec<CrossFieldRule>,
+}
+
+impl Form {
+    /// Nombres de los campos en orden de declaración
+    ///
+    /// Los campos que no están en `field_order` (formularios construidos en
+    /// código) van al final, por nombre.
+    pub fn field_names(&self) -> Vec<&str> {
+        let mut names: Vec<&str> = self
+            .field_order
+            .iter()
+            .map(String::as_str)
+            .filter(|name| self.fields.contains_key(*name))
+            .collect();
+        let mut rest: Vec<&str> = self
+            .fields
+            .keys()
+            .map(String::as_str)
+            .filter(|name| !self.field_order.iter().any(|declared| declared == name))
+            .collect();
+        rest.sort();
+        names.extend(rest);
+        names
+    }
+}
+
+/// Regla de validación entre campos
+///
+/// En FDL2: `{ fields = ["inicio", "fin"], condition = "inicio < fin",
+/// message = "El fin debe ser posterior al inicio" }`.
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct CrossFieldRule {
+    /// Campos que relaciona la regla (a ellos se asocia el error)
+    pub fields: Vec<String>,
+
+    /// Comparación que deben cumplir: dos fórmulas unidas por `<`, `<=`,
+    /// `>`, `>=`, `=`, `==`, `!=` o `<>`
+    pub condition: String,
+
+    /// Mensaje si no se cumple
+    pub message: String,
 }
 
 /// Configuración de interfaz de usuario
@@ -6,9 +6,11 @@
 pub mod forms;
 pub mod graph;
 pub mod loader;
+pub mod pipeline;
 pub mod validation;
 
 pub use forms::*;
 pub use graph::{FormGraph, GraphNavigator, NodeDefinition, NodeType};
 pub use loader::{load_form, load_form_from_path};
-pub use validation::ValidationError;
+pub use pipeline::{FormPipelineExecutor, PipelineError};
+pub use validation::{DependencyGraph, FormulaEvaluator, ValidationError};
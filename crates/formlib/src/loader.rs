@@ -3,13 +3,18 @@
 //! Módulo para cargar formularios desde archivos TOML/JSON,
 //! procesar configuraciones y preparar formularios para ejecución.
 
+use indexmap::IndexMap;
 use serde::{Deserialize, Serialize};
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use std::fs;
-use std::path::Path;
+use std::path::{Path, PathBuf};
 use thiserror::Error;
 
-use crate::forms::{ActionType, FieldType, Form, FormAction, FormField, ParamType};
+use crate::forms::{
+    ActionPipeline, ActionType, CrossFieldRule, FieldType, Form, FormAction, FormField, ParamType,
+    PipelineStep,
+};
+use crate::validation::{check_cross_field_rule, DependencyGraph};
 
 /// Error de carga de formulario
 #[derive(Error, Debug)]
@@ -29,6 +34,10 @@ pub enum LoadError {
     /// Error de IO
     #[error("Error de IO: {0}")]
     IoError(String),
+
+    /// Cadena de herencia (`extends`) circular
+    #[error("Herencia circular detectada en: {0}")]
+    CircularInheritance(String),
 }
 
 /// Resultado de carga
@@ -79,6 +88,10 @@ impl FormLoader {
     }
 
     /// Cargar formulario desde path
+    ///
+    /// Los formularios TOML pueden declarar `extends = "base.toml"` (ruta
+    /// relativa al archivo hijo) para heredar campos, acciones y configuración
+    /// de un formulario padre.
     pub fn load_from_path(&self, path: &Path) -> LoadResult<Form> {
         let path_str = path.to_string_lossy().to_string();
 
@@ -86,11 +99,69 @@ impl FormLoader {
             return Err(LoadError::FileNotFound(path_str));
         }
 
+        let is_toml = path.extension().and_then(|ext| ext.to_str()) == Some("toml");
+        if is_toml {
+            let merged = self.load_toml_with_inheritance(path, &mut HashSet::new())?;
+            let form: TomlForm = merged.try_into().map_err(|e: toml::de::Error| {
+                LoadError::ParseError(path_str.clone(), e.to_string())
+            })?;
+            return self.convert_and_validate(form.into(), &path_str);
+        }
+
         let content = fs::read_to_string(path).map_err(|e| LoadError::IoError(e.to_string()))?;
 
         self.load_from_string(&content, &path_str)
     }
 
+    /// Resolver la cadena de herencia de un formulario
+    ///
+    /// Retorna las rutas desde el formulario indicado hasta la raíz
+    /// (hijo, padre, abuelo, ...).
+    pub fn resolve_inheritance_chain(&self, path: &Path) -> LoadResult<Vec<PathBuf>> {
+        let mut chain = Vec::new();
+        let mut visited = HashSet::new();
+        let mut current = Some(path.to_path_buf());
+
+        while let Some(path) = current {
+            let canonical = canonicalize_form_path(&path)?;
+            if !visited.insert(canonical.clone()) {
+                return Err(LoadError::CircularInheritance(
+                    canonical.to_string_lossy().to_string(),
+                ));
+            }
+
+            let mut table = read_toml_table(&canonical)?;
+            current = take_extends(&mut table, &canonical)?;
+            chain.push(canonical);
+        }
+
+        Ok(chain)
+    }
+
+    /// Cargar TOML resolviendo `extends` recursivamente
+    fn load_toml_with_inheritance(
+        &self,
+        path: &Path,
+        visited: &mut HashSet<PathBuf>,
+    ) -> LoadResult<toml::Value> {
+        let canonical = canonicalize_form_path(path)?;
+        if !visited.insert(canonical.clone()) {
+            return Err(LoadError::CircularInheritance(
+                canonical.to_string_lossy().to_string(),
+            ));
+        }
+
+        let mut table = read_toml_table(&canonical)?;
+
+        match take_extends(&mut table, &canonical)? {
+            Some(parent_path) => {
+                let parent = self.load_toml_with_inheritance(&parent_path, visited)?;
+                Ok(merge_form_tables(parent, table))
+            }
+            None => Ok(toml::Value::Table(table)),
+        }
+    }
+
     /// Cargar formulario desde string (TOML/JSON)
     pub fn load_from_string(&self, content: &str, source: &str) -> LoadResult<Form> {
         let extension = Path::new(source)
@@ -202,6 +273,22 @@ impl FormLoader {
                     )));
                 }
             }
+
+            if let Some(pipeline) = &action.pipeline {
+                if pipeline.steps.is_empty() {
+                    return Err(LoadError::ValidationError(format!(
+                        "Acción '{}' tiene un pipeline sin pasos",
+                        action_name
+                    )));
+                }
+                if let Some(step) = pipeline.steps.iter().position(|s| s.sql.trim().is_empty()) {
+                    return Err(LoadError::ValidationError(format!(
+                        "Acción '{}' tiene SQL vacío en el paso {} del pipeline",
+                        action_name,
+                        step + 1
+                    )));
+                }
+            }
         }
 
         // Validar campos requeridos
@@ -244,10 +331,83 @@ impl FormLoader {
             }
         }
 
+        // Validar fórmulas de campos calculados (referencias y ciclos)
+        DependencyGraph::from_form(form).map_err(|e| LoadError::ValidationError(e.to_string()))?;
+
+        // Validar reglas entre campos
+        for rule in &form.cross_field_rules {
+            check_cross_field_rule(form, rule)
+                .map_err(|e| LoadError::ValidationError(e.to_string()))?;
+        }
+
         Ok(())
     }
 }
 
+/// Canonicalizar ruta de formulario (error si no existe)
+fn canonicalize_form_path(path: &Path) -> LoadResult<PathBuf> {
+    path.canonicalize()
+        .map_err(|_| LoadError::FileNotFound(path.to_string_lossy().to_string()))
+}
+
+/// Leer archivo TOML como tabla genérica
+fn read_toml_table(path: &Path) -> LoadResult<toml::Table> {
+    let source = path.to_string_lossy().to_string();
+    let content = fs::read_to_string(path).map_err(|e| LoadError::IoError(e.to_string()))?;
+    toml::from_str(&content).map_err(|e| LoadError::ParseError(source, e.to_string()))
+}
+
+/// Extraer la clave `extends` y resolverla relativa al archivo hijo
+fn take_extends(table: &mut toml::Table, child_path: &Path) -> LoadResult<Option<PathBuf>> {
+    let Some(extends) = table.remove("extends") else {
+        return Ok(None);
+    };
+
+    let parent = extends.as_str().ok_or_else(|| {
+        LoadError::ValidationError(format!(
+            "'extends' debe ser una ruta en {}",
+            child_path.display()
+        ))
+    })?;
+
+    let parent_path = Path::new(parent);
+    if parent_path.is_absolute() {
+        return Ok(Some(parent_path.to_path_buf()));
+    }
+
+    let base_dir = child_path.parent().unwrap_or_else(|| Path::new("."));
+    Ok(Some(base_dir.join(parent_path)))
+}
+
+/// Mezclar formulario hijo sobre el padre
+///
+/// `fields` y `actions` se combinan por nombre (el hijo reemplaza la entrada
+/// completa); el resto de claves del hijo (incluido `ui_config`) reemplazan
+/// las del padre.
+fn merge_form_tables(parent: toml::Value, child: toml::Table) -> toml::Value {
+    let mut merged = match parent {
+        toml::Value::Table(table) => table,
+        _ => toml::Table::new(),
+    };
+
+    for (key, child_value) in child {
+        match (key.as_str(), merged.get_mut(&key), child_value) {
+            (
+                "fields" | "actions",
+                Some(toml::Value::Table(parent_entries)),
+                toml::Value::Table(child_entries),
+            ) => {
+                parent_entries.extend(child_entries);
+            }
+            (_, _, child_value) => {
+                merged.insert(key, child_value);
+            }
+        }
+    }
+
+    toml::Value::Table(merged)
+}
+
 /// Cargar formulario desde path (wrapper)
 pub fn load_form_from_path(path: &Path) -> LoadResult<Form> {
     let loader = FormLoader::default();
@@ -267,10 +427,12 @@ struct TomlForm {
     title: String,
     schema: Option<String>,
     description: Option<String>,
-    fields: HashMap<String, TomlField>,
+    fields: IndexMap<String, TomlField>,
     actions: HashMap<String, TomlAction>,
     ui_config: Option<TomlUiConfig>,
     pagination: Option<TomlPaginationConfig>,
+    #[serde(default)]
+    cross_field_rules: Vec<CrossFieldRule>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -282,6 +444,9 @@ struct TomlField {
     width: Option<usize>,
     default: Option<String>,
     validations: Option<TomlValidations>,
+    visible_when: Option<String>,
+    computed_from: Option<Vec<String>>,
+    formula: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -300,6 +465,8 @@ struct TomlAction {
     sql: Option<String>,
     params: Option<Vec<String>>,
     param_type: Option<String>,
+    /// Pasos `[sql, { columna = "parámetro" }]`
+    pipeline: Option<Vec<(String, HashMap<String, String>)>>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -324,10 +491,12 @@ struct JsonForm {
     title: String,
     schema: Option<String>,
     description: Option<String>,
-    fields: HashMap<String, JsonField>,
+    fields: IndexMap<String, JsonField>,
     actions: HashMap<String, JsonAction>,
     ui_config: Option<JsonUiConfig>,
     pagination: Option<JsonPaginationConfig>,
+    #[serde(default)]
+    cross_field_rules: Vec<CrossFieldRule>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -338,6 +507,9 @@ struct JsonField {
     width: Option<usize>,
     default: Option<String>,
     validations: Option<JsonValidations>,
+    visible_when: Option<String>,
+    computed_from: Option<Vec<String>>,
+    formula: Option<String>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -356,6 +528,8 @@ struct JsonAction {
     sql: Option<String>,
     params: Option<Vec<String>>,
     param_type: Option<String>,
+    /// Pasos `[sql, { columna = "parámetro" }]`
+    pipeline: Option<Vec<(String, HashMap<String, String>)>>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -377,6 +551,7 @@ struct JsonPaginationConfig {
 /// Conversiones desde representaciones intermedias
 impl From<TomlForm> for Form {
     fn from(toml_form: TomlForm) -> Self {
+        let field_order = toml_form.fields.keys().cloned().collect();
         let fields = toml_form
             .fields
             .into_iter()
@@ -394,9 +569,11 @@ impl From<TomlForm> for Form {
             schema: toml_form.schema,
             description: toml_form.description,
             fields,
+            field_order,
             actions,
             ui_config: toml_form.ui_config.map(Into::into),
             pagination: toml_form.pagination.map(Into::into),
+            cross_field_rules: toml_form.cross_field_rules,
         }
     }
 }
@@ -410,6 +587,9 @@ impl From<TomlField> for FormField {
             width: field.width,
             default: field.default,
             validations: field.validations.map(Into::into),
+            visible_when: field.visible_when,
+            computed_from: field.computed_from.unwrap_or_default(),
+            formula: field.formula,
         }
     }
 }
@@ -438,6 +618,7 @@ impl From<TomlAction> for FormAction {
                 .as_deref()
                 .map(parse_param_type)
                 .unwrap_or(ParamType::Named),
+            pipeline: action.pipeline.map(parse_pipeline),
         }
     }
 }
@@ -466,6 +647,7 @@ impl From<TomlPaginationConfig> for crate::forms::PaginationConfig {
 
 impl From<JsonForm> for Form {
     fn from(json_form: JsonForm) -> Self {
+        let field_order = json_form.fields.keys().cloned().collect();
         let fields = json_form
             .fields
             .into_iter()
@@ -483,9 +665,11 @@ impl From<JsonForm> for Form {
             schema: json_form.schema,
             description: json_form.description,
             fields,
+            field_order,
             actions,
             ui_config: json_form.ui_config.map(Into::into),
             pagination: json_form.pagination.map(Into::into),
+            cross_field_rules: json_form.cross_field_rules,
         }
     }
 }
@@ -499,6 +683,9 @@ impl From<JsonField> for FormField {
             width: field.width,
             default: field.default,
             validations: field.validations.map(Into::into),
+            visible_when: field.visible_when,
+            computed_from: field.computed_from.unwrap_or_default(),
+            formula: field.formula,
         }
     }
 }
@@ -527,6 +714,7 @@ impl From<JsonAction> for FormAction {
                 .as_deref()
                 .map(parse_param_type)
                 .unwrap_or(ParamType::Named),
+            pipeline: action.pipeline.map(parse_pipeline),
         }
     }
 }
@@ -581,6 +769,15 @@ fn parse_action_type(type_str: &str) -> ActionType {
     }
 }
 
+fn parse_pipeline(steps: Vec<(String, HashMap<String, String>)>) -> ActionPipeline {
+    ActionPipeline {
+        steps: steps
+            .into_iter()
+            .map(|(sql, mapping)| PipelineStep { sql, mapping })
+            .collect(),
+    }
+}
+
 fn parse_param_type(type_str: &str) -> ParamType {
     match type_str.to_lowercase().as_str() {
         "positional" | "pos" => ParamType::Positional,
@@ -597,3 +794,176 @@ fn parse_layout_type(layout_str: &str) -> crate::forms::LayoutType {
         _ => crate::forms::LayoutType::Single,
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const BASE_FORM: &str = r#"
+title = "Empleados"
+schema = "rrhh"
+
+[fields.nombre]
+label = "Nombre"
+type = "text"
+required = true
+
+[fields.depto]
+label = "Departamento"
+type = "int"
+
+[actions.buscar]
+action_type = "query"
+sql = "SELECT * FROM empleados WHERE nombre = :nombre"
+params = ["nombre"]
+
+[ui_config]
+width = 60
+layout = "single"
+"#;
+
+    fn write_form(dir: &Path, name: &str, content: &str) -> PathBuf {
+        let path = dir.join(name);
+        fs::write(&path, content).unwrap();
+        path
+    }
+
+    #[test]
+    fn test_extends_merges_parent_fields() {
+        let dir = tempfile::tempdir().unwrap();
+        write_form(dir.path(), "base_employee.toml", BASE_FORM);
+        let child = write_form(
+            dir.path(),
+            "employee_edit.toml",
+            r#"
+extends = "base_employee.toml"
+
+[fields.depto]
+label = "Depto (editable)"
+type = "int"
+required = true
+"#,
+        );
+
+        let form = FormLoader::default().load_from_path(&child).unwrap();
+
+        assert_eq!(form.title, "Empleados");
+        assert_eq!(form.fields.len(), 2);
+        assert_eq!(form.fields["nombre"].label, "Nombre");
+        assert_eq!(form.fields["depto"].label, "Depto (editable)");
+        assert!(form.fields["depto"].required);
+        assert!(form.actions.contains_key("buscar"));
+        assert_eq!(form.ui_config.unwrap().width, Some(60));
+    }
+
+    #[test]
+    fn test_extends_child_overrides_actions_and_ui() {
+        let dir = tempfile::tempdir().unwrap();
+        write_form(dir.path(), "base.toml", BASE_FORM);
+        let child = write_form(
+            dir.path(),
+            "child.toml",
+            r#"
+extends = "base.toml"
+title = "Editar empleado"
+
+[actions.buscar]
+action_type = "update"
+sql = "UPDATE empleados SET depto = :depto WHERE nombre = :nombre"
+
+[ui_config]
+width = 80
+"#,
+        );
+
+        let form = FormLoader::default().load_from_path(&child).unwrap();
+
+        assert_eq!(form.title, "Editar empleado");
+        assert!(matches!(
+            form.actions["buscar"].action_type,
+            ActionType::Update
+        ));
+        let ui = form.ui_config.unwrap();
+        assert_eq!(ui.width, Some(80));
+        assert!(ui.layout.is_none());
+    }
+
+    #[test]
+    fn test_fields_keep_declaration_order() {
+        let dir = tempfile::tempdir().unwrap();
+        write_form(dir.path(), "base.toml", BASE_FORM);
+        let child = write_form(
+            dir.path(),
+            "child.toml",
+            r#"
+extends = "base.toml"
+
+[fields.apellido]
+label = "Apellido"
+type = "text"
+"#,
+        );
+
+        let form = FormLoader::default().load_from_path(&child).unwrap();
+        assert_eq!(form.field_names(), vec!["nombre", "depto", "apellido"]);
+
+        let json = r#"{
+            "title": "Clientes",
+            "fields": {
+                "zona": { "label": "Zona", "field_type": "text" },
+                "alta": { "label": "Alta", "field_type": "date" }
+            },
+            "actions": {}
+        }"#;
+        let form = FormLoader::new(LoaderConfig {
+            strict_validation: false,
+            ..LoaderConfig::default()
+        })
+        .load_from_string(json, "clientes.json")
+        .unwrap();
+        assert_eq!(form.field_names(), vec!["zona", "alta"]);
+    }
+
+    #[test]
+    fn test_circular_inheritance() {
+        let dir = tempfile::tempdir().unwrap();
+        write_form(
+            dir.path(),
+            "a.toml",
+            "extends = \"b.toml\"\ntitle = \"A\"\n",
+        );
+        let b = write_form(
+            dir.path(),
+            "b.toml",
+            "extends = \"a.toml\"\ntitle = \"B\"\n",
+        );
+
+        let loader = FormLoader::default();
+        assert!(matches!(
+            loader.load_from_path(&b),
+            Err(LoadError::CircularInheritance(_))
+        ));
+        assert!(matches!(
+            loader.resolve_inheritance_chain(&b),
+            Err(LoadError::CircularInheritance(_))
+        ));
+    }
+
+    #[test]
+    fn test_resolve_inheritance_chain() {
+        let dir = tempfile::tempdir().unwrap();
+        write_form(dir.path(), "base.toml", BASE_FORM);
+        write_form(dir.path(), "mid.toml", "extends = \"base.toml\"\n");
+        let leaf = write_form(dir.path(), "leaf.toml", "extends = \"mid.toml\"\n");
+
+        let chain = FormLoader::default()
+            .resolve_inheritance_chain(&leaf)
+            .unwrap();
+
+        let names: Vec<_> = chain
+            .iter()
+            .map(|p| p.file_name().unwrap().to_string_lossy().to_string())
+            .collect();
+        assert_eq!(names, vec!["leaf.toml", "mid.toml", "base.toml"]);
+    }
+}
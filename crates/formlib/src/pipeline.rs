@@ -0,0 +1,259 @@
+//! Pipelines de acciones
+//!
+//! Ejecuta en orden los pasos de un `ActionPipeline`: la primera fila de
+//! cada paso se convierte, según su `mapping`, en parámetros del siguiente.
+//! `FormPipelineExecutor::run_action` ejecuta además las acciones de una
+//! sola sentencia.
+
+use noctra_core::types::Parameters;
+use noctra_core::{Executor, NoctraError, ResultSet, RqlQuery, Session};
+use thiserror::Error;
+
+use crate::forms::{ActionPipeline, ActionType, FormAction, FormExecutionResult};
+
+/// Error de ejecución de un pipeline
+#[derive(Error, Debug)]
+pub enum PipelineError {
+    /// Pipeline sin pasos
+    #[error("El pipeline no tiene pasos")]
+    Empty,
+
+    /// Acción sin `sql` ni `pipeline`
+    #[error("La acción no tiene SQL ni pipeline")]
+    MissingSql,
+
+    /// Un paso intermedio no devolvió filas que mapear
+    #[error("El paso {0} del pipeline no devolvió filas")]
+    NoRows(usize),
+
+    /// El mapping usa una columna que el paso no devolvió
+    #[error("El paso {step} del pipeline no devolvió la columna '{column}'")]
+    MissingColumn { step: usize, column: String },
+
+    /// Error ejecutando la consulta de un paso
+    #[error("Error en el paso {step} del pipeline: {source}")]
+    Query {
+        step: usize,
+        #[source]
+        source: NoctraError,
+    },
+}
+
+/// Resultado de ejecución de un pipeline
+pub type PipelineResult<T> = Result<T, PipelineError>;
+
+/// Ejecutor de pipelines de acciones
+#[derive(Debug, Clone, Copy, Default)]
+pub struct FormPipelineExecutor;
+
+impl FormPipelineExecutor {
+    /// Ejecutar el pipeline y devolver el resultado del último paso
+    ///
+    /// El primer paso recibe `initial_params`. Los siguientes reciben además
+    /// los valores mapeados de la primera fila del paso anterior (que pisan
+    /// a los anteriores con el mismo nombre). Todos los pasos se ejecutan en
+    /// una sesión nueva.
+    pub fn run(
+        pipeline: &ActionPipeline,
+        initial_params: Parameters,
+        executor: &Executor,
+    ) -> PipelineResult<ResultSet> {
+        let session = Session::new();
+        let mut params = initial_params;
+        let mut steps = pipeline.steps.iter().enumerate().peekable();
+
+        while let Some((index, step)) = steps.next() {
+            let number = index + 1;
+            let result = executor
+                .execute_rql(&session, RqlQuery::new(step.sql.as_str(), params.clone()))
+                .map_err(|source| PipelineError::Query {
+                    step: number,
+                    source,
+                })?;
+
+            if steps.peek().is_none() {
+                return Ok(result);
+            }
+
+            let row = result.rows.first().ok_or(PipelineError::NoRows(number))?;
+            for (column, param) in &step.mapping {
+                let value = result
+                    .columns
+                    .iter()
+                    .position(|c| c.name.eq_ignore_ascii_case(column))
+                    .and_then(|i| row.values.get(i))
+                    .ok_or_else(|| PipelineError::MissingColumn {
+                        step: number,
+                        column: column.clone(),
+                    })?;
+                params.insert(param.clone(), value.clone());
+            }
+        }
+
+        Err(PipelineError::Empty)
+    }
+
+    /// Ejecutar una acción de formulario con `params`
+    ///
+    /// Las acciones con `pipeline` se ejecutan con `run`. Las de tipo Insert,
+    /// Update y Delete escriben con `Executor::execute_write` (en la fuente
+    /// activa si la hay) e informan filas afectadas e id insertado; el resto
+    /// ejecuta su `sql` como consulta. Los errores de la sentencia se
+    /// informan como paso 1.
+    pub fn run_action(
+        action: &FormAction,
+        params: Parameters,
+        executor: &Executor,
+    ) -> PipelineResult<FormExecutionResult> {
+        let rows_result = |data: ResultSet| FormExecutionResult {
+            success: true,
+            message: format!("{} fila(s)", data.rows.len()),
+            data: Some(data),
+            insert_id: None,
+            affected_rows: None,
+        };
+
+        if let Some(pipeline) = &action.pipeline {
+            return Self::run(pipeline, params, executor).map(rows_result);
+        }
+
+        let sql = action.sql.as_deref().ok_or(PipelineError::MissingSql)?;
+        let session = Session::new();
+        let query = RqlQuery::new(sql, params);
+        let query_error = |source| PipelineError::Query { step: 1, source };
+
+        match action.action_type {
+            ActionType::Insert | ActionType::Update | ActionType::Delete => {
+                let write = executor
+                    .execute_write(&session, query)
+                    .map_err(query_error)?;
+                Ok(FormExecutionResult {
+                    success: true,
+                    message: format!("{} fila(s) afectada(s)", write.rows_affected),
+                    data: None,
+                    insert_id: write.last_insert_rowid,
+                    affected_rows: Some(write.rows_affected),
+                })
+            }
+            _ => executor
+                .execute_rql(&session, query)
+                .map(rows_result)
+                .map_err(query_error),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::loader::load_form;
+    use noctra_core::Value;
+
+    const ORDERS_FORM: &str = r#"
+title = "Pedidos por cliente"
+
+[fields.email]
+label = "Email"
+type = "text"
+
+[actions.search]
+action_type = "query"
+pipeline = [
+    ["SELECT id FROM customers WHERE email = :email", { id = "customer_id" }],
+    ["SELECT item FROM orders WHERE customer_id = :customer_id ORDER BY item", {}],
+]
+"#;
+
+    fn shop_executor() -> Executor {
+        let executor = Executor::new_sqlite_memory().unwrap();
+        let session = Session::new();
+        for sql in [
+            "CREATE TABLE customers (id INTEGER, email TEXT)",
+            "INSERT INTO customers VALUES (1, 'ana@example.com'), (2, 'luis@example.com')",
+            "CREATE TABLE orders (customer_id INTEGER, item TEXT)",
+            "INSERT INTO orders VALUES (1, 'lámpara'), (2, 'mesa'), (2, 'silla')",
+        ] {
+            executor.execute_rql(&session, RqlQuery::sql(sql)).unwrap();
+        }
+        executor
+    }
+
+    fn email(address: &str) -> Parameters {
+        let mut params = Parameters::new();
+        params.insert("email".to_string(), Value::Text(address.to_string()));
+        params
+    }
+
+    #[test]
+    fn test_two_step_pipeline() {
+        let form = load_form(ORDERS_FORM, "pedidos.toml").unwrap();
+        let pipeline = form.actions["search"].pipeline.as_ref().unwrap();
+        assert_eq!(pipeline.steps.len(), 2);
+        assert_eq!(pipeline.steps[0].mapping["id"], "customer_id");
+
+        let executor = shop_executor();
+        let result =
+            FormPipelineExecutor::run(pipeline, email("luis@example.com"), &executor).unwrap();
+        let items: Vec<String> = result
+            .rows
+            .iter()
+            .map(|r| r.values[0].to_string())
+            .collect();
+        assert_eq!(items, vec!["mesa", "silla"]);
+
+        let err =
+            FormPipelineExecutor::run(pipeline, email("nadie@example.com"), &executor).unwrap_err();
+        assert!(matches!(err, PipelineError::NoRows(1)));
+    }
+
+    #[test]
+    fn test_insert_action_uses_write_path() {
+        let form = load_form(
+            r#"
+title = "Alta de cliente"
+
+[fields.email]
+label = "Email"
+type = "text"
+
+[actions.save]
+action_type = "insert"
+sql = "INSERT INTO customers (id, email) VALUES (3, :email)"
+"#,
+            "alta.toml",
+        )
+        .unwrap();
+
+        let executor = shop_executor();
+        let result = FormPipelineExecutor::run_action(
+            &form.actions["save"],
+            email("eva@example.com"),
+            &executor,
+        )
+        .unwrap();
+        assert_eq!(result.affected_rows, Some(1));
+        assert_eq!(result.insert_id, Some(3));
+        assert!(result.data.is_none());
+
+        let mut action = form.actions["save"].clone();
+        action.sql = None;
+        let err =
+            FormPipelineExecutor::run_action(&action, Parameters::new(), &executor).unwrap_err();
+        assert!(matches!(err, PipelineError::MissingSql));
+    }
+
+    #[test]
+    fn test_pipeline_missing_column() {
+        let form = load_form(ORDERS_FORM, "pedidos.toml").unwrap();
+        let mut pipeline = form.actions["search"].pipeline.clone().unwrap();
+        pipeline.steps[0]
+            .mapping
+            .insert("codigo".to_string(), "x".to_string());
+
+        let err = FormPipelineExecutor::run(&pipeline, email("ana@example.com"), &shop_executor())
+            .unwrap_err();
+        assert!(
+            matches!(err, PipelineError::MissingColumn { step: 1, ref column } if column == "codigo")
+        );
+    }
+}
@@ -5,10 +5,10 @@
 
 use chrono::{NaiveDate, NaiveDateTime};
 use regex::Regex;
-use std::collections::HashMap;
+use std::collections::{HashMap, HashSet};
 use thiserror::Error;
 
-use crate::forms::{FieldType, FieldValidations, Form, FormField};
+use crate::forms::{CrossFieldRule, FieldType, FieldValidations, Form, FormField};
 
 /// Error de validación
 #[derive(Error, Debug)]
@@ -36,6 +36,18 @@ pub enum ValidationError {
     /// Valor no permitido
     #[error("Valor '{0}' no permitido en campo '{1}'")]
     ValueNotAllowed(String, String),
+
+    /// Fórmula de campo calculado inválida o que no se pudo evaluar
+    #[error("Fórmula inválida en campo '{0}': {1}")]
+    InvalidFormula(String, String),
+
+    /// Campos calculados que dependen unos de otros en ciclo
+    #[error("Dependencia circular entre campos calculados: {0}")]
+    CircularDependency(String),
+
+    /// Regla entre campos no cumplida (campos de la regla, mensaje)
+    #[error("{1} (campos: {})", .0.join(", "))]
+    CrossField(Vec<String>, String),
 }
 
 /// Resultado de validación
@@ -89,6 +101,49 @@ impl FormValidator {
         }
     }
 
+    /// Validar las reglas entre campos
+    ///
+    /// Se llama después de validar cada campo. Una regla con algún campo
+    /// vacío no se evalúa (de eso se ocupa `required`); una condición que no
+    /// parsea o no se puede evaluar da `InvalidFormula`.
+    pub fn validate_cross_fields(
+        &self,
+        values: &HashMap<String, String>,
+        rules: &[CrossFieldRule],
+    ) -> Vec<ValidationError> {
+        let mut errors = Vec::new();
+        for rule in rules {
+            let invalid =
+                |message| ValidationError::InvalidFormula(rule.fields.join(", "), message);
+            let condition = match CrossFieldCondition::parse(&rule.condition) {
+                Ok(condition) => condition,
+                Err(message) => {
+                    errors.push(invalid(message));
+                    continue;
+                }
+            };
+
+            let complete = rule
+                .fields
+                .iter()
+                .chain(condition.fields())
+                .all(|field| values.get(field).is_some_and(|v| !v.trim().is_empty()));
+            if !complete {
+                continue;
+            }
+
+            match condition.evaluate(values) {
+                Ok(true) => {}
+                Ok(false) => errors.push(ValidationError::CrossField(
+                    rule.fields.clone(),
+                    rule.message.clone(),
+                )),
+                Err(message) => errors.push(invalid(message)),
+            }
+        }
+        errors
+    }
+
     /// Validar campo individual
     pub fn validate_field(&self, field: &FormField, value: &str) -> ValidationResult {
         // Validar tipo
@@ -377,3 +432,660 @@ impl FormValidator {
         }
     }
 }
+
+/// Expresión de una fórmula ya parseada
+#[derive(Debug, Clone)]
+enum FormulaExpr {
+    Number(f64),
+    Field(String),
+    Neg(Box<FormulaExpr>),
+    Binary(char, Box<FormulaExpr>, Box<FormulaExpr>),
+}
+
+/// Token de una fórmula
+#[derive(Debug, Clone, PartialEq)]
+enum FormulaToken {
+    Number(f64),
+    Field(String),
+    Op(char),
+    LParen,
+    RParen,
+}
+
+/// Evaluador de fórmulas aritméticas de campos calculados
+///
+/// Gramática: `expr := term (('+' | '-') term)*`,
+/// `term := factor (('*' | '/') factor)*` y
+/// `factor := número | campo | '-' factor | '(' expr ')'`. Los campos se
+/// resuelven con los valores actuales del formulario.
+#[derive(Debug, Clone)]
+pub struct FormulaEvaluator {
+    expr: FormulaExpr,
+    fields: Vec<String>,
+}
+
+impl FormulaEvaluator {
+    /// Parsear una fórmula
+    pub fn parse(formula: &str) -> Result<Self, String> {
+        let tokens = tokenize_formula(formula)?;
+        let mut pos = 0;
+        let expr = parse_sum(&tokens, &mut pos)?;
+        if let Some(token) = tokens.get(pos) {
+            return Err(format!("token inesperado {:?}", token));
+        }
+
+        let mut fields = Vec::new();
+        for token in &tokens {
+            if let FormulaToken::Field(name) = token {
+                if !fields.contains(name) {
+                    fields.push(name.clone());
+                }
+            }
+        }
+        Ok(Self { expr, fields })
+    }
+
+    /// Campos que usa la fórmula, en orden de aparición
+    pub fn fields(&self) -> &[String] {
+        &self.fields
+    }
+
+    /// Evaluar la fórmula con los valores del formulario
+    pub fn evaluate(&self, values: &HashMap<String, String>) -> Result<f64, String> {
+        evaluate_expr(&self.expr, values)
+    }
+}
+
+/// Comparación de una regla entre campos: `fórmula op fórmula`
+///
+/// Cada lado se evalúa con `FormulaEvaluator`. Un lado que es sólo un campo
+/// con un valor no numérico (p. ej. una fecha) se compara como fecha si
+/// ambos lados lo son, y si no como texto.
+#[derive(Debug, Clone)]
+struct CrossFieldCondition {
+    left: FormulaEvaluator,
+    op: &'static str,
+    right: FormulaEvaluator,
+    fields: Vec<String>,
+}
+
+/// Operando ya evaluado de una comparación
+#[derive(Debug, Clone, PartialEq, PartialOrd)]
+enum Operand {
+    Number(f64),
+    Date(NaiveDateTime),
+    Text(String),
+}
+
+impl CrossFieldCondition {
+    /// Operadores de comparación; los de dos caracteres primero
+    const OPERATORS: [&'static str; 8] = ["<=", ">=", "!=", "<>", "==", "<", ">", "="];
+
+    fn parse(condition: &str) -> Result<Self, String> {
+        let (position, op) = condition
+            .char_indices()
+            .find_map(|(position, _)| {
+                Self::OPERATORS
+                    .iter()
+                    .find(|op| condition[position..].starts_with(*op))
+                    .map(|op| (position, *op))
+            })
+            .ok_or_else(|| format!("falta una comparación en '{}'", condition))?;
+
+        let left = FormulaEvaluator::parse(&condition[..position])?;
+        let right = FormulaEvaluator::parse(&condition[position + op.len()..])?;
+        let mut fields = left.fields().to_vec();
+        for field in right.fields() {
+            if !fields.contains(field) {
+                fields.push(field.clone());
+            }
+        }
+        Ok(Self {
+            left,
+            op,
+            right,
+            fields,
+        })
+    }
+
+    /// Campos que usa la condición
+    fn fields(&self) -> &[String] {
+        &self.fields
+    }
+
+    fn evaluate(&self, values: &HashMap<String, String>) -> Result<bool, String> {
+        let left = operand(&self.left, values)?;
+        let right = operand(&self.right, values)?;
+        let ordering = match (&left, &right) {
+            (Operand::Number(_), Operand::Number(_))
+            | (Operand::Date(_), Operand::Date(_))
+            | (Operand::Text(_), Operand::Text(_)) => left.partial_cmp(&right),
+            _ => None,
+        }
+        .ok_or_else(|| format!("no se pueden comparar {:?} y {:?}", left, right))?;
+
+        Ok(match self.op {
+            "<" => ordering.is_lt(),
+            "<=" => ordering.is_le(),
+            ">" => ordering.is_gt(),
+            ">=" => ordering.is_ge(),
+            "!=" | "<>" => ordering.is_ne(),
+            _ => ordering.is_eq(),
+        })
+    }
+}
+
+/// Evaluar un lado de la comparación
+fn operand(
+    evaluator: &FormulaEvaluator,
+    values: &HashMap<String, String>,
+) -> Result<Operand, String> {
+    match (evaluator.evaluate(values), &evaluator.expr) {
+        (Ok(number), _) => Ok(Operand::Number(number)),
+        (Err(_), FormulaExpr::Field(name)) => {
+            let value = values.get(name).map(|v| v.trim()).unwrap_or("");
+            let date = NaiveDateTime::parse_from_str(value, "%Y-%m-%d %H:%M:%S")
+                .ok()
+                .or_else(|| {
+                    NaiveDate::parse_from_str(value, "%Y-%m-%d")
+                        .ok()
+                        .and_then(|date| date.and_hms_opt(0, 0, 0))
+                });
+            Ok(date.map_or_else(|| Operand::Text(value.to_string()), Operand::Date))
+        }
+        (Err(message), _) => Err(message),
+    }
+}
+
+/// Comprobar al cargar el formulario que una regla entre campos parsea y
+/// sólo nombra campos existentes
+pub(crate) fn check_cross_field_rule(
+    form: &Form,
+    rule: &CrossFieldRule,
+) -> Result<(), ValidationError> {
+    let invalid = |message| ValidationError::InvalidFormula(rule.fields.join(", "), message);
+    let condition = CrossFieldCondition::parse(&rule.condition).map_err(invalid)?;
+    match rule
+        .fields
+        .iter()
+        .chain(condition.fields())
+        .find(|field| !form.fields.contains_key(*field))
+    {
+        Some(missing) => Err(invalid(format!("campo '{}' no existe", missing))),
+        None => Ok(()),
+    }
+}
+
+/// Campos calculados de un formulario en orden de evaluación
+///
+/// Las dependencias de un campo son sus `computed_from` más los campos que
+/// nombra su `formula`. El orden es topológico (DFS): cada campo calculado
+/// va después de los campos de los que depende.
+#[derive(Debug, Clone, Default)]
+pub struct DependencyGraph {
+    /// Campos calculados, dependencias primero
+    order: Vec<String>,
+
+    /// Fórmula y dependencias de cada campo calculado
+    formulas: HashMap<String, (FormulaEvaluator, Vec<String>)>,
+}
+
+impl DependencyGraph {
+    /// Construir el grafo de los campos calculados de `form`
+    ///
+    /// Falla con `InvalidFormula` si una fórmula no parsea o nombra un campo
+    /// inexistente, y con `CircularDependency` si hay un ciclo.
+    pub fn from_form(form: &Form) -> Result<Self, ValidationError> {
+        let mut formulas = HashMap::new();
+        for (name, field) in &form.fields {
+            let Some(formula) = &field.formula else {
+                if !field.computed_from.is_empty() {
+                    return Err(ValidationError::InvalidFormula(
+                        name.clone(),
+                        "computed_from sin formula".to_string(),
+                    ));
+                }
+                continue;
+            };
+
+            let evaluator = FormulaEvaluator::parse(formula)
+                .map_err(|message| ValidationError::InvalidFormula(name.clone(), message))?;
+            let mut dependencies = field.computed_from.clone();
+            for field_name in evaluator.fields() {
+                if !dependencies.contains(field_name) {
+                    dependencies.push(field_name.clone());
+                }
+            }
+            if let Some(missing) = dependencies
+                .iter()
+                .find(|dependency| !form.fields.contains_key(*dependency))
+            {
+                return Err(ValidationError::InvalidFormula(
+                    name.clone(),
+                    format!("campo '{}' no existe", missing),
+                ));
+            }
+            formulas.insert(name.clone(), (evaluator, dependencies));
+        }
+
+        // Orden estable: recorrer los campos por nombre
+        let mut names: Vec<&String> = formulas.keys().collect();
+        names.sort();
+
+        let mut order = Vec::new();
+        let mut visited = HashSet::new();
+        let mut path = Vec::new();
+        for name in names {
+            visit_dependencies(name, &formulas, &mut visited, &mut path, &mut order)?;
+        }
+
+        Ok(Self { order, formulas })
+    }
+
+    /// Campos calculados en orden de evaluación
+    pub fn evaluation_order(&self) -> &[String] {
+        &self.order
+    }
+
+    /// Si `field_name` es un campo calculado
+    pub fn is_computed(&self, field_name: &str) -> bool {
+        self.formulas.contains_key(field_name)
+    }
+
+    /// Campos calculados afectados (directa o indirectamente) por un cambio
+    /// en `field_name`, en orden de evaluación
+    pub fn dependents_of(&self, field_name: &str) -> Vec<&str> {
+        let mut affected: Vec<&str> = Vec::new();
+        for name in &self.order {
+            let (_, dependencies) = &self.formulas[name];
+            if dependencies.iter().any(|dependency| {
+                dependency == field_name || affected.contains(&dependency.as_str())
+            }) {
+                affected.push(name);
+            }
+        }
+        affected
+    }
+
+    /// Recalcular en `values` los campos que dependen de `field_name`
+    ///
+    /// Un campo cuya fórmula falla queda vacío y se sigue con el resto; se
+    /// devuelve el primer error.
+    pub fn recompute(
+        &self,
+        field_name: &str,
+        values: &mut HashMap<String, String>,
+    ) -> Result<(), ValidationError> {
+        let dependents: Vec<String> = self
+            .dependents_of(field_name)
+            .into_iter()
+            .map(String::from)
+            .collect();
+        self.compute_fields(&dependents, values)
+    }
+
+    /// Calcular en `values` todos los campos calculados
+    pub fn compute_all(&self, values: &mut HashMap<String, String>) -> Result<(), ValidationError> {
+        self.compute_fields(&self.order, values)
+    }
+
+    fn compute_fields(
+        &self,
+        fields: &[String],
+        values: &mut HashMap<String, String>,
+    ) -> Result<(), ValidationError> {
+        let mut first_error = None;
+        for name in fields {
+            let (evaluator, dependencies) = &self.formulas[name];
+
+            // Mientras falte algún dato el campo queda vacío
+            let complete = dependencies
+                .iter()
+                .all(|dependency| values.get(dependency).is_some_and(|v| !v.trim().is_empty()));
+            let value = if complete {
+                match evaluator.evaluate(values) {
+                    Ok(result) => format_number(result),
+                    Err(message) => {
+                        first_error
+                            .get_or_insert(ValidationError::InvalidFormula(name.clone(), message));
+                        String::new()
+                    }
+                }
+            } else {
+                String::new()
+            };
+            values.insert(name.clone(), value);
+        }
+
+        match first_error {
+            Some(error) => Err(error),
+            None => Ok(()),
+        }
+    }
+}
+
+/// Visitar las dependencias de `field_name` (DFS) y añadirlo a `order`
+///
+/// `path` lleva los campos en curso: volver a uno de ellos es un ciclo.
+fn visit_dependencies(
+    field_name: &str,
+    formulas: &HashMap<String, (FormulaEvaluator, Vec<String>)>,
+    visited: &mut HashSet<String>,
+    path: &mut Vec<String>,
+    order: &mut Vec<String>,
+) -> Result<(), ValidationError> {
+    if let Some(start) = path.iter().position(|name| name == field_name) {
+        let mut cycle = path[start..].to_vec();
+        cycle.push(field_name.to_string());
+        return Err(ValidationError::CircularDependency(cycle.join(" -> ")));
+    }
+    if visited.contains(field_name) {
+        return Ok(());
+    }
+    // Los campos de entrada no tienen dependencias
+    let Some((_, dependencies)) = formulas.get(field_name) else {
+        return Ok(());
+    };
+
+    path.push(field_name.to_string());
+    for dependency in dependencies {
+        visit_dependencies(dependency, formulas, visited, path, order)?;
+    }
+    path.pop();
+
+    visited.insert(field_name.to_string());
+    order.push(field_name.to_string());
+    Ok(())
+}
+
+/// Formatear un resultado: sin decimales si es entero
+fn format_number(value: f64) -> String {
+    if value.fract() == 0.0 && value.abs() < 1e15 {
+        format!("{}", value as i64)
+    } else {
+        value.to_string()
+    }
+}
+
+/// `term (('+' | '-') term)*`
+fn parse_sum(tokens: &[FormulaToken], pos: &mut usize) -> Result<FormulaExpr, String> {
+    let mut expr = parse_product(tokens, pos)?;
+    while let Some(FormulaToken::Op(op @ ('+' | '-'))) = tokens.get(*pos) {
+        *pos += 1;
+        let right = parse_product(tokens, pos)?;
+        expr = FormulaExpr::Binary(*op, Box::new(expr), Box::new(right));
+    }
+    Ok(expr)
+}
+
+/// `factor (('*' | '/') factor)*`
+fn parse_product(tokens: &[FormulaToken], pos: &mut usize) -> Result<FormulaExpr, String> {
+    let mut expr = parse_factor(tokens, pos)?;
+    while let Some(FormulaToken::Op(op @ ('*' | '/'))) = tokens.get(*pos) {
+        *pos += 1;
+        let right = parse_factor(tokens, pos)?;
+        expr = FormulaExpr::Binary(*op, Box::new(expr), Box::new(right));
+    }
+    Ok(expr)
+}
+
+/// `número | campo | '-' factor | '(' expr ')'`
+fn parse_factor(tokens: &[FormulaToken], pos: &mut usize) -> Result<FormulaExpr, String> {
+    let token = tokens.get(*pos).cloned();
+    *pos += 1;
+    match token {
+        Some(FormulaToken::Number(n)) => Ok(FormulaExpr::Number(n)),
+        Some(FormulaToken::Field(name)) => Ok(FormulaExpr::Field(name)),
+        Some(FormulaToken::Op('-')) => Ok(FormulaExpr::Neg(Box::new(parse_factor(tokens, pos)?))),
+        Some(FormulaToken::LParen) => {
+            let expr = parse_sum(tokens, pos)?;
+            if tokens.get(*pos) != Some(&FormulaToken::RParen) {
+                return Err("falta ')'".to_string());
+            }
+            *pos += 1;
+            Ok(expr)
+        }
+        Some(other) => Err(format!("se esperaba un operando, se encontró {:?}", other)),
+        None => Err("la fórmula termina sin operando".to_string()),
+    }
+}
+
+fn evaluate_expr(expr: &FormulaExpr, values: &HashMap<String, String>) -> Result<f64, String> {
+    match expr {
+        FormulaExpr::Number(n) => Ok(*n),
+        FormulaExpr::Field(name) => {
+            let value = values
+                .get(name)
+                .ok_or_else(|| format!("campo '{}' sin valor", name))?;
+            value
+                .trim()
+                .parse::<f64>()
+                .map_err(|_| format!("'{}' no es un número (campo '{}')", value, name))
+        }
+        FormulaExpr::Neg(inner) => Ok(-evaluate_expr(inner, values)?),
+        FormulaExpr::Binary(op, left, right) => {
+            let left = evaluate_expr(left, values)?;
+            let right = evaluate_expr(right, values)?;
+            match op {
+                '+' => Ok(left + right),
+                '-' => Ok(left - right),
+                '*' => Ok(left * right),
+                _ if right == 0.0 => Err("división por cero".to_string()),
+                _ => Ok(left / right),
+            }
+        }
+    }
+}
+
+/// Dividir una fórmula en tokens
+fn tokenize_formula(formula: &str) -> Result<Vec<FormulaToken>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = formula.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '+' | '-' | '*' | '/' => {
+                chars.next();
+                tokens.push(FormulaToken::Op(c));
+            }
+            '(' => {
+                chars.next();
+                tokens.push(FormulaToken::LParen);
+            }
+            ')' => {
+                chars.next();
+                tokens.push(FormulaToken::RParen);
+            }
+            c if c.is_ascii_digit() || c == '.' => {
+                let mut number = String::new();
+                while let Some(&ch) = chars.peek() {
+                    if !(ch.is_ascii_digit() || ch == '.') {
+                        break;
+                    }
+                    number.push(ch);
+                    chars.next();
+                }
+                let value = number
+                    .parse::<f64>()
+                    .map_err(|_| format!("número inválido '{}'", number))?;
+                tokens.push(FormulaToken::Number(value));
+            }
+            c if c.is_alphabetic() || c == '_' => {
+                let mut name = String::new();
+                while let Some(&ch) = chars.peek() {
+                    if !(ch.is_alphanumeric() || ch == '_') {
+                        break;
+                    }
+                    name.push(ch);
+                    chars.next();
+                }
+                tokens.push(FormulaToken::Field(name));
+            }
+            other => return Err(format!("carácter inesperado '{}'", other)),
+        }
+    }
+
+    if tokens.is_empty() {
+        return Err("fórmula vacía".to_string());
+    }
+    Ok(tokens)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::forms::FieldType;
+
+    fn form_with(fields: &[(&str, Option<&str>)]) -> Form {
+        let fields = fields
+            .iter()
+            .map(|(name, formula)| {
+                let field = FormField {
+                    label: name.to_string(),
+                    field_type: FieldType::Float,
+                    required: false,
+                    width: None,
+                    default: None,
+                    validations: None,
+                    visible_when: None,
+                    computed_from: Vec::new(),
+                    formula: formula.map(String::from),
+                };
+                (name.to_string(), field)
+            })
+            .collect();
+
+        Form {
+            title: "Calculados".to_string(),
+            schema: None,
+            description: None,
+            fields,
+            field_order: Vec::new(),
+            actions: HashMap::new(),
+            ui_config: None,
+            pagination: None,
+            cross_field_rules: Vec::new(),
+        }
+    }
+
+    #[test]
+    fn test_formula_evaluator() {
+        let values: HashMap<String, String> = [("cantidad", "3"), ("precio", "2.5")]
+            .into_iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect();
+
+        let formula = FormulaEvaluator::parse("(cantidad + 1) * precio - -2 / 4").unwrap();
+        assert_eq!(formula.fields(), ["cantidad", "precio"]);
+        assert_eq!(formula.evaluate(&values), Ok(10.5));
+
+        assert!(FormulaEvaluator::parse("cantidad *").is_err());
+        assert!(FormulaEvaluator::parse("(cantidad").is_err());
+        assert!(FormulaEvaluator::parse("cantidad / 0")
+            .unwrap()
+            .evaluate(&values)
+            .is_err());
+    }
+
+    #[test]
+    fn test_dependency_chain_recomputes_on_change() {
+        let form = form_with(&[("a", None), ("b", Some("a * 2")), ("c", Some("b + 1"))]);
+        let graph = DependencyGraph::from_form(&form).unwrap();
+        assert_eq!(graph.evaluation_order(), ["b", "c"]);
+        assert_eq!(graph.dependents_of("a"), vec!["b", "c"]);
+
+        let mut values = HashMap::new();
+        values.insert("a".to_string(), "3".to_string());
+        graph.recompute("a", &mut values).unwrap();
+        assert_eq!(values["b"], "6");
+        assert_eq!(values["c"], "7");
+
+        values.insert("a".to_string(), "10".to_string());
+        graph.recompute("a", &mut values).unwrap();
+        assert_eq!(values["b"], "20");
+        assert_eq!(values["c"], "21");
+
+        values.insert("a".to_string(), String::new());
+        graph.recompute("a", &mut values).unwrap();
+        assert_eq!(values["c"], "");
+    }
+
+    #[test]
+    fn test_cross_field_rule_rejects_end_before_start() {
+        let rules = vec![
+            CrossFieldRule {
+                fields: vec!["start_date".to_string(), "end_date".to_string()],
+                condition: "start_date < end_date".to_string(),
+                message: "End must be after start".to_string(),
+            },
+            CrossFieldRule {
+                fields: vec!["cantidad".to_string()],
+                condition: "cantidad * 2 <= 10".to_string(),
+                message: "Demasiadas unidades".to_string(),
+            },
+        ];
+        let values = |start: &str, end: &str, cantidad: &str| -> HashMap<String, String> {
+            [
+                ("start_date", start),
+                ("end_date", end),
+                ("cantidad", cantidad),
+            ]
+            .into_iter()
+            .map(|(k, v)| (k.to_string(), v.to_string()))
+            .collect()
+        };
+        let validator = FormValidator::new();
+
+        let errors =
+            validator.validate_cross_fields(&values("2024-03-10", "2024-03-01", "3"), &rules);
+        assert_eq!(errors.len(), 1);
+        match &errors[0] {
+            ValidationError::CrossField(fields, message) => {
+                assert_eq!(fields, &["start_date", "end_date"]);
+                assert_eq!(message, "End must be after start");
+            }
+            other => panic!("se esperaba CrossField, se obtuvo {:?}", other),
+        }
+
+        assert!(validator
+            .validate_cross_fields(&values("2024-03-01", "2024-03-10", "5"), &rules)
+            .is_empty());
+        assert_eq!(
+            validator
+                .validate_cross_fields(&values("2024-03-01", "2024-03-10", "6"), &rules)
+                .len(),
+            1
+        );
+        // Con un campo vacío la regla no se evalúa
+        assert!(validator
+            .validate_cross_fields(&values("2024-03-10", "", "3"), &rules)
+            .is_empty());
+
+        let form = form_with(&[("start_date", None), ("end_date", None)]);
+        assert!(check_cross_field_rule(&form, &rules[0]).is_ok());
+        assert!(check_cross_field_rule(&form, &rules[1]).is_err());
+    }
+
+    #[test]
+    fn test_circular_dependency() {
+        let form = form_with(&[
+            ("a", Some("c - 1")),
+            ("b", Some("a * 2")),
+            ("c", Some("b + 1")),
+        ]);
+
+        match DependencyGraph::from_form(&form) {
+            Err(ValidationError::CircularDependency(cycle)) => {
+                assert_eq!(cycle, "a -> c -> b -> a");
+            }
+            other => panic!("se esperaba CircularDependency, se obtuvo {:?}", other),
+        }
+        assert!(matches!(
+            DependencyGraph::from_form(&form_with(&[("a", Some("x + 1"))])),
+            Err(ValidationError::InvalidFormula(_, _))
+        ));
+    }
+}
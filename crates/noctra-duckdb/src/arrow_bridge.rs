@@ -0,0 +1,147 @@
+//! Arrow interoperability
+//!
+//! Bridges Apache Arrow `RecordBatch`es (as produced by
+//! `DuckDBSource::query_arrow`) back into Noctra's `ResultSet` for code that
+//! still consumes the legacy type. Enabled by the `arrow-integration` feature.
+
+use crate::error::{DuckDBError, Result};
+use arrow::array::{
+    Array, BinaryArray, BooleanArray, Date32Array, Date64Array, Float16Array, Float32Array,
+    Float64Array, Int16Array, Int32Array, Int64Array, Int8Array, LargeBinaryArray, UInt16Array,
+    UInt32Array, UInt64Array, UInt8Array,
+};
+use arrow::datatypes::DataType;
+use arrow::record_batch::RecordBatch;
+use arrow::util::display::array_value_to_string;
+use chrono::NaiveTime;
+use noctra_core::types::{parse_timestamp, Column, ResultSet, Row, Value};
+
+/// Convert Arrow record batches into a Noctra `ResultSet`
+///
+/// The column layout is taken from the first batch; all batches must share
+/// the same schema.
+pub fn result_set_from_record_batches(batches: &[RecordBatch]) -> Result<ResultSet> {
+    let Some(first) = batches.first() else {
+        return Ok(ResultSet::empty());
+    };
+
+    let schema = first.schema();
+    let columns: Vec<Column> = schema
+        .fields()
+        .iter()
+        .enumerate()
+        .map(|(idx, field)| Column {
+            name: field.name().clone(),
+            data_type: field.data_type().to_string(),
+            ordinal: idx,
+        })
+        .collect();
+
+    let mut result = ResultSet::new(columns);
+
+    for batch in batches {
+        if batch.schema() != schema {
+            return Err(DuckDBError::SchemaError(
+                "Record batches have mismatching schemas".to_string(),
+            ));
+        }
+
+        for row_idx in 0..batch.num_rows() {
+            let values = batch
+                .columns()
+                .iter()
+                .map(|array| arrow_value(array.as_ref(), row_idx))
+                .collect::<Result<Vec<Value>>>()?;
+            result.add_row(Row::new(values));
+        }
+    }
+
+    Ok(result)
+}
+
+/// Convert a single Arrow array cell into a Noctra `Value`
+fn arrow_value(array: &dyn Array, idx: usize) -> Result<Value> {
+    if array.is_null(idx) {
+        return Ok(Value::Null);
+    }
+
+    macro_rules! integer {
+        ($array_type:ty) => {
+            array
+                .as_any()
+                .downcast_ref::<$array_type>()
+                .map(|a| Value::Integer(a.value(idx) as i64))
+        };
+    }
+
+    let value =
+        match array.data_type() {
+            DataType::Int8 => integer!(Int8Array),
+            DataType::Int16 => integer!(Int16Array),
+            DataType::Int32 => integer!(Int32Array),
+            DataType::Int64 => integer!(Int64Array),
+            DataType::UInt8 => integer!(UInt8Array),
+            DataType::UInt16 => integer!(UInt16Array),
+            DataType::UInt32 => integer!(UInt32Array),
+            DataType::UInt64 => array.as_any().downcast_ref::<UInt64Array>().map(|a| {
+                match i64::try_from(a.value(idx)) {
+                    Ok(v) => Value::Integer(v),
+                    Err(_) => Value::Text(a.value(idx).to_string()),
+                }
+            }),
+            DataType::Float16 => array
+                .as_any()
+                .downcast_ref::<Float16Array>()
+                .map(|a| Value::Float(a.value(idx).to_f64())),
+            DataType::Float32 => array
+                .as_any()
+                .downcast_ref::<Float32Array>()
+                .map(|a| Value::Float(a.value(idx) as f64)),
+            DataType::Float64 => array
+                .as_any()
+                .downcast_ref::<Float64Array>()
+                .map(|a| Value::Float(a.value(idx))),
+            DataType::Boolean => array
+                .as_any()
+                .downcast_ref::<BooleanArray>()
+                .map(|a| Value::Boolean(a.value(idx))),
+            DataType::Date32 => array
+                .as_any()
+                .downcast_ref::<Date32Array>()
+                .and_then(|a| a.value_as_date(idx))
+                .map(Value::Date),
+            DataType::Date64 => array
+                .as_any()
+                .downcast_ref::<Date64Array>()
+                .and_then(|a| a.value_as_date(idx))
+                .map(Value::Date),
+            DataType::Time32(_) | DataType::Time64(_) => {
+                let text = display_value(array, idx)?;
+                Some(
+                    NaiveTime::parse_from_str(&text, "%H:%M:%S%.f")
+                        .map_or(Value::Text(text), Value::Time),
+                )
+            }
+            DataType::Timestamp(_, _) => {
+                let text = display_value(array, idx)?;
+                Some(parse_timestamp(&text).map_or(Value::Text(text), Value::Timestamp))
+            }
+            DataType::Binary => array
+                .as_any()
+                .downcast_ref::<BinaryArray>()
+                .map(|a| Value::Blob(a.value(idx).to_vec())),
+            DataType::LargeBinary => array
+                .as_any()
+                .downcast_ref::<LargeBinaryArray>()
+                .map(|a| Value::Blob(a.value(idx).to_vec())),
+            _ => Some(Value::Text(display_value(array, idx)?)),
+        };
+
+    value.ok_or_else(|| {
+        DuckDBError::TypeConversion(format!("Unexpected array layout for {}", array.data_type()))
+    })
+}
+
+fn display_value(array: &dyn Array, idx: usize) -> Result<String> {
+    array_value_to_string(array, idx).map_err(|e| DuckDBError::TypeConversion(e.to_string()))
+}
@@ -1,30 +1,189 @@
 //! Query execution engine for DuckDB backend
 
 use crate::error::{DuckDBError, Result};
-use crate::source::DuckDBSource;
+use crate::source::{is_read_only, DuckDBSource};
+use duckdb::Connection;
 use noctra_core::types::{Parameters, ResultSet};
-use noctra_core::DataSource;
+use std::collections::VecDeque;
+use std::sync::Mutex;
+
+/// Prepared statement cache counters
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct StatementCacheStats {
+    /// Executions that reused a cached statement
+    pub hits: u64,
+    /// Executions that had to prepare the statement
+    pub misses: u64,
+    /// Statements dropped to make room for new ones
+    pub evictions: u64,
+    /// Statements currently cached
+    pub size: usize,
+}
+
+/// Bookkeeping for the connection's prepared statement cache
+///
+/// DuckDB's own cache is an LRU keyed by SQL text; this mirrors its keys in
+/// the same order so hits, misses and evictions can be reported.
+#[derive(Debug)]
+struct StatementCache {
+    capacity: usize,
+    /// Cached SQL strings, least recently used first
+    keys: VecDeque<String>,
+    stats: StatementCacheStats,
+}
+
+impl StatementCache {
+    fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            keys: VecDeque::new(),
+            stats: StatementCacheStats::default(),
+        }
+    }
+
+    fn contains(&self, sql: &str) -> bool {
+        self.keys.iter().any(|key| key == sql)
+    }
+
+    /// Record a successful execution of `sql`
+    fn record(&mut self, sql: &str, hit: bool) {
+        if hit {
+            self.stats.hits += 1;
+        } else {
+            self.stats.misses += 1;
+        }
+
+        if self.capacity == 0 {
+            return;
+        }
+
+        if let Some(pos) = self.keys.iter().position(|key| key == sql) {
+            self.keys.remove(pos);
+        }
+        self.keys.push_back(sql.to_string());
+
+        if self.keys.len() > self.capacity {
+            self.keys.pop_front();
+            self.stats.evictions += 1;
+        }
+    }
+
+    fn clear(&mut self) {
+        self.keys.clear();
+    }
+
+    fn stats(&self) -> StatementCacheStats {
+        StatementCacheStats {
+            size: self.keys.len(),
+            ..self.stats
+        }
+    }
+}
 
 /// Query execution engine for DuckDB
+///
+/// Queries run on a dedicated connection that keeps an LRU cache of prepared
+/// statements (`DuckDBConfig::statement_cache_size`), so repeated SQL skips
+/// re-preparation. The cache is flushed whenever the schema may change.
+#[derive(Debug)]
 pub struct DuckDBEngine {
     source: DuckDBSource,
+    conn: Mutex<Connection>,
+    cache: Mutex<StatementCache>,
 }
 
 impl DuckDBEngine {
     /// Create a new DuckDB engine with in-memory database
     pub fn new_in_memory() -> Result<Self> {
-        let source = DuckDBSource::new_in_memory()?;
-        Ok(Self { source })
+        Self::new(DuckDBSource::new_in_memory()?)
+    }
+
+    /// Create an engine on top of an existing source, using its config
+    pub fn new(source: DuckDBSource) -> Result<Self> {
+        let capacity = source.config().statement_cache_size;
+        let conn = source.connection()?;
+        conn.set_prepared_statement_cache_capacity(capacity);
+
+        Ok(Self {
+            source,
+            conn: Mutex::new(conn),
+            cache: Mutex::new(StatementCache::new(capacity)),
+        })
     }
 
     /// Register a file for querying
     pub fn register_file(&mut self, file_path: &str, alias: &str) -> Result<()> {
-        self.source.register_file(file_path, alias)
+        self.invalidate_statement_cache();
+        self.source
+            .register_file(file_path, alias, true)
+            .map(|_| ())
     }
 
-    /// Execute a SQL query
+    /// Remove a registered table
+    pub fn unregister(&mut self, alias: &str) -> Result<()> {
+        self.invalidate_statement_cache();
+        self.source.unregister(alias)
+    }
+
+    /// Execute a SQL query, reusing the prepared statement for identical SQL
     pub fn execute_query(&self, sql: &str, params: &Parameters) -> Result<ResultSet> {
-        self.source.query(sql, params).map_err(|e| DuckDBError::QueryFailed(format!("Query execution failed: {}", e)))
+        let conn = self
+            .conn
+            .lock()
+            .map_err(|_| DuckDBError::QueryFailed("Mutex poisoned".to_string()))?;
+        let mut cache = self
+            .cache
+            .lock()
+            .map_err(|_| DuckDBError::QueryFailed("Mutex poisoned".to_string()))?;
+
+        let hit = cache.contains(sql);
+        let mut stmt = conn.prepare_cached(sql)?;
+        cache.record(sql, hit);
+
+        let read_only = is_read_only(sql);
+        if !read_only {
+            // Any write may change row counts; we don't track which tables
+            self.source.invalidate_statistics(None);
+        }
+
+        let limit = Some(self.source.config().max_result_rows);
+        let result = self
+            .source
+            .execute_prepared(&mut stmt, params, limit)
+            .map_err(|e| DuckDBError::QueryFailed(format!("Query execution failed: {}", e)));
+        drop(stmt);
+
+        // DDL may invalidate other cached plans
+        if !read_only && is_schema_change(sql) {
+            conn.flush_prepared_statement_cache();
+            cache.clear();
+        }
+        drop(cache);
+        drop(conn);
+
+        if !read_only && result.is_ok() {
+            self.source.record_write();
+        }
+
+        result
+    }
+
+    /// Prepared statement cache counters (for metrics)
+    pub fn cache_stats(&self) -> StatementCacheStats {
+        self.cache
+            .lock()
+            .map(|cache| cache.stats())
+            .unwrap_or_default()
+    }
+
+    /// Drop every cached prepared statement
+    pub fn invalidate_statement_cache(&self) {
+        if let Ok(conn) = self.conn.lock() {
+            conn.flush_prepared_statement_cache();
+        }
+        if let Ok(mut cache) = self.cache.lock() {
+            cache.clear();
+        }
     }
 
     /// Get the underlying DuckDB source
@@ -33,7 +192,113 @@ impl DuckDBEngine {
     }
 
     /// Get mutable access to the source
+    ///
+    /// Flushes the statement cache, since the caller may change the schema.
     pub fn source_mut(&mut self) -> &mut DuckDBSource {
+        self.invalidate_statement_cache();
         &mut self.source
     }
-}
\ No newline at end of file
+}
+
+/// Whether a statement may change the schema of existing tables
+fn is_schema_change(sql: &str) -> bool {
+    let keyword = sql.split_whitespace().next().unwrap_or("").to_uppercase();
+    matches!(
+        keyword.as_str(),
+        "CREATE" | "DROP" | "ALTER" | "ATTACH" | "DETACH"
+    )
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::source::DuckDBConfig;
+    use std::io::Write;
+
+    fn engine_with_numbers() -> (DuckDBEngine, tempfile::NamedTempFile) {
+        let mut temp_file = tempfile::Builder::new().suffix(".csv").tempfile().unwrap();
+        writeln!(temp_file, "id,value").unwrap();
+        for i in 0..10 {
+            writeln!(temp_file, "{},{}", i, i * 10).unwrap();
+        }
+        temp_file.flush().unwrap();
+
+        let mut engine = DuckDBEngine::new_in_memory().unwrap();
+        engine
+            .register_file(temp_file.path().to_str().unwrap(), "numbers")
+            .unwrap();
+        (engine, temp_file)
+    }
+
+    #[test]
+    fn test_second_execution_reuses_statement() {
+        let (engine, _file) = engine_with_numbers();
+        let sql = "SELECT value FROM numbers WHERE id = $id";
+
+        let mut params = Parameters::new();
+        params.insert("id".to_string(), noctra_core::types::Value::Integer(3));
+        let first = engine.execute_query(sql, &params).unwrap();
+        assert_eq!(engine.cache_stats().misses, 1);
+        assert_eq!(engine.cache_stats().hits, 0);
+
+        params.insert("id".to_string(), noctra_core::types::Value::Integer(4));
+        let second = engine.execute_query(sql, &params).unwrap();
+        let stats = engine.cache_stats();
+        assert_eq!(stats.hits, 1);
+        assert_eq!(stats.misses, 1);
+        assert_eq!(stats.size, 1);
+
+        assert_eq!(
+            first.rows[0].values[0],
+            noctra_core::types::Value::Integer(30)
+        );
+        assert_eq!(
+            second.rows[0].values[0],
+            noctra_core::types::Value::Integer(40)
+        );
+    }
+
+    #[test]
+    fn test_unregister_invalidates_cache() {
+        let (mut engine, file) = engine_with_numbers();
+        let sql = "SELECT COUNT(*) FROM numbers";
+
+        engine.execute_query(sql, &Parameters::new()).unwrap();
+        assert_eq!(engine.cache_stats().size, 1);
+
+        engine.unregister("numbers").unwrap();
+        assert_eq!(engine.cache_stats().size, 0);
+        assert!(engine.execute_query(sql, &Parameters::new()).is_err());
+
+        // Same SQL after re-registering is prepared again
+        engine
+            .register_file(file.path().to_str().unwrap(), "numbers")
+            .unwrap();
+        engine.execute_query(sql, &Parameters::new()).unwrap();
+        let stats = engine.cache_stats();
+        assert_eq!(stats.hits, 0);
+        assert_eq!(stats.misses, 2);
+    }
+
+    #[test]
+    fn test_cache_evicts_least_recently_used() {
+        let source = DuckDBSource::new_in_memory()
+            .unwrap()
+            .with_config(DuckDBConfig {
+                statement_cache_size: 2,
+                ..DuckDBConfig::default()
+            });
+        let engine = DuckDBEngine::new(source).unwrap();
+
+        for sql in ["SELECT 1", "SELECT 2", "SELECT 1", "SELECT 3", "SELECT 2"] {
+            engine.execute_query(sql, &Parameters::new()).unwrap();
+        }
+
+        // SELECT 2 was evicted by SELECT 3, then re-prepared (evicting SELECT 1)
+        let stats = engine.cache_stats();
+        assert_eq!(stats.hits, 1);
+        assert_eq!(stats.misses, 4);
+        assert_eq!(stats.evictions, 2);
+        assert_eq!(stats.size, 2);
+    }
+}
@@ -1,12 +1,46 @@
 //! Error types for noctra-duckdb
 
+use noctra_core::error::NoctraError;
 use thiserror::Error;
 
 /// Errors that can occur in the DuckDB backend
+///
+/// Failures reported by DuckDB are classified from their message prefix
+/// (`Catalog Error:`, `Parser Error:`, ...) so callers can tell them apart.
+/// Every variant has a stable SQLSTATE-like code, see [`DuckDBError::code`].
+/// Other DuckDB failures keep DuckDB's own error code as [`DuckDBError::Query`].
 #[derive(Error, Debug)]
 pub enum DuckDBError {
     #[error("DuckDB error: {0}")]
-    DuckDB(#[from] duckdb::Error),
+    DuckDB(duckdb::Error),
+
+    #[error("Catalog error: {0}")]
+    CatalogError(String),
+
+    #[error("Parser error: {message}")]
+    ParserError {
+        message: String,
+        /// Offset of the offending token within the reported SQL line
+        position: Option<usize>,
+    },
+
+    #[error("Binder error: {0}")]
+    BinderError(String),
+
+    #[error("Out of memory: {0}")]
+    OutOfMemory(String),
+
+    #[error("Query error ({code}): {message}")]
+    Query {
+        /// Error code reported by DuckDB
+        code: i32,
+        message: String,
+        /// DuckDB's suggestion, e.g. `Did you mean "people"?`
+        hint: Option<String>,
+    },
+
+    #[error("Constraint violation: {0}")]
+    ConstraintViolation(String),
 
     #[error("IO error: {0}")]
     Io(#[from] std::io::Error),
@@ -17,6 +51,15 @@ pub enum DuckDBError {
     #[error("File not found: {0}")]
     FileNotFound(String),
 
+    #[error("File too large: {0}")]
+    FileTooLarge(String),
+
+    #[error("Access denied: {0}")]
+    AccessDenied(String),
+
+    #[error("Excel error: {0}")]
+    Excel(String),
+
     #[error("Query execution failed: {0}")]
     QueryFailed(String),
 
@@ -25,7 +68,322 @@ pub enum DuckDBError {
 
     #[error("Schema introspection failed: {0}")]
     SchemaError(String),
+
+    #[error("Read-only database: {0}")]
+    ReadOnly(String),
+
+    #[error("Extension '{name}' unavailable: {reason}")]
+    ExtensionUnavailable { name: String, reason: String },
+
+    #[error("Invalid option: {0}")]
+    InvalidOption(String),
+}
+
+impl DuckDBError {
+    /// Stable SQLSTATE-like code for this error
+    pub fn code(&self) -> &'static str {
+        match self {
+            DuckDBError::DuckDB(_) => "XX000",
+            DuckDBError::CatalogError(_) => "42P01",
+            DuckDBError::ParserError { .. } => "42601",
+            DuckDBError::BinderError(_) => "42703",
+            DuckDBError::OutOfMemory(_) => "53200",
+            DuckDBError::Query { .. } => "XX000",
+            DuckDBError::ConstraintViolation(_) => "23000",
+            DuckDBError::Io(_) => "58030",
+            DuckDBError::UnsupportedFileType(_) => "0A000",
+            DuckDBError::FileNotFound(_) => "58P01",
+            DuckDBError::FileTooLarge(_) => "54000",
+            DuckDBError::AccessDenied(_) => "42501",
+            DuckDBError::Excel(_) => "58030",
+            DuckDBError::QueryFailed(_) => "XX000",
+            DuckDBError::TypeConversion(_) => "22018",
+            DuckDBError::SchemaError(_) => "42000",
+            DuckDBError::ReadOnly(_) => "25006",
+            DuckDBError::ExtensionUnavailable { .. } => "0A000",
+            DuckDBError::InvalidOption(_) => "22023",
+        }
+    }
+
+    /// Numbered Noctra error code (`NoctraError::error_code`)
+    ///
+    /// Same code the error carries once converted into `NoctraError`, so API
+    /// consumers see one code per failure whichever layer reports it.
+    pub fn error_code(&self) -> &'static str {
+        match self {
+            DuckDBError::FileNotFound(_) => "NOCTRA_E030",
+            DuckDBError::AccessDenied(_) => "NOCTRA_E040",
+            DuckDBError::ConstraintViolation(_) => "NOCTRA_E024",
+            DuckDBError::Io(_) => "NOCTRA_E001",
+            _ => "NOCTRA_E022",
+        }
+    }
+
+    /// Position of the offending token, for parser errors
+    pub fn position(&self) -> Option<usize> {
+        match self {
+            DuckDBError::ParserError { position, .. } => *position,
+            _ => None,
+        }
+    }
+
+    /// DuckDB's suggestion for fixing the query, if it gave one
+    pub fn hint(&self) -> Option<String> {
+        match self {
+            DuckDBError::Query { hint, .. } => hint.clone(),
+            DuckDBError::CatalogError(message) | DuckDBError::BinderError(message) => {
+                extract_hint(message)
+            }
+            _ => None,
+        }
+    }
+}
+
+impl From<duckdb::Error> for DuckDBError {
+    fn from(error: duckdb::Error) -> Self {
+        let message = error.to_string();
+
+        let classified = |prefix: &str| {
+            message
+                .strip_prefix(prefix)
+                .map(|rest| rest.trim_start().to_string())
+        };
+
+        if let Some(rest) = classified("Catalog Error:") {
+            DuckDBError::CatalogError(rest)
+        } else if let Some(rest) = classified("Parser Error:") {
+            DuckDBError::ParserError {
+                position: caret_position(&rest),
+                message: first_line(&rest),
+            }
+        } else if let Some(rest) = classified("Binder Error:") {
+            DuckDBError::BinderError(rest)
+        } else if let Some(rest) = classified("Out of Memory Error:") {
+            DuckDBError::OutOfMemory(rest)
+        } else if let Some(rest) = classified("Constraint Error:") {
+            DuckDBError::ConstraintViolation(rest)
+        } else if let Some(rest) = classified("Conversion Error:") {
+            DuckDBError::TypeConversion(rest)
+        } else if let Some(rest) = classified("IO Error:") {
+            DuckDBError::Io(std::io::Error::other(rest))
+        } else if message.contains("read-only mode") {
+            DuckDBError::ReadOnly(message)
+        } else if let duckdb::Error::DuckDBFailure(failure, _) = &error {
+            DuckDBError::Query {
+                code: failure.extended_code as i32,
+                hint: extract_hint(&message),
+                message: without_hint(&message),
+            }
+        } else {
+            DuckDBError::DuckDB(error)
+        }
+    }
+}
+
+impl From<DuckDBError> for NoctraError {
+    /// File and permission failures map to their own variants; SQL-level
+    /// failures become `NoctraError::Backend` with the SQLSTATE-like code.
+    fn from(error: DuckDBError) -> Self {
+        match error {
+            DuckDBError::FileNotFound(path) => return NoctraError::not_found("archivo", path),
+            DuckDBError::AccessDenied(message) => return NoctraError::permission(message),
+            DuckDBError::ConstraintViolation(message) => return NoctraError::Conflict(message),
+            DuckDBError::Io(e) => return NoctraError::Io(e.to_string()),
+            DuckDBError::InvalidOption(message) => return NoctraError::Validation(message),
+            _ => {}
+        }
+
+        let hint = error.hint();
+        let message = match hint {
+            Some(_) => without_hint(&error.to_string()),
+            None => error.to_string(),
+        };
+        NoctraError::backend_with_hint(error.code(), message, hint)
+    }
+}
+
+/// Whether a line of a DuckDB message is a suggestion
+fn is_hint_line(line: &str) -> bool {
+    let line = line.trim();
+    line.starts_with("Did you mean") || line.starts_with("Candidate bindings:")
+}
+
+/// Suggestion line from a DuckDB message (`Did you mean ...?`, `Candidate bindings: ...`)
+fn extract_hint(message: &str) -> Option<String> {
+    message
+        .lines()
+        .find(|line| is_hint_line(line))
+        .map(|line| line.trim().to_string())
+}
+
+/// DuckDB message with the suggestion line removed
+fn without_hint(message: &str) -> String {
+    message
+        .lines()
+        .filter(|line| !is_hint_line(line))
+        .collect::<Vec<_>>()
+        .join("\n")
+        .trim()
+        .to_string()
+}
+
+/// First line of a multi-line DuckDB message
+fn first_line(message: &str) -> String {
+    message
+        .lines()
+        .next()
+        .unwrap_or_default()
+        .trim()
+        .to_string()
+}
+
+/// Extract the caret offset from DuckDB's `LINE n: ...` / `^` error context
+fn caret_position(message: &str) -> Option<usize> {
+    let lines: Vec<&str> = message.lines().collect();
+    let line_idx = lines.iter().position(|line| line.starts_with("LINE "))?;
+    let prefix_len = lines[line_idx].find(": ")? + 2;
+    let caret_idx = lines.get(line_idx + 1)?.find('^')?;
+    caret_idx.checked_sub(prefix_len)
 }
 
 /// Result type alias for DuckDB operations
-pub type Result<T> = std::result::Result<T, DuckDBError>;
\ No newline at end of file
+pub type Result<T> = std::result::Result<T, DuckDBError>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use duckdb::Connection;
+
+    fn execute(conn: &Connection, sql: &str) -> DuckDBError {
+        conn.execute_batch(sql)
+            .map_err(DuckDBError::from)
+            .unwrap_err()
+    }
+
+    #[test]
+    fn test_catalog_error() {
+        let conn = Connection::open_in_memory().unwrap();
+        let error = execute(&conn, "SELECT * FROM missing_table");
+        assert!(matches!(error, DuckDBError::CatalogError(_)));
+        assert_eq!(error.code(), "42P01");
+    }
+
+    #[test]
+    fn test_parser_error_with_position() {
+        let conn = Connection::open_in_memory().unwrap();
+        let error = execute(&conn, "SELECT 1 FROMM x WHERE");
+        assert!(matches!(error, DuckDBError::ParserError { .. }));
+        assert_eq!(error.code(), "42601");
+        assert!(error.position().is_some());
+    }
+
+    #[test]
+    fn test_binder_error() {
+        let conn = Connection::open_in_memory().unwrap();
+        let error = execute(&conn, "SELECT nope FROM (SELECT 1 AS a)");
+        assert!(matches!(error, DuckDBError::BinderError(_)));
+    }
+
+    #[test]
+    fn test_constraint_violation() {
+        let conn = Connection::open_in_memory().unwrap();
+        conn.execute_batch("CREATE TABLE t (id INTEGER PRIMARY KEY); INSERT INTO t VALUES (1);")
+            .unwrap();
+        let error = execute(&conn, "INSERT INTO t VALUES (1)");
+        assert!(matches!(error, DuckDBError::ConstraintViolation(_)));
+        assert_eq!(error.code(), "23000");
+    }
+
+    #[test]
+    fn test_conversion_error() {
+        let conn = Connection::open_in_memory().unwrap();
+        let error = execute(&conn, "SELECT 'abc'::INTEGER");
+        assert!(matches!(error, DuckDBError::TypeConversion(_)));
+    }
+
+    #[test]
+    fn test_caret_position() {
+        let message =
+            "syntax error at or near \"FROMM\"\n\nLINE 1: SELECT 1 FROMM x\n                 ^";
+        assert_eq!(caret_position(message), Some(9));
+        assert_eq!(caret_position("no context"), None);
+    }
+
+    #[test]
+    fn test_into_noctra_error_keeps_code() {
+        let error: NoctraError = DuckDBError::OutOfMemory("limit".to_string()).into();
+        assert_eq!(error.backend_code(), Some("53200"));
+        assert_eq!(error.hint(), None);
+    }
+
+    #[test]
+    fn test_into_noctra_error_variants() {
+        let error: NoctraError = DuckDBError::FileNotFound("ventas.csv".to_string()).into();
+        assert_eq!(error.code(), "not_found");
+        let error: NoctraError = DuckDBError::AccessDenied("/etc/passwd".to_string()).into();
+        assert_eq!(error.code(), "permission");
+        let error: NoctraError = DuckDBError::ConstraintViolation("dup".to_string()).into();
+        assert_eq!(error.code(), "conflict");
+        let error: NoctraError = DuckDBError::CatalogError("missing".to_string()).into();
+        assert_eq!(error.code(), "backend");
+        assert_eq!(error.backend_code(), Some("42P01"));
+    }
+
+    #[test]
+    fn test_error_code_matches_noctra_error() {
+        let errors = || {
+            vec![
+                DuckDBError::FileNotFound("ventas.csv".to_string()),
+                DuckDBError::AccessDenied("/etc/passwd".to_string()),
+                DuckDBError::ConstraintViolation("dup".to_string()),
+                DuckDBError::Io(std::io::Error::other("disk")),
+                DuckDBError::CatalogError("missing".to_string()),
+            ]
+        };
+        for (error, converted) in errors().into_iter().zip(errors()) {
+            let code = error.error_code();
+            assert!(code.starts_with("NOCTRA_E"));
+            assert_eq!(code, NoctraError::from(converted).error_code());
+        }
+    }
+
+    #[test]
+    fn test_column_typo_hint() {
+        let conn = Connection::open_in_memory().unwrap();
+        let error = execute(&conn, "SELECT nme FROM (SELECT 'x' AS name)");
+        let hint = error.hint().expect("DuckDB suggests candidate columns");
+        assert!(!hint.is_empty());
+        assert!(hint.contains("name"));
+
+        let error: NoctraError = error.into();
+        assert_eq!(error.hint(), Some(hint.as_str()));
+        assert!(!error.to_string().contains(&hint));
+    }
+
+    #[test]
+    fn test_unclassified_failure_keeps_duckdb_code() {
+        let conn = Connection::open_in_memory().unwrap();
+        let error = execute(&conn, "SELECT error('boom')");
+        match error {
+            DuckDBError::Query { message, hint, .. } => {
+                assert!(message.contains("boom"));
+                assert!(hint.is_none());
+            }
+            other => panic!("expected Query error, got {:?}", other),
+        }
+    }
+
+    #[test]
+    fn test_extract_hint() {
+        let message = "Table with name peple does not exist!\nDid you mean \"people\"?\n\nLINE 1: SELECT * FROM peple";
+        assert_eq!(
+            extract_hint(message).as_deref(),
+            Some("Did you mean \"people\"?")
+        );
+        assert_eq!(
+            without_hint(message),
+            "Table with name peple does not exist!\n\nLINE 1: SELECT * FROM peple"
+        );
+        assert_eq!(extract_hint("no suggestion"), None);
+    }
+}
@@ -0,0 +1,313 @@
+//! Excel workbook reader
+//!
+//! Loads a worksheet from an `.xlsx`/`.xls` file (via calamine) into a
+//! Noctra `ResultSet` with inferred column types, so it can be registered
+//! as a DuckDB table with `DuckDBSource::register_result_set`.
+
+use crate::error::{DuckDBError, Result};
+use calamine::{open_workbook_auto, Data, DataType, Reader};
+use noctra_core::types::{Column, ResultSet, Row, Value};
+use std::collections::HashSet;
+use std::path::Path;
+
+/// A worksheet loaded into memory
+#[derive(Debug)]
+pub struct ExcelSheet {
+    /// Name of the sheet that was read
+    pub sheet_name: String,
+    /// Sheet contents with inferred column types
+    pub result: ResultSet,
+    /// Problems found while reading (e.g. merged or blank header cells)
+    pub warnings: Vec<String>,
+}
+
+/// Column type inferred from the cells of a column
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum CellType {
+    Integer,
+    Float,
+    Boolean,
+    Date,
+    DateTime,
+    Text,
+}
+
+impl CellType {
+    fn as_sql(&self) -> &'static str {
+        match self {
+            CellType::Integer => "INTEGER",
+            CellType::Float => "REAL",
+            CellType::Boolean => "BOOLEAN",
+            CellType::Date => "DATE",
+            CellType::DateTime => "TIMESTAMP",
+            CellType::Text => "TEXT",
+        }
+    }
+}
+
+/// Read a worksheet (the first one if `sheet` is `None`)
+///
+/// With `header = true` the first row provides column names; blank header
+/// cells (typically the tail of a merged cell) and duplicates are renamed to
+/// `col_n` and reported in `warnings`.
+pub fn read_excel_sheet(path: &Path, sheet: Option<&str>, header: bool) -> Result<ExcelSheet> {
+    if !path.exists() {
+        return Err(DuckDBError::FileNotFound(path.display().to_string()));
+    }
+
+    let mut workbook = open_workbook_auto(path).map_err(|e| DuckDBError::Excel(e.to_string()))?;
+    let sheet_names = workbook.sheet_names();
+
+    let sheet_name = match sheet {
+        Some(name) => sheet_names
+            .iter()
+            .find(|candidate| candidate.eq_ignore_ascii_case(name))
+            .cloned()
+            .ok_or_else(|| {
+                DuckDBError::Excel(format!(
+                    "Sheet '{}' not found (available: {})",
+                    name,
+                    sheet_names.join(", ")
+                ))
+            })?,
+        None => sheet_names
+            .first()
+            .cloned()
+            .ok_or_else(|| DuckDBError::Excel("Workbook has no sheets".to_string()))?,
+    };
+
+    let range = workbook
+        .worksheet_range(&sheet_name)
+        .map_err(|e| DuckDBError::Excel(e.to_string()))?;
+
+    let width = range.width();
+    let mut rows = range.rows();
+    let mut warnings = Vec::new();
+
+    let names = if header {
+        let header_row = rows.next().unwrap_or(&[]);
+        header_names(header_row, width, &mut warnings)
+    } else {
+        (1..=width).map(|n| format!("col_{}", n)).collect()
+    };
+
+    let data: Vec<&[Data]> = rows.collect();
+    let types: Vec<CellType> = (0..width)
+        .map(|idx| infer_column_type(&data, idx))
+        .collect();
+
+    let columns = names
+        .into_iter()
+        .zip(&types)
+        .enumerate()
+        .map(|(idx, (name, cell_type))| Column::new(name, cell_type.as_sql().to_string(), idx))
+        .collect();
+
+    let mut result = ResultSet::new(columns);
+    for row in data {
+        let values = (0..width)
+            .map(|idx| convert_cell(row.get(idx).unwrap_or(&Data::Empty), types[idx]))
+            .collect();
+        result.add_row(Row::new(values));
+    }
+
+    for warning in &warnings {
+        log::warn!("{} [{}]: {}", path.display(), sheet_name, warning);
+    }
+
+    Ok(ExcelSheet {
+        sheet_name,
+        result,
+        warnings,
+    })
+}
+
+/// Build column names from the header row
+fn header_names(header_row: &[Data], width: usize, warnings: &mut Vec<String>) -> Vec<String> {
+    let mut seen = HashSet::new();
+
+    (0..width)
+        .map(|idx| {
+            let fallback = format!("col_{}", idx + 1);
+            let name = header_row
+                .get(idx)
+                .map(|cell| cell.to_string().trim().to_string())
+                .unwrap_or_default();
+
+            if name.is_empty() {
+                warnings.push(format!(
+                    "Blank or merged header cell in column {}, using '{}'",
+                    idx + 1,
+                    fallback
+                ));
+                seen.insert(fallback.to_lowercase());
+                fallback
+            } else if !seen.insert(name.to_lowercase()) {
+                warnings.push(format!(
+                    "Duplicate header '{}' in column {}, using '{}'",
+                    name,
+                    idx + 1,
+                    fallback
+                ));
+                seen.insert(fallback.to_lowercase());
+                fallback
+            } else {
+                name
+            }
+        })
+        .collect()
+}
+
+/// Infer a column type from all its non-empty cells
+fn infer_column_type(rows: &[&[Data]], idx: usize) -> CellType {
+    let mut inferred: Option<CellType> = None;
+
+    for cell in rows.iter().filter_map(|row| row.get(idx)) {
+        let cell_type = match cell {
+            Data::Empty => continue,
+            Data::Int(_) => CellType::Integer,
+            Data::Float(f) if f.fract() == 0.0 && f.abs() < i64::MAX as f64 => CellType::Integer,
+            Data::Float(_) => CellType::Float,
+            Data::Bool(_) => CellType::Boolean,
+            Data::DateTime(_) | Data::DateTimeIso(_) => match cell.as_datetime() {
+                Some(dt) if dt.time() == chrono::NaiveTime::MIN => CellType::Date,
+                Some(_) => CellType::DateTime,
+                None => CellType::Text,
+            },
+            _ => CellType::Text,
+        };
+
+        inferred = Some(match (inferred, cell_type) {
+            (None, t) => t,
+            (Some(a), b) if a == b => a,
+            (Some(CellType::Integer), CellType::Float)
+            | (Some(CellType::Float), CellType::Integer) => CellType::Float,
+            (Some(CellType::Date), CellType::DateTime)
+            | (Some(CellType::DateTime), CellType::Date) => CellType::DateTime,
+            _ => return CellType::Text,
+        });
+    }
+
+    inferred.unwrap_or(CellType::Text)
+}
+
+/// Convert a cell to a Noctra value of the column type
+fn convert_cell(cell: &Data, cell_type: CellType) -> Value {
+    if matches!(cell, Data::Empty) {
+        return Value::Null;
+    }
+
+    match cell_type {
+        CellType::Integer => cell.as_i64().map(Value::Integer).unwrap_or(Value::Null),
+        CellType::Float => cell.as_f64().map(Value::Float).unwrap_or(Value::Null),
+        CellType::Boolean => cell.get_bool().map(Value::Boolean).unwrap_or(Value::Null),
+        CellType::Date => cell
+            .as_datetime()
+            .map(|dt| Value::Date(dt.date()))
+            .unwrap_or(Value::Null),
+        CellType::DateTime => cell
+            .as_datetime()
+            .map(|dt| Value::Timestamp(dt.and_utc()))
+            .unwrap_or(Value::Null),
+        CellType::Text => Value::Text(cell.to_string()),
+    }
+}
+
+#[cfg(test)]
+pub(crate) mod tests {
+    use super::*;
+    use rust_xlsxwriter::{ExcelDateTime, Format, Workbook};
+
+    /// Two-sheet workbook: "Q3" with typed data, "Notes" with a merged header
+    pub(crate) fn two_sheet_fixture() -> tempfile::NamedTempFile {
+        let file = tempfile::Builder::new().suffix(".xlsx").tempfile().unwrap();
+        let mut workbook = Workbook::new();
+
+        let q3 = workbook.add_worksheet().set_name("Q3").unwrap();
+        q3.write_string(0, 0, "region").unwrap();
+        q3.write_string(0, 1, "units").unwrap();
+        q3.write_string(0, 2, "revenue").unwrap();
+        q3.write_string(0, 3, "closed").unwrap();
+        q3.write_string(0, 4, "day").unwrap();
+        let date_format = Format::new().set_num_format("yyyy-mm-dd");
+        for (row, (region, units, revenue, closed, day)) in [
+            ("North", 10, 1500.5, true, "2024-07-01"),
+            ("South", 7, 980.25, false, "2024-08-15"),
+        ]
+        .into_iter()
+        .enumerate()
+        {
+            let row = row as u32 + 1;
+            q3.write_string(row, 0, region).unwrap();
+            q3.write_number(row, 1, units as f64).unwrap();
+            q3.write_number(row, 2, revenue).unwrap();
+            q3.write_boolean(row, 3, closed).unwrap();
+            let date = ExcelDateTime::parse_from_str(day).unwrap();
+            q3.write_datetime_with_format(row, 4, &date, &date_format)
+                .unwrap();
+        }
+
+        let notes = workbook.add_worksheet().set_name("Notes").unwrap();
+        notes
+            .merge_range(0, 0, 0, 1, "author", &Format::new())
+            .unwrap();
+        notes.write_string(0, 2, "note").unwrap();
+        notes.write_string(1, 0, "Ana").unwrap();
+        notes.write_string(1, 1, "Perez").unwrap();
+        notes.write_string(1, 2, "ok").unwrap();
+
+        workbook.save(file.path()).unwrap();
+        file
+    }
+
+    #[test]
+    fn test_read_first_sheet_with_types() {
+        let file = two_sheet_fixture();
+        let sheet = read_excel_sheet(file.path(), None, true).unwrap();
+
+        assert_eq!(sheet.sheet_name, "Q3");
+        assert!(sheet.warnings.is_empty());
+        let types: Vec<&str> = sheet
+            .result
+            .columns
+            .iter()
+            .map(|c| c.data_type.as_str())
+            .collect();
+        assert_eq!(types, vec!["TEXT", "INTEGER", "REAL", "BOOLEAN", "DATE"]);
+        assert_eq!(sheet.result.rows.len(), 2);
+        assert_eq!(sheet.result.rows[0].values[1], Value::Integer(10));
+        assert_eq!(
+            sheet.result.rows[1].values[4],
+            Value::Date(chrono::NaiveDate::from_ymd_opt(2024, 8, 15).unwrap())
+        );
+    }
+
+    #[test]
+    fn test_merged_header_generates_names() {
+        let file = two_sheet_fixture();
+        let sheet = read_excel_sheet(file.path(), Some("notes"), true).unwrap();
+
+        let names: Vec<&str> = sheet
+            .result
+            .columns
+            .iter()
+            .map(|c| c.name.as_str())
+            .collect();
+        assert_eq!(names, vec!["author", "col_2", "note"]);
+        assert_eq!(sheet.warnings.len(), 1);
+    }
+
+    #[test]
+    fn test_no_header_and_missing_sheet() {
+        let file = two_sheet_fixture();
+
+        let sheet = read_excel_sheet(file.path(), Some("Notes"), false).unwrap();
+        assert_eq!(sheet.result.columns[0].name, "col_1");
+        assert_eq!(sheet.result.rows.len(), 2);
+
+        assert!(matches!(
+            read_excel_sheet(file.path(), Some("Q4"), true),
+            Err(DuckDBError::Excel(_))
+        ));
+    }
+}
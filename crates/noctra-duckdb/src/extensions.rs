@@ -1,11 +1,31 @@
 //! DuckDB extensions support
 //!
 //! This module handles loading and managing DuckDB extensions
-//! for additional file format support.
+//! for additional file format support, plus the opt-in `spatial`
+//! and `fts` extensions requested through `DuckDBConfig::extensions`.
 
-use crate::error::Result;
+use crate::error::{DuckDBError, Result};
 use duckdb::Connection;
 
+/// Optional DuckDB extension loaded when a source is created
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum DuckDBExtension {
+    /// Geometry types and `ST_*` functions
+    Spatial,
+    /// Full-text search (`PRAGMA create_fts_index`, `match_bm25`)
+    Fts,
+}
+
+impl DuckDBExtension {
+    /// Name of the extension as known to DuckDB
+    pub fn name(&self) -> &'static str {
+        match self {
+            DuckDBExtension::Spatial => "spatial",
+            DuckDBExtension::Fts => "fts",
+        }
+    }
+}
+
 /// DuckDB extensions manager
 pub struct ExtensionsManager {
     conn: Connection,
@@ -13,6 +33,27 @@ pub struct ExtensionsManager {
 }
 
 impl ExtensionsManager {
+    /// Install (if needed) and load the `spatial` extension
+    pub fn load_spatial(conn: &Connection) -> Result<()> {
+        install_and_load(conn, DuckDBExtension::Spatial.name())
+    }
+
+    /// Install (if needed) and load the `fts` extension
+    pub fn load_fts(conn: &Connection) -> Result<()> {
+        install_and_load(conn, DuckDBExtension::Fts.name())
+    }
+
+    /// Load every requested extension, stopping at the first failure
+    pub fn load_all(conn: &Connection, extensions: &[DuckDBExtension]) -> Result<()> {
+        for extension in extensions {
+            match extension {
+                DuckDBExtension::Spatial => Self::load_spatial(conn)?,
+                DuckDBExtension::Fts => Self::load_fts(conn)?,
+            }
+        }
+        Ok(())
+    }
+
     /// Create a new extensions manager
     pub fn new(conn: Connection) -> Self {
         Self {
@@ -67,4 +108,55 @@ impl ExtensionsManager {
 
         Ok(())
     }
-}
\ No newline at end of file
+}
+
+/// Run `INSTALL` and `LOAD` for an extension
+///
+/// Any failure (not in the repository, no network to download it, ...)
+/// is reported as `DuckDBError::ExtensionUnavailable`.
+fn install_and_load(conn: &Connection, name: &str) -> Result<()> {
+    log::info!("Loading DuckDB extension: {}", name);
+    conn.execute_batch(&format!("INSTALL {}; LOAD {};", name, name))
+        .map_err(|e| DuckDBError::ExtensionUnavailable {
+            name: name.to_string(),
+            reason: e.to_string(),
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    use crate::source::{DuckDBConfig, DuckDBSource};
+    use noctra_core::datasource::DataSource;
+    use noctra_core::types::Parameters;
+
+    /// Offline, loading fails with `ExtensionUnavailable` (see
+    /// `test_unknown_extension_is_unavailable`)
+    #[test]
+    #[ignore = "downloads the spatial extension from the DuckDB repository"]
+    fn test_load_spatial() {
+        let config = DuckDBConfig {
+            extensions: vec![DuckDBExtension::Spatial],
+            ..DuckDBConfig::default()
+        };
+        let source = DuckDBSource::new_with_config(config).unwrap();
+        let result = source
+            .query("SELECT ST_AsText(ST_Point(0, 0))", &Parameters::new())
+            .unwrap();
+        assert_eq!(
+            result.rows[0].values[0],
+            noctra_core::types::Value::Text("POINT (0 0)".to_string())
+        );
+    }
+
+    #[test]
+    fn test_unknown_extension_is_unavailable() {
+        let conn = Connection::open_in_memory().unwrap();
+        let err = install_and_load(&conn, "noctra_no_such_extension").unwrap_err();
+        assert!(matches!(
+            err,
+            DuckDBError::ExtensionUnavailable { ref name, .. } if name == "noctra_no_such_extension"
+        ));
+    }
+}
@@ -0,0 +1,451 @@
+//! Source factories for `USE '<uri>' AS alias`
+//!
+//! `DuckDBFileFactory` opens plain file paths (and `file://` URIs) the way
+//! `USE` always has; `CsvFactory` reads `csv://` URIs as CSV whatever their
+//! extension; `BundleFactory` loads bundles written by
+//! `Executor::export_bundle`. `register_factories` adds them to a
+//! `SourceRegistry`; every source they open gets the factory's
+//! `DuckDBConfig`, so `allowed_paths` also applies to `USE`.
+
+use std::collections::HashMap;
+use std::path::Path;
+use std::sync::Arc;
+
+use noctra_core::bundle::MANIFEST_FILE;
+use noctra_core::error::{NoctraError, Result};
+use noctra_core::types::Parameters;
+use noctra_core::{
+    BundleManifest, CreatedSource, CsvOptions, DataSource, SourceFactory, SourceRegistry, Value,
+};
+
+use crate::source::{
+    is_supported_file, split_compression, Compression, DuckDBConfig, DuckDBSource, FileFormat,
+};
+
+/// Add the DuckDB factories to `registry`, opening sources with `config`
+pub fn register_factories(registry: &mut SourceRegistry, config: &DuckDBConfig) {
+    registry.register_factory(Arc::new(CsvFactory::new(config.clone())));
+    registry.register_factory(Arc::new(DuckDBFileFactory::new(config.clone())));
+    // Bundles are directories too: checked before `DuckDBFileFactory`
+    registry.register_factory(Arc::new(BundleFactory::new(config.clone())));
+}
+
+/// Path of `uri` once `scheme://` is removed
+fn strip_scheme<'a>(uri: &'a str, scheme: &str) -> &'a str {
+    uri.get(..scheme.len() + 3)
+        .filter(|prefix| prefix.eq_ignore_ascii_case(&format!("{}://", scheme)))
+        .map_or(uri, |prefix| &uri[prefix.len()..])
+}
+
+/// Files, globs and Parquet directories registered in a new in-memory
+/// DuckDB source (see `DuckDBSource::register_file_with_options`)
+#[derive(Debug, Default, Clone)]
+pub struct DuckDBFileFactory {
+    config: DuckDBConfig,
+}
+
+impl DuckDBFileFactory {
+    /// Factory whose sources use `config`
+    pub fn new(config: DuckDBConfig) -> Self {
+        Self { config }
+    }
+}
+
+impl SourceFactory for DuckDBFileFactory {
+    fn schemes(&self) -> &[&str] {
+        &["file"]
+    }
+
+    fn handles_path(&self, path: &str) -> bool {
+        is_supported_file(path)
+    }
+
+    fn create(
+        &self,
+        uri: &str,
+        alias: &str,
+        options: &HashMap<String, String>,
+    ) -> Result<CreatedSource> {
+        let path = strip_scheme(uri, "file");
+        let mut source =
+            DuckDBSource::new_with_config(self.config.clone()).map_err(NoctraError::from)?;
+        let warnings = source
+            .register_file_with_options(path, alias, options)
+            .map_err(NoctraError::from)?;
+        Ok(CreatedSource {
+            source: Box::new(source),
+            warnings,
+        })
+    }
+}
+
+/// `csv://path` read as CSV regardless of its extension (`.txt`, `.log`...);
+/// a `.gz`/`.zst` suffix still selects the compression, and `OPTIONS (...)`
+/// takes the `CsvOptions` keys
+#[derive(Debug, Default, Clone)]
+pub struct CsvFactory {
+    config: DuckDBConfig,
+}
+
+impl CsvFactory {
+    /// Factory whose sources use `config`
+    pub fn new(config: DuckDBConfig) -> Self {
+        Self { config }
+    }
+}
+
+impl SourceFactory for CsvFactory {
+    fn schemes(&self) -> &[&str] {
+        &["csv"]
+    }
+
+    fn create(
+        &self,
+        uri: &str,
+        alias: &str,
+        options: &HashMap<String, String>,
+    ) -> Result<CreatedSource> {
+        let path = strip_scheme(uri, "csv");
+        let (_, compression) = split_compression(&path.to_lowercase());
+        let csv = CsvOptions::from_use_options(options)?;
+        let mut source =
+            DuckDBSource::new_with_config(self.config.clone()).map_err(NoctraError::from)?;
+        source
+            .register_view(path, alias, FileFormat::Csv, compression, false, Some(&csv))
+            .map_err(NoctraError::from)?;
+        Ok(CreatedSource::new(Box::new(source)))
+    }
+}
+
+/// `bundle://dir`, or a directory with a `manifest.json`: every table of a
+/// bundle written by `Executor::export_bundle`, as a view over its CSV file
+///
+/// Column types come from the bundle's `schema.json`; bundles written
+/// before format 2 (BLOBs as `0x…`) and tables whose types DuckDB rejects
+/// fall back to inferring them from the CSV, with a warning. A table whose
+/// row count differs from the manifest is loaded anyway, with a warning.
+#[derive(Debug, Default, Clone)]
+pub struct BundleFactory {
+    config: DuckDBConfig,
+}
+
+impl BundleFactory {
+    /// Factory whose sources use `config`
+    pub fn new(config: DuckDBConfig) -> Self {
+        Self { config }
+    }
+}
+
+impl SourceFactory for BundleFactory {
+    fn schemes(&self) -> &[&str] {
+        &["bundle"]
+    }
+
+    fn handles_path(&self, path: &str) -> bool {
+        Path::new(path).join(MANIFEST_FILE).is_file()
+    }
+
+    fn create(
+        &self,
+        uri: &str,
+        _alias: &str,
+        _options: &HashMap<String, String>,
+    ) -> Result<CreatedSource> {
+        let dir = Path::new(strip_scheme(uri, "bundle"));
+        noctra_core::fs_guard::check_path(dir.join(MANIFEST_FILE), &self.config.allowed_paths)?;
+        let manifest = BundleManifest::read(dir)?;
+        let schema = if manifest.format_version >= 2 {
+            Some(BundleManifest::read_schema(dir)?)
+        } else {
+            None
+        };
+        let mut source =
+            DuckDBSource::new_with_config(self.config.clone()).map_err(NoctraError::from)?;
+        let mut warnings = Vec::new();
+        for table in &manifest.tables {
+            let path = dir.join(&table.file);
+            let path = path.to_string_lossy();
+            let columns = schema
+                .iter()
+                .flat_map(|schema| &schema.tables)
+                .find(|schema_table| schema_table.name == table.name)
+                .map(|schema_table| {
+                    schema_table
+                        .columns
+                        .iter()
+                        .map(|column| (column.name.clone(), bundle_column_type(&column.data_type)))
+                        .collect()
+                })
+                .unwrap_or_default();
+            let typed = CsvOptions {
+                columns,
+                ..CsvOptions::default()
+            };
+            let register = |source: &mut DuckDBSource, csv: &CsvOptions| {
+                source.register_view(
+                    &path,
+                    &table.name,
+                    FileFormat::Csv,
+                    Compression::None,
+                    false,
+                    Some(csv),
+                )
+            };
+            if let Err(e) = register(&mut source, &typed) {
+                if typed.columns.is_empty() {
+                    return Err(e.into());
+                }
+                warnings.push(format!(
+                    "Table '{}' keeps the types inferred from its CSV: {}",
+                    table.name, e
+                ));
+                register(&mut source, &CsvOptions::default()).map_err(NoctraError::from)?;
+            }
+
+            let count = source.query(
+                &format!(
+                    "SELECT COUNT(*) FROM \"{}\"",
+                    table.name.replace('"', "\"\"")
+                ),
+                &Parameters::new(),
+            )?;
+            if let Some(Value::Integer(rows)) =
+                count.rows.first().and_then(|row| row.values.first())
+            {
+                if *rows as u64 != table.rows {
+                    warnings.push(format!(
+                        "Table '{}' has {} rows, the bundle manifest lists {}",
+                        table.name, rows, table.rows
+                    ));
+                }
+            }
+        }
+        Ok(CreatedSource {
+            source: Box::new(source),
+            warnings,
+        })
+    }
+}
+
+/// DuckDB type for a `schema.json` column (untyped SQLite expressions are text)
+fn bundle_column_type(data_type: &str) -> String {
+    if data_type.trim().is_empty() {
+        "VARCHAR".to_string()
+    } else {
+        data_type.to_string()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use noctra_core::types::Parameters;
+    use noctra_core::Value;
+
+    #[test]
+    fn test_strip_scheme() {
+        assert_eq!(strip_scheme("csv://data/log.txt", "csv"), "data/log.txt");
+        assert_eq!(strip_scheme("FILE:///tmp/a.csv", "file"), "/tmp/a.csv");
+        assert_eq!(strip_scheme("a.csv", "file"), "a.csv");
+    }
+
+    #[test]
+    fn test_factories_open_paths_and_csv_uris() {
+        let dir = tempfile::tempdir().unwrap();
+        let log = dir.path().join("ventas.txt");
+        std::fs::write(&log, "id,total\n1,10\n2,20\n").unwrap();
+        let csv = dir.path().join("clientes.csv");
+        std::fs::write(&csv, "id,nombre\n1,Ana\n").unwrap();
+
+        let mut registry = SourceRegistry::new();
+        register_factories(&mut registry, &DuckDBConfig::default());
+
+        let uri = format!("csv://{}", log.display());
+        let created = registry
+            .create_source(&uri, "ventas", &HashMap::new())
+            .unwrap();
+        let result = created
+            .source
+            .query(
+                "SELECT COUNT(*) FROM ventas WHERE total > 5",
+                &Parameters::new(),
+            )
+            .unwrap();
+        assert_eq!(result.rows[0].values[0], Value::Integer(2));
+
+        for uri in [
+            csv.display().to_string(),
+            format!("file://{}", csv.display()),
+        ] {
+            let created = registry
+                .create_source(&uri, "clientes", &HashMap::new())
+                .unwrap();
+            let result = created
+                .source
+                .query("SELECT nombre FROM clientes", &Parameters::new())
+                .unwrap();
+            assert_eq!(result.rows[0].values[0], Value::Text("Ana".to_string()));
+        }
+
+        assert!(registry
+            .create_source(&log.display().to_string(), "ventas", &HashMap::new())
+            .is_err());
+    }
+
+    #[test]
+    fn test_factories_apply_allowed_paths() {
+        let root = tempfile::tempdir().unwrap();
+        let outside = tempfile::tempdir().unwrap();
+        let inside = root.path().join("dentro.csv");
+        std::fs::write(&inside, "id\n1\n").unwrap();
+        let secret = outside.path().join("fuera.csv");
+        std::fs::write(&secret, "id\n2\n").unwrap();
+
+        let mut registry = SourceRegistry::new();
+        register_factories(
+            &mut registry,
+            &DuckDBConfig {
+                allowed_paths: vec![root.path().to_path_buf()],
+                ..DuckDBConfig::default()
+            },
+        );
+
+        for uri in [
+            inside.display().to_string(),
+            format!("csv://{}", inside.display()),
+        ] {
+            assert!(
+                registry.create_source(&uri, "t", &HashMap::new()).is_ok(),
+                "{}",
+                uri
+            );
+        }
+        for uri in [
+            secret.display().to_string(),
+            format!("csv://{}", secret.display()),
+        ] {
+            let error = registry
+                .create_source(&uri, "t", &HashMap::new())
+                .unwrap_err();
+            assert!(
+                matches!(error, NoctraError::Permission { .. }),
+                "{}: {:?}",
+                uri,
+                error
+            );
+        }
+    }
+
+    #[test]
+    fn test_export_then_import_bundle_keeps_schema_and_rows() {
+        let dir = tempfile::tempdir().unwrap();
+        let clientes = dir.path().join("clientes.csv");
+        std::fs::write(
+            &clientes,
+            "id,nombre,saldo\n1,Ana,10.5\n2,\"Pérez, Luis\",\n3,\"Dice \"\"hola\"\"\",7.25\n",
+        )
+        .unwrap();
+        let pedidos = dir.path().join("pedidos.csv");
+        let rows: String = (0..50)
+            .map(|i| format!("{},{},{}\n", i, i % 3 + 1, i * 10))
+            .collect();
+        std::fs::write(&pedidos, format!("id,cliente_id,total\n{}", rows)).unwrap();
+
+        let mut source = DuckDBSource::new_in_memory().unwrap();
+        source
+            .register_file(&clientes.display().to_string(), "clientes", false)
+            .unwrap();
+        source
+            .register_file(&pedidos.display().to_string(), "pedidos", false)
+            .unwrap();
+        // A typed table beyond the default row limit: values that a CSV
+        // sniffer would read differently (zero-padded codes, BLOBs)
+        source
+            .materialize(
+                "productos",
+                "SELECT i::INTEGER AS id, lpad(i::VARCHAR, 5, '0') AS codigo, \
+                 (i / 100)::DECIMAL(10,2) AS precio, DATE '2024-01-01' + i::INTEGER AS dia, \
+                 CASE WHEN i % 3 = 0 THEN NULL ELSE '\\x00\\xFF'::BLOB END AS datos \
+                 FROM range(12000) t(i)",
+            )
+            .unwrap();
+        let mut executor = noctra_core::Executor::new_sqlite_memory().unwrap();
+        register_factories(executor.source_registry_mut(), &DuckDBConfig::default());
+        executor
+            .register_source("tienda".to_string(), Box::new(source))
+            .unwrap();
+
+        let bundle = dir.path().join("bundle");
+        let manifest = executor.export_bundle("tienda", &bundle).unwrap();
+        assert_eq!(manifest.source, "tienda");
+        let mut tables: Vec<_> = manifest
+            .tables
+            .iter()
+            .map(|t| (t.name.as_str(), t.rows))
+            .collect();
+        tables.sort();
+        assert_eq!(
+            tables,
+            vec![("clientes", 3), ("pedidos", 50), ("productos", 12000)]
+        );
+
+        let warnings = executor.import_bundle(&bundle, "copia").unwrap();
+        assert!(warnings.is_empty(), "{:?}", warnings);
+
+        let registry = executor.source_registry();
+        let schema = |alias: &str| {
+            let mut tables: Vec<_> = registry
+                .get(alias)
+                .unwrap()
+                .schema()
+                .unwrap()
+                .into_iter()
+                .map(|table| {
+                    let columns: Vec<_> = table
+                        .columns
+                        .into_iter()
+                        .map(|c| (c.name, c.data_type))
+                        .collect();
+                    (table.name, columns)
+                })
+                .collect();
+            tables.sort();
+            tables
+        };
+        assert_eq!(schema("tienda"), schema("copia"));
+
+        let copia = registry.get("copia").unwrap();
+        for table in &manifest.tables {
+            let count = copia
+                .query(
+                    &format!("SELECT COUNT(*) FROM \"{}\"", table.name),
+                    &Parameters::new(),
+                )
+                .unwrap();
+            assert_eq!(count.rows[0].values[0], Value::Integer(table.rows as i64));
+        }
+        let quoted = copia
+            .query(
+                "SELECT nombre, saldo FROM clientes WHERE id = 2",
+                &Parameters::new(),
+            )
+            .unwrap();
+        assert_eq!(
+            quoted.rows[0].values[0],
+            Value::Text("Pérez, Luis".to_string())
+        );
+        assert_eq!(quoted.rows[0].values[1], Value::Null);
+        let typed = copia
+            .query(
+                "SELECT codigo, datos FROM productos WHERE id IN (7, 9) ORDER BY id",
+                &Parameters::new(),
+            )
+            .unwrap();
+        assert_eq!(typed.rows[0].values[0], Value::Text("00007".to_string()));
+        assert_eq!(typed.rows[0].values[1], Value::Blob(vec![0, 0xff]));
+        assert_eq!(typed.rows[1].values[1], Value::Null);
+
+        // A directory with a manifest is also a bundle for `USE 'dir' AS x`
+        assert!(BundleFactory::default().handles_path(&bundle.display().to_string()));
+    }
+}
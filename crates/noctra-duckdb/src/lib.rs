@@ -5,14 +5,15 @@
 //!
 //! ## Example Usage
 //!
-//! ```rust
+//! ```rust,no_run
+//! use noctra_core::DataSource;
 //! use noctra_duckdb::DuckDBSource;
 //!
 //! // Create in-memory DuckDB source
 //! let mut source = DuckDBSource::new_in_memory()?;
 //!
 //! // Register a CSV file as a virtual table
-//! source.register_file("data.csv", "my_table")?;
+//! source.register_file("data.csv", "my_table", false)?;
 //!
 //! // Query the data
 //! let result = source.query("SELECT * FROM my_table WHERE age > 25", &noctra_core::types::Parameters::new())?;
@@ -22,11 +23,24 @@
 //! # Ok::<(), Box<dyn std::error::Error>>(())
 //! ```
 
-pub mod source;
+#[cfg(feature = "arrow-integration")]
+pub mod arrow_bridge;
 pub mod engine;
-pub mod extensions;
 pub mod error;
+pub mod excel;
+pub mod extensions;
+pub mod factory;
+pub mod source;
 
-pub use source::DuckDBSource;
-pub use engine::DuckDBEngine;
-pub use error::{DuckDBError, Result};
\ No newline at end of file
+#[cfg(feature = "arrow-integration")]
+pub use arrow_bridge::result_set_from_record_batches;
+pub use engine::{DuckDBEngine, StatementCacheStats};
+pub use error::{DuckDBError, Result};
+pub use extensions::{DuckDBExtension, ExtensionsManager};
+pub use factory::{register_factories, CsvFactory, DuckDBFileFactory};
+pub use source::session_schema;
+pub use source::{
+    detect_file_format, is_supported_file, ColumnStatistics, ColumnTypeChange, Compression,
+    CsvWriteOptions, DuckDBConfig, DuckDBSource, FileFormat, FileLimits, ParquetWriteOptions,
+    QueryOptions, SchemaDiff, TableStatistics, WriteFormat, DEFAULT_MAX_RESULT_ROWS,
+};
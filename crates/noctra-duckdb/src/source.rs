@@ -4,334 +4,4697 @@
 //! enabling file-native queries for CSV, JSON, and Parquet files.
 
 use crate::error::{DuckDBError, Result};
+use crate::extensions::{DuckDBExtension, ExtensionsManager};
+use chrono::{DateTime, NaiveDate, NaiveTime, Utc};
+use duckdb::types::{TimeUnit, ValueRef};
 use duckdb::{params, Connection, Result as DuckResult, Row};
-use noctra_core::datasource::{ColumnInfo, DataSource, SourceType, TableInfo};
-use noctra_core::types::{Column, Parameters, ResultSet, Row as NoctraRow, Value};
+use noctra_core::datasource::{
+    ColumnInfo, CsvOptions, DataSource, SourceType, TableInfo, WriteResult,
+};
+use noctra_core::executor::{Backend, BackendInfo, PlanNode, QueryPlan, SqliteBackend};
+use noctra_core::session::Session;
+use noctra_core::types::{Column, Parameters, ResultSet, Row as NoctraRow, RowStream, Value};
+use std::borrow::Cow;
 use std::collections::HashMap;
-use std::path::Path;
-use std::sync::Mutex;
+use std::fs::File;
+use std::io::{Read, Seek, SeekFrom};
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+use std::sync::{mpsc, Arc, Mutex, RwLock};
+use std::time::{Duration, Instant, SystemTime};
+
+/// Default maximum on-disk size of a registered file (100MB)
+pub const DEFAULT_MAX_FILE_SIZE: u64 = 100 * 1024 * 1024;
+
+/// Default maximum estimated decompressed size of a compressed file (1GB)
+pub const DEFAULT_MAX_DECOMPRESSED_SIZE: u64 = 1024 * 1024 * 1024;
+
+/// Catalog name under which a linked SQLite database is attached
+pub const SQLITE_CATALOG: &str = "sqlite_db";
+
+/// Default maximum number of rows returned by `query()`
+pub const DEFAULT_MAX_RESULT_ROWS: usize = 10_000;
+
+/// Default number of write statements between automatic checkpoints
+pub const DEFAULT_AUTO_CHECKPOINT_INTERVAL: usize = 1_000;
+
+/// Column type for exact numbers (`NUMERIC`/`DECIMAL` and CSV amounts)
+#[cfg(feature = "decimal")]
+const DECIMAL_TYPE: &str = "DECIMAL(38,10)";
+
+/// Column type for `NUMERIC`/`DECIMAL` columns without the `decimal` feature
+#[cfg(not(feature = "decimal"))]
+const DECIMAL_TYPE: &str = "DOUBLE";
+
+/// Expansion ratio assumed when the decompressed size can't be read from the file
+const ASSUMED_COMPRESSION_RATIO: u64 = 10;
+
+/// File format of a registered file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum FileFormat {
+    Csv,
+    Json,
+    /// Newline-delimited JSON (`.ndjson`, `.jsonl`)
+    NdJson,
+    Parquet,
+    /// Excel workbook (`.xlsx`, `.xls`), loaded into a table on registration
+    Excel,
+}
+
+/// Compression codec of a registered file
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Compression {
+    None,
+    Gzip,
+    Zstd,
+}
+
+impl Compression {
+    /// Value for DuckDB's `compression=` reader option
+    pub fn as_duckdb_str(&self) -> Option<&'static str> {
+        match self {
+            Compression::None => None,
+            Compression::Gzip => Some("gzip"),
+            Compression::Zstd => Some("zstd"),
+        }
+    }
+}
+
+/// Connection settings for a DuckDBSource
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct DuckDBConfig {
+    /// Maximum number of idle read handles kept for concurrent queries
+    pub pool_size: usize,
+    /// Number of prepared statements cached by `DuckDBEngine` (0 disables the cache)
+    pub statement_cache_size: usize,
+    /// Maximum number of rows collected by `query()`; extra rows are dropped
+    /// and the result is marked as truncated
+    pub max_result_rows: usize,
+    /// Write statements after which the WAL is checkpointed into the
+    /// database file (0 disables automatic checkpoints)
+    pub auto_checkpoint_interval: usize,
+    /// Optional extensions loaded by `DuckDBSource::new_with_config`
+    pub extensions: Vec<DuckDBExtension>,
+    /// Directories files may be registered from; when non-empty, paths are
+    /// canonicalized (resolving `..` and symlinks) and must fall under one
+    /// of them. Empty keeps the system-directory blocklist
+    pub allowed_paths: Vec<PathBuf>,
+}
+
+impl Default for DuckDBConfig {
+    fn default() -> Self {
+        Self {
+            pool_size: 4,
+            statement_cache_size: 32,
+            max_result_rows: DEFAULT_MAX_RESULT_ROWS,
+            auto_checkpoint_interval: DEFAULT_AUTO_CHECKPOINT_INTERVAL,
+            extensions: Vec::new(),
+            allowed_paths: Vec::new(),
+        }
+    }
+}
+
+/// Per-call options for `DuckDBSource::query_with_options`
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq)]
+pub struct QueryOptions {
+    /// Collect every row, ignoring `DuckDBConfig::max_result_rows`
+    pub unlimited: bool,
+    /// Interrupt the query once it runs longer than this
+    pub timeout: Option<Duration>,
+}
+
+/// Parquet codecs accepted by `COPY ... (COMPRESSION ...)`
+const PARQUET_COMPRESSIONS: [&str; 6] = ["uncompressed", "snappy", "gzip", "zstd", "lz4", "brotli"];
+
+/// Options for writing Parquet with `DuckDBSource::write_table`
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct ParquetWriteOptions {
+    /// Codec (`snappy`, `zstd`...); DuckDB's default (snappy) when `None`
+    pub compression: Option<String>,
+    /// Rows per row group; DuckDB's default when `None`
+    pub row_group_size: Option<u64>,
+}
+
+impl ParquetWriteOptions {
+    /// Read `compression` and `row_group_size` from `OPTIONS (...)`
+    pub fn from_options(options: &HashMap<String, String>) -> Result<Self> {
+        let mut parsed = Self::default();
+        for (key, value) in options {
+            let value = value.trim();
+            match key.to_lowercase().as_str() {
+                "compression" => {
+                    let codec = value.to_lowercase();
+                    if !PARQUET_COMPRESSIONS.contains(&codec.as_str()) {
+                        return Err(DuckDBError::InvalidOption(format!(
+                            "Unknown Parquet compression '{}' (use {})",
+                            value,
+                            PARQUET_COMPRESSIONS.join(", ")
+                        )));
+                    }
+                    parsed.compression = Some(codec);
+                }
+                "row_group_size" => {
+                    let size = value.parse::<u64>().ok().filter(|size| *size > 0);
+                    parsed.row_group_size = Some(size.ok_or_else(|| {
+                        DuckDBError::InvalidOption(format!("Invalid row_group_size '{}'", value))
+                    })?);
+                }
+                other => {
+                    return Err(DuckDBError::InvalidOption(format!(
+                        "Unknown Parquet option '{}' (use compression, row_group_size)",
+                        other
+                    )))
+                }
+            }
+        }
+        Ok(parsed)
+    }
+}
+
+/// Options for writing CSV with `DuckDBSource::write_table`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct CsvWriteOptions {
+    /// Field delimiter
+    pub delimiter: char,
+    /// Write the column names as the first line
+    pub header: bool,
+}
+
+impl Default for CsvWriteOptions {
+    fn default() -> Self {
+        Self {
+            delimiter: ',',
+            header: true,
+        }
+    }
+}
+
+impl CsvWriteOptions {
+    /// Read `delimiter` and `header` from `OPTIONS (...)`
+    pub fn from_options(options: &HashMap<String, String>) -> Result<Self> {
+        let mut parsed = Self::default();
+        for (key, value) in options {
+            let value = value.trim();
+            match key.to_lowercase().as_str() {
+                "delimiter" => {
+                    let mut chars = value.chars();
+                    parsed.delimiter = match (chars.next(), chars.next()) {
+                        (Some(c), None) => c,
+                        _ => {
+                            return Err(DuckDBError::InvalidOption(format!(
+                                "CSV delimiter must be one character, got '{}'",
+                                value
+                            )))
+                        }
+                    };
+                }
+                "header" => {
+                    parsed.header = !matches!(value.to_lowercase().as_str(), "false" | "no" | "0");
+                }
+                other => {
+                    return Err(DuckDBError::InvalidOption(format!(
+                        "Unknown CSV option '{}' (use delimiter, header)",
+                        other
+                    )))
+                }
+            }
+        }
+        Ok(parsed)
+    }
+}
+
+/// Output format of `DuckDBSource::write_table`
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum WriteFormat {
+    Parquet(ParquetWriteOptions),
+    Csv(CsvWriteOptions),
+}
+
+impl WriteFormat {
+    /// Options of DuckDB's `COPY ... TO ... (...)`
+    fn copy_options(&self) -> String {
+        match self {
+            WriteFormat::Parquet(options) => {
+                let mut parts = vec!["FORMAT PARQUET".to_string()];
+                if let Some(codec) = &options.compression {
+                    parts.push(format!("COMPRESSION {}", codec));
+                }
+                if let Some(size) = options.row_group_size {
+                    parts.push(format!("ROW_GROUP_SIZE {}", size));
+                }
+                parts.join(", ")
+            }
+            WriteFormat::Csv(options) => format!(
+                "FORMAT CSV, HEADER {}, DELIMITER '{}'",
+                options.header,
+                options.delimiter.to_string().replace('\'', "''")
+            ),
+        }
+    }
+}
+
+/// Size limits applied when registering files
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct FileLimits {
+    /// Maximum on-disk (compressed) size in bytes
+    pub max_file_size: u64,
+    /// Maximum estimated decompressed size in bytes (compressed files only)
+    pub max_decompressed_size: u64,
+}
+
+impl Default for FileLimits {
+    fn default() -> Self {
+        Self {
+            max_file_size: DEFAULT_MAX_FILE_SIZE,
+            max_decompressed_size: DEFAULT_MAX_DECOMPRESSED_SIZE,
+        }
+    }
+}
+
+/// Detect file format and compression from the file name
+///
+/// Recognizes `.csv`, `.json`, `.ndjson`/`.jsonl` and `.parquet`, optionally
+/// followed by `.gz` or `.zst` (Parquet handles compression internally), and
+/// uncompressed `.xlsx`/`.xls` workbooks.
+pub fn detect_file_format(file_path: &str) -> Result<(FileFormat, Compression)> {
+    let lower = file_path.to_lowercase();
+    let (stem, compression) = split_compression(&lower);
+
+    let extension = Path::new(stem)
+        .extension()
+        .and_then(|e| e.to_str())
+        .unwrap_or("");
+
+    let format = match extension {
+        "csv" => FileFormat::Csv,
+        "json" => FileFormat::Json,
+        "ndjson" | "jsonl" => FileFormat::NdJson,
+        "parquet" if compression == Compression::None => FileFormat::Parquet,
+        "xlsx" | "xls" if compression == Compression::None => FileFormat::Excel,
+        _ => {
+            let name = Path::new(&lower)
+                .file_name()
+                .and_then(|n| n.to_str())
+                .unwrap_or(&lower);
+            let unsupported = name.find('.').map(|idx| &name[idx + 1..]).unwrap_or(name);
+            return Err(DuckDBError::UnsupportedFileType(unsupported.to_string()));
+        }
+    };
+
+    Ok((format, compression))
+}
+
+/// Split a lowercase file name into its name without the `.gz`/`.zst`
+/// suffix and the compression that suffix implies
+pub(crate) fn split_compression(lower: &str) -> (&str, Compression) {
+    if let Some(stem) = lower.strip_suffix(".gz") {
+        (stem, Compression::Gzip)
+    } else if let Some(stem) = lower
+        .strip_suffix(".zst")
+        .or_else(|| lower.strip_suffix(".zstd"))
+    {
+        (stem, Compression::Zstd)
+    } else {
+        (lower, Compression::None)
+    }
+}
+
+/// Convert a DuckDB failure into a coded `NoctraError`
+fn backend_error(error: duckdb::Error) -> noctra_core::error::NoctraError {
+    DuckDBError::from(error).into()
+}
+
+/// Run `f` on `conn`, interrupting it if it outlives `timeout`
+///
+/// A watchdog thread waits for `f` to finish and calls DuckDB's interrupt
+/// handle when the deadline passes; the resulting error is reported as
+/// `NoctraError::Timeout`.
+fn with_timeout<T>(
+    conn: &Connection,
+    timeout: Option<Duration>,
+    f: impl FnOnce(&Connection) -> noctra_core::error::Result<T>,
+) -> noctra_core::error::Result<T> {
+    let Some(timeout) = timeout else {
+        return f(conn);
+    };
+
+    let started = Instant::now();
+    let handle = conn.interrupt_handle();
+    let fired = Arc::new(AtomicBool::new(false));
+    let (done, finished) = mpsc::channel::<()>();
+    let watchdog = {
+        let fired = Arc::clone(&fired);
+        std::thread::spawn(move || {
+            if let Err(mpsc::RecvTimeoutError::Timeout) = finished.recv_timeout(timeout) {
+                fired.store(true, Ordering::SeqCst);
+                handle.interrupt();
+            }
+        })
+    };
+
+    let result = f(conn);
+    let _ = done.send(());
+    let _ = watchdog.join();
+
+    match result {
+        Err(_) if fired.load(Ordering::SeqCst) => Err(noctra_core::error::NoctraError::Timeout {
+            elapsed: started.elapsed(),
+        }),
+        other => other,
+    }
+}
+
+/// Values for a prepared statement's placeholders, bound by name or position
+/// (see `Parameters::lookup`)
+///
+/// Placeholders missing from `parameters` are bound as NULL.
+fn bound_values(
+    stmt: &duckdb::Statement<'_>,
+    parameters: &Parameters,
+) -> noctra_core::error::Result<Vec<duckdb::types::Value>> {
+    let mut values = Vec::with_capacity(stmt.parameter_count());
+    for idx in 1..=stmt.parameter_count() {
+        let name = stmt.parameter_name(idx).map_err(backend_error)?;
+        values.push(
+            parameters
+                .lookup(&name)
+                .map_or(duckdb::types::Value::Null, to_duckdb_value),
+        );
+    }
+    Ok(values)
+}
+
+/// Rewrite `$1::INTEGER` casts of positional parameters as `CAST($1 AS INTEGER)`
+///
+/// The cast is what makes `id = $1::INTEGER` compare a text value such as
+/// `"42"` with an integer column. Quoted literals and identifiers are left
+/// alone; SQL without casted parameters is returned as is.
+fn cast_placeholders(sql: &str) -> Cow<'_, str> {
+    if !sql.contains("::") {
+        return Cow::Borrowed(sql);
+    }
+    let bytes = sql.as_bytes();
+    let mut out = String::with_capacity(sql.len() + 16);
+    let mut quote = None;
+    let mut last = 0;
+    let mut i = 0;
+    while i < bytes.len() {
+        let b = bytes[i];
+        match quote {
+            Some(q) if b == q => quote = None,
+            Some(_) => {}
+            None if b == b'\'' || b == b'"' => quote = Some(b),
+            None if b == b'$' => {
+                let digits = bytes[i + 1..]
+                    .iter()
+                    .take_while(|c| c.is_ascii_digit())
+                    .count();
+                let cast_at = i + 1 + digits;
+                if digits > 0 && sql[cast_at..].starts_with("::") {
+                    let type_len = cast_type_len(&sql[cast_at + 2..]);
+                    if type_len > 0 {
+                        let end = cast_at + 2 + type_len;
+                        out.push_str(&sql[last..i]);
+                        out.push_str(&format!(
+                            "CAST({} AS {})",
+                            &sql[i..cast_at],
+                            &sql[cast_at + 2..end]
+                        ));
+                        last = end;
+                        i = end;
+                        continue;
+                    }
+                }
+            }
+            None => {}
+        }
+        i += 1;
+    }
+    if last == 0 {
+        return Cow::Borrowed(sql);
+    }
+    out.push_str(&sql[last..]);
+    Cow::Owned(out)
+}
+
+/// Length of the type name at the start of `s` (`INTEGER`, `DECIMAL(10, 2)`, `INT[]`)
+fn cast_type_len(s: &str) -> usize {
+    let name = s
+        .char_indices()
+        .take_while(|(i, c)| c.is_ascii_alphabetic() || *c == '_' || (*i > 0 && c.is_ascii_digit()))
+        .count();
+    if name == 0 {
+        return 0;
+    }
+    let mut len = name;
+    if s[len..].starts_with('(') {
+        if let Some(close) = s[len..].find(')') {
+            len += close + 1;
+        }
+    }
+    if s[len..].starts_with("[]") {
+        len += 2;
+    }
+    len
+}
+
+/// Convert a Noctra value into a DuckDB value for the appender
+fn to_duckdb_value(value: &Value) -> duckdb::types::Value {
+    use duckdb::types::Value as DuckValue;
+    match value {
+        Value::Null => DuckValue::Null,
+        Value::Integer(i) => DuckValue::BigInt(*i),
+        Value::Float(f) => DuckValue::Double(*f),
+        #[cfg(feature = "decimal")]
+        Value::Decimal(d) => DuckValue::Decimal((*d).into()),
+        Value::Boolean(b) => DuckValue::Boolean(*b),
+        Value::Text(s) => DuckValue::Text(s.clone()),
+        Value::Date(d) => {
+            DuckValue::Date32((*d - DateTime::UNIX_EPOCH.date_naive()).num_days() as i32)
+        }
+        Value::Time(t) => DuckValue::Time64(
+            TimeUnit::Microsecond,
+            (*t - NaiveTime::MIN).num_microseconds().unwrap_or_default(),
+        ),
+        Value::Timestamp(ts) => DuckValue::Timestamp(TimeUnit::Microsecond, ts.timestamp_micros()),
+        Value::Blob(bytes) => DuckValue::Blob(bytes.clone()),
+        other => DuckValue::Text(other.to_string()),
+    }
+}
+
+/// Map a Noctra column type to a DuckDB column type
+///
+/// Unknown types are inferred from the first non-null value in the column.
+fn duckdb_column_type(data_type: &str, result: &ResultSet, column_idx: usize) -> &'static str {
+    let upper = data_type.to_uppercase();
+    let base = upper.split('(').next().unwrap_or("").trim();
+
+    match base {
+        "INTEGER" | "INT" | "BIGINT" | "SMALLINT" | "TINYINT" | "HUGEINT" => "BIGINT",
+        "REAL" | "FLOAT" | "DOUBLE" => "DOUBLE",
+        "NUMERIC" | "DECIMAL" => DECIMAL_TYPE,
+        "BOOLEAN" | "BOOL" => "BOOLEAN",
+        "DATE" => "DATE",
+        "TIME" => "TIME",
+        "DATETIME" | "TIMESTAMP" => "TIMESTAMP",
+        "BLOB" | "BYTEA" => "BLOB",
+        "TEXT" | "VARCHAR" | "CHAR" | "STRING" => "VARCHAR",
+        _ => {
+            let sample = result
+                .rows
+                .iter()
+                .filter_map(|row| row.values.get(column_idx))
+                .find(|value| !value.is_null());
+            match sample {
+                Some(Value::Integer(_)) => "BIGINT",
+                Some(Value::Float(_)) => "DOUBLE",
+                #[cfg(feature = "decimal")]
+                Some(Value::Decimal(_)) => DECIMAL_TYPE,
+                Some(Value::Boolean(_)) => "BOOLEAN",
+                Some(Value::Date(_)) => "DATE",
+                Some(Value::Time(_)) => "TIME",
+                Some(Value::Timestamp(_)) => "TIMESTAMP",
+                Some(Value::Blob(_)) => "BLOB",
+                _ => "VARCHAR",
+            }
+        }
+    }
+}
+
+/// Whether a statement only reads data and can run on a shared read handle
+///
+/// `WITH` and `EXPLAIN` may wrap a write (`WITH a AS (...) DELETE ...`,
+/// `EXPLAIN ANALYZE INSERT ...`), so those are classified with sqlparser.
+pub(crate) fn is_read_only(sql: &str) -> bool {
+    let keyword = sql
+        .trim_start()
+        .split(|c: char| c.is_whitespace() || c == '(')
+        .next()
+        .unwrap_or("")
+        .to_uppercase();
+
+    match keyword.as_str() {
+        "SELECT" | "FROM" | "VALUES" | "TABLE" | "SHOW" | "DESCRIBE" | "SUMMARIZE" => true,
+        "WITH" | "EXPLAIN" => parses_as_read(sql),
+        _ => false,
+    }
+}
+
+/// Whether sqlparser sees only queries in `sql`, including nested statements
+///
+/// SQL that doesn't parse counts as a write: it then takes the exclusive
+/// slot, which is always safe.
+fn parses_as_read(sql: &str) -> bool {
+    use sqlparser::ast::{visit_statements, Statement};
+    use std::ops::ControlFlow;
+
+    let Ok(statements) =
+        sqlparser::parser::Parser::parse_sql(&sqlparser::dialect::DuckDbDialect {}, sql)
+    else {
+        return false;
+    };
+
+    let write = visit_statements(&statements, |statement| match statement {
+        Statement::Query(_) | Statement::Explain { .. } => ControlFlow::Continue(()),
+        _ => ControlFlow::Break(()),
+    });
+    write.is_continue()
+}
+
+/// Whether `name` is a plain SQL identifier (letter or `_`, then alphanumerics or `_`)
+fn is_identifier(name: &str) -> bool {
+    let mut chars = name.chars();
+    chars.next().is_some_and(|c| c.is_alphabetic() || c == '_')
+        && chars.all(|c| c.is_alphanumeric() || c == '_')
+}
+
+/// Build the `CREATE OR REPLACE VIEW` statement exposing a file as `view`
+fn file_view_sql(
+    file_path: &str,
+    view: &str,
+    format: FileFormat,
+    compression: Compression,
+    hive_partitioning: bool,
+    csv: Option<&CsvOptions>,
+) -> String {
+    format!(
+        "CREATE OR REPLACE VIEW {} AS SELECT * FROM {}",
+        view,
+        file_reader_sql(file_path, format, compression, hive_partitioning, csv)
+    )
+}
+
+/// Table function call that reads a file or glob (`read_csv_auto(...)`, etc.)
+///
+/// With `hive_partitioning`, `key=value` directories in the path become columns.
+/// `csv` pins the dialect of CSV files instead of leaving it to the sniffer.
+fn file_reader_sql(
+    file_path: &str,
+    format: FileFormat,
+    compression: Compression,
+    hive_partitioning: bool,
+    csv: Option<&CsvOptions>,
+) -> String {
+    let mut options = compression
+        .as_duckdb_str()
+        .map(|codec| format!(", compression='{}'", codec))
+        .unwrap_or_default();
+    if hive_partitioning {
+        options.push_str(", hive_partitioning=true");
+    }
+    if let (FileFormat::Csv, Some(csv)) = (format, csv) {
+        options.push_str(&csv_reader_options(csv));
+    }
+    // Read amounts such as 19.99 as DECIMAL instead of DOUBLE
+    #[cfg(feature = "decimal")]
+    if format == FileFormat::Csv && csv.is_none_or(|csv| csv.columns.is_empty()) {
+        options.push_str(&format!(
+            ", auto_type_candidates=['BOOLEAN', 'BIGINT', '{}', 'TIME', 'DATE', 'TIMESTAMP', 'VARCHAR']",
+            DECIMAL_TYPE
+        ));
+    }
+
+    match format {
+        FileFormat::Csv => format!("read_csv_auto('{}'{})", file_path, options),
+        FileFormat::Json => format!("read_json_auto('{}'{})", file_path, options),
+        FileFormat::NdJson => format!(
+            "read_json_auto('{}', format='newline_delimited'{})",
+            file_path, options
+        ),
+        FileFormat::Parquet => format!("read_parquet('{}'{})", file_path, options),
+        FileFormat::Excel => unreachable!("Excel files are loaded by register_excel"),
+    }
+}
+
+/// `read_csv_auto` options for `csv`
+///
+/// RFC 4180 dialect: the escape character is the quote character itself, so
+/// `"a,""b"",c"` is one field, and quoted fields may span lines. The header
+/// is only forced off, unless `columns` pins the names and types; otherwise
+/// with `has_header` DuckDB still sniffs it.
+fn csv_reader_options(csv: &CsvOptions) -> String {
+    let literal = |c: char| c.to_string().replace('\'', "''");
+    let mut options = format!(", quote='{0}', escape='{0}'", literal(csv.quote));
+    if let Some(delimiter) = csv.delimiter {
+        options.push_str(&format!(", delim='{}'", literal(delimiter)));
+    }
+    if !csv.has_header {
+        options.push_str(", header=false");
+    } else if !csv.columns.is_empty() {
+        options.push_str(", header=true");
+    }
+    if !csv.columns.is_empty() {
+        let quote = |text: &str| format!("'{}'", text.replace('\'', "''"));
+        let columns: Vec<String> = csv
+            .columns
+            .iter()
+            .map(|(name, data_type)| format!("{}: {}", quote(name), quote(data_type)))
+            .collect();
+        options.push_str(&format!(", columns={{{}}}", columns.join(", ")));
+    }
+    if csv.skip_rows > 0 {
+        options.push_str(&format!(", skip={}", csv.skip_rows));
+    }
+    if let Some(encoding) = &csv.encoding {
+        options.push_str(&format!(", encoding='{}'", encoding.replace('\'', "''")));
+    }
+    options
+}
+
+/// First row estimate in an EXPLAIN rendering (`~123 Rows` or `EC: 123`)
+fn explain_row_estimate(text: &str) -> Option<usize> {
+    let digits = |s: &str| -> Option<usize> {
+        let number: String = s
+            .trim_start()
+            .chars()
+            .take_while(|c| c.is_ascii_digit() || *c == ',')
+            .collect();
+        number.replace(',', "").parse().ok()
+    };
+
+    text.lines().find_map(|line| {
+        if let Some((_, rest)) = line.split_once("EC:") {
+            return digits(rest);
+        }
+        let (_, rest) = line.split_once('~')?;
+        rest.to_lowercase()
+            .contains("row")
+            .then(|| digits(rest))
+            .flatten()
+    })
+}
+
+/// Operator tree of an `EXPLAIN (ANALYZE, FORMAT JSON)` profile
+///
+/// The top level describes the query (unnamed, or `Query` in older
+/// versions); its operators hang from `children`, below the
+/// `EXPLAIN_ANALYZE` node DuckDB adds on top. Older versions also name the
+/// fields `name`, `cardinality` and `timing` and only report the estimate
+/// inside the `extra_info` text.
+fn parse_analyze_json(text: &str) -> noctra_core::error::Result<PlanNode> {
+    let profile: serde_json::Value = serde_json::from_str(text).map_err(|e| {
+        noctra_core::error::NoctraError::Serialization(format!(
+            "invalid EXPLAIN ANALYZE output: {}",
+            e
+        ))
+    })?;
+
+    let mut node = &profile;
+    while is_analyze_wrapper(node) {
+        match node
+            .get("children")
+            .and_then(|c| c.as_array())
+            .map(Vec::as_slice)
+        {
+            Some([child]) => node = child,
+            _ => break,
+        }
+    }
+
+    if is_analyze_wrapper(node) {
+        return Err(noctra_core::error::NoctraError::Serialization(
+            "EXPLAIN ANALYZE output has no operators".to_string(),
+        ));
+    }
+    Ok(analyze_plan_node(node))
+}
+
+/// Operator name of a profile node; `None` for the query-level node
+fn analyze_operator(node: &serde_json::Value) -> Option<&str> {
+    node.get("operator_name")
+        .or_else(|| node.get("operator_type"))
+        .or_else(|| node.get("name"))
+        .and_then(|name| name.as_str())
+        .map(str::trim)
+        .filter(|name| !name.is_empty())
+}
+
+/// Whether a profile node is the query itself or the `EXPLAIN_ANALYZE` operator
+fn is_analyze_wrapper(node: &serde_json::Value) -> bool {
+    analyze_operator(node)
+        .is_none_or(|name| name == "EXPLAIN_ANALYZE" || name.eq_ignore_ascii_case("query"))
+}
+
+fn analyze_plan_node(node: &serde_json::Value) -> PlanNode {
+    let number = |keys: &[&str]| {
+        keys.iter()
+            .find_map(|key| node.get(*key)?.as_f64())
+            .unwrap_or(0.0)
+    };
+
+    let estimated_rows = match node.get("extra_info") {
+        Some(serde_json::Value::Object(info)) => info.get("Estimated Cardinality").and_then(|v| {
+            v.as_u64()
+                .or_else(|| v.as_str().and_then(|s| s.trim().parse().ok()))
+        }),
+        Some(serde_json::Value::String(info)) => explain_row_estimate(info).map(|rows| rows as u64),
+        _ => None,
+    };
+
+    PlanNode {
+        operator: analyze_operator(node).unwrap_or_default().to_string(),
+        estimated_rows: estimated_rows.unwrap_or(0),
+        actual_rows: number(&["operator_cardinality", "cardinality"]) as u64,
+        actual_time_ms: number(&["operator_timing", "timing"]) * 1000.0,
+        children: node
+            .get("children")
+            .and_then(|c| c.as_array())
+            .map(|children| children.iter().map(analyze_plan_node).collect())
+            .unwrap_or_default(),
+    }
+}
+
+/// Tables scanned in an EXPLAIN rendering (`Table: name`)
+fn explain_tables(text: &str) -> Vec<String> {
+    text.lines()
+        .filter_map(|line| line.split_once("Table:"))
+        .filter_map(|(_, rest)| {
+            rest.split(|c: char| c.is_whitespace() || c == '│')
+                .find(|word| !word.is_empty())
+                .map(String::from)
+        })
+        .collect()
+}
+
+/// Schema holding the tables registered by a session
+///
+/// Session ids are UUIDs; anything that isn't alphanumeric becomes `_` so the
+/// name needs no quoting.
+pub fn session_schema(session_id: &str) -> String {
+    let id: String = session_id
+        .chars()
+        .map(|c| if c.is_ascii_alphanumeric() { c } else { '_' })
+        .collect();
+    format!("session_{}", id)
+}
+
+/// Double-quoted SQL identifier (embedded quotes are doubled)
+fn quote_ident(name: &str) -> String {
+    format!("\"{}\"", name.replace('"', "\"\""))
+}
+
+/// Whether `register_file_with_options` accepts this path
+///
+/// Supported files, globs of them and directories (of Parquet files) are accepted.
+pub fn is_supported_file(file_path: &str) -> bool {
+    Path::new(file_path).is_dir() || detect_file_format(file_path).is_ok()
+}
+
+/// Estimate the decompressed size of a compressed file
+///
+/// Uses the gzip ISIZE trailer or the zstd frame content size when present,
+/// otherwise falls back to a fixed expansion ratio.
+fn estimate_decompressed_size(path: &Path, compression: Compression, compressed_size: u64) -> u64 {
+    let declared = match compression {
+        Compression::None => return compressed_size,
+        Compression::Gzip => read_gzip_isize(path),
+        Compression::Zstd => read_zstd_content_size(path),
+    };
+
+    declared
+        .filter(|size| *size > 0)
+        .unwrap_or_else(|| compressed_size.saturating_mul(ASSUMED_COMPRESSION_RATIO))
+}
+
+/// Read the uncompressed size (mod 2^32) from the gzip trailer
+fn read_gzip_isize(path: &Path) -> Option<u64> {
+    let mut file = File::open(path).ok()?;
+    file.seek(SeekFrom::End(-4)).ok()?;
+    let mut trailer = [0u8; 4];
+    file.read_exact(&mut trailer).ok()?;
+    Some(u32::from_le_bytes(trailer) as u64)
+}
+
+/// Read Frame_Content_Size from the first zstd frame header, if declared
+fn read_zstd_content_size(path: &Path) -> Option<u64> {
+    let mut file = File::open(path).ok()?;
+    let mut header = [0u8; 18];
+    let read = file.read(&mut header).ok()?;
+    if read < 6 || header[..4] != [0x28, 0xB5, 0x2F, 0xFD] {
+        return None;
+    }
+
+    let descriptor = header[4];
+    let fcs_flag = descriptor >> 6;
+    let single_segment = (descriptor >> 5) & 1 == 1;
+    let dict_id_size = [0usize, 1, 2, 4][(descriptor & 0b11) as usize];
+    let fcs_size = match (fcs_flag, single_segment) {
+        (0, false) => return None,
+        (0, true) => 1,
+        (1, _) => 2,
+        (2, _) => 4,
+        _ => 8,
+    };
+
+    let start = 5 + usize::from(!single_segment) + dict_id_size;
+    let field = header.get(start..start + fcs_size)?;
+    let mut bytes = [0u8; 8];
+    bytes[..fcs_size].copy_from_slice(field);
+    let size = u64::from_le_bytes(bytes);
+
+    Some(if fcs_size == 2 { size + 256 } else { size })
+}
+
+/// Cached statistics for a single column
+#[derive(Debug, Clone, PartialEq)]
+pub struct ColumnStatistics {
+    /// Column name
+    pub name: String,
+    /// Number of NULL values
+    pub null_count: usize,
+    /// Approximate number of distinct values
+    pub distinct_count: usize,
+}
+
+/// Cached statistics for a registered table
+#[derive(Debug, Clone, PartialEq)]
+pub struct TableStatistics {
+    /// Number of rows at collection time
+    pub row_count: usize,
+    /// Per-column statistics
+    pub columns: Vec<ColumnStatistics>,
+    /// When the statistics were collected
+    pub collected_at: SystemTime,
+}
+
+/// A column whose type changed between two versions of a table
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct ColumnTypeChange {
+    /// Column name
+    pub name: String,
+    /// Type in the old schema
+    pub old_type: String,
+    /// Type in the new schema
+    pub new_type: String,
+}
+
+/// Column-level differences between two versions of a table's schema
+///
+/// Columns are matched by name (case-insensitively), so a renamed column
+/// shows up as one removed and one added column.
+#[derive(Debug, Clone, Default)]
+pub struct SchemaDiff {
+    /// Columns only present in the new schema
+    pub added: Vec<ColumnInfo>,
+    /// Columns only present in the old schema
+    pub removed: Vec<ColumnInfo>,
+    /// Columns present in both with a different type
+    pub retyped: Vec<ColumnTypeChange>,
+}
+
+impl SchemaDiff {
+    /// Compare two column lists
+    pub fn between(old: &[ColumnInfo], new: &[ColumnInfo]) -> Self {
+        let find = |columns: &[ColumnInfo], name: &str| {
+            columns
+                .iter()
+                .find(|column| column.name.eq_ignore_ascii_case(name))
+                .cloned()
+        };
+
+        let mut diff = Self::default();
+        for column in old {
+            match find(new, &column.name) {
+                None => diff.removed.push(column.clone()),
+                Some(current) if current.data_type != column.data_type => {
+                    diff.retyped.push(ColumnTypeChange {
+                        name: column.name.clone(),
+                        old_type: column.data_type.clone(),
+                        new_type: current.data_type,
+                    })
+                }
+                Some(_) => {}
+            }
+        }
+        diff.added = new
+            .iter()
+            .filter(|column| find(old, &column.name).is_none())
+            .cloned()
+            .collect();
+        diff
+    }
+
+    /// Whether both schemas have the same columns and types
+    pub fn is_empty(&self) -> bool {
+        self.added.is_empty() && self.removed.is_empty() && self.retyped.is_empty()
+    }
+}
+
+/// Session variables as text, set with `SET VARIABLE` (sorted by name)
+type SessionVariables = Vec<(String, Option<String>)>;
+
+/// Settings applied to a connection while it runs one of a session's queries
+#[derive(Debug, Clone, Default)]
+struct SessionScope {
+    /// Session schema first, then `main` (only if the session registered tables)
+    search_path: Option<String>,
+    /// Values read by `noctra_var()` and `noctra_user()`
+    variables: SessionVariables,
+}
+
+impl SessionScope {
+    /// Apply the settings to `conn`
+    fn apply(&self, conn: &Connection) -> noctra_core::error::Result<()> {
+        let mut sql = String::new();
+        if let Some(path) = &self.search_path {
+            sql.push_str(&format!("SET search_path = '{}';", path));
+        }
+        for (name, value) in &self.variables {
+            let value = match value {
+                Some(value) => format!("'{}'", value.replace('\'', "''")),
+                None => "NULL".to_string(),
+            };
+            sql.push_str(&format!(
+                "SET VARIABLE \"{}\" = {};",
+                name.replace('"', "\"\""),
+                value
+            ));
+        }
+        if sql.is_empty() {
+            return Ok(());
+        }
+        conn.execute_batch(&sql).map_err(backend_error)
+    }
+
+    /// Undo `apply` so the connection can be reused by another session
+    fn reset(&self, conn: &Connection) -> noctra_core::error::Result<()> {
+        let mut sql = String::new();
+        if self.search_path.is_some() {
+            sql.push_str("RESET search_path;");
+        }
+        for (name, _) in &self.variables {
+            sql.push_str(&format!(
+                "RESET VARIABLE \"{}\";",
+                name.replace('"', "\"\"")
+            ));
+        }
+        if sql.is_empty() {
+            return Ok(());
+        }
+        conn.execute_batch(&sql).map_err(backend_error)
+    }
+}
+
+/// Create the `noctra_*` SQL functions on a connection
+///
+/// They are temporary macros, so nothing is written to the database; each
+/// connection (including cloned read handles) needs its own.
+fn install_session_functions(conn: &Connection) -> Result<()> {
+    let os_user = std::env::var("USER")
+        .or_else(|_| std::env::var("USERNAME"))
+        .unwrap_or_default()
+        .replace('\'', "''");
+    conn.execute_batch(&format!(
+        "CREATE OR REPLACE TEMP MACRO noctra_var(var_name) AS getvariable(var_name);
+         CREATE OR REPLACE TEMP MACRO noctra_now() AS current_timestamp;
+         CREATE OR REPLACE TEMP MACRO noctra_user() AS
+             COALESCE(getvariable('user'), NULLIF('{os_user}', ''));",
+        os_user = os_user,
+    ))?;
+    Ok(())
+}
 
 /// DuckDB-powered data source for file-native queries
+///
+/// Read-only queries run on cloned connection handles so they can proceed
+/// concurrently; writes and DDL run on the primary connection with exclusive
+/// access.
 #[derive(Debug)]
 pub struct DuckDBSource {
-    /// DuckDB connection (wrapped in Mutex for thread safety)
+    /// Primary DuckDB connection (wrapped in Mutex for thread safety)
     conn: Mutex<Connection>,
+    /// Idle read handles cloned from the primary connection
+    read_pool: Mutex<Vec<Connection>>,
+    /// Shared for reads, exclusive for writes
+    access: RwLock<()>,
+    /// Connection settings
+    config: DuckDBConfig,
     /// Name/alias of this source
     name: String,
-    /// Registered file tables (alias -> file_path)
+    /// Registered tables (alias -> file_path, or source query if materialized)
     registered_files: HashMap<String, String>,
+    /// Columns of each file-backed view when it was registered (DuckDB
+    /// re-binds the view to the file's current columns on every lookup)
+    registered_schemas: HashMap<String, Vec<ColumnInfo>>,
+    /// Size limits applied by register_file
+    file_limits: FileLimits,
+    /// Cached table statistics (table -> stats), invalidated on writes
+    statistics: RwLock<HashMap<String, TableStatistics>>,
+    /// Tables of the linked SQLite database, qualified as `sqlite_db.<table>`
+    linked_tables: Vec<String>,
+    /// Session-scoped registrations (session id -> table -> file path)
+    session_files: RwLock<HashMap<String, HashMap<String, String>>>,
+    /// Whether the database was opened read-only
+    read_only: bool,
+    /// Write statements since the last checkpoint
+    writes_since_checkpoint: AtomicUsize,
+    /// Variables of each session for `noctra_var()` (session id -> variables)
+    session_variables: RwLock<HashMap<String, SessionVariables>>,
 }
 
-impl DuckDBSource {
-    /// Create a new DuckDB source with in-memory database
-    pub fn new_in_memory() -> Result<Self> {
-        let conn = Connection::open_in_memory()?;
-        Ok(Self {
-            conn: Mutex::new(conn),
-            name: "duckdb".to_string(),
-            registered_files: HashMap::new(),
-        })
+impl DuckDBSource {
+    /// Create a new DuckDB source with in-memory database
+    pub fn new_in_memory() -> Result<Self> {
+        Self::from_connection(Connection::open_in_memory()?, false)
+    }
+
+    /// Create a new DuckDB source with persistent database file
+    pub fn new_with_file<P: AsRef<Path>>(path: P) -> Result<Self> {
+        Self::from_connection(Connection::open(path)?, false)
+    }
+
+    /// Create an in-memory source with the given settings
+    ///
+    /// Loads the extensions listed in `config.extensions`.
+    pub fn new_with_config(config: DuckDBConfig) -> Result<Self> {
+        let source = Self::new_in_memory()?.with_config(config);
+        source.load_extensions()?;
+        Ok(source)
+    }
+
+    /// Open an existing database file without write access
+    pub fn new_read_only<P: AsRef<Path>>(path: P) -> Result<Self> {
+        let config = duckdb::Config::default().access_mode(duckdb::AccessMode::ReadOnly)?;
+        Self::from_connection(Connection::open_with_flags(path, config)?, true)
+    }
+
+    /// Read-only databases get no `noctra_*` functions
+    fn from_connection(conn: Connection, read_only: bool) -> Result<Self> {
+        if !read_only {
+            install_session_functions(&conn)?;
+        }
+        Ok(Self {
+            conn: Mutex::new(conn),
+            read_pool: Mutex::new(Vec::new()),
+            access: RwLock::new(()),
+            config: DuckDBConfig::default(),
+            name: "duckdb".to_string(),
+            registered_files: HashMap::new(),
+            registered_schemas: HashMap::new(),
+            file_limits: FileLimits::default(),
+            statistics: RwLock::new(HashMap::new()),
+            linked_tables: Vec::new(),
+            session_files: RwLock::new(HashMap::new()),
+            read_only,
+            writes_since_checkpoint: AtomicUsize::new(0),
+            session_variables: RwLock::new(HashMap::new()),
+        })
+    }
+
+    /// Whether the database was opened read-only
+    pub fn is_read_only_database(&self) -> bool {
+        self.read_only
+    }
+
+    /// Apply connection settings
+    pub fn with_config(mut self, config: DuckDBConfig) -> Self {
+        self.config = config;
+        self
+    }
+
+    /// Get the connection settings
+    pub fn config(&self) -> &DuckDBConfig {
+        &self.config
+    }
+
+    /// Load the extensions listed in the config
+    ///
+    /// Extensions are loaded database-wide, so pooled read handles see them too.
+    pub fn load_extensions(&self) -> Result<()> {
+        let conn = self
+            .conn
+            .lock()
+            .map_err(|_| DuckDBError::QueryFailed("Mutex poisoned".to_string()))?;
+        ExtensionsManager::load_all(&conn, &self.config.extensions)
+    }
+
+    /// Get a new handle to the underlying database
+    ///
+    /// The handle shares the database (and registered views) with this source
+    /// but can be used independently from other threads.
+    pub fn connection(&self) -> Result<Connection> {
+        let conn = self
+            .conn
+            .lock()
+            .map_err(|_| DuckDBError::QueryFailed("Mutex poisoned".to_string()))?;
+        let handle = conn.try_clone()?;
+        if !self.read_only {
+            install_session_functions(&handle)?;
+        }
+        Ok(handle)
+    }
+
+    /// Take an idle read handle from the pool, or clone a new one
+    fn checkout_reader(&self) -> Result<Connection> {
+        let pooled = self
+            .read_pool
+            .lock()
+            .map_err(|_| DuckDBError::QueryFailed("Mutex poisoned".to_string()))?
+            .pop();
+        match pooled {
+            Some(conn) => Ok(conn),
+            None => self.connection(),
+        }
+    }
+
+    /// Return a read handle to the pool (dropped if the pool is full)
+    fn checkin_reader(&self, conn: Connection) {
+        if let Ok(mut pool) = self.read_pool.lock() {
+            if pool.len() < self.config.pool_size {
+                pool.push(conn);
+            }
+        }
+    }
+
+    /// Set the size limits applied when registering files
+    pub fn set_file_limits(&mut self, limits: FileLimits) {
+        self.file_limits = limits;
+    }
+
+    /// Get the size limits applied when registering files
+    pub fn file_limits(&self) -> FileLimits {
+        self.file_limits
+    }
+
+    /// Register a file as a virtual table using DuckDB's read_*_auto functions
+    ///
+    /// Gzip/zstd-compressed CSV and (ND)JSON files are read transparently.
+    /// Registering over an existing table requires `replace`; the returned
+    /// diff then tells how the table's columns changed (e.g. because the
+    /// file was edited since it was first registered).
+    pub fn register_file(
+        &mut self,
+        file_path: &str,
+        alias: &str,
+        replace: bool,
+    ) -> Result<Option<SchemaDiff>> {
+        let previous = if self.registered_files.contains_key(alias) {
+            if !replace {
+                return Err(DuckDBError::CatalogError(format!(
+                    "Table '{}' is already registered",
+                    alias
+                )));
+            }
+            Some(self.registered_schema(alias)?)
+        } else {
+            None
+        };
+
+        self.register_file_with_options(file_path, alias, &HashMap::new())?;
+
+        match previous {
+            Some(previous) => Ok(Some(SchemaDiff::between(
+                &previous,
+                &self.get_table_schema(alias)?,
+            ))),
+            None => Ok(None),
+        }
+    }
+
+    /// Compare a file-backed table with the file's current contents
+    ///
+    /// Compares the columns inferred when the table was registered with the
+    /// ones inferred from the file on disk now and reports what changed.
+    pub fn detect_drift(&self, table: &str) -> Result<SchemaDiff> {
+        let file_path = self.registered_files.get(table).ok_or_else(|| {
+            DuckDBError::CatalogError(format!("Table '{}' is not registered", table))
+        })?;
+        if file_path.starts_with('(') {
+            return Err(DuckDBError::UnsupportedFileType(format!(
+                "Table '{}' is not backed by a file",
+                table
+            )));
+        }
+        let (format, compression) = detect_file_format(file_path)?;
+        if format == FileFormat::Excel {
+            return Err(DuckDBError::UnsupportedFileType(format!(
+                "Drift detection is not supported for Excel sheets: {}",
+                file_path
+            )));
+        }
+
+        let registered = self.registered_schema(table)?;
+        let sql = format!(
+            "DESCRIBE SELECT * FROM {}",
+            file_reader_sql(file_path, format, compression, false, None)
+        );
+        let current = {
+            let conn = self
+                .conn
+                .lock()
+                .map_err(|_| DuckDBError::QueryFailed("Mutex poisoned".to_string()))?;
+            let mut stmt = conn.prepare(&sql)?;
+            let rows = stmt.query_map([], |row| {
+                let name: String = row.get(0)?;
+                let data_type: String = row.get(1)?;
+                let null: Option<String> = row.get(2)?;
+                Ok(ColumnInfo {
+                    name,
+                    data_type: data_type.to_uppercase(),
+                    nullable: null.as_deref() != Some("NO"),
+                    default_value: None,
+                    primary_key: false,
+                })
+            })?;
+            rows.collect::<DuckResult<Vec<_>>>()?
+        };
+
+        Ok(SchemaDiff::between(&registered, &current))
+    }
+
+    /// Columns of a table as registered, falling back to the catalog for
+    /// tables not created by `register_view`
+    fn registered_schema(&self, table: &str) -> Result<Vec<ColumnInfo>> {
+        match self.registered_schemas.get(table) {
+            Some(columns) => Ok(columns.clone()),
+            None => self.get_table_schema(table),
+        }
+    }
+
+    /// Register a file, honoring `USE ... OPTIONS (...)` settings
+    ///
+    /// Excel files accept `sheet='name'` and `header=true|false`; CSV files
+    /// accept the `CsvOptions` keys (`quote`, `delimiter`, `header`, `skip`,
+    /// `encoding`);
+    /// other formats accept `hive_partitioning=true`. Globs and directories are
+    /// registered with `register_glob`/`register_directory`. Returns warnings
+    /// produced while loading (e.g. generated column names).
+    pub fn register_file_with_options(
+        &mut self,
+        file_path: &str,
+        alias: &str,
+        options: &HashMap<String, String>,
+    ) -> Result<Vec<String>> {
+        let option = |key: &str| {
+            options
+                .get(key)
+                .map(|v| v.trim().trim_matches('\'').trim_matches('"').to_string())
+        };
+        let flag = |key: &str, default: bool| match option(key).as_deref() {
+            None => default,
+            Some(v) => !matches!(v.to_lowercase().as_str(), "false" | "no" | "0"),
+        };
+        let hive_partitioning = flag("hive_partitioning", false);
+
+        if Path::new(file_path).is_dir() {
+            self.register_directory(file_path, alias, hive_partitioning)?;
+            return Ok(Vec::new());
+        }
+
+        let (format, compression) = detect_file_format(file_path)?;
+
+        if format == FileFormat::Excel {
+            let sheet = option("sheet");
+            return self.register_excel(file_path, alias, sheet.as_deref(), flag("header", true));
+        }
+
+        let csv = CsvOptions::from_use_options(options).map_err(|e| match e {
+            noctra_core::error::NoctraError::Validation(message) => {
+                DuckDBError::InvalidOption(message)
+            }
+            other => DuckDBError::InvalidOption(other.to_string()),
+        })?;
+        self.register_view(
+            file_path,
+            alias,
+            format,
+            compression,
+            hive_partitioning,
+            Some(&csv),
+        )?;
+        Ok(Vec::new())
+    }
+
+    /// Register every file matching a glob (e.g. `sales/*/*.parquet`) as one table
+    ///
+    /// With `hive_partitioning`, `key=value` directories in the matched paths
+    /// (`sales/year=2024/month=01/...`) become columns with inferred types,
+    /// and filters on them skip whole files.
+    pub fn register_glob(
+        &mut self,
+        pattern: &str,
+        alias: &str,
+        hive_partitioning: bool,
+    ) -> Result<()> {
+        let (format, compression) = detect_file_format(pattern)?;
+        if format == FileFormat::Excel {
+            return Err(DuckDBError::UnsupportedFileType(format!(
+                "Excel files can't be registered by glob: {}",
+                pattern
+            )));
+        }
+        self.register_view(pattern, alias, format, compression, hive_partitioning, None)
+    }
+
+    /// Register all Parquet files under a directory (recursively) as one table
+    ///
+    /// See `register_glob` for `hive_partitioning`.
+    pub fn register_directory(
+        &mut self,
+        dir: &str,
+        alias: &str,
+        hive_partitioning: bool,
+    ) -> Result<()> {
+        if !Path::new(dir).is_dir() {
+            return Err(DuckDBError::UnsupportedFileType(format!(
+                "{} is not a directory",
+                dir
+            )));
+        }
+        let pattern = format!("{}/**/*.parquet", dir.trim_end_matches('/'));
+        self.register_glob(&pattern, alias, hive_partitioning)
+    }
+
+    /// Create the view exposing a file (or glob) as `alias`
+    pub(crate) fn register_view(
+        &mut self,
+        file_path: &str,
+        alias: &str,
+        format: FileFormat,
+        compression: Compression,
+        hive_partitioning: bool,
+        csv: Option<&CsvOptions>,
+    ) -> Result<()> {
+        let file_path = &self.check_allowed_path(file_path)?;
+        self.check_file_limits(file_path, compression)?;
+        let sql = file_view_sql(
+            file_path,
+            alias,
+            format,
+            compression,
+            hive_partitioning,
+            csv,
+        );
+
+        log::debug!("Registering file: {} -> {}", file_path, sql);
+        {
+            let _exclusive = self
+                .access
+                .write()
+                .map_err(|_| DuckDBError::QueryFailed("Lock poisoned".to_string()))?;
+            let conn = self
+                .conn
+                .lock()
+                .map_err(|_| DuckDBError::QueryFailed("Mutex poisoned".to_string()))?;
+            conn.execute(&sql, [])?;
+        }
+        let columns = self.get_table_schema(alias)?;
+        self.registered_files
+            .insert(alias.to_string(), file_path.to_string());
+        self.registered_schemas.insert(alias.to_string(), columns);
+        self.invalidate_statistics(Some(alias));
+        Ok(())
+    }
+
+    /// Register a file as a table visible only to one session
+    ///
+    /// The view is created as `session_<id>.<table>`, so sessions sharing
+    /// this source can use the same table name for different files.
+    /// `query_for_session` resolves unqualified names against the session
+    /// schema first. Excel sheets can't be registered per session.
+    pub fn register_file_scoped(
+        &self,
+        session_id: &str,
+        file_path: &str,
+        table: &str,
+    ) -> Result<()> {
+        let (format, compression) = detect_file_format(file_path)?;
+        if format == FileFormat::Excel {
+            return Err(DuckDBError::UnsupportedFileType(format!(
+                "Excel files can't be registered per session: {}",
+                file_path
+            )));
+        }
+        let file_path = &self.check_allowed_path(file_path)?;
+        self.check_file_limits(file_path, compression)?;
+
+        let schema = session_schema(session_id);
+        let qualified = format!("{}.{}", schema, table);
+        let view = format!("{}.{}", quote_ident(&schema), quote_ident(table));
+        let sql = format!(
+            "CREATE SCHEMA IF NOT EXISTS {}; {}",
+            quote_ident(&schema),
+            file_view_sql(file_path, &view, format, compression, false, None)
+        );
+
+        log::debug!("Registering session file: {} -> {}", file_path, sql);
+        {
+            let _exclusive = self
+                .access
+                .write()
+                .map_err(|_| DuckDBError::QueryFailed("Lock poisoned".to_string()))?;
+            let conn = self
+                .conn
+                .lock()
+                .map_err(|_| DuckDBError::QueryFailed("Mutex poisoned".to_string()))?;
+            conn.execute_batch(&sql)?;
+        }
+        self.session_files
+            .write()
+            .map_err(|_| DuckDBError::QueryFailed("Lock poisoned".to_string()))?
+            .entry(session_id.to_string())
+            .or_default()
+            .insert(table.to_string(), file_path.to_string());
+        self.invalidate_statistics(Some(&qualified));
+        Ok(())
+    }
+
+    /// Tables registered by a session (table -> file path)
+    pub fn session_files(&self, session_id: &str) -> HashMap<String, String> {
+        self.session_files
+            .read()
+            .ok()
+            .and_then(|files| files.get(session_id).cloned())
+            .unwrap_or_default()
+    }
+
+    /// Drop every table a session registered and forget its variables
+    pub fn drop_session(&self, session_id: &str) -> Result<()> {
+        if let Ok(mut variables) = self.session_variables.write() {
+            variables.remove(session_id);
+        }
+        let removed = self
+            .session_files
+            .write()
+            .map_err(|_| DuckDBError::QueryFailed("Lock poisoned".to_string()))?
+            .remove(session_id);
+        let Some(tables) = removed else {
+            return Ok(());
+        };
+
+        let schema = session_schema(session_id);
+        let sql = format!("DROP SCHEMA IF EXISTS {} CASCADE", quote_ident(&schema));
+        log::debug!("Dropping session tables: {}", sql);
+        {
+            let _exclusive = self
+                .access
+                .write()
+                .map_err(|_| DuckDBError::QueryFailed("Lock poisoned".to_string()))?;
+            let conn = self
+                .conn
+                .lock()
+                .map_err(|_| DuckDBError::QueryFailed("Mutex poisoned".to_string()))?;
+            conn.execute(&sql, [])?;
+        }
+        for table in tables.keys() {
+            self.invalidate_statistics(Some(&format!("{}.{}", schema, table)));
+        }
+        Ok(())
+    }
+
+    /// Load an Excel worksheet into a table
+    ///
+    /// Reads `sheet` (or the first sheet) with calamine and stores it as a
+    /// native DuckDB table with inferred column types. Returns warnings about
+    /// blank/merged or duplicate header cells that were renamed to `col_n`.
+    pub fn register_excel(
+        &mut self,
+        file_path: &str,
+        table: &str,
+        sheet: Option<&str>,
+        header: bool,
+    ) -> Result<Vec<String>> {
+        let file_path = &self.check_allowed_path(file_path)?;
+        self.check_file_limits(file_path, Compression::None)?;
+
+        let sheet = crate::excel::read_excel_sheet(Path::new(file_path), sheet, header)?;
+        log::debug!(
+            "Registering Excel sheet: {} [{}] -> {}",
+            file_path,
+            sheet.sheet_name,
+            table
+        );
+
+        self.register_result_set(table, &sheet.result)?;
+        self.registered_files
+            .insert(table.to_string(), file_path.to_string());
+        Ok(sheet.warnings)
+    }
+
+    /// Materialize the result of a query into a native table
+    ///
+    /// The table replaces any existing table with the same name and can be
+    /// queried like a registered file.
+    pub fn materialize(&mut self, table: &str, sql: &str) -> Result<()> {
+        let ddl = format!("CREATE OR REPLACE TABLE {} AS {}", table, sql);
+        log::debug!("Materializing table: {}", ddl);
+        let _exclusive = self
+            .access
+            .write()
+            .map_err(|_| DuckDBError::QueryFailed("Lock poisoned".to_string()))?;
+        let conn = self.conn.lock().map_err(|_| DuckDBError::QueryFailed("Mutex poisoned".to_string()))?;
+        conn.execute(&ddl, [])?;
+        self.registered_files
+            .insert(table.to_string(), format!("(materialized) {}", sql));
+        self.invalidate_statistics(Some(table));
+        Ok(())
+    }
+
+    /// Create (or replace) a view over a query
+    ///
+    /// The view is tracked with the registered files, so it shows up in
+    /// `schema()` and can be dropped with `unregister`. An existing table
+    /// with the same name is not replaced.
+    pub fn create_view(&mut self, view_name: &str, sql: &str) -> Result<()> {
+        if !is_identifier(view_name) {
+            return Err(DuckDBError::CatalogError(format!(
+                "Invalid view name '{}'",
+                view_name
+            )));
+        }
+        if let Some(origin) = self.registered_files.get(view_name) {
+            if !origin.starts_with("(view)") {
+                return Err(DuckDBError::CatalogError(format!(
+                    "'{}' is already registered as a table",
+                    view_name
+                )));
+            }
+        }
+
+        let ddl = format!("CREATE OR REPLACE VIEW {} AS {}", view_name, sql);
+        log::debug!("Creating view: {}", ddl);
+        {
+            let _exclusive = self
+                .access
+                .write()
+                .map_err(|_| DuckDBError::QueryFailed("Lock poisoned".to_string()))?;
+            let conn = self
+                .conn
+                .lock()
+                .map_err(|_| DuckDBError::QueryFailed("Mutex poisoned".to_string()))?;
+            conn.execute(&ddl, [])?;
+        }
+        self.registered_files
+            .insert(view_name.to_string(), format!("(view) {}", sql));
+        self.invalidate_statistics(Some(view_name));
+        Ok(())
+    }
+
+    /// Register an in-memory `ResultSet` as a DuckDB table
+    ///
+    /// Column types come from `Column::data_type`, falling back to the first
+    /// non-null value when the type is unknown. Empty result sets create an
+    /// empty table with the same schema.
+    pub fn register_result_set(&mut self, name: &str, result: &ResultSet) -> Result<()> {
+        if result.columns.is_empty() {
+            return Err(DuckDBError::SchemaError(format!(
+                "Result set for '{}' has no columns",
+                name
+            )));
+        }
+
+        let mut seen = std::collections::HashSet::new();
+        for column in &result.columns {
+            if !seen.insert(column.name.to_lowercase()) {
+                return Err(DuckDBError::SchemaError(format!(
+                    "Duplicate column name '{}' in result set for '{}'",
+                    column.name, name
+                )));
+            }
+        }
+
+        let column_defs: Vec<String> = result
+            .columns
+            .iter()
+            .enumerate()
+            .map(|(idx, column)| {
+                format!(
+                    "\"{}\" {}",
+                    column.name.replace('"', "\"\""),
+                    duckdb_column_type(&column.data_type, result, idx)
+                )
+            })
+            .collect();
+        let ddl = format!(
+            "CREATE OR REPLACE TABLE {} ({})",
+            name,
+            column_defs.join(", ")
+        );
+        log::debug!("Registering result set: {}", ddl);
+
+        {
+            let _exclusive = self
+                .access
+                .write()
+                .map_err(|_| DuckDBError::QueryFailed("Lock poisoned".to_string()))?;
+            let conn = self
+                .conn
+                .lock()
+                .map_err(|_| DuckDBError::QueryFailed("Mutex poisoned".to_string()))?;
+            conn.execute(&ddl, [])?;
+        }
+
+        self.bulk_insert(name, &result.rows)?;
+        self.registered_files
+            .insert(name.to_string(), "(result set)".to_string());
+        Ok(())
+    }
+
+    /// Write `result` to `path` as Parquet or CSV; returns the rows written
+    ///
+    /// The rows are loaded into a scratch table (see `register_result_set`)
+    /// and written with DuckDB's `COPY ... TO`. The table is dropped
+    /// afterwards, whether or not the copy succeeded.
+    pub fn write_table(
+        &mut self,
+        result: &ResultSet,
+        path: &str,
+        format: WriteFormat,
+    ) -> Result<u64> {
+        const SCRATCH_TABLE: &str = "noctra_write_table";
+
+        self.register_result_set(SCRATCH_TABLE, result)?;
+        let copy = format!(
+            "COPY {} TO '{}' ({})",
+            SCRATCH_TABLE,
+            path.replace('\'', "''"),
+            format.copy_options()
+        );
+        log::debug!("Writing result set: {}", copy);
+
+        let outcome = {
+            let _exclusive = self
+                .access
+                .write()
+                .map_err(|_| DuckDBError::QueryFailed("Lock poisoned".to_string()))?;
+            let conn = self
+                .conn
+                .lock()
+                .map_err(|_| DuckDBError::QueryFailed("Mutex poisoned".to_string()))?;
+            let outcome = conn.execute(&copy, []);
+            conn.execute(&format!("DROP TABLE IF EXISTS {}", SCRATCH_TABLE), [])?;
+            outcome
+        };
+        self.registered_files.remove(SCRATCH_TABLE);
+        self.registered_schemas.remove(SCRATCH_TABLE);
+        self.invalidate_statistics(Some(SCRATCH_TABLE));
+        outcome?;
+
+        Ok(result.rows.len() as u64)
+    }
+
+    /// Attach the executor's SQLite database so DuckDB queries can join it
+    ///
+    /// Tables become available as `sqlite_db.<table>`. In-memory SQLite
+    /// databases live in another process' memory and cannot be linked.
+    pub fn link_sqlite_backend(&mut self, backend: &SqliteBackend) -> Result<Vec<String>> {
+        let path = backend.database_path().ok_or_else(|| {
+            DuckDBError::UnsupportedFileType(
+                "In-memory SQLite databases cannot be linked".to_string(),
+            )
+        })?;
+        self.link_sqlite_database(path)
+    }
+
+    /// Attach a SQLite database file read-only under the `sqlite_db` catalog
+    ///
+    /// Re-linking replaces the previously attached database. Returns the
+    /// qualified names of the linked tables.
+    pub fn link_sqlite_database(&mut self, path: &str) -> Result<Vec<String>> {
+        if !Path::new(path).exists() {
+            return Err(DuckDBError::FileNotFound(path.to_string()));
+        }
+
+        log::debug!("Linking SQLite database: {} as {}", path, SQLITE_CATALOG);
+        let _exclusive = self
+            .access
+            .write()
+            .map_err(|_| DuckDBError::QueryFailed("Lock poisoned".to_string()))?;
+        let conn = self
+            .conn
+            .lock()
+            .map_err(|_| DuckDBError::QueryFailed("Mutex poisoned".to_string()))?;
+
+        conn.execute_batch(&format!(
+            "INSTALL sqlite; LOAD sqlite; DETACH DATABASE IF EXISTS {catalog}; \
+             ATTACH '{path}' AS {catalog} (TYPE sqlite, READ_ONLY)",
+            catalog = SQLITE_CATALOG,
+            path = path.replace('\'', "''"),
+        ))?;
+
+        let mut stmt = conn.prepare(
+            "SELECT table_name FROM information_schema.tables \
+             WHERE table_catalog = ? ORDER BY table_name",
+        )?;
+        let tables = stmt
+            .query_map(params![SQLITE_CATALOG], |row| row.get::<_, String>(0))?
+            .map(|name| name.map(|name| format!("{}.{}", SQLITE_CATALOG, name)))
+            .collect::<DuckResult<Vec<String>>>()?;
+
+        self.linked_tables = tables.clone();
+        Ok(tables)
+    }
+
+    /// Qualified names of the tables of the linked SQLite database
+    pub fn linked_tables(&self) -> &[String] {
+        &self.linked_tables
+    }
+
+    /// Append rows to an existing table using DuckDB's appender
+    ///
+    /// Returns the number of rows inserted.
+    pub fn bulk_insert(&self, table: &str, rows: &[NoctraRow]) -> Result<usize> {
+        {
+            let _exclusive = self
+                .access
+                .write()
+                .map_err(|_| DuckDBError::QueryFailed("Lock poisoned".to_string()))?;
+            let conn = self
+                .conn
+                .lock()
+                .map_err(|_| DuckDBError::QueryFailed("Mutex poisoned".to_string()))?;
+            let mut appender = conn.appender(table)?;
+            for row in rows {
+                let values = row.values.iter().map(to_duckdb_value);
+                appender.append_row(duckdb::appender_params_from_iter(values))?;
+            }
+            appender.flush()?;
+        }
+        self.invalidate_statistics(Some(table));
+        self.record_write();
+        Ok(rows.len())
+    }
+
+    /// Get statistics for a table, collecting them if not cached
+    pub fn table_statistics(&self, table: &str) -> Result<TableStatistics> {
+        if let Some(stats) = self.cached_statistics(table) {
+            return Ok(stats);
+        }
+        self.refresh_statistics(table)
+    }
+
+    /// Get cached statistics for a table without scanning it
+    pub fn cached_statistics(&self, table: &str) -> Option<TableStatistics> {
+        self.statistics.read().ok()?.get(table).cloned()
+    }
+
+    /// Recollect statistics for a table (runs a full scan)
+    pub fn refresh_statistics(&self, table: &str) -> Result<TableStatistics> {
+        let columns = self.get_table_schema(table)?;
+
+        let mut aggregates = vec!["COUNT(*)".to_string()];
+        for column in &columns {
+            let quoted = column.name.replace('"', "\"\"");
+            aggregates.push(format!("COUNT(\"{}\")", quoted));
+            aggregates.push(format!("approx_count_distinct(\"{}\")", quoted));
+        }
+        let sql = format!(
+            "SELECT {} FROM {}",
+            aggregates.join(", "),
+            quote_ident(table)
+        );
+        log::debug!("Collecting statistics: {}", sql);
+
+        let counts: Vec<i64> = {
+            let _shared = self
+                .access
+                .read()
+                .map_err(|_| DuckDBError::QueryFailed("Lock poisoned".to_string()))?;
+            let conn = self.checkout_reader()?;
+            let counts = conn.query_row(&sql, [], |row| {
+                (0..aggregates.len())
+                    .map(|idx| row.get::<_, i64>(idx))
+                    .collect::<DuckResult<Vec<i64>>>()
+            });
+            self.checkin_reader(conn);
+            counts?
+        };
+
+        let row_count = counts[0] as usize;
+        let stats = TableStatistics {
+            row_count,
+            columns: columns
+                .iter()
+                .enumerate()
+                .map(|(idx, column)| ColumnStatistics {
+                    name: column.name.clone(),
+                    null_count: row_count - counts[1 + idx * 2] as usize,
+                    distinct_count: counts[2 + idx * 2] as usize,
+                })
+                .collect(),
+            collected_at: SystemTime::now(),
+        };
+
+        if let Ok(mut cache) = self.statistics.write() {
+            cache.insert(table.to_string(), stats.clone());
+        }
+        Ok(stats)
+    }
+
+    /// Drop cached statistics for one table, or all tables if `None`
+    pub(crate) fn invalidate_statistics(&self, table: Option<&str>) {
+        if let Ok(mut cache) = self.statistics.write() {
+            match table {
+                Some(table) => {
+                    cache.remove(table);
+                }
+                None => cache.clear(),
+            }
+        }
+    }
+
+    /// Publish the session's variables to the `noctra_*` SQL functions
+    ///
+    /// - `noctra_var(name)`: session variable as text (NULL if unset)
+    /// - `noctra_now()`: current timestamp
+    /// - `noctra_user()`: the `user` session variable, or the OS user
+    ///
+    /// Nothing is written to the database: each of the session's queries sets
+    /// the variables with `SET VARIABLE` on the connection that runs it and
+    /// resets them afterwards, so concurrent sessions never see each other's
+    /// values. Calling this again after a `LET` refreshes them. Read-only
+    /// databases don't get the functions.
+    pub fn register_session_functions(&self, session: &Session) -> Result<()> {
+        if self.read_only {
+            return Ok(());
+        }
+        let mut variables: SessionVariables = session
+            .list_variables()
+            .iter()
+            .map(|(name, value)| (name.clone(), (!value.is_null()).then(|| value.to_string())))
+            .collect();
+        variables.sort();
+
+        self.session_variables
+            .write()
+            .map_err(|_| DuckDBError::QueryFailed("Lock poisoned".to_string()))?
+            .insert(session.id().to_string(), variables);
+        Ok(())
+    }
+
+    /// Settings for one of a session's queries (see `SessionScope`)
+    fn session_scope(&self, session_id: &str) -> SessionScope {
+        let search_path = (!self.session_files(session_id).is_empty())
+            .then(|| format!("{},main", session_schema(session_id)));
+        let variables = self
+            .session_variables
+            .read()
+            .ok()
+            .and_then(|variables| variables.get(session_id).cloned())
+            .unwrap_or_default();
+        SessionScope {
+            search_path,
+            variables,
+        }
+    }
+
+    /// Flush the write-ahead log into the database file
+    ///
+    /// Fails with `DuckDBError::ReadOnly` if the database was opened
+    /// read-only. A no-op for in-memory databases.
+    pub fn checkpoint(&self) -> Result<()> {
+        self.run_maintenance("CHECKPOINT")
+    }
+
+    /// Reclaim space left by deleted rows and checkpoint
+    ///
+    /// Blocks freed by deleted rows are reused by later writes; DuckDB does
+    /// not shrink the file itself. Afterwards the write-ahead log is empty
+    /// and `pending_writes()` is 0.
+    pub fn vacuum(&self) -> Result<()> {
+        self.run_maintenance("VACUUM; CHECKPOINT")
+    }
+
+    /// Write statements since the last checkpoint
+    pub fn pending_writes(&self) -> usize {
+        self.writes_since_checkpoint.load(Ordering::Relaxed)
+    }
+
+    /// Run a maintenance statement with exclusive access
+    fn run_maintenance(&self, sql: &str) -> Result<()> {
+        if self.read_only {
+            return Err(DuckDBError::ReadOnly(format!(
+                "cannot run {} on a database opened read-only",
+                sql.split(';').next().unwrap_or(sql)
+            )));
+        }
+
+        let _exclusive = self
+            .access
+            .write()
+            .map_err(|_| DuckDBError::QueryFailed("Lock poisoned".to_string()))?;
+        let conn = self
+            .conn
+            .lock()
+            .map_err(|_| DuckDBError::QueryFailed("Mutex poisoned".to_string()))?;
+        conn.execute_batch(sql)?;
+        self.writes_since_checkpoint.store(0, Ordering::Relaxed);
+        Ok(())
+    }
+
+    /// Count a write statement, checkpointing every `auto_checkpoint_interval`
+    ///
+    /// Must be called without holding `access` or the primary connection.
+    pub(crate) fn record_write(&self) {
+        let interval = self.config.auto_checkpoint_interval;
+        let writes = self.writes_since_checkpoint.fetch_add(1, Ordering::Relaxed) + 1;
+        if interval == 0 || self.read_only || writes < interval {
+            return;
+        }
+
+        log::debug!("Automatic checkpoint after {} writes", writes);
+        if let Err(e) = self.checkpoint() {
+            // The write itself succeeded; retry on the next one
+            log::warn!("Automatic checkpoint failed: {}", e);
+        }
+    }
+
+    /// Enforce `DuckDBConfig::allowed_paths` and return the path to open
+    ///
+    /// With roots the path comes back canonicalized, so DuckDB reads the file
+    /// that was checked; without roots the system-directory blocklist applies
+    /// and the path is returned as given.
+    fn check_allowed_path(&self, file_path: &str) -> Result<String> {
+        noctra_core::fs_guard::check_path(file_path, &self.config.allowed_paths)
+            .map(|path| path.to_string_lossy().into_owned())
+            .map_err(|e| match e {
+                noctra_core::error::NoctraError::Permission { message, .. } => {
+                    DuckDBError::AccessDenied(message)
+                }
+                other => DuckDBError::AccessDenied(other.to_string()),
+            })
+    }
+
+    /// Enforce on-disk and estimated decompressed size limits
+    ///
+    /// A glob is checked file by file, as DuckDB expands it.
+    fn check_file_limits(&self, file_path: &str, compression: Compression) -> Result<()> {
+        if file_path.contains(['*', '?', '[']) {
+            for file in self.glob_files(file_path)? {
+                self.check_file_limits(&file, compression)?;
+            }
+            return Ok(());
+        }
+
+        let path = Path::new(file_path);
+        // Missing files are reported by DuckDB itself
+        let Ok(metadata) = std::fs::metadata(path) else {
+            return Ok(());
+        };
+
+        let size = metadata.len();
+        if size > self.file_limits.max_file_size {
+            return Err(DuckDBError::FileTooLarge(format!(
+                "{} is {} bytes (max: {} bytes)",
+                file_path, size, self.file_limits.max_file_size
+            )));
+        }
+
+        if compression != Compression::None {
+            let estimated = estimate_decompressed_size(path, compression, size);
+            if estimated > self.file_limits.max_decompressed_size {
+                return Err(DuckDBError::FileTooLarge(format!(
+                    "{} decompresses to ~{} bytes (max: {} bytes)",
+                    file_path, estimated, self.file_limits.max_decompressed_size
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Files matching a glob, listed by DuckDB's `glob()`
+    fn glob_files(&self, pattern: &str) -> Result<Vec<String>> {
+        let conn = self
+            .conn
+            .lock()
+            .map_err(|_| DuckDBError::QueryFailed("Mutex poisoned".to_string()))?;
+        // Like pragma_table_info, glob() takes a literal rather than a parameter
+        let sql = format!("SELECT file FROM glob('{}')", pattern.replace('\'', "''"));
+        let mut stmt = conn.prepare(&sql)?;
+        let files = stmt
+            .query_map([], |row| row.get(0))?
+            .collect::<std::result::Result<Vec<String>, _>>()?;
+        Ok(files)
+    }
+
+    /// Attach a SQLite database to DuckDB for cross-source queries
+    pub fn attach_sqlite(&mut self, db_path: &str, alias: &str) -> Result<()> {
+        let conn = self.conn.lock().map_err(|_| DuckDBError::QueryFailed("Mutex poisoned".to_string()))?;
+        let sql = format!("ATTACH '{}' AS {} (TYPE SQLITE)", db_path, alias);
+        log::debug!("Attaching SQLite DB: {}", sql);
+        conn.execute(&sql, [])?;
+        Ok(())
+    }
+
+    /// Execute a query and return the result as Apache Arrow record batches
+    #[cfg(feature = "arrow-integration")]
+    pub fn query_arrow(&self, sql: &str) -> Result<Vec<arrow::record_batch::RecordBatch>> {
+        log::debug!("Executing Arrow query: {}", sql);
+        let conn = self
+            .conn
+            .lock()
+            .map_err(|_| DuckDBError::QueryFailed("Mutex poisoned".to_string()))?;
+        let mut stmt = conn.prepare(sql)?;
+        let batches = stmt.query_arrow([])?.collect();
+        Ok(batches)
+    }
+
+    /// Remove a registered table or view
+    pub fn unregister(&mut self, alias: &str) -> Result<()> {
+        let origin = self.registered_files.get(alias).ok_or_else(|| {
+            DuckDBError::CatalogError(format!("Table '{}' is not registered", alias))
+        })?;
+
+        // Files and user views are views; Excel sheets, result sets and
+        // materialized queries are native tables
+        let is_table = (origin.starts_with('(') && !origin.starts_with("(view)"))
+            || matches!(detect_file_format(origin), Ok((FileFormat::Excel, _)));
+        let sql = format!(
+            "DROP {} IF EXISTS {}",
+            if is_table { "TABLE" } else { "VIEW" },
+            alias
+        );
+
+        log::debug!("Unregistering table: {}", sql);
+        {
+            let _exclusive = self
+                .access
+                .write()
+                .map_err(|_| DuckDBError::QueryFailed("Lock poisoned".to_string()))?;
+            let conn = self
+                .conn
+                .lock()
+                .map_err(|_| DuckDBError::QueryFailed("Mutex poisoned".to_string()))?;
+            conn.execute(&sql, [])?;
+        }
+        self.registered_files.remove(alias);
+        self.registered_schemas.remove(alias);
+        self.invalidate_statistics(Some(alias));
+        Ok(())
+    }
+
+    /// Get registered files
+    pub fn registered_files(&self) -> &HashMap<String, String> {
+        &self.registered_files
+    }
+
+    /// Convert DuckDB row to Noctra Row
+    fn duckdb_row_to_noctra_row(row: &Row, columns: &[Column]) -> DuckResult<NoctraRow> {
+        let mut values = Vec::new();
+
+        for idx in 0..columns.len() {
+            // Dispatch on the DuckDB type first so floats/decimals aren't
+            // truncated by the integer conversion, dates aren't dropped and
+            // VARCHAR values like '5' stay text
+            match row.get_ref(idx)? {
+                ValueRef::Null => {
+                    values.push(Value::Null);
+                    continue;
+                }
+                ValueRef::Boolean(b) => {
+                    values.push(Value::Boolean(b));
+                    continue;
+                }
+                ValueRef::Decimal(_decimal) => {
+                    // Scales above 28 don't fit rust_decimal; those stay floats
+                    #[cfg(feature = "decimal")]
+                    if let Ok(decimal) = rust_decimal::Decimal::try_from(_decimal) {
+                        values.push(Value::Decimal(decimal.normalize()));
+                        continue;
+                    }
+                    values.push(Value::Float(row.get::<_, f64>(idx)?));
+                    continue;
+                }
+                ValueRef::Float(_) | ValueRef::Double(_) => {
+                    values.push(Value::Float(row.get::<_, f64>(idx)?));
+                    continue;
+                }
+                ValueRef::Date32(_) => {
+                    values.push(Value::Date(row.get::<_, NaiveDate>(idx)?));
+                    continue;
+                }
+                ValueRef::Time64(..) => {
+                    values.push(Value::Time(row.get::<_, NaiveTime>(idx)?));
+                    continue;
+                }
+                ValueRef::Timestamp(..) => {
+                    values.push(Value::Timestamp(row.get::<_, DateTime<Utc>>(idx)?));
+                    continue;
+                }
+                ValueRef::Blob(bytes) => {
+                    values.push(Value::Blob(bytes.to_vec()));
+                    continue;
+                }
+                ValueRef::Text(bytes) => {
+                    values.push(Value::Text(String::from_utf8_lossy(bytes).into_owned()));
+                    continue;
+                }
+                _ => {}
+            }
+
+            // Try different types in order of preference
+            // First try as integer
+            if let Ok(val) = row.get::<_, Option<i64>>(idx) {
+                values.push(val.map(Value::Integer).unwrap_or(Value::Null));
+                continue;
+            }
+
+            // Then try as float
+            if let Ok(val) = row.get::<_, Option<f64>>(idx) {
+                values.push(val.map(Value::Float).unwrap_or(Value::Null));
+                continue;
+            }
+
+            // Then try as boolean
+            if let Ok(val) = row.get::<_, Option<bool>>(idx) {
+                values.push(val.map(Value::Boolean).unwrap_or(Value::Null));
+                continue;
+            }
+
+            // Finally try as string
+            if let Ok(val) = row.get::<_, Option<String>>(idx) {
+                values.push(val.map(Value::Text).unwrap_or(Value::Null));
+                continue;
+            }
+
+            // If all else fails, use Null
+            values.push(Value::Null);
+        }
+
+        Ok(NoctraRow { values })
+    }
+
+    /// Execute a query, optionally bypassing the result row limit
+    ///
+    /// `DataSource::query` uses the default options, so results are capped at
+    /// `DuckDBConfig::max_result_rows` and flagged with `ResultSet::truncated`.
+    pub fn query_with_options(
+        &self,
+        sql: &str,
+        options: QueryOptions,
+    ) -> noctra_core::error::Result<ResultSet> {
+        self.run_query(sql, &Parameters::new(), options, &SessionScope::default())
+    }
+
+    /// Execute a query on behalf of a session
+    ///
+    /// Unqualified table names are looked up in the session schema first and
+    /// then in `main`, so a session's `data` shadows a shared `data` table.
+    /// `noctra_var()` reads the session's variables.
+    pub fn query_for_session(
+        &self,
+        session_id: &str,
+        sql: &str,
+        parameters: &Parameters,
+        options: QueryOptions,
+    ) -> noctra_core::error::Result<ResultSet> {
+        self.run_query(sql, parameters, options, &self.session_scope(session_id))
+    }
+
+    /// Execute an INSERT, UPDATE or DELETE on behalf of a session
+    ///
+    /// Unqualified table names resolve as in `query_for_session`. DuckDB has
+    /// no rowids, so `last_insert_rowid` is always None.
+    pub fn execute_write_for_session(
+        &self,
+        session_id: &str,
+        sql: &str,
+        parameters: &Parameters,
+    ) -> noctra_core::error::Result<WriteResult> {
+        self.run_write(sql, parameters, &self.session_scope(session_id), None)
+    }
+
+    /// Execute a query and yield its rows as they are fetched
+    ///
+    /// Read-only queries run on their own handle in a producer thread, so
+    /// rows are converted one at a time and the row limit does not apply.
+    /// Writes and queries that need the session schema fall back to the
+    /// materialized path.
+    pub fn query_stream(
+        &self,
+        session_id: &str,
+        sql: &str,
+        parameters: &Parameters,
+    ) -> noctra_core::error::Result<RowStream> {
+        if !is_read_only(sql) || !self.session_files(session_id).is_empty() {
+            return self
+                .query_for_session(session_id, sql, parameters, QueryOptions::default())
+                .map(RowStream::from_result_set);
+        }
+
+        log::debug!("Streaming query: {}", sql);
+        let conn = self
+            .connection()
+            .map_err(noctra_core::error::NoctraError::from)?;
+        // The handle is not pooled, so the variables need no reset
+        self.session_scope(session_id).apply(&conn)?;
+        let sql = sql.to_string();
+        let parameters = parameters.clone();
+        RowStream::from_producer(move |sink| {
+            let mut stmt = conn
+                .prepare(&cast_placeholders(&sql))
+                .map_err(backend_error)?;
+            let values = bound_values(&stmt, &parameters)?;
+            let mut rows = stmt
+                .query(duckdb::params_from_iter(values))
+                .map_err(backend_error)?;
+
+            let columns: Vec<Column> = rows
+                .as_ref()
+                .map(|stmt| stmt.column_names())
+                .unwrap_or_default()
+                .into_iter()
+                .enumerate()
+                .map(|(ordinal, name)| Column {
+                    name,
+                    data_type: "UNKNOWN".to_string(),
+                    ordinal,
+                })
+                .collect();
+            if !sink.columns(columns.clone()) {
+                return Ok(());
+            }
+
+            while let Some(row) = rows.next().map_err(backend_error)? {
+                let row = Self::duckdb_row_to_noctra_row(row, &columns).map_err(|e| {
+                    noctra_core::error::NoctraError::Internal(format!(
+                        "Row conversion error: {}",
+                        e
+                    ))
+                })?;
+                if !sink.send(row) {
+                    break;
+                }
+            }
+            Ok(())
+        })
+    }
+
+    /// Validate a query with EXPLAIN, without running it
+    pub fn dry_run(&self, sql: &str) -> noctra_core::error::Result<QueryPlan> {
+        // EXPLAIN plans the statement without running it
+        let explain = self
+            .run_query(
+                &format!("EXPLAIN {}", sql),
+                &Parameters::new(),
+                QueryOptions {
+                    unlimited: true,
+                    ..Default::default()
+                },
+                &SessionScope::default(),
+            )
+            .map_err(|e| match e {
+                noctra_core::error::NoctraError::Backend { message, .. } => {
+                    noctra_core::error::NoctraError::DryRunFailed(message)
+                }
+                other => noctra_core::error::NoctraError::DryRunFailed(other.to_string()),
+            })?;
+
+        let mut plan = QueryPlan::from_sql(sql);
+        for row in &explain.rows {
+            let Some(Value::Text(text)) = row.values.last() else {
+                continue;
+            };
+            if plan.estimated_row_count.is_none() {
+                // The root operator comes first, so its estimate is the result size
+                plan.estimated_row_count = explain_row_estimate(text);
+            }
+            for table in explain_tables(text) {
+                plan.add_table(table);
+            }
+        }
+
+        Ok(plan)
+    }
+
+    /// Run a read-only query with DuckDB's profiler and return its plan
+    ///
+    /// The query really executes; `QueryPlan::root` holds each operator's
+    /// estimated and actual rows and its own time. Writes are rejected
+    /// rather than run twice.
+    pub fn explain_analyze(&self, sql: &str) -> noctra_core::error::Result<QueryPlan> {
+        if !is_read_only(sql) {
+            return Err(noctra_core::error::NoctraError::Validation(
+                "EXPLAIN ANALYZE runs the statement; only read-only queries are allowed"
+                    .to_string(),
+            ));
+        }
+
+        let explain = self.run_query(
+            &format!("EXPLAIN (ANALYZE, FORMAT JSON) {}", sql),
+            &Parameters::new(),
+            QueryOptions {
+                unlimited: true,
+                ..Default::default()
+            },
+            &SessionScope::default(),
+        )?;
+        // One row: (explain_key, explain_value) with the JSON profile
+        let Some(Value::Text(json)) = explain.rows.first().and_then(|row| row.values.last()) else {
+            return Err(noctra_core::error::NoctraError::Serialization(
+                "EXPLAIN ANALYZE returned no profile".to_string(),
+            ));
+        };
+
+        let root = parse_analyze_json(json)?;
+        let mut plan = QueryPlan::from_sql(sql);
+        plan.estimated_row_count = Some(root.estimated_rows as usize).filter(|rows| *rows > 0);
+        plan.root = Some(root);
+        Ok(plan)
+    }
+
+    /// Per-column statistics for a table, via DuckDB's `SUMMARIZE`
+    ///
+    /// One row per column with its type, min/max, approximate distinct count,
+    /// averages, quartiles and null percentage.
+    pub fn profile(&self, table: &str) -> noctra_core::error::Result<ResultSet> {
+        self.run_query(
+            &format!("SUMMARIZE {}", quote_ident(table)),
+            &Parameters::new(),
+            QueryOptions {
+                unlimited: true,
+                ..Default::default()
+            },
+            &SessionScope::default(),
+        )
+    }
+
+    /// Up to `n` rows picked at random from a table with reservoir sampling
+    ///
+    /// `TABLESAMPLE RESERVOIR` scans the whole table but keeps only `n` rows
+    /// in memory and never sorts, so large files cost one read.
+    pub fn sample(&self, table: &str, n: usize) -> noctra_core::error::Result<ResultSet> {
+        self.run_query(
+            &format!(
+                "SELECT * FROM {} TABLESAMPLE RESERVOIR({} ROWS)",
+                quote_ident(table),
+                n
+            ),
+            &Parameters::new(),
+            QueryOptions {
+                unlimited: true,
+                ..Default::default()
+            },
+            &SessionScope::default(),
+        )
+    }
+
+    /// Run a query on a read handle or the primary connection
+    ///
+    /// The session's `scope` only applies to this query; the connection is
+    /// reset before it goes back to the pool.
+    fn run_query(
+        &self,
+        sql: &str,
+        parameters: &Parameters,
+        options: QueryOptions,
+        scope: &SessionScope,
+    ) -> noctra_core::error::Result<ResultSet> {
+        log::debug!("Executing query: {}", sql);
+
+        let limit = if options.unlimited {
+            None
+        } else {
+            Some(self.config.max_result_rows)
+        };
+        let run = |conn: &Connection| {
+            scope.apply(conn)?;
+            let result = self.execute_on(conn, sql, parameters, limit);
+            scope.reset(conn)?;
+            result
+        };
+
+        if is_read_only(sql) {
+            let _shared = self.access.read().map_err(|_| {
+                noctra_core::error::NoctraError::Internal("Lock poisoned".to_string())
+            })?;
+            let conn = self
+                .checkout_reader()
+                .map_err(noctra_core::error::NoctraError::from)?;
+            let result = with_timeout(&conn, options.timeout, run);
+            self.checkin_reader(conn);
+            result
+        } else {
+            let result = {
+                let _exclusive = self.access.write().map_err(|_| {
+                    noctra_core::error::NoctraError::Internal("Lock poisoned".to_string())
+                })?;
+                let conn = self.conn.lock().map_err(|_| {
+                    noctra_core::error::NoctraError::Internal("Mutex poisoned".to_string())
+                })?;
+                // Any write may change row counts; we don't track which tables
+                self.invalidate_statistics(None);
+                with_timeout(&conn, options.timeout, run)
+            };
+            if result.is_ok() {
+                self.record_write();
+            }
+            result
+        }
+    }
+
+    /// Run a write statement on the primary connection
+    ///
+    /// `scope` behaves as in `run_query`; a write that outlives `timeout` is
+    /// interrupted.
+    fn run_write(
+        &self,
+        sql: &str,
+        parameters: &Parameters,
+        scope: &SessionScope,
+        timeout: Option<Duration>,
+    ) -> noctra_core::error::Result<WriteResult> {
+        log::debug!("Executing write: {}", sql);
+
+        let rows_affected = {
+            let _exclusive = self.access.write().map_err(|_| {
+                noctra_core::error::NoctraError::Internal("Lock poisoned".to_string())
+            })?;
+            let conn = self.conn.lock().map_err(|_| {
+                noctra_core::error::NoctraError::Internal("Mutex poisoned".to_string())
+            })?;
+            self.invalidate_statistics(None);
+
+            scope.apply(&conn)?;
+            let result = with_timeout(&conn, timeout, |conn| {
+                let mut stmt = conn
+                    .prepare(&cast_placeholders(sql))
+                    .map_err(backend_error)?;
+                let values = bound_values(&stmt, parameters)?;
+                stmt.execute(duckdb::params_from_iter(values))
+                    .map_err(backend_error)
+            });
+            scope.reset(&conn)?;
+            result?
+        };
+
+        self.record_write();
+        Ok(WriteResult {
+            rows_affected: rows_affected as u64,
+            last_insert_rowid: None,
+        })
+    }
+
+    /// Execute a query on the given connection and collect a ResultSet
+    ///
+    /// With a `limit`, at most `limit + 1` rows are fetched: the extra row
+    /// only tells us the result was cut short and is discarded.
+    fn execute_on(
+        &self,
+        conn: &Connection,
+        sql: &str,
+        parameters: &Parameters,
+        limit: Option<usize>,
+    ) -> noctra_core::error::Result<ResultSet> {
+        let mut stmt = conn
+            .prepare(&cast_placeholders(sql))
+            .map_err(backend_error)?;
+        self.execute_prepared(&mut stmt, parameters, limit)
+    }
+
+    /// Execute an already prepared statement and collect a ResultSet
+    ///
+    /// Placeholders (`$name` or `?`) are bound by name from `parameters`;
+    /// unbound placeholders are NULL. `limit` behaves as in `execute_on`.
+    pub(crate) fn execute_prepared(
+        &self,
+        stmt: &mut duckdb::Statement<'_>,
+        parameters: &Parameters,
+        limit: Option<usize>,
+    ) -> noctra_core::error::Result<ResultSet> {
+        let values = bound_values(stmt, parameters)?;
+        let mut rows_result = stmt
+            .query(duckdb::params_from_iter(values))
+            .map_err(backend_error)?;
+
+        // Get column metadata from first row (if exists)
+        let mut columns: Vec<Column> = Vec::new();
+        let mut rows: Vec<NoctraRow> = Vec::new();
+        let mut truncated = false;
+
+        if let Some(row) = rows_result.next().map_err(backend_error)? {
+            // Extract column names from the statement after query execution
+            let column_count = row.as_ref().column_count();
+            for idx in 0..column_count {
+                let name = row.as_ref().column_name(idx)
+                    .map_err(|e| noctra_core::error::NoctraError::Internal(format!("Column name error: {}", e)))?;
+                columns.push(Column {
+                    name: name.to_string(),
+                    data_type: "UNKNOWN".to_string(),
+                    ordinal: idx,
+                });
+            }
+
+            // Convert first row
+            rows.push(Self::duckdb_row_to_noctra_row(row, &columns)
+                .map_err(|e| noctra_core::error::NoctraError::Internal(format!("Row conversion error: {}", e)))?);
+
+            // Process remaining rows
+            while let Some(row) = rows_result.next().map_err(backend_error)? {
+                if limit.is_some_and(|max| rows.len() >= max) {
+                    truncated = true;
+                    break;
+                }
+                rows.push(Self::duckdb_row_to_noctra_row(row, &columns)
+                    .map_err(|e| noctra_core::error::NoctraError::Internal(format!("Row conversion error: {}", e)))?);
+            }
+        }
+
+        Ok(ResultSet {
+            columns,
+            rows,
+            rows_affected: None,
+            last_insert_rowid: None,
+            truncated,
+        })
+    }
+
+    /// Get table schema from DuckDB information_schema
+    fn get_table_schema(&self, table_name: &str) -> Result<Vec<ColumnInfo>> {
+        let conn = self
+            .conn
+            .lock()
+            .map_err(|_| DuckDBError::QueryFailed("Mutex poisoned".to_string()))?;
+
+        // pragma_table_info also covers views; DuckDB rejects a prepared
+        // parameter as its argument, so the name goes in as a string literal
+        let sql = format!(
+            "SELECT name, type, \"notnull\", dflt_value, pk FROM pragma_table_info('{}')",
+            table_name.replace('\'', "''")
+        );
+
+        let mut stmt = conn.prepare(&sql)?;
+        let rows = stmt.query_map([], |row| {
+            let name: String = row.get(0)?;
+            let data_type: String = row.get(1)?;
+            let notnull: bool = row.get(2)?;
+            let default_value: Option<String> = row.get(3)?;
+            let primary_key: bool = row.get(4)?;
+            Ok((name, data_type, !notnull, default_value, primary_key))
+        })?;
+
+        let mut columns = Vec::new();
+        for row_result in rows {
+            let (name, data_type, nullable, default_value, primary_key) = row_result?;
+            columns.push(ColumnInfo {
+                name,
+                data_type: data_type.to_uppercase(),
+                nullable,
+                default_value,
+                primary_key,
+            });
+        }
+
+        Ok(columns)
+    }
+}
+
+impl DataSource for DuckDBSource {
+    fn query(&self, sql: &str, parameters: &Parameters) -> noctra_core::error::Result<ResultSet> {
+        self.run_query(
+            sql,
+            parameters,
+            QueryOptions::default(),
+            &SessionScope::default(),
+        )
+    }
+
+    fn query_in_session(
+        &self,
+        session_id: &str,
+        sql: &str,
+        parameters: &Parameters,
+    ) -> noctra_core::error::Result<ResultSet> {
+        self.query_for_session(session_id, sql, parameters, QueryOptions::default())
+    }
+
+    fn query_with_timeout(
+        &self,
+        session_id: &str,
+        sql: &str,
+        parameters: &Parameters,
+        timeout: Duration,
+    ) -> noctra_core::error::Result<ResultSet> {
+        let options = QueryOptions {
+            timeout: Some(timeout),
+            ..Default::default()
+        };
+        self.query_for_session(session_id, sql, parameters, options)
+    }
+
+    fn query_stream(
+        &self,
+        session_id: &str,
+        sql: &str,
+        parameters: &Parameters,
+    ) -> noctra_core::error::Result<RowStream> {
+        DuckDBSource::query_stream(self, session_id, sql, parameters)
+    }
+
+    fn execute_write(
+        &self,
+        sql: &str,
+        parameters: &Parameters,
+    ) -> noctra_core::error::Result<WriteResult> {
+        self.run_write(sql, parameters, &SessionScope::default(), None)
+    }
+
+    fn execute_write_in_session(
+        &self,
+        session_id: &str,
+        sql: &str,
+        parameters: &Parameters,
+    ) -> noctra_core::error::Result<WriteResult> {
+        self.execute_write_for_session(session_id, sql, parameters)
+    }
+
+    fn execute_write_with_timeout(
+        &self,
+        session_id: &str,
+        sql: &str,
+        parameters: &Parameters,
+        timeout: Duration,
+    ) -> noctra_core::error::Result<WriteResult> {
+        self.run_write(
+            sql,
+            parameters,
+            &self.session_scope(session_id),
+            Some(timeout),
+        )
+    }
+
+    fn binds_parameters(&self) -> bool {
+        true
+    }
+
+    fn dry_run(&self, sql: &str) -> noctra_core::error::Result<QueryPlan> {
+        DuckDBSource::dry_run(self, sql)
+    }
+
+    fn explain_analyze(&self, sql: &str) -> noctra_core::error::Result<QueryPlan> {
+        DuckDBSource::explain_analyze(self, sql)
+    }
+
+    fn profile(&self, table: &str) -> noctra_core::error::Result<ResultSet> {
+        DuckDBSource::profile(self, table)
+    }
+
+    fn sample(&self, table: &str, n: usize) -> noctra_core::error::Result<ResultSet> {
+        DuckDBSource::sample(self, table, n)
+    }
+
+    fn checkpoint(&self) -> noctra_core::error::Result<()> {
+        Ok(DuckDBSource::checkpoint(self)?)
+    }
+
+    fn sync_session(&self, session: &Session) -> noctra_core::error::Result<()> {
+        Ok(self.register_session_functions(session)?)
+    }
+
+    fn register_session_file(
+        &self,
+        session_id: &str,
+        path: &str,
+        table: &str,
+    ) -> noctra_core::error::Result<()> {
+        Ok(self.register_file_scoped(session_id, path, table)?)
+    }
+
+    fn release_session(&self, session_id: &str) -> noctra_core::error::Result<()> {
+        Ok(self.drop_session(session_id)?)
+    }
+
+    fn create_view(&mut self, name: &str, sql: &str) -> noctra_core::error::Result<()> {
+        Ok(DuckDBSource::create_view(self, name, sql)?)
+    }
+
+    fn schema(&self) -> noctra_core::error::Result<Vec<TableInfo>> {
+        let mut tables = Vec::new();
+
+        // Return schema for registered files and linked SQLite tables only.
+        // Row counts come from the statistics cache; computing them here
+        // would scan every file.
+        let linked = self.linked_tables.iter();
+        for alias in self.registered_files.keys().chain(linked) {
+            let columns = self.get_table_schema(alias)?;
+            let stats = self.cached_statistics(alias);
+            let is_view = self
+                .registered_files
+                .get(alias)
+                .is_some_and(|origin| origin.starts_with("(view)"));
+            tables.push(TableInfo {
+                name: alias.clone(),
+                columns,
+                row_count: stats.as_ref().map(|s| s.row_count),
+                row_count_updated_at: stats.map(|s| s.collected_at),
+                is_view,
+            });
+        }
+
+        Ok(tables)
+    }
+
+    fn source_type(&self) -> SourceType {
+        // A single registered CSV/JSON file is reported as such (with its compression)
+        if self.registered_files.len() == 1 {
+            if let Some(path) = self.registered_files.values().next() {
+                if let Ok((format, compression)) = detect_file_format(path) {
+                    let compression = compression.as_duckdb_str().map(String::from);
+                    match format {
+                        FileFormat::Csv => {
+                            return SourceType::CSV {
+                                path: path.clone(),
+                                delimiter: ',',
+                                has_header: true,
+                                encoding: "utf-8".to_string(),
+                                compression,
+                            };
+                        }
+                        FileFormat::Json | FileFormat::NdJson => {
+                            return SourceType::JSON {
+                                path: path.clone(),
+                                compression,
+                            };
+                        }
+                        FileFormat::Parquet | FileFormat::Excel => {}
+                    }
+                }
+            }
+        }
+
+        SourceType::Memory {
+            capacity: 0, // In-memory DuckDB
+        }
+    }
+
+    fn name(&self) -> &str {
+        &self.name
+    }
+
+    fn link_sqlite(&mut self, path: &str) -> noctra_core::error::Result<Vec<String>> {
+        Ok(self.link_sqlite_database(path)?)
+    }
+}
+
+/// Lets a `QueryEngine` route analytical queries to DuckDB
+impl Backend for DuckDBSource {
+    fn execute_query(
+        &self,
+        sql: &str,
+        parameters: &Parameters,
+    ) -> noctra_core::error::Result<ResultSet> {
+        DataSource::query(self, sql, parameters)
+    }
+
+    fn execute_statement(
+        &self,
+        sql: &str,
+        parameters: &Parameters,
+    ) -> noctra_core::error::Result<ResultSet> {
+        DataSource::query(self, sql, parameters)
+    }
+
+    fn ping(&self) -> noctra_core::error::Result<()> {
+        self.query_with_options("SELECT 1", QueryOptions::default())
+            .map(|_| ())
+    }
+
+    fn backend_info(&self) -> BackendInfo {
+        BackendInfo {
+            name: "DuckDB".to_string(),
+            version: self
+                .conn
+                .lock()
+                .ok()
+                .and_then(|conn| conn.version().ok())
+                .unwrap_or_default(),
+            url: format!("duckdb://{}", self.name),
+            features: vec![
+                "sql".to_string(),
+                noctra_core::query_engine::COLUMNAR_FEATURE.to_string(),
+                "files".to_string(),
+            ],
+        }
+    }
+
+    /// Only cached statistics are used; routing never scans a table
+    fn table_row_count(&self, table: &str) -> Option<usize> {
+        self.cached_statistics(table).map(|stats| stats.row_count)
+    }
+
+    fn dry_run(&self, sql: &str) -> noctra_core::error::Result<QueryPlan> {
+        DuckDBSource::dry_run(self, sql)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Write;
+
+    #[test]
+    fn test_new_in_memory() {
+        let source = DuckDBSource::new_in_memory().unwrap();
+        assert_eq!(source.name(), "duckdb");
+    }
+
+    #[test]
+    fn test_register_csv_file() {
+        let mut temp_file = tempfile::Builder::new().suffix(".csv").tempfile().unwrap();
+        writeln!(temp_file, "name,age,city").unwrap();
+        writeln!(temp_file, "Alice,30,NYC").unwrap();
+        writeln!(temp_file, "Bob,25,LA").unwrap();
+        temp_file.flush().unwrap();
+
+        let mut source = DuckDBSource::new_in_memory().unwrap();
+        source
+            .register_file(temp_file.path().to_str().unwrap(), "test_table", false)
+            .unwrap();
+
+        assert!(source.registered_files().contains_key("test_table"));
+    }
+
+    #[test]
+    fn test_detect_drift_after_file_changes() {
+        let temp_file = tempfile::Builder::new().suffix(".csv").tempfile().unwrap();
+        let path = temp_file.path().to_str().unwrap().to_string();
+        std::fs::write(&path, "id,name,age\n1,Alice,30\n").unwrap();
+
+        let mut source = DuckDBSource::new_in_memory().unwrap();
+        assert!(source
+            .register_file(&path, "people", false)
+            .unwrap()
+            .is_none());
+        assert!(source.detect_drift("people").unwrap().is_empty());
+
+        // Rename `name` and turn `age` into text
+        std::fs::write(&path, "id,full_name,age\n1,Alice,thirty\n").unwrap();
+        let diff = source.detect_drift("people").unwrap();
+        let names =
+            |columns: &[ColumnInfo]| columns.iter().map(|c| c.name.clone()).collect::<Vec<_>>();
+        assert_eq!(names(&diff.removed), vec!["name"]);
+        assert_eq!(names(&diff.added), vec!["full_name"]);
+        assert_eq!(diff.retyped.len(), 1);
+        assert_eq!(diff.retyped[0].name, "age");
+        assert_eq!(diff.retyped[0].new_type, "VARCHAR");
+
+        // Re-registering requires `replace` and reports the same diff
+        assert!(matches!(
+            source.register_file(&path, "people", false),
+            Err(DuckDBError::CatalogError(_))
+        ));
+        let diff = source
+            .register_file(&path, "people", true)
+            .unwrap()
+            .unwrap();
+        assert_eq!(names(&diff.removed), vec!["name"]);
+        assert_eq!(names(&diff.added), vec!["full_name"]);
+        assert!(source.detect_drift("people").unwrap().is_empty());
+    }
+
+    #[test]
+    fn test_schema_uses_cached_statistics() {
+        let mut temp_file = tempfile::Builder::new().suffix(".csv").tempfile().unwrap();
+        writeln!(temp_file, "name,city").unwrap();
+        writeln!(temp_file, "Alice,NYC").unwrap();
+        writeln!(temp_file, "Bob,").unwrap();
+        writeln!(temp_file, "Carol,NYC").unwrap();
+        temp_file.flush().unwrap();
+
+        let mut source = DuckDBSource::new_in_memory().unwrap();
+        source
+            .register_file(temp_file.path().to_str().unwrap(), "people", false)
+            .unwrap();
+
+        // No statistics collected yet: schema() must not scan
+        let tables = source.schema().unwrap();
+        assert_eq!(tables[0].row_count, None);
+        assert!(source.cached_statistics("people").is_none());
+
+        let stats = source.table_statistics("people").unwrap();
+        assert_eq!(stats.row_count, 3);
+        assert_eq!(stats.columns[1].name, "city");
+        assert_eq!(stats.columns[1].null_count, 1);
+        assert_eq!(stats.columns[1].distinct_count, 1);
+
+        let tables = source.schema().unwrap();
+        assert_eq!(tables[0].row_count, Some(3));
+        assert_eq!(tables[0].row_count_updated_at, Some(stats.collected_at));
+    }
+
+    #[test]
+    fn test_statistics_invalidated_by_bulk_insert() {
+        let source = DuckDBSource::new_in_memory().unwrap();
+        source
+            .query(
+                "CREATE TABLE events (id INTEGER, kind VARCHAR)",
+                &Parameters::new(),
+            )
+            .unwrap();
+        assert_eq!(source.table_statistics("events").unwrap().row_count, 0);
+
+        let rows = vec![
+            NoctraRow::new(vec![Value::Integer(1), Value::Text("click".to_string())]),
+            NoctraRow::new(vec![Value::Integer(2), Value::Null]),
+        ];
+        assert_eq!(source.bulk_insert("events", &rows).unwrap(), 2);
+        assert!(source.cached_statistics("events").is_none());
+
+        let stats = source.table_statistics("events").unwrap();
+        assert_eq!(stats.row_count, 2);
+        assert_eq!(stats.columns[1].null_count, 1);
+    }
+
+    #[test]
+    fn test_statistics_invalidated_by_materialize() {
+        let mut source = DuckDBSource::new_in_memory().unwrap();
+        source
+            .materialize("numbers", "SELECT * FROM range(10)")
+            .unwrap();
+        assert_eq!(source.table_statistics("numbers").unwrap().row_count, 10);
+
+        source
+            .materialize("numbers", "SELECT * FROM range(25)")
+            .unwrap();
+        assert!(source.cached_statistics("numbers").is_none());
+        assert_eq!(source.table_statistics("numbers").unwrap().row_count, 25);
+
+        // Other write statements invalidate everything
+        source
+            .query("DELETE FROM numbers WHERE range < 5", &Parameters::new())
+            .unwrap();
+        assert!(source.cached_statistics("numbers").is_none());
+        assert_eq!(source.refresh_statistics("numbers").unwrap().row_count, 20);
+    }
+
+    #[test]
+    fn test_create_view() {
+        let mut source = DuckDBSource::new_in_memory().unwrap();
+        source
+            .materialize("numbers", "SELECT * FROM range(10)")
+            .unwrap();
+        source
+            .create_view(
+                "big_numbers",
+                "SELECT range AS n FROM numbers WHERE range >= 7",
+            )
+            .unwrap();
+
+        let result = source
+            .query("SELECT COUNT(*) FROM big_numbers", &Parameters::new())
+            .unwrap();
+        assert_eq!(result.rows[0].values[0], Value::Integer(3));
+
+        let tables = source.query("SHOW TABLES", &Parameters::new()).unwrap();
+        assert!(tables
+            .rows
+            .iter()
+            .any(|row| row.values[0] == Value::Text("big_numbers".to_string())));
+
+        let schema = source.schema().unwrap();
+        let view = schema.iter().find(|t| t.name == "big_numbers").unwrap();
+        assert!(view.is_view);
+        assert_eq!(view.columns[0].name, "n");
+        assert!(!schema.iter().find(|t| t.name == "numbers").unwrap().is_view);
+
+        // Invalid names and existing tables are rejected
+        assert!(source.create_view("bad name", "SELECT 1").is_err());
+        assert!(source.create_view("numbers", "SELECT 1").is_err());
+
+        source.unregister("big_numbers").unwrap();
+        assert!(source
+            .query("SELECT * FROM big_numbers", &Parameters::new())
+            .is_err());
+    }
+
+    #[test]
+    fn test_register_result_set_roundtrip() {
+        let mut result = ResultSet::new(vec![
+            Column::new("id", "INTEGER", 0),
+            Column::new("name", "TEXT", 1),
+            Column::new("score", "REAL", 2),
+            Column::new("active", "UNKNOWN", 3),
+        ]);
+        result.add_row(NoctraRow::new(vec![
+            Value::Integer(1),
+            Value::Text("Alice".to_string()),
+            Value::Float(9.5),
+            Value::Boolean(true),
+        ]));
+        result.add_row(NoctraRow::new(vec![
+            Value::Integer(2),
+            Value::Null,
+            Value::Float(7.25),
+            Value::Boolean(false),
+        ]));
+
+        let mut source = DuckDBSource::new_in_memory().unwrap();
+        source.register_result_set("stash", &result).unwrap();
+
+        let back = source
+            .query(
+                "SELECT id, name, score, active FROM stash ORDER BY id",
+                &Parameters::new(),
+            )
+            .unwrap();
+        assert_eq!(back.rows, result.rows);
+        let schema = source.get_table_schema("stash").unwrap();
+        assert_eq!(schema[0].data_type, "BIGINT");
+        assert_eq!(schema[3].data_type, "BOOLEAN");
+    }
+
+    #[test]
+    fn test_register_result_set_empty_and_duplicates() {
+        let mut source = DuckDBSource::new_in_memory().unwrap();
+
+        let empty = ResultSet::new(vec![
+            Column::new("id", "INTEGER", 0),
+            Column::new("label", "TEXT", 1),
+        ]);
+        source.register_result_set("empty_stash", &empty).unwrap();
+        let schema = source.get_table_schema("empty_stash").unwrap();
+        assert_eq!(schema.len(), 2);
+        assert_eq!(source.table_statistics("empty_stash").unwrap().row_count, 0);
+
+        let duplicated = ResultSet::new(vec![
+            Column::new("id", "INTEGER", 0),
+            Column::new("ID", "TEXT", 1),
+        ]);
+        assert!(matches!(
+            source.register_result_set("dup", &duplicated),
+            Err(DuckDBError::SchemaError(_))
+        ));
+    }
+
+    #[test]
+    fn test_write_table_parquet_and_csv() {
+        let mut result = ResultSet::new(vec![
+            Column::new("id", "INTEGER", 0),
+            Column::new("label", "TEXT", 1),
+        ]);
+        for id in 0..100 {
+            result.add_row(NoctraRow::new(vec![
+                Value::Integer(id),
+                Value::Text(format!("row {}", id)),
+            ]));
+        }
+        let dir = tempfile::tempdir().unwrap();
+        let mut source = DuckDBSource::new_in_memory().unwrap();
+
+        let parquet = dir.path().join("out.parquet");
+        let options = HashMap::from([
+            ("compression".to_string(), "zstd".to_string()),
+            ("row_group_size".to_string(), "65536".to_string()),
+        ]);
+        let format = WriteFormat::Parquet(ParquetWriteOptions::from_options(&options).unwrap());
+        let written = source
+            .write_table(&result, parquet.to_str().unwrap(), format)
+            .unwrap();
+        assert_eq!(written, 100);
+
+        let csv = dir.path().join("out.csv");
+        let format = WriteFormat::Csv(CsvWriteOptions {
+            delimiter: ';',
+            header: true,
+        });
+        assert_eq!(
+            source
+                .write_table(&result, csv.to_str().unwrap(), format)
+                .unwrap(),
+            100
+        );
+        assert!(std::fs::read_to_string(&csv)
+            .unwrap()
+            .starts_with("id;label\n0;row 0\n"));
+
+        // The scratch table is not left registered
+        assert!(!source.registered_files.contains_key("noctra_write_table"));
+
+        let mut reader = DuckDBSource::new_in_memory().unwrap();
+        reader
+            .register_file(parquet.to_str().unwrap(), "written", false)
+            .unwrap();
+        let count = reader
+            .query("SELECT COUNT(*) FROM written", &Parameters::new())
+            .unwrap();
+        assert_eq!(count.rows[0].values[0], Value::Integer(100));
+
+        let bad = HashMap::from([("compression".to_string(), "rar".to_string())]);
+        assert!(matches!(
+            ParquetWriteOptions::from_options(&bad),
+            Err(DuckDBError::InvalidOption(_))
+        ));
+    }
+
+    #[test]
+    fn test_register_excel_sheets() {
+        let workbook = crate::excel::tests::two_sheet_fixture();
+        let path = workbook.path().to_str().unwrap();
+
+        let mut source = DuckDBSource::new_in_memory().unwrap();
+        let mut options = HashMap::new();
+        options.insert("sheet".to_string(), "'Q3'".to_string());
+        let warnings = source
+            .register_file_with_options(path, "rep", &options)
+            .unwrap();
+        assert!(warnings.is_empty());
+
+        let result = source
+            .query(
+                "SELECT region, units FROM rep WHERE revenue > 1000",
+                &Parameters::new(),
+            )
+            .unwrap();
+        assert_eq!(result.rows.len(), 1);
+        assert_eq!(result.rows[0].values[0], Value::Text("North".to_string()));
+
+        let tables = source.schema().unwrap();
+        let types: Vec<&str> = tables[0]
+            .columns
+            .iter()
+            .map(|c| c.data_type.as_str())
+            .collect();
+        assert_eq!(
+            types,
+            vec!["VARCHAR", "BIGINT", "DOUBLE", "BOOLEAN", "DATE"]
+        );
+
+        let warnings = source
+            .register_excel(path, "notes", Some("Notes"), true)
+            .unwrap();
+        assert_eq!(warnings.len(), 1);
+        assert_eq!(source.get_table_schema("notes").unwrap()[1].name, "col_2");
+        assert_eq!(
+            source.registered_files().get("notes").map(String::as_str),
+            Some(path)
+        );
+    }
+
+    #[test]
+    fn test_query_csv_data() {
+        let mut temp_file = tempfile::Builder::new().suffix(".csv").tempfile().unwrap();
+        writeln!(temp_file, "name,age").unwrap();
+        writeln!(temp_file, "Alice,30").unwrap();
+        writeln!(temp_file, "Bob,25").unwrap();
+        temp_file.flush().unwrap();
+
+        let mut source = DuckDBSource::new_in_memory().unwrap();
+        source
+            .register_file(temp_file.path().to_str().unwrap(), "people", false)
+            .unwrap();
+
+        let result = source.query("SELECT * FROM people", &Parameters::new()).unwrap();
+        assert_eq!(result.rows.len(), 2);
+        assert_eq!(result.columns.len(), 2);
+        assert_eq!(result.columns[0].name, "name");
+        assert_eq!(result.columns[1].name, "age");
+    }
+
+    /// The legacy `CsvDataSource` (and its hand-written GROUP BY) is gone;
+    /// grouped aggregations over CSV files run in DuckDB
+    #[test]
+    fn test_csv_group_by_multiple_keys() {
+        let mut temp_file = tempfile::Builder::new().suffix(".csv").tempfile().unwrap();
+        writeln!(temp_file, "name,dept,city,salary").unwrap();
+        writeln!(temp_file, "Alice,eng,NYC,100").unwrap();
+        writeln!(temp_file, "Bob,eng,LA,80").unwrap();
+        writeln!(temp_file, "Carol,eng,NYC,120").unwrap();
+        writeln!(temp_file, "Dave,sales,LA,60").unwrap();
+        writeln!(temp_file, "Eve,sales,LA,70").unwrap();
+        temp_file.flush().unwrap();
+
+        let mut source = DuckDBSource::new_in_memory().unwrap();
+        source
+            .register_file(temp_file.path().to_str().unwrap(), "employees", false)
+            .unwrap();
+
+        let result = source
+            .query(
+                "SELECT dept, COUNT(*), AVG(salary) FROM employees GROUP BY dept ORDER BY dept",
+                &Parameters::new(),
+            )
+            .unwrap();
+        assert_eq!(result.columns.len(), 3);
+        assert_eq!(
+            result
+                .rows
+                .iter()
+                .map(|r| r.values.clone())
+                .collect::<Vec<_>>(),
+            vec![
+                vec![
+                    Value::Text("eng".to_string()),
+                    Value::Integer(3),
+                    Value::Float(100.0)
+                ],
+                vec![
+                    Value::Text("sales".to_string()),
+                    Value::Integer(2),
+                    Value::Float(65.0)
+                ],
+            ]
+        );
+
+        let result = source
+            .query(
+                "SELECT dept, city, SUM(salary) AS total FROM employees \
+                 GROUP BY dept, city ORDER BY total DESC LIMIT 2",
+                &Parameters::new(),
+            )
+            .unwrap();
+        assert_eq!(result.columns[2].name, "total");
+        assert_eq!(result.rows.len(), 2);
+        assert_eq!(result.rows[0].values[0], Value::Text("eng".to_string()));
+        assert_eq!(result.rows[0].values[1], Value::Text("NYC".to_string()));
+        assert_eq!(result.rows[1].values[1], Value::Text("LA".to_string()));
+    }
+
+    /// WHERE over CSV files runs in DuckDB too: `LIKE`, `ILIKE` and
+    /// `IS [NOT] NULL` need no hand-written matcher
+    #[test]
+    fn test_csv_like_ilike_and_is_null() {
+        let mut temp_file = tempfile::Builder::new().suffix(".csv").tempfile().unwrap();
+        writeln!(temp_file, "name,email").unwrap();
+        writeln!(temp_file, "Alice,alice@example.com").unwrap();
+        writeln!(temp_file, "Anderson,").unwrap();
+        writeln!(temp_file, "bob,bob@example.com").unwrap();
+        writeln!(temp_file, "Jackson,").unwrap();
+        writeln!(temp_file, "Amy_Lee,amy@example.com").unwrap();
+        temp_file.flush().unwrap();
+
+        let mut source = DuckDBSource::new_in_memory().unwrap();
+        source
+            .register_file(temp_file.path().to_str().unwrap(), "t", false)
+            .unwrap();
+        let names = |condition: &str| -> Vec<Value> {
+            source
+                .query(
+                    &format!("SELECT name FROM t WHERE {} ORDER BY name", condition),
+                    &Parameters::new(),
+                )
+                .unwrap()
+                .rows
+                .into_iter()
+                .map(|row| row.values[0].clone())
+                .collect()
+        };
+        let text = |names: &[&str]| -> Vec<Value> {
+            names
+                .iter()
+                .map(|name| Value::Text(name.to_string()))
+                .collect()
+        };
+
+        assert_eq!(
+            names("name LIKE 'A%'"),
+            text(&["Alice", "Amy_Lee", "Anderson"])
+        );
+        assert_eq!(names("name LIKE '%son'"), text(&["Anderson", "Jackson"]));
+        assert_eq!(
+            names("name NOT LIKE '%son'"),
+            text(&["Alice", "Amy_Lee", "bob"])
+        );
+        assert_eq!(names("name LIKE '_ob'"), text(&["bob"]));
+        assert_eq!(names("name LIKE 'Amy\\_%' ESCAPE '\\'"), text(&["Amy_Lee"]));
+        assert_eq!(
+            names("name ILIKE 'a%'"),
+            text(&["Alice", "Amy_Lee", "Anderson"])
+        );
+        assert_eq!(names("name ILIKE 'BOB'"), text(&["bob"]));
+        assert_eq!(names("email IS NULL"), text(&["Anderson", "Jackson"]));
+        assert_eq!(
+            names("email IS NOT NULL AND name LIKE 'A%'"),
+            text(&["Alice", "Amy_Lee"])
+        );
+    }
+
+    #[test]
+    fn test_schema_introspection() {
+        let mut temp_file = tempfile::Builder::new().suffix(".csv").tempfile().unwrap();
+        writeln!(temp_file, "id,name,active").unwrap();
+        writeln!(temp_file, "1,Alice,true").unwrap();
+        temp_file.flush().unwrap();
+
+        let mut source = DuckDBSource::new_in_memory().unwrap();
+        source
+            .register_file(temp_file.path().to_str().unwrap(), "users", false)
+            .unwrap();
+
+        let schema = source.schema().unwrap();
+        // Schema should contain the registered table
+        assert!(!schema.is_empty());
+        let users_table = schema.iter().find(|t| t.name == "users");
+        assert!(users_table.is_some(), "users table should be in schema");
+        let users_table = users_table.unwrap();
+        assert_eq!(users_table.name, "users");
+        // DuckDB may infer different column counts, just check it's > 0
+        assert!(!users_table.columns.is_empty());
+    }
+
+    #[test]
+    fn test_json_support() {
+        let mut temp_file = tempfile::Builder::new().suffix(".json").tempfile().unwrap();
+        writeln!(temp_file, r#"[{{"name": "Alice", "age": 30}}, {{"name": "Bob", "age": 25}}]"#).unwrap();
+        temp_file.flush().unwrap();
+
+        let mut source = DuckDBSource::new_in_memory().unwrap();
+        source
+            .register_file(temp_file.path().to_str().unwrap(), "people", false)
+            .unwrap();
+
+        let result = source
+            .query("SELECT * FROM people", &Parameters::new())
+            .unwrap();
+        assert_eq!(result.rows.len(), 2);
+        assert_eq!(result.columns.len(), 2);
+    }
+
+    #[test]
+    fn test_register_hive_partitioned_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        let sales = dir.path().join("sales");
+        let sales = sales.to_str().unwrap();
+
+        let mut source = DuckDBSource::new_in_memory().unwrap();
+        source
+            .query_with_options(
+                &format!(
+                    "COPY (SELECT 2023 + range % 2 AS year, 1 + range % 3 AS month, range AS amount FROM range(60)) \
+                     TO '{}' (FORMAT PARQUET, PARTITION_BY (year, month))",
+                    sales
+                ),
+                QueryOptions::default(),
+            )
+            .unwrap();
+
+        let mut options = HashMap::new();
+        options.insert("hive_partitioning".to_string(), "true".to_string());
+        source
+            .register_file_with_options(sales, "sales", &options)
+            .unwrap();
+
+        // Partition columns are part of the schema, with inferred types
+        let schema = source.schema().unwrap();
+        let columns = &schema.iter().find(|t| t.name == "sales").unwrap().columns;
+        for partition in ["year", "month"] {
+            let column = columns.iter().find(|c| c.name == partition).unwrap();
+            assert_eq!(column.data_type, "BIGINT");
+        }
+
+        let result = source
+            .query(
+                "SELECT COUNT(*) FROM sales WHERE year = 2024 AND month = 2",
+                &Parameters::new(),
+            )
+            .unwrap();
+        assert_eq!(result.rows[0].values[0], Value::Integer(10));
+
+        // The partition filter is pushed into the scan instead of a FILTER operator
+        let explain = source
+            .query_with_options(
+                "EXPLAIN SELECT amount FROM sales WHERE year = 2024",
+                QueryOptions::default(),
+            )
+            .unwrap();
+        let plan: String = explain
+            .rows
+            .iter()
+            .filter_map(|row| match row.values.last() {
+                Some(Value::Text(text)) => Some(text.as_str()),
+                _ => None,
+            })
+            .collect();
+        assert!(
+            plan.contains("Filters"),
+            "partition filter not pushed down:\n{}",
+            plan
+        );
+        assert!(plan.contains("year"));
+
+        // A glob over the same layout works the same way
+        source
+            .register_glob(
+                &format!("{}/year=2023/*/*.parquet", sales),
+                "sales_2023",
+                true,
+            )
+            .unwrap();
+        let result = source
+            .query("SELECT DISTINCT year FROM sales_2023", &Parameters::new())
+            .unwrap();
+        assert_eq!(result.rows.len(), 1);
+        assert_eq!(result.rows[0].values[0], Value::Integer(2023));
+    }
+
+    #[test]
+    fn test_parquet_support() {
+        // For now, just test that the registration doesn't fail
+        // Full Parquet testing would require creating a Parquet file
+        let mut source = DuckDBSource::new_in_memory().unwrap();
+        // This should not panic even if file doesn't exist (DuckDB handles it)
+        let result = source.register_file("nonexistent.parquet", "test", false);
+        // DuckDB will fail if file doesn't exist, so we expect an error
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_attach_sqlite() {
+        let mut source = DuckDBSource::new_in_memory().unwrap();
+        // Test attaching a non-existent SQLite DB (should fail gracefully)
+        let result = source.attach_sqlite("nonexistent.db", "test");
+        assert!(result.is_err());
+    }
+
+    #[test]
+    fn test_unsupported_file_type() {
+        let mut source = DuckDBSource::new_in_memory().unwrap();
+        let result = source.register_file("test.txt", "invalid", false);
+        assert!(matches!(result, Err(DuckDBError::UnsupportedFileType(_))));
+    }
+
+    #[test]
+    fn test_concurrent_reads() {
+        use std::sync::Arc;
+
+        let mut temp_file = tempfile::Builder::new().suffix(".csv").tempfile().unwrap();
+        writeln!(temp_file, "id,value").unwrap();
+        for i in 0..100 {
+            writeln!(temp_file, "{},{}", i, i * 2).unwrap();
+        }
+        temp_file.flush().unwrap();
+
+        let mut source = DuckDBSource::new_in_memory().unwrap();
+        source
+            .register_file(temp_file.path().to_str().unwrap(), "numbers", false)
+            .unwrap();
+        let source = Arc::new(source);
+
+        let handles: Vec<_> = (0..16)
+            .map(|i| {
+                let source = Arc::clone(&source);
+                std::thread::spawn(move || {
+                    source.query(
+                        &format!("SELECT * FROM numbers WHERE id >= {}", i),
+                        &Parameters::new(),
+                    )
+                })
+            })
+            .collect();
+
+        for (i, handle) in handles.into_iter().enumerate() {
+            let result = handle.join().unwrap().expect("concurrent SELECT failed");
+            assert_eq!(result.rows.len(), 100 - i);
+        }
+
+        assert!(source.read_pool.lock().unwrap().len() <= source.config().pool_size);
+
+        // Writes still work after concurrent reads
+        source
+            .query("CREATE TABLE t AS SELECT 1 AS x", &Parameters::new())
+            .unwrap();
+        let result = source.query("SELECT * FROM t", &Parameters::new()).unwrap();
+        assert_eq!(result.rows.len(), 1);
+    }
+
+    #[test]
+    fn test_join_csv_with_linked_sqlite_table() {
+        let dir = tempfile::tempdir().unwrap();
+        let db_path = dir.path().join("main.db");
+        let backend = SqliteBackend::with_file(db_path.to_str().unwrap()).unwrap();
+        backend
+            .execute_statement(
+                "CREATE TABLE customers (id INTEGER, name TEXT)",
+                &Parameters::new(),
+            )
+            .unwrap();
+        backend
+            .execute_statement(
+                "INSERT INTO customers VALUES (1, 'Alice'), (2, 'Bob')",
+                &Parameters::new(),
+            )
+            .unwrap();
+
+        let mut temp_file = tempfile::Builder::new().suffix(".csv").tempfile().unwrap();
+        writeln!(temp_file, "customer_id,amount").unwrap();
+        writeln!(temp_file, "1,10").unwrap();
+        writeln!(temp_file, "2,5").unwrap();
+        writeln!(temp_file, "1,7").unwrap();
+        temp_file.flush().unwrap();
+
+        let mut source = DuckDBSource::new_in_memory().unwrap();
+        source
+            .register_file(temp_file.path().to_str().unwrap(), "orders", false)
+            .unwrap();
+        let linked = source.link_sqlite_backend(&backend).unwrap();
+        assert_eq!(linked, vec!["sqlite_db.customers".to_string()]);
+
+        let result = source
+            .query(
+                "SELECT c.name, SUM(o.amount)::BIGINT AS total FROM orders o \
+                 JOIN sqlite_db.customers c ON c.id = o.customer_id \
+                 GROUP BY c.name ORDER BY c.name",
+                &Parameters::new(),
+            )
+            .unwrap();
+        assert_eq!(result.rows.len(), 2);
+        assert_eq!(result.rows[0].values[0], Value::Text("Alice".to_string()));
+        assert_eq!(result.rows[0].values[1], Value::Integer(17));
+        assert_eq!(result.rows[1].values[0], Value::Text("Bob".to_string()));
+
+        // SHOW TABLES lists the linked table under the sqlite alias
+        let tables: Vec<String> = source
+            .schema()
+            .unwrap()
+            .into_iter()
+            .map(|t| t.name)
+            .collect();
+        assert!(tables.contains(&"orders".to_string()));
+        assert!(tables.contains(&"sqlite_db.customers".to_string()));
+    }
+
+    #[test]
+    fn test_session_scoped_registrations() {
+        let write_csv = |value: &str| {
+            let mut file = tempfile::Builder::new().suffix(".csv").tempfile().unwrap();
+            writeln!(file, "owner").unwrap();
+            writeln!(file, "{}", value).unwrap();
+            file.flush().unwrap();
+            file
+        };
+        let file_a = write_csv("alice");
+        let file_b = write_csv("bob");
+
+        let source = std::sync::Arc::new(DuckDBSource::new_in_memory().unwrap());
+        let mut sessions = noctra_core::SessionManager::new(Default::default());
+        let hook_source = source.clone();
+        sessions.on_session_removed(move |id| hook_source.drop_session(id).unwrap());
+
+        let a = sessions.create().unwrap().lock().unwrap().clone();
+        let b = sessions.create().unwrap().lock().unwrap().clone();
+        source
+            .register_file_scoped(a.id(), file_a.path().to_str().unwrap(), "data")
+            .unwrap();
+        source
+            .register_file_scoped(b.id(), file_b.path().to_str().unwrap(), "data")
+            .unwrap();
+
+        let owner = |session_id: &str| {
+            let result = source
+                .query_in_session(session_id, "SELECT owner FROM data", &Parameters::new())
+                .unwrap();
+            result.rows[0].values[0].clone()
+        };
+        assert_eq!(owner(a.id()), Value::Text("alice".to_string()));
+        assert_eq!(owner(b.id()), Value::Text("bob".to_string()));
+
+        // Table names are quoted, so any name works
+        source
+            .register_file_scoped(b.id(), file_b.path().to_str().unwrap(), "mis \"datos\"")
+            .unwrap();
+        let result = source
+            .query_in_session(
+                b.id(),
+                "SELECT owner FROM \"mis \"\"datos\"\"\"",
+                &Parameters::new(),
+            )
+            .unwrap();
+        assert_eq!(result.rows[0].values[0], Value::Text("bob".to_string()));
+
+        // Outside any session the scoped tables are not visible
+        assert!(source
+            .query("SELECT owner FROM data", &Parameters::new())
+            .is_err());
+
+        // Removing the session drops its schema; the other session is untouched
+        sessions.remove_session(a.id());
+        assert!(source.session_files(a.id()).is_empty());
+        assert!(source
+            .query_in_session(a.id(), "SELECT owner FROM data", &Parameters::new())
+            .is_err());
+        assert_eq!(owner(b.id()), Value::Text("bob".to_string()));
+    }
+
+    #[test]
+    fn test_dry_run_uses_explain() {
+        let source = DuckDBSource::new_in_memory().unwrap();
+        source
+            .query_with_options(
+                "CREATE TABLE items AS SELECT range AS id FROM range(100)",
+                QueryOptions::default(),
+            )
+            .unwrap();
+
+        let plan = source
+            .dry_run("SELECT id FROM items WHERE id > $min")
+            .unwrap();
+        assert!(!plan.is_mutating);
+        assert_eq!(plan.referenced_tables, vec!["items".to_string()]);
+        assert_eq!(plan.parameter_names, vec!["min".to_string()]);
+
+        let plan = source.dry_run("SELECT * FROM items").unwrap();
+        assert_eq!(plan.estimated_row_count, Some(100));
+
+        let plan = source.dry_run("DELETE FROM items WHERE id < 10").unwrap();
+        assert!(plan.is_mutating);
+        let count = source
+            .query("SELECT COUNT(*) FROM items", &Parameters::new())
+            .unwrap();
+        assert_eq!(count.rows[0].values[0], Value::Integer(100));
+
+        let err = source.dry_run("SELECT * FROM missing").unwrap_err();
+        assert!(matches!(
+            err,
+            noctra_core::error::NoctraError::DryRunFailed(_)
+        ));
+    }
+
+    #[test]
+    fn test_parse_analyze_json_builds_operator_tree() {
+        let json = r#"{
+            "query_name": "SELECT name FROM items WHERE id > 10",
+            "latency": 0.0021,
+            "rows_returned": 89,
+            "children": [{
+                "operator_name": "EXPLAIN_ANALYZE",
+                "operator_cardinality": 0,
+                "operator_timing": 0.0,
+                "extra_info": {},
+                "children": [{
+                    "operator_name": "PROJECTION",
+                    "operator_cardinality": 89,
+                    "operator_timing": 0.00001,
+                    "extra_info": {"Projections": "name", "Estimated Cardinality": "20"},
+                    "children": [{
+                        "operator_name": "TABLE_SCAN",
+                        "operator_cardinality": 89,
+                        "operator_timing": 0.0015,
+                        "extra_info": {"Table": "items", "Filters": "id>10", "Estimated Cardinality": "20"},
+                        "children": []
+                    }]
+                }]
+            }]
+        }"#;
+
+        let root = parse_analyze_json(json).unwrap();
+        assert_eq!(root.operator, "PROJECTION");
+        assert_eq!(root.estimated_rows, 20);
+        assert_eq!(root.actual_rows, 89);
+        assert_eq!(root.children.len(), 1);
+
+        let scan = &root.children[0];
+        assert_eq!(scan.operator, "TABLE_SCAN");
+        assert_eq!(scan.actual_rows, 89);
+        assert!((scan.actual_time_ms - 1.5).abs() < 1e-9);
+        assert!(scan.children.is_empty());
+
+        let lines = root.tree_lines();
+        assert_eq!(lines.len(), 2);
+        assert!(lines[1].starts_with("  TABLE_SCAN"));
+
+        // Older profiles: `name`/`cardinality`/`timing`, estimate in the text
+        let legacy = r#"{"name": "Query", "children": [{"name": "SEQ_SCAN ", "timing": 0.002, "cardinality": 5, "extra_info": "items\n\nEC: 7", "children": []}]}"#;
+        let root = parse_analyze_json(legacy).unwrap();
+        assert_eq!(root.operator, "SEQ_SCAN");
+        assert_eq!(root.estimated_rows, 7);
+        assert_eq!(root.actual_rows, 5);
+        assert!((root.actual_time_ms - 2.0).abs() < 1e-9);
+
+        assert!(parse_analyze_json(r#"{"children": []}"#).is_err());
+        assert!(parse_analyze_json("not json").is_err());
+    }
+
+    #[test]
+    fn test_profile_summarizes_each_column() {
+        let source = DuckDBSource::new_in_memory().unwrap();
+        source
+            .query_with_options(
+                "CREATE TABLE people AS SELECT range AS id, 'name_' || range AS name, NULL::DOUBLE AS score FROM range(50)",
+                QueryOptions::default(),
+            )
+            .unwrap();
+
+        let profile = source.profile("people").unwrap();
+        assert_eq!(profile.rows.len(), 3);
+
+        let names: Vec<&str> = profile.columns.iter().map(|c| c.name.as_str()).collect();
+        for expected in [
+            "column_name",
+            "column_type",
+            "min",
+            "max",
+            "approx_unique",
+            "null_percentage",
+        ] {
+            assert!(
+                names.contains(&expected),
+                "missing statistic column {}",
+                expected
+            );
+        }
+        assert_eq!(profile.rows[0].values[0], Value::Text("id".to_string()));
+
+        assert!(source.profile("missing").is_err());
+        assert!(source.profile("(SELECT 1)").is_err());
+    }
+
+    #[test]
+    fn test_sample_returns_requested_rows_from_csv() {
+        let temp_file = tempfile::Builder::new().suffix(".csv").tempfile().unwrap();
+        let rows: String = (0..1000).map(|i| format!("{},item_{}\n", i, i)).collect();
+        std::fs::write(temp_file.path(), format!("id,name\n{}", rows)).unwrap();
+
+        let mut source = DuckDBSource::new_in_memory().unwrap();
+        source
+            .register_file(temp_file.path().to_str().unwrap(), "items", false)
+            .unwrap();
+
+        let sample = DataSource::sample(&source, "items", 5).unwrap();
+        assert_eq!(sample.rows.len(), 5);
+        assert_eq!(sample.columns.len(), 2);
+        assert!(source.sample("missing", 5).is_err());
+
+        // The name is an identifier, not SQL spliced into the query
+        assert!(source.sample("items LIMIT 1; --", 5).is_err());
+    }
+
+    #[test]
+    fn test_session_functions_filter_by_variable() {
+        use noctra_core::executor::{Executor, RqlQuery};
+
+        let source = DuckDBSource::new_in_memory().unwrap();
+        source
+            .query_with_options(
+                "CREATE TABLE employees AS SELECT * FROM (VALUES ('ana', 'eng'), ('luis', 'ops'), ('eva', 'eng')) t(name, dept)",
+                QueryOptions::default(),
+            )
+            .unwrap();
+
+        let backend = SqliteBackend::with_file(":memory:").unwrap();
+        let mut executor = Executor::new(std::sync::Arc::new(backend));
+        executor
+            .register_source("duck".to_string(), Box::new(source))
+            .unwrap();
+
+        let mut session = Session::new();
+        session.set_variable("dept", "eng");
+        let sql = "SELECT name FROM employees WHERE dept = noctra_var('dept') ORDER BY name";
+        let result = executor.execute_rql(&session, RqlQuery::sql(sql)).unwrap();
+        let names: Vec<Value> = result
+            .rows
+            .iter()
+            .map(|row| row.values[0].clone())
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                Value::Text("ana".to_string()),
+                Value::Text("eva".to_string())
+            ]
+        );
+
+        // LET mid-session: the next query sees the new value
+        session.set_variable("dept", "ops");
+        let result = executor.execute_rql(&session, RqlQuery::sql(sql)).unwrap();
+        assert_eq!(result.rows.len(), 1);
+        assert_eq!(result.rows[0].values[0], Value::Text("luis".to_string()));
+
+        session.remove_variable("dept");
+        let result = executor.execute_rql(&session, RqlQuery::sql(sql)).unwrap();
+        assert!(result.rows.is_empty());
+    }
+
+    #[test]
+    fn test_executor_runs_pivot_on_duckdb() {
+        use noctra_core::executor::{Executor, RqlQuery};
+
+        let source = DuckDBSource::new_in_memory().unwrap();
+        source
+            .query_with_options(
+                "CREATE TABLE sales AS SELECT * FROM (VALUES ('ar', 2023, 10), ('ar', 2024, 15), ('uy', 2024, 7)) t(country, year, amount)",
+                QueryOptions::default(),
+            )
+            .unwrap();
+
+        let backend = SqliteBackend::with_file(":memory:").unwrap();
+        let mut executor = Executor::new(std::sync::Arc::new(backend));
+        executor
+            .register_source("duck".to_string(), Box::new(source))
+            .unwrap();
+
+        // Flagged DuckDB-specific SQL reaches DuckDB as written
+        let sql = "PIVOT sales ON year USING sum(amount) GROUP BY country ORDER BY country";
+        let result = executor
+            .execute_rql(
+                &Session::new(),
+                RqlQuery::sql(sql).with_duckdb_specific(true),
+            )
+            .unwrap();
+        let columns: Vec<&str> = result.columns.iter().map(|c| c.name.as_str()).collect();
+        assert_eq!(columns, vec!["country", "2023", "2024"]);
+        assert_eq!(result.rows.len(), 2);
+    }
+
+    #[test]
+    fn test_executor_binds_parameters() {
+        use noctra_core::executor::{Executor, RqlQuery};
+
+        let source = DuckDBSource::new_in_memory().unwrap();
+        source
+            .query_with_options(
+                "CREATE TABLE employees AS SELECT * FROM (VALUES ('ana', 'eng', 3), ('luis', 'ops', 5), ('eva', 'eng', 7)) t(name, dept, level)",
+                QueryOptions::default(),
+            )
+            .unwrap();
+
+        let backend = SqliteBackend::with_file(":memory:").unwrap();
+        let mut executor = Executor::new(std::sync::Arc::new(backend));
+        executor
+            .register_source("duck".to_string(), Box::new(source))
+            .unwrap();
+        let session = Session::new();
+
+        let mut params = Parameters::new();
+        params.insert("dept".to_string(), Value::Text("eng".to_string()));
+        params.insert("1".to_string(), Value::Integer(5));
+        params.insert("2".to_string(), Value::Text("o'brien".to_string()));
+        let names = |sql: &str| -> Vec<Value> {
+            let result = executor
+                .execute_rql(&session, RqlQuery::new(sql, params.clone()))
+                .unwrap();
+            result
+                .rows
+                .iter()
+                .map(|row| row.values[0].clone())
+                .collect()
+        };
+        let text = |name: &str| Value::Text(name.to_string());
+
+        // Named (DuckDB has no `:name` syntax of its own)
+        assert_eq!(
+            names("SELECT name FROM employees WHERE dept = :dept ORDER BY name"),
+            vec![text("ana"), text("eva")]
+        );
+        // Positional
+        assert_eq!(
+            names("SELECT name FROM employees WHERE level >= ? AND name <> ? ORDER BY name"),
+            vec![text("eva"), text("luis")]
+        );
+        // Mixed
+        assert_eq!(
+            names("SELECT name FROM employees WHERE dept = :dept AND level > $1"),
+            vec![text("eva")]
+        );
+
+        let err = executor
+            .execute_rql(
+                &session,
+                RqlQuery::sql("SELECT * FROM employees WHERE dept = :dept AND level = ?"),
+            )
+            .unwrap_err();
+        assert!(
+            matches!(err, noctra_core::error::NoctraError::MissingParameter(ref names) if names == "dept, ?1")
+        );
     }
 
-    /// Create a new DuckDB source with persistent database file
-    pub fn new_with_file<P: AsRef<Path>>(path: P) -> Result<Self> {
-        let conn = Connection::open(path)?;
-        Ok(Self {
-            conn: Mutex::new(conn),
-            name: "duckdb".to_string(),
-            registered_files: HashMap::new(),
-        })
+    #[test]
+    fn test_cast_placeholders() {
+        assert_eq!(
+            cast_placeholders("SELECT * FROM t WHERE id = $1"),
+            "SELECT * FROM t WHERE id = $1"
+        );
+        assert_eq!(
+            cast_placeholders("SELECT * FROM t WHERE id = $1::INTEGER AND total > $12::DECIMAL(10, 2)"),
+            "SELECT * FROM t WHERE id = CAST($1 AS INTEGER) AND total > CAST($12 AS DECIMAL(10, 2))"
+        );
+        assert_eq!(
+            cast_placeholders("SELECT '$1::INT', created::DATE, $2::int[] FROM t"),
+            "SELECT '$1::INT', created::DATE, CAST($2 AS int[]) FROM t"
+        );
     }
 
-    /// Register a file as a virtual table using DuckDB's read_*_auto functions
-    pub fn register_file(&mut self, file_path: &str, alias: &str) -> Result<()> {
-        let extension = std::path::Path::new(file_path)
-            .extension()
-            .and_then(|e| e.to_str())
-            .unwrap_or("")
-            .to_lowercase();
-
-        let sql = match extension.as_str() {
-            "csv" => format!(
-                "CREATE OR REPLACE VIEW {} AS SELECT * FROM read_csv_auto('{}')",
-                alias, file_path
-            ),
-            "json" => format!(
-                "CREATE OR REPLACE VIEW {} AS SELECT * FROM read_json_auto('{}')",
-                alias, file_path
-            ),
-            "parquet" => format!(
-                "CREATE OR REPLACE VIEW {} AS SELECT * FROM read_parquet('{}')",
-                alias, file_path
-            ),
-            _ => return Err(DuckDBError::UnsupportedFileType(extension)),
-        };
+    #[test]
+    fn test_positional_parameter_cast_coerces_text() {
+        let source = DuckDBSource::new_in_memory().unwrap();
+        source
+            .query_with_options(
+                "CREATE TABLE t AS SELECT * FROM (VALUES (41, 'a'), (42, 'b')) v(id, name)",
+                QueryOptions::default(),
+            )
+            .unwrap();
 
-        log::debug!("Registering file: {} -> {}", file_path, sql);
-        let conn = self.conn.lock().map_err(|_| DuckDBError::QueryFailed("Mutex poisoned".to_string()))?;
-        conn.execute(&sql, [])?;
-        self.registered_files.insert(alias.to_string(), file_path.to_string());
-        Ok(())
+        let mut params = Parameters::new();
+        params.insert("1".to_string(), Value::Text("42".to_string()));
+        let result = source
+            .query("SELECT name FROM t WHERE id = $1::INTEGER", &params)
+            .unwrap();
+        assert_eq!(result.rows.len(), 1);
+        assert_eq!(result.rows[0].values[0], Value::Text("b".to_string()));
     }
 
-    /// Attach a SQLite database to DuckDB for cross-source queries
-    pub fn attach_sqlite(&mut self, db_path: &str, alias: &str) -> Result<()> {
-        let conn = self.conn.lock().map_err(|_| DuckDBError::QueryFailed("Mutex poisoned".to_string()))?;
-        let sql = format!("ATTACH '{}' AS {} (TYPE SQLITE)", db_path, alias);
-        log::debug!("Attaching SQLite DB: {}", sql);
-        conn.execute(&sql, [])?;
-        Ok(())
+    #[test]
+    fn test_session_helper_functions() {
+        let source = DuckDBSource::new_in_memory().unwrap();
+        let mut session = Session::new();
+        session.set_variable("user", "auditor");
+        session.set_variable("limit", 5i64);
+        source.register_session_functions(&session).unwrap();
+
+        let result = source
+            .query_in_session(
+                session.id(),
+                "SELECT noctra_user(), noctra_var('limit'), noctra_var('missing'), noctra_now() IS NOT NULL",
+                &Parameters::new(),
+            )
+            .unwrap();
+        assert_eq!(result.rows[0].values[0], Value::Text("auditor".to_string()));
+        assert_eq!(result.rows[0].values[1], Value::Text("5".to_string()));
+        assert_eq!(result.rows[0].values[2], Value::Null);
+        assert_eq!(result.rows[0].values[3], Value::Boolean(true));
     }
 
-    /// Get registered files
-    pub fn registered_files(&self) -> &HashMap<String, String> {
-        &self.registered_files
+    #[test]
+    fn test_session_variables_are_isolated_per_session() {
+        let source = DuckDBSource::new_in_memory().unwrap();
+        let mut sessions = Vec::new();
+        for region in ["norte", "sur"] {
+            let mut session = Session::new();
+            session.set_variable("region", region);
+            source.register_session_functions(&session).unwrap();
+            sessions.push(session);
+        }
+
+        // Publishing the second session does not overwrite the first one
+        for (session, region) in sessions.iter().zip(["norte", "sur"]) {
+            let result = source
+                .query_in_session(
+                    session.id(),
+                    "SELECT noctra_var('region')",
+                    &Parameters::new(),
+                )
+                .unwrap();
+            assert_eq!(result.rows[0].values[0], Value::Text(region.to_string()));
+        }
+
+        // Pooled handles are reset: a query without a session sees nothing
+        let result = source
+            .query("SELECT noctra_var('region')", &Parameters::new())
+            .unwrap();
+        assert_eq!(result.rows[0].values[0], Value::Null);
+
+        // Nothing was written to the database
+        let result = source
+            .query(
+                "SELECT count(*) FROM duckdb_schemas() WHERE schema_name = 'noctra'",
+                &Parameters::new(),
+            )
+            .unwrap();
+        assert_eq!(result.rows[0].values[0], Value::Integer(0));
     }
 
-    /// Convert DuckDB row to Noctra Row
-    fn duckdb_row_to_noctra_row(&self, row: &Row, columns: &[Column]) -> DuckResult<NoctraRow> {
-        let mut values = Vec::new();
+    #[test]
+    fn test_session_functions_skip_read_only_database() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("ro.duckdb");
+        DuckDBSource::new_with_file(&path).unwrap();
 
-        for idx in 0..columns.len() {
-            // Try different types in order of preference
-            // First try as integer
-            if let Ok(val) = row.get::<_, Option<i64>>(idx) {
-                values.push(val.map(Value::Integer).unwrap_or(Value::Null));
-                continue;
-            }
+        let source = DuckDBSource::new_read_only(&path).unwrap();
+        let mut session = Session::new();
+        session.set_variable("region", "norte");
+        source.register_session_functions(&session).unwrap();
+        assert!(source
+            .query_in_session(session.id(), "SELECT 1", &Parameters::new())
+            .is_ok());
+    }
 
-            // Then try as float
-            if let Ok(val) = row.get::<_, Option<f64>>(idx) {
-                values.push(val.map(Value::Float).unwrap_or(Value::Null));
-                continue;
-            }
+    #[test]
+    fn test_cost_based_routing_between_sqlite_and_duckdb() {
+        use noctra_core::query_engine::{QueryEngine, RoutingStrategy};
 
-            // Then try as boolean
-            if let Ok(val) = row.get::<_, Option<bool>>(idx) {
-                values.push(val.map(Value::Boolean).unwrap_or(Value::Null));
-                continue;
-            }
+        let dir = tempfile::tempdir().unwrap();
+        let parquet = dir.path().join("events.parquet");
+        let parquet = parquet.to_str().unwrap();
 
-            // Finally try as string
-            if let Ok(val) = row.get::<_, Option<String>>(idx) {
-                values.push(val.map(Value::Text).unwrap_or(Value::Null));
-                continue;
+        let mut source = DuckDBSource::new_in_memory().unwrap();
+        source
+            .query_with_options(
+                &format!(
+                    "COPY (SELECT range AS id, range % 7 AS kind FROM range(200000)) TO '{}' (FORMAT PARQUET)",
+                    parquet
+                ),
+                QueryOptions::default(),
+            )
+            .unwrap();
+        source.register_file(parquet, "events", false).unwrap();
+        source.table_statistics("events").unwrap();
+
+        let sqlite = SqliteBackend::with_file(":memory:").unwrap();
+        sqlite
+            .execute_statement(
+                "CREATE TABLE users (id INTEGER PRIMARY KEY, name TEXT)",
+                &Parameters::new(),
+            )
+            .unwrap();
+
+        let mut engine = QueryEngine::new(RoutingStrategy::Primary);
+        engine.add_backend("sqlite", std::sync::Arc::new(sqlite));
+        engine.add_backend("duckdb", std::sync::Arc::new(source));
+        engine.set_strategy(RoutingStrategy::CostBased);
+
+        let group_by = "SELECT kind, COUNT(*) FROM events GROUP BY kind";
+        assert_eq!(engine.select_backend(group_by).unwrap(), "duckdb");
+        let result = engine.execute(group_by, &Parameters::new()).unwrap();
+        assert_eq!(result.rows.len(), 7);
+
+        let lookup = "SELECT name FROM users WHERE id = 1";
+        assert_eq!(engine.select_backend(lookup).unwrap(), "sqlite");
+        assert!(engine.execute(lookup, &Parameters::new()).is_ok());
+    }
+
+    #[test]
+    fn test_executor_writes_to_materialized_table() {
+        use noctra_core::executor::{Executor, RqlQuery};
+
+        let mut source = DuckDBSource::new_in_memory().unwrap();
+        source
+            .materialize("staging", "SELECT 1 AS id, 'ana' AS name")
+            .unwrap();
+
+        let backend = SqliteBackend::with_file(":memory:").unwrap();
+        let mut executor = Executor::new(std::sync::Arc::new(backend));
+        executor
+            .register_source("duck".to_string(), Box::new(source))
+            .unwrap();
+        let session = Session::new();
+
+        let mut params = Parameters::new();
+        params.insert("name".to_string(), Value::Text("luis".to_string()));
+        let write = executor
+            .execute_write(
+                &session,
+                RqlQuery::new("INSERT INTO staging VALUES (2, :name), (3, 'eva')", params),
+            )
+            .unwrap();
+        assert_eq!(
+            write,
+            WriteResult {
+                rows_affected: 2,
+                last_insert_rowid: None
             }
+        );
 
-            // If all else fails, use Null
-            values.push(Value::Null);
-        }
+        // execute_rql routes writes the same way
+        let result = executor
+            .execute_rql(&session, RqlQuery::sql("DELETE FROM staging WHERE id = 1"))
+            .unwrap();
+        assert!(result.columns.is_empty());
+        assert_eq!(result.rows_affected, Some(1));
 
-        Ok(NoctraRow { values })
+        let result = executor
+            .execute_rql(
+                &session,
+                RqlQuery::sql("SELECT name FROM staging ORDER BY id"),
+            )
+            .unwrap();
+        let names: Vec<Value> = result
+            .rows
+            .iter()
+            .map(|row| row.values[0].clone())
+            .collect();
+        assert_eq!(
+            names,
+            vec![
+                Value::Text("luis".to_string()),
+                Value::Text("eva".to_string())
+            ]
+        );
     }
 
-    /// Get table schema from DuckDB information_schema
-    fn get_table_schema(&self, table_name: &str) -> Result<Vec<ColumnInfo>> {
-        let conn = self.conn.lock().map_err(|_| DuckDBError::QueryFailed("Mutex poisoned".to_string()))?;
+    #[test]
+    fn test_vacuum_shrinks_database_after_bulk_delete() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("events.duckdb");
+        let source = DuckDBSource::new_with_file(&path).unwrap();
+        // Blocks holding data; freed blocks stay in the file for reuse
+        let used_blocks = |source: &DuckDBSource| {
+            let size = source
+                .query_with_options(
+                    "SELECT used_blocks FROM pragma_database_size()",
+                    QueryOptions::default(),
+                )
+                .unwrap();
+            match size.rows[0].values[0] {
+                Value::Integer(blocks) => blocks,
+                ref other => panic!("unexpected used_blocks: {:?}", other),
+            }
+        };
+        source
+            .query_with_options(
+                "CREATE TABLE events AS SELECT range AS id, repeat('x', 200) AS payload FROM range(500000)",
+                QueryOptions::default(),
+            )
+            .unwrap();
+        source.checkpoint().unwrap();
+        let before = used_blocks(&source);
 
-        // Use PRAGMA table_info for DuckDB views
-        let sql = "PRAGMA table_info(?)";
+        source
+            .query_with_options(
+                "DELETE FROM events WHERE id >= 1000",
+                QueryOptions::default(),
+            )
+            .unwrap();
+        assert_eq!(source.pending_writes(), 1);
+        source.vacuum().unwrap();
+        assert_eq!(source.pending_writes(), 0);
 
-        let mut stmt = conn.prepare(sql).map_err(|e| DuckDBError::QueryFailed(format!("Prepare error: {}", e)))?;
-        let rows = stmt.query_map(params![table_name], |row| {
-            let name: String = row.get(1)?; // column name
-            let data_type: String = row.get(2)?; // data type
-            let notnull: i32 = row.get(3)?; // not null flag
-            Ok((name, data_type, notnull == 0)) // nullable if notnull == 0
-        }).map_err(|e| DuckDBError::QueryFailed(format!("Query map error: {}", e)))?;
+        // The fully deleted row groups no longer take space
+        let after = used_blocks(&source);
+        assert!(
+            after < before,
+            "database did not shrink: {} -> {} blocks",
+            before,
+            after
+        );
 
-        let mut columns = Vec::new();
-        for row_result in rows {
-            let (name, data_type, nullable) = row_result.map_err(|e| DuckDBError::QueryFailed(format!("Row get error: {}", e)))?;
-            columns.push(ColumnInfo {
-                name,
-                data_type: data_type.to_uppercase(),
-                nullable,
-                default_value: None,
+        // Everything is in the database file: the log holds nothing to replay
+        let wal = dir.path().join("events.duckdb.wal");
+        assert!(std::fs::metadata(&wal).map_or(true, |meta| meta.len() == 0));
+        drop(source);
+        let reopened = DuckDBSource::new_with_file(&path).unwrap();
+        let count = reopened
+            .query_with_options("SELECT COUNT(*) FROM events", QueryOptions::default())
+            .unwrap();
+        assert_eq!(count.rows[0].values[0], Value::Integer(1000));
+    }
+
+    #[test]
+    fn test_auto_checkpoint_interval() {
+        let source = DuckDBSource::new_in_memory()
+            .unwrap()
+            .with_config(DuckDBConfig {
+                auto_checkpoint_interval: 3,
+                ..DuckDBConfig::default()
             });
+        source
+            .query_with_options("CREATE TABLE t (id INTEGER)", QueryOptions::default())
+            .unwrap();
+        source
+            .query_with_options("INSERT INTO t VALUES (1)", QueryOptions::default())
+            .unwrap();
+        assert_eq!(source.pending_writes(), 2);
+
+        // Third write reaches the interval and checkpoints
+        source
+            .bulk_insert(
+                "t",
+                &[NoctraRow {
+                    values: vec![Value::Integer(2)],
+                }],
+            )
+            .unwrap();
+        assert_eq!(source.pending_writes(), 0);
+
+        // Reads and failed writes don't count
+        source.query("SELECT * FROM t", &Parameters::new()).unwrap();
+        assert!(source
+            .query_with_options("INSERT INTO missing VALUES (1)", QueryOptions::default())
+            .is_err());
+        assert_eq!(source.pending_writes(), 0);
+    }
+
+    #[test]
+    fn test_checkpoint_read_only_database() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("archive.duckdb");
+        {
+            let source = DuckDBSource::new_with_file(&path).unwrap();
+            source
+                .query_with_options("CREATE TABLE t AS SELECT 1 AS id", QueryOptions::default())
+                .unwrap();
         }
 
-        Ok(columns)
+        let source = DuckDBSource::new_read_only(&path).unwrap();
+        assert!(source.is_read_only_database());
+        let err = source.checkpoint().unwrap_err();
+        assert!(matches!(err, DuckDBError::ReadOnly(_)));
+        assert_eq!(err.code(), "25006");
+        assert!(matches!(source.vacuum(), Err(DuckDBError::ReadOnly(_))));
+
+        let err = DataSource::checkpoint(&source).unwrap_err();
+        assert_eq!(err.backend_code(), Some("25006"));
     }
-}
 
-impl DataSource for DuckDBSource {
-    fn query(&self, sql: &str, _parameters: &Parameters) -> noctra_core::error::Result<ResultSet> {
-        log::debug!("Executing query: {}", sql);
+    #[test]
+    fn test_link_in_memory_sqlite_fails() {
+        let backend = SqliteBackend::with_file(":memory:").unwrap();
+        let mut source = DuckDBSource::new_in_memory().unwrap();
+        assert!(source.link_sqlite_backend(&backend).is_err());
+    }
 
-        let conn = self.conn.lock().map_err(|_| noctra_core::error::NoctraError::Internal("Mutex poisoned".to_string()))?;
+    #[test]
+    fn test_query_result_row_limit() {
+        let source = DuckDBSource::new_in_memory()
+            .unwrap()
+            .with_config(DuckDBConfig {
+                max_result_rows: 10,
+                ..DuckDBConfig::default()
+            });
 
-        // Prepare and execute query
-        let mut stmt = conn.prepare(sql).map_err(|e| noctra_core::error::NoctraError::Internal(format!("DuckDB prepare error: {}", e)))?;
-        let mut rows_result = stmt
-            .query([])
-            .map_err(|e| noctra_core::error::NoctraError::Internal(format!("DuckDB query error: {}", e)))?;
+        let result = source
+            .query("SELECT * FROM range(25)", &Parameters::new())
+            .unwrap();
+        assert_eq!(result.rows.len(), 10);
+        assert!(result.truncated);
 
-        // Get column metadata from first row (if exists)
-        let mut columns: Vec<Column> = Vec::new();
-        let mut rows: Vec<NoctraRow> = Vec::new();
+        // Exactly at the limit: nothing was dropped
+        let result = source
+            .query("SELECT * FROM range(10)", &Parameters::new())
+            .unwrap();
+        assert_eq!(result.rows.len(), 10);
+        assert!(!result.truncated);
 
-        if let Some(row) = rows_result.next().map_err(|e| noctra_core::error::NoctraError::Internal(format!("DuckDB row error: {}", e)))? {
-            // Extract column names from the statement after query execution
-            let column_count = row.as_ref().column_count();
-            for idx in 0..column_count {
-                let name = row.as_ref().column_name(idx)
-                    .map_err(|e| noctra_core::error::NoctraError::Internal(format!("Column name error: {}", e)))?;
-                columns.push(Column {
-                    name: name.to_string(),
-                    data_type: "UNKNOWN".to_string(),
-                    ordinal: idx,
-                });
-            }
+        let result = source
+            .query("SELECT * FROM range(3)", &Parameters::new())
+            .unwrap();
+        assert_eq!(result.rows.len(), 3);
+        assert!(!result.truncated);
 
-            // Convert first row
-            rows.push(self.duckdb_row_to_noctra_row(&row, &columns)
-                .map_err(|e| noctra_core::error::NoctraError::Internal(format!("Row conversion error: {}", e)))?);
+        let result = source
+            .query_with_options(
+                "SELECT * FROM range(25)",
+                QueryOptions {
+                    unlimited: true,
+                    ..Default::default()
+                },
+            )
+            .unwrap();
+        assert_eq!(result.rows.len(), 25);
+        assert!(!result.truncated);
+    }
 
-            // Process remaining rows
-            while let Some(row) = rows_result.next().map_err(|e| noctra_core::error::NoctraError::Internal(format!("DuckDB row error: {}", e)))? {
-                rows.push(self.duckdb_row_to_noctra_row(&row, &columns)
-                    .map_err(|e| noctra_core::error::NoctraError::Internal(format!("Row conversion error: {}", e)))?);
-            }
-        }
+    #[test]
+    fn test_query_with_timeout_interrupts() {
+        let source = DuckDBSource::new_in_memory().unwrap();
+        let started = std::time::Instant::now();
+        let error = source
+            .query_with_timeout(
+                "s1",
+                "SELECT count(*) FROM range(1000000000000) a, range(1000) b",
+                &Parameters::new(),
+                Duration::from_millis(100),
+            )
+            .unwrap_err();
+        assert!(
+            matches!(error, noctra_core::error::NoctraError::Timeout { .. }),
+            "{:?}",
+            error
+        );
+        assert!(started.elapsed() < Duration::from_secs(10));
 
-        Ok(ResultSet {
-            columns,
-            rows,
-            rows_affected: None,
-            last_insert_rowid: None,
-        })
+        // The interrupted handle is reusable
+        let result = source.query("SELECT 42", &Parameters::new()).unwrap();
+        assert_eq!(result.rows[0].values[0], Value::Integer(42));
     }
 
-    fn schema(&self) -> noctra_core::error::Result<Vec<TableInfo>> {
-        let mut tables = Vec::new();
+    #[test]
+    fn test_execute_write_with_timeout_interrupts() {
+        let source = DuckDBSource::new_in_memory().unwrap();
+        source
+            .query("CREATE TABLE totals (n BIGINT)", &Parameters::new())
+            .unwrap();
 
-        // Return schema for registered files only
-        for (alias, _file_path) in &self.registered_files {
-            if let Ok(columns) = self.get_table_schema(alias) {
-                tables.push(TableInfo {
-                    name: alias.clone(),
-                    columns,
-                    row_count: None, // DuckDB doesn't provide row counts efficiently
-                });
-            }
-        }
+        let started = std::time::Instant::now();
+        let error = source
+            .execute_write_with_timeout(
+                "s1",
+                "INSERT INTO totals SELECT count(*) FROM range(1000000000000) a, range(1000) b",
+                &Parameters::new(),
+                Duration::from_millis(100),
+            )
+            .unwrap_err();
+        assert!(
+            matches!(error, noctra_core::error::NoctraError::Timeout { .. }),
+            "{:?}",
+            error
+        );
+        assert!(started.elapsed() < Duration::from_secs(10));
 
-        Ok(tables)
+        let result = source
+            .query("SELECT count(*) FROM totals", &Parameters::new())
+            .unwrap();
+        assert_eq!(result.rows[0].values[0], Value::Integer(0));
     }
 
-    fn source_type(&self) -> SourceType {
-        SourceType::Memory {
-            capacity: 0, // In-memory DuckDB
-        }
+    #[test]
+    fn test_query_stream_ignores_row_limit() {
+        let source = DuckDBSource::new_in_memory()
+            .unwrap()
+            .with_config(DuckDBConfig {
+                max_result_rows: 10,
+                ..DuckDBConfig::default()
+            });
+
+        let mut stream = source
+            .query_stream("s1", "SELECT range AS n FROM range(25)", &Parameters::new())
+            .unwrap();
+        assert_eq!(stream.columns()[0].name, "n");
+        assert_eq!(stream.next().unwrap().unwrap().values[0], Value::Integer(0));
+        assert_eq!(stream.count(), 24);
+
+        // Dropping a partially read stream releases its handle
+        let mut stream = source
+            .query_stream("s1", "SELECT * FROM range(25)", &Parameters::new())
+            .unwrap();
+        assert!(stream.next().is_some());
+        drop(stream);
+        assert_eq!(
+            source
+                .query("SELECT 1", &Parameters::new())
+                .unwrap()
+                .rows
+                .len(),
+            1
+        );
+
+        let empty = source
+            .query_stream("s1", "SELECT * FROM range(0)", &Parameters::new())
+            .unwrap();
+        assert_eq!(empty.columns().len(), 1);
+        assert_eq!(empty.count(), 0);
     }
 
-    fn name(&self) -> &str {
-        &self.name
+    #[test]
+    fn test_is_read_only() {
+        assert!(is_read_only("  select * from t"));
+        assert!(is_read_only("WITH a AS (SELECT 1) SELECT * FROM a"));
+        assert!(is_read_only("EXPLAIN SELECT * FROM t"));
+        assert!(!is_read_only("INSERT INTO t VALUES (1)"));
+        assert!(!is_read_only("CREATE VIEW v AS SELECT 1"));
+        // A read prefix may still wrap a write
+        assert!(!is_read_only(
+            "WITH a AS (SELECT 1 AS id) INSERT INTO t SELECT id FROM a"
+        ));
+        assert!(!is_read_only(
+            "WITH a AS (SELECT 1 AS id) DELETE FROM t WHERE id IN (SELECT id FROM a)"
+        ));
+        assert!(!is_read_only("EXPLAIN ANALYZE INSERT INTO t VALUES (1)"));
+        assert!(!is_read_only("EXPLAIN ANALYZE UPDATE t SET x = 1"));
     }
-}
 
-#[cfg(test)]
-mod tests {
-    use super::*;
-    use std::io::Write;
+    #[test]
+    fn test_detect_compressed_formats() {
+        assert_eq!(
+            detect_file_format("logs.csv.gz").unwrap(),
+            (FileFormat::Csv, Compression::Gzip)
+        );
+        assert_eq!(
+            detect_file_format("logs.CSV.ZST").unwrap(),
+            (FileFormat::Csv, Compression::Zstd)
+        );
+        assert_eq!(
+            detect_file_format("events.json.gz").unwrap(),
+            (FileFormat::Json, Compression::Gzip)
+        );
+        assert_eq!(
+            detect_file_format("events.ndjson.gz").unwrap(),
+            (FileFormat::NdJson, Compression::Gzip)
+        );
+        assert_eq!(
+            detect_file_format("data.parquet").unwrap(),
+            (FileFormat::Parquet, Compression::None)
+        );
+        assert_eq!(
+            detect_file_format("Report.XLSX").unwrap(),
+            (FileFormat::Excel, Compression::None)
+        );
+        assert!(matches!(
+            detect_file_format("report.xlsx.gz"),
+            Err(DuckDBError::UnsupportedFileType(_))
+        ));
+        assert!(matches!(
+            detect_file_format("data.parquet.gz"),
+            Err(DuckDBError::UnsupportedFileType(_))
+        ));
+        assert!(matches!(
+            detect_file_format("notes.txt.gz"),
+            Err(DuckDBError::UnsupportedFileType(_))
+        ));
+    }
 
+    #[cfg(feature = "arrow-integration")]
     #[test]
-    fn test_new_in_memory() {
+    fn test_query_arrow_primitive_types() {
+        use arrow::datatypes::DataType;
+
         let source = DuckDBSource::new_in_memory().unwrap();
-        assert_eq!(source.name(), "duckdb");
+        let batches = source
+            .query_arrow(
+                "SELECT 1::TINYINT AS t, 2::SMALLINT AS s, 3::INTEGER AS i, 4::BIGINT AS b, \
+                 1.5::FLOAT AS f, 2.5::DOUBLE AS d, true AS flag, 'x'::VARCHAR AS v, DATE '2024-01-31' AS day",
+            )
+            .unwrap();
+
+        assert_eq!(batches.len(), 1);
+        let schema = batches[0].schema();
+        let types: Vec<DataType> = schema
+            .fields()
+            .iter()
+            .map(|f| f.data_type().clone())
+            .collect();
+        assert_eq!(
+            types,
+            vec![
+                DataType::Int8,
+                DataType::Int16,
+                DataType::Int32,
+                DataType::Int64,
+                DataType::Float32,
+                DataType::Float64,
+                DataType::Boolean,
+                DataType::Utf8,
+                DataType::Date32,
+            ]
+        );
+
+        let result = crate::result_set_from_record_batches(&batches).unwrap();
+        assert_eq!(result.row_count(), 1);
+        assert_eq!(result.rows[0].values[3], Value::Integer(4));
+        assert_eq!(result.rows[0].values[6], Value::Boolean(true));
+        assert_eq!(
+            result.rows[0].values[8],
+            Value::Date(NaiveDate::from_ymd_opt(2024, 1, 31).unwrap())
+        );
+    }
+
+    fn gzip_fixture(suffix: &str, content: &str) -> tempfile::NamedTempFile {
+        let temp_file = tempfile::Builder::new().suffix(suffix).tempfile().unwrap();
+        let mut encoder = flate2::write::GzEncoder::new(
+            temp_file.reopen().unwrap(),
+            flate2::Compression::default(),
+        );
+        encoder.write_all(content.as_bytes()).unwrap();
+        encoder.finish().unwrap();
+        temp_file
     }
 
     #[test]
-    fn test_register_csv_file() {
-        let mut temp_file = tempfile::Builder::new().suffix(".csv").tempfile().unwrap();
-        writeln!(temp_file, "name,age,city").unwrap();
-        writeln!(temp_file, "Alice,30,NYC").unwrap();
-        writeln!(temp_file, "Bob,25,LA").unwrap();
-        temp_file.flush().unwrap();
+    fn test_register_gzipped_csv() {
+        let temp_file = gzip_fixture(".csv.gz", "name,age\nAlice,30\nBob,25\n");
 
         let mut source = DuckDBSource::new_in_memory().unwrap();
-        source.register_file(temp_file.path().to_str().unwrap(), "test_table").unwrap();
+        source
+            .register_file(temp_file.path().to_str().unwrap(), "people", false)
+            .unwrap();
 
-        assert!(source.registered_files().contains_key("test_table"));
+        let result = source
+            .query("SELECT * FROM people", &Parameters::new())
+            .unwrap();
+        assert_eq!(result.rows.len(), 2);
+        assert_eq!(source.source_type().compression(), Some("gzip"));
     }
 
     #[test]
-    fn test_query_csv_data() {
-        let mut temp_file = tempfile::Builder::new().suffix(".csv").tempfile().unwrap();
-        writeln!(temp_file, "name,age").unwrap();
-        writeln!(temp_file, "Alice,30").unwrap();
-        writeln!(temp_file, "Bob,25").unwrap();
-        temp_file.flush().unwrap();
+    fn test_register_gzipped_ndjson() {
+        let temp_file = gzip_fixture(".ndjson.gz", "{\"name\": \"Alice\"}\n{\"name\": \"Bob\"}\n");
 
         let mut source = DuckDBSource::new_in_memory().unwrap();
-        source.register_file(temp_file.path().to_str().unwrap(), "people").unwrap();
+        source
+            .register_file(temp_file.path().to_str().unwrap(), "events", false)
+            .unwrap();
 
-        let result = source.query("SELECT * FROM people", &Parameters::new()).unwrap();
+        let result = source
+            .query("SELECT * FROM events", &Parameters::new())
+            .unwrap();
         assert_eq!(result.rows.len(), 2);
-        assert_eq!(result.columns.len(), 2);
-        assert_eq!(result.columns[0].name, "name");
-        assert_eq!(result.columns[1].name, "age");
+        assert_eq!(source.source_type().type_name(), "json");
     }
 
+    #[cfg(feature = "decimal")]
     #[test]
-    fn test_schema_introspection() {
+    fn test_csv_amounts_sum_exactly() {
         let mut temp_file = tempfile::Builder::new().suffix(".csv").tempfile().unwrap();
-        writeln!(temp_file, "id,name,active").unwrap();
-        writeln!(temp_file, "1,Alice,true").unwrap();
+        writeln!(temp_file, "item,amount").unwrap();
+        for _ in 0..10 {
+            writeln!(temp_file, "pen,0.10").unwrap();
+        }
+        writeln!(temp_file, "book,19.99").unwrap();
         temp_file.flush().unwrap();
 
         let mut source = DuckDBSource::new_in_memory().unwrap();
-        source.register_file(temp_file.path().to_str().unwrap(), "users").unwrap();
+        source
+            .register_file(temp_file.path().to_str().unwrap(), "sales", false)
+            .unwrap();
 
-        let schema = source.schema().unwrap();
-        // Schema should contain the registered table
-        assert!(!schema.is_empty());
-        let users_table = schema.iter().find(|t| t.name == "users");
-        assert!(users_table.is_some(), "users table should be in schema");
-        let users_table = users_table.unwrap();
-        assert_eq!(users_table.name, "users");
-        // DuckDB may infer different column counts, just check it's > 0
-        assert!(users_table.columns.len() > 0);
+        // As DOUBLEs this would be 20.989999999999998
+        let result = source
+            .query("SELECT SUM(amount) FROM sales", &Parameters::new())
+            .unwrap();
+        let expected: rust_decimal::Decimal = "20.99".parse().unwrap();
+        assert_eq!(result.rows[0].values[0], Value::Decimal(expected));
+        assert_eq!(result.rows[0].values[0].to_string(), "20.99");
+
+        let mut params = Parameters::new();
+        params.insert(
+            "price".to_string(),
+            Value::Decimal("19.99".parse().unwrap()),
+        );
+        let result = source
+            .query("SELECT item FROM sales WHERE amount = $price", &params)
+            .unwrap();
+        assert_eq!(result.rows[0].values[0], Value::Text("book".to_string()));
     }
 
     #[test]
-    fn test_json_support() {
-        let mut temp_file = tempfile::Builder::new().suffix(".json").tempfile().unwrap();
-        writeln!(temp_file, r#"[{{"name": "Alice", "age": 30}}, {{"name": "Bob", "age": 25}}]"#).unwrap();
-        temp_file.flush().unwrap();
+    fn test_temporal_and_blob_values() {
+        let source = DuckDBSource::new_in_memory().unwrap();
+        let result = source
+            .query(
+                "SELECT DATE '2024-03-01' AS day, TIME '09:15:00' AS at, \
+                 TIMESTAMP '2024-03-01 09:15:00.5' AS ts, '\\xCA\\xFE'::BLOB AS data",
+                &Parameters::new(),
+            )
+            .unwrap();
 
-        let mut source = DuckDBSource::new_in_memory().unwrap();
-        source.register_file(temp_file.path().to_str().unwrap(), "people").unwrap();
+        let row = &result.rows[0].values;
+        assert_eq!(
+            row[0],
+            Value::Date(NaiveDate::from_ymd_opt(2024, 3, 1).unwrap())
+        );
+        assert_eq!(
+            row[1],
+            Value::Time(NaiveTime::from_hms_opt(9, 15, 0).unwrap())
+        );
+        assert_eq!(row[2].to_string(), "2024-03-01T09:15:00.500Z");
+        assert_eq!(row[3], Value::Blob(vec![0xca, 0xfe]));
 
-        let result = source.query("SELECT * FROM people", &noctra_core::Parameters::new()).unwrap();
-        assert_eq!(result.rows.len(), 2);
-        assert_eq!(result.columns.len(), 2);
+        // Typed parameters bind as DuckDB temporal values
+        let mut params = Parameters::new();
+        params.insert("ts".to_string(), row[2].clone());
+        let result = source
+            .query(
+                "SELECT $ts::TIMESTAMP = TIMESTAMP '2024-03-01 09:15:00.5'",
+                &params,
+            )
+            .unwrap();
+        assert_eq!(result.rows[0].values[0], Value::Boolean(true));
     }
 
     #[test]
-    fn test_parquet_support() {
-        // For now, just test that the registration doesn't fail
-        // Full Parquet testing would require creating a Parquet file
+    fn test_decompressed_size_limit() {
+        let temp_file = gzip_fixture(
+            ".csv.gz",
+            &"id,value\n1,aaaaaaaaaaaaaaaaaaaaaaaaaaaaaa\n".repeat(100),
+        );
+
         let mut source = DuckDBSource::new_in_memory().unwrap();
-        // This should not panic even if file doesn't exist (DuckDB handles it)
-        let result = source.register_file("nonexistent.parquet", "test");
-        // DuckDB will fail if file doesn't exist, so we expect an error
-        assert!(result.is_err());
+        source.set_file_limits(FileLimits {
+            max_file_size: DEFAULT_MAX_FILE_SIZE,
+            max_decompressed_size: 1024,
+        });
+
+        let result = source.register_file(temp_file.path().to_str().unwrap(), "big", false);
+        assert!(matches!(result, Err(DuckDBError::FileTooLarge(_))));
     }
 
     #[test]
-    fn test_attach_sqlite() {
+    fn test_glob_checks_each_file_size() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("small.csv"), "id\n1\n").unwrap();
+        std::fs::write(
+            dir.path().join("large.csv"),
+            format!("id\n{}", "1\n".repeat(1000)),
+        )
+        .unwrap();
+
         let mut source = DuckDBSource::new_in_memory().unwrap();
-        // Test attaching a non-existent SQLite DB (should fail gracefully)
-        let result = source.attach_sqlite("nonexistent.db", "test");
-        assert!(result.is_err());
+        source.set_file_limits(FileLimits {
+            max_file_size: 100,
+            max_decompressed_size: DEFAULT_MAX_DECOMPRESSED_SIZE,
+        });
+
+        let pattern = format!("{}/*.csv", dir.path().display());
+        let result = source.register_glob(&pattern, "all", false);
+        assert!(
+            matches!(result, Err(DuckDBError::FileTooLarge(ref message)) if message.contains("large.csv"))
+        );
+
+        let pattern = format!("{}/small*.csv", dir.path().display());
+        source.register_glob(&pattern, "small", false).unwrap();
     }
 
     #[test]
-    fn test_unsupported_file_type() {
+    fn test_allowed_paths_sandbox() {
+        let root = tempfile::tempdir().unwrap();
+        let outside = tempfile::tempdir().unwrap();
+        std::fs::write(root.path().join("inside.csv"), "id\n1\n").unwrap();
+        std::fs::write(outside.path().join("secret.csv"), "id\n2\n").unwrap();
+
+        let mut source = DuckDBSource::new_in_memory()
+            .unwrap()
+            .with_config(DuckDBConfig {
+                allowed_paths: vec![root.path().to_path_buf()],
+                ..DuckDBConfig::default()
+            });
+
+        let inside = root.path().join("inside.csv");
+        source
+            .register_file(inside.to_str().unwrap(), "inside", false)
+            .unwrap();
+
+        let escape = root
+            .path()
+            .join("..")
+            .join(outside.path().file_name().unwrap())
+            .join("secret.csv");
+        let result = source.register_file(escape.to_str().unwrap(), "secret", false);
+        assert!(matches!(result, Err(DuckDBError::AccessDenied(_))));
+
+        #[cfg(unix)]
+        {
+            let link = root.path().join("link.csv");
+            std::os::unix::fs::symlink(outside.path().join("secret.csv"), &link).unwrap();
+            let result = source.register_file(link.to_str().unwrap(), "secret", false);
+            assert!(matches!(result, Err(DuckDBError::AccessDenied(_))));
+        }
+
+        // The view reads the canonical path that was checked
+        let relative = root.path().join("sub").join("..").join("inside.csv");
+        std::fs::create_dir(root.path().join("sub")).unwrap();
+        source
+            .register_file(relative.to_str().unwrap(), "relative", false)
+            .unwrap();
+        let canonical = inside.canonicalize().unwrap();
+        assert_eq!(
+            source.registered_files.get("relative").map(String::as_str),
+            canonical.to_str()
+        );
+    }
+
+    #[test]
+    fn test_blocklist_applies_without_allowed_paths() {
         let mut source = DuckDBSource::new_in_memory().unwrap();
-        let result = source.register_file("test.txt", "invalid");
-        assert!(matches!(result, Err(DuckDBError::UnsupportedFileType(_))));
+        for path in [
+            "/etc/passwd.csv",
+            "/proc/self/status.csv",
+            "datos/../secreto.csv",
+        ] {
+            let result = source.register_file(path, "t", false);
+            assert!(
+                matches!(result, Err(DuckDBError::AccessDenied(_))),
+                "{}: {:?}",
+                path,
+                result
+            );
+        }
     }
-}
\ No newline at end of file
+}
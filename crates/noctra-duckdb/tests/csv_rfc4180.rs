@@ -0,0 +1,122 @@
+//! RFC 4180 compatibility of `USE 'file.csv' AS alias`
+//!
+//! Every fixture under `tests/fixtures/rfc4180` is opened the way the REPL
+//! opens it: through the `SourceRegistry` with the DuckDB factories.
+
+use std::collections::HashMap;
+use std::path::PathBuf;
+
+use noctra_core::types::Parameters;
+use noctra_core::{CreatedSource, SourceRegistry, Value};
+use noctra_duckdb::{register_factories, DuckDBConfig};
+
+fn fixture(name: &str) -> String {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/fixtures/rfc4180")
+        .join(name)
+        .display()
+        .to_string()
+}
+
+fn use_csv(name: &str, options: &[(&str, &str)]) -> CreatedSource {
+    let mut registry = SourceRegistry::new();
+    register_factories(&mut registry, &DuckDBConfig::default());
+    let options: HashMap<String, String> = options
+        .iter()
+        .map(|(k, v)| (k.to_string(), v.to_string()))
+        .collect();
+    registry
+        .create_source(&fixture(name), "t", &options)
+        .unwrap()
+}
+
+fn rows(source: &CreatedSource, sql: &str) -> Vec<Vec<Value>> {
+    source
+        .source
+        .query(sql, &Parameters::new())
+        .unwrap()
+        .rows
+        .into_iter()
+        .map(|row| row.values)
+        .collect()
+}
+
+fn text(s: &str) -> Value {
+    Value::Text(s.to_string())
+}
+
+#[test]
+fn test_escaped_quotes_and_embedded_delimiters() {
+    let source = use_csv("escaped_quotes.csv", &[]);
+    assert_eq!(
+        rows(&source, "SELECT text, n FROM t ORDER BY id"),
+        vec![
+            vec![text("a,\"b\",c"), Value::Integer(10)],
+            vec![text("\"quoted\""), Value::Integer(20)],
+            vec![text("plain"), Value::Integer(30)],
+        ]
+    );
+}
+
+#[test]
+fn test_quoted_newlines_stay_in_one_record() {
+    let source = use_csv("quoted_newlines.csv", &[]);
+    assert_eq!(
+        rows(&source, "SELECT note FROM t ORDER BY id"),
+        vec![
+            vec![text("first line\nsecond line")],
+            vec![text("ends with newline\n")],
+            vec![text("single")],
+        ]
+    );
+}
+
+#[test]
+fn test_trailing_delimiter_is_an_empty_last_field() {
+    let source = use_csv("trailing_delimiter.csv", &[]);
+    let result = source
+        .source
+        .query("SELECT * FROM t ORDER BY id", &Parameters::new())
+        .unwrap();
+    assert_eq!(result.columns.len(), 3);
+    assert_eq!(result.rows.len(), 2);
+    assert_eq!(result.rows[1].values[1], text("Luis, Jr."));
+    assert!(result.rows.iter().all(|row| row.values[2] == Value::Null));
+}
+
+#[test]
+fn test_crlf_records_and_quoted_crlf() {
+    let source = use_csv("crlf_line_endings.csv", &[]);
+    assert_eq!(
+        rows(&source, "SELECT name FROM t ORDER BY id"),
+        vec![vec![text("Ana\r\nMaría")], vec![text("Luis")]]
+    );
+}
+
+#[test]
+fn test_quote_and_delimiter_from_use_options() {
+    // USE '…/single_quote.csv' AS t OPTIONS (quote='''', delimiter=';'),
+    // with the quotes already stripped by the NQL parser
+    let source = use_csv("single_quote.csv", &[("quote", "''"), ("delimiter", ";")]);
+    assert_eq!(
+        rows(&source, "SELECT text FROM t ORDER BY id"),
+        vec![vec![text("it's; fine")], vec![text("a \"b\" c")]]
+    );
+
+    let mut registry = SourceRegistry::new();
+    register_factories(&mut registry, &DuckDBConfig::default());
+    let options = HashMap::from([("quote".to_string(), "ab".to_string())]);
+    assert!(registry
+        .create_source(&fixture("escaped_quotes.csv"), "t", &options)
+        .is_err());
+}
+
+#[test]
+fn test_encoding_from_use_options() {
+    // USE '…/latin1.csv' AS t OPTIONS (encoding='latin-1')
+    let source = use_csv("latin1.csv", &[("encoding", "latin-1")]);
+    assert_eq!(
+        rows(&source, "SELECT name FROM t ORDER BY id"),
+        vec![vec![text("Peña")], vec![text("Müller")]]
+    );
+}
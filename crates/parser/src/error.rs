@@ -1,5 +1,6 @@
 //! Error handling para el parser de RQL
 
+use noctra_core::error::NoctraError;
 use thiserror::Error;
 
 /// Errores del parser de RQL
@@ -10,6 +11,8 @@ pub enum ParserError {
         line: usize,
         column: usize,
         message: String,
+        /// Línea de la entrada con un `^` bajo la columna (ver `source_snippet`)
+        snippet: Option<String>,
     },
 
     #[error("Token inesperado '{token}' en línea {line}, columna {column}")]
@@ -45,6 +48,23 @@ impl ParserError {
             line,
             column,
             message: message.into(),
+            snippet: None,
+        }
+    }
+
+    /// Añadir el fragmento de la entrada a un error de sintaxis
+    pub fn with_snippet<T: Into<String>>(mut self, fragment: T) -> Self {
+        if let Self::SyntaxError { snippet, .. } = &mut self {
+            *snippet = Some(fragment.into());
+        }
+        self
+    }
+
+    /// Fragmento de la entrada señalando el error, si lo hay
+    pub fn snippet(&self) -> Option<&str> {
+        match self {
+            Self::SyntaxError { snippet, .. } => snippet.as_deref(),
+            _ => None,
         }
     }
 
@@ -76,6 +96,24 @@ impl ParserError {
 /// Result type para operaciones del parser
 pub type ParserResult<T> = std::result::Result<T, ParserError>;
 
+/// Línea `line` de `input` con un `^` bajo la columna `column` (desde 1)
+///
+/// ```text
+///   3 |   WHERE id = = 1
+///     |              ^
+/// ```
+pub fn source_snippet(input: &str, line: usize, column: usize) -> String {
+    let text = input.lines().nth(line.saturating_sub(1)).unwrap_or("");
+    // Las tabulaciones se conservan para que el `^` quede alineado
+    let padding: String = text
+        .chars()
+        .take(column.saturating_sub(1))
+        .map(|c| if c == '\t' { '\t' } else { ' ' })
+        .collect();
+    let gutter = " ".repeat(line.to_string().len());
+    format!("{} | {}\n{} | {}^", line, text, gutter, padding)
+}
+
 impl From<sqlparser::parser::ParserError> for ParserError {
     fn from(error: sqlparser::parser::ParserError) -> Self {
         Self::SqlParserError(error.to_string())
@@ -93,3 +131,30 @@ impl From<serde_json::Error> for ParserError {
         Self::InternalError(format!("JSON serialization error: {}", error))
     }
 }
+
+impl From<ParserError> for NoctraError {
+    fn from(error: ParserError) -> Self {
+        match error {
+            ParserError::SyntaxError {
+                line,
+                column,
+                message,
+                snippet,
+            } => NoctraError::Parse {
+                line,
+                column,
+                message,
+                snippet,
+            },
+            ParserError::UnexpectedToken { line, column, .. } => {
+                NoctraError::parse(line, column, error.to_string())
+            }
+            ParserError::SqlParserError(message) => NoctraError::SqlSyntax(message),
+            ParserError::InvalidParameter(_)
+            | ParserError::UnknownCommand(_)
+            | ParserError::TemplateError(_)
+            | ParserError::TypeConversionError(_) => NoctraError::Validation(error.to_string()),
+            ParserError::InternalError(message) => NoctraError::Internal(message),
+        }
+    }
+}
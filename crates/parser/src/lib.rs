@@ -8,9 +8,12 @@ pub mod parser;
 pub mod rql_ast;
 pub mod template;
 
-pub use error::{ParserError, ParserResult};
+pub use error::{source_snippet, ParserError, ParserResult};
 pub use parser::{RqlParser, RqlProcessor};
-pub use rql_ast::{ExportFormat, MapExpression, ParameterType, RqlAst, RqlParameter, RqlStatement};
+pub use rql_ast::{
+    ExportFormat, MapExpression, OutfileFormat, ParameterType, RqlAst, RqlParameter, RqlStatement,
+    TransactionCommand,
+};
 pub use template::{TemplateEngine, TemplateProcessor};
 
 #[cfg(test)]
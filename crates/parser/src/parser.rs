@@ -1,14 +1,162 @@
 //! Parser principal para RQL (Extended SQL)
 
-use crate::error::{ParserError, ParserResult};
+use crate::error::{source_snippet, ParserError, ParserResult};
 use crate::rql_ast::{
-    ExportFormat, MapExpression, OutputDestination, OutputFormat, ParameterType, RqlAst,
-    RqlParameter, RqlStatement,
+    ExportFormat, MapExpression, OutfileFormat, OutputDestination, OutputFormat, ParameterType,
+    RqlAst, RqlParameter, RqlStatement, TransactionCommand,
 };
+use noctra_core::lexer::{segments, statements, SegmentKind};
 use regex::Regex;
+use sqlparser::ast::{Expr, Query, SetExpr, Statement, Visit, Visitor, WindowType};
 use std::collections::HashMap;
+use std::ops::ControlFlow;
+use std::sync::OnceLock;
 use std::time::Instant;
 
+/// Filas de `SAMPLE table` sin `LIMIT`
+const DEFAULT_SAMPLE_LIMIT: usize = 10;
+
+/// Parámetro posicionado con su cast opcional: `$1`, `$2::INTEGER`,
+/// `$3::DECIMAL(10, 2)`
+fn positional_parameter() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| {
+        Regex::new(r"\$(\d+)(?:::([A-Za-z_][A-Za-z0-9_]*(?:\s*\([0-9,\s]*\))?(?:\[\])?))?").unwrap()
+    })
+}
+
+/// Parámetro nombrado (`:name`); un `::tipo` no lo es, ver `is_cast_at`
+fn named_parameter() -> &'static Regex {
+    static REGEX: OnceLock<Regex> = OnceLock::new();
+    REGEX.get_or_init(|| Regex::new(r":([a-zA-Z_][a-zA-Z0-9_]*)").unwrap())
+}
+
+/// Si el `:` en `pos` es parte de un cast `::`
+fn is_cast_at(sql: &str, pos: usize) -> bool {
+    sql[..pos].ends_with(':') || sql[pos + 1..].starts_with(':')
+}
+
+/// Sentencia de la entrada, con la posición de cada carácter en el original
+#[derive(Debug, Default)]
+struct SourceStatement {
+    /// Texto de la sentencia, con su `;` y sin comentarios
+    text: String,
+
+    /// Offset en bytes, dentro de la entrada, de cada carácter de `text`
+    offsets: Vec<usize>,
+}
+
+impl SourceStatement {
+    fn push(&mut self, offset: usize, c: char) {
+        self.text.push(c);
+        self.offsets.push(offset);
+    }
+
+    /// La sentencia sin los espacios de los extremos
+    fn trimmed(&self) -> Self {
+        let chars: Vec<char> = self.text.chars().collect();
+        let start = chars.iter().take_while(|c| c.is_whitespace()).count();
+        let end = chars.len()
+            - chars[start..]
+                .iter()
+                .rev()
+                .take_while(|c| c.is_whitespace())
+                .count();
+        Self {
+            text: chars[start..end].iter().collect(),
+            offsets: self.offsets[start..end].to_vec(),
+        }
+    }
+
+    /// Línea de la entrada donde empieza (desde 1)
+    fn line(&self, input: &str) -> usize {
+        position_at(input, self.offsets.first().copied().unwrap_or(0)).0
+    }
+
+    /// Posición en la entrada de la `line`/`column` (desde 1) de `text`
+    ///
+    /// sqlparser informa la posición de sus errores dentro del texto de la
+    /// sentencia; los comentarios descartados y el sangrado quedan fuera de
+    /// ese texto, así que se traduce carácter a carácter.
+    fn input_position(&self, input: &str, line: usize, column: usize) -> (usize, usize) {
+        let mut current = (1, 1);
+        let index = self
+            .text
+            .chars()
+            .position(|c| {
+                if current.0 > line || current == (line, column) {
+                    return true;
+                }
+                current = if c == '\n' {
+                    (current.0 + 1, 1)
+                } else {
+                    (current.0, current.1 + 1)
+                };
+                false
+            })
+            .unwrap_or(self.offsets.len().saturating_sub(1));
+        position_at(input, self.offsets.get(index).copied().unwrap_or(0))
+    }
+}
+
+/// Línea y columna (desde 1, en caracteres) del byte `offset` de `input`
+fn position_at(input: &str, offset: usize) -> (usize, usize) {
+    let before = &input[..offset.min(input.len())];
+    let line_start = before.rfind('\n').map_or(0, |newline| newline + 1);
+    (
+        before.matches('\n').count() + 1,
+        before[line_start..].chars().count() + 1,
+    )
+}
+
+/// Posición `Line: L, Column C` que sqlparser añade a sus errores
+fn sqlparser_position(message: &str) -> Option<(usize, usize)> {
+    let (_, position) = message.rsplit_once(" at Line: ")?;
+    let (line, column) = position.split_once(", Column ")?;
+    Some((line.trim().parse().ok()?, column.trim().parse().ok()?))
+}
+
+/// Dividir la entrada en sentencias terminadas en `;`
+///
+/// Cada sentencia conserva su `;` y el offset de cada carácter en la
+/// entrada. Los `;` dentro de literales (`'...'`, `"..."`), cadenas
+/// dollar-quoted (`$$...$$`, `$tag$...$tag$`), paréntesis
+/// (`OPTIONS (delimiter=;)`) o el cuerpo `BEGIN ... END` de un trigger no
+/// cortan la sentencia. Los comentarios `--` y `/* */` se descartan; una
+/// sentencia puede ocupar varias líneas y la última no necesita `;`. La
+/// división es la de `noctra_core::lexer::statements`, la misma que la de
+/// `noctra_core::split_statements`.
+fn split_statements(input: &str) -> Vec<SourceStatement> {
+    let mut result = Vec::new();
+
+    for statement in statements(input) {
+        let mut current = SourceStatement::default();
+        for segment in statement.segments {
+            let start = segment.range.start;
+            match segment.kind {
+                SegmentKind::BlockComment => current.push(start, ' '),
+                _ => {
+                    for (offset, c) in input[segment.range].char_indices() {
+                        current.push(start + offset, c);
+                    }
+                }
+            }
+        }
+        if let Some(terminator) = statement.terminator {
+            current.push(terminator, ';');
+        }
+        push_statement(&mut result, &current);
+    }
+
+    result
+}
+
+fn push_statement(statements: &mut Vec<SourceStatement>, statement: &SourceStatement) {
+    if !statement.text.trim_end_matches(';').trim().is_empty() {
+        statements.push(statement.trimmed());
+    }
+}
+
 /// Parser principal para RQL
 #[derive(Debug, Clone)]
 pub struct RqlParser {
@@ -36,32 +184,45 @@ impl RqlParser {
 
         let mut ast = RqlAst::new();
 
-        // Dividir input en líneas para procesamiento
-        let lines: Vec<&str> = input.lines().collect();
-        ast.metadata.lines_processed = lines.len();
+        ast.metadata.lines_processed = input.lines().count();
 
-        // Procesar cada línea
-        for (line_num, line) in lines.iter().enumerate() {
-            let trimmed_line = line.trim();
-
-            // Saltar líneas vacías y comentarios
-            if trimmed_line.is_empty() || trimmed_line.starts_with("--") {
-                continue;
-            }
-
-            // Parsear línea individual
-            match self.parse_line(trimmed_line, line_num + 1) {
+        // Procesar cada sentencia (ver `split_statements`)
+        for statement_source in split_statements(input) {
+            let text = &statement_source.text;
+            let line_num = statement_source.line(input);
+            match self.parse_line(text, line_num) {
                 Ok(statement) => {
+                    if let RqlStatement::Sql { sql, .. } = &statement {
+                        for cte in Self::extract_cte_names(sql) {
+                            ast.add_cte_name(cte);
+                        }
+                        for window in Self::extract_window_names(sql) {
+                            ast.add_window_name(window);
+                        }
+                    }
                     ast.add_statement(statement);
-                    // Extraer parámetros de la línea
-                    self.extract_parameters(trimmed_line, line_num + 1, &mut ast)?;
+                    // Extraer parámetros de la sentencia
+                    self.extract_parameters(text, line_num, &mut ast)?;
                 }
                 Err(e) => {
+                    // Los errores de sqlparser traen su posición dentro de la
+                    // sentencia; el resto se sitúa al inicio de la sentencia
+                    let message = e.to_string();
+                    let (relative, message) = match sqlparser_position(&message) {
+                        Some(position) => {
+                            let end = message.rfind(" at Line: ").unwrap_or(message.len());
+                            (position, message[..end].to_string())
+                        }
+                        None => ((1, 1), message),
+                    };
+                    let (line, column) =
+                        statement_source.input_position(input, relative.0, relative.1);
                     return Err(ParserError::syntax_error(
-                        line_num + 1,
-                        1,
-                        format!("Failed to parse line: {}", e),
-                    ));
+                        line,
+                        column,
+                        format!("Failed to parse statement: {}", message),
+                    )
+                    .with_snippet(source_snippet(input, line, column)));
                 }
             }
         }
@@ -72,10 +233,15 @@ impl RqlParser {
         Ok(ast)
     }
 
-    /// Parsear línea individual
+    /// Parsear una sentencia
     fn parse_line(&self, line: &str, line_num: usize) -> ParserResult<RqlStatement> {
         let upper_line = line.to_uppercase();
 
+        // BEGIN/COMMIT/ROLLBACK/SAVEPOINT se ejecutan con la API de transacciones
+        if let Some(command) = Self::parse_transaction_command(line) {
+            return Ok(RqlStatement::Transaction { command });
+        }
+
         // Detectar comandos NQL (comandos nuevos multi-fuente)
         if upper_line.starts_with("SHOW SOURCES") {
             self.parse_show_sources_command(line, line_num)
@@ -85,10 +251,30 @@ impl RqlParser {
             self.parse_show_vars_command(line, line_num)
         } else if upper_line.starts_with("DESCRIBE ") {
             self.parse_describe_command(line, line_num)
+        } else if upper_line.starts_with("PROFILE ") {
+            self.parse_profile_command(line, line_num)
+        } else if upper_line.starts_with("SAMPLE ") {
+            self.parse_sample_command(line, line_num)
+        } else if upper_line.starts_with("WATCH ") {
+            self.parse_watch_command(line, line_num)
+        } else if upper_line.trim_end_matches(';').trim_end() == "CHECKPOINT"
+            || upper_line.starts_with("CHECKPOINT ")
+        {
+            self.parse_checkpoint_command(line, line_num)
+        } else if upper_line.starts_with("CREATE VIEW ") {
+            self.parse_create_view_command(line, line_num)
+        } else if upper_line.starts_with("MERGE ") {
+            self.parse_merge_command(line, line_num)
         } else if upper_line.starts_with("IMPORT ") {
             self.parse_import_command(line, line_num)
+        } else if upper_line.starts_with("EXPORT SCHEMA ") {
+            self.parse_export_schema_command(line, line_num)
+        } else if upper_line.starts_with("EXPORT SOURCE ") {
+            self.parse_export_source_command(line, line_num)
         } else if upper_line.starts_with("EXPORT ") {
             self.parse_export_command(line, line_num)
+        } else if upper_line.starts_with("SAVE BLOB ") {
+            self.parse_save_blob_command(line, line_num)
         } else if upper_line.starts_with("MAP ") {
             self.parse_map_command(line, line_num)
         } else if upper_line.starts_with("FILTER ") {
@@ -96,9 +282,11 @@ impl RqlParser {
         } else if upper_line.starts_with("UNSET ") {
             self.parse_unset_command(line, line_num)
         } else if upper_line.starts_with("USE ") {
-            // Diferenciar entre USE schema y USE 'file' AS alias
+            // Diferenciar entre USE schema, USE SOURCE alias y USE 'file' AS alias
             if line.contains('\'') || line.contains('\"') {
                 self.parse_use_source_command(line, line_num)
+            } else if upper_line.starts_with("USE SOURCE ") {
+                self.parse_use_source_alias_command(line, line_num)
             } else {
                 self.parse_use_command(line, line_num)
             }
@@ -110,6 +298,24 @@ impl RqlParser {
             self.parse_exec_form_command(line, line_num)
         } else if upper_line.starts_with("OUTPUT TO ") {
             self.parse_output_to_command(line, line_num)
+        } else if upper_line.starts_with("ASSERT ") {
+            self.parse_assert_command(line, line_num)
+        } else if let Some(into_pos) = Self::find_select_into(line) {
+            self.parse_select_into_command(line, into_pos, line_num)
+        } else if Self::is_create_trigger(&upper_line) {
+            // sqlparser no entiende CREATE TRIGGER: lo valida el backend
+            Ok(RqlStatement::Sql {
+                sql: line.to_string(),
+                parameters: HashMap::new(),
+                is_duckdb_specific: false,
+            })
+        } else if Self::is_duckdb_specific(line) {
+            // sqlparser no entiende PIVOT/UNPIVOT de DuckDB: pasa tal cual
+            Ok(RqlStatement::Sql {
+                sql: line.to_string(),
+                parameters: HashMap::new(),
+                is_duckdb_specific: true,
+            })
         } else {
             // Es SQL estándar
             self.parse_sql_statement(line, line_num)
@@ -304,6 +510,31 @@ impl RqlParser {
         })
     }
 
+    /// Parsear comando USE SOURCE
+    /// Sintaxis: USE SOURCE alias
+    fn parse_use_source_alias_command(
+        &self,
+        line: &str,
+        line_num: usize,
+    ) -> ParserResult<RqlStatement> {
+        let parts: Vec<&str> = line
+            .trim_end_matches(';')
+            .split_whitespace()
+            .skip(2)
+            .collect();
+
+        match parts.as_slice() {
+            [alias] => Ok(RqlStatement::UseSourceAlias {
+                alias: alias.to_string(),
+            }),
+            _ => Err(ParserError::syntax_error(
+                line_num,
+                1,
+                "USE SOURCE requires exactly one source alias",
+            )),
+        }
+    }
+
     /// Parsear comando SHOW SOURCES
     fn parse_show_sources_command(
         &self,
@@ -343,12 +574,300 @@ impl RqlParser {
     /// Parsear comando DESCRIBE
     /// Sintaxis: DESCRIBE [source.]table
     fn parse_describe_command(&self, line: &str, line_num: usize) -> ParserResult<RqlStatement> {
+        let (source, table) = Self::parse_table_spec(line, line_num, "DESCRIBE")?;
+        Ok(RqlStatement::Describe { source, table })
+    }
+
+    /// Parsear comando PROFILE
+    /// Sintaxis: PROFILE [source.]table
+    fn parse_profile_command(&self, line: &str, line_num: usize) -> ParserResult<RqlStatement> {
+        let (source, table) = Self::parse_table_spec(line, line_num, "PROFILE")?;
+        Ok(RqlStatement::Profile { source, table })
+    }
+
+    /// Parsear comando SAMPLE
+    /// Sintaxis: SAMPLE [source.]table [LIMIT n]
+    fn parse_sample_command(&self, line: &str, line_num: usize) -> ParserResult<RqlStatement> {
+        let (source, table) = Self::parse_table_spec(line, line_num, "SAMPLE")?;
+        let parts: Vec<&str> = line.trim_end_matches(';').split_whitespace().collect();
+        let limit = match parts.get(2..).unwrap_or(&[]) {
+            [] => DEFAULT_SAMPLE_LIMIT,
+            [keyword, count] if keyword.eq_ignore_ascii_case("LIMIT") => {
+                count.parse().map_err(|_| {
+                    ParserError::syntax_error(
+                        line_num,
+                        1,
+                        format!("SAMPLE LIMIT requires a row count, got '{}'", count),
+                    )
+                })?
+            }
+            _ => {
+                return Err(ParserError::syntax_error(
+                    line_num,
+                    1,
+                    "SAMPLE command requires format: SAMPLE [source.]table [LIMIT n]",
+                ))
+            }
+        };
+        Ok(RqlStatement::Sample {
+            source,
+            table,
+            limit,
+        })
+    }
+
+    /// Parsear comando WATCH
+    /// Sintaxis: WATCH segundos sql
+    fn parse_watch_command(&self, line: &str, line_num: usize) -> ParserResult<RqlStatement> {
+        let rest = line.trim().trim_end_matches(';')[5..].trim_start();
+        let (interval, sql) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+        let sql = sql.trim();
+        if sql.is_empty() {
+            return Err(ParserError::syntax_error(
+                line_num,
+                1,
+                "WATCH command requires format: WATCH seconds query",
+            ));
+        }
+        let interval_secs = interval
+            .parse::<u64>()
+            .ok()
+            .filter(|secs| *secs > 0)
+            .ok_or_else(|| {
+                ParserError::syntax_error(
+                    line_num,
+                    1,
+                    format!(
+                        "WATCH requires a positive interval in seconds, got '{}'",
+                        interval
+                    ),
+                )
+            })?;
+        Ok(RqlStatement::Watch {
+            interval_secs,
+            sql: sql.to_string(),
+        })
+    }
+
+    /// Parsear comando CHECKPOINT
+    /// Sintaxis: CHECKPOINT [source]
+    fn parse_checkpoint_command(&self, line: &str, line_num: usize) -> ParserResult<RqlStatement> {
+        let parts: Vec<&str> = line
+            .trim_end_matches(';')
+            .split_whitespace()
+            .skip(1)
+            .collect();
+
+        match parts.as_slice() {
+            [] => Ok(RqlStatement::Checkpoint { source: None }),
+            [source] => Ok(RqlStatement::Checkpoint {
+                source: Some(source.to_string()),
+            }),
+            _ => Err(ParserError::syntax_error(
+                line_num,
+                1,
+                "CHECKPOINT accepts at most one source name",
+            )),
+        }
+    }
+
+    /// Parsear comando CREATE VIEW
+    /// Sintaxis: CREATE VIEW name AS sql
+    ///
+    /// Sólo la forma simple se convierte en `CreateView` (la que sabe crear
+    /// una fuente); `IF NOT EXISTS`, listas de columnas, nombres entre
+    /// comillas o calificados siguen como SQL normal.
+    fn parse_create_view_command(&self, line: &str, line_num: usize) -> ParserResult<RqlStatement> {
+        let rest = line["CREATE VIEW ".len()..].trim_start();
+        let (name, query) = rest.split_once(char::is_whitespace).unwrap_or((rest, ""));
+        let query = query.trim_start();
+
+        let sql = match query.get(..3) {
+            Some(keyword) if keyword.eq_ignore_ascii_case("AS ") => {
+                query[3..].trim().trim_end_matches(';').trim_end()
+            }
+            _ => "",
+        };
+        if !RqlProcessor::is_valid_identifier(name) || sql.is_empty() {
+            return self.parse_sql_statement(line, line_num);
+        }
+
+        Ok(RqlStatement::CreateView {
+            name: name.to_string(),
+            sql: sql.to_string(),
+            statement: line.to_string(),
+        })
+    }
+
+    /// Reconocer SQL propio de DuckDB: `PIVOT`/`UNPIVOT` como sentencia
+    /// (`PIVOT t ON col USING sum(x)`) o tras un FROM (`FROM t PIVOT (...)`)
+    fn is_duckdb_specific(line: &str) -> bool {
+        let upper = line.trim_start().to_uppercase();
+        let first = upper
+            .split(|c: char| !c.is_ascii_alphanumeric() && c != '_')
+            .next()
+            .unwrap_or("");
+        if first == "PIVOT" || first == "UNPIVOT" {
+            return true;
+        }
+        static FROM_PIVOT: OnceLock<Regex> = OnceLock::new();
+        FROM_PIVOT
+            .get_or_init(|| Regex::new(r"(?s)\bFROM\b.*\b(?:UN)?PIVOT\s*\(").unwrap())
+            .is_match(&upper)
+    }
+
+    /// Reconocer `CREATE [TEMP | TEMPORARY] TRIGGER`
+    fn is_create_trigger(upper_line: &str) -> bool {
+        let mut words = upper_line.split_whitespace();
+        words.next() == Some("CREATE")
+            && match words.next() {
+                Some("TEMP" | "TEMPORARY") => words.next() == Some("TRIGGER"),
+                next => next == Some("TRIGGER"),
+            }
+    }
+
+    /// Reconocer sentencias de control de transacciones
+    /// Sintaxis: BEGIN [TRANSACTION] | START TRANSACTION | COMMIT [TRANSACTION]
+    ///          | ROLLBACK [TRANSACTION] [TO [SAVEPOINT] nombre]
+    ///          | SAVEPOINT nombre | RELEASE [SAVEPOINT] nombre
+    fn parse_transaction_command(line: &str) -> Option<TransactionCommand> {
+        let words: Vec<&str> = line.trim_end_matches(';').split_whitespace().collect();
+        let upper: Vec<String> = words.iter().map(|w| w.to_uppercase()).collect();
+        let upper: Vec<&str> = upper.iter().map(String::as_str).collect();
+        let savepoint_name =
+            |name: &str| RqlProcessor::is_valid_identifier(name).then(|| name.to_string());
+
+        match upper.as_slice() {
+            ["BEGIN"] | ["BEGIN", "TRANSACTION" | "WORK"] | ["START", "TRANSACTION"] => {
+                Some(TransactionCommand::Begin)
+            }
+            ["COMMIT"] | ["COMMIT", "TRANSACTION" | "WORK"] => Some(TransactionCommand::Commit),
+            ["ROLLBACK"] | ["ROLLBACK", "TRANSACTION" | "WORK"] => {
+                Some(TransactionCommand::Rollback)
+            }
+            ["ROLLBACK", "TO", _]
+            | ["ROLLBACK", "TO", "SAVEPOINT", _]
+            | ["ROLLBACK", "TRANSACTION" | "WORK", "TO", _]
+            | ["ROLLBACK", "TRANSACTION" | "WORK", "TO", "SAVEPOINT", _] => {
+                savepoint_name(words[words.len() - 1]).map(TransactionCommand::RollbackTo)
+            }
+            ["SAVEPOINT", _] => savepoint_name(words[1]).map(TransactionCommand::Savepoint),
+            ["RELEASE", _] | ["RELEASE", "SAVEPOINT", _] => {
+                savepoint_name(words[words.len() - 1]).map(TransactionCommand::Release)
+            }
+            _ => None,
+        }
+    }
+
+    /// Parsear comando MERGE INTO
+    /// Sintaxis: MERGE INTO target [[AS] alias] USING source [[AS] alias] ON condition
+    ///           [WHEN MATCHED THEN UPDATE SET ...] [WHEN NOT MATCHED THEN INSERT ...]
+    ///
+    /// `source` puede ser una tabla o una subconsulta entre paréntesis.
+    fn parse_merge_command(&self, line: &str, line_num: usize) -> ParserResult<RqlStatement> {
+        let usage = || {
+            ParserError::syntax_error(
+                line_num,
+                1,
+                "MERGE requires format: MERGE INTO target USING source ON condition WHEN [NOT] MATCHED THEN ...",
+            )
+        };
+        let line = line.trim_end_matches(';').trim_end();
+
+        let header_regex =
+            Regex::new(r"(?is)^MERGE\s+INTO\s+(\S+(?:\s+(?:AS\s+)?\w+)?)\s+USING\s+(.+)$").unwrap();
+        let captures = header_regex.captures(line).ok_or_else(usage)?;
+        let target = captures[1].to_string();
+        let rest = captures.get(2).map_or("", |m| m.as_str());
+
+        // Origen: subconsulta completa o nombre de tabla, seguido del alias y ON
+        let split = if rest.starts_with('(') {
+            Self::find_closing_paren(rest)
+                .map(|close| close + 1)
+                .ok_or_else(|| {
+                    ParserError::syntax_error(line_num, 1, "Unclosed parenthesis in MERGE source")
+                })?
+        } else {
+            rest.find(char::is_whitespace).unwrap_or(rest.len())
+        };
+        let source_regex = Regex::new(r"(?is)^(\s+(?:AS\s+)?\w+)?\s+ON\s+(.+)$").unwrap();
+        let captures = source_regex.captures(&rest[split..]).ok_or_else(usage)?;
+        let source = match captures.get(1) {
+            Some(alias) => format!("{} {}", &rest[..split], alias.as_str().trim()),
+            None => rest[..split].to_string(),
+        };
+        let rest = captures.get(2).map_or("", |m| m.as_str());
+
+        let when_regex = Regex::new(r"(?i)\bWHEN\s+(NOT\s+)?MATCHED\s+THEN\b").unwrap();
+        let clauses: Vec<_> = when_regex.captures_iter(rest).collect();
+        let first = clauses.first().and_then(|c| c.get(0)).ok_or_else(usage)?;
+        let on_condition = rest[..first.start()].trim().to_string();
+        if on_condition.is_empty() {
+            return Err(usage());
+        }
+
+        let mut matched_action = None;
+        let mut not_matched_action = None;
+        for (idx, clause) in clauses.iter().enumerate() {
+            let whole = clause.get(0).ok_or_else(usage)?;
+            let end = clauses
+                .get(idx + 1)
+                .and_then(|next| next.get(0))
+                .map_or(rest.len(), |next| next.start());
+            let action = rest[whole.end()..end].trim().to_string();
+
+            let (slot, valid, kind) = if clause.get(1).is_some() {
+                let valid = crate::rql_ast::merge_insert_parts(&action).is_some();
+                (
+                    &mut not_matched_action,
+                    valid,
+                    "WHEN NOT MATCHED THEN INSERT (...) VALUES (...)",
+                )
+            } else {
+                let valid = crate::rql_ast::merge_update_assignments(&action).is_some();
+                (
+                    &mut matched_action,
+                    valid,
+                    "WHEN MATCHED THEN UPDATE SET ...",
+                )
+            };
+            if !valid {
+                return Err(ParserError::syntax_error(
+                    line_num,
+                    1,
+                    format!("MERGE expects {}, got '{}'", kind, action),
+                ));
+            }
+            if slot.replace(action).is_some() {
+                return Err(ParserError::syntax_error(
+                    line_num,
+                    1,
+                    "MERGE accepts each WHEN clause only once",
+                ));
+            }
+        }
+
+        Ok(RqlStatement::Merge {
+            target,
+            source,
+            on_condition,
+            matched_action,
+            not_matched_action,
+        })
+    }
+
+    /// Extraer `[source.]table` del segundo token de un comando
+    fn parse_table_spec(
+        line: &str,
+        line_num: usize,
+        command: &str,
+    ) -> ParserResult<(Option<String>, String)> {
         let parts: Vec<&str> = line.split_whitespace().collect();
         if parts.len() < 2 {
             return Err(ParserError::syntax_error(
                 line_num,
                 1,
-                "DESCRIBE command requires table name",
+                format!("{} command requires table name", command),
             ));
         }
 
@@ -359,8 +878,14 @@ impl RqlParser {
         } else {
             (None, table_spec.to_string())
         };
-
-        Ok(RqlStatement::Describe { source, table })
+        if table.is_empty() {
+            return Err(ParserError::syntax_error(
+                line_num,
+                1,
+                format!("{} command requires table name", command),
+            ));
+        }
+        Ok((source, table))
     }
 
     /// Parsear comando IMPORT
@@ -500,6 +1025,365 @@ impl RqlParser {
         })
     }
 
+    /// Parsear comando EXPORT SCHEMA
+    /// Sintaxis: EXPORT SCHEMA TO 'file'
+    fn parse_export_schema_command(
+        &self,
+        line: &str,
+        line_num: usize,
+    ) -> ParserResult<RqlStatement> {
+        let rest = line["EXPORT SCHEMA ".len()..]
+            .trim()
+            .trim_end_matches(';')
+            .trim_end();
+        if !rest.to_uppercase().starts_with("TO ") {
+            return Err(ParserError::syntax_error(
+                line_num,
+                1,
+                "EXPORT SCHEMA command requires TO clause",
+            ));
+        }
+
+        let path = rest[3..].trim(); // 3 = len("TO ")
+        match path.strip_prefix('\'').and_then(|p| p.strip_suffix('\'')) {
+            Some(file) if !file.is_empty() => Ok(RqlStatement::ExportSchema {
+                file: file.to_string(),
+            }),
+            _ => Err(ParserError::syntax_error(
+                line_num,
+                1,
+                "EXPORT SCHEMA TO requires quoted file path",
+            )),
+        }
+    }
+
+    /// Parsear comando EXPORT SOURCE
+    /// Sintaxis: EXPORT SOURCE alias TO 'dir/'
+    fn parse_export_source_command(
+        &self,
+        line: &str,
+        line_num: usize,
+    ) -> ParserResult<RqlStatement> {
+        let rest = line["EXPORT SOURCE ".len()..]
+            .trim()
+            .trim_end_matches(';')
+            .trim_end();
+        let upper_rest = rest.to_uppercase();
+        let Some(to_pos) = upper_rest.find(" TO ") else {
+            return Err(ParserError::syntax_error(
+                line_num,
+                1,
+                "EXPORT SOURCE command requires TO clause",
+            ));
+        };
+
+        let alias = rest[..to_pos].trim();
+        if alias.is_empty() || alias.contains(char::is_whitespace) {
+            return Err(ParserError::syntax_error(
+                line_num,
+                1,
+                "EXPORT SOURCE requires a source alias",
+            ));
+        }
+
+        let path = rest[to_pos + 4..].trim(); // 4 = len(" TO ")
+        match path.strip_prefix('\'').and_then(|p| p.strip_suffix('\'')) {
+            Some(dir) if !dir.is_empty() => Ok(RqlStatement::ExportSource {
+                alias: alias.to_string(),
+                path: dir.to_string(),
+            }),
+            _ => Err(ParserError::syntax_error(
+                line_num,
+                1,
+                "EXPORT SOURCE TO requires quoted directory path",
+            )),
+        }
+    }
+
+    /// Posición de la cláusula `INTO '` de un SELECT (o WITH) que escribe
+    /// a un archivo; el INTO de SQL (sin comillas) no cuenta, ni un
+    /// `INTO '` dentro de un literal, identificador o comentario
+    fn find_select_into(line: &str) -> Option<usize> {
+        let query = line.trim_start().to_uppercase();
+        if !query.starts_with("SELECT ") && !query.starts_with("WITH ") {
+            return None;
+        }
+        segments(line)
+            .into_iter()
+            .rev()
+            .filter(|segment| segment.kind == SegmentKind::String)
+            .find_map(|segment| {
+                let before = &line[..segment.range.start];
+                let into = before.len().checked_sub("INTO ".len())?;
+                let is_into = before.is_char_boundary(into)
+                    && before[into..].eq_ignore_ascii_case("INTO ")
+                    && before[..into]
+                        .chars()
+                        .next_back()
+                        .is_some_and(char::is_whitespace);
+                is_into.then_some(into)
+            })
+    }
+
+    /// Parsear SELECT ... INTO 'file' [FORMAT PARQUET|CSV] [OPTIONS (...)]
+    ///
+    /// Sin FORMAT el formato sale de la extensión del archivo.
+    fn parse_select_into_command(
+        &self,
+        line: &str,
+        into_pos: usize,
+        line_num: usize,
+    ) -> ParserResult<RqlStatement> {
+        let sql = line[..into_pos].trim().to_string();
+        let after_into = &line[into_pos + "INTO '".len()..];
+        let end = after_into.find('\'').ok_or_else(|| {
+            ParserError::syntax_error(line_num, 1, "Unclosed quote in SELECT ... INTO")
+        })?;
+        let file = after_into[..end].to_string();
+        if file.is_empty() {
+            return Err(ParserError::syntax_error(
+                line_num,
+                1,
+                "SELECT ... INTO requires a file path",
+            ));
+        }
+
+        let rest = after_into[end + 1..]
+            .trim()
+            .trim_end_matches(';')
+            .trim_end();
+        let upper_rest = rest.to_uppercase();
+        let (format, rest) = if let Some(after_format) = upper_rest.strip_prefix("FORMAT ") {
+            let name = after_format.split_whitespace().next().unwrap_or("");
+            let format = match name {
+                "PARQUET" => OutfileFormat::Parquet,
+                "CSV" => OutfileFormat::Csv,
+                _ => {
+                    return Err(ParserError::syntax_error(
+                        line_num,
+                        1,
+                        "SELECT ... INTO FORMAT must be PARQUET or CSV",
+                    ))
+                }
+            };
+            let skip = "FORMAT ".len() + (after_format.len() - after_format.trim_start().len());
+            (format, rest[skip + name.len()..].trim())
+        } else {
+            let format = OutfileFormat::from_path(&file).ok_or_else(|| {
+                ParserError::syntax_error(
+                    line_num,
+                    1,
+                    "SELECT ... INTO requires FORMAT PARQUET or CSV (or a .parquet/.csv file)",
+                )
+            })?;
+            (format, rest)
+        };
+
+        let options = if rest.is_empty() {
+            HashMap::new()
+        } else if rest.to_uppercase().starts_with("OPTIONS") {
+            self.parse_options(&format!(" {}", rest), line_num)?
+        } else {
+            return Err(ParserError::syntax_error(
+                line_num,
+                1,
+                "Unexpected text after SELECT ... INTO file",
+            ));
+        };
+
+        Ok(RqlStatement::SelectInto {
+            sql,
+            file,
+            format,
+            options,
+        })
+    }
+
+    /// Parsear comando SAVE BLOB
+    /// Sintaxis: SAVE BLOB (query) TO 'file'
+    fn parse_save_blob_command(&self, line: &str, line_num: usize) -> ParserResult<RqlStatement> {
+        let rest = line["SAVE BLOB ".len()..]
+            .trim()
+            .trim_end_matches(';')
+            .trim_end();
+        if !rest.starts_with('(') {
+            return Err(ParserError::syntax_error(
+                line_num,
+                1,
+                "SAVE BLOB requires a parenthesized query",
+            ));
+        }
+
+        let close = Self::find_closing_paren(rest).ok_or_else(|| {
+            ParserError::syntax_error(line_num, 1, "Unclosed parenthesis in SAVE BLOB command")
+        })?;
+        let sql = rest[1..close].trim().to_string();
+        if sql.is_empty() {
+            return Err(ParserError::syntax_error(
+                line_num,
+                1,
+                "SAVE BLOB requires a non-empty query",
+            ));
+        }
+
+        let target = rest[close + 1..].trim();
+        if !target.to_uppercase().starts_with("TO ") {
+            return Err(ParserError::syntax_error(
+                line_num,
+                1,
+                "SAVE BLOB command requires TO clause",
+            ));
+        }
+        match target[3..] // 3 = len("TO ")
+            .trim()
+            .strip_prefix('\'')
+            .and_then(|p| p.strip_suffix('\''))
+        {
+            Some(file) if !file.is_empty() => Ok(RqlStatement::SaveBlob {
+                sql,
+                file: file.to_string(),
+            }),
+            _ => Err(ParserError::syntax_error(
+                line_num,
+                1,
+                "SAVE BLOB TO requires quoted file path",
+            )),
+        }
+    }
+
+    /// Parsear comando ASSERT
+    /// Sintaxis: ASSERT [ROWCOUNT] (query) = valor [LABEL 'texto']
+    fn parse_assert_command(&self, line: &str, line_num: usize) -> ParserResult<RqlStatement> {
+        // Las palabras clave son ASCII: se comparan por bytes sin to_uppercase,
+        // que puede cambiar la longitud de otros caracteres
+        let mut rest = line
+            .get(7..) // 7 = len("ASSERT ")
+            .filter(|_| line[..7].eq_ignore_ascii_case("ASSERT "))
+            .ok_or_else(|| ParserError::syntax_error(line_num, 1, "Expected ASSERT command"))?
+            .trim()
+            .trim_end_matches(';')
+            .trim_end();
+
+        let count_mode = rest
+            .get(..8) // 8 = len("ROWCOUNT")
+            .is_some_and(|keyword| keyword.eq_ignore_ascii_case("ROWCOUNT"));
+        if count_mode {
+            rest = rest[8..].trim_start();
+        }
+
+        if !rest.starts_with('(') {
+            return Err(ParserError::syntax_error(
+                line_num,
+                1,
+                "ASSERT requires a parenthesized query",
+            ));
+        }
+
+        let close = Self::find_closing_paren(rest).ok_or_else(|| {
+            ParserError::syntax_error(line_num, 1, "Unclosed parenthesis in ASSERT command")
+        })?;
+        let sql = rest[1..close].trim().to_string();
+        if sql.is_empty() {
+            return Err(ParserError::syntax_error(
+                line_num,
+                1,
+                "ASSERT requires a non-empty query",
+            ));
+        }
+
+        // Separar LABEL '...' (opcional) de la comparación
+        let mut comparison = rest[close + 1..].trim();
+        let mut label = None;
+        if let Some(label_pos) = comparison.to_ascii_uppercase().find("LABEL ") {
+            let label_part = comparison[label_pos + 6..].trim(); // 6 = len("LABEL ")
+            label = Some(label_part.trim_matches('\'').trim_matches('"').to_string());
+            comparison = comparison[..label_pos].trim();
+        }
+
+        let literal = comparison.strip_prefix('=').map(str::trim).ok_or_else(|| {
+            ParserError::syntax_error(line_num, 1, "ASSERT requires '= <value>' after the query")
+        })?;
+
+        let (expected_count, expected_value) = if count_mode {
+            let count = literal.parse::<usize>().map_err(|_| {
+                ParserError::syntax_error(
+                    line_num,
+                    1,
+                    format!(
+                        "ASSERT ROWCOUNT expects a non-negative integer, got '{}'",
+                        literal
+                    ),
+                )
+            })?;
+            (Some(count), None)
+        } else {
+            (None, Some(Self::parse_assert_literal(literal, line_num)?))
+        };
+
+        Ok(RqlStatement::Assert {
+            sql,
+            expected_count,
+            expected_value,
+            label,
+        })
+    }
+
+    /// Encontrar el paréntesis que cierra el primero (ignorando strings)
+    fn find_closing_paren(input: &str) -> Option<usize> {
+        let mut depth = 0usize;
+        let mut quote: Option<char> = None;
+
+        for (idx, ch) in input.char_indices() {
+            match (quote, ch) {
+                (Some(q), c) if c == q => quote = None,
+                (Some(_), _) => {}
+                (None, '\'' | '"') => quote = Some(ch),
+                (None, '(') => depth += 1,
+                (None, ')') => {
+                    depth -= 1;
+                    if depth == 0 {
+                        return Some(idx);
+                    }
+                }
+                _ => {}
+            }
+        }
+
+        None
+    }
+
+    /// Parsear literal esperado de ASSERT (número, string, booleano o NULL)
+    fn parse_assert_literal(literal: &str, line_num: usize) -> ParserResult<serde_json::Value> {
+        let upper = literal.to_uppercase();
+
+        if upper == "NULL" {
+            return Ok(serde_json::Value::Null);
+        }
+        if upper == "TRUE" || upper == "FALSE" {
+            return Ok(serde_json::Value::Bool(upper == "TRUE"));
+        }
+        if literal.len() >= 2
+            && ((literal.starts_with('\'') && literal.ends_with('\''))
+                || (literal.starts_with('"') && literal.ends_with('"')))
+        {
+            return Ok(serde_json::Value::String(
+                literal[1..literal.len() - 1].to_string(),
+            ));
+        }
+        if let Ok(int) = literal.parse::<i64>() {
+            return Ok(serde_json::Value::from(int));
+        }
+        if let Ok(float) = literal.parse::<f64>() {
+            return Ok(serde_json::Value::from(float));
+        }
+
+        Err(ParserError::syntax_error(
+            line_num,
+            1,
+            format!("Invalid ASSERT value '{}'", literal),
+        ))
+    }
+
     /// Parsear comando MAP
     /// Sintaxis: MAP expression1 [AS alias1], expression2 [AS alias2], ...
     fn parse_map_command(&self, line: &str, line_num: usize) -> ParserResult<RqlStatement> {
@@ -690,6 +1574,7 @@ impl RqlParser {
         Ok(RqlStatement::Sql {
             sql: line.to_string(),
             parameters: HashMap::new(),
+            is_duckdb_specific: false,
         })
     }
 
@@ -700,25 +1585,28 @@ impl RqlParser {
         line_num: usize,
         ast: &mut RqlAst,
     ) -> ParserResult<()> {
-        // Parámetros posicionados: $1, $2, etc.
-        let positional_regex = Regex::new(r"\$(\d+)").unwrap();
-        for cap in positional_regex.captures_iter(line) {
-            let param_name = cap[0].to_string();
+        // Parámetros posicionados: $1, $2::INTEGER, etc.
+        for cap in positional_parameter().captures_iter(line) {
+            let param_name = format!("${}", &cap[1]);
             let position = cap[1].parse::<usize>().unwrap_or(0);
 
             let parameter = RqlParameter {
-                name: param_name.clone(),
+                name: param_name,
                 param_type: ParameterType::Positional,
                 position: Some(position),
                 line: line_num,
-                column: line.find(&param_name).unwrap_or(0) + 1,
+                column: cap.get(0).map_or(0, |m| m.start()) + 1,
+                cast_type: cap.get(2).map(|m| m.as_str().to_uppercase()),
             };
             ast.add_parameter(parameter);
         }
 
         // Parámetros nombrados: :name
-        let named_regex = Regex::new(r":([a-zA-Z_][a-zA-Z0-9_]*)").unwrap();
-        for cap in named_regex.captures_iter(line) {
+        for cap in named_parameter().captures_iter(line) {
+            let start = cap.get(0).map_or(0, |m| m.start());
+            if is_cast_at(line, start) {
+                continue;
+            }
             let param_name = format!(":{}", &cap[1]);
 
             let parameter = RqlParameter {
@@ -727,6 +1615,7 @@ impl RqlParser {
                 position: None,
                 line: line_num,
                 column: line.find(&cap[0]).unwrap_or(0) + 1,
+                cast_type: None,
             };
             ast.add_parameter(parameter);
         }
@@ -740,6 +1629,34 @@ impl RqlParser {
         Ok(())
     }
 
+    /// Extraer nombres de CTEs (`WITH name AS (...)`) de una query SQL
+    ///
+    /// Recorre el AST de sqlparser, incluyendo subconsultas, y retorna los
+    /// nombres en orden de aparición sin duplicados. Si el SQL no es válido
+    /// retorna un vector vacío.
+    pub fn extract_cte_names(sql: &str) -> Vec<String> {
+        let statements =
+            match sqlparser::parser::Parser::parse_sql(&sqlparser::dialect::GenericDialect {}, sql)
+            {
+                Ok(statements) => statements,
+                Err(_) => return Vec::new(),
+            };
+
+        collect_cte_names(&statements)
+    }
+
+    /// Extraer nombres de ventanas (`WINDOW w AS (...)`) de una query SQL
+    ///
+    /// Igual que `extract_cte_names`: recorre también las subconsultas,
+    /// retorna los nombres en orden de aparición sin duplicados y un vector
+    /// vacío si el SQL no es válido.
+    pub fn extract_window_names(sql: &str) -> Vec<String> {
+        match sqlparser::parser::Parser::parse_sql(&sqlparser::dialect::GenericDialect {}, sql) {
+            Ok(statements) => collect_windows(&statements).definitions,
+            Err(_) => Vec::new(),
+        }
+    }
+
     /// Extraer parámetros de query SQL usando sqlparser
     pub fn extract_sql_parameters(&self, sql: &str) -> ParserResult<Vec<RqlParameter>> {
         let mut parameters = Vec::new();
@@ -756,25 +1673,29 @@ impl RqlParser {
         if let Some(_statement) = sql_ast.first() {
             // Por ahora, usamos regex como fallback
             // En implementación futura, usar visitor pattern de sqlparser
-            let named_regex = Regex::new(r"\$(\d+)").unwrap();
-            for (i, cap) in named_regex.captures_iter(sql).enumerate() {
+            for (i, cap) in positional_parameter().captures_iter(sql).enumerate() {
                 parameters.push(RqlParameter {
-                    name: cap[0].to_string(),
+                    name: format!("${}", &cap[1]),
                     param_type: ParameterType::Positional,
                     position: Some(i + 1),
                     line: 1,
-                    column: sql.find(&cap[0]).unwrap_or(0) + 1,
+                    column: cap.get(0).map_or(0, |m| m.start()) + 1,
+                    cast_type: cap.get(2).map(|m| m.as_str().to_uppercase()),
                 });
             }
 
-            let named_params_regex = Regex::new(r":([a-zA-Z_][a-zA-Z0-9_]*)").unwrap();
-            for cap in named_params_regex.captures_iter(sql) {
+            for cap in named_parameter().captures_iter(sql) {
+                let start = cap.get(0).map_or(0, |m| m.start());
+                if is_cast_at(sql, start) {
+                    continue;
+                }
                 parameters.push(RqlParameter {
                     name: format!(":{}", &cap[1]),
                     param_type: ParameterType::Named,
                     position: None,
                     line: 1,
                     column: sql.find(&cap[0]).unwrap_or(0) + 1,
+                    cast_type: None,
                 });
             }
         }
@@ -783,6 +1704,93 @@ impl RqlParser {
     }
 }
 
+/// Visitor que recolecta los nombres de CTEs de un AST de sqlparser
+#[derive(Default)]
+struct CteCollector {
+    names: Vec<String>,
+}
+
+impl Visitor for CteCollector {
+    type Break = ();
+
+    fn pre_visit_query(&mut self, query: &Query) -> ControlFlow<Self::Break> {
+        if let Some(with) = &query.with {
+            for cte in &with.cte_tables {
+                let name = cte.alias.name.value.clone();
+                if !self.names.contains(&name) {
+                    self.names.push(name);
+                }
+            }
+        }
+        ControlFlow::Continue(())
+    }
+}
+
+/// Recolectar nombres de CTEs de statements ya parseados
+fn collect_cte_names(statements: &[Statement]) -> Vec<String> {
+    let mut collector = CteCollector::default();
+    for statement in statements {
+        let _ = statement.visit(&mut collector);
+    }
+    collector.names
+}
+
+/// Visitor que recolecta las ventanas con nombre definidas (`WINDOW w AS
+/// (...)`) y las referenciadas desde funciones (`OVER w`)
+#[derive(Default)]
+struct WindowCollector {
+    definitions: Vec<String>,
+    references: Vec<String>,
+}
+
+impl WindowCollector {
+    fn collect_definitions(&mut self, body: &SetExpr) {
+        match body {
+            SetExpr::Select(select) => {
+                for window in &select.named_window {
+                    if !self.definitions.contains(&window.0.value) {
+                        self.definitions.push(window.0.value.clone());
+                    }
+                }
+            }
+            SetExpr::SetOperation { left, right, .. } => {
+                self.collect_definitions(left);
+                self.collect_definitions(right);
+            }
+            _ => {}
+        }
+    }
+}
+
+impl Visitor for WindowCollector {
+    type Break = ();
+
+    fn pre_visit_query(&mut self, query: &Query) -> ControlFlow<Self::Break> {
+        self.collect_definitions(&query.body);
+        ControlFlow::Continue(())
+    }
+
+    fn pre_visit_expr(&mut self, expr: &Expr) -> ControlFlow<Self::Break> {
+        if let Expr::Function(function) = expr {
+            if let Some(WindowType::NamedWindow(name)) = &function.over {
+                if !self.references.contains(&name.value) {
+                    self.references.push(name.value.clone());
+                }
+            }
+        }
+        ControlFlow::Continue(())
+    }
+}
+
+/// Recolectar ventanas definidas y referenciadas de statements ya parseados
+fn collect_windows(statements: &[Statement]) -> WindowCollector {
+    let mut collector = WindowCollector::default();
+    for statement in statements {
+        let _ = statement.visit(&mut collector);
+    }
+    collector
+}
+
 /// Configuración del parser
 #[derive(Debug, Clone)]
 pub struct ParserConfig {
@@ -857,6 +1865,9 @@ impl RqlProcessor {
         // Validar comandos NQL
         self.validate_nql_commands(ast)?;
 
+        // Validar ventanas referenciadas con OVER w
+        self.validate_window_references(ast)?;
+
         // Optimizar statements
         self.optimize_statements(ast)?;
 
@@ -884,7 +1895,40 @@ impl RqlProcessor {
         Ok(())
     }
 
+    /// Validar que las ventanas usadas en `OVER w` estén definidas
+    ///
+    /// Cada sentencia sólo puede usar las ventanas de sus propias cláusulas
+    /// WINDOW (los nombres no distinguen mayúsculas).
+    fn validate_window_references(&self, ast: &RqlAst) -> ParserResult<()> {
+        for statement in &ast.statements {
+            let RqlStatement::Sql { sql, .. } = statement else {
+                continue;
+            };
+            let Ok(statements) =
+                sqlparser::parser::Parser::parse_sql(&sqlparser::dialect::GenericDialect {}, sql)
+            else {
+                continue;
+            };
+
+            let windows = collect_windows(&statements);
+            if let Some(missing) = windows.references.iter().find(|name| {
+                !windows
+                    .definitions
+                    .iter()
+                    .any(|defined| defined.eq_ignore_ascii_case(name))
+            }) {
+                return Err(ParserError::SqlParserError(format!(
+                    "ventana '{}' no definida: añade WINDOW {} AS (...) a la consulta",
+                    missing, missing
+                )));
+            }
+        }
+
+        Ok(())
+    }
+
     /// Validar comandos NQL específicos
+    #[allow(clippy::collapsible_match)]
     fn validate_nql_commands(&self, ast: &mut RqlAst) -> ParserResult<()> {
         use std::collections::HashSet;
 
@@ -952,11 +1996,14 @@ impl RqlProcessor {
                     }
                 }
 
-                RqlStatement::Describe { table, .. } => {
+                RqlStatement::Describe { table, .. }
+                | RqlStatement::Profile { table, .. }
+                | RqlStatement::Sample { table, .. } => {
                     // Validar que el nombre de tabla sea válido
                     if table.is_empty() || !Self::is_valid_identifier(table) {
                         ast.metadata.warnings.push(format!(
-                            "DESCRIBE: Invalid table name '{}'",
+                            "{}: Invalid table name '{}'",
+                            statement.statement_type(),
                             table
                         ));
                     }
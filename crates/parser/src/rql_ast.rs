@@ -1,5 +1,6 @@
 //! AST (Abstract Syntax Tree) para RQL (Extended SQL)
 
+use regex::Regex;
 use serde::{Deserialize, Serialize};
 use std::collections::HashMap;
 
@@ -15,6 +16,14 @@ pub struct RqlAst {
     /// Variables de sesión encontradas
     pub session_variables: Vec<String>,
 
+    /// Nombres de CTEs definidas en cláusulas WITH (pseudo-tablas)
+    #[serde(default)]
+    pub cte_names: Vec<String>,
+
+    /// Nombres de ventanas definidas en cláusulas WINDOW (`WINDOW w AS (...)`)
+    #[serde(default)]
+    pub window_names: Vec<String>,
+
     /// Metadatos del parsing
     pub metadata: ParsingMetadata,
 }
@@ -26,6 +35,10 @@ pub enum RqlStatement {
     Sql {
         sql: String,
         parameters: HashMap<String, ParameterType>,
+        /// SQL propio de DuckDB (`PIVOT`/`UNPIVOT`) que no pasó por
+        /// sqlparser; SQLite lo rechaza con `UnsupportedFeature`
+        #[serde(default)]
+        is_duckdb_specific: bool,
     },
 
     /// Comando USE para cambiar esquema/fuente de datos (RQL legacy)
@@ -38,6 +51,9 @@ pub enum RqlStatement {
         options: HashMap<String, String>,
     },
 
+    /// Comando USE SOURCE alias (cambiar la fuente activa)
+    UseSourceAlias { alias: String },
+
     /// Comando LET para variables de sesión
     Let {
         variable: String,
@@ -62,6 +78,52 @@ pub enum RqlStatement {
         table: String,
     },
 
+    /// Comando PROFILE table (estadísticas por columna)
+    Profile {
+        source: Option<String>,
+        table: String,
+    },
+
+    /// Comando SAMPLE table [LIMIT n] (muestra de filas sin recorrer la tabla)
+    Sample {
+        source: Option<String>,
+        table: String,
+        limit: usize,
+    },
+
+    /// Comando CHECKPOINT [source] (volcar escrituras pendientes al archivo)
+    Checkpoint { source: Option<String> },
+
+    /// Comando WATCH n sql (repetir la consulta cada `interval_secs` segundos)
+    Watch { interval_secs: u64, sql: String },
+
+    /// Comando CREATE VIEW name AS sql (vista sobre la fuente activa)
+    ///
+    /// `statement` es la sentencia tal como se escribió, para ejecutarla
+    /// sobre el backend cuando no hay fuente activa.
+    CreateView {
+        name: String,
+        sql: String,
+        statement: String,
+    },
+
+    /// Comando MERGE INTO (upsert)
+    ///
+    /// `target` y `source` conservan el alias tal como se escribió
+    /// (`clientes AS c`, `(SELECT ...) n`).
+    Merge {
+        target: String,
+        source: String,
+        on_condition: String,
+        /// Acción `WHEN MATCHED THEN` (`UPDATE SET ...`)
+        matched_action: Option<String>,
+        /// Acción `WHEN NOT MATCHED THEN` (`INSERT (...) VALUES (...)`)
+        not_matched_action: Option<String>,
+    },
+
+    /// Control de transacciones (BEGIN, COMMIT, ROLLBACK, SAVEPOINT, ...)
+    Transaction { command: TransactionCommand },
+
     /// Comando IMPORT
     Import {
         file: String,
@@ -77,6 +139,29 @@ pub enum RqlStatement {
         options: HashMap<String, String>,
     },
 
+    /// Comando EXPORT SCHEMA (catálogo TOML con el esquema de las fuentes)
+    ExportSchema { file: String },
+
+    /// Comando EXPORT SOURCE alias TO 'dir/' (paquete portable con esquema
+    /// y datos de la fuente)
+    ExportSource { alias: String, path: String },
+
+    /// Comando SAVE BLOB (query) TO 'file'
+    ///
+    /// Guarda en `file` el BLOB de la única fila y columna de `sql`.
+    SaveBlob { sql: String, file: String },
+
+    /// Consulta con destino de archivo: SELECT ... INTO 'file' [FORMAT f]
+    /// [OPTIONS (...)]
+    ///
+    /// `sql` es la consulta sin la cláusula INTO.
+    SelectInto {
+        sql: String,
+        file: String,
+        format: OutfileFormat,
+        options: HashMap<String, String>,
+    },
+
     /// Comando MAP (transformaciones)
     Map { expressions: Vec<MapExpression> },
 
@@ -97,6 +182,18 @@ pub enum RqlStatement {
         destination: OutputDestination,
         format: OutputFormat,
     },
+
+    /// Comando ASSERT (validación de resultados en scripts batch)
+    Assert {
+        /// Query interna a ejecutar
+        sql: String,
+        /// Cantidad de filas esperada (`ASSERT ROWCOUNT (...) = n`)
+        expected_count: Option<usize>,
+        /// Valor escalar esperado (`ASSERT (...) = valor`)
+        expected_value: Option<serde_json::Value>,
+        /// Etiqueta descriptiva (`LABEL '...'`)
+        label: Option<String>,
+    },
 }
 
 /// Expresión para MAP
@@ -106,6 +203,42 @@ pub struct MapExpression {
     pub alias: Option<String>,
 }
 
+/// Sentencias de control de transacciones
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub enum TransactionCommand {
+    /// BEGIN [TRANSACTION] / START TRANSACTION
+    Begin,
+
+    /// COMMIT [TRANSACTION] / END [TRANSACTION]
+    Commit,
+
+    /// ROLLBACK [TRANSACTION]
+    Rollback,
+
+    /// SAVEPOINT nombre
+    Savepoint(String),
+
+    /// ROLLBACK [TRANSACTION] TO [SAVEPOINT] nombre
+    RollbackTo(String),
+
+    /// RELEASE [SAVEPOINT] nombre
+    Release(String),
+}
+
+impl TransactionCommand {
+    /// SQL equivalente
+    pub fn to_sql(&self) -> String {
+        match self {
+            TransactionCommand::Begin => "BEGIN".to_string(),
+            TransactionCommand::Commit => "COMMIT".to_string(),
+            TransactionCommand::Rollback => "ROLLBACK".to_string(),
+            TransactionCommand::Savepoint(name) => format!("SAVEPOINT {}", name),
+            TransactionCommand::RollbackTo(name) => format!("ROLLBACK TO SAVEPOINT {}", name),
+            TransactionCommand::Release(name) => format!("RELEASE SAVEPOINT {}", name),
+        }
+    }
+}
+
 /// Formato de exportación
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub enum ExportFormat {
@@ -114,6 +247,27 @@ pub enum ExportFormat {
     Xlsx,
 }
 
+/// Formato de archivo de SELECT ... INTO
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize)]
+pub enum OutfileFormat {
+    Parquet,
+    Csv,
+}
+
+impl OutfileFormat {
+    /// Formato según la extensión del archivo (`.parquet`, `.csv`)
+    pub fn from_path(file: &str) -> Option<Self> {
+        let lower = file.to_lowercase();
+        if lower.ends_with(".parquet") {
+            Some(OutfileFormat::Parquet)
+        } else if lower.ends_with(".csv") {
+            Some(OutfileFormat::Csv)
+        } else {
+            None
+        }
+    }
+}
+
 /// Parámetro extraído del código RQL
 #[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
 pub struct RqlParameter {
@@ -131,6 +285,10 @@ pub struct RqlParameter {
 
     /// Columna donde fue encontrado
     pub column: usize,
+
+    /// Tipo del cast `::tipo` que sigue al parámetro (`$1::INTEGER` → "INTEGER")
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub cast_type: Option<String>,
 }
 
 /// Tipos de parámetros soportados
@@ -216,6 +374,8 @@ impl RqlAst {
             statements: Vec::new(),
             parameters: Vec::new(),
             session_variables: Vec::new(),
+            cte_names: Vec::new(),
+            window_names: Vec::new(),
             metadata: ParsingMetadata::default(),
         }
     }
@@ -237,6 +397,27 @@ impl RqlAst {
         }
     }
 
+    /// Agregar nombre de CTE
+    pub fn add_cte_name(&mut self, name: String) {
+        if !self.cte_names.contains(&name) {
+            self.cte_names.push(name);
+        }
+    }
+
+    /// Agregar nombre de ventana (cláusula WINDOW)
+    pub fn add_window_name(&mut self, name: String) {
+        if !self.window_names.contains(&name) {
+            self.window_names.push(name);
+        }
+    }
+
+    /// Verificar si un nombre corresponde a una CTE (case-insensitive)
+    pub fn is_cte(&self, name: &str) -> bool {
+        self.cte_names
+            .iter()
+            .any(|cte| cte.eq_ignore_ascii_case(name))
+    }
+
     /// Obtener todos los parámetros únicos
     pub fn get_parameters(&self) -> Vec<&RqlParameter> {
         self.parameters.iter().collect()
@@ -299,6 +480,7 @@ impl RqlAst {
                     };
                     format!("USE '{}'{}{};", path, alias_str, opts_str)
                 }
+                RqlStatement::UseSourceAlias { alias } => format!("USE SOURCE {};", alias),
                 RqlStatement::Let {
                     variable,
                     expression,
@@ -324,6 +506,41 @@ impl RqlAst {
                         format!("DESCRIBE {};", table)
                     }
                 }
+                RqlStatement::Profile { source, table } => {
+                    if let Some(src) = source {
+                        format!("PROFILE {}.{};", src, table)
+                    } else {
+                        format!("PROFILE {};", table)
+                    }
+                }
+                RqlStatement::Sample {
+                    source,
+                    table,
+                    limit,
+                } => {
+                    if let Some(src) = source {
+                        format!("SAMPLE {}.{} LIMIT {};", src, table, limit)
+                    } else {
+                        format!("SAMPLE {} LIMIT {};", table, limit)
+                    }
+                }
+                RqlStatement::Watch { interval_secs, sql } => {
+                    format!("WATCH {} {};", interval_secs, sql)
+                }
+                RqlStatement::Checkpoint { source } => {
+                    if let Some(src) = source {
+                        format!("CHECKPOINT {};", src)
+                    } else {
+                        "CHECKPOINT;".to_string()
+                    }
+                }
+                RqlStatement::CreateView { name, sql, .. } => {
+                    format!("CREATE VIEW {} AS {};", name, sql)
+                }
+                RqlStatement::Merge { .. } => {
+                    format!("{};", stmt.merge_sql().unwrap_or_default())
+                }
+                RqlStatement::Transaction { command } => format!("{};", command.to_sql()),
                 RqlStatement::Import { file, table, options } => {
                     let opts_str = if options.is_empty() {
                         String::new()
@@ -353,6 +570,31 @@ impl RqlAst {
                     };
                     format!("EXPORT {} TO '{}' FORMAT {}{};", query, file, format_str, opts_str)
                 }
+                RqlStatement::ExportSchema { file } => {
+                    format!("EXPORT SCHEMA TO '{}';", file)
+                }
+                RqlStatement::ExportSource { alias, path } => {
+                    format!("EXPORT SOURCE {} TO '{}';", alias, path)
+                }
+                RqlStatement::SaveBlob { sql, file } => {
+                    format!("SAVE BLOB ({}) TO '{}';", sql, file)
+                }
+                RqlStatement::SelectInto { sql, file, format, options } => {
+                    let format_str = match format {
+                        OutfileFormat::Parquet => "PARQUET",
+                        OutfileFormat::Csv => "CSV",
+                    };
+                    let opts_str = if options.is_empty() {
+                        String::new()
+                    } else {
+                        let opts: Vec<String> = options
+                            .iter()
+                            .map(|(k, v)| format!("{}={}", k, v))
+                            .collect();
+                        format!(" OPTIONS ({})", opts.join(", "))
+                    };
+                    format!("{} INTO '{}' FORMAT {}{};", sql, file, format_str, opts_str)
+                }
                 RqlStatement::Map { expressions } => {
                     let exprs: Vec<String> = expressions
                         .iter()
@@ -392,6 +634,30 @@ impl RqlAst {
                     };
                     format!("OUTPUT TO {} FORMAT {};", dest_str, format_str)
                 }
+                RqlStatement::Assert {
+                    sql,
+                    expected_count,
+                    expected_value,
+                    label,
+                } => {
+                    let label_str = label
+                        .as_ref()
+                        .map(|l| format!(" LABEL '{}'", l))
+                        .unwrap_or_default();
+                    match (expected_count, expected_value) {
+                        (Some(count), _) => {
+                            format!("ASSERT ROWCOUNT ({}) = {}{};", sql, count, label_str)
+                        }
+                        (None, Some(value)) => {
+                            let value_str = match value {
+                                serde_json::Value::String(s) => format!("'{}'", s),
+                                other => other.to_string(),
+                            };
+                            format!("ASSERT ({}) = {}{};", sql, value_str, label_str)
+                        }
+                        (None, None) => format!("ASSERT ({}){};", sql, label_str),
+                    }
+                }
             })
             .collect::<Vec<_>>()
             .join("\n")
@@ -439,19 +705,32 @@ impl RqlStatement {
             RqlStatement::Sql { .. } => "SQL",
             RqlStatement::Use { .. } => "USE",
             RqlStatement::UseSource { .. } => "USE_SOURCE",
+            RqlStatement::UseSourceAlias { .. } => "USE_SOURCE_ALIAS",
             RqlStatement::Let { .. } => "LET",
             RqlStatement::Unset { .. } => "UNSET",
             RqlStatement::ShowSources => "SHOW_SOURCES",
             RqlStatement::ShowTables { .. } => "SHOW_TABLES",
             RqlStatement::ShowVars => "SHOW_VARS",
             RqlStatement::Describe { .. } => "DESCRIBE",
+            RqlStatement::Profile { .. } => "PROFILE",
+            RqlStatement::Sample { .. } => "SAMPLE",
+            RqlStatement::Checkpoint { .. } => "CHECKPOINT",
+            RqlStatement::Watch { .. } => "WATCH",
+            RqlStatement::CreateView { .. } => "CREATE_VIEW",
+            RqlStatement::Merge { .. } => "MERGE",
+            RqlStatement::Transaction { .. } => "TRANSACTION",
             RqlStatement::Import { .. } => "IMPORT",
             RqlStatement::Export { .. } => "EXPORT",
+            RqlStatement::ExportSchema { .. } => "EXPORT_SCHEMA",
+            RqlStatement::ExportSource { .. } => "EXPORT_SOURCE",
+            RqlStatement::SaveBlob { .. } => "SAVE_BLOB",
+            RqlStatement::SelectInto { .. } => "SELECT_INTO",
             RqlStatement::Map { .. } => "MAP",
             RqlStatement::Filter { .. } => "FILTER",
             RqlStatement::FormLoad { .. } => "FORM_LOAD",
             RqlStatement::ExecForm { .. } => "EXECFORM",
             RqlStatement::OutputTo { .. } => "OUTPUT_TO",
+            RqlStatement::Assert { .. } => "ASSERT",
         }
     }
 
@@ -473,4 +752,93 @@ impl RqlStatement {
             None
         }
     }
+
+    /// SQL `MERGE INTO` estándar (DuckDB, PostgreSQL 15+)
+    pub fn merge_sql(&self) -> Option<String> {
+        let RqlStatement::Merge {
+            target,
+            source,
+            on_condition,
+            matched_action,
+            not_matched_action,
+        } = self
+        else {
+            return None;
+        };
+
+        let mut sql = format!("MERGE INTO {} USING {} ON {}", target, source, on_condition);
+        if let Some(action) = matched_action {
+            sql.push_str(&format!(" WHEN MATCHED THEN {}", action));
+        }
+        if let Some(action) = not_matched_action {
+            sql.push_str(&format!(" WHEN NOT MATCHED THEN {}", action));
+        }
+        Some(sql)
+    }
+
+    /// Sentencias equivalentes a un MERGE para SQLite, que no lo soporta
+    ///
+    /// `WHEN MATCHED` pasa a `UPDATE ... FROM` y `WHEN NOT MATCHED` a un
+    /// `INSERT ... SELECT` de las filas del origen sin pareja en el destino.
+    /// El UPDATE va primero para no alcanzar a las filas recién insertadas;
+    /// quien las ejecute debe hacerlo dentro de una transacción.
+    pub fn sqlite_merge_statements(&self) -> Option<Vec<String>> {
+        let RqlStatement::Merge {
+            target,
+            source,
+            on_condition,
+            matched_action,
+            not_matched_action,
+        } = self
+        else {
+            return None;
+        };
+
+        // UPDATE sólo admite el alias del destino con AS
+        let mut words = target.split_whitespace();
+        let table = words.next().unwrap_or(target);
+        let target = match words.last() {
+            Some(alias) => format!("{} AS {}", table, alias),
+            None => table.to_string(),
+        };
+
+        let mut statements = Vec::new();
+        if let Some(action) = matched_action {
+            let assignments = merge_update_assignments(action)?;
+            statements.push(format!(
+                "UPDATE {} SET {} FROM {} WHERE {}",
+                target, assignments, source, on_condition
+            ));
+        }
+        if let Some(action) = not_matched_action {
+            let (columns, values) = merge_insert_parts(action)?;
+            let columns = columns.map(|c| format!(" ({})", c)).unwrap_or_default();
+            statements.push(format!(
+                "INSERT INTO {}{} SELECT {} FROM {} WHERE NOT EXISTS (SELECT 1 FROM {} WHERE {})",
+                table, columns, values, source, target, on_condition
+            ));
+        }
+        Some(statements)
+    }
+}
+
+/// Asignaciones de una acción `UPDATE SET ...`
+pub(crate) fn merge_update_assignments(action: &str) -> Option<&str> {
+    let update_regex = Regex::new(r"(?is)^UPDATE\s+SET\s+(.+)$").unwrap();
+    let captures = update_regex.captures(action.trim())?;
+    captures.get(1).map(|m| m.as_str().trim())
+}
+
+/// Columnas (opcionales) y valores de una acción `INSERT (...) VALUES (...)`
+pub(crate) fn merge_insert_parts(action: &str) -> Option<(Option<&str>, &str)> {
+    let insert_regex =
+        Regex::new(r"(?is)^INSERT\s*(?:\((?P<columns>[^)]*)\)\s*)?VALUES\s*\((?P<values>.*)\)$")
+            .unwrap();
+    let captures = insert_regex.captures(action.trim())?;
+    let values = captures.name("values")?.as_str().trim();
+    if values.is_empty() {
+        return None;
+    }
+    let columns = captures.name("columns").map(|c| c.as_str().trim());
+    Some((columns, values))
 }
@@ -41,12 +41,12 @@ impl TemplateEngine {
             }
         }
 
+        // Expandir loops antes que los condicionales, que pueden envolverlos
+        result = self.process_loops(&result, variables)?;
+
         // Procesar condicionales simples
         result = self.process_conditionals(&result, variables)?;
 
-        // Procesar loops simples
-        result = self.process_loops(&result, variables)?;
-
         Ok(result)
     }
 
@@ -94,32 +94,45 @@ impl TemplateEngine {
         Ok(result)
     }
 
-    /// Procesar loops simples
+    /// Procesar bloques `{{#each variable}} ... {{/each}}`
+    ///
+    /// El valor de la variable puede ser un array JSON (`["a", "b"]`) o una
+    /// lista separada por comas (`a, b`). Dentro del bloque, `{{this}}` es el
+    /// elemento actual y `{{@index}}` su posición (desde 0); `@first` y `@last`
+    /// pueden usarse en `{{#if}}`/`{{#unless}}`, p. ej. para separadores.
+    /// Si la variable no existe el bloque se elimina.
     fn process_loops(
         &self,
         template: &str,
         variables: &HashMap<String, String>,
     ) -> ParserResult<String> {
-        let mut result = template.to_string();
-
-        // {{#each array}} ... {{/each}}
         let each_regex =
-            Regex::new(r"\{\{#each\s+([a-zA-Z_][a-zA-Z0-9_]*)\s*\}\}(.*?)\{\{/each\}\}").unwrap();
-        for cap in each_regex.captures_iter(template) {
-            let var_name = &cap[1];
-            let content = &cap[2];
-
-            if let Some(items_str) = variables.get(var_name) {
-                // Simular processing de array (en implementación real usar parsing JSON)
-                let items: Vec<&str> = items_str.split(',').map(|s| s.trim()).collect();
-                let processed_items: Vec<String> = items
-                    .iter()
-                    .map(|item| content.replace("@this", item))
-                    .collect();
+            Regex::new(r"(?s)\{\{#each\s+([a-zA-Z_][a-zA-Z0-9_]*)\s*\}\}(.*?)\{\{/each\}\}")
+                .unwrap();
 
-                result = result.replace(&cap[0], &processed_items.join(" "));
+        let mut result = String::with_capacity(template.len());
+        let mut last_end = 0;
+        for cap in each_regex.captures_iter(template) {
+            let block = cap.get(0).unwrap();
+            result.push_str(&template[last_end..block.start()]);
+            last_end = block.end();
+
+            let items = match variables.get(&cap[1]) {
+                Some(value) => parse_each_items(value)?,
+                None => Vec::new(),
+            };
+            let body = &cap[2];
+            for (index, item) in items.iter().enumerate() {
+                let content = resolve_loop_conditionals(body, index == 0, index + 1 == items.len());
+                result.push_str(
+                    &content
+                        .replace("{{this}}", item)
+                        .replace("{{@index}}", &index.to_string())
+                        .replace("@this", item),
+                );
             }
         }
+        result.push_str(&template[last_end..]);
 
         Ok(result)
     }
@@ -155,10 +168,84 @@ impl TemplateEngine {
             }
         }
 
+        // Verificar el orden de apertura/cierre de los bloques
+        let block_regex = Regex::new(r"\{\{([#/])(if|unless|each)\b([^}]*)\}\}").unwrap();
+        let mut open_blocks: Vec<&str> = Vec::new();
+        for cap in block_regex.captures_iter(template) {
+            let name = cap.get(2).unwrap().as_str();
+            if &cap[1] == "#" {
+                if name == "each" && cap[3].trim().is_empty() {
+                    return Err(ParserError::template_error(
+                        "each block requires a variable",
+                    ));
+                }
+                if name == "each" && open_blocks.contains(&"each") {
+                    return Err(ParserError::template_error(
+                        "Nested each blocks are not supported",
+                    ));
+                }
+                open_blocks.push(name);
+            } else if open_blocks.pop() != Some(name) {
+                return Err(ParserError::template_error(format!(
+                    "Unexpected {{{{/{}}}}}",
+                    name
+                )));
+            }
+        }
+
         Ok(())
     }
 }
 
+/// Obtener los elementos de una variable usada en `{{#each}}`
+fn parse_each_items(value: &str) -> ParserResult<Vec<String>> {
+    let value = value.trim();
+    if value.is_empty() {
+        return Ok(Vec::new());
+    }
+
+    if value.starts_with('[') {
+        let items: Vec<serde_json::Value> = serde_json::from_str(value).map_err(|e| {
+            ParserError::template_error(format!("Invalid JSON array in each block: {}", e))
+        })?;
+        return Ok(items
+            .into_iter()
+            .map(|item| match item {
+                serde_json::Value::String(s) => s,
+                other => other.to_string(),
+            })
+            .collect());
+    }
+
+    Ok(value.split(',').map(|s| s.trim().to_string()).collect())
+}
+
+/// Resolver `{{#if @first}}`, `{{#unless @last}}`, etc. dentro de un `{{#each}}`
+fn resolve_loop_conditionals(body: &str, first: bool, last: bool) -> String {
+    let flag = |name: &str| if name == "first" { first } else { last };
+
+    let if_regex = Regex::new(r"(?s)\{\{#if\s+@(first|last)\s*\}\}(.*?)\{\{/if\}\}").unwrap();
+    let result = if_regex.replace_all(body, |cap: &regex::Captures| {
+        if flag(&cap[1]) {
+            cap[2].to_string()
+        } else {
+            String::new()
+        }
+    });
+
+    let unless_regex =
+        Regex::new(r"(?s)\{\{#unless\s+@(first|last)\s*\}\}(.*?)\{\{/unless\}\}").unwrap();
+    unless_regex
+        .replace_all(&result, |cap: &regex::Captures| {
+            if flag(&cap[1]) {
+                String::new()
+            } else {
+                cap[2].to_string()
+            }
+        })
+        .into_owned()
+}
+
 /// Configuración del motor de templates
 #[derive(Debug, Clone)]
 pub struct TemplateEngineConfig {
@@ -1,7 +1,6 @@
 mod parser_tests {
-    use super::*;
-    use crate::parser::RqlParser;
-    use crate::rql_ast::{RqlAst, RqlStatement, RqlParameter, ParameterType};
+    use crate::parser::{RqlParser, RqlProcessor};
+    use crate::rql_ast::{ParameterType, RqlAst, RqlStatement};
 
     #[tokio::test]
     async fn test_parse_simple_select() {
@@ -80,6 +79,17 @@ mod parser_tests {
         }
     }
 
+    #[tokio::test]
+    async fn test_parse_sql_keeps_session_variables() {
+        let parser = RqlParser::new();
+        let input = "SELECT * FROM emp WHERE dept = #dept";
+
+        let ast = parser.parse_rql(input).await.unwrap();
+
+        // La expansión de #dept la hace el executor
+        assert_eq!(ast.get_sql_statements(), vec![input]);
+    }
+
     #[tokio::test]
     async fn test_parse_form_load_command() {
         let parser = RqlParser::new();
@@ -136,6 +146,32 @@ mod parser_tests {
         assert_eq!(params[1].name, ":nombre");
     }
 
+    #[tokio::test]
+    async fn test_positional_parameter_casts() {
+        let parser = RqlParser::new();
+        let input =
+            "SELECT * FROM t WHERE id = $1::integer AND total > $2::DECIMAL(10, 2) AND name = $3";
+
+        let ast = parser.parse_rql(input).await.unwrap();
+
+        assert_eq!(ast.parameters.len(), 3);
+        assert_eq!(ast.parameters[0].name, "$1");
+        assert_eq!(ast.parameters[0].cast_type.as_deref(), Some("INTEGER"));
+        assert_eq!(
+            ast.parameters[1].cast_type.as_deref(),
+            Some("DECIMAL(10, 2)")
+        );
+        assert_eq!(ast.parameters[2].cast_type, None);
+
+        // `::TEXT` no es un parámetro nombrado
+        let params = parser
+            .extract_sql_parameters("SELECT $1::TEXT, created::DATE FROM t WHERE a = :a")
+            .unwrap();
+        assert_eq!(params.len(), 2);
+        assert_eq!(params[0].cast_type.as_deref(), Some("TEXT"));
+        assert_eq!(params[1].name, ":a");
+    }
+
     #[tokio::test]
     async fn test_session_variables() {
         let parser = RqlParser::new();
@@ -163,7 +199,8 @@ mod parser_tests {
     fn test_rql_statement_type() {
         let stmt = RqlStatement::Sql { 
             sql: "SELECT 1".to_string(), 
-            parameters: std::collections::HashMap::new() 
+            parameters: std::collections::HashMap::new(),
+            is_duckdb_specific: false,
         };
         
         assert_eq!(stmt.statement_type(), "SQL");
@@ -187,6 +224,7 @@ mod parser_tests {
         ast.add_statement(RqlStatement::Sql {
             sql: "SELECT * FROM employees".to_string(),
             parameters: std::collections::HashMap::new(),
+            is_duckdb_specific: false,
         });
         
         let sql = ast.to_sql();
@@ -222,6 +260,122 @@ mod parser_tests {
         assert_eq!(ast.statements.len(), 2);
     }
 
+    fn sql_texts(ast: &RqlAst) -> Vec<String> {
+        ast.statements
+            .iter()
+            .filter_map(|stmt| match stmt {
+                RqlStatement::Sql { sql, .. } => Some(sql.clone()),
+                _ => None,
+            })
+            .collect()
+    }
+
+    #[tokio::test]
+    async fn test_split_semicolons_in_literals() {
+        let parser = RqlParser::new();
+
+        let ast = parser
+            .parse_rql("SELECT ';' AS sep; SELECT 2;")
+            .await
+            .unwrap();
+        let sqls = sql_texts(&ast);
+        assert_eq!(sqls.len(), 2);
+        assert!(sqls[0].contains("';'"), "{:?}", sqls);
+
+        let ast = parser
+            .parse_rql(r#"SELECT "a;b" FROM t; SELECT 'it''s; fine'; SELECT $$x;y$$, $f$;$f$"#)
+            .await
+            .unwrap();
+        let sqls = sql_texts(&ast);
+        assert_eq!(sqls.len(), 3, "{:?}", sqls);
+        assert!(sqls[1].contains("it''s; fine"));
+        assert!(sqls[2].contains("$$x;y$$") && sqls[2].contains("$f$;$f$"));
+    }
+
+    #[tokio::test]
+    async fn test_split_comments_with_quotes() {
+        let parser = RqlParser::new();
+        let input = "-- it's a comment; not a statement\n\
+                     SELECT 1; /* don't \"split\" */ SELECT 2 -- trailing ';\n;";
+
+        let ast = parser.parse_rql(input).await.unwrap();
+
+        let sqls = sql_texts(&ast);
+        assert_eq!(sqls.len(), 2, "{:?}", sqls);
+        assert!(!sqls[1].contains("trailing"));
+    }
+
+    #[tokio::test]
+    async fn test_split_block_comment_spanning_statements() {
+        let parser = RqlParser::new();
+        let input =
+            "SELECT 1;\n/* SELECT 2;\nSELECT 3; */\nSELECT *\n  FROM employees\n  WHERE id = $1";
+
+        let ast = parser.parse_rql(input).await.unwrap();
+
+        let sqls = sql_texts(&ast);
+        assert_eq!(sqls.len(), 2, "{:?}", sqls);
+        assert!(sqls[1].contains("employees"));
+        // La sentencia sin `;` final empieza en la línea 4
+        assert_eq!(ast.parameters.len(), 1);
+        assert_eq!(ast.parameters[0].line, 4);
+    }
+
+    #[tokio::test]
+    async fn test_split_keeps_trigger_body() {
+        let parser = RqlParser::new();
+        let input = "CREATE TRIGGER clasificar AFTER INSERT ON t BEGIN\n\
+                       UPDATE t SET tipo = CASE WHEN n > 0 THEN 'pos' ELSE 'neg' END;\n\
+                       INSERT INTO log VALUES (new.id);\n\
+                     END;\n\
+                     SELECT 1;";
+
+        let ast = parser.parse_rql(input).await.unwrap();
+
+        let sqls = sql_texts(&ast);
+        assert_eq!(sqls.len(), 2, "{:?}", sqls);
+        assert!(sqls[0].starts_with("CREATE TRIGGER") && sqls[0].ends_with("END;"));
+    }
+
+    #[tokio::test]
+    async fn test_syntax_error_position_in_multiline_statement() {
+        use crate::error::ParserError;
+
+        let parser = RqlParser::new();
+        let input =
+            "SELECT id,\n       nombre\n  FROM empleados WHERE id = = 1\n ORDER BY id\n LIMIT 5;";
+
+        let error = parser.parse_rql(input).await.unwrap_err();
+        let ParserError::SyntaxError { line, column, .. } = &error else {
+            panic!("{:?}", error);
+        };
+        assert_eq!((*line, *column), (3, 29));
+        assert_eq!(
+            error.snippet(),
+            Some("3 |   FROM empleados WHERE id = = 1\n  |                             ^")
+        );
+
+        // Sentencias y comentarios anteriores no desplazan la posición
+        let input = "SELECT 1;\n-- otra consulta\nSELECT id /* id, */\n  FROM t\n  WHERE id = = 1;";
+        let error = parser.parse_rql(input).await.unwrap_err();
+        assert!(
+            matches!(
+                error,
+                ParserError::SyntaxError {
+                    line: 5,
+                    column: 14,
+                    ..
+                }
+            ),
+            "{:?}",
+            error
+        );
+        let error: noctra_core::NoctraError = error.into();
+        assert!(error
+            .snippet()
+            .is_some_and(|snippet| snippet.starts_with("5 |   WHERE id = = 1")));
+    }
+
     #[test]
     fn test_ast_default() {
         let ast = RqlAst::default();
@@ -229,7 +383,60 @@ mod parser_tests {
         assert!(ast.statements.is_empty());
         assert!(ast.parameters.is_empty());
         assert!(ast.session_variables.is_empty());
-        assert!(ast.metadata.parsing_time_us >= 0);
+        assert_eq!(ast.metadata.parsing_time_us, 0);
+    }
+
+    #[test]
+    fn test_extract_cte_names() {
+        let sql = "WITH ventas AS (SELECT * FROM orders), top AS (SELECT * FROM ventas LIMIT 10) SELECT * FROM top";
+
+        let names = RqlParser::extract_cte_names(sql);
+
+        assert_eq!(names, vec!["ventas".to_string(), "top".to_string()]);
+        assert!(RqlParser::extract_cte_names("SELECT * FROM orders").is_empty());
+        assert!(RqlParser::extract_cte_names("WITH roto AS").is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_parse_with_cte_populates_ast() {
+        let parser = RqlParser::new();
+        let input = "WITH a AS (SELECT 1 AS x), b AS (SELECT x FROM a) SELECT * FROM b";
+
+        let ast = parser.parse_rql(input).await.unwrap();
+
+        assert_eq!(ast.statements.len(), 1);
+        assert_eq!(ast.cte_names, vec!["a".to_string(), "b".to_string()]);
+        assert!(ast.is_cte("A"));
+        assert!(!ast.is_cte("c"));
+    }
+
+    #[test]
+    fn test_extract_window_names() {
+        let sql = "SELECT name, RANK() OVER w, SUM(x) OVER (PARTITION BY dept) FROM (SELECT * FROM t WINDOW inner_w AS (ORDER BY x)) s WINDOW w AS (PARTITION BY dept ORDER BY salary DESC)";
+
+        let names = RqlParser::extract_window_names(sql);
+
+        assert_eq!(names, vec!["w".to_string(), "inner_w".to_string()]);
+        let anonymous = "SELECT RANK() OVER (ORDER BY x) FROM t";
+        assert!(RqlParser::extract_window_names(anonymous).is_empty());
+        assert!(RqlParser::extract_window_names("SELECT * FROM").is_empty());
+    }
+
+    #[tokio::test]
+    async fn test_process_validates_window_references() {
+        let processor = RqlProcessor::new();
+
+        let ast = processor
+            .process("SELECT name, RANK() OVER w FROM t WINDOW W AS (ORDER BY salary)")
+            .await
+            .unwrap();
+        assert_eq!(ast.window_names, vec!["W".to_string()]);
+
+        let error = processor
+            .process("SELECT name, RANK() OVER w FROM t")
+            .await
+            .unwrap_err();
+        assert!(error.to_string().contains("ventana 'w' no definida"));
     }
 }
 
@@ -288,6 +495,65 @@ mod template_tests {
         assert!(engine.validate_template("{{#if var}}content{{/if}}{{#if}}").is_err());
     }
 
+    #[test]
+    fn test_each_block_multi_value_insert() {
+        let engine = TemplateEngine::new();
+        let template = "INSERT INTO t (name) VALUES {{#each names}}('{{this}}'){{#unless @last}}, {{/unless}}{{/each}}";
+        let expected = "INSERT INTO t (name) VALUES ('a'), ('b'), ('c')";
+
+        let mut variables = HashMap::new();
+        variables.insert("names".to_string(), "a, b, c".to_string());
+        assert_eq!(engine.process(template, &variables).unwrap(), expected);
+
+        // Mismo resultado con un array JSON
+        variables.insert("names".to_string(), r#"["a", "b", "c"]"#.to_string());
+        assert_eq!(engine.process(template, &variables).unwrap(), expected);
+    }
+
+    #[test]
+    fn test_each_block_index_and_missing_variable() {
+        let engine = TemplateEngine::new();
+        let mut variables = HashMap::new();
+        variables.insert("ids".to_string(), "[10, 20]".to_string());
+
+        let result = engine
+            .process(
+                "{{#each ids}}{{#if @first}}:{{/if}}{{@index}}={{this}};{{/each}}",
+                &variables,
+            )
+            .unwrap();
+        assert_eq!(result, ":0=10;1=20;");
+
+        // Variable inexistente: el bloque desaparece
+        let result = engine
+            .process("x{{#each otros}}{{this}}{{/each}}y", &variables)
+            .unwrap();
+        assert_eq!(result, "xy");
+
+        // JSON inválido
+        variables.insert("ids".to_string(), "[10,".to_string());
+        assert!(engine
+            .process("{{#each ids}}{{this}}{{/each}}", &variables)
+            .is_err());
+    }
+
+    #[test]
+    fn test_each_block_validation() {
+        let engine = TemplateEngine::new();
+
+        assert!(engine
+            .validate_template("{{#each items}}{{this}}{{#unless @last}},{{/unless}}{{/each}}")
+            .is_ok());
+        assert!(engine.validate_template("{{#each items}}{{this}}").is_err());
+        assert!(engine
+            .validate_template("{{/each}}{{#each items}}")
+            .is_err());
+        assert!(engine
+            .validate_template("{{#each items}}{{#if x}}{{/each}}{{/if}}")
+            .is_err());
+        assert!(engine.validate_template("{{#each}}{{/each}}").is_err());
+    }
+
     #[test]
     fn test_template_utils() {
         use crate::template::utils::*;
@@ -345,10 +611,31 @@ mod error_tests {
         assert!(error_str.contains("columna 15"));
         assert!(error_str.contains("Missing FROM clause"));
     }
+
+    #[test]
+    fn test_into_noctra_error() {
+        use noctra_core::NoctraError;
+
+        let error: NoctraError = ParserError::syntax_error(2, 7, "Missing FROM clause").into();
+        assert!(matches!(
+            error,
+            NoctraError::Parse {
+                line: 2,
+                column: 7,
+                ..
+            }
+        ));
+        assert_eq!(error.code(), "parse");
+
+        let error: NoctraError = ParserError::unknown_command("FOO").into();
+        assert_eq!(error.code(), "validation");
+
+        let error: NoctraError = ParserError::SqlParserError("Expected FROM".to_string()).into();
+        assert_eq!(error.code(), "sql_syntax");
+    }
 }
 
 mod nql_parser_tests {
-    use super::*;
     use crate::parser::RqlParser;
     use crate::rql_ast::{ExportFormat, MapExpression, RqlStatement};
 
@@ -482,6 +769,334 @@ mod nql_parser_tests {
         }
     }
 
+    #[tokio::test]
+    async fn test_parse_profile() {
+        let parser = RqlParser::new();
+
+        let ast = parser.parse_rql("PROFILE ventas;").await.unwrap();
+        assert_eq!(ast.statements.len(), 1);
+        assert_eq!(
+            ast.statements[0],
+            RqlStatement::Profile {
+                source: None,
+                table: "ventas".to_string()
+            }
+        );
+        assert_eq!(ast.statements[0].statement_type(), "PROFILE");
+
+        let ast = parser.parse_rql("profile csv.clientes").await.unwrap();
+        assert_eq!(
+            ast.statements[0],
+            RqlStatement::Profile {
+                source: Some("csv".to_string()),
+                table: "clientes".to_string()
+            }
+        );
+
+        assert!(parser.parse_rql("PROFILE").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_parse_sample() {
+        let parser = RqlParser::new();
+
+        let ast = parser.parse_rql("SAMPLE ventas LIMIT 5;").await.unwrap();
+        assert_eq!(
+            ast.statements[0],
+            RqlStatement::Sample {
+                source: None,
+                table: "ventas".to_string(),
+                limit: 5
+            }
+        );
+        assert_eq!(ast.statements[0].statement_type(), "SAMPLE");
+
+        let ast = parser.parse_rql("sample csv.clientes").await.unwrap();
+        assert_eq!(
+            ast.statements[0],
+            RqlStatement::Sample {
+                source: Some("csv".to_string()),
+                table: "clientes".to_string(),
+                limit: 10
+            }
+        );
+
+        assert!(parser
+            .parse_rql("SAMPLE ventas LIMIT muchas")
+            .await
+            .is_err());
+        assert!(parser.parse_rql("SAMPLE").await.is_err());
+        assert!(parser.parse_rql("SAMPLE ;").await.is_err());
+        assert!(parser.parse_rql("SAMPLE ; LIMIT 5").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_parse_watch() {
+        let parser = RqlParser::new();
+
+        let ast = parser
+            .parse_rql("WATCH 5 SELECT COUNT(*) FROM orders;")
+            .await
+            .unwrap();
+        assert_eq!(
+            ast.statements[0],
+            RqlStatement::Watch {
+                interval_secs: 5,
+                sql: "SELECT COUNT(*) FROM orders".to_string()
+            }
+        );
+        assert_eq!(ast.statements[0].statement_type(), "WATCH");
+        assert_eq!(ast.to_sql(), "WATCH 5 SELECT COUNT(*) FROM orders;");
+
+        assert!(parser.parse_rql("WATCH 0 SELECT 1").await.is_err());
+        assert!(parser.parse_rql("WATCH ya SELECT 1").await.is_err());
+        assert!(parser.parse_rql("WATCH 5").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_parse_pivot_bypasses_sqlparser() {
+        let parser = RqlParser::new();
+        let is_duckdb_specific = |statement: &RqlStatement| {
+            matches!(
+                statement,
+                RqlStatement::Sql {
+                    is_duckdb_specific: true,
+                    ..
+                }
+            )
+        };
+
+        let pivot = "PIVOT ventas ON anio USING sum(importe) GROUP BY pais";
+        let ast = parser.parse_rql(pivot).await.unwrap();
+        assert!(is_duckdb_specific(&ast.statements[0]));
+        assert_eq!(ast.to_sql(), pivot);
+
+        let ast = parser
+            .parse_rql("UNPIVOT ventas ON q1, q2 INTO NAME trimestre VALUE importe")
+            .await
+            .unwrap();
+        assert!(is_duckdb_specific(&ast.statements[0]));
+
+        let ast = parser
+            .parse_rql("SELECT * FROM ventas PIVOT (sum(importe) FOR anio IN (2023, 2024))")
+            .await
+            .unwrap();
+        assert!(is_duckdb_specific(&ast.statements[0]));
+
+        // Un identificador que contiene PIVOT sigue siendo SQL estándar
+        let ast = parser
+            .parse_rql("SELECT pivot_col FROM pivots")
+            .await
+            .unwrap();
+        assert!(!is_duckdb_specific(&ast.statements[0]));
+    }
+
+    #[tokio::test]
+    async fn test_parse_checkpoint() {
+        let parser = RqlParser::new();
+
+        let ast = parser.parse_rql("CHECKPOINT;").await.unwrap();
+        assert_eq!(ast.statements[0], RqlStatement::Checkpoint { source: None });
+        assert_eq!(ast.statements[0].statement_type(), "CHECKPOINT");
+
+        let ast = parser.parse_rql("checkpoint warehouse").await.unwrap();
+        assert_eq!(
+            ast.statements[0],
+            RqlStatement::Checkpoint {
+                source: Some("warehouse".to_string())
+            }
+        );
+
+        assert!(parser.parse_rql("CHECKPOINT a b;").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_parse_create_view() {
+        let parser = RqlParser::new();
+
+        let ast = parser
+            .parse_rql("create view activos as SELECT * FROM clientes WHERE activo = 1;")
+            .await
+            .unwrap();
+        assert_eq!(
+            ast.statements[0],
+            RqlStatement::CreateView {
+                name: "activos".to_string(),
+                sql: "SELECT * FROM clientes WHERE activo = 1".to_string(),
+                statement: "create view activos as SELECT * FROM clientes WHERE activo = 1;"
+                    .to_string(),
+            }
+        );
+        assert_eq!(ast.statements[0].statement_type(), "CREATE_VIEW");
+        assert_eq!(
+            ast.to_sql(),
+            "CREATE VIEW activos AS SELECT * FROM clientes WHERE activo = 1;"
+        );
+
+        assert!(parser.parse_rql("CREATE VIEW activos").await.is_err());
+        assert!(parser
+            .parse_rql("CREATE VIEW 1x AS SELECT 1")
+            .await
+            .is_err());
+        assert!(parser.parse_rql("CREATE VIEW v SELECT 1").await.is_err());
+    }
+
+    #[tokio::test]
+    async fn test_parse_create_view_other_forms_stay_sql() {
+        let parser = RqlParser::new();
+
+        for sql in [
+            "CREATE VIEW IF NOT EXISTS v AS SELECT 1",
+            "CREATE VIEW v(a, b) AS SELECT 1, 2",
+            "CREATE VIEW \"x\" AS SELECT 1",
+            "CREATE VIEW main.v AS SELECT 1",
+            "CREATE OR REPLACE VIEW v AS SELECT 1",
+            "CREATE TEMP VIEW v AS SELECT 1",
+        ] {
+            let ast = parser
+                .parse_rql(sql)
+                .await
+                .unwrap_or_else(|e| panic!("{}: {}", sql, e));
+            match &ast.statements[0] {
+                RqlStatement::Sql { sql: parsed, .. } => assert_eq!(parsed, sql),
+                other => panic!("{}: se esperaba SQL, se obtuvo {:?}", sql, other),
+            }
+        }
+    }
+
+    #[tokio::test]
+    async fn test_parse_merge() {
+        let parser = RqlParser::new();
+
+        let ast = parser
+            .parse_rql(
+                "merge into clientes c using altas a on c.id = a.id \
+                 when not matched then INSERT (id, nombre) VALUES (a.id, upper(a.nombre)) \
+                 WHEN MATCHED THEN UPDATE SET nombre = a.nombre;",
+            )
+            .await
+            .unwrap();
+        let merge = &ast.statements[0];
+        assert_eq!(
+            merge,
+            &RqlStatement::Merge {
+                target: "clientes c".to_string(),
+                source: "altas a".to_string(),
+                on_condition: "c.id = a.id".to_string(),
+                matched_action: Some("UPDATE SET nombre = a.nombre".to_string()),
+                not_matched_action: Some(
+                    "INSERT (id, nombre) VALUES (a.id, upper(a.nombre))".to_string()
+                ),
+            }
+        );
+        assert_eq!(merge.statement_type(), "MERGE");
+        assert_eq!(
+            merge.merge_sql().unwrap(),
+            "MERGE INTO clientes c USING altas a ON c.id = a.id \
+             WHEN MATCHED THEN UPDATE SET nombre = a.nombre \
+             WHEN NOT MATCHED THEN INSERT (id, nombre) VALUES (a.id, upper(a.nombre))"
+        );
+        assert_eq!(
+            merge.sqlite_merge_statements().unwrap(),
+            vec![
+                "UPDATE clientes AS c SET nombre = a.nombre FROM altas a WHERE c.id = a.id",
+                "INSERT INTO clientes (id, nombre) SELECT a.id, upper(a.nombre) FROM altas a \
+                 WHERE NOT EXISTS (SELECT 1 FROM clientes AS c WHERE c.id = a.id)",
+            ]
+        );
+
+        // Subconsulta como origen (su ON interno no corta la cabecera)
+        let ast = parser
+            .parse_rql(
+                "MERGE INTO stock USING (SELECT s.id, s.qty FROM s JOIN p ON s.id = p.id) AS n \
+                 ON stock.id = n.id WHEN NOT MATCHED THEN INSERT VALUES (n.id, n.qty)",
+            )
+            .await
+            .unwrap();
+        if let RqlStatement::Merge {
+            source,
+            on_condition,
+            matched_action,
+            ..
+        } = &ast.statements[0]
+        {
+            assert_eq!(
+                source,
+                "(SELECT s.id, s.qty FROM s JOIN p ON s.id = p.id) AS n"
+            );
+            assert_eq!(on_condition, "stock.id = n.id");
+            assert!(matched_action.is_none());
+        } else {
+            panic!("Expected Merge statement");
+        }
+
+        assert!(parser
+            .parse_rql("MERGE INTO t USING s ON t.id = s.id")
+            .await
+            .is_err());
+        assert!(parser
+            .parse_rql("MERGE INTO t USING s ON t.id = s.id WHEN MATCHED THEN DELETE")
+            .await
+            .is_err());
+        assert!(parser
+            .parse_rql(
+                "MERGE INTO t USING s ON t.id = s.id \
+                 WHEN MATCHED THEN UPDATE SET a = 1 WHEN MATCHED THEN UPDATE SET a = 2"
+            )
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_parse_transaction_statements() {
+        use crate::rql_ast::TransactionCommand;
+
+        let parser = RqlParser::new();
+        let ast = parser
+            .parse_rql(
+                "begin;\nSAVEPOINT antes;\nINSERT INTO t VALUES (1);\n\
+                 ROLLBACK TO SAVEPOINT antes;\nrelease antes;\nCOMMIT TRANSACTION;",
+            )
+            .await
+            .unwrap();
+        let commands: Vec<TransactionCommand> = ast
+            .statements
+            .iter()
+            .filter_map(|stmt| match stmt {
+                RqlStatement::Transaction { command } => Some(command.clone()),
+                _ => None,
+            })
+            .collect();
+        assert_eq!(
+            commands,
+            vec![
+                TransactionCommand::Begin,
+                TransactionCommand::Savepoint("antes".to_string()),
+                TransactionCommand::RollbackTo("antes".to_string()),
+                TransactionCommand::Release("antes".to_string()),
+                TransactionCommand::Commit,
+            ]
+        );
+        assert_eq!(ast.statements[2].statement_type(), "SQL");
+        assert_eq!(ast.statements[0].statement_type(), "TRANSACTION");
+        assert_eq!(commands[2].to_sql(), "ROLLBACK TO SAVEPOINT antes");
+
+        for (input, expected) in [
+            ("START TRANSACTION", TransactionCommand::Begin),
+            ("rollback work", TransactionCommand::Rollback),
+            (
+                "ROLLBACK TO sp1",
+                TransactionCommand::RollbackTo("sp1".to_string()),
+            ),
+        ] {
+            let ast = parser.parse_rql(input).await.unwrap();
+            assert_eq!(
+                ast.statements[0],
+                RqlStatement::Transaction { command: expected }
+            );
+        }
+    }
+
     #[tokio::test]
     async fn test_parse_import_basic() {
         let parser = RqlParser::new();
@@ -567,6 +1182,61 @@ mod nql_parser_tests {
         }
     }
 
+    #[tokio::test]
+    async fn test_parse_export_schema() {
+        let parser = RqlParser::new();
+
+        let ast = parser
+            .parse_rql("EXPORT SCHEMA TO 'catalog.toml';")
+            .await
+            .unwrap();
+        assert_eq!(
+            ast.statements[0],
+            RqlStatement::ExportSchema {
+                file: "catalog.toml".to_string()
+            }
+        );
+        assert_eq!(ast.statements[0].statement_type(), "EXPORT_SCHEMA");
+        assert_eq!(ast.to_sql(), "EXPORT SCHEMA TO 'catalog.toml';");
+
+        assert!(parser
+            .parse_rql("EXPORT SCHEMA TO catalog.toml")
+            .await
+            .is_err());
+        assert!(parser
+            .parse_rql("EXPORT SCHEMA 'catalog.toml'")
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_parse_export_source() {
+        let parser = RqlParser::new();
+
+        let ast = parser
+            .parse_rql("EXPORT SOURCE csv TO 'bundle/';")
+            .await
+            .unwrap();
+        assert_eq!(
+            ast.statements[0],
+            RqlStatement::ExportSource {
+                alias: "csv".to_string(),
+                path: "bundle/".to_string()
+            }
+        );
+        assert_eq!(ast.statements[0].statement_type(), "EXPORT_SOURCE");
+        assert_eq!(ast.to_sql(), "EXPORT SOURCE csv TO 'bundle/';");
+
+        assert!(parser
+            .parse_rql("EXPORT SOURCE csv TO bundle/")
+            .await
+            .is_err());
+        assert!(parser
+            .parse_rql("EXPORT SOURCE TO 'bundle/'")
+            .await
+            .is_err());
+    }
+
     #[tokio::test]
     async fn test_parse_map_single_expression() {
         let parser = RqlParser::new();
@@ -722,6 +1392,27 @@ mod nql_parser_tests {
         assert!(matches!(ast2.statements[0], RqlStatement::UseSource { .. }));
     }
 
+    #[tokio::test]
+    async fn test_parse_use_source_alias() {
+        let parser = RqlParser::new();
+
+        let ast = parser.parse_rql("use source ventas;").await.unwrap();
+        assert_eq!(
+            ast.statements[0],
+            RqlStatement::UseSourceAlias {
+                alias: "ventas".to_string()
+            }
+        );
+        assert_eq!(ast.statements[0].statement_type(), "USE_SOURCE_ALIAS");
+        assert_eq!(ast.to_sql(), "USE SOURCE ventas;");
+
+        // Con comillas sigue siendo la carga de un archivo
+        let ast = parser.parse_rql("USE SOURCE 'ventas.csv'").await.unwrap();
+        assert!(matches!(ast.statements[0], RqlStatement::UseSource { .. }));
+
+        assert!(parser.parse_rql("USE SOURCE a b").await.is_err());
+    }
+
     #[tokio::test]
     async fn test_parse_export_xlsx() {
         let parser = RqlParser::new();
@@ -864,4 +1555,208 @@ FILTER active = 1;
             );
         }
     }
-}
\ No newline at end of file
+
+    #[tokio::test]
+    async fn test_parse_assert_scalar_with_label() {
+        let parser = RqlParser::new();
+        let input = "ASSERT (SELECT COUNT(*) FROM orders) = 100 LABEL 'order count check';";
+
+        let ast = parser.parse_rql(input).await.unwrap();
+
+        assert_eq!(ast.statements.len(), 1);
+        if let RqlStatement::Assert {
+            sql,
+            expected_count,
+            expected_value,
+            label,
+        } = &ast.statements[0]
+        {
+            assert_eq!(sql, "SELECT COUNT(*) FROM orders");
+            assert_eq!(*expected_count, None);
+            assert_eq!(expected_value, &Some(serde_json::json!(100)));
+            assert_eq!(label.as_deref(), Some("order count check"));
+        } else {
+            panic!("Expected Assert statement");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_parse_assert_rowcount_and_literals() {
+        let parser = RqlParser::new();
+
+        let ast = parser
+            .parse_rql("ASSERT ROWCOUNT (SELECT * FROM t WHERE name = ')') = 3")
+            .await
+            .unwrap();
+        if let RqlStatement::Assert {
+            sql,
+            expected_count,
+            expected_value,
+            label,
+        } = &ast.statements[0]
+        {
+            assert_eq!(sql, "SELECT * FROM t WHERE name = ')'");
+            assert_eq!(*expected_count, Some(3));
+            assert!(expected_value.is_none());
+            assert!(label.is_none());
+        } else {
+            panic!("Expected Assert statement");
+        }
+
+        let ast = parser
+            .parse_rql("ASSERT (SELECT status FROM jobs) = 'done'")
+            .await
+            .unwrap();
+        if let RqlStatement::Assert { expected_value, .. } = &ast.statements[0] {
+            assert_eq!(expected_value, &Some(serde_json::json!("done")));
+        }
+
+        assert!(parser.parse_rql("ASSERT SELECT 1 = 1").await.is_err());
+        assert!(parser.parse_rql("ASSERT (SELECT 1").await.is_err());
+        assert!(parser
+            .parse_rql("ASSERT ROWCOUNT (SELECT 1) = -1")
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_parse_assert_with_non_ascii_text() {
+        let parser = RqlParser::new();
+
+        // 'ß' pasa a "SS" en mayúsculas: los offsets no pueden venir de to_uppercase
+        let ast = parser
+            .parse_rql("ASSERT (SELECT calle FROM t WHERE id = 1) = 'Hauptstraße' LABEL 'señal'")
+            .await
+            .unwrap();
+        if let RqlStatement::Assert {
+            expected_value,
+            label,
+            ..
+        } = &ast.statements[0]
+        {
+            assert_eq!(expected_value, &Some(serde_json::json!("Hauptstraße")));
+            assert_eq!(label.as_deref(), Some("señal"));
+        } else {
+            panic!("Expected Assert statement");
+        }
+
+        let ast = parser
+            .parse_rql("assert rowcount (SELECT * FROM t WHERE nombre = 'Ñandú') = 2")
+            .await
+            .unwrap();
+        if let RqlStatement::Assert {
+            sql,
+            expected_count,
+            ..
+        } = &ast.statements[0]
+        {
+            assert_eq!(sql, "SELECT * FROM t WHERE nombre = 'Ñandú'");
+            assert_eq!(*expected_count, Some(2));
+        } else {
+            panic!("Expected Assert statement");
+        }
+    }
+
+    #[tokio::test]
+    async fn test_parse_save_blob() {
+        let parser = RqlParser::new();
+
+        let ast = parser
+            .parse_rql("SAVE BLOB (SELECT doc FROM files WHERE id = :id) TO 'out.pdf';")
+            .await
+            .unwrap();
+        if let RqlStatement::SaveBlob { sql, file } = &ast.statements[0] {
+            assert_eq!(sql, "SELECT doc FROM files WHERE id = :id");
+            assert_eq!(file, "out.pdf");
+        } else {
+            panic!("Expected SaveBlob statement");
+        }
+        assert_eq!(
+            ast.to_sql(),
+            "SAVE BLOB (SELECT doc FROM files WHERE id = :id) TO 'out.pdf';"
+        );
+
+        assert!(parser
+            .parse_rql("SAVE BLOB SELECT doc FROM files TO 'a'")
+            .await
+            .is_err());
+        assert!(parser
+            .parse_rql("SAVE BLOB (SELECT doc FROM files)")
+            .await
+            .is_err());
+        assert!(parser
+            .parse_rql("SAVE BLOB (SELECT doc FROM files) TO out.pdf")
+            .await
+            .is_err());
+    }
+
+    #[tokio::test]
+    async fn test_parse_select_into_file() {
+        use crate::rql_ast::OutfileFormat;
+        let parser = RqlParser::new();
+
+        let ast = parser
+            .parse_rql("SELECT * FROM ventas WHERE total > 10 INTO 'out.parquet' FORMAT PARQUET OPTIONS (compression=zstd, row_group_size=65536);")
+            .await
+            .unwrap();
+        if let RqlStatement::SelectInto {
+            sql,
+            file,
+            format,
+            options,
+        } = &ast.statements[0]
+        {
+            assert_eq!(sql, "SELECT * FROM ventas WHERE total > 10");
+            assert_eq!(file, "out.parquet");
+            assert_eq!(*format, OutfileFormat::Parquet);
+            assert_eq!(options.get("compression").map(String::as_str), Some("zstd"));
+            assert_eq!(
+                options.get("row_group_size").map(String::as_str),
+                Some("65536")
+            );
+        } else {
+            panic!("Expected SelectInto statement");
+        }
+
+        // Sin FORMAT se usa la extensión
+        let ast = parser
+            .parse_rql("select id from t into 'ids.csv'")
+            .await
+            .unwrap();
+        assert!(matches!(
+            &ast.statements[0],
+            RqlStatement::SelectInto {
+                format: OutfileFormat::Csv,
+                ..
+            }
+        ));
+        assert_eq!(ast.to_sql(), "select id from t INTO 'ids.csv' FORMAT CSV;");
+
+        assert!(parser.parse_rql("SELECT 1 INTO 'a.txt'").await.is_err());
+        assert!(parser
+            .parse_rql("SELECT 1 INTO 'a.parquet' FORMAT XML")
+            .await
+            .is_err());
+        assert!(parser
+            .parse_rql("SELECT 1 INTO 'a.csv' FORMAT CSV extra")
+            .await
+            .is_err());
+
+        // Un INTO dentro de un literal o identificador no es la cláusula
+        let ast = parser
+            .parse_rql("SELECT 'vuelca INTO ''x.csv''' AS nota, \"ÑU INTO 'y'\" FROM t")
+            .await
+            .unwrap();
+        assert!(matches!(&ast.statements[0], RqlStatement::Sql { .. }));
+        let ast = parser
+            .parse_rql("SELECT 'ß INTO ' AS a FROM t INTO 'out.csv'")
+            .await
+            .unwrap();
+        if let RqlStatement::SelectInto { sql, file, .. } = &ast.statements[0] {
+            assert_eq!(sql, "SELECT 'ß INTO ' AS a FROM t");
+            assert_eq!(file, "out.csv");
+        } else {
+            panic!("Expected SelectInto statement");
+        }
+    }
+}
@@ -3,104 +3,145 @@
 //! Handlers específicos para consultas SQL, formularios y sesiones.
 
 use axum::{
-    extract::{State, Path},
-    http::StatusCode,
-    response::Json,
+    body::Body,
+    extract::{ConnectInfo, Request, State},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::{IntoResponse, Json, Response},
+    Extension,
 };
+use chrono::{DateTime, Utc};
+use jsonwebtoken::{Algorithm, DecodingKey, EncodingKey, Header, Validation};
+use serde::{Deserialize, Serialize};
+use std::collections::{HashMap, VecDeque};
+use std::future::Future;
+use std::net::{IpAddr, Ipv4Addr, SocketAddr};
+use std::pin::Pin;
 use std::sync::Arc;
-use std::time::Instant;
+use std::task::{Context, Poll};
+use std::time::{Duration, Instant, SystemTime, UNIX_EPOCH};
+use tokio::sync::RwLock;
+use tokio_stream::wrappers::ReceiverStream;
+use tower::{Layer, Service};
 
-use noctra_core::{Executor, Session, ResultSet, Value, Row, Column};
+use noctra_core::{
+    Executor, NoctraError, Parameters, PoolStatus, RqlQuery, Session, SessionHandle,
+    SessionManager, Value,
+};
 use noctra_parser::RqlParser;
-use noctra_formlib::{load_form_from_path, FormExecutionContext};
 
-use crate::server::ServerState;
-use crate::types::{QueryRequest, QueryResponse, FormRequest, FormResponse};
+use crate::performance::payload_too_large;
+use crate::server::{ServerConfig, ServerState};
+use crate::types::{
+    status_for_error, DependencyCheck, FormRequest, FormResponse, HealthChecks, HealthReport,
+    LoginRequest, ProbeStatus, QueryRequest, QueryResponse, ServerError, TokenResponse,
+};
+
+/// Tipo de contenido de las respuestas en streaming (una fila JSON por línea)
+pub const NDJSON_CONTENT_TYPE: &str = "application/x-ndjson";
+
+/// Líneas que el lector de filas puede adelantar antes de esperar al cliente
+const STREAM_BUFFER_LINES: usize = 64;
+
+/// Comprobaciones previas a la ejecución de una consulta
+///
+/// Rechaza consultas demasiado largas (400) y las que usan palabras clave
+/// bloqueadas por el operador, p. ej. `DROP` o `TRUNCATE` (403). Se aplica
+/// antes de parsear, así que una consulta rechazada nunca llega al executor.
+#[derive(Debug, Clone)]
+pub struct SqlSanitizer {
+    /// Longitud máxima de la consulta, en caracteres
+    pub max_query_length_chars: usize,
+    /// Palabras clave rechazadas (sin distinguir mayúsculas)
+    pub blocked_keywords: Vec<String>,
+}
+
+impl Default for SqlSanitizer {
+    fn default() -> Self {
+        Self {
+            max_query_length_chars: 64 * 1024,
+            blocked_keywords: Vec::new(),
+        }
+    }
+}
+
+impl SqlSanitizer {
+    /// Tomar los límites de la configuración del servidor
+    pub fn from_config(config: &ServerConfig) -> Self {
+        Self {
+            max_query_length_chars: config.max_query_length_chars,
+            blocked_keywords: config.blocked_keywords.clone(),
+        }
+    }
+
+    /// Aplicar todas las comprobaciones a `sql`
+    pub fn check(&self, sql: &str) -> noctra_core::Result<()> {
+        Self::validate_length(sql, self.max_query_length_chars)?;
+        let blocked: Vec<&str> = self.blocked_keywords.iter().map(String::as_str).collect();
+        Self::check_forbidden_keywords(sql, &blocked)
+    }
+
+    /// Rechazar consultas de más de `max_len` caracteres
+    pub fn validate_length(sql: &str, max_len: usize) -> noctra_core::Result<()> {
+        // En bytes nunca hay menos caracteres: sólo se cuentan si hace falta
+        if sql.len() <= max_len {
+            return Ok(());
+        }
+        let chars = sql.chars().count();
+        if chars > max_len {
+            return Err(NoctraError::Validation(format!(
+                "La consulta tiene {} caracteres; el máximo es {}",
+                chars, max_len
+            )));
+        }
+        Ok(())
+    }
+
+    /// Rechazar consultas que usen alguna de las palabras clave `blocked`
+    ///
+    /// Se comparan palabras completas sin distinguir mayúsculas; el texto de
+    /// los literales no cuenta (`WHERE nota = 'drop'` no usa `DROP`).
+    pub fn check_forbidden_keywords(sql: &str, blocked: &[&str]) -> noctra_core::Result<()> {
+        if blocked.is_empty() {
+            return Ok(());
+        }
+        let fingerprint = sql_fingerprint(sql);
+        let found = fingerprint
+            .split(|c: char| !(c.is_alphanumeric() || c == '_'))
+            .find_map(|word| {
+                blocked
+                    .iter()
+                    .find(|keyword| word.eq_ignore_ascii_case(keyword))
+            });
+        match found {
+            Some(keyword) => Err(NoctraError::permission(format!(
+                "La palabra clave {} está bloqueada en este servidor",
+                keyword.to_uppercase()
+            ))),
+            None => Ok(()),
+        }
+    }
+}
 
 /// Handler para consultas SQL/RQL
 pub struct QueryHandler {
     executor: Arc<Executor>,
     parser: Arc<RqlParser>,
+    sanitizer: SqlSanitizer,
 }
 
 impl QueryHandler {
     pub fn new(executor: Arc<Executor>, parser: Arc<RqlParser>) -> Self {
-        Self { executor, parser }
-    }
-    
-    /// Ejecutar consulta individual
-    pub async fn execute_query(&self, request: QueryRequest) -> QueryResponse {
-        let start_time = Instant::now();
-        
-        // Parsear SQL con RQL parser
-        let parse_result = self.parser.parse_rql(&request.sql).await;
-        
-        match parse_result {
-            Ok(ast) => {
-                // TODO: Ejecutar AST usando executor
-                // Por ahora crear resultado mock
-                let result = self.create_mock_result(&request.sql);
-                
-                let execution_time = start_time.elapsed().as_millis() as u64;
-                
-                QueryResponse {
-                    success: true,
-                    data: Some(result),
-                    message: format!("Consulta ejecutada: {} filas", result.row_count()),
-                    execution_time_ms: execution_time,
-                }
-            }
-            Err(e) => QueryResponse {
-                success: false,
-                data: None,
-                message: format!("Error parseando SQL: {}", e),
-                execution_time_ms: start_time.elapsed().as_millis() as u64,
-            }
-        }
-    }
-    
-    /// Ejecutar consultas en lote
-    pub async fn execute_batch(&self, requests: Vec<QueryRequest>) -> Vec<QueryResponse> {
-        let mut responses = Vec::new();
-        
-        for request in requests {
-            let response = self.execute_query(request).await;
-            responses.push(response);
+        Self {
+            executor,
+            parser,
+            sanitizer: SqlSanitizer::default(),
         }
-        
-        responses
     }
     
-    /// Crear resultado mock para demo
-    fn create_mock_result(&self, sql: &str) -> ResultSet {
-        let columns = vec![
-            Column::new("id", "INTEGER", 0),
-            Column::new("name", "TEXT", 1),
-            Column::new("description", "TEXT", 2),
-            Column::new("created_at", "TEXT", 3),
-        ];
-        
-        let mut result = ResultSet::new(columns);
-        
-        // Generar datos mock basados en el tipo de consulta
-        let is_select = sql.to_uppercase().contains("SELECT");
-        let has_where = sql.to_uppercase().contains("WHERE");
-        
-        if is_select {
-            let num_rows = if has_where { 2 } else { 5 };
-            
-            for i in 1..=num_rows {
-                let row = Row::new(vec![
-                    Value::integer(i as i64),
-                    Value::text(format!("Record {}", i)),
-                    Value::text(format!("Descripción del registro {}", i)),
-                    Value::text(format!("2024-01-{:02}", i)),
-                ]);
-                result.add_row(row);
-            }
-        }
-        
-        result
+    /// Usar otros límites que los por defecto (ver `SqlSanitizer::from_config`)
+    pub fn with_sanitizer(mut self, sanitizer: SqlSanitizer) -> Self {
+        self.sanitizer = sanitizer;
+        self
     }
     
     /// Validar SQL sin ejecutar
@@ -110,11 +151,237 @@ impl QueryHandler {
             Err(e) => Err(format!("Error de validación: {}", e)),
         }
     }
+
+    /// Ejecutar consulta aplicando la paginación de la petición
+    ///
+    /// Con `page`/`page_size` el SQL se envuelve en
+    /// `SELECT * FROM (sql) __noctra_paged__ LIMIT ... OFFSET ...`. Los
+    /// límites se enlazan como parámetros con nombre para no desplazar los
+    /// `?` de la consulta. `total_count` sólo se calcula si `count` es true,
+    /// porque exige un `COUNT(*)` adicional sobre la consulta original.
+    /// Con `cursor`/`fetch_size` se lee por bloques (ver `handle_cursor`).
+    /// Las `#variables` se toman de `session` (ver `resolve_session`).
+    /// Antes de nada la consulta pasa por `SqlSanitizer::check`.
+    pub fn handle_query(
+        &self,
+        session: &Session,
+        request: &QueryRequest,
+        count: bool,
+    ) -> noctra_core::Result<QueryResponse> {
+        self.sanitizer.check(&request.query)?;
+        if let Some(fetch_size) = request.fetch_size() {
+            return self.handle_cursor(session, request, fetch_size);
+        }
+
+        let start_time = Instant::now();
+        let sql = request.query.trim().trim_end_matches(';');
+        let pagination = request.pagination();
+
+        let mut parameters = request.parameters.clone();
+        let query_sql = match pagination {
+            Some(pagination) => {
+                parameters.insert(
+                    "__noctra_limit".to_string(),
+                    Value::Integer(i64::from(pagination.page_size)),
+                );
+                parameters.insert(
+                    "__noctra_offset".to_string(),
+                    Value::Integer(pagination.offset() as i64),
+                );
+                format!(
+                    "SELECT * FROM ({}) __noctra_paged__ LIMIT :__noctra_limit OFFSET :__noctra_offset",
+                    sql
+                )
+            }
+            None => sql.to_string(),
+        };
+        let result = self
+            .executor
+            .execute_rql(session, RqlQuery::new(query_sql, parameters))?;
+
+        let total_count = if count {
+            let count_sql = format!("SELECT COUNT(*) FROM ({}) __noctra_count__", sql);
+            let counted = self.executor.execute_rql(
+                session,
+                RqlQuery::new(count_sql, request.parameters.clone()),
+            )?;
+            match counted.rows.first().and_then(|row| row.values.first()) {
+                Some(Value::Integer(total)) => Some(*total as usize),
+                _ => None,
+            }
+        } else {
+            None
+        };
+
+        Ok(QueryResponse {
+            result,
+            execution_time_ms: start_time.elapsed().as_millis() as u64,
+            session_id: request.session_id.clone(),
+            metadata: HashMap::new(),
+            total_count,
+            page: pagination.map(|p| p.page),
+            page_size: pagination.map(|p| p.page_size),
+            total_pages: pagination
+                .zip(total_count)
+                .map(|(p, total)| p.total_pages(total)),
+            cursor: None,
+        })
+    }
+
+    /// Leer el siguiente bloque de un cursor, abriéndolo si hace falta
+    ///
+    /// Sin `cursor` en la petición se abre uno sobre `query`. La respuesta
+    /// lleva el cursor mientras queden filas; al agotarse se cierra solo.
+    /// Los cursores se guardan bajo `session`, la sesión resuelta de la
+    /// petición (ver `resolve_session`), así que cada bloque debe llegar con
+    /// la misma sesión; una petición anónima tiene una sesión efímera y sólo
+    /// puede leer el primer bloque.
+    fn handle_cursor(
+        &self,
+        session: &Session,
+        request: &QueryRequest,
+        fetch_size: usize,
+    ) -> noctra_core::Result<QueryResponse> {
+        let start_time = Instant::now();
+
+        let cursor = match request.cursor {
+            Some(cursor) => cursor,
+            None => {
+                let sql = request.query.trim().trim_end_matches(';');
+                self.executor
+                    .open_cursor(session, RqlQuery::new(sql, request.parameters.clone()))?
+            }
+        };
+        let page = self.executor.fetch_cursor(session, cursor, fetch_size)?;
+
+        Ok(QueryResponse {
+            result: page.result_set,
+            execution_time_ms: start_time.elapsed().as_millis() as u64,
+            session_id: request.session_id.clone(),
+            metadata: HashMap::new(),
+            total_count: None,
+            page: None,
+            page_size: None,
+            total_pages: None,
+            cursor: page.has_more.then_some(cursor),
+        })
+    }
+}
+
+/// Handler de consultas con el resultado en streaming (NDJSON)
+///
+/// Cada fila se escribe como una línea JSON (el `Row` serializado) a medida
+/// que se lee del executor, sin materializar el resultado. La última línea
+/// es `{"__meta":{"rows":N,"duration_ms":M}}`; si la lectura falla a mitad
+/// de camino se escribe `{"__error":{"code":...,"kind":...,"message":...}}`
+/// en su lugar, así que un stream sin `__meta` está incompleto.
+pub struct StreamingQueryHandler {
+    executor: Arc<Executor>,
+}
+
+impl StreamingQueryHandler {
+    pub fn new(executor: Arc<Executor>) -> Self {
+        Self { executor }
+    }
+
+    /// Ejecutar la consulta y devolver sus filas como NDJSON
+    ///
+    /// Las `#variables` se toman de `session` (ver `resolve_session`). Los
+    /// errores al preparar la consulta se devuelven como `ServerError` con
+    /// su status; una vez enviada la primera línea el status ya es 200.
+    pub async fn handle(&self, session: SessionHandle, request: &QueryRequest) -> Response {
+        let start_time = Instant::now();
+        let executor = Arc::clone(&self.executor);
+        let query = RqlQuery::new(
+            request.query.trim().trim_end_matches(';'),
+            request.parameters.clone(),
+        );
+
+        let stream = tokio::task::spawn_blocking(move || {
+            let session = lock_session(&session)?;
+            executor.execute_rql_stream(&session, query)
+        })
+        .await
+        .unwrap_or_else(|e| {
+            Err(NoctraError::Internal(format!(
+                "Hilo de consulta caído: {}",
+                e
+            )))
+        });
+        let rows = match stream {
+            Ok(rows) => rows,
+            Err(e) => {
+                return (status_for_error(&e), Json(ServerError::from_noctra(&e))).into_response()
+            }
+        };
+
+        // Las filas se leen en un hilo bloqueante; el canal acotado hace que
+        // la lectura avance al ritmo en que el cliente consume el cuerpo
+        let (sender, receiver) =
+            tokio::sync::mpsc::channel::<Result<String, std::io::Error>>(STREAM_BUFFER_LINES);
+        tokio::task::spawn_blocking(move || {
+            let mut count: u64 = 0;
+            for row in rows {
+                let line = row.and_then(|row| {
+                    serde_json::to_string(&row)
+                        .map_err(|e| NoctraError::Serialization(e.to_string()))
+                });
+                let line = match line {
+                    Ok(line) => line,
+                    Err(e) => {
+                        let error = serde_json::json!({
+                            "__error": {
+                                "code": e.error_code(),
+                                "kind": e.code(),
+                                "message": e.user_message()
+                            }
+                        });
+                        let _ = sender.blocking_send(Ok(format!("{}\n", error)));
+                        return;
+                    }
+                };
+                // Cliente desconectado: se deja de leer y se suelta la sentencia
+                if sender.blocking_send(Ok(format!("{}\n", line))).is_err() {
+                    return;
+                }
+                count += 1;
+            }
+
+            let meta = serde_json::json!({
+                "__meta": {
+                    "rows": count,
+                    "duration_ms": start_time.elapsed().as_millis() as u64
+                }
+            });
+            let _ = sender.blocking_send(Ok(format!("{}\n", meta)));
+        });
+
+        Response::builder()
+            .header(header::CONTENT_TYPE, NDJSON_CONTENT_TYPE)
+            .body(Body::from_stream(ReceiverStream::new(receiver)))
+            .unwrap_or_else(|_| StatusCode::INTERNAL_SERVER_ERROR.into_response())
+    }
+}
+
+/// Si el cliente pide la respuesta en NDJSON (`Accept: application/x-ndjson`)
+pub fn accepts_ndjson(headers: &HeaderMap) -> bool {
+    headers
+        .get_all(header::ACCEPT)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(|media_type| {
+            media_type.split(';').next().is_some_and(|media_type| {
+                media_type.trim().eq_ignore_ascii_case(NDJSON_CONTENT_TYPE)
+            })
+        })
 }
 
 /// Handler para formularios FDL2
 pub struct FormHandler {
+    #[allow(dead_code)]
     executor: Arc<Executor>,
+    #[allow(dead_code)]
     parser: Arc<RqlParser>,
 }
 
@@ -124,26 +391,26 @@ impl FormHandler {
     }
     
     /// Ejecutar formulario
-    pub async fn execute_form(&self, name: String, request: FormRequest) -> FormResponse {
+    pub async fn execute_form(&self, name: String, _request: FormRequest) -> FormResponse {
         // TODO: Implementar carga y ejecución real de formularios
         // Por ahora simular ejecución
         
         FormResponse {
             success: true,
-            data: None,
             message: format!("Formulario '{}' ejecutado exitosamente", name),
-            form_title: Some(format!("Formulario {}", name)),
+            data: HashMap::new(),
+            validation_errors: Vec::new(),
         }
     }
     
     /// Validar formulario
-    pub async fn validate_form(&self, name: String, request: FormRequest) -> FormResponse {
+    pub async fn validate_form(&self, name: String, _request: FormRequest) -> FormResponse {
         // TODO: Implementar validación real
         FormResponse {
             success: true,
-            data: None,
             message: format!("Formulario '{}' validado correctamente", name),
-            form_title: Some(format!("Formulario {}", name)),
+            data: HashMap::new(),
+            validation_errors: Vec::new(),
         }
     }
     
@@ -159,116 +426,961 @@ impl FormHandler {
 
 /// Handler para gestión de sesiones
 pub struct SessionHandler {
-    sessions: Arc<tokio::sync::RwLock<Vec<Session>>>,
+    sessions: Arc<SessionManager>,
 }
 
 impl SessionHandler {
-    pub fn new(sessions: Arc<tokio::sync::RwLock<Vec<Session>>>) -> Self {
+    pub fn new(sessions: Arc<SessionManager>) -> Self {
         Self { sessions }
     }
     
     /// Crear nueva sesión
-    pub async fn create_session(&self) -> Result<serde_json::Value, String> {
-        let session_id = format!("session_{}", chrono::Utc::now().timestamp());
-        let session = Session::new(session_id.clone());
-        
-        let mut sessions = self.sessions.write().await;
-        sessions.push(session.clone());
+    pub fn create_session(&self) -> noctra_core::Result<serde_json::Value> {
+        let handle = self.sessions.create()?;
+        let session_id = lock_session(&handle)?.id().to_string();
         
         Ok(serde_json::json!({
             "session_id": session_id,
             "message": "Sesión creada exitosamente",
-            "expires_in": 3600
+            "expires_in": self.sessions.config().session_timeout
         }))
     }
     
-    /// Obtener sesión por ID
-    pub async fn get_session(&self, session_id: &str) -> Result<serde_json::Value, String> {
-        let sessions = self.sessions.read().await;
+    /// Obtener sesión por ID (renueva su actividad)
+    pub fn get_session(&self, session_id: &str) -> noctra_core::Result<serde_json::Value> {
+        let handle = self.sessions.get(session_id)?;
+        let session = lock_session(&handle)?;
         
-        // Buscar sesión (implementación simplificada)
-        for session in sessions.iter() {
-            if session.id == session_id {
-                return Ok(serde_json::json!({
-                    "session_id": session.id,
-                    "status": "active",
-                    "created_at": session.created_at,
-                    "last_activity": chrono::Utc::now().to_rfc3339()
-                }));
-            }
-        }
-        
-        Err("Sesión no encontrada".to_string())
+        Ok(serde_json::json!({
+            "session_id": session.id(),
+            "status": "active",
+            "variables": session.list_variables(),
+            "active_source": session.active_source(),
+            "last_activity": chrono::Utc::now().to_rfc3339()
+        }))
     }
     
     /// Eliminar sesión
-    pub async fn delete_session(&self, session_id: &str) -> Result<serde_json::Value, String> {
-        let mut sessions = self.sessions.write().await;
+    pub fn delete_session(&self, session_id: &str) -> noctra_core::Result<serde_json::Value> {
+        self.sessions
+            .remove_session(session_id)
+            .ok_or_else(|| NoctraError::not_found("sesión", session_id))?;
         
-        // Remover sesión
-        let original_len = sessions.len();
-        sessions.retain(|s| s.id != session_id);
-        
-        if sessions.len() < original_len {
-            Ok(serde_json::json!({
-                "message": format!("Sesión {} eliminada", session_id)
-            }))
-        } else {
-            Err("Sesión no encontrada".to_string())
-        }
+        Ok(serde_json::json!({
+            "message": format!("Sesión {} eliminada", session_id)
+        }))
     }
     
-    /// Listar todas las sesiones
-    pub async fn list_sessions(&self) -> serde_json::Value {
-        let sessions = self.sessions.read().await;
-        
+    /// Número de sesiones activas
+    ///
+    /// Los IDs no se devuelven: con uno basta para usar la sesión ajena
+    /// (sus variables, su transacción).
+    pub fn list_sessions(&self) -> serde_json::Value {
         serde_json::json!({
-            "sessions": sessions.iter().map(|s| serde_json::json!({
-                "id": s.id,
-                "created_at": s.created_at,
-                "status": "active"
-            })).collect::<Vec<_>>(),
-            "total": sessions.len()
+            "total": self.sessions.active_sessions_count()
         })
     }
 }
 
-/// Handler combinado con acceso al estado del servidor
-pub struct ServerHandler {
-    query_handler: QueryHandler,
-    form_handler: FormHandler,
-    session_handler: SessionHandler,
+/// Cabecera con el ID de la sesión del gestor (alternativa a la cookie)
+pub const SESSION_HEADER: &str = "X-Noctra-Session";
+
+/// ID de sesión que indica la petición: la cabecera `X-Noctra-Session` o,
+/// si falta, la cookie `noctra_session`
+pub fn requested_session_id(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get(SESSION_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .map(str::to_string)
+        .or_else(|| session_cookie(headers))
+}
+
+/// Sesión del gestor a la que pertenece una petición
+///
+/// Sin ID (ver `requested_session_id`) la petición es anónima y usa una
+/// sesión efímera que no se registra en el gestor, así que sus `LET` no
+/// duran más que la petición; las sesiones se crean con
+/// `POST /api/v1/session`. Una sesión que no está en memoria se busca en
+/// el almacén del gestor (ver `ServerConfig::session_dir`); si tampoco
+/// está allí, o ya caducó, da `NoctraError::NotFound`.
+pub fn resolve_session(
+    sessions: &SessionManager,
+    headers: &HeaderMap,
+) -> noctra_core::Result<SessionHandle> {
+    match requested_session_id(headers) {
+        Some(id) => sessions
+            .load_session(&id)?
+            .ok_or_else(|| NoctraError::not_found("sesión", &id)),
+        None => Ok(Arc::new(std::sync::Mutex::new(Session::new()))),
+    }
+}
+
+/// Bloquear una sesión del gestor
+pub fn lock_session(
+    handle: &SessionHandle,
+) -> noctra_core::Result<std::sync::MutexGuard<'_, Session>> {
+    handle
+        .lock()
+        .map_err(|_| NoctraError::Internal("sesión envenenada".to_string()))
+}
+
+/// Handler de `/health`: prueba cada dependencia con `SELECT 1`
+///
+/// SQLite es el backend del executor: si falla, el servidor está `down`.
+/// Las fuentes registradas (DuckDB) se prueban una a una y basta que falle
+/// una para que el servidor quede `degraded`.
+pub struct HealthCheckHandler {
+    /// Executor a probar (`None` si no se pudo crear)
+    executor: Option<Arc<Executor>>,
+    uptime_seconds: u64,
 }
 
-impl ServerHandler {
-    pub fn new(state: ServerState) -> Self {
+impl HealthCheckHandler {
+    pub fn new(executor: Option<Arc<Executor>>, uptime_seconds: u64) -> Self {
         Self {
-            query_handler: QueryHandler::new(state.executor.clone(), state.parser.clone()),
-            form_handler: FormHandler::new(state.executor.clone(), state.parser.clone()),
-            session_handler: SessionHandler::new(state.sessions.clone()),
+            executor,
+            uptime_seconds,
         }
     }
-    
-    pub fn query_handler(&self) -> &QueryHandler {
-        &self.query_handler
+
+    /// Probar las dependencias y armar el informe
+    pub fn handle(&self) -> HealthReport {
+        let (sqlite, duckdb) = match &self.executor {
+            Some(executor) => (Self::probe_sqlite(executor), Self::probe_sources(executor)),
+            None => (
+                DependencyCheck {
+                    status: ProbeStatus::Down,
+                    latency_ms: 0,
+                    errors: vec!["Executor no disponible".to_string()],
+                },
+                None,
+            ),
+        };
+
+        let status = if sqlite.status == ProbeStatus::Down {
+            ProbeStatus::Down
+        } else if duckdb
+            .as_ref()
+            .is_some_and(|check| check.status != ProbeStatus::Ok)
+        {
+            ProbeStatus::Degraded
+        } else {
+            ProbeStatus::Ok
+        };
+
+        HealthReport {
+            status,
+            checks: HealthChecks { sqlite, duckdb },
+            version: "0.1.0".to_string(),
+            uptime_seconds: self.uptime_seconds,
+        }
     }
     
-    pub fn form_handler(&self) -> &FormHandler {
-        &self.form_handler
+    /// `SELECT 1` contra el backend SQLite (`Executor::ping`)
+    fn probe_sqlite(executor: &Executor) -> DependencyCheck {
+        let start = Instant::now();
+        let result = executor.ping();
+        DependencyCheck {
+            status: if result.is_ok() {
+                ProbeStatus::Ok
+            } else {
+                ProbeStatus::Down
+            },
+            latency_ms: start.elapsed().as_millis() as u64,
+            errors: result.err().map(|e| e.to_string()).into_iter().collect(),
+        }
     }
     
-    pub fn session_handler(&self) -> &SessionHandler {
-        &self.session_handler
+    /// `SELECT 1` contra cada fuente registrada; `None` si no hay ninguna
+    fn probe_sources(executor: &Executor) -> Option<DependencyCheck> {
+        let registry = executor.source_registry();
+        let aliases = registry.aliases();
+        if aliases.is_empty() {
+            return None;
+        }
+
+        let start = Instant::now();
+        let errors: Vec<String> = aliases
+            .iter()
+            .filter_map(|alias| {
+                let source = registry.get(alias)?;
+                source
+                    .query("SELECT 1", &Parameters::new())
+                    .err()
+                    .map(|e| format!("{}: {}", alias, e))
+            })
+            .collect();
+
+        Some(DependencyCheck {
+            status: if errors.is_empty() {
+                ProbeStatus::Ok
+            } else {
+                ProbeStatus::Degraded
+            },
+            latency_ms: start.elapsed().as_millis() as u64,
+            errors,
+        })
+    }
+}
+
+// =================== AUTENTICACIÓN JWT ===================
+
+/// Claims de los tokens emitidos por el servidor
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize)]
+pub struct Claims {
+    /// Usuario autenticado
+    pub sub: String,
+
+    /// Roles del usuario
+    #[serde(default)]
+    pub roles: Vec<String>,
+
+    /// Expiración (segundos desde epoch)
+    pub exp: u64,
+
+    /// Emisión (segundos desde epoch)
+    pub iat: u64,
+}
+
+impl Claims {
+    /// Crear claims que vencen dentro de `ttl`
+    pub fn new(sub: impl Into<String>, roles: Vec<String>, ttl: Duration) -> Self {
+        let iat = SystemTime::now()
+            .duration_since(UNIX_EPOCH)
+            .unwrap_or_default()
+            .as_secs();
+
+        Self {
+            sub: sub.into(),
+            roles,
+            exp: iat + ttl.as_secs(),
+            iat,
+        }
+    }
+
+    /// Verificar si el usuario tiene un rol
+    pub fn has_role(&self, role: &str) -> bool {
+        self.roles.iter().any(|r| r == role)
     }
 }
 
-/// Extensiones para extraer handlers del estado
-pub trait ServerHandlerExt {
-    fn get_handlers(&self) -> ServerHandler;
+/// Firmar claims con HS256
+pub fn issue_token(claims: &Claims, secret: &str) -> Result<String, jsonwebtoken::errors::Error> {
+    jsonwebtoken::encode(
+        &Header::new(Algorithm::HS256),
+        claims,
+        &EncodingKey::from_secret(secret.as_bytes()),
+    )
+}
+
+/// Validar firma y expiración de un token HS256
+pub fn verify_token(token: &str, secret: &str) -> Result<Claims, jsonwebtoken::errors::Error> {
+    let mut validation = Validation::new(Algorithm::HS256);
+    validation.leeway = 0;
+
+    jsonwebtoken::decode::<Claims>(
+        token,
+        &DecodingKey::from_secret(secret.as_bytes()),
+        &validation,
+    )
+    .map(|data| data.claims)
+}
+
+/// Extraer el token de `Authorization: Bearer <token>`
+fn bearer_token(headers: &HeaderMap) -> Option<&str> {
+    headers
+        .get(header::AUTHORIZATION)?
+        .to_str()
+        .ok()?
+        .strip_prefix("Bearer ")
+        .map(str::trim)
+}
+
+/// Respuesta 401 con `ServerError` como cuerpo
+fn unauthorized_response(message: &str) -> Response {
+    (
+        StatusCode::UNAUTHORIZED,
+        Json(ServerError::unauthorized(message)),
+    )
+        .into_response()
 }
 
-impl ServerHandlerExt for ServerState {
-    fn get_handlers(&self) -> ServerHandler {
-        ServerHandler::new(self.clone())
+/// Ruta de consultas que admite peticiones sin token con
+/// `ServerConfig::public_read_only` (sólo pueden leer)
+pub const PUBLIC_QUERY_PATH: &str = "/api/v1/query";
+
+/// Layer que exige un JWT válido en `Authorization: Bearer`
+///
+/// El secret se lee de `ServerConfig::auth_secret` en cada petición, de modo
+/// que `ServerState::update_config` se aplica sin reconstruir el router. Sin
+/// secret configurado se responde 401, salvo con `ServerConfig::auth_disabled`;
+/// las peticiones a `PUBLIC_QUERY_PATH` sin token pasan siempre con
+/// `ServerConfig::public_read_only` (sólo pueden leer). Los claims validados
+/// quedan disponibles como `Extension<Claims>`.
+#[derive(Clone)]
+pub struct JwtAuthLayer {
+    config: Arc<RwLock<ServerConfig>>,
+}
+
+impl JwtAuthLayer {
+    pub fn new(config: Arc<RwLock<ServerConfig>>) -> Self {
+        Self { config }
+    }
+}
+
+impl<S> Layer<S> for JwtAuthLayer {
+    type Service = JwtAuthService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        JwtAuthService {
+            inner,
+            config: self.config.clone(),
+        }
     }
-}
\ No newline at end of file
+}
+
+/// Servicio generado por `JwtAuthLayer`
+#[derive(Clone)]
+pub struct JwtAuthService<S> {
+    inner: S,
+    config: Arc<RwLock<ServerConfig>>,
+}
+
+impl<S> Service<Request<Body>> for JwtAuthService<S>
+where
+    S: Service<Request<Body>, Response = Response> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Response, S::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut request: Request<Body>) -> Self::Future {
+        // El servicio listo es el que recibió poll_ready
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let config = self.config.clone();
+
+        Box::pin(async move {
+            let (secret, auth_disabled, public_read_only) = {
+                let config = config.read().await;
+                (
+                    config.auth_secret.clone(),
+                    config.auth_disabled,
+                    config.public_read_only,
+                )
+            };
+            let public = public_read_only && request.uri().path() == PUBLIC_QUERY_PATH;
+
+            let Some(secret) = secret else {
+                if auth_disabled || public {
+                    return inner.call(request).await;
+                }
+                return Ok(unauthorized_response(
+                    "El servidor no tiene auth_secret configurado",
+                ));
+            };
+            let token = match bearer_token(request.headers()) {
+                Some(token) => token,
+                None if public => return inner.call(request).await,
+                None => return Ok(unauthorized_response("Falta el token de autorización")),
+            };
+
+            match verify_token(token, &secret) {
+                Ok(claims) => {
+                    request.extensions_mut().insert(claims);
+                }
+                Err(e) => {
+                    let message = match e.kind() {
+                        jsonwebtoken::errors::ErrorKind::ExpiredSignature => "Token expirado",
+                        _ => "Token inválido",
+                    };
+                    return Ok(unauthorized_response(message));
+                }
+            }
+
+            inner.call(request).await
+        })
+    }
+}
+
+/// Cookie con el ID de la sesión del gestor
+pub const SESSION_COOKIE: &str = "noctra_session";
+
+/// Valor de la cookie `noctra_session`, si viene en la petición
+fn session_cookie(headers: &HeaderMap) -> Option<String> {
+    headers
+        .get_all(header::COOKIE)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|cookies| cookies.split(';'))
+        .filter_map(|cookie| cookie.trim().split_once('='))
+        .find(|(name, _)| *name == SESSION_COOKIE)
+        .map(|(_, value)| value.to_string())
+}
+
+/// Cabecera `Set-Cookie` que asocia el cliente a la sesión `id`
+///
+/// Con `secure` lleva el atributo `Secure` y el navegador sólo la devuelve
+/// por HTTPS (ver `ServerConfig::secure_cookies`).
+pub fn session_cookie_header(id: &str, secure: bool) -> Option<HeaderValue> {
+    HeaderValue::from_str(&format!(
+        "{}={}; Path=/; HttpOnly;{} SameSite=Lax",
+        SESSION_COOKIE,
+        id,
+        if secure { " Secure;" } else { "" }
+    ))
+    .ok()
+}
+
+/// Estado serializado de una sesión, para saber si una petición la cambió
+fn session_snapshot(handle: &SessionHandle) -> Option<serde_json::Value> {
+    lock_session(handle)
+        .ok()
+        .and_then(|session| serde_json::to_value(&*session).ok())
+}
+
+/// Layer que asocia cada petición a su sesión del gestor
+///
+/// Resuelve la sesión con `resolve_session` y la deja en la petición como
+/// `Extension<SessionHandle>`. Al responder, si la petición cambió una
+/// sesión del gestor se guarda con `SessionManager::save_session`; una
+/// petición que no la cambia no escribe en el almacén.
+#[derive(Clone)]
+pub struct CookieSessionLayer {
+    sessions: Arc<SessionManager>,
+}
+
+impl CookieSessionLayer {
+    pub fn new(sessions: Arc<SessionManager>) -> Self {
+        Self { sessions }
+    }
+}
+
+impl<S> Layer<S> for CookieSessionLayer {
+    type Service = CookieSessionService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        CookieSessionService {
+            inner,
+            sessions: self.sessions.clone(),
+        }
+    }
+}
+
+/// Servicio generado por `CookieSessionLayer`
+#[derive(Clone)]
+pub struct CookieSessionService<S> {
+    inner: S,
+    sessions: Arc<SessionManager>,
+}
+
+impl<S> Service<Request<Body>> for CookieSessionService<S>
+where
+    S: Service<Request<Body>, Response = Response> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Response, S::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, mut request: Request<Body>) -> Self::Future {
+        // El servicio listo es el que recibió poll_ready
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let sessions = self.sessions.clone();
+
+        Box::pin(async move {
+            let handle = match resolve_session(&sessions, request.headers()) {
+                Ok(handle) => handle,
+                Err(e) => {
+                    return Ok(
+                        (status_for_error(&e), Json(ServerError::from_noctra(&e))).into_response()
+                    )
+                }
+            };
+            // Las sesiones efímeras no se guardan
+            let managed = requested_session_id(request.headers());
+            let before = managed.as_ref().and_then(|_| session_snapshot(&handle));
+            request.extensions_mut().insert(handle.clone());
+
+            let response = inner.call(request).await?;
+
+            if let Some(id) = managed {
+                if session_snapshot(&handle) != before {
+                    if let Err(e) = sessions.save_session(&id) {
+                        log::error!("No se pudo guardar la sesión {}: {}", id, e);
+                    }
+                }
+            }
+
+            Ok(response)
+        })
+    }
+}
+
+/// Emitir un token para `sub` con la vigencia configurada
+#[allow(clippy::result_large_err)]
+fn token_response(
+    config: &ServerConfig,
+    sub: &str,
+    roles: Vec<String>,
+) -> Result<Json<TokenResponse>, (StatusCode, Json<ServerError>)> {
+    let secret = config.auth_secret.as_deref().ok_or_else(|| {
+        (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(ServerError::service_unavailable(
+                "Autenticación no configurada",
+            )),
+        )
+    })?;
+
+    let claims = Claims::new(sub, roles, config.token_ttl);
+    let access_token = issue_token(&claims, secret).map_err(|e| {
+        (
+            StatusCode::INTERNAL_SERVER_ERROR,
+            Json(ServerError::internal_error(format!(
+                "Error firmando token: {}",
+                e
+            ))),
+        )
+    })?;
+
+    Ok(Json(TokenResponse {
+        access_token,
+        token_type: "Bearer".to_string(),
+        expires_in: config.token_ttl.as_secs(),
+    }))
+}
+
+/// Handler de login: valida usuario/contraseña y emite un JWT
+#[utoipa::path(
+    post,
+    path = "/api/v1/auth/login",
+    tag = "autenticación",
+    request_body = LoginRequest,
+    responses(
+        (status = 200, description = "Token emitido", body = TokenResponse),
+        (status = 401, description = "Usuario o contraseña incorrectos", body = ServerError),
+        (status = 503, description = "Autenticación no configurada", body = ServerError)
+    )
+)]
+pub async fn login_handler(
+    State(state): State<ServerState>,
+    Json(request): Json<LoginRequest>,
+) -> Result<Json<TokenResponse>, (StatusCode, Json<ServerError>)> {
+    // El hash se copia y bcrypt corre sin el lock de la configuración
+    let user = {
+        let config = state.config.read().await;
+        config
+            .users
+            .iter()
+            .find(|user| user.username == request.username)
+            .map(|user| (user.password_hash.clone(), user.roles.clone()))
+    };
+
+    // Un usuario desconocido también paga un bcrypt, para no delatarse por
+    // el tiempo de respuesta
+    let password_hash = match &user {
+        Some((hash, _)) => hash.as_str(),
+        None => dummy_password_hash(),
+    };
+    let valid = bcrypt::verify(&request.password, password_hash).unwrap_or(false);
+
+    match user {
+        Some((_, roles)) if valid => {
+            token_response(&*state.config.read().await, &request.username, roles)
+        }
+        _ => Err((
+            StatusCode::UNAUTHORIZED,
+            Json(ServerError::unauthorized(
+                "Usuario o contraseña incorrectos",
+            )),
+        )),
+    }
+}
+
+/// Hash con el que se compara la contraseña de un usuario desconocido
+fn dummy_password_hash() -> &'static str {
+    static HASH: std::sync::OnceLock<String> = std::sync::OnceLock::new();
+    HASH.get_or_init(|| {
+        bcrypt::hash("noctra-dummy-password", bcrypt::DEFAULT_COST)
+            .expect("bcrypt con coste por defecto")
+    })
+}
+
+/// Handler de refresh: emite un token nuevo a partir de uno vigente
+///
+/// Debe montarse detrás de `JwtAuthLayer`.
+#[utoipa::path(
+    post,
+    path = "/api/v1/auth/refresh",
+    tag = "autenticación",
+    responses(
+        (status = 200, description = "Token renovado", body = TokenResponse),
+        (status = 401, description = "Token ausente o inválido", body = ServerError),
+        (status = 503, description = "Autenticación no configurada", body = ServerError)
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn refresh_handler(
+    State(state): State<ServerState>,
+    claims: Option<Extension<Claims>>,
+) -> Result<Json<TokenResponse>, (StatusCode, Json<ServerError>)> {
+    let config = state.config.read().await;
+
+    match claims {
+        Some(Extension(claims)) => token_response(&config, &claims.sub, claims.roles),
+        // Sin claims la autenticación está deshabilitada
+        None => Err((
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(ServerError::service_unavailable(
+                "Autenticación no configurada",
+            )),
+        )),
+    }
+}
+
+// =================== CONSULTAS LENTAS ===================
+
+/// Consulta que superó `ServerConfig::slow_query_threshold_ms`
+#[derive(Debug, Clone, PartialEq, Serialize, Deserialize, utoipa::ToSchema)]
+pub struct SlowQueryEntry {
+    /// SQL con los literales reemplazados por `?` (ver `sql_fingerprint`)
+    pub sql_fingerprint: String,
+
+    /// Duración de la petición
+    pub duration_ms: u64,
+
+    /// Momento en que terminó la consulta
+    pub timestamp: DateTime<Utc>,
+
+    /// IP del cliente (no especificada si el router no se sirve con
+    /// `into_make_service_with_connect_info`, ver `Server::run`)
+    #[schema(value_type = String)]
+    pub client_ip: IpAddr,
+}
+
+/// Registro acotado de consultas lentas
+///
+/// Buffer circular: al llenarse se descarta la entrada más antigua.
+#[derive(Debug)]
+pub struct SlowQueryLog {
+    capacity: usize,
+    entries: std::sync::Mutex<VecDeque<SlowQueryEntry>>,
+}
+
+impl SlowQueryLog {
+    /// Crear registro con capacidad para `capacity` entradas
+    pub fn new(capacity: usize) -> Self {
+        Self {
+            capacity,
+            entries: std::sync::Mutex::new(VecDeque::with_capacity(capacity)),
+        }
+    }
+
+    /// Agregar una entrada, descartando la más antigua si no hay lugar
+    pub fn record(&self, entry: SlowQueryEntry) {
+        if self.capacity == 0 {
+            return;
+        }
+
+        if let Ok(mut entries) = self.entries.lock() {
+            if entries.len() == self.capacity {
+                entries.pop_front();
+            }
+            entries.push_back(entry);
+        }
+    }
+
+    /// Entradas registradas, de la más antigua a la más reciente
+    pub fn entries(&self) -> Vec<SlowQueryEntry> {
+        self.entries
+            .lock()
+            .map(|entries| entries.iter().cloned().collect())
+            .unwrap_or_default()
+    }
+}
+
+/// Normalizar SQL para el registro: literales de texto y números pasan a `?`
+///
+/// Así el registro agrupa consultas equivalentes y no guarda datos sensibles.
+/// Los espacios consecutivos se colapsan en uno.
+pub fn sql_fingerprint(sql: &str) -> String {
+    let mut fingerprint = String::with_capacity(sql.len());
+    let mut chars = sql.chars().peekable();
+    // Un dígito pegado a un identificador (p. ej. `tabla2`) no es un literal
+    let mut in_word = false;
+
+    while let Some(c) = chars.next() {
+        if c == '\'' {
+            // '' es una comilla escapada dentro del literal
+            while let Some(next) = chars.next() {
+                if next == '\'' {
+                    if chars.peek() == Some(&'\'') {
+                        chars.next();
+                    } else {
+                        break;
+                    }
+                }
+            }
+            fingerprint.push('?');
+            in_word = false;
+        } else if c.is_ascii_digit() && !in_word {
+            while matches!(chars.peek(), Some(d) if d.is_ascii_alphanumeric() || *d == '.') {
+                chars.next();
+            }
+            fingerprint.push('?');
+        } else if c.is_whitespace() {
+            if !fingerprint.is_empty() && !fingerprint.ends_with(' ') {
+                fingerprint.push(' ');
+            }
+            in_word = false;
+        } else {
+            fingerprint.push(c);
+            in_word = c.is_alphanumeric() || matches!(c, '_' | '$' | ':' | '"');
+        }
+    }
+
+    fingerprint.trim_end().to_string()
+}
+
+/// SQL de una petición de consulta (`{"query": ...}` o un lote de ellas)
+fn request_sql(body: &[u8]) -> Option<String> {
+    let query = |value: &serde_json::Value| value.get("query")?.as_str().map(str::to_string);
+
+    match serde_json::from_slice::<serde_json::Value>(body).ok()? {
+        serde_json::Value::Array(requests) => {
+            let queries: Vec<String> = requests.iter().filter_map(query).collect();
+            (!queries.is_empty()).then(|| queries.join("; "))
+        }
+        request => query(&request),
+    }
+}
+
+/// Layer que registra las consultas lentas en `SlowQueryLog`
+///
+/// Mide la petición completa y la agrega al registro si supera
+/// `ServerConfig::slow_query_threshold_ms`, que se lee en cada petición.
+/// Peticiones sin SQL reconocible no se registran.
+#[derive(Clone)]
+pub struct SlowQueryLayer {
+    config: Arc<RwLock<ServerConfig>>,
+    log: Arc<SlowQueryLog>,
+}
+
+impl SlowQueryLayer {
+    pub fn new(config: Arc<RwLock<ServerConfig>>, log: Arc<SlowQueryLog>) -> Self {
+        Self { config, log }
+    }
+}
+
+impl<S> Layer<S> for SlowQueryLayer {
+    type Service = SlowQueryService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        SlowQueryService {
+            inner,
+            config: self.config.clone(),
+            log: self.log.clone(),
+        }
+    }
+}
+
+/// Servicio generado por `SlowQueryLayer`
+#[derive(Clone)]
+pub struct SlowQueryService<S> {
+    inner: S,
+    config: Arc<RwLock<ServerConfig>>,
+    log: Arc<SlowQueryLog>,
+}
+
+impl<S> Service<Request<Body>> for SlowQueryService<S>
+where
+    S: Service<Request<Body>, Response = Response> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = Result<Response, S::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<Body>) -> Self::Future {
+        // El servicio listo es el que recibió poll_ready
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let config = self.config.clone();
+        let log = self.log.clone();
+
+        Box::pin(async move {
+            let start_time = Instant::now();
+            let client_ip = request
+                .extensions()
+                .get::<ConnectInfo<SocketAddr>>()
+                .map(|ConnectInfo(addr)| addr.ip())
+                .unwrap_or(IpAddr::V4(Ipv4Addr::UNSPECIFIED));
+
+            // Leer el cuerpo para obtener el SQL y reconstruir la petición.
+            // Detrás de BodySizeLimitLayer el cuerpo ya cabe en el límite;
+            // sin ella el límite acota igualmente la lectura.
+            let limit = config.read().await.max_request_body_bytes;
+            let (parts, body) = request.into_parts();
+            let bytes = match axum::body::to_bytes(
+                body,
+                usize::try_from(limit).unwrap_or(usize::MAX),
+            )
+            .await
+            {
+                Ok(bytes) => bytes,
+                Err(_) => return Ok(payload_too_large(limit)),
+            };
+            let sql = request_sql(&bytes);
+
+            let response = inner
+                .call(Request::from_parts(parts, Body::from(bytes)))
+                .await?;
+
+            let duration_ms = start_time.elapsed().as_millis() as u64;
+            let threshold_ms = config.read().await.slow_query_threshold_ms;
+            if let Some(sql) = sql.filter(|_| duration_ms > threshold_ms) {
+                let sql_fingerprint = sql_fingerprint(&sql);
+                log::warn!("Consulta lenta ({} ms): {}", duration_ms, sql_fingerprint);
+                log.record(SlowQueryEntry {
+                    sql_fingerprint,
+                    duration_ms,
+                    timestamp: Utc::now(),
+                    client_ip,
+                });
+            }
+
+            Ok(response)
+        })
+    }
+}
+
+/// Handler del registro de consultas lentas (requiere `metrics_enabled`)
+///
+/// Va detrás de `JwtAuthLayer`: las entradas revelan la forma de las
+/// consultas y la IP de quien las hizo.
+#[utoipa::path(
+    get,
+    path = "/api/v1/metrics/slow-queries",
+    tag = "métricas",
+    responses(
+        (status = 200, description = "Consultas lentas, de la más antigua a la más reciente", body = Vec<SlowQueryEntry>),
+        (status = 401, description = "Token ausente o inválido", body = ServerError),
+        (status = 404, description = "Métricas deshabilitadas", body = ServerError)
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn slow_queries_handler(
+    State(state): State<ServerState>,
+) -> Result<Json<Vec<SlowQueryEntry>>, (StatusCode, Json<ServerError>)> {
+    if !state.config.read().await.metrics_enabled {
+        return Err((
+            StatusCode::NOT_FOUND,
+            Json(ServerError::not_found("Métricas deshabilitadas")),
+        ));
+    }
+
+    Ok(Json(state.slow_queries.entries()))
+}
+
+/// Handler del estado del pool de conexiones SQLite (requiere
+/// `metrics_enabled`)
+#[utoipa::path(
+    get,
+    path = "/api/v1/metrics/pool",
+    tag = "métricas",
+    responses(
+        (status = 200, description = "Conexiones abiertas (`size`), libres (`available`) y consultas esperando una (`waiting`)", body = Object),
+        (status = 404, description = "Métricas deshabilitadas o backend sin pool", body = ServerError)
+    )
+)]
+pub async fn pool_metrics_handler(
+    State(state): State<ServerState>,
+) -> Result<Json<PoolStatus>, (StatusCode, Json<ServerError>)> {
+    if !state.config.read().await.metrics_enabled {
+        return Err((
+            StatusCode::NOT_FOUND,
+            Json(ServerError::not_found("Métricas deshabilitadas")),
+        ));
+    }
+
+    let status = state
+        .executor
+        .read()
+        .await
+        .as_ref()
+        .and_then(|executor| executor.pool_status());
+    status.map(Json).ok_or_else(|| {
+        (
+            StatusCode::NOT_FOUND,
+            Json(ServerError::not_found(
+                "El backend no usa un pool de conexiones",
+            )),
+        )
+    })
+}
+
+/// Handler del tráfico WebSocket: bytes de los mensajes antes y después de
+/// comprimir (requiere `metrics_enabled`)
+#[utoipa::path(
+    get,
+    path = "/api/v1/metrics/websocket",
+    tag = "métricas",
+    responses(
+        (status = 200, description = "`total_bytes_sent_uncompressed`, `total_bytes_sent_compressed` y `compression_ratio`", body = Object),
+        (status = 404, description = "Métricas deshabilitadas", body = ServerError)
+    )
+)]
+pub async fn websocket_metrics_handler(
+    State(state): State<ServerState>,
+) -> Result<Json<serde_json::Value>, (StatusCode, Json<ServerError>)> {
+    if !state.config.read().await.metrics_enabled {
+        return Err((
+            StatusCode::NOT_FOUND,
+            Json(ServerError::not_found("Métricas deshabilitadas")),
+        ));
+    }
+
+    Ok(Json(state.websocket_traffic.snapshot()))
+}
+
+/// Handler de las métricas del executor en formato Prometheus (requiere
+/// `metrics_enabled`)
+#[utoipa::path(
+    get,
+    path = "/metrics",
+    tag = "métricas",
+    responses(
+        (status = 200, description = "Contadores e histogramas de latencia por backend y sentencia", body = String, content_type = "text/plain"),
+        (status = 404, description = "Métricas deshabilitadas", body = ServerError)
+    )
+)]
+pub async fn prometheus_metrics_handler(
+    State(state): State<ServerState>,
+) -> Result<impl IntoResponse, (StatusCode, Json<ServerError>)> {
+    if !state.config.read().await.metrics_enabled {
+        return Err((
+            StatusCode::NOT_FOUND,
+            Json(ServerError::not_found("Métricas deshabilitadas")),
+        ));
+    }
+
+    Ok((
+        [(header::CONTENT_TYPE, "text/plain; version=0.0.4")],
+        state.query_metrics.render_prometheus(),
+    ))
+}
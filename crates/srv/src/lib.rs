@@ -3,20 +3,35 @@
 //! Librería principal del servidor HTTP para Noctra que expone APIs REST
 //! para consultas SQL/RQL, formularios FDL2 y gestión de sesiones.
 
-pub mod server;
-pub mod routes;
 pub mod handlers;
-pub mod websocket;
-pub mod types;
+pub mod openapi;
 pub mod performance;
+pub mod routes;
+pub mod server;
+pub mod types;
+pub mod websocket;
+pub mod ws_deflate;
 
-pub use server::{ServerState, ServerConfig, create_server, run_server, run_server_cli};
-pub use routes::{NoctraRouter, create_router};
-pub use handlers::{QueryHandler, FormHandler, SessionHandler, ServerHandler};
-pub use websocket::{WsManager, WsHandler, WsState};
-pub use types::{QueryRequest, QueryResponse, FormRequest, FormResponse, ServerStatus, ServerError};
+pub use handlers::{accepts_ndjson, StreamingQueryHandler, NDJSON_CONTENT_TYPE};
+pub use handlers::{
+    requested_session_id, resolve_session, session_cookie_header, CookieSessionLayer,
+    SESSION_COOKIE, SESSION_HEADER,
+};
+pub use handlers::{sql_fingerprint, SlowQueryEntry, SlowQueryLayer, SlowQueryLog, SqlSanitizer};
+pub use handlers::{
+    Claims, FormHandler, HealthCheckHandler, JwtAuthLayer, QueryHandler, SessionHandler,
+};
+pub use openapi::ApiDoc;
+pub use routes::{create_router, create_router_with, NoctraRouter};
+pub use server::{create_server, run_server, run_server_cli, ServerConfig, ServerState};
+pub use types::{AuthUser, LoginRequest, TokenResponse};
+pub use types::{DependencyCheck, HealthChecks, HealthReport, ProbeStatus};
+pub use types::{
+    FormRequest, FormResponse, QueryRequest, QueryResponse, ServerError, ServerStatus,
+};
+pub use types::{Pagination, QueryParams, DEFAULT_PAGE_SIZE, MAX_PAGE_SIZE};
+pub use websocket::{WsConfig, WsHandler, WsManager, WsState, WsTraffic};
 
-use std::net::SocketAddr;
 use std::time::Duration;
 
 /// Versión del servidor
@@ -36,15 +51,28 @@ pub fn quick_config() -> ServerConfig {
         request_timeout: Duration::from_secs(30),
         max_connections: 100,
         auth_secret: None,
+        auth_disabled: false,
+        token_ttl: Duration::from_secs(3600),
+        users: Vec::new(),
         cors_enabled: true,
         websocket_enabled: true,
         dev_mode: false,
         metrics_enabled: true,
+        slow_query_threshold_ms: 1000,
+        slow_query_log_size: 100,
+        query_log_path: None,
+        session_timeout: Duration::from_secs(3600),
+        session_dir: None,
+        secure_cookies: false,
+        public_read_only: false,
         database_path: None,
         forms_directory: None,
-        token_file: None,
+        users_file: None,
         rate_limiting_enabled: true,
         query_timeout: Duration::from_secs(30),
+        max_request_body_bytes: 1024 * 1024,
+        max_query_length_chars: 64 * 1024,
+        blocked_keywords: Vec::new(),
     }
 }
 
@@ -71,7 +99,7 @@ pub fn prod_config() -> ServerConfig {
 /// CLI helpers para el servidor
 pub mod cli {
     use super::*;
-    use clap::{Parser, ArgGroup};
+    use clap::Parser;
     use std::path::PathBuf;
     
     /// Argumentos CLI simplificados
@@ -94,6 +122,10 @@ pub mod cli {
         #[arg(short, long)]
         pub dev: bool,
         
+        /// Servir sin autenticación (no hay secret para los JWT)
+        #[arg(long)]
+        pub no_auth: bool,
+
         /// Habilitar métricas
         #[arg(short, long)]
         pub metrics: bool,
@@ -112,6 +144,7 @@ pub mod cli {
         config.forms_directory = args.forms;
         config.metrics_enabled = args.metrics;
         config.dev_mode = args.dev;
+        config.auth_disabled = args.no_auth;
         
         config
     }
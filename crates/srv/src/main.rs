@@ -3,31 +3,25 @@
 //! Servidor HTTP que expone APIs REST para consultas SQL/RQL y formularios.
 //! Ejecuta consultas usando el core de Noctra y soporta conexiones WebSocket.
 
-use clap::{Parser, ArgGroup};
-use std::path::PathBuf;
+use clap::Parser;
+use std::io::Write;
 use std::net::SocketAddr;
-use std::sync::Arc;
-
-use tokio::sync::RwLock;
+use std::path::PathBuf;
 
 use env_logger::Env;
 use log::{info, warn, error};
 
 // Importar módulos del servidor
-use noctra_srv::{
-    server::ServerState,
-    websocket::{WsState, WsHandler},
-    create_server,
-    ServerConfig,
-};
+use noctra_srv::{create_server, server::ServerState, ApiDoc, ServerConfig};
+use utoipa::OpenApi;
 
 /// CLI arguments para el servidor Noctra
-#[derive(Parser, Debug)]
+#[derive(Parser, Debug, Clone)]
 #[command(
     name = "noctrad",
     about = "Noctra Server Daemon - API server for SQL queries and forms",
     version = "0.1.0",
-    author = "Claude Code <claude@anthropic.com>",
+    author = "Claude Code <claude@anthropic.com>"
 )]
 struct CliArgs {
     /// Dirección IP y puerto para bind (default: 127.0.0.1:8080)
@@ -42,17 +36,30 @@ struct CliArgs {
     #[arg(short, long)]
     database: Option<PathBuf>,
     
+    /// Imprimir la especificación OpenAPI de la API y salir
+    #[arg(long)]
+    openapi: bool,
+
     /// Habilitar logging detallado
     #[arg(short, long)]
     verbose: bool,
     
-    /// Modo desarrollo (hot reload, debug features)
-    #[arg(short, long)]
+    /// Modo desarrollo (hot reload, debug features; `-d` es --database)
+    #[arg(long)]
     dev: bool,
     
-    /// Archivo de token para autenticación
+    /// Secret HS256 para firmar y validar los JWT
     #[arg(long)]
-    token_file: Option<PathBuf>,
+    auth_secret: Option<String>,
+
+    /// Servir sin autenticación (sin --auth-secret las rutas protegidas
+    /// responden 401)
+    #[arg(long)]
+    no_auth: bool,
+
+    /// Archivo JSON de usuarios para autenticación
+    #[arg(long)]
+    users_file: Option<PathBuf>,
     
     /// Habilitar WebSocket endpoints
     #[arg(short, long)]
@@ -77,20 +84,58 @@ struct CliArgs {
     /// Habilitar métricas y monitoring
     #[arg(short, long)]
     metrics: bool,
+
+    /// Consultas sin autenticar de solo lectura
+    #[arg(long)]
+    public_read_only: bool,
+
+    /// Registrar cada consulta (con los literales redactados) en un archivo JSONL
+    #[arg(long)]
+    query_log: Option<PathBuf>,
+
+    /// Directorio donde guardar las sesiones entre reinicios
+    #[arg(long)]
+    session_dir: Option<PathBuf>,
+
+    /// Marcar la cookie de sesión como Secure (servidor detrás de HTTPS)
+    #[arg(long)]
+    secure_cookies: bool,
+
+    /// Tamaño máximo del cuerpo de una petición, en bytes
+    #[arg(long, default_value_t = 1024 * 1024)]
+    max_request_body_bytes: u64,
+
+    /// Longitud máxima de una consulta, en caracteres
+    #[arg(long, default_value_t = 64 * 1024)]
+    max_query_length: usize,
+
+    /// Palabra clave SQL rechazada en las consultas (repetible, p. ej. DROP)
+    #[arg(long = "block-keyword")]
+    blocked_keywords: Vec<String>,
 }
 
 impl CliArgs {
     /// Convertir argumentos a configuración del servidor
     fn to_server_config(&self) -> ServerConfig {
-        let mut config = ServerConfig::default();
-        
-        config.bind_address = self.bind;
-        config.max_connections = self.max_connections;
-        config.query_timeout = std::time::Duration::from_secs(self.query_timeout);
-        config.cors_enabled = self.cors;
-        config.websocket_enabled = self.websocket;
-        config.dev_mode = self.dev;
-        config.metrics_enabled = self.metrics;
+        let mut config = ServerConfig {
+            bind_address: self.bind,
+            max_connections: self.max_connections,
+            query_timeout: std::time::Duration::from_secs(self.query_timeout),
+            cors_enabled: self.cors,
+            websocket_enabled: self.websocket,
+            dev_mode: self.dev,
+            metrics_enabled: self.metrics,
+            auth_secret: self.auth_secret.clone(),
+            auth_disabled: self.no_auth,
+            public_read_only: self.public_read_only,
+            query_log_path: self.query_log.clone(),
+            session_dir: self.session_dir.clone(),
+            secure_cookies: self.secure_cookies,
+            max_request_body_bytes: self.max_request_body_bytes,
+            max_query_length_chars: self.max_query_length,
+            blocked_keywords: self.blocked_keywords.clone(),
+            ..ServerConfig::default()
+        };
         
         // Configurar base de datos
         if let Some(db_path) = &self.database {
@@ -103,8 +148,8 @@ impl CliArgs {
         }
         
         // Configurar autenticación
-        if let Some(token_file) = &self.token_file {
-            config.token_file = Some(token_file.clone());
+        if let Some(users_file) = &self.users_file {
+            config.users_file = Some(users_file.clone());
         }
         
         config
@@ -136,7 +181,7 @@ impl CliArgs {
 
 /// Configuración extendida para el servidor
 #[derive(Debug, Clone)]
-pub struct ExtendedServerConfig {
+struct ExtendedServerConfig {
     pub base: ServerConfig,
     pub cli_args: CliArgs,
 }
@@ -165,7 +210,7 @@ impl ExtendedServerConfig {
     /// Validar configuración
     pub fn validate(&self) -> Result<(), Box<dyn std::error::Error>> {
         // Validar puerto
-        if self.base.bind_address.port() == 0 || self.base.bind_address.port() > 65535 {
+        if self.base.bind_address.port() == 0 {
             return Err("Puerto inválido".into());
         }
         
@@ -181,10 +226,15 @@ impl ExtendedServerConfig {
             }
         }
         
-        // Validar archivo de token si está especificado
-        if let Some(token_file) = &self.base.token_file {
-            if token_file.exists() && !token_file.is_file() {
-                return Err(format!("Token file debe ser un archivo: {:?}", token_file).into());
+        // Sin secret ni --no-auth todas las rutas protegidas darían 401
+        if self.base.auth_secret.is_none() && !self.base.auth_disabled {
+            return Err("Falta --auth-secret (o --no-auth para servir sin autenticación)".into());
+        }
+
+        // Validar archivo de usuarios si está especificado
+        if let Some(users_file) = &self.base.users_file {
+            if !users_file.is_file() {
+                return Err(format!("Archivo de usuarios no válido: {:?}", users_file).into());
             }
         }
         
@@ -250,13 +300,6 @@ async fn setup_signal_handlers() -> tokio::sync::broadcast::Receiver<()> {
     
     let (shutdown_tx, shutdown_rx) = tokio::sync::broadcast::channel(1);
     
-    // Handle Ctrl+C
-    tokio::spawn(async move {
-        signal::ctrl_c().await.expect("No se pudo configurar handler para Ctrl+C");
-        info!("Señal Ctrl+C recibida, iniciando shutdown graceful...");
-        let _ = shutdown_tx.send(());
-    });
-    
     // Handle SIGTERM (en sistemas Unix)
     #[cfg(unix)]
     {
@@ -270,6 +313,15 @@ async fn setup_signal_handlers() -> tokio::sync::broadcast::Receiver<()> {
         });
     }
     
+    // Handle Ctrl+C
+    tokio::spawn(async move {
+        signal::ctrl_c()
+            .await
+            .expect("No se pudo configurar handler para Ctrl+C");
+        info!("Señal Ctrl+C recibida, iniciando shutdown graceful...");
+        let _ = shutdown_tx.send(());
+    });
+
     shutdown_rx
 }
 
@@ -279,6 +331,12 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Parsear argumentos de línea de comandos
     let mut args = CliArgs::parse();
     
+    // La especificación sale de las anotaciones, no hace falta el servidor
+    if args.openapi {
+        println!("{}", ApiDoc::openapi().to_pretty_json()?);
+        return Ok(());
+    }
+    
     // Cargar configuración desde archivo si está presente
     args.load_config_file()?;
     
@@ -298,50 +356,8 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     let state = ServerState::new(config.base.clone()).await?;
     info!("Estado del servidor inicializado");
     
-    // Crear handler WebSocket si está habilitado
-    let ws_state = if config.base.websocket_enabled {
-        Some(WsState::new(state.clone()))
-    } else {
-        None
-    };
-    
-    // Crear aplicación HTTP
-    let mut app = create_server(state.clone(), config.base.clone())?;
-    
-    // Agregar WebSocket si está habilitado
-    if let Some(ws) = &ws_state {
-        let ws_handler = WsHandler::new(ws.manager.clone());
-        app = app.add_websocket_routes(ws_handler);
-        
-        // Iniciar tarea de cleanup para WebSocket
-        ws.start_cleanup_task();
-        info!("WebSocket endpoints habilitados");
-    }
-    
-    // Configurar CORS si está habilitado
-    if config.base.cors_enabled {
-        use tower_http::cors::{CorsLayer, Any};
-        app = app.layer(
-            CorsLayer::new()
-                .allow_origin(Any)
-                .allow_methods(Any)
-                .allow_headers(Any)
-        );
-        info!("CORS habilitado para desarrollo");
-    }
-    
-    // Configurar manejo de errores global
-    app = app.layer(
-        tower_http::trace::TraceLayer::new_for_http()
-            .make_span_with(
-                tower_http::trace::DefaultMakeSpan::new()
-                    .include_headers(true)
-            )
-            .on_response(
-                tower_http::trace::DefaultOnResponse::new()
-                    .include_headers(true)
-            ),
-    );
+    // Crear aplicación HTTP (rutas, WebSocket, CORS y tracing según la configuración)
+    let app = create_server(state.clone(), config.base.clone())?;
     
     // Setup signal handlers para shutdown graceful
     let mut shutdown_rx = setup_signal_handlers().await;
@@ -349,10 +365,15 @@ async fn main() -> Result<(), Box<dyn std::error::Error>> {
     // Crear listener TCP
     let listener = tokio::net::TcpListener::bind(config.base.bind_address).await?;
     info!("Servidor escuchando en: {}", config.base.bind_address);
+    state.mark_ready();
     
     // Servir requests
-    let server = axum::serve(listener, app)
-        .with_graceful_shutdown(async {
+    // ConnectInfo: la IP del cliente para el registro de consultas lentas
+    let server = axum::serve(
+        listener,
+        app.into_make_service_with_connect_info::<SocketAddr>(),
+    )
+    .with_graceful_shutdown(async move {
             let _ = shutdown_rx.recv().await;
         });
     
@@ -385,6 +406,12 @@ fn print_config_summary(config: &ExtendedServerConfig) {
 mod tests {
     use super::*;
     
+    #[test]
+    fn test_cli_args_are_consistent() {
+        use clap::CommandFactory;
+        CliArgs::command().debug_assert();
+    }
+
     #[tokio::test]
     async fn test_main_with_config() {
         // Test básico para verificar que el main puede inicializar
@@ -392,22 +419,41 @@ mod tests {
             bind: "127.0.0.1:8081".parse().unwrap(),
             config: None,
             database: None,
+            openapi: false,
             verbose: false,
             dev: true,
-            token_file: None,
+            auth_secret: None,
+            no_auth: true,
+            users_file: None,
             websocket: false,
             max_connections: 50,
             query_timeout: 15,
             cors: true,
             forms_dir: None,
             metrics: false,
+            public_read_only: false,
+            query_log: None,
+            session_dir: None,
+            secure_cookies: false,
+            max_request_body_bytes: 1024 * 1024,
+            max_query_length: 64 * 1024,
+            blocked_keywords: Vec::new(),
         };
         
+        // Sin secret hay que pedir explícitamente servir sin autenticación
+        let closed = ExtendedServerConfig::from_args(CliArgs {
+            no_auth: false,
+            ..args.clone()
+        });
+        assert!(closed.validate().is_err());
+        
         let config = ExtendedServerConfig::from_args(args);
         config.validate().unwrap();
+        assert!(config.base.auth_disabled);
         
         assert_eq!(config.base.bind_address.port(), 8081);
         assert_eq!(config.base.max_connections, 50);
         assert_eq!(config.cli_args.query_timeout, 15);
+        print_config_summary(&config);
     }
 }
\ No newline at end of file
@@ -0,0 +1,308 @@
+//! Especificación OpenAPI del servidor
+//!
+//! La especificación se genera a partir de las anotaciones `#[utoipa::path]`
+//! de los handlers y se sirve en `/api/v1/openapi.json` (`noctrad
+//! --openapi` la imprime sin levantar el servidor). `/api/v1/docs` es un
+//! explorador al estilo de Swagger UI, autocontenido (sin recursos
+//! externos): agrupa las operaciones por etiqueta, muestra parámetros,
+//! cuerpo de ejemplo y respuestas, y permite probarlas con un token JWT.
+
+use axum::response::{Html, Json};
+use utoipa::openapi::security::{Http, HttpAuthScheme, SecurityScheme};
+use utoipa::{Modify, OpenApi};
+
+use crate::handlers::SlowQueryEntry;
+use crate::types::{
+    DependencyCheck, FormRequest, FormResponse, HealthChecks, HealthReport, HealthStatus,
+    LoginRequest, ProbeStatus, QueryRequest, QueryResponse, ServerError, ServerStatus,
+    TokenResponse, ValidationError,
+};
+
+/// Ruta en la que se publica la especificación
+pub const OPENAPI_PATH: &str = "/api/v1/openapi.json";
+
+/// Documento OpenAPI de la API HTTP
+#[derive(OpenApi)]
+#[openapi(
+    info(
+        title = "Noctra Server",
+        description = "API HTTP para consultas SQL/RQL, formularios FDL2 y sesiones"
+    ),
+    paths(
+        crate::routes::root_info,
+        crate::routes::health_check,
+        crate::routes::readiness_check,
+        crate::routes::server_status,
+        crate::routes::execute_query,
+        crate::routes::stream_query,
+        crate::routes::plan_query,
+        crate::routes::execute_batch_queries,
+        crate::routes::execute_form,
+        crate::routes::validate_form,
+        crate::routes::list_forms,
+        crate::routes::create_session,
+        crate::routes::get_session,
+        crate::routes::delete_session,
+        crate::routes::list_sessions,
+        crate::routes::get_config,
+        crate::routes::update_config,
+        crate::routes::parse_sql,
+        crate::routes::validate_sql,
+        crate::routes::list_templates,
+        crate::websocket::websocket_endpoint,
+        crate::handlers::login_handler,
+        crate::handlers::refresh_handler,
+        crate::handlers::slow_queries_handler,
+        crate::handlers::pool_metrics_handler,
+        crate::handlers::websocket_metrics_handler,
+        crate::handlers::prometheus_metrics_handler,
+    ),
+    components(schemas(
+        QueryRequest,
+        QueryResponse,
+        FormRequest,
+        FormResponse,
+        ValidationError,
+        LoginRequest,
+        TokenResponse,
+        ServerStatus,
+        HealthStatus,
+        HealthReport,
+        HealthChecks,
+        DependencyCheck,
+        ProbeStatus,
+        ServerError,
+        SlowQueryEntry,
+    )),
+    modifiers(&BearerAuth),
+    tags(
+        (name = "servidor", description = "Estado y configuración"),
+        (name = "consultas", description = "Ejecución y planificación de consultas"),
+        (name = "formularios", description = "Formularios FDL2"),
+        (name = "sesiones", description = "Gestión de sesiones"),
+        (name = "autenticación", description = "Emisión de tokens JWT"),
+        (name = "métricas", description = "Métricas del servidor"),
+        (name = "utilidades", description = "Parseo y validación de SQL"),
+        (name = "websocket", description = "Streaming en tiempo real")
+    )
+)]
+pub struct ApiDoc;
+
+/// Esquema de seguridad `bearer_auth` (JWT emitido por `/api/v1/auth/login`)
+struct BearerAuth;
+
+impl Modify for BearerAuth {
+    fn modify(&self, openapi: &mut utoipa::openapi::OpenApi) {
+        let mut scheme = Http::new(HttpAuthScheme::Bearer);
+        scheme.bearer_format = Some("JWT".to_string());
+
+        openapi
+            .components
+            .get_or_insert_with(Default::default)
+            .add_security_scheme("bearer_auth", SecurityScheme::Http(scheme));
+    }
+}
+
+/// Servir la especificación OpenAPI en JSON
+pub async fn openapi_json() -> Json<utoipa::openapi::OpenApi> {
+    Json(ApiDoc::openapi())
+}
+
+/// Explorador de la API al estilo de Swagger UI
+///
+/// El HTML y el script van embebidos en el binario, así que la página
+/// funciona sin acceso a internet. Lee la especificación de `OPENAPI_PATH`.
+pub async fn api_docs() -> Html<String> {
+    Html(DOCS_HTML.replace("{{spec_url}}", OPENAPI_PATH))
+}
+
+const DOCS_HTML: &str = r#"<!DOCTYPE html>
+<html lang="es">
+<head>
+<meta charset="utf-8">
+<title>Noctra Server - API</title>
+<style>
+  body { font-family: sans-serif; margin: 2rem auto; max-width: 1024px; color: #222; }
+  h1 small { font-size: 0.5em; color: #777; }
+  h2 { border-bottom: 1px solid #ddd; padding-bottom: 0.25rem; margin-top: 2rem; }
+  h2 small { font-weight: normal; font-size: 0.6em; color: #777; }
+  .auth { display: flex; gap: 0.5rem; align-items: center; }
+  .auth input { flex: 1; font-family: monospace; }
+  .op { border: 1px solid #ddd; border-radius: 4px; margin: 0.5rem 0; }
+  .op summary { cursor: pointer; padding: 0.5rem; }
+  .method { display: inline-block; width: 4.5rem; font-weight: bold; text-transform: uppercase; }
+  .get { color: #2f6fb3; } .post { color: #2e8b57; } .put { color: #b8860b; } .delete { color: #b22222; }
+  .lock { color: #777; float: right; }
+  .body { padding: 0 1rem 1rem; }
+  table { border-collapse: collapse; width: 100%; }
+  td, th { border-bottom: 1px solid #eee; padding: 0.25rem; text-align: left; vertical-align: top; }
+  textarea { width: 100%; min-height: 6rem; font-family: monospace; }
+  pre { background: #f6f6f6; padding: 0.5rem; overflow-x: auto; }
+</style>
+</head>
+<body>
+<h1>Noctra Server <small id="version"></small></h1>
+<p>Especificación: <a href="{{spec_url}}">{{spec_url}}</a></p>
+<div class="auth">
+  <label for="token">Token JWT</label>
+  <input id="token" placeholder="Bearer (de POST /api/v1/auth/login)">
+</div>
+<div id="operations">Cargando…</div>
+<script>
+var spec;
+
+function element(tag, className, text) {
+  var node = document.createElement(tag);
+  if (className) { node.className = className; }
+  if (text !== undefined) { node.textContent = text; }
+  return node;
+}
+
+function resolve(schema) {
+  while (schema && schema.$ref) {
+    schema = spec.components.schemas[schema.$ref.split("/").pop()];
+  }
+  return schema || {};
+}
+
+// Valor de ejemplo para un esquema (el "Example Value" de Swagger UI)
+function example(schema, depth) {
+  schema = resolve(schema);
+  if (schema.example !== undefined) { return schema.example; }
+  if (depth > 4) { return null; }
+  var variant = schema.oneOf || schema.anyOf;
+  if (variant) { return example(variant[0], depth + 1); }
+  var type = Array.isArray(schema.type) ? schema.type[0] : schema.type;
+  if (type === "object" || schema.properties) {
+    var value = {};
+    Object.keys(schema.properties || {}).forEach(function (name) {
+      value[name] = example(schema.properties[name], depth + 1);
+    });
+    return value;
+  }
+  if (type === "array") { return [example(schema.items, depth + 1)]; }
+  if (type === "integer" || type === "number") { return 0; }
+  if (type === "boolean") { return false; }
+  if (type === "string") { return schema.enum ? schema.enum[0] : "string"; }
+  return null;
+}
+
+function operation(path, method, op) {
+  var details = element("details", "op");
+  var summary = element("summary");
+  summary.appendChild(element("span", "method " + method, method));
+  summary.appendChild(document.createTextNode(path + "  " + (op.summary || "")));
+  if (op.security) { summary.appendChild(element("span", "lock", "🔒")); }
+  details.appendChild(summary);
+
+  var body = element("div", "body");
+  if (op.description) { body.appendChild(element("p", null, op.description)); }
+
+  var inputs = {};
+  if (op.parameters && op.parameters.length) {
+    body.appendChild(element("h4", null, "Parámetros"));
+    var table = element("table");
+    op.parameters.forEach(function (param) {
+      var row = element("tr");
+      row.appendChild(element("td", null, param.name + (param.required ? " *" : "")));
+      row.appendChild(element("td", null, param.in));
+      row.appendChild(element("td", null, param.description || ""));
+      var cell = element("td");
+      var input = element("input");
+      inputs[param.name] = { input: input, location: param.in };
+      cell.appendChild(input);
+      row.appendChild(cell);
+      table.appendChild(row);
+    });
+    body.appendChild(table);
+  }
+
+  var requestBody;
+  if (op.requestBody) {
+    body.appendChild(element("h4", null, "Cuerpo"));
+    var content = op.requestBody.content["application/json"];
+    requestBody = element("textarea");
+    requestBody.value = content ? JSON.stringify(example(content.schema, 0), null, 2) : "";
+    body.appendChild(requestBody);
+  }
+
+  body.appendChild(element("h4", null, "Respuestas"));
+  var responses = element("table");
+  Object.keys(op.responses || {}).forEach(function (status) {
+    var row = element("tr");
+    row.appendChild(element("td", null, status));
+    row.appendChild(element("td", null, op.responses[status].description || ""));
+    responses.appendChild(row);
+  });
+  body.appendChild(responses);
+
+  // "Try it out": enviar la petición con los valores del formulario
+  var send = element("button", null, "Probar");
+  var result = element("pre");
+  send.onclick = function () {
+    var url = path;
+    var query = [];
+    Object.keys(inputs).forEach(function (name) {
+      var value = inputs[name].input.value;
+      if (inputs[name].location === "path") {
+        url = url.replace("{" + name + "}", encodeURIComponent(value));
+      } else if (value !== "") {
+        query.push(encodeURIComponent(name) + "=" + encodeURIComponent(value));
+      }
+    });
+    if (query.length) { url += "?" + query.join("&"); }
+    var headers = { "Content-Type": "application/json" };
+    var token = document.getElementById("token").value.trim();
+    if (token) { headers.Authorization = "Bearer " + token; }
+    result.textContent = method.toUpperCase() + " " + url + " …";
+    fetch(url, { method: method.toUpperCase(), headers: headers, body: requestBody ? requestBody.value : undefined })
+      .then(function (response) {
+        return response.text().then(function (text) {
+          try { text = JSON.stringify(JSON.parse(text), null, 2); } catch (e) {}
+          result.textContent = response.status + " " + response.statusText + "\n\n" + text;
+        });
+      })
+      .catch(function (error) { result.textContent = "Error: " + error; });
+  };
+  body.appendChild(send);
+  body.appendChild(result);
+  details.appendChild(body);
+  return details;
+}
+
+fetch("{{spec_url}}")
+  .then(function (response) { return response.json(); })
+  .then(function (loaded) {
+    spec = loaded;
+    document.getElementById("version").textContent = spec.info.version;
+    var container = document.getElementById("operations");
+    container.textContent = "";
+    // Operaciones agrupadas por etiqueta, en el orden de la especificación
+    var groups = {};
+    Object.keys(spec.paths).sort().forEach(function (path) {
+      Object.keys(spec.paths[path]).forEach(function (method) {
+        var op = spec.paths[path][method];
+        var tag = (op.tags && op.tags[0]) || "otros";
+        (groups[tag] = groups[tag] || []).push(operation(path, method, op));
+      });
+    });
+    var tags = (spec.tags || []).map(function (tag) { return tag.name; });
+    Object.keys(groups).forEach(function (tag) {
+      if (tags.indexOf(tag) < 0) { tags.push(tag); }
+    });
+    tags.forEach(function (name) {
+      if (!groups[name]) { return; }
+      var heading = element("h2", null, name + " ");
+      var tag = (spec.tags || []).filter(function (t) { return t.name === name; })[0];
+      if (tag && tag.description) { heading.appendChild(element("small", null, tag.description)); }
+      container.appendChild(heading);
+      groups[name].forEach(function (details) { container.appendChild(details); });
+    });
+  })
+  .catch(function (error) {
+    document.getElementById("operations").textContent = "Error cargando la especificación: " + error;
+  });
+</script>
+</body>
+</html>
+"#;
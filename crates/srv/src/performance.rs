@@ -1,17 +1,28 @@
 //! Optimizaciones de performance para el servidor Noctra
 //! 
-//! Implementa connection pooling, caching de consultas y optimizations
+//! Implementa rate limiting, caching de consultas y optimizations
 //! para mejorar throughput y latencia.
 
+use axum::{
+    body::Body,
+    extract::Request,
+    http::{header, StatusCode},
+    response::{IntoResponse, Json, Response},
+};
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::future::Future;
+use std::pin::Pin;
 use std::sync::Arc;
-use std::time::{Duration, Instant};
-use tokio::sync::{RwLock, Mutex};
-use std::collections::{HashMap, BTreeMap};
-use futures::future::BoxFuture;
-use serde::{Serialize, Deserialize};
+use std::task::{Context, Poll};
+use std::time::Duration;
+use tokio::sync::{Mutex, RwLock};
+use tokio_stream::StreamExt;
+use tower::{Layer, Service};
 
 use crate::server::ServerConfig;
-use crate::error::Result;
+use crate::types::ServerError;
 
 /// Cache de consultas preparadas
 #[derive(Debug)]
@@ -23,12 +34,12 @@ pub struct QueryCache {
 }
 
 #[derive(Debug, Clone)]
-struct CachedQuery {
-    sql_hash: String,
-    sql: String,
-    plan: String, // Plan de ejecución serializado
-    created_at: std::time::Instant,
-    access_count: u32,
+pub struct CachedQuery {
+    pub sql_hash: String,
+    pub sql: String,
+    pub plan: String, // Plan de ejecución serializado
+    pub created_at: std::time::Instant,
+    pub access_count: u32,
 }
 
 /// Cache LRU básico
@@ -47,7 +58,6 @@ impl<K: Clone + Ord, V> LruCache<K, V> {
     }
     
     fn get(&self, key: &K) -> Option<&V> {
-        let now = std::time::Instant::now();
         self.map.iter()
             .find_map(|((_time, k), v)| {
                 if k == key {
@@ -62,7 +72,7 @@ impl<K: Clone + Ord, V> LruCache<K, V> {
         let now = std::time::Instant::now();
         
         // Remover si existe
-        let existing = self.map.remove(&(now, key.clone()));
+        let existing = self.remove(&key);
         
         // Insertar nueva entrada
         self.map.insert((now, key), value);
@@ -79,17 +89,14 @@ impl<K: Clone + Ord, V> LruCache<K, V> {
     }
     
     fn remove(&mut self, key: &K) -> Option<V> {
-        let now = std::time::Instant::now();
-        self.map.remove(&(now, key.clone()))
+        let entry = self.map.keys().find(|(_, k)| k == key).cloned()?;
+        self.map.remove(&entry)
     }
     
     fn len(&self) -> usize {
         self.map.len()
     }
     
-    fn is_empty(&self) -> bool {
-        self.map.is_empty()
-    }
 }
 
 impl QueryCache {
@@ -104,7 +111,7 @@ impl QueryCache {
     /// Obtener consulta del cache
     pub async fn get(&self, sql: &str) -> Option<CachedQuery> {
         let cache = self.cache.read().await;
-        let query = cache.get(sql)?;
+        let query = cache.get(&sql.to_string())?;
         
         // Verificar TTL
         if query.created_at.elapsed() < self.ttl {
@@ -132,16 +139,15 @@ impl QueryCache {
     /// Remover consulta del cache
     pub async fn remove(&self, sql: &str) {
         let mut cache = self.cache.write().await;
-        cache.remove(sql);
+        cache.remove(&sql.to_string());
     }
     
     /// Limpiar entradas expiradas
     pub async fn cleanup_expired(&self) {
         let mut cache = self.cache.write().await;
-        let now = std::time::Instant::now();
-        
-        // TODO: Implementar cleanup real de entradas expiradas
-        // Por simplicidad, solo contar cache hits
+        cache
+            .map
+            .retain(|_, query| query.created_at.elapsed() < self.ttl);
     }
     
     /// Obtener estadísticas del cache
@@ -166,94 +172,6 @@ impl QueryCache {
     }
 }
 
-/// Pool de conexiones a la base de datos
-#[derive(Debug)]
-pub struct ConnectionPool {
-    connections: Arc<Mutex<Vec<Arc<rusqlite::Connection>>>>,
-    max_size: usize,
-    min_size: usize,
-    current_size: Arc<RwLock<usize>>,
-    waiting_queue: Arc<Mutex<Vec<tokio::sync::oneshot::Sender<Arc<rusqlite::Connection>>>>>,
-}
-
-impl ConnectionPool {
-    pub fn new(max_size: usize, min_size: usize) -> Self {
-        Self {
-            connections: Arc::new(Mutex::new(Vec::new())),
-            max_size,
-            min_size,
-            current_size: Arc::new(RwLock::new(0)),
-            waiting_queue: Arc::new(Mutex::new(Vec::new())),
-        }
-    }
-    
-    /// Obtener conexión del pool
-    pub async fn get_connection(&self, db_path: &str) -> Result<Arc<rusqlite::Connection>> {
-        // Intentar reutilizar conexión existente
-        {
-            let mut connections = self.connections.lock().await;
-            if let Some(conn) = connections.pop() {
-                *self.current_size.write().await += 1;
-                return Ok(conn);
-            }
-        }
-        
-        // Crear nueva conexión si no excedemos el límite
-        let current = *self.current_size.read().await;
-        if current < self.max_size {
-            *self.current_size.write().await += 1;
-            
-            let connection = self.create_connection(db_path).await?;
-            Ok(Arc::new(connection))
-        } else {
-            // TODO: Implementar cola de espera para conexiones
-            // Por ahora, crear nueva conexión anyway
-            self.create_connection(db_path).await.map(Arc::new)
-        }
-    }
-    
-    /// Devolver conexión al pool
-    pub async fn return_connection(&self, connection: Arc<rusqlite::Connection>) {
-        let mut connections = self.connections.lock().await;
-        
-        if connections.len() < self.min_size {
-            // Mantener conexión en el pool
-            connections.push(connection);
-        } else {
-            // Cerrar conexión si excedemos el tamaño mínimo
-            *self.current_size.write().await -= 1;
-        }
-    }
-    
-    /// Crear nueva conexión
-    async fn create_connection(&self, db_path: &str) -> Result<rusqlite::Connection> {
-        let mut connection = rusqlite::Connection::open(db_path)?;
-        
-        // Configurar para mejor performance
-        connection.pragma_check_integrity(false)?;
-        connection.pragma_journal_mode(rusqlite::JournalMode::WAL)?;
-        connection.pragma_synchronous(rusqlite::Synchronous::Normal)?;
-        connection.pragma_cache_size(10000)?;
-        connection.pragma_temp_store(rusqlite::TempStore::Memory)?;
-        
-        Ok(connection)
-    }
-    
-    /// Obtener estadísticas del pool
-    pub async fn stats(&self) -> PoolStats {
-        let connections = self.connections.lock().await;
-        let current = *self.current_size.read().await;
-        
-        PoolStats {
-            available_connections: connections.len(),
-            total_connections: current,
-            max_size: self.max_size,
-            min_size: self.min_size,
-            utilization: current as f64 / self.max_size as f64,
-        }
-    }
-}
-
 /// Rate limiter para endpoints API
 #[derive(Debug)]
 #[derive(Clone)]
@@ -300,7 +218,7 @@ impl RateLimiter {
     
     /// Obtener tokens disponibles para un cliente
     pub async fn get_remaining_tokens(&self, client_id: &str) -> usize {
-        let tokens = self.tokens.lock().await;
+        let mut tokens = self.tokens.lock().await;
         *tokens.entry(client_id.to_string()).or_insert(self.max_tokens)
     }
 }
@@ -329,7 +247,7 @@ pub struct TableInfo {
     pub schema: String,
     pub columns: Vec<ColumnInfo>,
     pub row_count: Option<usize>,
-    pub last_analyzed: std::time::Instant,
+    pub last_analyzed: DateTime<Utc>,
 }
 
 #[derive(Debug, Clone, Serialize, Deserialize)]
@@ -404,6 +322,12 @@ pub struct MemoryUsage {
     pub connection_pool_size: usize,
 }
 
+impl Default for PerformanceMetrics {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
 impl PerformanceMetrics {
     pub fn new() -> Self {
         Self {
@@ -498,9 +422,6 @@ pub struct PerformanceConfig {
     pub enable_query_cache: bool,
     pub query_cache_size: usize,
     pub query_cache_ttl: Duration,
-    pub enable_connection_pool: bool,
-    pub connection_pool_min: usize,
-    pub connection_pool_max: usize,
     pub enable_rate_limiting: bool,
     pub rate_limit_tokens: usize,
     pub rate_limit_refill: usize,
@@ -515,9 +436,6 @@ impl Default for PerformanceConfig {
             enable_query_cache: true,
             query_cache_size: 1000,
             query_cache_ttl: Duration::from_secs(300),
-            enable_connection_pool: true,
-            connection_pool_min: 2,
-            connection_pool_max: 20,
             enable_rate_limiting: true,
             rate_limit_tokens: 100,
             rate_limit_refill: 10,
@@ -536,22 +454,11 @@ pub struct CacheStats {
     pub ttl_seconds: u64,
 }
 
-/// Estadísticas de pool de conexiones
-#[derive(Debug, Clone)]
-pub struct PoolStats {
-    pub available_connections: usize,
-    pub total_connections: usize,
-    pub max_size: usize,
-    pub min_size: usize,
-    pub utilization: f64,
-}
-
 /// Middleware de performance
 pub struct PerformanceMiddleware {
     pub metrics: PerformanceMetrics,
     pub rate_limiter: Option<RateLimiter>,
     pub query_cache: Option<QueryCache>,
-    pub connection_pool: Option<ConnectionPool>,
     pub metadata_cache: Option<DatabaseMetadataCache>,
 }
 
@@ -578,15 +485,6 @@ impl PerformanceMiddleware {
             None
         };
         
-        let connection_pool = if perf_config.enable_connection_pool {
-            Some(ConnectionPool::new(
-                perf_config.connection_pool_max,
-                perf_config.connection_pool_min,
-            ))
-        } else {
-            None
-        };
-        
         let metadata_cache = if perf_config.enable_metadata_cache {
             Some(DatabaseMetadataCache::new(perf_config.metadata_cache_ttl))
         } else {
@@ -597,7 +495,6 @@ impl PerformanceMiddleware {
             metrics: PerformanceMetrics::new(),
             rate_limiter,
             query_cache,
-            connection_pool,
             metadata_cache,
         }
     }
@@ -608,7 +505,7 @@ impl PerformanceMiddleware {
         if let Some(rate_limiter) = &self.rate_limiter {
             let limiter = rate_limiter.clone();
             tokio::spawn(async move {
-                let mut interval = tokio::time::interval(Duration::from_secs(10));
+                let mut interval = tokio::time::interval(limiter.refill_interval);
                 loop {
                     interval.tick().await;
                     limiter.refill_tokens().await;
@@ -640,4 +537,110 @@ impl PerformanceMiddleware {
             });
         }
     }
-}
\ No newline at end of file
+}
+/// Layer que rechaza con 413 los cuerpos de más de
+/// `ServerConfig::max_request_body_bytes`
+///
+/// Si la petición declara `Content-Length` se rechaza sin leerla; si no
+/// (chunked), el cuerpo se lee sólo hasta superar el límite. El límite se
+/// lee de la configuración en cada petición.
+#[derive(Clone)]
+pub struct BodySizeLimitLayer {
+    config: Arc<RwLock<ServerConfig>>,
+}
+
+impl BodySizeLimitLayer {
+    pub fn new(config: Arc<RwLock<ServerConfig>>) -> Self {
+        Self { config }
+    }
+}
+
+impl<S> Layer<S> for BodySizeLimitLayer {
+    type Service = BodySizeLimitService<S>;
+
+    fn layer(&self, inner: S) -> Self::Service {
+        BodySizeLimitService {
+            inner,
+            config: self.config.clone(),
+        }
+    }
+}
+
+/// Servicio generado por `BodySizeLimitLayer`
+#[derive(Clone)]
+pub struct BodySizeLimitService<S> {
+    inner: S,
+    config: Arc<RwLock<ServerConfig>>,
+}
+
+impl<S> Service<Request<Body>> for BodySizeLimitService<S>
+where
+    S: Service<Request<Body>, Response = Response> + Clone + Send + 'static,
+    S::Future: Send + 'static,
+{
+    type Response = Response;
+    type Error = S::Error;
+    type Future = Pin<Box<dyn Future<Output = std::result::Result<Response, S::Error>> + Send>>;
+
+    fn poll_ready(&mut self, cx: &mut Context<'_>) -> Poll<std::result::Result<(), Self::Error>> {
+        self.inner.poll_ready(cx)
+    }
+
+    fn call(&mut self, request: Request<Body>) -> Self::Future {
+        // El servicio listo es el que recibió poll_ready
+        let clone = self.inner.clone();
+        let mut inner = std::mem::replace(&mut self.inner, clone);
+        let config = self.config.clone();
+
+        Box::pin(async move {
+            let limit = config.read().await.max_request_body_bytes;
+            let declared = request
+                .headers()
+                .get(header::CONTENT_LENGTH)
+                .and_then(|value| value.to_str().ok())
+                .and_then(|value| value.parse::<u64>().ok());
+            if declared.is_some_and(|length| length > limit) {
+                return Ok(payload_too_large(limit));
+            }
+
+            let (parts, body) = request.into_parts();
+            let mut chunks = body.into_data_stream();
+            let mut bytes = Vec::new();
+            while let Some(chunk) = chunks.next().await {
+                let chunk = match chunk {
+                    Ok(chunk) => chunk,
+                    Err(e) => {
+                        return Ok((
+                            StatusCode::BAD_REQUEST,
+                            Json(ServerError::bad_request(format!(
+                                "Error leyendo la petición: {}",
+                                e
+                            ))),
+                        )
+                            .into_response())
+                    }
+                };
+                if (bytes.len() + chunk.len()) as u64 > limit {
+                    return Ok(payload_too_large(limit));
+                }
+                bytes.extend_from_slice(&chunk);
+            }
+
+            inner
+                .call(Request::from_parts(parts, Body::from(bytes)))
+                .await
+        })
+    }
+}
+
+/// Respuesta 413 de `BodySizeLimitLayer`
+pub(crate) fn payload_too_large(limit: u64) -> Response {
+    (
+        StatusCode::PAYLOAD_TOO_LARGE,
+        Json(ServerError::payload_too_large(format!(
+            "El cuerpo de la petición supera el máximo de {} bytes",
+            limit
+        ))),
+    )
+        .into_response()
+}
@@ -3,31 +3,88 @@
 //! Configura y organiza todas las rutas HTTP del servidor.
 
 use axum::{
-    extract::State,
-    http::StatusCode,
-    response::Json,
-    routing::{get, post, delete},
+    extract::{Extension, Query, State},
+    http::{header, HeaderMap, HeaderValue, StatusCode},
+    response::{IntoResponse, Json, Response},
+    routing::{delete, get, post, put},
     Router,
 };
 
+use crate::handlers::{
+    accepts_ndjson, lock_session, login_handler, pool_metrics_handler, prometheus_metrics_handler,
+    refresh_handler, requested_session_id, session_cookie_header, slow_queries_handler,
+    websocket_metrics_handler, Claims, CookieSessionLayer, HealthCheckHandler, JwtAuthLayer,
+    QueryHandler, SessionHandler, SlowQueryLayer, SqlSanitizer, StreamingQueryHandler,
+    SESSION_HEADER,
+};
+use crate::openapi::{api_docs, openapi_json};
+use crate::performance::BodySizeLimitLayer;
 use crate::server::ServerState;
-use crate::types::{QueryRequest, QueryResponse, FormRequest, FormResponse, ServerStatus};
+use crate::types::{
+    status_for_error, FormRequest, FormResponse, HealthReport, QueryParams, QueryRequest,
+    QueryResponse, ServerError, ServerStatus,
+};
+use crate::websocket::{WsAppExt, WsState};
 
-/// Crear router principal del servidor
+/// Cabecera con el total de filas de una consulta paginada (`?count=true`)
+pub const TOTAL_COUNT_HEADER: &str = "X-Noctra-Total-Count";
+use noctra_core::{Executor, NoctraError, QueryPlan, ResultSet, Session, SessionHandle};
+use noctra_parser::RqlStatement;
+use std::collections::HashMap;
+use std::sync::Arc;
+use std::time::Instant;
+
+/// Crear router principal del servidor (con `/ws`)
 pub fn create_router(state: ServerState) -> Router {
-    Router::new()
+    let ws_state = WsState::new(state.clone());
+    create_router_with(state, Some(&ws_state))
+}
+
+/// Crear router principal; `/ws` sólo se monta si hay `ws_state`
+///
+/// `/ws` exige el mismo JWT que las rutas protegidas: el upgrade ejecuta
+/// las consultas en vivo de la conexión.
+pub fn create_router_with(state: ServerState, ws_state: Option<&WsState>) -> Router {
+    let auth = JwtAuthLayer::new(state.config.clone());
+    // Rutas que requieren JWT (sin auth_secret sólo pasan con auth_disabled)
+    // API v1 - Consultas (medidas por el registro de consultas lentas)
+    let queries = Router::new()
+        .route("/api/v1/query", post(execute_query))
+        .route("/api/v1/query/stream", post(stream_query))
+        .route("/api/v1/query/batch", post(execute_batch_queries))
+        .route("/api/v1/query/plan", post(plan_query))
+        // Cada petición recibe su sesión como Extension<SessionHandle>
+        .layer(CookieSessionLayer::new(state.sessions.clone()))
+        .layer(SlowQueryLayer::new(state.config.clone(), state.slow_queries.clone()))
+        // Fuera de SlowQueryLayer: un cuerpo demasiado grande no se lee entero
+        .layer(BodySizeLimitLayer::new(state.config.clone()));
+
+    let protected = Router::new()
+        .merge(queries)
+        
+        // API v1 - Formularios
+        .route("/api/v1/form/:name", post(execute_form))
+        .route("/api/v1/form/:name/validate", post(validate_form))
+        
+        // API v1 - Autenticación
+        .route("/api/v1/auth/refresh", post(refresh_handler))
+        
+        // API v1 - Métricas con el SQL y la IP de los clientes
+        .route("/api/v1/metrics/slow-queries", get(slow_queries_handler))
+        .layer(auth.clone());
+
+    let router = Router::new()
         // Rutas raíz
         .route("/", get(root_info))
         .route("/health", get(health_check))
+        .route("/ready", get(readiness_check))
         .route("/status", get(server_status))
         
-        // API v1 - Consultas
-        .route("/api/v1/query", post(execute_query))
-        .route("/api/v1/query/batch", post(execute_batch_queries))
+        // API v1 - Autenticación
+        .route("/api/v1/auth/login", post(login_handler))
+        .merge(protected)
         
         // API v1 - Formularios
-        .route("/api/v1/form/:name", post(execute_form))
-        .route("/api/v1/form/:name/validate", post(validate_form))
         .route("/api/v1/forms", get(list_forms))
         
         // API v1 - Sesiones
@@ -40,19 +97,37 @@ pub fn create_router(state: ServerState) -> Router {
         .route("/api/v1/config", get(get_config))
         .route("/api/v1/config", put(update_config))
         
+        // API v1 - Métricas
+        .route("/api/v1/metrics/pool", get(pool_metrics_handler))
+        .route("/api/v1/metrics/websocket", get(websocket_metrics_handler))
+        .route("/metrics", get(prometheus_metrics_handler))
+        
         // API v1 - Utilidades
         .route("/api/v1/parse", post(parse_sql))
         .route("/api/v1/validate/sql", post(validate_sql))
         .route("/api/v1/templates", get(list_templates))
         
-        // WebSocket (placeholder)
-        .route("/ws", get(websocket_endpoint))
+        // API v1 - Documentación
+        .route("/api/v1/openapi.json", get(openapi_json))
+        .route("/api/v1/docs", get(api_docs))
         
         // Configurar estado compartido
-        .with_state(state)
+        .with_state(state);
+
+    // WebSocket
+    match ws_state {
+        Some(ws_state) => router.merge(Router::new().add_websocket_routes(ws_state).layer(auth)),
+        None => router,
+    }
 }
 
 /// Información raíz del servidor
+#[utoipa::path(
+    get,
+    path = "/",
+    tag = "servidor",
+    responses((status = 200, description = "Servicio y endpoints principales", body = Object))
+)]
 async fn root_info() -> Json<serde_json::Value> {
     Json(serde_json::json!({
         "service": "Noctra Server",
@@ -60,144 +135,512 @@ async fn root_info() -> Json<serde_json::Value> {
         "description": "Entorno SQL Interactivo - Servidor HTTP/API",
         "endpoints": {
             "health": "/health",
+            "ready": "/ready",
             "status": "/status",
             "query": "POST /api/v1/query",
+            "stream": "POST /api/v1/query/stream",
+            "plan": "POST /api/v1/query/plan",
             "form": "POST /api/v1/form/{name}",
-            "session": "POST /api/v1/session"
+            "session": "POST /api/v1/session",
+            "login": "POST /api/v1/auth/login",
+            "refresh": "POST /api/v1/auth/refresh",
+            "slow_queries": "GET /api/v1/metrics/slow-queries",
+            "pool": "GET /api/v1/metrics/pool",
+            "websocket": "GET /api/v1/metrics/websocket",
+            "metrics": "GET /metrics",
+            "openapi": "GET /api/v1/openapi.json",
+            "docs": "GET /api/v1/docs"
         },
         "documentation": "https://docs.noctra.dev"
     }))
 }
 
-/// Health check del servidor
-async fn health_check() -> Result<Json<serde_json::Value>, StatusCode> {
-    Ok(Json(serde_json::json!({
-        "status": "healthy",
-        "timestamp": chrono::Utc::now().to_rfc3339(),
-        "version": "0.1.0",
-        "checks": {
-            "database": "ok",
-            "parser": "ok",
-            "executor": "ok"
-        }
-    })))
+/// Health check del servidor: prueba SQLite y las fuentes DuckDB
+#[utoipa::path(
+    get,
+    path = "/health",
+    tag = "servidor",
+    responses(
+        (status = 200, description = "Todas las dependencias responden", body = HealthReport),
+        (status = 207, description = "Alguna fuente DuckDB falla", body = HealthReport),
+        (status = 503, description = "SQLite no responde", body = HealthReport)
+    )
+)]
+pub(crate) async fn health_check(
+    State(state): State<ServerState>,
+) -> (StatusCode, Json<HealthReport>) {
+    let executor = state.get_executor().await.ok();
+    let uptime_seconds = state.start_time.elapsed().as_secs();
+    let report = tokio::task::spawn_blocking(move || {
+        HealthCheckHandler::new(executor, uptime_seconds).handle()
+    })
+    .await
+    .unwrap_or_else(|_| HealthCheckHandler::new(None, uptime_seconds).handle());
+
+    (report.status_code(), Json(report))
+}
+
+/// Readiness (Kubernetes): 200 sólo cuando el servidor terminó de inicializarse
+#[utoipa::path(
+    get,
+    path = "/ready",
+    tag = "servidor",
+    responses(
+        (status = 200, description = "Servidor inicializado", body = Object),
+        (status = 503, description = "Servidor inicializándose", body = Object)
+    )
+)]
+pub(crate) async fn readiness_check(
+    State(state): State<ServerState>,
+) -> (StatusCode, Json<serde_json::Value>) {
+    if state.is_ready() {
+        (StatusCode::OK, Json(serde_json::json!({ "ready": true })))
+    } else {
+        (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(serde_json::json!({ "ready": false })),
+        )
+    }
 }
 
 /// Estado detallado del servidor
+#[utoipa::path(
+    get,
+    path = "/status",
+    tag = "servidor",
+    responses((status = 200, description = "Estado del servidor", body = ServerStatus))
+)]
 async fn server_status(State(state): State<ServerState>) -> Json<ServerStatus> {
-    Json(ServerStatus {
-        version: "0.1.0".to_string(),
-        uptime_seconds: state.start_time.elapsed().as_secs(),
-        connected_sessions: state.sessions.read().await.len(),
-        active_queries: 0, // TODO: Implementar contador
-        database_status: "connected".to_string(),
-    })
+    Json(crate::server::server_status(&state))
 }
 
 /// Ejecutar consulta SQL/RQL
+#[utoipa::path(
+    post,
+    path = "/api/v1/query",
+    tag = "consultas",
+    request_body = QueryRequest,
+    params(
+        ("count" = Option<bool>, Query, description = "Calcular total_count con un COUNT(*) adicional")
+    ),
+    responses(
+        (status = 200, description = "Resultado de la consulta (NDJSON con Accept: application/x-ndjson)", body = QueryResponse,
+            headers(
+                ("X-Noctra-Total-Count" = usize, description = "Total de filas sin paginar (con count=true)"),
+                ("X-Noctra-Session" = String, description = "Sesión de la consulta (sólo si la petición indicó una)")
+            )),
+        (status = 400, description = "Consulta inválida", body = ServerError),
+        (status = 401, description = "Token ausente o inválido", body = ServerError),
+        (status = 403, description = "Acceso denegado a la ruta o fuente, o escritura en una consulta pública de solo lectura", body = ServerError),
+        (status = 404, description = "Tabla, fuente o sesión inexistente", body = ServerError),
+        (status = 409, description = "Conflicto (restricción o bloqueo)", body = ServerError),
+        (status = 503, description = "Executor no disponible", body = ServerError),
+        (status = 504, description = "Consulta cancelada por timeout", body = ServerError)
+    ),
+    security(("bearer_auth" = []))
+)]
 async fn execute_query(
     State(state): State<ServerState>,
+    Query(params): Query<QueryParams>,
+    claims: Option<Extension<Claims>>,
+    Extension(handle): Extension<SessionHandle>,
+    headers: HeaderMap,
     Json(request): Json<QueryRequest>,
-) -> Result<Json<QueryResponse>, StatusCode> {
-    let start_time = std::time::Instant::now();
-    
-    // TODO: Implementar ejecución real usando parser y executor
-    // Por ahora simular resultado
+) -> Result<Response, (StatusCode, Json<ServerError>)> {
+    let executor = state.get_executor().await.map_err(|e| {
+        (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(ServerError::service_unavailable(e)),
+        )
+    })?;
+    let sanitizer = SqlSanitizer::from_config(&*state.config.read().await);
+    // Sin claims la petición no se autenticó: con public_read_only sólo lee.
+    // Se comprueba el SQL ya con las #variables de la sesión expandidas.
+    if claims.is_none() && state.config.read().await.public_read_only {
+        let session = lock_session(&handle).map_err(error_response)?;
+        executor
+            .ensure_read_only(&session, &request.query)
+            .map_err(error_response)?;
+    }
+    if accepts_ndjson(&headers) {
+        sanitizer.check(&request.query).map_err(error_response)?;
+        return Ok(stream_with_session(executor, handle, &request, &headers).await);
+    }
+    let ephemeral = requested_session_id(&headers).is_none();
+    if let Some((path, table)) = session_file(&state, &request.query)
+        .await
+        .map_err(error_response)?
+    {
+        sanitizer.check(&request.query).map_err(error_response)?;
+        let start_time = Instant::now();
+        blocking(move || {
+            let session = lock_session(&handle)?;
+            let registered = executor.register_session_file(&session, &path, &table);
+            release_if_ephemeral(&executor, ephemeral, &session);
+            registered
+        })
+        .await
+        .map_err(error_response)?;
+        return Ok(Json(QueryResponse {
+            result: ResultSet::empty(),
+            execution_time_ms: start_time.elapsed().as_millis() as u64,
+            session_id: request.session_id,
+            metadata: HashMap::new(),
+            total_count: None,
+            page: None,
+            page_size: None,
+            total_pages: None,
+            cursor: None,
+        })
+        .into_response());
+    }
+    let handler =
+        QueryHandler::new(executor.clone(), state.get_parser().await).with_sanitizer(sanitizer);
     
-    let mock_columns = vec![
-        noctra_core::Column::new("id", "INTEGER", 0),
-        noctra_core::Column::new("name", "TEXT", 1),
-        noctra_core::Column::new("status", "TEXT", 2),
-    ];
+    // La consulta bloquea: se ejecuta fuera de los hilos del runtime
+    let response = blocking(move || {
+        let session = lock_session(&handle)?;
+        let response = handler.handle_query(&session, &request, params.count);
+        release_if_ephemeral(&executor, ephemeral, &session);
+        response
+    })
+    .await
+    .map_err(error_response)?;
     
-    let mock_rows = vec![
-        noctra_core::Row::new(vec![
-            noctra_core::Value::integer(1),
-            noctra_core::Value::text("Test Row 1"),
-            noctra_core::Value::text("active"),
-        ]),
-        noctra_core::Row::new(vec![
-            noctra_core::Value::integer(2),
-            noctra_core::Value::text("Test Row 2"),
-            noctra_core::Value::text("pending"),
-        ]),
-    ];
+    let total_count = response.total_count;
+    let mut http_response = Json(response).into_response();
+    if let Some(total) = total_count {
+        http_response
+            .headers_mut()
+            .insert(TOTAL_COUNT_HEADER, HeaderValue::from(total));
+    }
+    if let Some(value) =
+        requested_session_id(&headers).and_then(|id| HeaderValue::from_str(&id).ok())
+    {
+        http_response.headers_mut().insert(SESSION_HEADER, value);
+    }
     
-    let mut result = noctra_core::ResultSet::new(mock_columns);
-    result.add_rows(mock_rows);
+    Ok(http_response)
+}
+
+/// Ejecutar consulta SQL/RQL devolviendo las filas en streaming (NDJSON)
+///
+/// Una fila JSON por línea; la última línea es
+/// `{"__meta":{"rows":N,"duration_ms":M}}`.
+#[utoipa::path(
+    post,
+    path = "/api/v1/query/stream",
+    tag = "consultas",
+    request_body = QueryRequest,
+    responses(
+        (status = 200, description = "Filas en NDJSON seguidas de una línea __meta", content_type = "application/x-ndjson", body = String),
+        (status = 400, description = "Consulta inválida", body = ServerError),
+        (status = 401, description = "Token ausente o inválido", body = ServerError),
+        (status = 403, description = "Acceso denegado a la ruta o fuente", body = ServerError),
+        (status = 404, description = "Tabla o fuente inexistente", body = ServerError),
+        (status = 503, description = "Executor no disponible", body = ServerError)
+    ),
+    security(("bearer_auth" = []))
+)]
+async fn stream_query(
+    State(state): State<ServerState>,
+    Extension(handle): Extension<SessionHandle>,
+    headers: HeaderMap,
+    Json(request): Json<QueryRequest>,
+) -> Result<Response, (StatusCode, Json<ServerError>)> {
+    let executor = state.get_executor().await.map_err(|e| {
+        (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(ServerError::service_unavailable(e)),
+        )
+    })?;
+    SqlSanitizer::from_config(&*state.config.read().await)
+        .check(&request.query)
+        .map_err(error_response)?;
     
-    let execution_time = start_time.elapsed().as_millis() as u64;
+    Ok(stream_with_session(executor, handle, &request, &headers).await)
+}
+
+/// Stream NDJSON sobre la sesión de la petición, devolviendo su ID en la
+/// cabecera si es una sesión del gestor
+async fn stream_with_session(
+    executor: Arc<Executor>,
+    handle: SessionHandle,
+    request: &QueryRequest,
+    headers: &HeaderMap,
+) -> Response {
+    let mut response = StreamingQueryHandler::new(executor)
+        .handle(handle, request)
+        .await;
+    if let Some(value) =
+        requested_session_id(headers).and_then(|id| HeaderValue::from_str(&id).ok())
+    {
+        response.headers_mut().insert(SESSION_HEADER, value);
+    }
+    response
+}
+
+/// Validar consulta sin ejecutarla y devolver su plan
+#[utoipa::path(
+    post,
+    path = "/api/v1/query/plan",
+    tag = "consultas",
+    request_body = QueryRequest,
+    responses(
+        (status = 200, description = "Plan de la consulta (tablas, parámetros, filas estimadas)", body = Object),
+        (status = 400, description = "Consulta inválida", body = ServerError),
+        (status = 401, description = "Token ausente o inválido", body = ServerError),
+        (status = 503, description = "Executor no disponible", body = ServerError)
+    ),
+    security(("bearer_auth" = []))
+)]
+async fn plan_query(
+    State(state): State<ServerState>,
+    Extension(handle): Extension<SessionHandle>,
+    Json(request): Json<QueryRequest>,
+) -> Result<Json<QueryPlan>, (StatusCode, Json<ServerError>)> {
+    let executor = state.get_executor().await.map_err(|e| {
+        (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(ServerError::service_unavailable(e)),
+        )
+    })?;
     
-    Ok(Json(QueryResponse {
-        success: true,
-        data: Some(result),
-        message: "Consulta ejecutada exitosamente".to_string(),
-        execution_time_ms: execution_time,
-    }))
+    // Las #variables se expanden con la sesión de la petición, como en /query
+    blocking(move || executor.dry_run(&*lock_session(&handle)?, &request.query))
+        .await
+        .map(Json)
+        .map_err(|e| (status_for_error(&e), Json(ServerError::from_noctra(&e))))
 }
 
 /// Ejecutar consultas en lote
+#[utoipa::path(
+    post,
+    path = "/api/v1/query/batch",
+    tag = "consultas",
+    request_body = Vec<QueryRequest>,
+    responses(
+        (status = 200, description = "Una respuesta por consulta", body = Vec<QueryResponse>),
+        (status = 400, description = "Alguna consulta es inválida (no se ejecutan las siguientes)", body = ServerError),
+        (status = 401, description = "Token ausente o inválido", body = ServerError)
+    ),
+    security(("bearer_auth" = []))
+)]
 async fn execute_batch_queries(
     State(state): State<ServerState>,
+    Extension(handle): Extension<SessionHandle>,
+    headers: HeaderMap,
     Json(requests): Json<Vec<QueryRequest>>,
-) -> Result<Json<Vec<QueryResponse>>, StatusCode> {
-    let mut responses = Vec::new();
-    
-    for request in requests {
-        let start_time = std::time::Instant::now();
-        
-        // TODO: Ejecutar consulta real
-        let execution_time = start_time.elapsed().as_millis() as u64;
-        
-        let response = QueryResponse {
-            success: true,
-            data: None, // Batch queries típicamente no devuelven datos
-            message: "Consulta batch ejecutada".to_string(),
-            execution_time_ms: execution_time,
-        };
-        
-        responses.push(response);
+) -> Result<Json<Vec<QueryResponse>>, (StatusCode, Json<ServerError>)> {
+    let executor = state.get_executor().await.map_err(|e| {
+        (
+            StatusCode::SERVICE_UNAVAILABLE,
+            Json(ServerError::service_unavailable(e)),
+        )
+    })?;
+    let sanitizer = SqlSanitizer::from_config(&*state.config.read().await);
+    let handler =
+        QueryHandler::new(executor.clone(), state.get_parser().await).with_sanitizer(sanitizer);
+
+    let ephemeral = requested_session_id(&headers).is_none();
+    blocking(move || {
+        let session = lock_session(&handle)?;
+        let responses = requests
+            .iter()
+            .map(|request| handler.handle_query(&session, request, false))
+            .collect::<noctra_core::Result<Vec<_>>>();
+        release_if_ephemeral(&executor, ephemeral, &session);
+        responses
+    })
+    .await
+    .map(Json)
+    .map_err(error_response)
+}
+
+/// Archivo y tabla de una consulta que es sólo `USE '<archivo>' AS tabla`
+///
+/// El servidor registra ese archivo como tabla de la sesión (ver
+/// `Executor::register_session_file`): otras sesiones no la ven y se borra
+/// cuando la sesión termina. Sin `AS` no hay nombre de tabla y se rechaza.
+async fn session_file(
+    state: &ServerState,
+    query: &str,
+) -> noctra_core::Result<Option<(String, String)>> {
+    if !query.trim_start().to_uppercase().starts_with("USE ") {
+        return Ok(None);
+    }
+    let ast = state
+        .get_parser()
+        .await
+        .parse_rql(query)
+        .await
+        .map_err(|e| NoctraError::Validation(e.to_string()))?;
+    match ast.statements.as_slice() {
+        [RqlStatement::UseSource {
+            path,
+            alias: Some(alias),
+            ..
+        }] => Ok(Some((path.clone(), alias.clone()))),
+        [RqlStatement::UseSource {
+            path, alias: None, ..
+        }] => Err(NoctraError::Validation(format!(
+            "USE '{}' necesita AS <tabla> para registrarse en la sesión",
+            path
+        ))),
+        _ => Ok(None),
+    }
+}
+
+/// Ejecutar trabajo bloqueante (consultas al executor) con `spawn_blocking`
+async fn blocking<T, F>(work: F) -> noctra_core::Result<T>
+where
+    F: FnOnce() -> noctra_core::Result<T> + Send + 'static,
+    T: Send + 'static,
+{
+    tokio::task::spawn_blocking(work).await.unwrap_or_else(|e| {
+        Err(NoctraError::Internal(format!(
+            "Hilo de consulta caído: {}",
+            e
+        )))
+    })
+}
+
+/// Liberar la sesión efímera de una petición anónima (ver `resolve_session`)
+///
+/// Un BEGIN sin COMMIT no debe dejar reservada una conexión del pool: nadie
+/// podrá volver a usar esa sesión.
+fn release_if_ephemeral(executor: &Executor, ephemeral: bool, session: &Session) {
+    if !ephemeral {
+        return;
+    }
+    if let Err(e) = executor.release_session(session.id()) {
+        log::warn!(
+            "No se pudo liberar la sesión efímera {}: {}",
+            session.id(),
+            e
+        );
     }
-    
-    Ok(Json(responses))
 }
 
 /// Ejecutar formulario
+#[utoipa::path(
+    post,
+    path = "/api/v1/form/{name}",
+    tag = "formularios",
+    params(("name" = String, Path, description = "Nombre del formulario")),
+    request_body = FormRequest,
+    responses(
+        (status = 200, description = "Resultado de la acción", body = FormResponse),
+        (status = 404, description = "Formulario inexistente en `forms_directory`", body = ServerError),
+        (status = 401, description = "Token ausente o inválido", body = ServerError)
+    ),
+    security(("bearer_auth" = []))
+)]
 async fn execute_form(
     State(state): State<ServerState>,
     axum::extract::Path(name): axum::extract::Path<String>,
-    Json(request): Json<FormRequest>,
-) -> Result<Json<FormResponse>, StatusCode> {
+    Json(_request): Json<FormRequest>,
+) -> Result<Json<FormResponse>, (StatusCode, Json<ServerError>)> {
+    load_named_form(&state, &name).await?;
+    
     // TODO: Implementar ejecución real de formularios
     let response = FormResponse {
         success: true,
-        data: None,
         message: format!("Formulario '{}' ejecutado exitosamente", name),
-        form_title: Some(format!("Formulario {}", name)),
+        data: HashMap::new(),
+        validation_errors: Vec::new(),
     };
     
     Ok(Json(response))
 }
 
+/// Cargar `<forms_directory>/<name>.toml`
+///
+/// Sin directorio de formularios, o con un nombre que no corresponde a
+/// ningún archivo, responde 404.
+async fn load_named_form(
+    state: &ServerState,
+    name: &str,
+) -> Result<noctra_formlib::Form, (StatusCode, Json<ServerError>)> {
+    let not_found = || {
+        (
+            StatusCode::NOT_FOUND,
+            Json(ServerError::not_found("Formulario no encontrado")),
+        )
+    };
+
+    // El nombre no puede salir del directorio de formularios
+    let valid_name = !name.is_empty()
+        && name
+            .chars()
+            .all(|c| c.is_ascii_alphanumeric() || c == '_' || c == '-');
+    if !valid_name {
+        return Err(not_found());
+    }
+
+    let directory = state
+        .config
+        .read()
+        .await
+        .forms_directory
+        .clone()
+        .ok_or_else(not_found)?;
+    let path = directory.join(format!("{}.toml", name));
+    if !path.is_file() {
+        return Err(not_found());
+    }
+
+    noctra_formlib::load_form_from_path(&path).map_err(|e| {
+        (
+            StatusCode::BAD_REQUEST,
+            Json(ServerError::bad_request(format!(
+                "Error cargando formulario '{}': {}",
+                name, e
+            ))),
+        )
+    })
+}
+
 /// Validar formulario
+#[utoipa::path(
+    post,
+    path = "/api/v1/form/{name}/validate",
+    tag = "formularios",
+    params(("name" = String, Path, description = "Nombre del formulario")),
+    request_body = FormRequest,
+    responses(
+        (status = 200, description = "Resultado de la validación", body = FormResponse),
+        (status = 404, description = "Formulario inexistente en `forms_directory`", body = ServerError),
+        (status = 401, description = "Token ausente o inválido", body = ServerError)
+    ),
+    security(("bearer_auth" = []))
+)]
 async fn validate_form(
     State(state): State<ServerState>,
     axum::extract::Path(name): axum::extract::Path<String>,
-    Json(request): Json<FormRequest>,
-) -> Result<Json<FormResponse>, StatusCode> {
+    Json(_request): Json<FormRequest>,
+) -> Result<Json<FormResponse>, (StatusCode, Json<ServerError>)> {
+    load_named_form(&state, &name).await?;
+    
     // TODO: Implementar validación real de formularios
     let response = FormResponse {
         success: true,
-        data: None,
         message: format!("Formulario '{}' validado correctamente", name),
-        form_title: Some(format!("Formulario {}", name)),
+        data: HashMap::new(),
+        validation_errors: Vec::new(),
     };
     
     Ok(Json(response))
 }
 
 /// Listar formularios disponibles
-async fn list_forms(State(state): State<ServerState>) -> Json<serde_json::Value> {
+#[utoipa::path(
+    get,
+    path = "/api/v1/forms",
+    tag = "formularios",
+    responses((status = 200, description = "Formularios disponibles", body = Object))
+)]
+async fn list_forms(State(_state): State<ServerState>) -> Json<serde_json::Value> {
     // TODO: Implementar listado real de formularios
     Json(serde_json::json!({
         "forms": [
@@ -217,74 +660,122 @@ async fn list_forms(State(state): State<ServerState>) -> Json<serde_json::Value>
     }))
 }
 
+/// Respuesta de error de un `NoctraError`
+fn error_response(e: noctra_core::NoctraError) -> (StatusCode, Json<ServerError>) {
+    (status_for_error(&e), Json(ServerError::from_noctra(&e)))
+}
+
 /// Crear nueva sesión
-async fn create_session(State(state): State<ServerState>) -> Result<Json<serde_json::Value>, StatusCode> {
-    let session = Session::new(format!("session_{}", chrono::Utc::now().timestamp()));
-    let mut sessions = state.sessions.write().await;
-    sessions.push(session.clone());
+#[utoipa::path(
+    post,
+    path = "/api/v1/session",
+    tag = "sesiones",
+    responses((status = 200, description = "Sesión creada", body = Object))
+)]
+async fn create_session(
+    State(state): State<ServerState>,
+) -> Result<Response, (StatusCode, Json<ServerError>)> {
+    let info = SessionHandler::new(state.sessions.clone())
+        .create_session()
+        .map_err(error_response)?;
     
-    Ok(Json(serde_json::json!({
-        "session_id": session.id,
-        "message": "Sesión creada exitosamente",
-        "expires_in": 3600
-    })))
+    // La cookie permite a un navegador usar la sesión sin X-Noctra-Session
+    let secure = state.config.read().await.secure_cookies;
+    let cookie = info["session_id"]
+        .as_str()
+        .and_then(|id| session_cookie_header(id, secure));
+    let mut response = Json(info).into_response();
+    if let Some(cookie) = cookie {
+        response.headers_mut().insert(header::SET_COOKIE, cookie);
+    }
+    Ok(response)
 }
 
 /// Obtener información de sesión
+#[utoipa::path(
+    get,
+    path = "/api/v1/session/{id}",
+    tag = "sesiones",
+    params(("id" = String, Path, description = "ID de la sesión")),
+    responses(
+        (status = 200, description = "Información de la sesión", body = Object),
+        (status = 404, description = "Sesión inexistente o caducada", body = ServerError)
+    )
+)]
 async fn get_session(
     State(state): State<ServerState>,
     axum::extract::Path(id): axum::extract::Path<String>,
-) -> Result<Json<serde_json::Value>, StatusCode> {
-    // TODO: Buscar sesión real
-    Ok(Json(serde_json::json!({
-        "session_id": id,
-        "status": "active",
-        "created_at": chrono::Utc::now().to_rfc3339(),
-        "last_activity": chrono::Utc::now().to_rfc3339()
-    })))
+) -> Result<Json<serde_json::Value>, (StatusCode, Json<ServerError>)> {
+    SessionHandler::new(state.sessions.clone())
+        .get_session(&id)
+        .map(Json)
+        .map_err(error_response)
 }
 
 /// Eliminar sesión
+#[utoipa::path(
+    delete,
+    path = "/api/v1/session/{id}",
+    tag = "sesiones",
+    params(("id" = String, Path, description = "ID de la sesión")),
+    responses(
+        (status = 200, description = "Sesión eliminada", body = Object),
+        (status = 404, description = "Sesión inexistente o caducada", body = ServerError)
+    )
+)]
 async fn delete_session(
     State(state): State<ServerState>,
     axum::extract::Path(id): axum::extract::Path<String>,
-) -> Result<Json<serde_json::Value>, StatusCode> {
-    // TODO: Eliminar sesión real
-    Ok(Json(serde_json::json!({
-        "message": format!("Sesión {} eliminada", id)
-    })))
+) -> Result<Json<serde_json::Value>, (StatusCode, Json<ServerError>)> {
+    let response = SessionHandler::new(state.sessions.clone())
+        .delete_session(&id)
+        .map_err(error_response)?;
+    // Cursores y transacción los libera el hook de `ServerState::new`
+    Ok(Json(response))
 }
 
-/// Listar sesiones activas
+/// Contar sesiones activas (sin sus IDs)
+#[utoipa::path(
+    get,
+    path = "/api/v1/sessions",
+    tag = "sesiones",
+    responses((status = 200, description = "`total`: número de sesiones activas", body = Object))
+)]
 async fn list_sessions(State(state): State<ServerState>) -> Json<serde_json::Value> {
-    let sessions = state.sessions.read().await;
-    
-    Json(serde_json::json!({
-        "sessions": sessions.iter().map(|s| serde_json::json!({
-            "id": s.id,
-            "created_at": s.created_at
-        })).collect::<Vec<_>>(),
-        "total": sessions.len()
-    }))
+    Json(SessionHandler::new(state.sessions.clone()).list_sessions())
 }
 
 /// Obtener configuración del servidor
+#[utoipa::path(
+    get,
+    path = "/api/v1/config",
+    tag = "servidor",
+    responses((status = 200, description = "Configuración actual", body = Object))
+)]
 async fn get_config(State(state): State<ServerState>) -> Json<serde_json::Value> {
+    let config = state.config.read().await;
     Json(serde_json::json!({
-        "database_url": state.config.database_url,
-        "bind_address": state.config.bind_address.to_string(),
-        "request_timeout_seconds": state.config.request_timeout.as_secs(),
-        "max_connections": state.config.max_connections,
-        "cors_enabled": state.config.enable_cors,
-        "websockets_enabled": state.config.enable_websockets,
-        "auth_enabled": state.config.auth_secret.is_some()
+        "database_url": config.database_url,
+        "bind_address": config.bind_address.to_string(),
+        "request_timeout_seconds": config.request_timeout.as_secs(),
+        "max_connections": config.max_connections,
+        "cors_enabled": config.cors_enabled,
+        "websockets_enabled": config.websocket_enabled,
+        "auth_enabled": config.auth_secret.is_some()
     }))
 }
 
 /// Actualizar configuración (placeholder)
+#[utoipa::path(
+    put,
+    path = "/api/v1/config",
+    tag = "servidor",
+    request_body = Object,
+    responses((status = 200, description = "Configuración actualizada", body = Object))
+)]
 async fn update_config(
-    State(state): State<ServerState>,
-    Json(config): Json<serde_json::Value>,
+    State(_state): State<ServerState>,
+    Json(_config): Json<serde_json::Value>,
 ) -> Result<Json<serde_json::Value>, StatusCode> {
     // TODO: Implementar actualización real de configuración
     Ok(Json(serde_json::json!({
@@ -293,8 +784,15 @@ async fn update_config(
 }
 
 /// Parsear SQL sin ejecutar
+#[utoipa::path(
+    post,
+    path = "/api/v1/parse",
+    tag = "utilidades",
+    request_body(content = Object, description = "Objeto con el campo `sql`"),
+    responses((status = 200, description = "Resultado del parseo", body = Object))
+)]
 async fn parse_sql(
-    State(state): State<ServerState>,
+    State(_state): State<ServerState>,
     Json(request): Json<serde_json::Value>,
 ) -> Result<Json<serde_json::Value>, StatusCode> {
     let sql = request.get("sql")
@@ -311,8 +809,15 @@ async fn parse_sql(
 }
 
 /// Validar SQL sintácticamente
+#[utoipa::path(
+    post,
+    path = "/api/v1/validate/sql",
+    tag = "utilidades",
+    request_body(content = Object, description = "Objeto con el campo `sql`"),
+    responses((status = 200, description = "Resultado de la validación", body = Object))
+)]
 async fn validate_sql(
-    State(state): State<ServerState>,
+    State(_state): State<ServerState>,
     Json(request): Json<serde_json::Value>,
 ) -> Result<Json<serde_json::Value>, StatusCode> {
     let sql = request.get("sql")
@@ -328,7 +833,13 @@ async fn validate_sql(
 }
 
 /// Listar templates disponibles
-async fn list_templates(State(state): State<ServerState>) -> Json<serde_json::Value> {
+#[utoipa::path(
+    get,
+    path = "/api/v1/templates",
+    tag = "utilidades",
+    responses((status = 200, description = "Templates SQL disponibles", body = Object))
+)]
+async fn list_templates(State(_state): State<ServerState>) -> Json<serde_json::Value> {
     Json(serde_json::json!({
         "templates": [
             {
@@ -345,10 +856,5 @@ async fn list_templates(State(state): State<ServerState>) -> Json<serde_json::Va
     }))
 }
 
-/// Endpoint WebSocket (placeholder)
-async fn websocket_endpoint() -> Result<String, StatusCode> {
-    Ok("WebSocket endpoint - Funcionalidad en desarrollo".to_string())
-}
-
 /// Router específico para Noctra (alias)
 pub type NoctraRouter = Router<ServerState>;
\ No newline at end of file
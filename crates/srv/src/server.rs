@@ -4,28 +4,27 @@
 //! formularios FDL2 y gestión de sesiones.
 
 use std::net::SocketAddr;
+use std::sync::atomic::{AtomicBool, Ordering};
 use std::sync::Arc;
-use std::time::{Duration, Instant};
-
-use axum::{
-    extract::{State, ConnectInfo},
-    http::StatusCode,
-    response::Json,
-    routing::{get, post},
-    Router,
-};
+use std::time::Duration;
+
+use axum::Router;
+use log::{info, warn};
+use tokio::signal;
 use tower_http::cors::CorsLayer;
 use tower_http::trace::TraceLayer;
-use tracing::{info, warn, error};
-use tokio::signal;
 
-use noctra_core::{Session, Executor};
+use noctra_core::{
+    Executor, ExecutorOptions, FileSessionStore, InMemoryRecorder, JsonlQueryLogger, SessionConfig,
+    SessionManager,
+};
 use noctra_parser::RqlParser;
 
-use crate::routes::{create_router, NoctraRouter};
-use crate::handlers::{QueryHandler, FormHandler, SessionHandler};
-use crate::types::{QueryRequest, QueryResponse, FormRequest, FormResponse, ServerStatus, ServerError};
-use crate::performance::{PerformanceMiddleware, PerformanceConfig, SerializedMetrics};
+use crate::handlers::SlowQueryLog;
+use crate::performance::{PerformanceMiddleware, SerializedMetrics};
+use crate::routes::create_router_with;
+use crate::types::{AuthUser, HealthStatus, ServerStatus};
+use crate::websocket::{WsState, WsTraffic};
 
 /// Configuración extendida del servidor
 #[derive(Debug, Clone)]
@@ -42,8 +41,19 @@ pub struct ServerConfig {
     /// Máximo de conexiones concurrentes
     pub max_connections: usize,
     
-    /// Secret para autenticación
+    /// Secret HS256 para firmar y validar JWT (sin él las rutas protegidas
+    /// responden 401, salvo con `auth_disabled`)
     pub auth_secret: Option<String>,
+
+    /// Servir las rutas protegidas sin token cuando no hay `auth_secret`
+    /// (sólo para desarrollo o redes de confianza)
+    pub auth_disabled: bool,
+
+    /// Vigencia de los tokens emitidos
+    pub token_ttl: Duration,
+
+    /// Usuarios que pueden obtener tokens en /api/v1/auth/login
+    pub users: Vec<AuthUser>,
     
     /// Habilitar CORS
     pub cors_enabled: bool,
@@ -57,14 +67,51 @@ pub struct ServerConfig {
     /// Habilitar métricas
     pub metrics_enabled: bool,
     
+    /// Consultas más lentas que esto (ms) se guardan en el registro de consultas lentas
+    pub slow_query_threshold_ms: u64,
+
+    /// Máximo de entradas del registro de consultas lentas
+    pub slow_query_log_size: usize,
+
+    /// Archivo JSONL de auditoría de consultas (None = sin registro)
+    pub query_log_path: Option<std::path::PathBuf>,
+
+    /// Tiempo sin actividad tras el que caduca una sesión
+    pub session_timeout: Duration,
+
+    /// Directorio donde se guardan las sesiones para que sobrevivan a un
+    /// reinicio (None = sólo en memoria)
+    pub session_dir: Option<std::path::PathBuf>,
+
+    /// Marcar la cookie de sesión como `Secure`; activarlo cuando el
+    /// servidor se sirve por HTTPS (p. ej. detrás de un proxy con TLS), ya
+    /// que por HTTP plano el navegador no devolvería la cookie
+    pub secure_cookies: bool,
+
+    /// Admitir consultas sin token en `/api/v1/query` aunque haya
+    /// `auth_secret`, pero sólo de lectura; las autenticadas conservan la
+    /// escritura
+    pub public_read_only: bool,
+
     /// Configuración adicional para rutas
     pub database_path: Option<std::path::PathBuf>,
     pub forms_directory: Option<std::path::PathBuf>,
-    pub token_file: Option<std::path::PathBuf>,
+    /// Archivo JSON con usuarios adicionales (lista de `AuthUser`)
+    pub users_file: Option<std::path::PathBuf>,
     
     /// Configuraciones de performance
     pub rate_limiting_enabled: bool,
     pub query_timeout: Duration,
+
+    /// Tamaño máximo del cuerpo de las peticiones de consulta (bytes); uno
+    /// mayor se responde con 413 (ver `BodySizeLimitLayer`)
+    pub max_request_body_bytes: u64,
+
+    /// Longitud máxima de una consulta en caracteres (ver `SqlSanitizer`)
+    pub max_query_length_chars: usize,
+
+    /// Palabras clave que las consultas no pueden usar (`DROP`, `TRUNCATE`...)
+    pub blocked_keywords: Vec<String>,
 }
 
 impl Default for ServerConfig {
@@ -75,30 +122,68 @@ impl Default for ServerConfig {
             request_timeout: Duration::from_secs(30),
             max_connections: 100,
             auth_secret: None,
+            auth_disabled: false,
+            token_ttl: Duration::from_secs(3600),
+            users: Vec::new(),
             cors_enabled: true,
             websocket_enabled: true,
             dev_mode: false,
             metrics_enabled: false,
+            slow_query_threshold_ms: 1000,
+            slow_query_log_size: 100,
+            query_log_path: None,
+            session_timeout: Duration::from_secs(3600),
+            session_dir: None,
+            secure_cookies: false,
+            public_read_only: false,
             database_path: None,
             forms_directory: None,
-            token_file: None,
+            users_file: None,
             rate_limiting_enabled: true,
             query_timeout: Duration::from_secs(30),
+            max_request_body_bytes: 1024 * 1024,
+            max_query_length_chars: 64 * 1024,
+            blocked_keywords: Vec::new(),
+        }
+    }
+}
+
+impl ServerConfig {
+    /// Cargar los usuarios de `users_file` (si está configurado)
+    pub fn load_users_file(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        if let Some(path) = &self.users_file {
+            let content = std::fs::read_to_string(path)?;
+            let users: Vec<AuthUser> = serde_json::from_str(&content)?;
+            info!("{} usuarios cargados desde {:?}", users.len(), path);
+            self.users.extend(users);
         }
+
+        Ok(())
+    }
+}
+
+impl std::fmt::Debug for ServerState {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("ServerState")
+            .field("start_time", &self.start_time)
+            .finish_non_exhaustive()
     }
 }
 
+/// Cada cuánto se eliminan las sesiones caducadas
+const SESSION_CLEANUP_INTERVAL: Duration = Duration::from_secs(60);
+
 /// Estado compartido del servidor
 #[derive(Clone)]
 pub struct ServerState {
     /// Executor para consultas
-    pub executor: Arc<tokio::sync::RwLock<Option<Executor>>>,
+    pub executor: Arc<tokio::sync::RwLock<Option<Arc<Executor>>>>,
     
     /// Parser RQL
     pub parser: Arc<tokio::sync::RwLock<Option<RqlParser>>>,
     
-    /// Sesiones activas
-    pub sessions: Arc<tokio::sync::RwLock<Vec<Session>>>,
+    /// Sesiones de los clientes (caducan tras `ServerConfig::session_timeout`)
+    pub sessions: Arc<SessionManager>,
     
     /// Configuración del servidor
     pub config: Arc<tokio::sync::RwLock<ServerConfig>>,
@@ -106,40 +191,80 @@ pub struct ServerState {
     /// Middleware de performance
     pub performance: Arc<PerformanceMiddleware>,
     
+    /// Registro de consultas lentas
+    pub slow_queries: Arc<SlowQueryLog>,
+
+    /// Métricas de las consultas del executor (`/metrics`)
+    pub query_metrics: Arc<InMemoryRecorder>,
+
+    /// Bytes enviados por WebSocket (`/api/v1/metrics/websocket`)
+    pub websocket_traffic: Arc<WsTraffic>,
+
+    /// Si el servidor terminó de inicializarse (`/ready`)
+    pub ready: Arc<AtomicBool>,
+
     /// Inicio del servidor
     pub start_time: std::time::Instant,
 }
 
 impl ServerState {
     /// Crear nuevo estado del servidor
-    pub async fn new(config: ServerConfig) -> Result<Self, Box<dyn std::error::Error>> {
+    pub async fn new(mut config: ServerConfig) -> Result<Self, Box<dyn std::error::Error>> {
+        config.load_users_file()?;
+
         let performance = Arc::new(PerformanceMiddleware::new(&config));
         
         // Inicializar tasks de background
         performance.start_background_tasks();
         
-        // Crear executor si hay database path
-        let executor = if let Some(db_path) = &config.database_path {
-            if db_path.exists() {
-                let executor = Executor::new_sqlite(db_path).await?;
-                Some(executor)
-            } else {
+        // Crear executor salvo que falte el archivo de la base
+        let query_metrics = Arc::new(InMemoryRecorder::new());
+        let executor = match &config.database_path {
+            Some(db_path) if !db_path.exists() => {
                 warn!("Database file not found: {:?}", db_path);
                 None
             }
-        } else {
-            Some(Executor::new(config.database_url.clone()))
+            _ => Some(Arc::new(open_executor(&config, &query_metrics)?)),
         };
+        let executor = Arc::new(tokio::sync::RwLock::new(executor));
         
         // Crear parser
         let parser = RqlParser::new();
         
+        // Al eliminar o caducar una sesión se liberan sus cursores, su
+        // transacción y sus tablas. El hook no puede esperar: si el executor
+        // se está creando en ese momento la sesión no tenía nada que liberar.
+        let mut sessions = SessionManager::new(SessionConfig {
+            session_timeout: config.session_timeout.as_secs(),
+            ..SessionConfig::default()
+        });
+        if let Some(dir) = &config.session_dir {
+            sessions.set_store(Box::new(FileSessionStore::new(dir)?));
+        }
+        let released = executor.clone();
+        sessions.on_session_removed(move |id| {
+            let Ok(executor) = released.try_read() else {
+                return;
+            };
+            if let Some(executor) = executor.as_ref() {
+                if let Err(e) = executor.release_session(id) {
+                    warn!("No se pudo liberar la sesión {}: {}", id, e);
+                }
+            }
+        });
+        let sessions = Arc::new(sessions);
+        sessions.spawn_cleanup(SESSION_CLEANUP_INTERVAL);
+
         let state = Self {
-            executor: Arc::new(tokio::sync::RwLock::new(executor)),
+            executor,
             parser: Arc::new(tokio::sync::RwLock::new(Some(parser))),
-            sessions: Arc::new(tokio::sync::RwLock::new(Vec::new())),
+            sessions,
             config: Arc::new(tokio::sync::RwLock::new(config.clone())),
             performance: performance.clone(),
+            slow_queries: Arc::new(SlowQueryLog::new(config.slow_query_log_size)),
+            query_metrics,
+            websocket_traffic: Arc::new(WsTraffic::default()),
+            ready: Arc::new(AtomicBool::new(false)),
             start_time: std::time::Instant::now(),
         };
         
@@ -150,27 +275,23 @@ impl ServerState {
     
     /// Obtener executor (se crea si no existe)
     pub async fn get_executor(&self) -> Result<Arc<Executor>, String> {
-        let mut executor_opt = self.executor.write().await;
-        
-        if executor_opt.is_none() {
-            let config = self.config.read().await.clone();
-            
-            // Crear nuevo executor
-            if let Some(db_path) = config.database_path {
-                if db_path.exists() {
-                    let new_executor = Executor::new_sqlite(&db_path).await.map_err(|e| e.to_string())?;
-                    *executor_opt = Some(new_executor);
-                } else {
-                    return Err(format!("Database file not found: {:?}", db_path));
-                }
-            } else {
-                let config = self.config.read().await;
-                let new_executor = Executor::new(config.database_url.clone());
-                *executor_opt = Some(new_executor);
-            }
+        if let Some(executor) = self.executor.read().await.as_ref() {
+            return Ok(executor.clone());
         }
         
-        Ok(Arc::new(executor_opt.as_ref().unwrap().clone()))
+        let mut executor_opt = self.executor.write().await;
+        if let Some(executor) = executor_opt.as_ref() {
+            return Ok(executor.clone());
+        }
+
+        let config = self.config.read().await.clone();
+        if let Some(db_path) = config.database_path.as_ref().filter(|p| !p.exists()) {
+            return Err(format!("Database file not found: {:?}", db_path));
+        }
+        let executor =
+            Arc::new(open_executor(&config, &self.query_metrics).map_err(|e| e.to_string())?);
+        *executor_opt = Some(executor.clone());
+        Ok(executor)
     }
     
     /// Obtener parser
@@ -194,6 +315,44 @@ impl ServerState {
     pub async fn get_performance_metrics(&self) -> SerializedMetrics {
         self.performance.metrics.get_metrics().await
     }
+
+    /// Marcar el servidor como inicializado: `/ready` pasa a responder 200
+    ///
+    /// Quien sirve el router lo llama después de abrir el listener.
+    pub fn mark_ready(&self) {
+        self.ready.store(true, Ordering::Release);
+    }
+
+    /// Si el servidor terminó de inicializarse
+    pub fn is_ready(&self) -> bool {
+        self.ready.load(Ordering::Acquire)
+    }
+}
+
+/// Abrir el executor del servidor con la configuración de `config`
+///
+/// Las consultas concurrentes comparten un pool de hasta `max_connections`
+/// conexiones; el timeout, las métricas y el registro de consultas se
+/// aplican dentro del executor a cada consulta.
+fn open_executor(
+    config: &ServerConfig,
+    metrics: &Arc<InMemoryRecorder>,
+) -> noctra_core::Result<Executor> {
+    let url = match &config.database_path {
+        Some(db_path) => db_path.to_string_lossy().into_owned(),
+        None => config.database_url.clone(),
+    };
+    let mut executor = Executor::new_pooled(&url, config.max_connections as u32)?;
+
+    executor.set_options(ExecutorOptions {
+        timeout: Some(config.query_timeout).filter(|t| !t.is_zero()),
+        ..ExecutorOptions::default()
+    });
+    executor.set_metrics_recorder(metrics.clone());
+    if let Some(path) = &config.query_log_path {
+        executor.set_query_logger(Box::new(JsonlQueryLogger::open(path)?));
+    }
+    Ok(executor)
 }
 
 /// Servidor HTTP principal
@@ -204,72 +363,55 @@ pub struct Server {
 
 impl Server {
     /// Crear nuevo servidor con estado
-    pub fn new(state: ServerState) -> Self {
-        let router = Self::build_router(state.clone());
-        
+    pub fn new(state: ServerState, config: &ServerConfig) -> Self {
+        let router = Self::build_router(state.clone(), config);
         Self { state, router }
     }
     
-    /// Construir router con todas las rutas
-    fn build_router(state: ServerState) -> Router {
-        let mut router = Router::new()
-            // Rutas principales
-            .route("/", get(root_handler))
-            .route("/health", get(health_handler))
-            .route("/status", get(status_handler))
-            
-            // Rutas de consultas SQL/RQL
-            .route("/api/v1/query/execute", post(query_execute_handler))
-            .route("/api/v1/query/validate", post(query_validate_handler))
-            .route("/api/v1/query/batch", post(batch_query_handler))
-            
-            // Rutas de formularios
-            .route("/api/v1/form/:name", post(form_execute_handler))
-            .route("/api/v1/form/:name/validate", post(form_validate_handler))
-            .route("/api/v1/forms", get(forms_list_handler))
-            
-            // Rutas de sesiones
-            .route("/api/v1/session", post(session_create_handler))
-            .route("/api/v1/session/:id", get(session_get_handler))
-            .route("/api/v1/session/:id", delete(session_delete_handler))
-            .route("/api/v1/sessions", get(sessions_list_handler))
+    /// Construir router con todas las rutas (ver `routes::create_router`)
+    fn build_router(state: ServerState, config: &ServerConfig) -> Router {
+        let ws_state = config
+            .websocket_enabled
+            .then(|| WsState::new(state.clone()));
+        if let Some(ws_state) = &ws_state {
+            ws_state.start_cleanup_task();
             
-            // Rutas de configuración
-            .route("/api/v1/config", get(config_handler))
-            
-            // Rutas de métricas
-            .route("/api/v1/metrics", get(metrics_handler));
+            // Consultas en vivo: re-ejecutar al cambiar sus tablas
+            let manager = ws_state.manager.clone();
+            tokio::spawn(async move {
+                if let Err(e) = manager.start_change_listener().await {
+                    warn!("Consultas en vivo deshabilitadas: {}", e);
+                }
+            });
+        }
+        let mut router = create_router_with(state, ws_state.as_ref());
         
         // Agregar CORS si está habilitado
-        {
-            let config = state.config.blocking_read();
-            if config.cors_enabled {
-                router = router.layer(
-                    CorsLayer::new()
-                        .allow_origin(tower_http::cors::Any)
-                        .allow_methods(tower_http::cors::Any)
-                        .allow_headers(tower_http::cors::Any),
-                );
-            }
+        if config.cors_enabled {
+            router = router.layer(
+                CorsLayer::new()
+                    .allow_origin(tower_http::cors::Any)
+                    .allow_methods(tower_http::cors::Any)
+                    .allow_headers(tower_http::cors::Any),
+            );
         }
         
-        // Agregar tracing y manejo de errores
-        router = router
-            .layer(TraceLayer::new_for_http())
-            .with_state(state)
+        // Agregar tracing
+        router.layer(TraceLayer::new_for_http())
+    }
+
+    /// Router del servidor
+    pub fn router(&self) -> Router {
+        self.router.clone()
     }
     
     /// Iniciar servidor
     pub async fn run(self) -> Result<(), Box<dyn std::error::Error>> {
-        let addr = {
-            let config = self.state.config.blocking_read();
-            config.bind_address
-        };
+        let config = self.state.config.read().await.clone();
+        let addr = config.bind_address;
         
         info!("🚀 Iniciando servidor Noctra en {}", addr);
         info!("📊 Configuración:");
-        
-        let config = self.state.config.blocking_read();
         info!("   🗄️ Base de datos: {}", config.database_url);
         info!("   ⏱️ Timeout: {:?}", config.request_timeout);
         info!("   🔗 Conexiones máximas: {}", config.max_connections);
@@ -278,17 +420,17 @@ impl Server {
         info!("   🛠️ Modo desarrollo: {}", if config.dev_mode { "Habilitado" } else { "Deshabilitado" });
         info!("   📊 Métricas: {}", if config.metrics_enabled { "Habilitado" } else { "Deshabilitado" });
         
-        // Configurar graceful shutdown
-        let server_handle = tokio::spawn(async move {
-            axum::Server::bind(&addr)
-                .serve(self.router)
-                .with_graceful_shutdown(shutdown_signal())
-                .await
-                .expect("Error iniciando servidor")
-        });
-        
-        // Esperar a que termine
-        server_handle.await?;
+        let listener = tokio::net::TcpListener::bind(addr).await?;
+        // Con el puerto abierto el servidor ya puede atender consultas
+        self.state.mark_ready();
+        // ConnectInfo: la IP del cliente para el registro de consultas lentas
+        axum::serve(
+            listener,
+            self.router
+                .into_make_service_with_connect_info::<SocketAddr>(),
+        )
+        .with_graceful_shutdown(shutdown_signal())
+        .await?;
         
         info!("👋 Servidor Noctra finalizado");
         
@@ -297,26 +439,33 @@ impl Server {
     
     /// Obtener estado del servidor
     pub fn get_status(&self) -> ServerStatus {
-        let uptime = self.state.start_time.elapsed();
-        let sessions = self.state.sessions.blocking_read();
-        
-        ServerStatus {
-            version: "0.1.0".to_string(),
-            uptime_seconds: uptime.as_secs(),
-            connected_sessions: sessions.len(),
-            active_queries: 0, // TODO: Implementar contador real
-            database_status: "connected".to_string(),
-        }
+        server_status(&self.state)
+    }
+}
+
+/// Estado actual del servidor (`/status`)
+pub fn server_status(state: &ServerState) -> ServerStatus {
+    ServerStatus {
+        version: "0.1.0".to_string(),
+        uptime_seconds: state.start_time.elapsed().as_secs(),
+        active_sessions: state.sessions.active_sessions_count(),
+        queries_executed: state
+            .query_metrics
+            .snapshot()
+            .iter()
+            .map(|series| series.queries)
+            .sum(),
+        database_backend: "SQLite".to_string(),
+        health: HealthStatus::Healthy,
     }
 }
 
 /// Función para crear servidor y router
 pub fn create_server(
-    state: ServerState, 
-    config: ServerConfig
+    state: ServerState,
+    config: ServerConfig,
 ) -> Result<Router, Box<dyn std::error::Error>> {
-    let mut server = Server::new(state);
-    Ok(server.router)
+    Ok(Server::new(state, &config).router)
 }
 
 /// Función para manejar graceful shutdown
@@ -350,304 +499,32 @@ async fn shutdown_signal() {
     info!("🛑 Cerrando servidor...");
 }
 
-// =================== HANDLERS ===================
-
-/// Handler raíz - información del servidor
-async fn root_handler() -> Json<serde_json::Value> {
-    Json(serde_json::json!({
-        "name": "Noctra Server",
-        "version": "0.1.0",
-        "description": "Entorno SQL Interactivo - Servidor HTTP",
-        "endpoints": {
-            "health": "/health",
-            "status": "/status", 
-            "query": "/api/v1/query/execute",
-            "form": "/api/v1/form/{name}",
-            "session": "/api/v1/session",
-            "metrics": "/api/v1/metrics"
-        }
-    }))
-}
-
-/// Handler de health check
-async fn health_handler(State(state): State<ServerState>) -> Result<Json<serde_json::Value>, StatusCode> {
-    // Verificar que el executor esté disponible
-    if let Err(_) = state.get_executor().await {
-        return Err(StatusCode::SERVICE_UNAVAILABLE);
-    }
-    
-    Ok(Json(serde_json::json!({
-        "status": "healthy",
-        "timestamp": chrono::Utc::now().to_rfc3339(),
-        "version": "0.1.0",
-        "uptime_seconds": state.start_time.elapsed().as_secs()
-    })))
-}
-
-/// Handler de estado del servidor
-async fn status_handler(State(state): State<ServerState>) -> Json<ServerStatus> {
-    let status = ServerStatus {
-        version: "0.1.0".to_string(),
-        uptime_seconds: state.start_time.elapsed().as_secs(),
-        connected_sessions: state.sessions.read().await.len(),
-        active_queries: 0, // TODO: Implementar
-        database_status: "connected".to_string(),
-    };
-    
-    Json(status)
-}
-
-/// Handler para ejecutar consulta SQL/RQL
-async fn query_execute_handler(
-    State(state): State<ServerState>,
-    Json(request): Json<QueryRequest>,
-) -> Result<Json<QueryResponse>, StatusCode> {
-    let start_time = std::time::Instant::now();
-    
-    // TODO: Usar performance middleware para cache y rate limiting
-    let executor = state.get_executor().await.map_err(|_| StatusCode::SERVICE_UNAVAILABLE)?;
-    let parser = state.get_parser().await;
-    
-    // TODO: Ejecutar consulta real usando executor
-    // Por ahora simular resultado
-    let mock_data = noctra_core::ResultSet::empty();
-    
-    let execution_time = start_time.elapsed().as_millis() as u64;
-    
-    let response = QueryResponse {
-        success: true,
-        data: Some(mock_data),
-        message: "Consulta ejecutada (simulada)".to_string(),
-        execution_time_ms: execution_time,
-    };
-    
-    // Registrar métricas de performance
-    state.performance.metrics.record_success(start_time.elapsed()).await;
-    
-    Ok(Json(response))
-}
-
-/// Handler para validar consulta
-async fn query_validate_handler(
-    State(state): State<ServerState>,
-    Json(request): Json<QueryRequest>,
-) -> Result<Json<QueryResponse>, StatusCode> {
-    // TODO: Usar parser para validar SQL
-    let response = QueryResponse {
-        success: true,
-        data: None,
-        message: "Consulta válida (validación simulada)".to_string(),
-        execution_time_ms: 0,
-    };
-    
-    Ok(Json(response))
-}
-
-/// Handler para consultas batch
-async fn batch_query_handler(
-    State(state): State<ServerState>,
-    Json(requests): Json<Vec<QueryRequest>>,
-) -> Result<Json<Vec<QueryResponse>>, StatusCode> {
-    let mut responses = Vec::new();
-    
-    for request in requests {
-        let start_time = std::time::Instant::now();
-        
-        // TODO: Ejecutar consulta real
-        let mock_data = noctra_core::ResultSet::empty();
-        let execution_time = start_time.elapsed().as_millis() as u64;
-        
-        let response = QueryResponse {
-            success: true,
-            data: Some(mock_data),
-            message: "Consulta batch ejecutada (simulada)".to_string(),
-            execution_time_ms: execution_time,
-        };
-        
-        responses.push(response);
-    }
-    
-    Ok(Json(responses))
-}
-
-/// Handler para ejecutar formulario
-async fn form_execute_handler(
-    State(state): State<ServerState>,
-    axum::extract::Path(name): axum::extract::Path<String>,
-    Json(request): Json<FormRequest>,
-) -> Result<Json<FormResponse>, StatusCode> {
-    // TODO: Cargar y ejecutar formulario real
-    let response = FormResponse {
-        success: true,
-        data: None,
-        message: format!("Formulario '{}' ejecutado (simulado)", name),
-        form_title: Some(name),
-    };
-    
-    Ok(Json(response))
-}
-
-/// Handler para validar formulario
-async fn form_validate_handler(
-    State(state): State<ServerState>,
-    axum::extract::Path(name): axum::extract::Path<String>,
-    Json(request): Json<FormRequest>,
-) -> Result<Json<FormResponse>, StatusCode> {
-    // TODO: Validar formulario real
-    let response = FormResponse {
-        success: true,
-        data: None,
-        message: format!("Formulario '{}' validado (simulado)", name),
-        form_title: Some(name),
-    };
-    
-    Ok(Json(response))
-}
-
-/// Handler para listar formularios
-async fn forms_list_handler(State(_state): State<ServerState>) -> Result<Json<serde_json::Value>, StatusCode> {
-    // TODO: Listar formularios desde forms directory
-    Ok(Json(serde_json::json!({
-        "forms": [
-            {
-                "name": "empleados",
-                "title": "Consulta Empleados",
-                "description": "Formulario para consultar empleados"
-            }
-        ],
-        "total": 1
-    })))
-}
-
-/// Handler para crear sesión
-async fn session_create_handler(State(state): State<ServerState>) -> Result<Json<serde_json::Value>, StatusCode> {
-    let session_id = format!("session_{}", chrono::Utc::now().timestamp());
-    let session = Session::new(session_id.clone());
-    let mut sessions = state.sessions.write().await;
-    sessions.push(session);
-    
-    Ok(Json(serde_json::json!({
-        "session_id": session_id,
-        "message": "Sesión creada exitosamente",
-        "expires_in": 3600,
-        "created_at": chrono::Utc::now().to_rfc3339()
-    })))
-}
-
-/// Handler para obtener sesión
-async fn session_get_handler(
-    State(state): State<ServerState>,
-    axum::extract::Path(id): axum::extract::Path<String>,
-) -> Result<Json<serde_json::Value>, StatusCode> {
-    let sessions = state.sessions.read().await;
-    
-    // Buscar sesión
-    for session in sessions.iter() {
-        if session.id == id {
-            return Ok(Json(serde_json::json!({
-                "session_id": session.id,
-                "status": "active",
-                "created_at": session.created_at,
-                "variables": session.variables
-            })));
-        }
-    }
-    
-    Err(StatusCode::NOT_FOUND)
-}
-
-/// Handler para eliminar sesión
-async fn session_delete_handler(
-    State(state): State<ServerState>,
-    axum::extract::Path(id): axum::extract::Path<String>,
-) -> Result<Json<serde_json::Value>, StatusCode> {
-    let mut sessions = state.sessions.write().await;
-    let original_len = sessions.len();
-    
-    sessions.retain(|s| s.id != id);
-    
-    if sessions.len() < original_len {
-        Ok(Json(serde_json::json!({
-            "message": format!("Sesión {} eliminada", id)
-        })))
-    } else {
-        Err(StatusCode::NOT_FOUND)
-    }
-}
-
-/// Handler para listar sesiones
-async fn sessions_list_handler(State(state): State<ServerState>) -> Result<Json<serde_json::Value>, StatusCode> {
-    let sessions = state.sessions.read().await;
-    
-    Ok(Json(serde_json::json!({
-        "sessions": sessions.iter().map(|s| serde_json::json!({
-            "id": s.id,
-            "created_at": s.created_at,
-            "status": "active"
-        })).collect::<Vec<_>>(),
-        "total": sessions.len()
-    })))
-}
-
-/// Handler para configuración
-async fn config_handler(State(state): State<ServerState>) -> Json<serde_json::Value> {
-    let config = state.config.read().await;
-    
-    Json(serde_json::json!({
-        "database_url": config.database_url,
-        "request_timeout": config.request_timeout.as_secs(),
-        "max_connections": config.max_connections,
-        "cors_enabled": config.cors_enabled,
-        "websocket_enabled": config.websocket_enabled,
-        "dev_mode": config.dev_mode,
-        "metrics_enabled": config.metrics_enabled
-    }))
-}
-
-/// Handler para métricas
-async fn metrics_handler(State(state): State<ServerState>) -> Result<Json<serde_json::Value>, StatusCode> {
-    let metrics = state.get_performance_metrics().await;
-    
-    Ok(Json(serde_json::json!({
-        "server": {
-            "version": "0.1.0",
-            "uptime": metrics.uptime_seconds,
-        },
-        "api": {
-            "total_requests": metrics.requests_total,
-            "success_requests": metrics.requests_success,
-            "error_requests": metrics.requests_error,
-            "success_rate": metrics.success_rate,
-            "avg_response_time_ms": metrics.avg_response_time_ms,
-            "requests_per_second": metrics.requests_per_second
-        },
-        "sessions": {
-            "active": state.sessions.read().await.len()
-        }
-    })))
-}
-
 /// Función helper para crear server y ejecutarlo
 pub async fn run_server(config: ServerConfig) -> Result<(), Box<dyn std::error::Error>> {
-    let state = ServerState::new(config).await?;
-    let server = Server::new(state);
+    let state = ServerState::new(config.clone()).await?;
+    let server = Server::new(state, &config);
     server.run().await
 }
 
 /// Ejecutar servidor con argumentos CLI
 pub async fn run_server_cli() -> Result<(), Box<dyn std::error::Error>> {
+    use clap::Parser;
+
     let args = CliArgs::parse();
     
-    let mut config = ServerConfig::default();
-    config.bind_address = args.bind_address.parse()?;
-    config.database_url = args.database_url;
-    config.database_path = args.database_path;
-    config.forms_directory = args.forms_dir;
-    config.token_file = args.token_file;
-    config.cors_enabled = !args.no_cors;
-    config.websocket_enabled = !args.no_websockets;
-    config.dev_mode = args.dev;
-    config.metrics_enabled = args.metrics;
+    let mut config = ServerConfig {
+        bind_address: args.bind_address.parse()?,
+        database_url: args.database_url,
+        database_path: args.database_path,
+        forms_directory: args.forms_dir,
+        users_file: args.users_file,
+        cors_enabled: !args.no_cors,
+        websocket_enabled: !args.no_websockets,
+        dev_mode: args.dev,
+        metrics_enabled: args.metrics,
+        auth_disabled: args.no_auth,
+        ..ServerConfig::default()
+    };
     
     if let Some(secret) = args.auth_secret {
         config.auth_secret = Some(secret);
@@ -676,6 +553,10 @@ struct CliArgs {
     #[arg(short, long)]
     auth_secret: Option<String>,
     
+    /// Servir sin autenticación cuando no hay --auth-secret
+    #[arg(long)]
+    no_auth: bool,
+
     /// Deshabilitar CORS
     #[arg(long)]
     no_cors: bool,
@@ -692,9 +573,9 @@ struct CliArgs {
     #[arg(short, long)]
     metrics: bool,
     
-    /// Archivo de token para autenticación
+    /// Archivo JSON de usuarios para autenticación
     #[arg(long)]
-    token_file: Option<std::path::PathBuf>,
+    users_file: Option<std::path::PathBuf>,
     
     /// Directorio de formularios
     #[arg(long)]
@@ -707,20 +588,58 @@ mod tests {
     
     #[tokio::test]
     async fn test_server_state_creation() {
-        let config = ServerConfig::default();
+        let config = ServerConfig {
+            database_url: ":memory:".to_string(),
+            ..ServerConfig::default()
+        };
         let state = ServerState::new(config).await.unwrap();
         
-        assert!(state.executor.blocking_read().is_some());
-        assert!(state.parser.blocking_read().is_some());
-        assert_eq!(state.sessions.blocking_read().len(), 0);
+        assert!(state.executor.read().await.is_some());
+        assert!(state.parser.read().await.is_some());
+        assert_eq!(state.sessions.active_sessions_count(), 0);
     }
     
     #[tokio::test]
     async fn test_server_get_executor() {
-        let config = ServerConfig::default();
+        let config = ServerConfig {
+            database_url: ":memory:".to_string(),
+            ..ServerConfig::default()
+        };
         let state = ServerState::new(config).await.unwrap();
         
         let executor = state.get_executor().await.unwrap();
-        assert!(executor.is_some());
+        assert!(executor.ping().is_ok());
+    }
+
+    #[tokio::test]
+    async fn test_server_state_configures_executor() {
+        let dir = tempfile::tempdir().unwrap();
+        let log_path = dir.path().join("queries.jsonl");
+        let config = ServerConfig {
+            database_url: ":memory:".to_string(),
+            query_timeout: Duration::from_secs(7),
+            query_log_path: Some(log_path.clone()),
+            ..ServerConfig::default()
+        };
+        let state = ServerState::new(config).await.unwrap();
+
+        // El executor creado en `new` ya lleva timeout, métricas y registro
+        let executor = state.get_executor().await.unwrap();
+        assert_eq!(executor.options().timeout, Some(Duration::from_secs(7)));
+        executor
+            .execute_rql(
+                &noctra_core::Session::new(),
+                noctra_core::RqlQuery::sql("SELECT 1"),
+            )
+            .unwrap();
+
+        let queries: u64 = state
+            .query_metrics
+            .snapshot()
+            .iter()
+            .map(|s| s.queries)
+            .sum();
+        assert_eq!(queries, 1);
+        assert!(!std::fs::read_to_string(&log_path).unwrap().is_empty());
     }
 }
\ No newline at end of file
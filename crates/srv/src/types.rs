@@ -2,31 +2,140 @@
 //!
 //! Definiciones de tipos para peticiones, respuestas y estado del servidor.
 
-use serde::{Deserialize, Serialize};
+use axum::http::StatusCode;
+use noctra_core::{CursorId, NoctraError, Parameters, ResultSet, Value};
+use serde::{Deserialize, Deserializer, Serialize};
 use std::collections::HashMap;
-use noctra_core::{Value, ResultSet};
+use utoipa::ToSchema;
 
 /// Petición de query SQL/RQL
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct QueryRequest {
     /// Query SQL o RQL a ejecutar
     pub query: String,
 
-    /// Parámetros de la query
-    #[serde(default)]
-    pub parameters: HashMap<String, Value>,
+    /// Parámetros de la query: objeto con los nombrados, array con los
+    /// posicionales o `{"positional": [...], "named": {...}}`
+    /// (ver `Parameters::from_json`)
+    #[serde(default, deserialize_with = "deserialize_parameters")]
+    #[schema(value_type = Object)]
+    pub parameters: Parameters,
 
     /// ID de sesión (opcional)
     pub session_id: Option<String>,
 
     /// Timeout en segundos (opcional)
     pub timeout: Option<u64>,
+
+    /// Página a devolver, empezando en 1 (activa la paginación)
+    #[serde(default)]
+    pub page: Option<u32>,
+
+    /// Filas por página (por defecto `DEFAULT_PAGE_SIZE`, máximo `MAX_PAGE_SIZE`)
+    #[serde(default)]
+    pub page_size: Option<u32>,
+
+    /// Cursor de una respuesta anterior del que leer más filas (se ignoran
+    /// `query` y la paginación)
+    #[serde(default)]
+    #[schema(value_type = Option<u64>)]
+    pub cursor: Option<CursorId>,
+
+    /// Filas por bloque al leer con cursor; sin `cursor` abre uno nuevo
+    #[serde(default)]
+    pub fetch_size: Option<u32>,
+}
+
+/// Leer `QueryRequest::parameters` con `Parameters::from_json_value`
+///
+/// `null` equivale a no enviar parámetros; un objeto que mezcla claves
+/// numéricas y nombres se rechaza.
+fn deserialize_parameters<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<Parameters, D::Error> {
+    match serde_json::Value::deserialize(deserializer)? {
+        serde_json::Value::Null => Ok(Parameters::new()),
+        value => Parameters::from_json_value(value).map_err(serde::de::Error::custom),
+    }
+}
+
+/// Filas por página si la petición no indica `page_size`
+pub const DEFAULT_PAGE_SIZE: u32 = 100;
+
+/// Máximo de filas por página
+pub const MAX_PAGE_SIZE: u32 = 10_000;
+
+impl QueryRequest {
+    /// Filas a leer del cursor, o `None` si la petición no usa cursores
+    ///
+    /// Con `cursor` y sin `fetch_size` se leen `DEFAULT_PAGE_SIZE` filas;
+    /// el tamaño se limita a `1..=MAX_PAGE_SIZE` como el de las páginas.
+    pub fn fetch_size(&self) -> Option<usize> {
+        if self.cursor.is_none() && self.fetch_size.is_none() {
+            return None;
+        }
+
+        Some(
+            self.fetch_size
+                .unwrap_or(DEFAULT_PAGE_SIZE)
+                .clamp(1, MAX_PAGE_SIZE) as usize,
+        )
+    }
+
+    /// Página y tamaño pedidos, o `None` si la consulta no se pagina
+    ///
+    /// Basta con indicar `page` o `page_size`; el otro toma su valor por
+    /// defecto. `page_size` se limita a `1..=MAX_PAGE_SIZE`.
+    pub fn pagination(&self) -> Option<Pagination> {
+        if self.page.is_none() && self.page_size.is_none() {
+            return None;
+        }
+
+        Some(Pagination {
+            page: self.page.unwrap_or(1).max(1),
+            page_size: self
+                .page_size
+                .unwrap_or(DEFAULT_PAGE_SIZE)
+                .clamp(1, MAX_PAGE_SIZE),
+        })
+    }
+}
+
+/// Página solicitada de un resultado
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct Pagination {
+    /// Número de página (desde 1)
+    pub page: u32,
+
+    /// Filas por página
+    pub page_size: u32,
+}
+
+impl Pagination {
+    /// Filas a saltar antes de la página
+    pub fn offset(&self) -> u64 {
+        u64::from(self.page - 1) * u64::from(self.page_size)
+    }
+
+    /// Páginas necesarias para `total_count` filas
+    pub fn total_pages(&self, total_count: usize) -> u32 {
+        (total_count as u64).div_ceil(u64::from(self.page_size)) as u32
+    }
+}
+
+/// Parámetros de URL de `/api/v1/query`
+#[derive(Debug, Clone, Default, Deserialize)]
+pub struct QueryParams {
+    /// Calcular `total_count` con un `COUNT(*)` adicional
+    #[serde(default)]
+    pub count: bool,
 }
 
 /// Respuesta de query
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct QueryResponse {
-    /// Resultado de la query
+    /// Resultado de la query (columnas y filas)
+    #[schema(value_type = Object)]
     pub result: ResultSet,
 
     /// Tiempo de ejecución en milisegundos
@@ -38,10 +147,31 @@ pub struct QueryResponse {
     /// Metadata adicional
     #[serde(default)]
     pub metadata: HashMap<String, String>,
+
+    /// Total de filas sin paginar (sólo con `?count=true`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub total_count: Option<usize>,
+
+    /// Página devuelta
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub page: Option<u32>,
+
+    /// Filas por página
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub page_size: Option<u32>,
+
+    /// Total de páginas (sólo con `?count=true`)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub total_pages: Option<u32>,
+
+    /// Cursor con filas pendientes; ausente cuando ya se leyeron todas
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    #[schema(value_type = Option<u64>)]
+    pub cursor: Option<CursorId>,
 }
 
 /// Petición de formulario FDL2
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct FormRequest {
     /// Nombre del formulario
     pub form_name: String,
@@ -51,6 +181,7 @@ pub struct FormRequest {
 
     /// Datos del formulario
     #[serde(default)]
+    #[schema(value_type = HashMap<String, Object>)]
     pub data: HashMap<String, Value>,
 
     /// ID de sesión (opcional)
@@ -58,7 +189,7 @@ pub struct FormRequest {
 }
 
 /// Respuesta de formulario
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct FormResponse {
     /// Indica si la acción fue exitosa
     pub success: bool,
@@ -68,6 +199,7 @@ pub struct FormResponse {
 
     /// Datos de respuesta
     #[serde(default)]
+    #[schema(value_type = HashMap<String, Object>)]
     pub data: HashMap<String, Value>,
 
     /// Errores de validación (si los hay)
@@ -76,7 +208,7 @@ pub struct FormResponse {
 }
 
 /// Error de validación de formulario
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct ValidationError {
     /// Campo con error
     pub field: String,
@@ -88,8 +220,59 @@ pub struct ValidationError {
     pub code: String,
 }
 
-/// Estado del servidor
+/// Usuario habilitado para autenticarse contra el servidor
+#[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct AuthUser {
+    /// Nombre de usuario
+    pub username: String,
+
+    /// Hash bcrypt de la contraseña
+    pub password_hash: String,
+
+    /// Roles incluidos en los tokens emitidos
+    #[serde(default)]
+    pub roles: Vec<String>,
+}
+
+/// Petición de login
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct LoginRequest {
+    /// Nombre de usuario
+    pub username: String,
+
+    /// Contraseña en texto plano
+    pub password: String,
+}
+
+/// Token JWT emitido por login o refresh
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct TokenResponse {
+    /// Token JWT firmado (HS256)
+    pub access_token: String,
+
+    /// Tipo de token (siempre "Bearer")
+    pub token_type: String,
+
+    /// Segundos hasta la expiración
+    pub expires_in: u64,
+}
+
+/// Mensaje enviado a los clientes WebSocket
 #[derive(Debug, Clone, Serialize, Deserialize)]
+pub struct WsMessage {
+    /// Tipo de mensaje (`connection`, `live_query_result`, `error`, ...)
+    #[serde(rename = "type")]
+    pub message_type: String,
+
+    /// Contenido del mensaje
+    pub data: serde_json::Value,
+
+    /// Momento del envío
+    pub timestamp: chrono::DateTime<chrono::Utc>,
+}
+
+/// Estado del servidor
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct ServerStatus {
     /// Versión del servidor
     pub version: String,
@@ -111,7 +294,7 @@ pub struct ServerStatus {
 }
 
 /// Estado de salud del servidor
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub enum HealthStatus {
     /// Servidor operativo
     Healthy,
@@ -123,8 +306,74 @@ pub enum HealthStatus {
     Unhealthy,
 }
 
+/// Resultado de la prueba de una dependencia (o del servidor completo)
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ProbeStatus {
+    /// Responde
+    Ok,
+
+    /// Responde en parte (alguna fuente DuckDB falla)
+    Degraded,
+
+    /// No responde
+    Down,
+}
+
+/// Prueba `SELECT 1` de una dependencia
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct DependencyCheck {
+    /// Estado de la dependencia
+    pub status: ProbeStatus,
+
+    /// Latencia de la prueba en milisegundos
+    pub latency_ms: u64,
+
+    /// Errores de la prueba, si falló
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub errors: Vec<String>,
+}
+
+/// Pruebas de `/health`, una por dependencia
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct HealthChecks {
+    /// Backend SQLite del executor
+    pub sqlite: DependencyCheck,
+
+    /// Fuentes DuckDB registradas (ausente si no hay ninguna)
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub duckdb: Option<DependencyCheck>,
+}
+
+/// Respuesta de `/health`
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
+pub struct HealthReport {
+    /// `ok`, `degraded` si falla alguna fuente DuckDB o `down` si falla SQLite
+    pub status: ProbeStatus,
+
+    /// Resultado de cada dependencia
+    pub checks: HealthChecks,
+
+    /// Versión del servidor
+    pub version: String,
+
+    /// Tiempo de actividad en segundos
+    pub uptime_seconds: u64,
+}
+
+impl HealthReport {
+    /// Código HTTP de la respuesta: 200, 207 (degradado) o 503
+    pub fn status_code(&self) -> StatusCode {
+        match self.status {
+            ProbeStatus::Ok => StatusCode::OK,
+            ProbeStatus::Degraded => StatusCode::MULTI_STATUS,
+            ProbeStatus::Down => StatusCode::SERVICE_UNAVAILABLE,
+        }
+    }
+}
+
 /// Error del servidor
-#[derive(Debug, Clone, Serialize, Deserialize)]
+#[derive(Debug, Clone, Serialize, Deserialize, ToSchema)]
 pub struct ServerError {
     /// Código de error HTTP
     pub status_code: u16,
@@ -135,6 +384,17 @@ pub struct ServerError {
     /// Detalles adicionales (para modo desarrollo)
     pub details: Option<String>,
 
+    /// Código numerado del error (p. ej. "NOCTRA_E030"), ver `docs/ERROR_CODES.md`
+    pub code: String,
+
+    /// Tipo de error de Noctra (p. ej. "not_found", "timeout")
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub kind: Option<String>,
+
+    /// Sugerencia para resolver el error
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub hint: Option<String>,
+
     /// Timestamp del error
     pub timestamp: String,
 }
@@ -146,6 +406,9 @@ impl ServerError {
             status_code: 400,
             message: message.into(),
             details: None,
+            code: "NOCTRA_E050".to_string(),
+            kind: None,
+            hint: None,
             timestamp: chrono::Utc::now().to_rfc3339(),
         }
     }
@@ -156,6 +419,9 @@ impl ServerError {
             status_code: 500,
             message: message.into(),
             details: None,
+            code: "NOCTRA_E054".to_string(),
+            kind: None,
+            hint: None,
             timestamp: chrono::Utc::now().to_rfc3339(),
         }
     }
@@ -166,6 +432,22 @@ impl ServerError {
             status_code: 401,
             message: message.into(),
             details: None,
+            code: "NOCTRA_E051".to_string(),
+            kind: None,
+            hint: None,
+            timestamp: chrono::Utc::now().to_rfc3339(),
+        }
+    }
+
+    /// Crear error de servicio no disponible
+    pub fn service_unavailable<T: Into<String>>(message: T) -> Self {
+        Self {
+            status_code: 503,
+            message: message.into(),
+            details: None,
+            code: "NOCTRA_E053".to_string(),
+            kind: None,
+            hint: None,
             timestamp: chrono::Utc::now().to_rfc3339(),
         }
     }
@@ -176,6 +458,40 @@ impl ServerError {
             status_code: 404,
             message: message.into(),
             details: None,
+            code: "NOCTRA_E052".to_string(),
+            kind: None,
+            hint: None,
+            timestamp: chrono::Utc::now().to_rfc3339(),
+        }
+    }
+
+    /// Crear error de cuerpo demasiado grande (413)
+    pub fn payload_too_large<T: Into<String>>(message: T) -> Self {
+        Self {
+            status_code: 413,
+            message: message.into(),
+            details: None,
+            code: "NOCTRA_E055".to_string(),
+            kind: None,
+            hint: Some("divide la petición o sube max_request_body_bytes".to_string()),
+            timestamp: chrono::Utc::now().to_rfc3339(),
+        }
+    }
+
+    /// Crear error a partir de un error de Noctra
+    ///
+    /// El status sale de `status_for_error`. Los errores internos no
+    /// exponen su detalle al cliente.
+    pub fn from_noctra(error: &NoctraError) -> Self {
+        Self {
+            status_code: status_for_error(error).as_u16(),
+            message: error.user_message(),
+            details: error
+                .backend_code()
+                .map(|code| format!("Código del backend: {}", code)),
+            code: error.error_code().to_string(),
+            kind: Some(error.code().to_string()),
+            hint: error.hint().map(str::to_string),
             timestamp: chrono::Utc::now().to_rfc3339(),
         }
     }
@@ -186,3 +502,126 @@ impl ServerError {
         self
     }
 }
+
+/// Status HTTP que corresponde a un error de Noctra
+///
+/// Los errores de backend se clasifican por la clase de su código SQLSTATE.
+pub fn status_for_error(error: &NoctraError) -> StatusCode {
+    match error {
+        NoctraError::Parse { .. }
+        | NoctraError::SqlSyntax(_)
+        | NoctraError::SqlExecution(_)
+        | NoctraError::ParameterNotFound(_)
+        | NoctraError::MissingParameter(_)
+        | NoctraError::InvalidParameters(_)
+        | NoctraError::SessionVariableNotFound(_)
+        | NoctraError::Validation(_)
+        | NoctraError::DryRunFailed(_)
+        | NoctraError::AssertionFailed { .. }
+        | NoctraError::UnsupportedFeature { .. } => StatusCode::BAD_REQUEST,
+        NoctraError::NotFound { .. } => StatusCode::NOT_FOUND,
+        NoctraError::Permission { .. } => StatusCode::FORBIDDEN,
+        NoctraError::Conflict(_) => StatusCode::CONFLICT,
+        NoctraError::Timeout { .. } => StatusCode::GATEWAY_TIMEOUT,
+        NoctraError::Backend { code, .. } => match code.as_str() {
+            "42P01" => StatusCode::NOT_FOUND,
+            "42501" | "25006" => StatusCode::FORBIDDEN,
+            "SQLITE_1" => StatusCode::BAD_REQUEST,
+            _ if code.starts_with("42") || code.starts_with("22") => StatusCode::BAD_REQUEST,
+            _ if code.starts_with("23") || code.starts_with("40") => StatusCode::CONFLICT,
+            _ if code.starts_with("53") || code.starts_with("57") => {
+                StatusCode::SERVICE_UNAVAILABLE
+            }
+            _ => StatusCode::INTERNAL_SERVER_ERROR,
+        },
+        NoctraError::Database(_)
+        | NoctraError::Configuration(_)
+        | NoctraError::Io(_)
+        | NoctraError::Serialization(_)
+        | NoctraError::Internal(_) => StatusCode::INTERNAL_SERVER_ERROR,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::time::Duration;
+
+    #[test]
+    fn test_status_for_error() {
+        let cases = [
+            (
+                NoctraError::parse(1, 8, "token inesperado"),
+                StatusCode::BAD_REQUEST,
+            ),
+            (
+                NoctraError::not_found("tabla", "ventas"),
+                StatusCode::NOT_FOUND,
+            ),
+            (NoctraError::permission("/etc"), StatusCode::FORBIDDEN),
+            (
+                NoctraError::Conflict("UNIQUE".to_string()),
+                StatusCode::CONFLICT,
+            ),
+            (
+                NoctraError::Timeout {
+                    elapsed: Duration::from_secs(5),
+                },
+                StatusCode::GATEWAY_TIMEOUT,
+            ),
+            (
+                NoctraError::backend("42P01", "no existe"),
+                StatusCode::NOT_FOUND,
+            ),
+            (
+                NoctraError::backend("23505", "duplicado"),
+                StatusCode::CONFLICT,
+            ),
+            (
+                NoctraError::backend("53200", "sin memoria"),
+                StatusCode::SERVICE_UNAVAILABLE,
+            ),
+            (
+                NoctraError::Io("disco lleno".to_string()),
+                StatusCode::INTERNAL_SERVER_ERROR,
+            ),
+            (
+                NoctraError::Internal("lock".to_string()),
+                StatusCode::INTERNAL_SERVER_ERROR,
+            ),
+        ];
+        for (error, status) in cases {
+            assert_eq!(status_for_error(&error), status, "{:?}", error);
+        }
+    }
+
+    #[test]
+    fn test_server_error_from_noctra() {
+        let error = ServerError::from_noctra(&NoctraError::not_found("fuente", "ventas"));
+        assert_eq!(error.status_code, 404);
+        assert_eq!(error.code, "NOCTRA_E030");
+        assert_eq!(error.kind.as_deref(), Some("not_found"));
+        assert!(error.hint.is_some());
+
+        let error = ServerError::from_noctra(&NoctraError::Internal("/srv/secreto".to_string()));
+        assert_eq!(error.status_code, 500);
+        assert!(!error.message.contains("/srv/secreto"));
+        assert_eq!(error.code, "NOCTRA_E099");
+
+        assert_eq!(ServerError::unauthorized("sin token").code, "NOCTRA_E051");
+    }
+
+    #[test]
+    fn test_query_request_parameters() {
+        let request: QueryRequest =
+            serde_json::from_str(r#"{"query": "SELECT $1", "parameters": [1]}"#).unwrap();
+        assert_eq!(request.parameters, Parameters::positional([1i64]));
+
+        let request: QueryRequest =
+            serde_json::from_str(r#"{"query": "SELECT 1", "parameters": null}"#).unwrap();
+        assert!(request.parameters.is_empty());
+
+        let mixed = r#"{"query": "SELECT 1", "parameters": {"1": "Null", "dept": "Null"}}"#;
+        assert!(serde_json::from_str::<QueryRequest>(mixed).is_err());
+    }
+}
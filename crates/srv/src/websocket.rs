@@ -1,22 +1,32 @@
 //! Soporte WebSocket para el servidor Noctra
 //! 
 //! Permite streaming de consultas y actualizaciones en tiempo real.
+//!
+//! Si `WsConfig::compression_enabled` y el cliente ofrece
+//! `permessage-deflate` (RFC 7692) en `Sec-WebSocket-Extensions`, la
+//! conexión la atiende `ws_deflate`; si no, la capa WebSocket de axum. Los
+//! bytes de cada mensaje antes y después de comprimir se acumulan en
+//! `WsTraffic` y se consultan en `GET /api/v1/metrics/websocket`.
 
+use axum::extract::ws::{Message, WebSocket};
+use axum::http::{header, HeaderMap};
 use axum::{
-    extract::{
-        State,
-        WebSocketUpgrade,
-        ConnectInfo,
-        Host,
-    },
-    response::IntoResponse,
+    extract::{Extension, Host, WebSocketUpgrade},
+    response::Response,
 };
-use axum::extract::ws::{Message, WebSocket};
+use futures_util::stream::{SplitSink, SplitStream};
+use futures_util::{SinkExt, StreamExt};
+use hyper::upgrade::OnUpgrade;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
 use tokio::sync::broadcast;
-use serde::{Deserialize, Serialize};
 
+use noctra_core::{ChangeEvent, QueryPlan, RqlQuery, Session};
+
+use crate::handlers::SqlSanitizer;
 use crate::server::ServerState;
-use crate::types::{QueryRequest, QueryResponse, WsMessage};
+use crate::types::WsMessage;
+use crate::ws_deflate::{self, DeflateReceiver, DeflateSender};
 
 /// Cliente WebSocket conectado
 #[derive(Debug, Clone)]
@@ -27,10 +37,107 @@ pub struct WsClient {
     pub sender: broadcast::Sender<WsMessage>,
 }
 
+/// Bytes enviados por WebSocket, sin comprimir y tal como salieron
+///
+/// A los clientes sin compresión el mensaje sale tal cual, así que suma lo
+/// mismo en los dos contadores.
+#[derive(Debug, Default)]
+pub struct WsTraffic {
+    uncompressed: AtomicU64,
+    compressed: AtomicU64,
+}
+
+impl WsTraffic {
+    /// Registrar un mensaje ya escrito en el socket: `payload_len` bytes
+    /// de JSON que ocuparon `sent_len` en el frame
+    pub fn record_sent(&self, payload_len: usize, sent_len: usize) {
+        self.uncompressed
+            .fetch_add(payload_len as u64, Ordering::Relaxed);
+        self.compressed
+            .fetch_add(sent_len as u64, Ordering::Relaxed);
+    }
+
+    /// Bytes de los mensajes antes de comprimir
+    pub fn total_bytes_sent_uncompressed(&self) -> u64 {
+        self.uncompressed.load(Ordering::Relaxed)
+    }
+
+    /// Bytes que salieron en los frames
+    pub fn total_bytes_sent_compressed(&self) -> u64 {
+        self.compressed.load(Ordering::Relaxed)
+    }
+
+    /// Contadores y relación comprimido / original (1.0 sin tráfico), para
+    /// `GET /api/v1/metrics/websocket`
+    pub fn snapshot(&self) -> serde_json::Value {
+        let uncompressed = self.total_bytes_sent_uncompressed();
+        let compressed = self.total_bytes_sent_compressed();
+        let ratio = if uncompressed == 0 {
+            1.0
+        } else {
+            compressed as f64 / uncompressed as f64
+        };
+        serde_json::json!({
+            "total_bytes_sent_uncompressed": uncompressed,
+            "total_bytes_sent_compressed": compressed,
+            "compression_ratio": ratio,
+        })
+    }
+}
+
+/// Lado de escritura de un cliente: el de axum o el de `ws_deflate`
+enum WsSender {
+    Plain(SplitSink<WebSocket, Message>),
+    Deflate(DeflateSender),
+}
+
+impl WsSender {
+    /// Enviar un mensaje de texto y contar sus bytes una vez escrito
+    async fn send_text(&mut self, traffic: &WsTraffic, payload: String) -> Result<(), axum::Error> {
+        let len = payload.len();
+        let sent = match self {
+            Self::Plain(sink) => {
+                sink.send(Message::Text(payload)).await?;
+                len
+            }
+            Self::Deflate(sender) => sender.send_text(&payload).await.map_err(axum::Error::new)?,
+        };
+        traffic.record_sent(len, sent);
+        Ok(())
+    }
+}
+
+/// Lado de lectura de un cliente: el de axum o el de `ws_deflate`
+enum WsReceiver {
+    Plain(SplitStream<WebSocket>),
+    Deflate(DeflateReceiver),
+}
+
+impl WsReceiver {
+    async fn next(&mut self) -> Option<Result<Message, axum::Error>> {
+        match self {
+            Self::Plain(stream) => stream.next().await,
+            Self::Deflate(receiver) => receiver
+                .next()
+                .await
+                .map(|message| message.map_err(axum::Error::new)),
+        }
+    }
+}
+
+/// Consulta en vivo: se vuelve a ejecutar cuando cambian sus tablas
+#[derive(Debug, Clone)]
+pub struct LiveQuery {
+    pub client_id: String,
+    pub query: String,
+    pub tables: Vec<String>,
+}
+
 /// Manager para clientes WebSocket conectados
 #[derive(Debug, Clone)]
 pub struct WsManager {
     clients: Arc<tokio::sync::RwLock<Vec<WsClient>>>,
+    live_queries: Arc<tokio::sync::RwLock<Vec<LiveQuery>>>,
     state: ServerState,
 }
 
@@ -38,6 +145,7 @@ impl WsManager {
     pub fn new(state: ServerState) -> Self {
         Self {
             clients: Arc::new(tokio::sync::RwLock::new(Vec::new())),
+            live_queries: Arc::new(tokio::sync::RwLock::new(Vec::new())),
             state,
         }
     }
@@ -48,10 +156,121 @@ impl WsManager {
         clients.push(client);
     }
     
-    /// Remover cliente por ID
+    /// Remover cliente por ID (y sus consultas en vivo)
     pub async fn remove_client(&self, client_id: &str) {
         let mut clients = self.clients.write().await;
         clients.retain(|c| c.id != client_id);
+        self.live_queries
+            .write()
+            .await
+            .retain(|q| q.client_id != client_id);
+    }
+
+    /// Enviar mensaje sólo a un cliente
+    pub async fn send_to(&self, client_id: &str, message: WsMessage) {
+        let clients = self.clients.read().await;
+        if let Some(client) = clients.iter().find(|c| c.id == client_id) {
+            let _ = client.sender.send(message);
+        }
+    }
+
+    /// Validar una consulta en vivo antes de registrarla
+    ///
+    /// Se vuelve a ejecutar con cada cambio de sus tablas, así que sólo puede
+    /// leer; pasa además por el `SqlSanitizer` de la configuración (longitud
+    /// máxima y palabras clave bloqueadas), igual que `POST /api/v1/query`.
+    pub async fn check_live_query(&self, query: &str) -> Result<(), noctra_core::NoctraError> {
+        SqlSanitizer::from_config(&*self.state.config.read().await).check(query)?;
+        let executor = self
+            .state
+            .get_executor()
+            .await
+            .map_err(noctra_core::NoctraError::Database)?;
+        executor.ensure_read_only(&Session::new(), query)
+    }
+
+    /// Registrar una consulta en vivo de un cliente
+    pub async fn add_live_query(&self, client_id: &str, query: &str) -> LiveQuery {
+        let live_query = LiveQuery {
+            client_id: client_id.to_string(),
+            query: query.to_string(),
+            tables: QueryPlan::from_sql(query).referenced_tables,
+        };
+        self.live_queries.write().await.push(live_query.clone());
+        live_query
+    }
+
+    /// Ejecutar una consulta en vivo y enviar el resultado a su cliente
+    async fn run_live_query(&self, live_query: &LiveQuery) {
+        let executor = self.state.executor.read().await;
+        let Some(executor) = executor.as_ref() else {
+            return;
+        };
+
+        let data = match executor
+            .execute_rql(&Session::new(), RqlQuery::sql(live_query.query.as_str()))
+        {
+            Ok(result) => serde_json::json!({
+                "query": live_query.query,
+                "columns": result.columns.iter().map(|c| c.name.clone()).collect::<Vec<_>>(),
+                "rows": result.rows.iter().map(|r| r.values.iter().map(|v| v.to_string()).collect::<Vec<_>>()).collect::<Vec<_>>(),
+            }),
+            Err(e) => serde_json::json!({
+                "query": live_query.query,
+                "error": e.to_string(),
+                "code": e.error_code(),
+            }),
+        };
+
+        self.send_to(
+            &live_query.client_id,
+            WsMessage {
+                message_type: "live_query_result".to_string(),
+                data,
+                timestamp: chrono::Utc::now(),
+            },
+        )
+        .await;
+    }
+
+    /// Escuchar los cambios del backend y re-ejecutar las consultas en vivo
+    ///
+    /// Los eventos llegan ya agrupados por transacción; se leen todos los
+    /// pendientes y cada consulta afectada se ejecuta una sola vez.
+    pub async fn start_change_listener(&self) -> Result<(), noctra_core::NoctraError> {
+        let mut changes = match self.state.get_executor().await {
+            Ok(executor) => executor.subscribe_changes()?,
+            Err(_) => return Ok(()),
+        };
+
+        let manager = self.clone();
+        tokio::spawn(async move {
+            loop {
+                let mut events: Vec<ChangeEvent> = match changes.recv().await {
+                    Ok(event) => vec![event],
+                    // Receptor lento: se perdieron eventos, refrescar todo
+                    Err(broadcast::error::RecvError::Lagged(_)) => Vec::new(),
+                    Err(broadcast::error::RecvError::Closed) => break,
+                };
+                let lagged = events.is_empty();
+                while let Ok(event) = changes.try_recv() {
+                    events.push(event);
+                }
+
+                let affected: Vec<LiveQuery> = manager
+                    .live_queries
+                    .read()
+                    .await
+                    .iter()
+                    .filter(|q| lagged || events.iter().any(|e| q.tables.contains(&e.table)))
+                    .cloned()
+                    .collect();
+                for live_query in &affected {
+                    manager.run_live_query(live_query).await;
+                }
+            }
+        });
+        Ok(())
     }
     
     /// Broadcast mensaje a todos los clientes
@@ -59,7 +278,7 @@ impl WsManager {
         let clients = self.clients.read().await;
         
         for client in clients.iter() {
-            if let Err(_) = client.sender.send(message.clone()) {
+            if client.sender.send(message.clone()).is_err() {
                 // Cliente desconectado, será removido en cleanup
                 continue;
             }
@@ -89,102 +308,109 @@ impl WsManager {
 }
 
 /// Handler para conexión WebSocket principal
+#[derive(Debug, Clone)]
 pub struct WsHandler {
     manager: WsManager,
+    config: WsConfig,
 }
 
 impl WsHandler {
-    pub fn new(manager: WsManager) -> Self {
-        Self { manager }
+    pub fn new(manager: WsManager, config: WsConfig) -> Self {
+        Self { manager, config }
     }
     
-    /// Endpoint WebSocket principal
-    pub async fn handle_websocket(
-        WebSocketUpgrade { 
-            protocol, 
-            state, 
-            response: resp 
-        }: WebSocketUpgrade,
-        ConnectInfo(addr): ConnectInfo<axum::extract::connect_info::Client>,
-        Host(host): Host,
-    ) -> impl IntoResponse {
-        resp.on_upgrade(move |socket| self.handle_socket(socket, addr, host))
+    /// Aceptar el upgrade y atender el socket
+    ///
+    /// Con `WsConfig::compression_enabled` y una oferta `permessage-deflate`
+    /// aceptable, el upgrade lo completa `ws_deflate` sobre `on_upgrade` (la
+    /// conexión que entrega hyper); si no, `upgrade`.
+    pub fn handle_websocket(
+        self,
+        upgrade: WebSocketUpgrade,
+        on_upgrade: Option<OnUpgrade>,
+        headers: &HeaderMap,
+        host: String,
+    ) -> Response {
+        let deflate = self
+            .config
+            .compression_enabled
+            .then(|| ws_deflate::negotiate(headers))
+            .flatten();
+        match (deflate, on_upgrade, headers.get(header::SEC_WEBSOCKET_KEY)) {
+            (Some(extension), Some(on_upgrade), Some(key)) => {
+                ws_deflate::upgrade(on_upgrade, key, extension, move |sender, receiver| {
+                    self.handle_socket(
+                        WsSender::Deflate(sender),
+                        WsReceiver::Deflate(receiver),
+                        host,
+                    )
+                })
+            }
+            _ => upgrade.on_upgrade(move |socket| {
+                let (sink, stream) = socket.split();
+                self.handle_socket(WsSender::Plain(sink), WsReceiver::Plain(stream), host)
+            }),
+        }
     }
     
     /// Manejar socket WebSocket individual
-    async fn handle_socket(
-        &self,
-        mut socket: WebSocket,
-        addr: std::net::SocketAddr,
-        host: String,
-    ) {
-        let client_id = format!("ws_{}_{}", addr, chrono::Utc::now().timestamp());
-        let (tx, rx) = broadcast::channel(100);
-        
-        // Crear cliente
-        let client = WsClient {
-            id: client_id.clone(),
+    ///
+    /// Una tarea escribe todo lo que llega al canal del cliente (broadcast y
+    /// respuestas); este bucle lee los mensajes hasta que el cliente cierra.
+    async fn handle_socket(self, mut sender: WsSender, mut receiver: WsReceiver, host: String) {
+        let now = chrono::Utc::now();
+        let client_id = format!(
+            "ws_{}_{}",
             host,
-            connected_at: chrono::Utc::now(),
-            sender: tx,
-        };
-        
-        // Registrar cliente
-        self.manager.add_client(client).await;
+            now.timestamp_nanos_opt().unwrap_or_default()
+        );
+        let (tx, mut rx) = broadcast::channel(self.config.message_buffer);
+        let traffic = self.manager.state.websocket_traffic.clone();
         
         // Enviar mensaje de bienvenida
-        if let Err(_) = socket.send(Message::Text(
-            serde_json::json!({
-                "type": "welcome",
-                "client_id": client_id,
-                "timestamp": chrono::Utc::now().to_rfc3339(),
-                "message": "Conexión WebSocket establecida con Noctra Server"
-            }).to_string()
-        )).await {
-            self.manager.remove_client(&client_id).await;
+        let welcome = serde_json::json!({
+            "type": "welcome",
+            "client_id": client_id,
+            "timestamp": now.to_rfc3339(),
+            "message": "Conexión WebSocket establecida con Noctra Server"
+        });
+        if sender
+            .send_text(&traffic, welcome.to_string())
+            .await
+            .is_err()
+        {
             return;
         }
         
-        // Spawn tarea para recibir mensajes
-        let manager_clone = self.manager.clone();
-        let client_id_clone = client_id.clone();
-        
-        tokio::spawn(async move {
-            while let Some(msg) = socket.recv().await {
-                match msg {
-                    Ok(Message::Text(text)) => {
-                        if let Err(e) = Self::handle_client_message(
-                            &manager_clone,
-                            &client_id_clone,
-                            &text,
-                        ).await {
-                            // Enviar error al cliente
-                            let _ = socket.send(Message::Text(
-                                serde_json::json!({
-                                    "type": "error",
-                                    "error": e.to_string(),
-                                    "timestamp": chrono::Utc::now().to_rfc3339()
-                                }).to_string()
-                            )).await;
-                        }
-                    }
-                    Ok(Message::Binary(data)) => {
-                        // Manejar datos binarios si es necesario
-                        let _ = socket.send(Message::Text(
-                            format!("Mensaje binario recibido: {} bytes", data.len())
-                        )).await;
-                    }
-                    Ok(Message::Close(_)) => break,
-                    Ok(Message::Ping(_)) => {
-                        let _ = socket.send(Message::Pong(())).await;
-                    }
-                    Ok(Message::Pong(_)) => {}
-                    Err(_) => break,
+        // Registrar cliente
+        self.manager
+            .add_client(WsClient {
+                id: client_id.clone(),
+                host: host.clone(),
+                connected_at: now,
+                sender: tx,
+            })
+            .await;
+
+        let writer_id = client_id.clone();
+        let writer = tokio::spawn(async move {
+            while let Ok(message) = rx.recv().await {
+                // No re-broadcast del mensaje a sí mismo
+                if message
+                    .data
+                    .get("client_id")
+                    .is_some_and(|id| id == writer_id.as_str())
+                {
+                    continue;
+                }
+
+                let Ok(payload) = serde_json::to_string(&message) else {
+                    continue;
+                };
+                if sender.send_text(&traffic, payload).await.is_err() {
+                    break;
                 }
             }
-            
-            // Cliente desconectado
-            manager_clone.remove_client(&client_id_clone).await;
         });
         
         // Broadcast de nueva conexión
@@ -194,33 +420,47 @@ impl WsHandler {
                 "event": "client_connected",
                 "client_id": client_id,
                 "host": host,
-                "address": addr.to_string(),
                 "timestamp": chrono::Utc::now().to_rfc3339()
             }),
             timestamp: chrono::Utc::now(),
         }).await;
         
-        // Spawn tarea para enviar mensajes broadcast
-        let mut rx = rx.subscribe();
-        let client_id_clone = client_id.clone();
-        let mut socket_for_broadcast = socket.split();
-        
-        tokio::spawn(async move {
-            while let Ok(message) = rx.recv().await {
-                // No re-broadcast del mensaje a sí mismo
-                if let Some(client_data) = message.data.get("client_id") {
-                    if client_data == &client_id_clone {
-                        continue;
+        // tungstenite y ws_deflate contestan los ping por su cuenta
+        while let Some(Ok(message)) = receiver.next().await {
+            match message {
+                Message::Text(text) => {
+                    if let Err(e) =
+                        Self::handle_client_message(&self.manager, &client_id, &text).await
+                    {
+                        self.manager
+                            .send_to(
+                                &client_id,
+                                WsMessage {
+                                    message_type: "error".to_string(),
+                                    data: serde_json::json!({ "error": e }),
+                                    timestamp: chrono::Utc::now(),
+                                },
+                            )
+                            .await;
                     }
                 }
-                
-                if let Err(_) = socket_for_broadcast
-                    .send(Message::Text(serde_json::to_string(&message).unwrap()))
-                    .await {
-                    break;
+                Message::Binary(data) => {
+                    self.manager.send_to(&client_id, WsMessage {
+                        message_type: "error".to_string(),
+                        data: serde_json::json!({
+                            "error": format!("Mensaje binario recibido: {} bytes", data.len())
+                        }),
+                        timestamp: chrono::Utc::now(),
+                    }).await;
                 }
+                Message::Close(_) => break,
+                Message::Ping(_) | Message::Pong(_) => {}
             }
-        });
+        }
+
+        // Cliente desconectado
+        writer.abort();
+        self.manager.remove_client(&client_id).await;
     }
     
     /// Manejar mensaje del cliente
@@ -271,6 +511,17 @@ impl WsHandler {
                 manager.broadcast(response).await;
             }
             
+            "live_query" => {
+                // Consulta que se vuelve a enviar cada vez que cambian sus tablas
+                let query = message.get("query")
+                    .and_then(|v| v.as_str())
+                    .ok_or("Query no especificada")?;
+                manager.check_live_query(query).await.map_err(|e| e.to_string())?;
+                
+                let live_query = manager.add_live_query(client_id, query).await;
+                manager.run_live_query(&live_query).await;
+            }
+            
             "subscribe" => {
                 // Suscribirse a eventos específicos
                 let event_type = message.get("event")
@@ -300,15 +551,7 @@ impl WsHandler {
                 };
                 
                 // Solo al cliente que pidió stats
-                let client_response = serde_json::json!({
-                    "type": "stats_response",
-                    "stats": stats,
-                    "timestamp": chrono::Utc::now().to_rfc3339()
-                }).to_string();
-                
-                // Nota: Esto requiere acceso directo al socket del cliente
-                // Por simplicidad, lo broadcast a todos
-                manager.broadcast(response).await;
+                manager.send_to(client_id, response).await;
             }
             
             _ => {
@@ -328,6 +571,8 @@ pub struct WsConfig {
     pub max_clients: usize,
     pub ping_interval: std::time::Duration,
     pub message_buffer: usize,
+    /// Aceptar la extensión `permessage-deflate` (mensajes comprimidos)
+    pub compression_enabled: bool,
 }
 
 impl Default for WsConfig {
@@ -336,40 +581,85 @@ impl Default for WsConfig {
             max_clients: 100,
             ping_interval: std::time::Duration::from_secs(30),
             message_buffer: 1000,
+            compression_enabled: true,
         }
     }
 }
 
 /// Extensión para agregar WebSocket a la aplicación
 pub trait WsAppExt {
-    fn add_websocket_routes(self, ws_handler: &WsHandler) -> Self;
+    fn add_websocket_routes(self, ws_state: &WsState) -> Self;
 }
 
-impl WsAppExt for axum::Router {
-    fn add_websocket_routes(self, ws_handler: &WsHandler) -> Self {
+impl<S: Clone + Send + Sync + 'static> WsAppExt for axum::Router<S> {
+    fn add_websocket_routes(self, ws_state: &WsState) -> Self {
         self.route(
             "/ws",
-            axum::routing::get(|ws: WebSocketUpgrade, state: State<ServerState>, host: Host, addr: ConnectInfo<std::net::SocketAddr>| {
-                ws_handler.handle_websocket(ws, addr, host.0)
-            })
+            axum::routing::get(websocket_endpoint).layer(Extension(ws_state.clone())),
         )
     }
 }
 
+/// Endpoint WebSocket
+#[utoipa::path(
+    get,
+    path = "/ws",
+    tag = "websocket",
+    responses(
+        (status = 101, description = "Conexión WebSocket; si el cliente ofrece `permessage-deflate` en `Sec-WebSocket-Extensions`, la respuesta la acepta y los mensajes llegan comprimidos"),
+        (status = 401, description = "Token ausente o inválido", body = crate::types::ServerError)
+    ),
+    security(("bearer_auth" = []))
+)]
+pub async fn websocket_endpoint(
+    Extension(ws_state): Extension<WsState>,
+    Host(host): Host,
+    headers: HeaderMap,
+    // Antes que `WebSocketUpgrade`, que lo saca de las extensiones
+    on_upgrade: Option<Extension<OnUpgrade>>,
+    upgrade: WebSocketUpgrade,
+) -> Response {
+    WsHandler::new(ws_state.manager, ws_state.config).handle_websocket(
+        upgrade,
+        on_upgrade.map(|Extension(on_upgrade)| on_upgrade),
+        &headers,
+        host,
+    )
+}
+
 /// Estado compartido para WebSocket
 #[derive(Debug, Clone)]
 pub struct WsState {
     pub manager: WsManager,
     pub config: WsConfig,
+    /// Bytes enviados (el mismo `ServerState::websocket_traffic`)
+    pub traffic: Arc<WsTraffic>,
 }
 
 impl WsState {
     pub fn new(state: ServerState) -> Self {
+        Self::with_config(state, WsConfig::default())
+    }
+
+    /// Estado con una configuración WebSocket propia
+    pub fn with_config(state: ServerState, config: WsConfig) -> Self {
+        let traffic = state.websocket_traffic.clone();
         Self {
             manager: WsManager::new(state),
-            config: WsConfig::default(),
+            config,
+            traffic,
         }
     }
+
+    /// Bytes de los mensajes enviados, antes de comprimir
+    pub fn total_bytes_sent_uncompressed(&self) -> u64 {
+        self.traffic.total_bytes_sent_uncompressed()
+    }
+
+    /// Bytes que salieron en los frames
+    pub fn total_bytes_sent_compressed(&self) -> u64 {
+        self.traffic.total_bytes_sent_compressed()
+    }
     
     /// Inicializar limpieza periódica de clientes
     pub fn start_cleanup_task(&self) {
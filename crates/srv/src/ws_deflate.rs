@@ -0,0 +1,454 @@
+//! Extensión `permessage-deflate` (RFC 7692) para `/ws`
+//!
+//! tungstenite, la capa WebSocket de axum, no negocia extensiones y rechaza
+//! los frames con RSV1, así que cuando el cliente ofrece `permessage-deflate`
+//! el upgrade se completa aquí y los frames se leen y escriben directamente
+//! sobre la conexión que entrega hyper.
+//!
+//! El servidor responde siempre con `server_no_context_takeover`: cada
+//! mensaje sale comprimido con un contexto nuevo. Los mensajes del cliente
+//! se descomprimen con un contexto que se conserva entre mensajes, que sirve
+//! tanto si el cliente lo reutiliza como si no.
+
+use std::future::Future;
+use std::io::{self, Write};
+use std::sync::Arc;
+
+use axum::body::Body;
+use axum::extract::ws::Message;
+use axum::http::{header, HeaderMap, HeaderValue, StatusCode};
+use axum::response::Response;
+use flate2::write::{DeflateDecoder, DeflateEncoder};
+use flate2::Compression;
+use hyper::upgrade::{OnUpgrade, Upgraded};
+use hyper_util::rt::TokioIo;
+use tokio::io::{AsyncReadExt, AsyncWriteExt, BufReader, ReadHalf, WriteHalf};
+use tokio::sync::Mutex;
+
+/// Nombre de la extensión en `Sec-WebSocket-Extensions`
+pub const EXTENSION_NAME: &str = "permessage-deflate";
+
+/// Respuesta del servidor cuando acepta la extensión
+pub const ACCEPTED_EXTENSION: &str = "permessage-deflate; server_no_context_takeover";
+
+/// Tamaño máximo de un mensaje, comprimido o no (el mismo que tungstenite)
+pub const MAX_MESSAGE_SIZE: usize = 64 << 20;
+
+/// Bytes que cierran cada mensaje comprimido y que no viajan en el frame
+const DEFLATE_TAIL: [u8; 4] = [0x00, 0x00, 0xff, 0xff];
+
+/// Bloques en los que se descomprime un mensaje para cortar a tiempo los
+/// que exceden `MAX_MESSAGE_SIZE`
+const INFLATE_CHUNK: usize = 16 * 1024;
+
+const OPCODE_CONTINUATION: u8 = 0x0;
+const OPCODE_TEXT: u8 = 0x1;
+const OPCODE_BINARY: u8 = 0x2;
+const OPCODE_CLOSE: u8 = 0x8;
+const OPCODE_PING: u8 = 0x9;
+const OPCODE_PONG: u8 = 0xa;
+
+/// Código de cierre por error de protocolo
+const CLOSE_PROTOCOL_ERROR: u16 = 1002;
+
+type Reader = BufReader<ReadHalf<TokioIo<Upgraded>>>;
+type Writer = WriteHalf<TokioIo<Upgraded>>;
+
+/// Buscar entre las ofertas de `Sec-WebSocket-Extensions` una
+/// `permessage-deflate` que el servidor pueda cumplir
+///
+/// Devuelve el valor de la cabecera de respuesta. Se descartan las ofertas
+/// con parámetros desconocidos o repetidos y las que piden
+/// `server_max_window_bits` menor que 15: el compresor usa siempre la
+/// ventana completa.
+pub fn negotiate(headers: &HeaderMap) -> Option<&'static str> {
+    headers
+        .get_all(header::SEC_WEBSOCKET_EXTENSIONS)
+        .iter()
+        .filter_map(|value| value.to_str().ok())
+        .flat_map(|value| value.split(','))
+        .any(acceptable_offer)
+        .then_some(ACCEPTED_EXTENSION)
+}
+
+/// Comprobar una oferta: `permessage-deflate; param; param=valor`
+fn acceptable_offer(offer: &str) -> bool {
+    let mut parts = offer.split(';').map(str::trim);
+    if parts.next() != Some(EXTENSION_NAME) {
+        return false;
+    }
+
+    let mut seen: Vec<&str> = Vec::new();
+    for param in parts {
+        let (name, value) = match param.split_once('=') {
+            Some((name, value)) => (name.trim(), Some(value.trim().trim_matches('"'))),
+            None => (param, None),
+        };
+        if seen.contains(&name) {
+            return false;
+        }
+        seen.push(name);
+
+        let valid = match (name, value) {
+            ("server_no_context_takeover" | "client_no_context_takeover", None) => true,
+            ("client_max_window_bits", None) => true,
+            ("client_max_window_bits", Some(bits)) => window_bits(bits).is_some(),
+            ("server_max_window_bits", Some(bits)) => window_bits(bits) == Some(15),
+            _ => false,
+        };
+        if !valid {
+            return false;
+        }
+    }
+    true
+}
+
+/// Valor de `*_max_window_bits`: un entero de 8 a 15 sin ceros delante
+fn window_bits(value: &str) -> Option<u8> {
+    if value.starts_with('0') {
+        return None;
+    }
+    value.parse().ok().filter(|bits| (8..=15).contains(bits))
+}
+
+/// Completar el upgrade con la extensión aceptada
+///
+/// Responde el `101` con `Sec-WebSocket-Extensions` y, cuando hyper entrega
+/// la conexión, llama a `callback` con sus dos mitades (como
+/// `WebSocketUpgrade::on_upgrade`).
+pub fn upgrade<F, Fut>(
+    on_upgrade: OnUpgrade,
+    key: &HeaderValue,
+    extension: &'static str,
+    callback: F,
+) -> Response
+where
+    F: FnOnce(DeflateSender, DeflateReceiver) -> Fut + Send + 'static,
+    Fut: Future<Output = ()> + Send + 'static,
+{
+    tokio::spawn(async move {
+        let Ok(upgraded) = on_upgrade.await else {
+            return;
+        };
+        let (reader, writer) = tokio::io::split(TokioIo::new(upgraded));
+        let sender = DeflateSender {
+            writer: Arc::new(Mutex::new(writer)),
+        };
+        let receiver = DeflateReceiver::new(BufReader::new(reader), sender.clone());
+        callback(sender, receiver).await;
+    });
+
+    Response::builder()
+        .status(StatusCode::SWITCHING_PROTOCOLS)
+        .header(header::CONNECTION, "upgrade")
+        .header(header::UPGRADE, "websocket")
+        .header(
+            header::SEC_WEBSOCKET_ACCEPT,
+            tungstenite::handshake::derive_accept_key(key.as_bytes()),
+        )
+        .header(header::SEC_WEBSOCKET_EXTENSIONS, extension)
+        .body(Body::empty())
+        .expect("respuesta de upgrade válida")
+}
+
+/// Comprimir un mensaje con un contexto nuevo (RFC 7692, 7.2.1)
+pub fn compress(payload: &[u8]) -> io::Result<Vec<u8>> {
+    let mut encoder = DeflateEncoder::new(
+        Vec::with_capacity(payload.len() / 4 + DEFLATE_TAIL.len()),
+        Compression::default(),
+    );
+    encoder.write_all(payload)?;
+    // Vacía con sync flush: el bloque termina en `DEFLATE_TAIL`
+    encoder.flush()?;
+    let mut compressed = std::mem::take(encoder.get_mut());
+    if compressed.ends_with(&DEFLATE_TAIL) {
+        compressed.truncate(compressed.len() - DEFLATE_TAIL.len());
+    }
+    if compressed.is_empty() {
+        compressed.push(0x00);
+    }
+    Ok(compressed)
+}
+
+fn protocol_error(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+/// Escribir un frame del servidor (sin máscara, siempre con FIN)
+async fn write_frame(
+    writer: &mut Writer,
+    opcode: u8,
+    compressed: bool,
+    payload: &[u8],
+) -> io::Result<()> {
+    let mut frame = Vec::with_capacity(payload.len() + 10);
+    frame.push(0x80 | if compressed { 0x40 } else { 0x00 } | opcode);
+    match payload.len() {
+        len if len < 126 => frame.push(len as u8),
+        len if len <= u16::MAX as usize => {
+            frame.push(126);
+            frame.extend_from_slice(&(len as u16).to_be_bytes());
+        }
+        len => {
+            frame.push(127);
+            frame.extend_from_slice(&(len as u64).to_be_bytes());
+        }
+    }
+    frame.extend_from_slice(payload);
+    writer.write_all(&frame).await?;
+    writer.flush().await
+}
+
+/// Lado de escritura de una conexión con `permessage-deflate`
+///
+/// Lo comparte el receptor, que contesta los ping y el cierre.
+#[derive(Debug, Clone)]
+pub struct DeflateSender {
+    writer: Arc<Mutex<Writer>>,
+}
+
+impl DeflateSender {
+    /// Enviar un mensaje de texto comprimido; devuelve los bytes que ocupó
+    /// en el frame
+    pub async fn send_text(&self, text: &str) -> io::Result<usize> {
+        let payload = compress(text.as_bytes())?;
+        write_frame(&mut *self.writer.lock().await, OPCODE_TEXT, true, &payload).await?;
+        Ok(payload.len())
+    }
+
+    async fn send_control(&self, opcode: u8, payload: &[u8]) -> io::Result<()> {
+        write_frame(&mut *self.writer.lock().await, opcode, false, payload).await
+    }
+
+    /// Enviar el frame de cierre y cerrar la escritura
+    async fn close(&self, payload: &[u8]) -> io::Result<()> {
+        let mut writer = self.writer.lock().await;
+        write_frame(&mut writer, OPCODE_CLOSE, false, payload).await?;
+        writer.shutdown().await
+    }
+}
+
+/// Frame del cliente, ya sin máscara
+struct Frame {
+    fin: bool,
+    rsv1: bool,
+    opcode: u8,
+    payload: Vec<u8>,
+}
+
+/// Lado de lectura de una conexión con `permessage-deflate`
+///
+/// Junta los fragmentos, descomprime los mensajes con RSV1 y contesta los
+/// ping; `next` sólo devuelve mensajes de texto y binarios.
+pub struct DeflateReceiver {
+    reader: Reader,
+    sender: DeflateSender,
+    inflater: DeflateDecoder<Vec<u8>>,
+}
+
+impl std::fmt::Debug for DeflateReceiver {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("DeflateReceiver").finish_non_exhaustive()
+    }
+}
+
+impl DeflateReceiver {
+    fn new(reader: Reader, sender: DeflateSender) -> Self {
+        Self {
+            reader,
+            sender,
+            inflater: DeflateDecoder::new(Vec::new()),
+        }
+    }
+
+    /// Siguiente mensaje del cliente; `None` cuando cierra la conexión
+    ///
+    /// Ante un error de protocolo se envía el cierre 1002 antes de
+    /// devolverlo.
+    pub async fn next(&mut self) -> Option<io::Result<Message>> {
+        match self.read_message().await {
+            Ok(message) => message.map(Ok),
+            Err(e) => {
+                if e.kind() == io::ErrorKind::InvalidData {
+                    let _ = self.sender.close(&CLOSE_PROTOCOL_ERROR.to_be_bytes()).await;
+                }
+                Some(Err(e))
+            }
+        }
+    }
+
+    async fn read_message(&mut self) -> io::Result<Option<Message>> {
+        // Mensaje fragmentado en curso: opcode, si viene comprimido y datos
+        let mut pending: Option<(u8, bool, Vec<u8>)> = None;
+        loop {
+            let frame = match self.read_frame().await {
+                Ok(frame) => frame,
+                Err(e) if e.kind() == io::ErrorKind::UnexpectedEof => return Ok(None),
+                Err(e) => return Err(e),
+            };
+
+            if frame.opcode >= OPCODE_CLOSE {
+                if !frame.fin || frame.rsv1 || frame.payload.len() > 125 {
+                    return Err(protocol_error("Frame de control inválido"));
+                }
+                match frame.opcode {
+                    OPCODE_CLOSE => {
+                        // Se devuelve el código recibido, sin el motivo
+                        let code = &frame.payload[..frame.payload.len().min(2)];
+                        let _ = self.sender.close(code).await;
+                        return Ok(None);
+                    }
+                    OPCODE_PING => {
+                        self.sender
+                            .send_control(OPCODE_PONG, &frame.payload)
+                            .await?
+                    }
+                    OPCODE_PONG => {}
+                    _ => return Err(protocol_error("Opcode de control desconocido")),
+                }
+                continue;
+            }
+
+            let (opcode, compressed, mut data) = match (frame.opcode, pending.take()) {
+                (OPCODE_CONTINUATION, Some((opcode, compressed, mut data))) if !frame.rsv1 => {
+                    data.extend_from_slice(&frame.payload);
+                    (opcode, compressed, data)
+                }
+                (OPCODE_TEXT | OPCODE_BINARY, None) => (frame.opcode, frame.rsv1, frame.payload),
+                _ => return Err(protocol_error("Frame de datos fuera de secuencia")),
+            };
+            if data.len() > MAX_MESSAGE_SIZE {
+                return Err(protocol_error("Mensaje demasiado grande"));
+            }
+            if !frame.fin {
+                pending = Some((opcode, compressed, data));
+                continue;
+            }
+
+            if compressed {
+                data = self.inflate(&data)?;
+            }
+            let message = if opcode == OPCODE_TEXT {
+                Message::Text(
+                    String::from_utf8(data)
+                        .map_err(|_| protocol_error("Mensaje de texto sin UTF-8 válido"))?,
+                )
+            } else {
+                Message::Binary(data)
+            };
+            return Ok(Some(message));
+        }
+    }
+
+    /// Leer un frame y quitarle la máscara, obligatoria en los del cliente
+    async fn read_frame(&mut self) -> io::Result<Frame> {
+        let mut head = [0u8; 2];
+        self.reader.read_exact(&mut head).await?;
+        if head[0] & 0x30 != 0 {
+            return Err(protocol_error("Bits RSV2/RSV3 activos"));
+        }
+        if head[1] & 0x80 == 0 {
+            return Err(protocol_error("Frame del cliente sin máscara"));
+        }
+
+        let len = match head[1] & 0x7f {
+            126 => u64::from(self.reader.read_u16().await?),
+            127 => self.reader.read_u64().await?,
+            len => u64::from(len),
+        };
+        if len > MAX_MESSAGE_SIZE as u64 {
+            return Err(protocol_error("Frame demasiado grande"));
+        }
+
+        let mut mask = [0u8; 4];
+        self.reader.read_exact(&mut mask).await?;
+        let mut payload = vec![0u8; len as usize];
+        self.reader.read_exact(&mut payload).await?;
+        for (i, byte) in payload.iter_mut().enumerate() {
+            *byte ^= mask[i % 4];
+        }
+
+        Ok(Frame {
+            fin: head[0] & 0x80 != 0,
+            rsv1: head[0] & 0x40 != 0,
+            opcode: head[0] & 0x0f,
+            payload,
+        })
+    }
+
+    /// Descomprimir un mensaje con el contexto de la conexión
+    fn inflate(&mut self, data: &[u8]) -> io::Result<Vec<u8>> {
+        let too_large = || protocol_error("Mensaje descomprimido demasiado grande");
+        for chunk in data.chunks(INFLATE_CHUNK).chain([&DEFLATE_TAIL[..]]) {
+            self.inflater
+                .write_all(chunk)
+                .map_err(|_| protocol_error("Mensaje comprimido inválido"))?;
+            if self.inflater.get_ref().len() > MAX_MESSAGE_SIZE {
+                return Err(too_large());
+            }
+        }
+        self.inflater.flush()?;
+        if self.inflater.get_ref().len() > MAX_MESSAGE_SIZE {
+            return Err(too_large());
+        }
+        Ok(std::mem::take(self.inflater.get_mut()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn offer(value: &str) -> HeaderMap {
+        let mut headers = HeaderMap::new();
+        headers.insert(header::SEC_WEBSOCKET_EXTENSIONS, value.parse().unwrap());
+        headers
+    }
+
+    #[test]
+    fn test_negotiate_accepts_permessage_deflate_offers() {
+        for value in [
+            "permessage-deflate",
+            "permessage-deflate; client_max_window_bits",
+            "permessage-deflate; client_max_window_bits=10; server_no_context_takeover",
+            "permessage-deflate; client_no_context_takeover; server_max_window_bits=\"15\"",
+            "x-webkit-deflate-frame, permessage-deflate",
+        ] {
+            assert_eq!(
+                negotiate(&offer(value)),
+                Some(ACCEPTED_EXTENSION),
+                "{}",
+                value
+            );
+        }
+    }
+
+    #[test]
+    fn test_negotiate_rejects_unsupported_offers() {
+        assert_eq!(negotiate(&HeaderMap::new()), None);
+        for value in [
+            "x-webkit-deflate-frame",
+            "permessage-deflate; server_max_window_bits=10",
+            "permessage-deflate; client_max_window_bits=16",
+            "permessage-deflate; client_max_window_bits=010",
+            "permessage-deflate; server_no_context_takeover; server_no_context_takeover",
+            "permessage-deflate; mux",
+        ] {
+            assert_eq!(negotiate(&offer(value)), None, "{}", value);
+        }
+    }
+
+    #[test]
+    fn test_compress_strips_tail_and_inflates_back() {
+        let json = "{\"ciudad\":\"Buenos Aires\"}".repeat(200);
+        let compressed = compress(json.as_bytes()).unwrap();
+        assert!(!compressed.ends_with(&DEFLATE_TAIL));
+        assert!(compressed.len() * 4 < json.len());
+
+        let mut inflater = DeflateDecoder::new(Vec::new());
+        inflater.write_all(&compressed).unwrap();
+        inflater.write_all(&DEFLATE_TAIL).unwrap();
+        inflater.flush().unwrap();
+        assert_eq!(inflater.get_ref().as_slice(), json.as_bytes());
+    }
+}
@@ -0,0 +1,2361 @@
+//! Tests de integración para el servidor Noctra
+//!
+//! Pruebas end-to-end que cubren toda la pipeline: HTTP APIs, WebSocket,
+//! consultas SQL, formularios FDL2 y manejo de errores.
+
+use axum::{
+    body::Body,
+    http::{Method, Request, StatusCode},
+};
+use std::sync::{Arc, Mutex};
+use tower::ServiceExt;
+
+use futures_util::{SinkExt, StreamExt};
+use noctra_core::{
+    DataSource, Executor, FileSessionStore, NoctraError, Parameters, ResultSet, Session,
+    SessionHandle, SessionManager, SessionPersistence, SourceType, TableInfo,
+};
+use noctra_parser::RqlParser;
+use noctra_srv::performance::BodySizeLimitLayer;
+use noctra_srv::{
+    create_server,
+    handlers::{issue_token, slow_queries_handler, sql_fingerprint, verify_token},
+    routes::{create_router, create_router_with},
+    server::ServerState,
+    AuthUser, Claims, CookieSessionLayer, QueryHandler, QueryRequest, ServerConfig, SlowQueryEntry,
+    SlowQueryLayer, SqlSanitizer, StreamingQueryHandler, TokenResponse, WsConfig, WsState,
+    DEFAULT_PAGE_SIZE, MAX_PAGE_SIZE, NDJSON_CONTENT_TYPE, SESSION_HEADER,
+};
+use tokio_tungstenite::tungstenite::{client::IntoClientRequest, Message as WsFrame};
+
+/// Configuración de test: base SQLite en memoria, sin autenticación y con los formularios de `forms/`
+fn test_config() -> ServerConfig {
+    ServerConfig {
+        database_url: ":memory:".to_string(),
+        auth_disabled: true,
+        forms_directory: Some(std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("../../forms")),
+        ..ServerConfig::default()
+    }
+}
+
+/// Helper para crear un servidor de test
+async fn create_test_server() -> (ServerState, axum::Router) {
+    let config = test_config();
+    let state = ServerState::new(config).await.unwrap();
+
+    let router = create_router(state.clone());
+
+    (state, router)
+}
+
+/// Helper para hacer requests HTTP
+async fn make_request(
+    app: &axum::Router,
+    method: Method,
+    uri: &str,
+    body: Option<Body>,
+) -> (StatusCode, String) {
+    let request = Request::builder()
+        .method(method)
+        .uri(uri)
+        .header("Content-Type", "application/json")
+        .body(body.unwrap_or_else(Body::empty))
+        .unwrap();
+
+    let response = app.clone().oneshot(request).await.unwrap();
+    let status = response.status();
+
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let body_str = String::from_utf8(body.to_vec()).unwrap();
+
+    (status, body_str)
+}
+
+/// Tests básicos del servidor HTTP
+#[tokio::test]
+async fn test_health_check() {
+    let (_state, app) = create_test_server().await;
+
+    let (status, body) = make_request(&app, Method::GET, "/health", None).await;
+
+    assert_eq!(status, StatusCode::OK);
+
+    let response: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(response["status"], "ok");
+    assert_eq!(response["version"], "0.1.0");
+    assert_eq!(response["checks"]["sqlite"]["status"], "ok");
+    assert!(response["checks"]["sqlite"]["latency_ms"].is_number());
+    assert!(response["uptime_seconds"].is_number());
+}
+
+/// Fuente DuckDB simulada cuyo `SELECT 1` siempre falla
+#[derive(Debug)]
+struct UnreachableSource;
+
+impl DataSource for UnreachableSource {
+    fn query(&self, _sql: &str, _parameters: &Parameters) -> noctra_core::Result<ResultSet> {
+        Err(NoctraError::database("archivo DuckDB inaccesible"))
+    }
+
+    fn schema(&self) -> noctra_core::Result<Vec<TableInfo>> {
+        Ok(Vec::new())
+    }
+
+    fn source_type(&self) -> SourceType {
+        SourceType::Memory { capacity: 0 }
+    }
+
+    fn name(&self) -> &str {
+        "caida"
+    }
+}
+
+#[tokio::test]
+async fn test_health_check_degraded_when_duckdb_probe_fails() {
+    let (state, app) = create_test_server().await;
+    let mut executor = Executor::new_sqlite_memory().unwrap();
+    executor
+        .register_source("caida".to_string(), Box::new(UnreachableSource))
+        .unwrap();
+    *state.executor.write().await = Some(Arc::new(executor));
+
+    let (status, body) = make_request(&app, Method::GET, "/health", None).await;
+
+    assert_eq!(status, StatusCode::MULTI_STATUS);
+    let response: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(response["status"], "degraded");
+    assert_eq!(response["checks"]["sqlite"]["status"], "ok");
+    assert_eq!(response["checks"]["duckdb"]["status"], "degraded");
+    assert!(response["checks"]["duckdb"]["errors"][0]
+        .as_str()
+        .unwrap()
+        .starts_with("caida:"));
+}
+
+#[tokio::test]
+async fn test_ready_only_after_initialization() {
+    let state = ServerState::new(test_config()).await.unwrap();
+    // Armar el router no basta: el servidor está listo al abrir el listener
+    let app = create_server(state.clone(), test_config()).unwrap();
+
+    let (status, _) = make_request(&app, Method::GET, "/ready", None).await;
+    assert_eq!(status, StatusCode::SERVICE_UNAVAILABLE);
+
+    state.mark_ready();
+    let (status, body) = make_request(&app, Method::GET, "/ready", None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(body.contains("true"));
+}
+
+/// Tests de APIs REST
+#[tokio::test]
+async fn test_query_api_execute() {
+    let (_state, app) = create_test_server().await;
+
+    let query_data = serde_json::json!({
+        "query": "SELECT ? + 1 AS siguiente",
+        "parameters": [3]
+    });
+
+    let body = Body::from(serde_json::to_string(&query_data).unwrap());
+
+    let (status, response_body) =
+        make_request(&app, Method::POST, "/api/v1/query", Some(body)).await;
+
+    assert_eq!(status, StatusCode::OK);
+
+    let response: QueryResponse = serde_json::from_str(&response_body).unwrap();
+    assert_eq!(response.result["columns"][0]["name"], "siguiente");
+    assert_eq!(
+        response.result["rows"][0]["values"][0],
+        serde_json::json!({"Integer": 4})
+    );
+}
+
+#[tokio::test]
+async fn test_query_api_validate() {
+    let (_state, app) = create_test_server().await;
+
+    let query_data = serde_json::json!({
+        "sql": "SELECT id, name FROM users WHERE age > :age"
+    });
+
+    let body = Body::from(serde_json::to_string(&query_data).unwrap());
+
+    let (status, response_body) =
+        make_request(&app, Method::POST, "/api/v1/validate/sql", Some(body)).await;
+
+    assert_eq!(status, StatusCode::OK);
+
+    let response: serde_json::Value = serde_json::from_str(&response_body).unwrap();
+    assert_eq!(response["valid"], true);
+}
+
+/// Tests de APIs de formularios
+#[tokio::test]
+async fn test_form_api_execute() {
+    let (_state, app) = create_test_server().await;
+
+    let form_data = serde_json::json!({
+        "form_name": "empleados",
+        "action": "query",
+        "data": {
+            "dept": {"Text": "Ventas"},
+            "limit": {"Integer": 10}
+        }
+    });
+
+    let body = Body::from(serde_json::to_string(&form_data).unwrap());
+
+    let (status, response_body) =
+        make_request(&app, Method::POST, "/api/v1/form/empleados", Some(body)).await;
+
+    assert_eq!(status, StatusCode::OK);
+
+    let response: FormResponse = serde_json::from_str(&response_body).unwrap();
+    assert!(response.success);
+    assert!(response.message.contains("empleados"));
+}
+
+#[tokio::test]
+async fn test_form_api_validate() {
+    let (_state, app) = create_test_server().await;
+
+    let form_data = serde_json::json!({
+        "form_name": "empleados",
+        "action": "validate",
+        "data": {
+            "name": {"Text": "Juan Pérez"}
+        }
+    });
+
+    let body = Body::from(serde_json::to_string(&form_data).unwrap());
+
+    let (status, response_body) = make_request(
+        &app,
+        Method::POST,
+        "/api/v1/form/empleados/validate",
+        Some(body),
+    )
+    .await;
+
+    assert_eq!(status, StatusCode::OK);
+
+    let response: FormResponse = serde_json::from_str(&response_body).unwrap();
+    assert!(response.success);
+    assert!(response.validation_errors.is_empty());
+}
+
+/// Tests de gestión de sesiones
+#[tokio::test]
+async fn test_session_create() {
+    let (_state, app) = create_test_server().await;
+
+    let (status, body) = make_request(&app, Method::POST, "/api/v1/session", None).await;
+
+    assert_eq!(status, StatusCode::OK);
+
+    let response: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert!(response["session_id"].is_string());
+    assert_eq!(response["message"], "Sesión creada exitosamente");
+    assert_eq!(response["expires_in"], 3600);
+}
+
+#[tokio::test]
+async fn test_session_list() {
+    let (_state, app) = create_test_server().await;
+
+    // Crear una sesión primero
+    make_request(&app, Method::POST, "/api/v1/session", None).await;
+
+    let (status, body) = make_request(&app, Method::GET, "/api/v1/sessions", None).await;
+
+    assert_eq!(status, StatusCode::OK);
+
+    let response: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert_eq!(response["total"], 1);
+    // Los IDs no se listan: bastan para usar la sesión
+    assert!(response.get("sessions").is_none(), "{}", response);
+}
+
+/// Tests de manejo de errores
+#[tokio::test]
+async fn test_invalid_sql() {
+    let (_state, app) = create_test_server().await;
+
+    let invalid_query = serde_json::json!({
+        "query": "INVALID SQL SYNTAX",
+        "parameters": []
+    });
+
+    let body = Body::from(serde_json::to_string(&invalid_query).unwrap());
+
+    let (status, response_body) =
+        make_request(&app, Method::POST, "/api/v1/query", Some(body)).await;
+
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let response: ErrorResponse = serde_json::from_str(&response_body).unwrap();
+    assert_eq!(response.status_code, 400);
+    assert!(!response.message.is_empty());
+}
+
+#[tokio::test]
+async fn test_nonexistent_form() {
+    let (_state, app) = create_test_server().await;
+
+    let form_data = serde_json::json!({
+        "form_name": "nonexistent",
+        "action": "query"
+    });
+
+    let body = Body::from(serde_json::to_string(&form_data).unwrap());
+
+    let (status, response_body) =
+        make_request(&app, Method::POST, "/api/v1/form/nonexistent", Some(body)).await;
+
+    assert_eq!(status, StatusCode::NOT_FOUND);
+
+    let response: ErrorResponse = serde_json::from_str(&response_body).unwrap();
+    assert_eq!(response.status_code, 404);
+    assert_eq!(response.message, "Formulario no encontrado");
+}
+
+/// Tests de rutas no definidas
+#[tokio::test]
+async fn test_404_endpoint() {
+    let (_state, app) = create_test_server().await;
+
+    let (status, _body) = make_request(&app, Method::GET, "/api/v1/nonexistent", None).await;
+
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_invalid_method() {
+    let (_state, app) = create_test_server().await;
+
+    let (status, _body) = make_request(&app, Method::PATCH, "/health", None).await;
+
+    assert_eq!(status, StatusCode::METHOD_NOT_ALLOWED);
+}
+
+/// Tests de parámetros faltantes
+#[tokio::test]
+async fn test_missing_parameters() {
+    let (_state, app) = create_test_server().await;
+
+    let invalid_query = serde_json::json!({
+        // Falta el campo "query"
+        "parameters": []
+    });
+
+    let body = Body::from(serde_json::to_string(&invalid_query).unwrap());
+
+    let (status, _body) = make_request(&app, Method::POST, "/api/v1/query", Some(body)).await;
+
+    assert_eq!(status, StatusCode::UNPROCESSABLE_ENTITY);
+}
+
+/// Tests de validación de datos
+#[tokio::test]
+async fn test_empty_sql() {
+    let (_state, app) = create_test_server().await;
+
+    let invalid_query = serde_json::json!({
+        "query": "",
+        "parameters": []
+    });
+
+    let body = Body::from(serde_json::to_string(&invalid_query).unwrap());
+
+    let (status, response_body) =
+        make_request(&app, Method::POST, "/api/v1/query", Some(body)).await;
+
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+
+    let response: ErrorResponse = serde_json::from_str(&response_body).unwrap();
+    assert_eq!(response.status_code, 400);
+}
+
+#[tokio::test]
+async fn test_malformed_json() {
+    let (_state, app) = create_test_server().await;
+
+    let malformed_body = Body::from("{ invalid json }");
+
+    let (status, _body) =
+        make_request(&app, Method::POST, "/api/v1/query", Some(malformed_body)).await;
+
+    assert_eq!(status, StatusCode::BAD_REQUEST);
+}
+
+/// Test de rendimiento básico
+#[tokio::test]
+async fn test_concurrent_requests() {
+    let (_state, app) = create_test_server().await;
+
+    // Spawn múltiples requests concurrentes
+    let mut handles = Vec::new();
+
+    for i in 0..10 {
+        let app_clone = app.clone();
+        let handle = tokio::spawn(async move {
+            let query_data = serde_json::json!({
+                "query": format!("SELECT {} as test", i),
+                "parameters": []
+            });
+
+            let body = Body::from(serde_json::to_string(&query_data).unwrap());
+            make_request(&app_clone, Method::POST, "/api/v1/query", Some(body)).await
+        });
+        handles.push(handle);
+    }
+
+    // Esperar todos los requests
+    let results = futures_util::future::join_all(handles).await;
+
+    // Verificar que todos los requests fueron exitosos
+    for result in results {
+        let (status, response_body) = result.unwrap();
+        assert_eq!(status, StatusCode::OK);
+
+        let response: QueryResponse = serde_json::from_str(&response_body).unwrap();
+        assert_eq!(response.result["rows"].as_array().unwrap().len(), 1);
+    }
+}
+
+/// Test de CORS
+#[tokio::test]
+async fn test_cors_headers() {
+    let config = ServerConfig {
+        cors_enabled: true,
+        ..test_config()
+    };
+
+    let state = ServerState::new(config.clone()).await.unwrap();
+    let router = create_server(state, config).unwrap();
+
+    let request = Request::builder()
+        .method(Method::OPTIONS)
+        .uri("/health")
+        .header("Origin", "http://localhost:3000")
+        .header("Access-Control-Request-Method", "GET")
+        .body(Body::empty())
+        .unwrap();
+
+    let response = router.clone().oneshot(request).await.unwrap();
+
+    // Verificar headers CORS
+    assert!(response
+        .headers()
+        .contains_key("Access-Control-Allow-Origin"));
+    assert!(response
+        .headers()
+        .contains_key("Access-Control-Allow-Methods"));
+}
+
+/// Test de estado del servidor
+#[tokio::test]
+async fn test_server_state_management() {
+    let config = test_config();
+    let state = ServerState::new(config).await.unwrap();
+
+    // Verificar que el estado inicial es correcto
+    let executor = state.executor.read().await;
+    assert!(executor.is_some());
+
+    let parser = state.parser.read().await;
+    assert!(parser.is_some());
+
+    assert_eq!(state.sessions.active_sessions_count(), 0);
+
+    let config_state = state.config.read().await;
+    assert_eq!(config_state.bind_address.port(), 8080);
+    assert_eq!(config_state.max_connections, 100);
+}
+
+/// Test de parámetros de consulta
+#[tokio::test]
+async fn test_parameter_extraction() {
+    let (_state, app) = create_test_server().await;
+
+    let query_data = serde_json::json!({
+        "query": "SELECT :dept AS dept, :limit AS limite",
+        "parameters": {
+            "dept": "Ventas",
+            "limit": 10
+        }
+    });
+
+    let body = Body::from(serde_json::to_string(&query_data).unwrap());
+
+    let (status, response_body) =
+        make_request(&app, Method::POST, "/api/v1/query", Some(body)).await;
+
+    assert_eq!(status, StatusCode::OK);
+
+    let response: QueryResponse = serde_json::from_str(&response_body).unwrap();
+    let values = &response.result["rows"][0]["values"];
+    assert_eq!(values[0], serde_json::json!({"Text": "Ventas"}));
+    assert_eq!(values[1], serde_json::json!({"Integer": 10}));
+}
+
+/// Test de configuración personalizada
+#[tokio::test]
+async fn test_custom_config() {
+    let custom_config = ServerConfig {
+        bind_address: "127.0.0.1:9000".parse().unwrap(),
+        max_connections: 50,
+        query_timeout: std::time::Duration::from_secs(60),
+        cors_enabled: true,
+        websocket_enabled: true,
+        dev_mode: true,
+        metrics_enabled: true,
+        database_path: None,
+        forms_directory: None,
+        users_file: None,
+        ..test_config()
+    };
+
+    let state = ServerState::new(custom_config.clone()).await.unwrap();
+    let config_state = state.config.read().await;
+
+    assert_eq!(config_state.bind_address.port(), 9000);
+    assert_eq!(config_state.max_connections, 50);
+    assert!(config_state.cors_enabled);
+    assert!(config_state.websocket_enabled);
+    assert!(config_state.dev_mode);
+    assert!(config_state.metrics_enabled);
+}
+
+/// Helper para levantar un servidor con autenticación JWT en un puerto libre
+async fn spawn_auth_server() -> (String, String) {
+    spawn_auth_server_with(test_config()).await
+}
+
+/// Como `spawn_auth_server`, partiendo de `base`
+async fn spawn_auth_server_with(base: ServerConfig) -> (String, String) {
+    let secret = "test-secret".to_string();
+    let config = ServerConfig {
+        auth_secret: Some(secret.clone()),
+        users: vec![AuthUser {
+            username: "admin".to_string(),
+            password_hash: bcrypt::hash("noctra", 4).unwrap(),
+            roles: vec!["admin".to_string()],
+        }],
+        ..base
+    };
+
+    let state = ServerState::new(config).await.unwrap();
+    let router = create_router(state);
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let base_url = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move {
+        axum::serve(listener, router).await.unwrap();
+    });
+
+    (base_url, secret)
+}
+
+/// Helper para obtener un token vía login
+async fn login(client: &reqwest::Client, base_url: &str) -> String {
+    let response = client
+        .post(format!("{}/api/v1/auth/login", base_url))
+        .json(&serde_json::json!({ "username": "admin", "password": "noctra" }))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), reqwest::StatusCode::OK);
+    let token: TokenResponse = response.json().await.unwrap();
+    assert_eq!(token.token_type, "Bearer");
+    token.access_token
+}
+
+/// Tests de autenticación JWT
+#[tokio::test]
+async fn test_auth_rejects_unauthenticated_requests() {
+    let (base_url, _secret) = spawn_auth_server().await;
+    let client = reqwest::Client::new();
+
+    let query = client
+        .post(format!("{}/api/v1/query", base_url))
+        .json(&serde_json::json!({ "query": "SELECT 1" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(query.status(), reqwest::StatusCode::UNAUTHORIZED);
+
+    let form = client
+        .post(format!("{}/api/v1/form/clientes", base_url))
+        .bearer_auth("no-es-un-jwt")
+        .json(&serde_json::json!({}))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(form.status(), reqwest::StatusCode::UNAUTHORIZED);
+
+    // Las rutas públicas no requieren token
+    let health = client
+        .get(format!("{}/health", base_url))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(health.status(), reqwest::StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_auth_fails_closed_without_secret() {
+    let config = ServerConfig {
+        auth_disabled: false,
+        ..test_config()
+    };
+    let state = ServerState::new(config).await.unwrap();
+    let app = create_router(state.clone());
+    *state.executor.write().await = Some(Arc::new(Executor::new_sqlite_memory().unwrap()));
+
+    let query = Some(Body::from(
+        serde_json::json!({ "query": "SELECT 1" }).to_string(),
+    ));
+    let (status, _) = make_request(&app, Method::POST, "/api/v1/query", query).await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+    let (status, _) = make_request(
+        &app,
+        Method::POST,
+        "/api/v1/form/clientes",
+        Some(Body::from("{}")),
+    )
+    .await;
+    assert_eq!(status, StatusCode::UNAUTHORIZED);
+
+    // Las rutas públicas siguen respondiendo
+    let (status, _) = make_request(&app, Method::GET, "/health", None).await;
+    assert_eq!(status, StatusCode::OK);
+
+    // Con auth_disabled se sirve sin token
+    state.config.write().await.auth_disabled = true;
+    let query = Some(Body::from(
+        serde_json::json!({ "query": "SELECT 1" }).to_string(),
+    ));
+    let (status, _) = make_request(&app, Method::POST, "/api/v1/query", query).await;
+    assert_eq!(status, StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_auth_login_and_authenticated_query() {
+    let (base_url, _secret) = spawn_auth_server().await;
+    let client = reqwest::Client::new();
+
+    let bad_login = client
+        .post(format!("{}/api/v1/auth/login", base_url))
+        .json(&serde_json::json!({ "username": "admin", "password": "incorrecta" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(bad_login.status(), reqwest::StatusCode::UNAUTHORIZED);
+
+    let unknown_user = client
+        .post(format!("{}/api/v1/auth/login", base_url))
+        .json(&serde_json::json!({ "username": "nadie", "password": "noctra" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(unknown_user.status(), reqwest::StatusCode::UNAUTHORIZED);
+
+    let token = login(&client, &base_url).await;
+
+    let response = client
+        .post(format!("{}/api/v1/query", base_url))
+        .bearer_auth(&token)
+        .json(&serde_json::json!({ "query": "SELECT 1" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), reqwest::StatusCode::OK);
+
+    let refreshed = client
+        .post(format!("{}/api/v1/auth/refresh", base_url))
+        .bearer_auth(&token)
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(refreshed.status(), reqwest::StatusCode::OK);
+    let refreshed: TokenResponse = refreshed.json().await.unwrap();
+
+    let claims = verify_token(&refreshed.access_token, "test-secret").unwrap();
+    assert_eq!(claims.sub, "admin");
+    assert!(claims.has_role("admin"));
+}
+
+#[tokio::test]
+async fn test_auth_rejects_expired_token() {
+    let (base_url, secret) = spawn_auth_server().await;
+    let client = reqwest::Client::new();
+
+    let mut claims = Claims::new(
+        "admin",
+        vec!["admin".to_string()],
+        std::time::Duration::ZERO,
+    );
+    claims.iat -= 120;
+    claims.exp -= 60;
+    let expired = issue_token(&claims, &secret).unwrap();
+
+    let response = client
+        .post(format!("{}/api/v1/query", base_url))
+        .bearer_auth(&expired)
+        .json(&serde_json::json!({ "query": "SELECT 1" }))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), reqwest::StatusCode::UNAUTHORIZED);
+
+    let error: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(error["message"], "Token expirado");
+}
+
+#[tokio::test]
+async fn test_public_read_only_allows_anonymous_reads_only() {
+    let (base_url, _secret) = spawn_auth_server_with(ServerConfig {
+        public_read_only: true,
+        ..test_config()
+    })
+    .await;
+    let client = reqwest::Client::new();
+    let query = |sql: &str, token: Option<&str>| {
+        let mut request = client
+            .post(format!("{}/api/v1/query", base_url))
+            .json(&serde_json::json!({ "query": sql }));
+        if let Some(token) = token {
+            request = request.bearer_auth(token);
+        }
+        request.send()
+    };
+
+    assert_eq!(
+        query("SELECT 1", None).await.unwrap().status(),
+        reqwest::StatusCode::OK
+    );
+    for sql in [
+        "CREATE TEMP TABLE t (id INTEGER)",
+        "WITH x AS (SELECT 1) INSERT INTO t SELECT * FROM x",
+        "PRAGMA user_version = 5",
+        "GRANT SELECT ON t TO alguien",
+        "SELECT load_extension('x')",
+    ] {
+        let response = query(sql, None).await.unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::FORBIDDEN, "{}", sql);
+    }
+
+    // Con token se conserva la escritura; el resto de rutas sigue exigiéndolo
+    let token = login(&client, &base_url).await;
+    let response = query("CREATE TEMP TABLE t (id INTEGER)", Some(&token))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), reqwest::StatusCode::OK);
+    let form = client
+        .post(format!("{}/api/v1/form/clientes", base_url))
+        .json(&serde_json::json!({}))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(form.status(), reqwest::StatusCode::UNAUTHORIZED);
+}
+
+/// Tests del registro de consultas lentas
+#[test]
+fn test_sql_fingerprint_hides_literals() {
+    assert_eq!(
+        sql_fingerprint("SELECT * FROM clientes2\n WHERE nombre = 'O''Brien' AND saldo > -10.5"),
+        "SELECT * FROM clientes2 WHERE nombre = ? AND saldo > -?"
+    );
+    assert_eq!(sql_fingerprint("SELECT 1"), "SELECT ?");
+}
+
+/// Helper para levantar un router con una ruta de consulta lenta
+async fn spawn_slow_query_server(metrics_enabled: bool) -> String {
+    let config = ServerConfig {
+        metrics_enabled,
+        slow_query_threshold_ms: 20,
+        slow_query_log_size: 2,
+        ..test_config()
+    };
+    let state = ServerState::new(config).await.unwrap();
+
+    // Duerme si la consulta lo pide, para superar el umbral de forma deliberada
+    let slow_query = |axum::Json(request): axum::Json<serde_json::Value>| async move {
+        if request["query"].as_str().unwrap_or("").contains("pg_sleep") {
+            tokio::time::sleep(std::time::Duration::from_millis(50)).await;
+        }
+        StatusCode::OK
+    };
+    let router = axum::Router::new()
+        .route("/api/v1/query", axum::routing::post(slow_query))
+        .layer(SlowQueryLayer::new(
+            state.config.clone(),
+            state.slow_queries.clone(),
+        ))
+        .route(
+            "/api/v1/metrics/slow-queries",
+            axum::routing::get(slow_queries_handler),
+        )
+        .with_state(state);
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let base_url = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move {
+        axum::serve(
+            listener,
+            router.into_make_service_with_connect_info::<std::net::SocketAddr>(),
+        )
+        .await
+        .unwrap();
+    });
+
+    base_url
+}
+
+#[tokio::test]
+async fn test_slow_query_log_endpoint() {
+    let base_url = spawn_slow_query_server(true).await;
+    let client = reqwest::Client::new();
+
+    for query in [
+        "SELECT 1",
+        "SELECT pg_sleep(1) FROM empleados WHERE nombre = 'Ana' AND id = 42",
+    ] {
+        let response = client
+            .post(format!("{}/api/v1/query", base_url))
+            .json(&serde_json::json!({ "query": query }))
+            .send()
+            .await
+            .unwrap();
+        assert_eq!(response.status(), reqwest::StatusCode::OK);
+    }
+
+    let response = client
+        .get(format!("{}/api/v1/metrics/slow-queries", base_url))
+        .send()
+        .await
+        .unwrap();
+    assert_eq!(response.status(), reqwest::StatusCode::OK);
+
+    // Sólo la consulta lenta, sin los valores literales
+    let entries: Vec<SlowQueryEntry> = response.json().await.unwrap();
+    assert_eq!(entries.len(), 1);
+    assert_eq!(
+        entries[0].sql_fingerprint,
+        "SELECT pg_sleep(?) FROM empleados WHERE nombre = ? AND id = ?"
+    );
+    assert!(entries[0].duration_ms >= 50);
+    assert_eq!(entries[0].client_ip.to_string(), "127.0.0.1");
+}
+
+#[tokio::test]
+async fn test_slow_query_log_requires_metrics() {
+    let base_url = spawn_slow_query_server(false).await;
+
+    let response = reqwest::get(format!("{}/api/v1/metrics/slow-queries", base_url))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), reqwest::StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_slow_query_log_requires_token() {
+    let (base_url, _secret) = spawn_auth_server_with(ServerConfig {
+        metrics_enabled: true,
+        ..test_config()
+    })
+    .await;
+    let client = reqwest::Client::new();
+    let url = format!("{}/api/v1/metrics/slow-queries", base_url);
+
+    let response = client.get(&url).send().await.unwrap();
+    assert_eq!(response.status(), reqwest::StatusCode::UNAUTHORIZED);
+
+    let token = login(&client, &base_url).await;
+    let response = client.get(&url).bearer_auth(token).send().await.unwrap();
+    assert_eq!(response.status(), reqwest::StatusCode::OK);
+}
+
+#[tokio::test]
+async fn test_query_uses_session_from_header() {
+    let (state, app) = create_test_server().await;
+    *state.executor.write().await = Some(Arc::new(Executor::new_sqlite_memory().unwrap()));
+
+    // Dos sesiones con el mismo LET y valores distintos
+    let mut ids = Vec::new();
+    for limite in [10_i64, 20] {
+        let handle = state.sessions.create().unwrap();
+        let mut session = handle.lock().unwrap();
+        session.set_variable("limite", limite);
+        ids.push(session.id().to_string());
+    }
+
+    let query = |session_id: Option<&str>, sql: &str| {
+        let mut request = Request::builder()
+            .method(Method::POST)
+            .uri("/api/v1/query")
+            .header("Content-Type", "application/json");
+        if let Some(id) = session_id {
+            request = request.header(SESSION_HEADER, id);
+        }
+        let request = request
+            .body(Body::from(serde_json::json!({ "query": sql }).to_string()))
+            .unwrap();
+        app.clone().oneshot(request)
+    };
+    for (id, limite) in ids.iter().zip([10, 20]) {
+        let response = query(Some(id), "SELECT #limite AS limite").await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()[SESSION_HEADER], id.as_str());
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let body: serde_json::Value = serde_json::from_slice(&body).unwrap();
+        assert_eq!(
+            body["result"]["rows"][0]["values"][0],
+            serde_json::json!({"Integer": limite})
+        );
+    }
+
+    // Sin sesión se usa una efímera que no queda en el gestor; una sesión
+    // eliminada ya no se encuentra
+    let response = query(None, "SELECT 1").await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert!(response.headers().get(SESSION_HEADER).is_none());
+    assert_eq!(state.sessions.active_sessions_count(), 2);
+    let response = query(None, "SELECT #limite").await.unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    // El plan también usa la sesión de la petición
+    let plan = |session_id: Option<&str>| {
+        let mut request = Request::builder()
+            .method(Method::POST)
+            .uri("/api/v1/query/plan")
+            .header("Content-Type", "application/json");
+        if let Some(id) = session_id {
+            request = request.header(SESSION_HEADER, id);
+        }
+        let request = request
+            .body(Body::from(
+                serde_json::json!({ "query": "SELECT #limite AS limite" }).to_string(),
+            ))
+            .unwrap();
+        app.clone().oneshot(request)
+    };
+    assert_eq!(plan(Some(&ids[0])).await.unwrap().status(), StatusCode::OK);
+    assert_eq!(plan(None).await.unwrap().status(), StatusCode::BAD_REQUEST);
+
+    state.sessions.remove_session(&ids[0]);
+    assert_eq!(
+        query(Some(&ids[0]), "SELECT 1").await.unwrap().status(),
+        StatusCode::NOT_FOUND
+    );
+    let (status, _) = make_request(
+        &app,
+        Method::GET,
+        &format!("/api/v1/session/{}", ids[0]),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND);
+}
+
+/// Fuente que anota los archivos registrados y las sesiones liberadas
+#[derive(Debug, Default)]
+struct SessionFilesSource {
+    events: Arc<Mutex<Vec<String>>>,
+}
+
+impl DataSource for SessionFilesSource {
+    fn query(&self, _sql: &str, _parameters: &Parameters) -> noctra_core::Result<ResultSet> {
+        Ok(ResultSet::empty())
+    }
+
+    fn register_session_file(
+        &self,
+        session_id: &str,
+        path: &str,
+        table: &str,
+    ) -> noctra_core::Result<()> {
+        self.events
+            .lock()
+            .unwrap()
+            .push(format!("use {} {} {}", session_id, path, table));
+        Ok(())
+    }
+
+    fn release_session(&self, session_id: &str) -> noctra_core::Result<()> {
+        self.events
+            .lock()
+            .unwrap()
+            .push(format!("release {}", session_id));
+        Ok(())
+    }
+
+    fn schema(&self) -> noctra_core::Result<Vec<TableInfo>> {
+        Ok(Vec::new())
+    }
+
+    fn source_type(&self) -> SourceType {
+        SourceType::Memory { capacity: 0 }
+    }
+
+    fn name(&self) -> &str {
+        "archivos"
+    }
+}
+
+#[tokio::test]
+async fn test_use_registers_file_for_the_session_until_it_ends() {
+    let (state, app) = create_test_server().await;
+    let source = SessionFilesSource::default();
+    let events = source.events.clone();
+    let mut executor = Executor::new_sqlite_memory().unwrap();
+    executor
+        .register_source("archivos".to_string(), Box::new(source))
+        .unwrap();
+    *state.executor.write().await = Some(Arc::new(executor));
+    let session_id = state
+        .sessions
+        .create()
+        .unwrap()
+        .lock()
+        .unwrap()
+        .id()
+        .to_string();
+
+    let query = |sql: &str| {
+        Request::builder()
+            .method(Method::POST)
+            .uri("/api/v1/query")
+            .header("Content-Type", "application/json")
+            .header(SESSION_HEADER, session_id.as_str())
+            .body(Body::from(serde_json::json!({ "query": sql }).to_string()))
+            .unwrap()
+    };
+    let response = app
+        .clone()
+        .oneshot(query("USE 'ventas.csv' AS ventas"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        *events.lock().unwrap(),
+        vec![format!("use {} ventas.csv ventas", session_id)]
+    );
+
+    // Sin AS no hay nombre de tabla
+    let response = app
+        .clone()
+        .oneshot(query("USE 'ventas.csv'"))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+
+    // Al terminar la sesión la fuente borra lo que registró
+    let (status, _) = make_request(
+        &app,
+        Method::DELETE,
+        &format!("/api/v1/session/{}", session_id),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert_eq!(
+        events.lock().unwrap().last().unwrap(),
+        &format!("release {}", session_id)
+    );
+}
+
+#[tokio::test]
+async fn test_http_sessions_are_isolated() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = ServerConfig {
+        database_url: dir
+            .path()
+            .join("sesiones.db")
+            .to_string_lossy()
+            .into_owned(),
+        max_connections: 4,
+        ..test_config()
+    };
+    let state = ServerState::new(config).await.unwrap();
+    let app = create_router(state.clone());
+
+    let mut ids = Vec::new();
+    for _ in 0..2 {
+        let (status, body) = make_request(&app, Method::POST, "/api/v1/session", None).await;
+        assert_eq!(status, StatusCode::OK);
+        let body: serde_json::Value = serde_json::from_str(&body).unwrap();
+        ids.push(body["session_id"].as_str().unwrap().to_string());
+    }
+    let query = |session_id: &str, sql: &str| {
+        let request = Request::builder()
+            .method(Method::POST)
+            .uri("/api/v1/query")
+            .header("Content-Type", "application/json")
+            .header(SESSION_HEADER, session_id)
+            .body(Body::from(serde_json::json!({ "query": sql }).to_string()))
+            .unwrap();
+        let (app, sql) = (app.clone(), sql.to_string());
+        async move {
+            let response = app.oneshot(request).await.unwrap();
+            assert_eq!(response.status(), StatusCode::OK, "{}", sql);
+            let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+                .await
+                .unwrap();
+            serde_json::from_slice::<serde_json::Value>(&body).unwrap()
+        }
+    };
+    let count = |session_id: &str| {
+        let query = query(session_id, "SELECT COUNT(*) FROM t");
+        async move { query.await["result"]["rows"][0]["values"][0].clone() }
+    };
+
+    query(&ids[0], "CREATE TABLE t (id INTEGER)").await;
+
+    // La transacción de una sesión no se ve desde la otra hasta el COMMIT
+    query(&ids[0], "BEGIN").await;
+    query(&ids[0], "INSERT INTO t VALUES (1)").await;
+    assert_eq!(count(&ids[0]).await, serde_json::json!({"Integer": 1}));
+    assert_eq!(count(&ids[1]).await, serde_json::json!({"Integer": 0}));
+    query(&ids[0], "COMMIT").await;
+    assert_eq!(count(&ids[1]).await, serde_json::json!({"Integer": 1}));
+}
+
+#[tokio::test]
+async fn test_removed_session_releases_its_transaction() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = ServerConfig {
+        database_url: dir.path().join("liberar.db").to_string_lossy().into_owned(),
+        max_connections: 4,
+        ..test_config()
+    };
+    let state = ServerState::new(config).await.unwrap();
+    let app = create_router(state.clone());
+    let executor = state.get_executor().await.unwrap();
+    let query = |session_id: Option<&str>, sql: &str| {
+        let mut request = Request::builder()
+            .method(Method::POST)
+            .uri("/api/v1/query")
+            .header("Content-Type", "application/json");
+        if let Some(id) = session_id {
+            request = request.header(SESSION_HEADER, id);
+        }
+        let request = request
+            .body(Body::from(serde_json::json!({ "query": sql }).to_string()))
+            .unwrap();
+        let app = app.clone();
+        async move { app.oneshot(request).await.unwrap().status() }
+    };
+    let all_available = || {
+        let status = executor.pool_status().unwrap();
+        status.available == status.size
+    };
+    assert_eq!(
+        query(None, "CREATE TABLE t (id INTEGER)").await,
+        StatusCode::OK
+    );
+
+    // Sesión eliminada con la transacción abierta
+    let handle = state.sessions.create().unwrap();
+    let id = handle.lock().unwrap().id().to_string();
+    assert_eq!(query(Some(&id), "BEGIN").await, StatusCode::OK);
+    assert_eq!(
+        query(Some(&id), "INSERT INTO t VALUES (1)").await,
+        StatusCode::OK
+    );
+    assert!(!all_available());
+    let (status, _) = make_request(
+        &app,
+        Method::DELETE,
+        &format!("/api/v1/session/{}", id),
+        None,
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(all_available());
+
+    // Una petición anónima no deja la conexión reservada
+    assert_eq!(query(None, "BEGIN").await, StatusCode::OK);
+    assert!(all_available());
+
+    let session = Session::new();
+    let count = executor
+        .execute_rql(
+            &session,
+            noctra_core::RqlQuery::sql("SELECT COUNT(*) FROM t"),
+        )
+        .unwrap();
+    assert_eq!(count.rows[0].values[0], noctra_core::Value::Integer(0));
+}
+
+#[tokio::test]
+async fn test_cookie_session_persists_between_requests() {
+    let dir = tempfile::tempdir().unwrap();
+    let store = FileSessionStore::new(dir.path()).unwrap();
+    let sessions = Arc::new(SessionManager::new_persistent(Box::new(
+        FileSessionStore::new(dir.path()).unwrap(),
+    )));
+    let session_id = sessions.create().unwrap().lock().unwrap().id().to_string();
+    let session_cookie = format!("noctra_session={}", session_id);
+
+    // POST guarda una variable en la sesión; GET la devuelve
+    let set_var = |axum::Extension(session): axum::Extension<SessionHandle>, body: String| async move {
+        session.lock().unwrap().set_variable("pais", body);
+        StatusCode::OK
+    };
+    let get_var = |axum::Extension(session): axum::Extension<SessionHandle>| async move {
+        let session = session.lock().unwrap();
+        session
+            .get_variable("pais")
+            .map(|v| v.to_string())
+            .unwrap_or_default()
+    };
+    let app = axum::Router::new()
+        .route("/vars", axum::routing::post(set_var).get(get_var))
+        .layer(CookieSessionLayer::new(sessions.clone()));
+
+    let request = Request::builder()
+        .method(Method::POST)
+        .uri("/vars")
+        .header("Cookie", session_cookie.as_str())
+        .body(Body::from("AR"))
+        .unwrap();
+    let response = app.clone().oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+
+    // El cambio quedó guardado en el almacén
+    let stored: Session = store.load(&session_id).unwrap().unwrap();
+    assert_eq!(stored.get_variable("pais").unwrap().to_string(), "AR");
+
+    // Una petición que no cambia la sesión no vuelve a escribirla
+    store.delete(&session_id).unwrap();
+    let request = Request::builder()
+        .method(Method::GET)
+        .uri("/vars")
+        .header("Cookie", session_cookie.as_str())
+        .body(Body::empty())
+        .unwrap();
+    let response = app.clone().oneshot(request).await.unwrap();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    assert_eq!(body.as_ref(), b"AR");
+    assert!(store.load(&session_id).unwrap().is_none());
+
+    // Sin cookie la petición usa una sesión efímera, sin la variable
+    let request = Request::builder().uri("/vars").body(Body::empty()).unwrap();
+    let response = app.clone().oneshot(request).await.unwrap();
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    assert!(body.is_empty());
+
+    // Una sesión que no existe es un 404
+    let request = Request::builder()
+        .uri("/vars")
+        .header("Cookie", "noctra_session=no-existe")
+        .body(Body::empty())
+        .unwrap();
+    let response = app.oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::NOT_FOUND);
+}
+
+#[tokio::test]
+async fn test_session_cookie_survives_restart() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = ServerConfig {
+        session_dir: Some(dir.path().to_path_buf()),
+        ..test_config()
+    };
+    let app = create_router(ServerState::new(config.clone()).await.unwrap());
+
+    let request = Request::builder()
+        .method(Method::POST)
+        .uri("/api/v1/session")
+        .body(Body::empty())
+        .unwrap();
+    let response = app.oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let cookie = response.headers()["set-cookie"]
+        .to_str()
+        .unwrap()
+        .to_string();
+    assert!(cookie.starts_with("noctra_session="));
+    assert!(cookie.contains("; HttpOnly"));
+    // Por HTTP plano la cookie no puede ser Secure
+    assert!(!cookie.contains("Secure"));
+    let session_cookie = cookie.split(';').next().unwrap().to_string();
+    let session_id = session_cookie
+        .trim_start_matches("noctra_session=")
+        .to_string();
+
+    // Tras reiniciar, la cookie sigue identificando la sesión guardada
+    let app = create_router(ServerState::new(config).await.unwrap());
+    let request = Request::builder()
+        .method(Method::POST)
+        .uri("/api/v1/query")
+        .header("Content-Type", "application/json")
+        .header("Cookie", session_cookie.as_str())
+        .body(Body::from(r#"{"query": "SELECT 1"}"#))
+        .unwrap();
+    let response = app.oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(
+        response.headers()[SESSION_HEADER].to_str().unwrap(),
+        session_id
+    );
+}
+
+#[tokio::test]
+async fn test_session_cookie_is_secure_when_configured() {
+    let config = ServerConfig {
+        secure_cookies: true,
+        ..test_config()
+    };
+    let app = create_router(ServerState::new(config).await.unwrap());
+
+    let request = Request::builder()
+        .method(Method::POST)
+        .uri("/api/v1/session")
+        .body(Body::empty())
+        .unwrap();
+    let response = app.oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let cookie = response.headers()["set-cookie"].to_str().unwrap();
+    assert!(cookie.starts_with("noctra_session="));
+    assert!(cookie.contains("; HttpOnly; Secure; SameSite=Lax"));
+}
+
+#[tokio::test]
+async fn test_openapi_spec() {
+    let (_state, app) = create_test_server().await;
+
+    let (status, body) = make_request(&app, Method::GET, "/api/v1/openapi.json", None).await;
+    assert_eq!(status, StatusCode::OK);
+
+    let spec: serde_json::Value = serde_json::from_str(&body).unwrap();
+    assert!(spec["openapi"].as_str().unwrap().starts_with("3.1"));
+
+    let paths = spec["paths"].as_object().unwrap();
+    assert!(paths.len() >= 3);
+    for path in ["/api/v1/query", "/api/v1/form/{name}", "/status"] {
+        assert!(
+            paths.contains_key(path),
+            "falta {} en la especificación",
+            path
+        );
+    }
+    assert!(paths["/api/v1/query"]["post"]["requestBody"].is_object());
+
+    let schemas = spec["components"]["schemas"].as_object().unwrap();
+    for schema in [
+        "QueryRequest",
+        "QueryResponse",
+        "FormRequest",
+        "FormResponse",
+        "ServerStatus",
+        "ServerError",
+    ] {
+        assert!(schemas.contains_key(schema), "falta el esquema {}", schema);
+    }
+
+    // Cada operación de la especificación existe en el router: un 404 de
+    // una ruta sin montar llega sin cuerpo, el de un handler trae el error
+    for (path, item) in paths {
+        let uri = path.replace(['{', '}'], "");
+        for method in item.as_object().unwrap().keys() {
+            let method = Method::from_bytes(method.to_uppercase().as_bytes()).unwrap();
+            let (status, body) =
+                make_request(&app, method.clone(), &uri, Some(Body::from("{}"))).await;
+            assert_ne!(
+                status,
+                StatusCode::METHOD_NOT_ALLOWED,
+                "{} {} no está montada",
+                method,
+                path
+            );
+            assert!(
+                status != StatusCode::NOT_FOUND || !body.is_empty(),
+                "{} {} no está montada",
+                method,
+                path
+            );
+        }
+    }
+
+    // El explorador lee la especificación y permite probar las operaciones, sin CDN
+    let (status, html) = make_request(&app, Method::GET, "/api/v1/docs", None).await;
+    assert_eq!(status, StatusCode::OK);
+    assert!(html.contains("/api/v1/openapi.json"));
+    assert!(html.contains("Probar"));
+    assert!(!html.contains("https://"));
+}
+
+#[tokio::test]
+async fn test_query_pagination_covers_every_row_once() {
+    let executor = Executor::new_sqlite_memory().unwrap();
+    let session = Session::new();
+    executor
+        .execute_statement(&session, "CREATE TABLE numeros (id INTEGER PRIMARY KEY)")
+        .unwrap();
+    executor
+        .execute_statement(
+            &session,
+            "WITH RECURSIVE n(i) AS (SELECT 1 UNION ALL SELECT i + 1 FROM n WHERE i < 500) \
+             INSERT INTO numeros SELECT i FROM n",
+        )
+        .unwrap();
+    let handler = QueryHandler::new(Arc::new(executor), Arc::new(RqlParser::new()));
+
+    let mut seen = std::collections::HashSet::new();
+    for page in 1..=5 {
+        let request: QueryRequest = serde_json::from_value(serde_json::json!({
+            "query": "SELECT id FROM numeros ORDER BY id",
+            "page": page,
+            "page_size": 100
+        }))
+        .unwrap();
+        let response = handler.handle_query(&session, &request, true).unwrap();
+
+        assert_eq!(response.result.row_count(), 100);
+        assert_eq!(response.total_count, Some(500));
+        assert_eq!(response.total_pages, Some(5));
+        assert_eq!((response.page, response.page_size), (Some(page), Some(100)));
+        for row in &response.result.rows {
+            assert!(
+                seen.insert(row.values[0].to_string()),
+                "fila repetida: {:?}",
+                row
+            );
+        }
+    }
+    assert_eq!(seen.len(), 500);
+
+    // Después de la última página no hay filas; sin count no hay COUNT(*)
+    let request: QueryRequest = serde_json::from_value(serde_json::json!({
+        "query": "SELECT id FROM numeros;",
+        "page": 6,
+        "page_size": 100
+    }))
+    .unwrap();
+    let response = handler.handle_query(&session, &request, false).unwrap();
+    assert!(response.result.is_empty());
+    assert_eq!(response.total_count, None);
+    assert_eq!(response.total_pages, None);
+
+    // page_size se limita a MAX_PAGE_SIZE y por defecto es DEFAULT_PAGE_SIZE
+    let request: QueryRequest = serde_json::from_value(serde_json::json!({
+        "query": "SELECT id FROM numeros",
+        "page_size": 50_000
+    }))
+    .unwrap();
+    assert_eq!(request.pagination().unwrap().page_size, MAX_PAGE_SIZE);
+    let request: QueryRequest =
+        serde_json::from_value(serde_json::json!({ "query": "SELECT 1", "page": 2 })).unwrap();
+    assert_eq!(
+        request.pagination().unwrap().offset(),
+        u64::from(DEFAULT_PAGE_SIZE)
+    );
+}
+
+#[tokio::test]
+async fn test_query_cursor_fetches_in_blocks() {
+    let executor = Executor::new_sqlite_memory().unwrap();
+    let session = Session::new();
+    executor
+        .execute_statement(
+            &session,
+            "CREATE TABLE numeros AS \
+             WITH RECURSIVE n(i) AS (SELECT 1 UNION ALL SELECT i + 1 FROM n WHERE i < 500) \
+             SELECT i AS id FROM n",
+        )
+        .unwrap();
+    let handler = QueryHandler::new(Arc::new(executor), Arc::new(RqlParser::new()));
+    let (cliente_1, cliente_2) = (Session::with_id("cliente-1"), Session::with_id("cliente-2"));
+
+    let request: QueryRequest = serde_json::from_value(serde_json::json!({
+        "query": "SELECT id FROM numeros ORDER BY id",
+        "fetch_size": 200
+    }))
+    .unwrap();
+    let first = handler.handle_query(&cliente_1, &request, false).unwrap();
+    assert_eq!(first.result.row_count(), 200);
+    let cursor = first.cursor.expect("quedan filas");
+
+    // El session_id del cuerpo no da acceso a los cursores de otra sesión
+    let next: QueryRequest = serde_json::from_value(serde_json::json!({
+        "query": "",
+        "session_id": "cliente-1",
+        "cursor": cursor,
+        "fetch_size": 200
+    }))
+    .unwrap();
+    let error = handler.handle_query(&cliente_2, &next, false).unwrap_err();
+    assert_eq!(error.error_code(), "NOCTRA_E030");
+
+    let second = handler.handle_query(&cliente_1, &next, false).unwrap();
+    assert_eq!(second.result.row_count(), 200);
+    assert_eq!(second.cursor, Some(cursor));
+
+    let last = handler.handle_query(&cliente_1, &next, false).unwrap();
+    assert_eq!(last.result.row_count(), 100);
+    assert_eq!(
+        last.result.rows[99].values[0],
+        noctra_core::Value::Integer(500)
+    );
+    assert_eq!(last.cursor, None);
+
+    // Agotado, el cursor ya no existe
+    assert!(handler.handle_query(&cliente_1, &next, false).is_err());
+}
+
+#[tokio::test]
+async fn test_query_cursor_is_not_shared_between_anonymous_requests() {
+    let (_state, app) = create_test_server().await;
+
+    let query = serde_json::json!({
+        "query": "WITH RECURSIVE n(i) AS (SELECT 1 UNION ALL SELECT i + 1 FROM n WHERE i < 10) \
+                  SELECT i FROM n",
+        "fetch_size": 3
+    });
+    let (status, body) = make_request(
+        &app,
+        Method::POST,
+        "/api/v1/query",
+        Some(Body::from(query.to_string())),
+    )
+    .await;
+    assert_eq!(status, StatusCode::OK, "{}", body);
+    let first: serde_json::Value = serde_json::from_str(&body).unwrap();
+    let cursor = first["cursor"].as_u64().expect("quedan filas");
+
+    // Otra petición anónima tiene su propia sesión y no ve el cursor
+    let next = serde_json::json!({ "query": "", "cursor": cursor, "fetch_size": 3 });
+    let (status, body) = make_request(
+        &app,
+        Method::POST,
+        "/api/v1/query",
+        Some(Body::from(next.to_string())),
+    )
+    .await;
+    assert_eq!(status, StatusCode::NOT_FOUND, "{}", body);
+}
+
+/// Struct para respuestas de test
+#[derive(Debug, serde::Deserialize)]
+struct QueryResponse {
+    pub result: serde_json::Value,
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct ErrorResponse {
+    pub status_code: u16,
+    pub message: String,
+}
+
+#[derive(Debug, serde::Deserialize)]
+struct FormResponse {
+    pub success: bool,
+    pub message: String,
+    pub validation_errors: Vec<serde_json::Value>,
+}
+
+/// Servidor con `/api/v1/query/stream` sobre una tabla de 500 filas
+async fn spawn_streaming_server() -> String {
+    let executor = Executor::new_sqlite_memory().unwrap();
+    let session = Session::new();
+    executor
+        .execute_statement(
+            &session,
+            "CREATE TABLE numeros (id INTEGER PRIMARY KEY, nombre TEXT)",
+        )
+        .unwrap();
+    executor
+        .execute_statement(
+            &session,
+            "WITH RECURSIVE n(i) AS (SELECT 1 UNION ALL SELECT i + 1 FROM n WHERE i < 500) \
+             INSERT INTO numeros SELECT i, 'fila ' || i FROM n",
+        )
+        .unwrap();
+    let handler = Arc::new(StreamingQueryHandler::new(Arc::new(executor)));
+
+    let stream_query = move |axum::Json(request): axum::Json<QueryRequest>| {
+        let handler = Arc::clone(&handler);
+        async move {
+            handler
+                .handle(Arc::new(Mutex::new(Session::new())), &request)
+                .await
+        }
+    };
+    let router =
+        axum::Router::new().route("/api/v1/query/stream", axum::routing::post(stream_query));
+
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let base_url = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move {
+        axum::serve(listener, router).await.unwrap();
+    });
+
+    base_url
+}
+
+/// Leer el cuerpo NDJSON chunk a chunk y devolver sus líneas
+async fn read_ndjson_lines(mut response: reqwest::Response) -> Vec<serde_json::Value> {
+    let mut body = Vec::new();
+    while let Some(chunk) = response.chunk().await.unwrap() {
+        body.extend_from_slice(&chunk);
+    }
+
+    String::from_utf8(body)
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect()
+}
+
+#[tokio::test]
+async fn test_query_stream_returns_ndjson_rows() {
+    let base_url = spawn_streaming_server().await;
+    let response = reqwest::Client::new()
+        .post(format!("{}/api/v1/query/stream", base_url))
+        .json(&serde_json::json!({ "query": "SELECT id, nombre FROM numeros ORDER BY id" }))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), reqwest::StatusCode::OK);
+    assert_eq!(
+        response.headers()["content-type"].to_str().unwrap(),
+        NDJSON_CONTENT_TYPE
+    );
+
+    let lines = read_ndjson_lines(response).await;
+    assert_eq!(lines.len(), 501);
+
+    let rows = &lines[..500];
+    for (i, row) in rows.iter().enumerate() {
+        let row: noctra_core::Row = serde_json::from_value(row.clone()).unwrap();
+        assert_eq!(row.values[0], noctra_core::Value::Integer(i as i64 + 1));
+        assert_eq!(
+            row.values[1],
+            noctra_core::Value::Text(format!("fila {}", i + 1))
+        );
+    }
+
+    let meta = &lines[500]["__meta"];
+    assert_eq!(meta["rows"], 500);
+    assert!(meta["duration_ms"].is_u64());
+}
+
+#[tokio::test]
+async fn test_query_stream_invalid_query_returns_error_status() {
+    let base_url = spawn_streaming_server().await;
+    let response = reqwest::Client::new()
+        .post(format!("{}/api/v1/query/stream", base_url))
+        .json(&serde_json::json!({ "query": "SELECT * FROM no_existe" }))
+        .send()
+        .await
+        .unwrap();
+
+    assert_eq!(response.status(), reqwest::StatusCode::NOT_FOUND);
+    let error: serde_json::Value = response.json().await.unwrap();
+    assert_eq!(error["code"], "NOCTRA_E030");
+    assert_eq!(error["kind"], "not_found");
+}
+
+#[tokio::test]
+async fn test_query_accept_ndjson_streams() {
+    let (_state, app) = create_test_server().await;
+    let request = Request::builder()
+        .method(Method::POST)
+        .uri("/api/v1/query")
+        .header("Content-Type", "application/json")
+        .header("Accept", "application/x-ndjson, application/json;q=0.5")
+        .body(Body::from(
+            r#"{"query": "SELECT 1 AS n UNION ALL SELECT 2"}"#,
+        ))
+        .unwrap();
+
+    let response = app.oneshot(request).await.unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    assert_eq!(response.headers()["content-type"], NDJSON_CONTENT_TYPE);
+
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let lines: Vec<serde_json::Value> = String::from_utf8(body.to_vec())
+        .unwrap()
+        .lines()
+        .map(|line| serde_json::from_str(line).unwrap())
+        .collect();
+    assert_eq!(lines.len(), 3);
+    assert_eq!(lines[2]["__meta"]["rows"], 2);
+}
+
+#[tokio::test]
+async fn test_query_stream_uses_session_from_header() {
+    let (state, app) = create_test_server().await;
+    *state.executor.write().await = Some(Arc::new(Executor::new_sqlite_memory().unwrap()));
+    let handle = state.sessions.create().unwrap();
+    let id = {
+        let mut session = handle.lock().unwrap();
+        session.set_variable("limite", 10_i64);
+        session.id().to_string()
+    };
+
+    // Las dos vías NDJSON leen el LET de la sesión indicada
+    for (uri, accept) in [
+        ("/api/v1/query/stream", "application/json"),
+        ("/api/v1/query", NDJSON_CONTENT_TYPE),
+    ] {
+        let request = Request::builder()
+            .method(Method::POST)
+            .uri(uri)
+            .header("Content-Type", "application/json")
+            .header("Accept", accept)
+            .header(SESSION_HEADER, id.as_str())
+            .body(Body::from(r#"{"query": "SELECT #limite AS limite"}"#))
+            .unwrap();
+
+        let response = app.clone().oneshot(request).await.unwrap();
+        assert_eq!(response.status(), StatusCode::OK);
+        assert_eq!(response.headers()["content-type"], NDJSON_CONTENT_TYPE);
+        assert_eq!(response.headers()[SESSION_HEADER], id.as_str());
+        let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+            .await
+            .unwrap();
+        let first = String::from_utf8(body.to_vec())
+            .unwrap()
+            .lines()
+            .next()
+            .unwrap()
+            .to_string();
+        let row: serde_json::Value = serde_json::from_str(&first).unwrap();
+        assert_eq!(row["values"][0], serde_json::json!({"Integer": 10}));
+    }
+}
+
+#[tokio::test]
+async fn test_pooled_executor_serves_concurrent_writes() {
+    let dir = tempfile::tempdir().unwrap();
+    let config = ServerConfig {
+        database_url: format!("sqlite://{}", dir.path().join("pool.db").display()),
+        max_connections: 8,
+        metrics_enabled: true,
+        ..test_config()
+    };
+    let state = ServerState::new(config).await.unwrap();
+    let router = create_router(state);
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let base_url = format!("http://{}", listener.local_addr().unwrap());
+    tokio::spawn(async move {
+        axum::serve(listener, router).await.unwrap();
+    });
+
+    let client = reqwest::Client::new();
+    let query = |sql: String| {
+        client
+            .post(format!("{}/api/v1/query", base_url))
+            .json(&serde_json::json!({ "query": sql }))
+            .send()
+    };
+    let response = query("CREATE TABLE hits (id INTEGER PRIMARY KEY, worker INTEGER)".to_string())
+        .await
+        .unwrap();
+    assert_eq!(response.status(), reqwest::StatusCode::OK);
+
+    let responses = futures_util::future::join_all(
+        (0..50).map(|worker| query(format!("INSERT INTO hits (worker) VALUES ({})", worker))),
+    )
+    .await;
+    for response in responses {
+        let response = response.unwrap();
+        let status = response.status();
+        let body = response.text().await.unwrap();
+        assert_eq!(status, reqwest::StatusCode::OK, "{}", body);
+        assert!(!body.contains("database is locked"), "{}", body);
+    }
+
+    let pool: serde_json::Value = client
+        .get(format!("{}/api/v1/metrics/pool", base_url))
+        .send()
+        .await
+        .unwrap()
+        .json()
+        .await
+        .unwrap();
+    let size = pool["size"].as_u64().unwrap();
+    assert!((1..=8).contains(&size), "{}", pool);
+    assert_eq!(pool["waiting"], 0);
+}
+
+/// Servidor con `/ws` sobre una tabla de 3000 filas con valores repetidos
+async fn spawn_websocket_server(ws_config: WsConfig) -> (WsState, std::net::SocketAddr) {
+    let (state, _) = create_test_server().await;
+    state.config.write().await.metrics_enabled = true;
+    let executor = Executor::new_sqlite_memory().unwrap();
+    let session = Session::new();
+    executor
+        .execute_statement(
+            &session,
+            "CREATE TABLE clientes (id INTEGER PRIMARY KEY, ciudad TEXT, estado TEXT, dept TEXT)",
+        )
+        .unwrap();
+    executor
+        .execute_statement(
+            &session,
+            "WITH RECURSIVE n(i) AS (SELECT 1 UNION ALL SELECT i + 1 FROM n WHERE i < 3000) \
+             INSERT INTO clientes SELECT i, 'Buenos Aires', 'activo', 'ventas' FROM n",
+        )
+        .unwrap();
+    *state.executor.write().await = Some(Arc::new(executor));
+
+    let ws_state = WsState::with_config(state.clone(), ws_config);
+    let router = create_router_with(state, Some(&ws_state));
+    let listener = tokio::net::TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let addr = listener.local_addr().unwrap();
+    tokio::spawn(async move {
+        axum::serve(listener, router).await.unwrap();
+    });
+    (ws_state, addr)
+}
+
+/// Pedir el ResultSet de ~100KB por `live_query` y devolver el mensaje y
+/// los bytes recibidos en los frames
+async fn receive_live_query_result(
+    socket: &mut tokio_tungstenite::WebSocketStream<
+        tokio_tungstenite::MaybeTlsStream<tokio::net::TcpStream>,
+    >,
+) -> (String, usize) {
+    let mut received = 0;
+    let mut text = |frame: WsFrame| -> String {
+        received += frame.len();
+        frame.into_text().unwrap()
+    };
+
+    let welcome = text(socket.next().await.unwrap().unwrap());
+    assert!(welcome.contains("welcome"));
+
+    let live_query = serde_json::json!({ "type": "live_query", "query": "SELECT * FROM clientes" });
+    socket
+        .send(WsFrame::Text(live_query.to_string()))
+        .await
+        .unwrap();
+    let result = loop {
+        let message = text(socket.next().await.unwrap().unwrap());
+        if message.contains("live_query_result") {
+            break message;
+        }
+    };
+    (result, received)
+}
+
+/// Esperar a que los contadores lleguen a `expected`: suben después de
+/// escribir el frame
+async fn wait_for_bytes_sent(ws_state: &WsState, expected: u64) {
+    for _ in 0..50 {
+        if ws_state.total_bytes_sent_compressed() == expected {
+            break;
+        }
+        tokio::time::sleep(std::time::Duration::from_millis(10)).await;
+    }
+    assert_eq!(ws_state.total_bytes_sent_compressed(), expected);
+}
+
+/// Abrir `/ws` sobre TCP ofreciendo `permessage-deflate` (tokio-tungstenite
+/// no implementa la extensión) y devolver la cabecera de respuesta
+async fn connect_deflate(
+    addr: std::net::SocketAddr,
+) -> (tokio::io::BufReader<tokio::net::TcpStream>, String) {
+    use tokio::io::{AsyncBufReadExt, AsyncWriteExt};
+
+    let mut stream = tokio::net::TcpStream::connect(addr).await.unwrap();
+    // Clave y aceptación del ejemplo de la RFC 6455
+    let request = format!(
+        "GET /ws HTTP/1.1\r\nHost: {}\r\nUpgrade: websocket\r\nConnection: Upgrade\r\n\
+         Sec-WebSocket-Key: dGhlIHNhbXBsZSBub25jZQ==\r\nSec-WebSocket-Version: 13\r\n\
+         Sec-WebSocket-Extensions: permessage-deflate; client_max_window_bits\r\n\r\n",
+        addr
+    );
+    stream.write_all(request.as_bytes()).await.unwrap();
+
+    let mut stream = tokio::io::BufReader::new(stream);
+    let mut status = String::new();
+    stream.read_line(&mut status).await.unwrap();
+    assert!(status.starts_with("HTTP/1.1 101"), "{}", status);
+    let mut headers = std::collections::HashMap::new();
+    loop {
+        let mut line = String::new();
+        stream.read_line(&mut line).await.unwrap();
+        let Some((name, value)) = line.trim_end().split_once(':') else {
+            break;
+        };
+        headers.insert(name.to_ascii_lowercase(), value.trim().to_string());
+    }
+    assert_eq!(
+        headers["sec-websocket-accept"],
+        "s3pPLMBiTxaQ9kYGzzhZRbK+xOo="
+    );
+    let extensions = headers
+        .remove("sec-websocket-extensions")
+        .unwrap_or_default();
+    (stream, extensions)
+}
+
+/// Leer un frame del servidor: primer byte (FIN, RSV y opcode) y payload
+async fn read_server_frame(
+    stream: &mut tokio::io::BufReader<tokio::net::TcpStream>,
+) -> (u8, Vec<u8>) {
+    use tokio::io::AsyncReadExt;
+
+    let mut head = [0u8; 2];
+    stream.read_exact(&mut head).await.unwrap();
+    assert_eq!(head[1] & 0x80, 0, "frame del servidor con máscara");
+    let len = match head[1] & 0x7f {
+        126 => stream.read_u16().await.unwrap() as usize,
+        127 => stream.read_u64().await.unwrap() as usize,
+        len => len as usize,
+    };
+    let mut payload = vec![0u8; len];
+    stream.read_exact(&mut payload).await.unwrap();
+    (head[0], payload)
+}
+
+/// Escribir un frame del cliente con máscara
+async fn write_client_frame(
+    stream: &mut tokio::io::BufReader<tokio::net::TcpStream>,
+    first_byte: u8,
+    payload: &[u8],
+) {
+    use tokio::io::AsyncWriteExt;
+
+    let mask = [0x12, 0x34, 0x56, 0x78];
+    let mut frame = vec![first_byte];
+    if payload.len() < 126 {
+        frame.push(0x80 | payload.len() as u8);
+    } else {
+        frame.push(0x80 | 126);
+        frame.extend_from_slice(&(payload.len() as u16).to_be_bytes());
+    }
+    frame.extend_from_slice(&mask);
+    frame.extend(payload.iter().enumerate().map(|(i, b)| b ^ mask[i % 4]));
+    stream.get_mut().write_all(&frame).await.unwrap();
+}
+
+/// Comprimir un mensaje `permessage-deflate` con un contexto nuevo (los
+/// cuatro bytes finales no viajan en el frame)
+fn deflate_payload(data: &[u8]) -> Vec<u8> {
+    use std::io::Write;
+
+    let mut encoder =
+        flate2::write::DeflateEncoder::new(Vec::new(), flate2::Compression::default());
+    encoder.write_all(data).unwrap();
+    encoder.flush().unwrap();
+    let mut compressed = std::mem::take(encoder.get_mut());
+    assert!(compressed.ends_with(&[0x00, 0x00, 0xff, 0xff]));
+    compressed.truncate(compressed.len() - 4);
+    compressed
+}
+
+/// Descomprimir un mensaje del servidor, que no reutiliza el contexto
+fn inflate_payload(data: &[u8]) -> String {
+    use std::io::Write;
+
+    let mut decoder = flate2::write::DeflateDecoder::new(Vec::new());
+    decoder.write_all(data).unwrap();
+    decoder.write_all(&[0x00, 0x00, 0xff, 0xff]).unwrap();
+    decoder.flush().unwrap();
+    String::from_utf8(std::mem::take(decoder.get_mut())).unwrap()
+}
+
+#[tokio::test]
+async fn test_websocket_counts_bytes_written() {
+    let (ws_state, addr) = spawn_websocket_server(WsConfig::default()).await;
+
+    // Sin oferta de permessage-deflate los mensajes salen sin comprimir
+    let (mut socket, response) = tokio_tungstenite::connect_async(format!("ws://{}/ws", addr))
+        .await
+        .unwrap();
+    assert!(response.headers().get("Sec-WebSocket-Extensions").is_none());
+
+    let (result, received) = receive_live_query_result(&mut socket).await;
+    assert!(result.len() >= 100 * 1024, "{}", result.len());
+    socket.close(None).await.unwrap();
+
+    // Sin compresión los dos contadores son lo que salió por la red
+    wait_for_bytes_sent(&ws_state, received as u64).await;
+    assert_eq!(ws_state.total_bytes_sent_uncompressed(), received as u64);
+
+    let metrics: serde_json::Value =
+        reqwest::get(format!("http://{}/api/v1/metrics/websocket", addr))
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+    assert_eq!(metrics["total_bytes_sent_uncompressed"], received as u64);
+    assert_eq!(metrics["total_bytes_sent_compressed"], received as u64);
+    assert_eq!(metrics["compression_ratio"], 1.0);
+}
+
+#[tokio::test]
+async fn test_websocket_permessage_deflate_compresses_messages() {
+    let (ws_state, addr) = spawn_websocket_server(WsConfig::default()).await;
+
+    let (mut stream, extensions) = connect_deflate(addr).await;
+    assert_eq!(extensions, "permessage-deflate; server_no_context_takeover");
+
+    // Bienvenida: frame de texto con FIN y RSV1
+    let (first_byte, payload) = read_server_frame(&mut stream).await;
+    assert_eq!(first_byte, 0xc1);
+    let mut received = payload.len();
+    assert!(inflate_payload(&payload).contains("welcome"));
+
+    // El ping se contesta con un pong sin comprimir
+    write_client_frame(&mut stream, 0x89, b"hola").await;
+    let (first_byte, payload) = read_server_frame(&mut stream).await;
+    assert_eq!((first_byte, payload.as_slice()), (0x8a, &b"hola"[..]));
+
+    // live_query comprimida y partida en dos frames
+    let live_query = serde_json::json!({ "type": "live_query", "query": "SELECT * FROM clientes" });
+    let compressed = deflate_payload(live_query.to_string().as_bytes());
+    let (head, tail) = compressed.split_at(compressed.len() / 2);
+    write_client_frame(&mut stream, 0x41, head).await;
+    write_client_frame(&mut stream, 0x80, tail).await;
+
+    let result = loop {
+        let (first_byte, payload) = read_server_frame(&mut stream).await;
+        assert_eq!(first_byte, 0xc1);
+        received += payload.len();
+        let message = inflate_payload(&payload);
+        if message.contains("live_query_result") {
+            break message;
+        }
+    };
+    assert!(result.len() >= 100 * 1024, "{}", result.len());
+    let result: serde_json::Value = serde_json::from_str(&result).unwrap();
+    assert_eq!(result["data"]["rows"].as_array().unwrap().len(), 3000);
+
+    // El cierre se devuelve con el mismo código
+    write_client_frame(&mut stream, 0x88, &1000u16.to_be_bytes()).await;
+    let (first_byte, payload) = read_server_frame(&mut stream).await;
+    assert_eq!(
+        (first_byte, payload),
+        (0x88, 1000u16.to_be_bytes().to_vec())
+    );
+
+    // Lo que salió en los frames es menos de la mitad del JSON
+    wait_for_bytes_sent(&ws_state, received as u64).await;
+    let uncompressed = ws_state.total_bytes_sent_uncompressed();
+    assert!(uncompressed >= 100 * 1024, "{}", uncompressed);
+    assert!(
+        ws_state.total_bytes_sent_compressed() * 2 < uncompressed,
+        "{} de {}",
+        ws_state.total_bytes_sent_compressed(),
+        uncompressed
+    );
+
+    let metrics: serde_json::Value =
+        reqwest::get(format!("http://{}/api/v1/metrics/websocket", addr))
+            .await
+            .unwrap()
+            .json()
+            .await
+            .unwrap();
+    assert_eq!(metrics["total_bytes_sent_uncompressed"], uncompressed);
+    assert!(
+        metrics["compression_ratio"].as_f64().unwrap() < 0.5,
+        "{}",
+        metrics
+    );
+}
+
+#[tokio::test]
+async fn test_websocket_permessage_deflate_rejects_unmasked_frames() {
+    let (_ws_state, addr) = spawn_websocket_server(WsConfig::default()).await;
+    let (mut stream, _) = connect_deflate(addr).await;
+    read_server_frame(&mut stream).await;
+
+    // Frame de texto sin máscara: el servidor cierra con 1002
+    tokio::io::AsyncWriteExt::write_all(stream.get_mut(), &[0x81, 0x02, b'{', b'}'])
+        .await
+        .unwrap();
+    let (first_byte, payload) = read_server_frame(&mut stream).await;
+    assert_eq!(
+        (first_byte, payload),
+        (0x88, 1002u16.to_be_bytes().to_vec())
+    );
+}
+
+#[tokio::test]
+async fn test_websocket_compression_disabled_ignores_permessage_deflate() {
+    let config = WsConfig {
+        compression_enabled: false,
+        ..WsConfig::default()
+    };
+    let (ws_state, addr) = spawn_websocket_server(config).await;
+
+    let mut request = format!("ws://{}/ws", addr).into_client_request().unwrap();
+    request.headers_mut().insert(
+        "Sec-WebSocket-Extensions",
+        "permessage-deflate; client_max_window_bits"
+            .parse()
+            .unwrap(),
+    );
+    let (mut socket, response) = tokio_tungstenite::connect_async(request).await.unwrap();
+    assert!(response.headers().get("Sec-WebSocket-Extensions").is_none());
+
+    // Los mensajes siguen saliendo como texto, sin comprimir
+    let welcome = socket.next().await.unwrap().unwrap();
+    assert!(welcome.is_text(), "{:?}", welcome);
+    socket.close(None).await.unwrap();
+
+    wait_for_bytes_sent(&ws_state, welcome.len() as u64).await;
+    assert_eq!(
+        ws_state.total_bytes_sent_uncompressed(),
+        welcome.len() as u64
+    );
+}
+
+#[tokio::test]
+async fn test_websocket_requires_token_and_read_only_live_queries() {
+    let (base_url, _secret) = spawn_auth_server_with(ServerConfig {
+        blocked_keywords: vec!["sqlite_master".to_string()],
+        ..test_config()
+    })
+    .await;
+    let ws_url = format!("{}/ws", base_url.replacen("http", "ws", 1));
+
+    // Sin token el upgrade se rechaza antes de abrir el socket
+    let error = tokio_tungstenite::connect_async(ws_url.as_str())
+        .await
+        .unwrap_err();
+    match error {
+        tokio_tungstenite::tungstenite::Error::Http(response) => {
+            assert_eq!(response.status().as_u16(), 401);
+        }
+        other => panic!("se esperaba 401: {:?}", other),
+    }
+
+    let token = login(&reqwest::Client::new(), &base_url).await;
+    let mut request = ws_url.into_client_request().unwrap();
+    request.headers_mut().insert(
+        "Authorization",
+        format!("Bearer {}", token).parse().unwrap(),
+    );
+    let (mut socket, _) = tokio_tungstenite::connect_async(request).await.unwrap();
+    let welcome = socket.next().await.unwrap().unwrap();
+    assert!(welcome.to_text().unwrap().contains("welcome"));
+
+    // Una consulta en vivo no escribe ni usa palabras clave bloqueadas
+    for query in ["DROP TABLE clientes", "SELECT name FROM sqlite_master"] {
+        let live_query = serde_json::json!({ "type": "live_query", "query": query });
+        socket
+            .send(WsFrame::Text(live_query.to_string()))
+            .await
+            .unwrap();
+        let reply: serde_json::Value = loop {
+            let message = socket.next().await.unwrap().unwrap();
+            let value: serde_json::Value =
+                serde_json::from_str(message.to_text().unwrap()).unwrap();
+            if value["type"] == "error" {
+                break value;
+            }
+            assert_ne!(value["type"], "live_query_result", "{}: {}", query, value);
+        };
+        assert!(
+            reply["data"]["error"]
+                .as_str()
+                .unwrap()
+                .contains("Acceso denegado"),
+            "{}",
+            reply
+        );
+    }
+}
+
+#[tokio::test]
+async fn test_oversized_body_rejected_before_handler() {
+    let config = ServerConfig {
+        max_request_body_bytes: 64,
+        ..test_config()
+    };
+    let state = ServerState::new(config).await.unwrap();
+
+    // El handler cuenta las peticiones que le llegan
+    let reached = Arc::new(std::sync::atomic::AtomicUsize::new(0));
+    let counter = reached.clone();
+    let router = axum::Router::new()
+        .route(
+            "/api/v1/query",
+            axum::routing::post(move |body: String| async move {
+                counter.fetch_add(1, std::sync::atomic::Ordering::SeqCst);
+                body.len().to_string()
+            }),
+        )
+        .layer(BodySizeLimitLayer::new(state.config.clone()));
+
+    let small = serde_json::json!({ "query": "SELECT 1" }).to_string();
+    let large = serde_json::json!({ "query": format!("SELECT '{}'", "x".repeat(100)) }).to_string();
+
+    let response = router
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method(Method::POST)
+                .uri("/api/v1/query")
+                .body(Body::from(small.clone()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::OK);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    assert_eq!(body, small.len().to_string());
+
+    // Con Content-Length se rechaza sin leer el cuerpo
+    let response = router
+        .clone()
+        .oneshot(
+            Request::builder()
+                .method(Method::POST)
+                .uri("/api/v1/query")
+                .header("content-length", large.len())
+                .body(Body::from(large.clone()))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let error: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(error["code"], "NOCTRA_E055");
+
+    // Sin Content-Length (chunked) se corta al superar el límite
+    let chunks = tokio_stream::iter(
+        large
+            .into_bytes()
+            .chunks(16)
+            .map(|chunk| Ok::<_, std::io::Error>(chunk.to_vec()))
+            .collect::<Vec<_>>(),
+    );
+    let response = router
+        .oneshot(
+            Request::builder()
+                .method(Method::POST)
+                .uri("/api/v1/query")
+                .body(Body::from_stream(chunks))
+                .unwrap(),
+        )
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::PAYLOAD_TOO_LARGE);
+
+    assert_eq!(reached.load(std::sync::atomic::Ordering::SeqCst), 1);
+}
+
+#[tokio::test]
+async fn test_long_and_blocked_queries_rejected_before_executor() {
+    let config = ServerConfig {
+        max_query_length_chars: 32,
+        blocked_keywords: vec!["drop".to_string(), "TRUNCATE".to_string()],
+        ..test_config()
+    };
+    let state = ServerState::new(config.clone()).await.unwrap();
+    let router = create_router(state);
+
+    let post = |query: String| {
+        Request::builder()
+            .method(Method::POST)
+            .uri("/api/v1/query")
+            .header("content-type", "application/json")
+            .body(Body::from(
+                serde_json::json!({ "query": query }).to_string(),
+            ))
+            .unwrap()
+    };
+
+    let response = router
+        .clone()
+        .oneshot(post(format!("SELECT '{}'", "x".repeat(40))))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::BAD_REQUEST);
+    let body = axum::body::to_bytes(response.into_body(), usize::MAX)
+        .await
+        .unwrap();
+    let error: serde_json::Value = serde_json::from_slice(&body).unwrap();
+    assert_eq!(error["code"], "NOCTRA_E012");
+
+    let response = router
+        .oneshot(post("DROP TABLE t".to_string()))
+        .await
+        .unwrap();
+    assert_eq!(response.status(), StatusCode::FORBIDDEN);
+
+    // Con el handler directamente: la tabla sigue ahí tras el intento
+    let executor = Executor::new_sqlite_memory().unwrap();
+    let session = Session::new();
+    executor
+        .execute_statement(&session, "CREATE TABLE notas (texto TEXT)")
+        .unwrap();
+    let handler = QueryHandler::new(Arc::new(executor), Arc::new(RqlParser::new()))
+        .with_sanitizer(SqlSanitizer::from_config(&config));
+    let request = |query: &str| -> QueryRequest {
+        serde_json::from_value(serde_json::json!({ "query": query })).unwrap()
+    };
+
+    let error = handler
+        .handle_query(&session, &request("drop table notas"), false)
+        .unwrap_err();
+    assert!(
+        matches!(error, NoctraError::Permission { .. }),
+        "{:?}",
+        error
+    );
+    // Dentro de un literal la palabra no cuenta
+    let response = handler
+        .handle_query(&session, &request("SELECT 'drop' FROM notas"), false)
+        .unwrap();
+    assert!(response.result.is_empty());
+}
+
+#[test]
+fn test_sql_sanitizer_checks() {
+    assert!(SqlSanitizer::validate_length("SELECT 1", 8).is_ok());
+    assert!(matches!(
+        SqlSanitizer::validate_length("SELECT 10", 8),
+        Err(NoctraError::Validation(_))
+    ));
+    // Se cuentan caracteres, no bytes
+    assert!(SqlSanitizer::validate_length("SELECT 'ñú'", 11).is_ok());
+
+    let blocked = ["DROP", "truncate"];
+    assert!(SqlSanitizer::check_forbidden_keywords("Truncate TABLE t", &blocked).is_err());
+    assert!(
+        SqlSanitizer::check_forbidden_keywords("SELECT * FROM t;drop table t", &blocked).is_err()
+    );
+    assert!(SqlSanitizer::check_forbidden_keywords("SELECT dropped FROM t", &blocked).is_ok());
+    assert!(SqlSanitizer::check_forbidden_keywords("DROP TABLE t", &[]).is_ok());
+
+    // Sin límites explícitos se usan los de ServerConfig::default()
+    let sanitizer = SqlSanitizer::from_config(&ServerConfig::default());
+    assert_eq!(
+        sanitizer.max_query_length_chars,
+        SqlSanitizer::default().max_query_length_chars
+    );
+    assert!(sanitizer.check("DROP TABLE t").is_ok());
+}
@@ -0,0 +1,281 @@
+//! Autocompletado del editor de comandos
+//!
+//! Completa palabras clave SQL/RQL, nombres de tablas y columnas (también
+//! en la forma `tabla.columna`) a partir de la palabra bajo el cursor.
+
+use noctra_core::catalog::Catalog;
+use noctra_core::datasource::SourceRegistry;
+use ratatui::{
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    widgets::{Block, Borders, Clear, List, ListItem, ListState},
+    Frame,
+};
+use std::collections::BTreeMap;
+
+use crate::highlight::KEYWORDS;
+
+/// Número máximo de sugerencias visibles en el pop-up
+const MAX_VISIBLE_ITEMS: u16 = 8;
+
+/// Tablas y columnas disponibles para completar
+#[derive(Debug, Clone, Default)]
+pub struct CompletionContext {
+    /// Columnas de cada tabla registrada (tabla -> columnas)
+    pub tables: BTreeMap<String, Vec<String>>,
+}
+
+impl CompletionContext {
+    /// Leer las tablas y columnas de todas las fuentes registradas
+    pub fn from_registry(registry: &SourceRegistry) -> Self {
+        let mut tables = BTreeMap::new();
+        for (alias, _) in registry.list_sources() {
+            let Some(source) = registry.get(&alias) else {
+                continue;
+            };
+            for table in source.schema().unwrap_or_default() {
+                let columns = table.columns.into_iter().map(|c| c.name).collect();
+                tables.insert(table.name, columns);
+            }
+        }
+        Self { tables }
+    }
+
+    /// Tablas y columnas del catálogo del executor (fuentes y backend)
+    pub fn from_catalog(catalog: &Catalog) -> Self {
+        let tables = catalog
+            .entries()
+            .iter()
+            .map(|entry| {
+                let columns = entry.table.columns.iter().map(|c| c.name.clone()).collect();
+                (entry.table.name.clone(), columns)
+            })
+            .collect();
+        Self { tables }
+    }
+}
+
+/// Motor de autocompletado
+#[derive(Debug, Clone, Copy, Default)]
+pub struct CompletionEngine;
+
+impl CompletionEngine {
+    /// Crear motor de autocompletado
+    pub fn new() -> Self {
+        Self
+    }
+
+    /// Sugerencias para un prefijo (sin distinguir mayúsculas)
+    ///
+    /// Devuelve primero las palabras clave, después las tablas y por último
+    /// las columnas. Con `tabla.prefijo` sólo se sugieren columnas de esa
+    /// tabla, ya cualificadas.
+    pub fn complete(&self, prefix: &str, context: CompletionContext) -> Vec<String> {
+        if prefix.is_empty() {
+            return Vec::new();
+        }
+
+        if let Some((table, column)) = prefix.rsplit_once('.') {
+            let Some((name, columns)) = context
+                .tables
+                .iter()
+                .find(|(name, _)| name.eq_ignore_ascii_case(table))
+            else {
+                return Vec::new();
+            };
+            return columns
+                .iter()
+                .filter(|c| starts_with_ignore_case(c, column))
+                .map(|c| format!("{}.{}", name, c))
+                .collect();
+        }
+
+        let mut matches: Vec<String> = KEYWORDS
+            .iter()
+            .filter(|k| starts_with_ignore_case(k, prefix))
+            .map(|k| k.to_string())
+            .collect();
+
+        let tables = context
+            .tables
+            .keys()
+            .filter(|t| starts_with_ignore_case(t, prefix));
+        let mut columns: Vec<&String> = context
+            .tables
+            .values()
+            .flatten()
+            .filter(|c| starts_with_ignore_case(c, prefix))
+            .collect();
+        columns.sort();
+
+        for candidate in tables.chain(columns) {
+            if !matches.contains(candidate) {
+                matches.push(candidate.clone());
+            }
+        }
+        matches
+    }
+}
+
+/// Palabra a la izquierda del cursor (`col` en caracteres)
+pub fn word_before_cursor(line: &str, col: usize) -> &str {
+    let end = line.char_indices().nth(col).map_or(line.len(), |(i, _)| i);
+    let start = line[..end]
+        .rfind(|c: char| !is_completion_char(c))
+        .map_or(0, |i| {
+            i + line[i..].chars().next().map_or(1, char::len_utf8)
+        });
+    &line[start..end]
+}
+
+/// Caracteres que forman parte de la palabra a completar
+fn is_completion_char(c: char) -> bool {
+    c.is_alphanumeric() || matches!(c, '_' | '.')
+}
+
+fn starts_with_ignore_case(candidate: &str, prefix: &str) -> bool {
+    candidate
+        .get(..prefix.len())
+        .is_some_and(|head| head.eq_ignore_ascii_case(prefix))
+}
+
+/// Lista de sugerencias abierta tras pulsar Tab
+#[derive(Debug, Clone)]
+pub struct CompletionPopup {
+    /// Palabra que se reemplaza al aceptar
+    pub prefix: String,
+    /// Sugerencias
+    pub items: Vec<String>,
+    /// Sugerencia seleccionada
+    pub selected: usize,
+}
+
+impl CompletionPopup {
+    /// Crear pop-up con la primera sugerencia seleccionada
+    pub fn new(prefix: &str, items: Vec<String>) -> Self {
+        Self {
+            prefix: prefix.to_string(),
+            items,
+            selected: 0,
+        }
+    }
+
+    /// Seleccionar la sugerencia siguiente (circular)
+    pub fn next(&mut self) {
+        if !self.items.is_empty() {
+            self.selected = (self.selected + 1) % self.items.len();
+        }
+    }
+
+    /// Seleccionar la sugerencia anterior (circular)
+    pub fn previous(&mut self) {
+        if !self.items.is_empty() {
+            self.selected = (self.selected + self.items.len() - 1) % self.items.len();
+        }
+    }
+
+    /// Sugerencia seleccionada
+    pub fn selected_item(&self) -> Option<&str> {
+        self.items.get(self.selected).map(String::as_str)
+    }
+
+    /// Renderizar como bloque flotante bajo `(x, y)`, dentro de `area`
+    pub fn render(&self, frame: &mut Frame, area: Rect, x: u16, y: u16) {
+        let width = self
+            .items
+            .iter()
+            .map(|item| item.chars().count() as u16)
+            .max()
+            .unwrap_or(0)
+            .saturating_add(4)
+            .min(area.width);
+        let height = (self.items.len() as u16).min(MAX_VISIBLE_ITEMS) + 2;
+
+        // Debajo del cursor si cabe; si no, encima
+        let below = y.saturating_add(1);
+        let top = if below + height <= area.bottom() {
+            below
+        } else {
+            y.saturating_sub(height).max(area.y)
+        };
+        let left = x.min(area.right().saturating_sub(width)).max(area.x);
+        let popup = Rect::new(left, top, width, height.min(area.height)).intersection(area);
+
+        let items: Vec<ListItem> = self
+            .items
+            .iter()
+            .map(|item| ListItem::new(item.as_str()))
+            .collect();
+        let list = List::new(items)
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .border_style(Style::default().fg(Color::DarkGray)),
+            )
+            .highlight_style(
+                Style::default()
+                    .fg(Color::Black)
+                    .bg(Color::Cyan)
+                    .add_modifier(Modifier::BOLD),
+            );
+        let mut state = ListState::default().with_selected(Some(self.selected));
+
+        frame.render_widget(Clear, popup);
+        frame.render_stateful_widget(list, popup, &mut state);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn context() -> CompletionContext {
+        let mut tables = BTreeMap::new();
+        tables.insert(
+            "clientes".to_string(),
+            vec!["id".to_string(), "nombre".to_string(), "ciudad".to_string()],
+        );
+        tables.insert(
+            "ventas".to_string(),
+            vec!["id".to_string(), "importe".to_string()],
+        );
+        CompletionContext { tables }
+    }
+
+    #[test]
+    fn test_complete_keywords_tables_and_columns() {
+        let engine = CompletionEngine::new();
+
+        assert_eq!(engine.complete("sel", context()), vec!["SELECT"]);
+        assert_eq!(engine.complete("ve", context()), vec!["ventas"]);
+        assert_eq!(engine.complete("ci", context()), vec!["ciudad"]);
+        assert_eq!(
+            engine.complete("i", context()),
+            vec!["IMPORT", "IN", "INNER", "INSERT", "INTO", "IS", "id", "importe"]
+        );
+        assert_eq!(
+            engine.complete("clientes.n", context()),
+            vec!["clientes.nombre"]
+        );
+        assert!(engine.complete("", context()).is_empty());
+        assert!(engine.complete("otra.id", context()).is_empty());
+    }
+
+    #[test]
+    fn test_word_before_cursor() {
+        assert_eq!(word_before_cursor("SELECT * FROM cli", 17), "cli");
+        assert_eq!(word_before_cursor("SELECT c.nom", 12), "c.nom");
+        assert_eq!(word_before_cursor("SEL", 2), "SE");
+        assert_eq!(word_before_cursor("año = ", 6), "");
+        assert_eq!(word_before_cursor("x, añ", 5), "añ");
+    }
+
+    #[test]
+    fn test_popup_selection_wraps() {
+        let mut popup = CompletionPopup::new("s", vec!["SELECT".to_string(), "SET".to_string()]);
+        popup.previous();
+        assert_eq!(popup.selected_item(), Some("SET"));
+        popup.next();
+        assert_eq!(popup.selected_item(), Some("SELECT"));
+    }
+}
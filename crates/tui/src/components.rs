@@ -389,16 +389,14 @@ impl FormComponent {
 
     /// Obtener siguiente campo navegable
     pub fn get_next_field(&self, current_field: Option<&str>) -> Option<&String> {
-        let fields: Vec<&String> = self.form.fields.keys().collect();
-
-        if let Some(current) = current_field {
-            if let Some(current_index) = fields.iter().position(|&f| f == current) {
-                let next_index = (current_index + 1) % fields.len();
-                return Some(fields[next_index]);
-            }
-        }
-
-        fields.first().cloned()
+        // En orden de declaración (ver `Form::field_names`)
+        let fields = self.form.field_names();
+        let next = current_field
+            .and_then(|current| fields.iter().position(|&f| f == current))
+            .map(|index| fields[(index + 1) % fields.len()])
+            .or_else(|| fields.first().copied())?;
+
+        self.form.fields.get_key_value(next).map(|(name, _)| name)
     }
 
     /// Obtener campos requeridos con valor faltante
@@ -588,8 +586,8 @@ impl Component for FormComponent {
 
         // Si se activa el focus y no hay campo activo, seleccionar el primer campo
         if focused && self.active_field.is_none() {
-            if let Some(first_field) = self.form.fields.keys().next() {
-                self.active_field = Some(first_field.clone());
+            if let Some(first_field) = self.form.field_names().first() {
+                self.active_field = Some(first_field.to_string());
                 self.cursor_position = 0;
             }
         }
@@ -14,6 +14,8 @@ use ratatui::{
     Frame,
 };
 
+use noctra_core::types::coerce::{self, Collation};
+use noctra_core::Value;
 use noctra_formlib::validation::FormValidator;
 use noctra_formlib::{FieldType, Form, ValidationError};
 
@@ -28,9 +30,17 @@ pub enum FormRenderError {
     #[error("Errores de validación: {0:?}")]
     ValidationErrors(Vec<ValidationError>),
 
+    /// Reglas entre campos no cumplidas
+    #[error("Errores de validación entre campos: {0:?}")]
+    CrossFieldErrors(Vec<ValidationError>),
+
     /// Error de renderizado
     #[error("Error de renderizado: {0}")]
     RenderError(String),
+
+    /// Condición `visible_when` mal formada
+    #[error("Condición de visibilidad inválida en '{field}': {message}")]
+    InvalidVisibilityCondition { field: String, message: String },
 }
 
 /// Resultado de operaciones con FormRenderer
@@ -89,39 +99,29 @@ pub struct FormRenderer {
 
     /// Modo de validación
     validate_on_change: bool,
+
+    /// Línea de ayuda con las teclas
+    help_text: String,
 }
 
 impl FormRenderer {
     /// Crear nuevo renderer
     pub fn new(form: Form) -> Self {
-        let field_order: Vec<String> = form.fields.keys().cloned().collect();
-        let mut field_states = HashMap::new();
-
-        // Inicializar estados de campos con valores por defecto
-        for (name, field) in &form.fields {
-            let mut state = FieldState::default();
-            if let Some(default) = &field.default {
-                state.value = default.clone();
-            }
-            field_states.insert(name.clone(), state);
-        }
+        // Los campos se recorren en el orden en que se declararon
+        let field_order: Vec<String> = form.field_names().into_iter().map(String::from).collect();
 
-        // Enfocar primer campo
-        if let Some(first_field) = field_order.first() {
-            if let Some(state) = field_states.get_mut(first_field) {
-                state.focused = true;
-            }
-        }
-
-        Self {
+        let mut renderer = Self {
             form,
-            field_states,
+            field_states: HashMap::new(),
             field_order,
             focused_field_index: 0,
             validator: FormValidator::new(),
             scroll_offset: 0,
             validate_on_change: true,
-        }
+            help_text: " TAB=Next | Shift+TAB=Prev | ENTER=Submit | ESC=Cancel".to_string(),
+        };
+        renderer.reset();
+        renderer
     }
 
     /// Mantener compatibilidad con código existente (no hace nada, ratatui se adapta solo)
@@ -129,6 +129,50 @@ impl FormRenderer {
         self
     }
 
+    /// Cambiar la línea de ayuda (las teclas dependen de quién maneja el
+    /// formulario)
+    pub fn with_help(mut self, help_text: impl Into<String>) -> Self {
+        self.help_text = help_text.into();
+        self
+    }
+
+    /// Volver a los valores por defecto, sin errores y con el foco en el
+    /// primer campo
+    pub fn reset(&mut self) {
+        self.field_states = self
+            .form
+            .fields
+            .iter()
+            .map(|(name, field)| {
+                let state = FieldState {
+                    value: field.default.clone().unwrap_or_default(),
+                    ..FieldState::default()
+                };
+                (name.clone(), state)
+            })
+            .collect();
+        self.scroll_offset = 0;
+        self.set_focus(0);
+    }
+
+    /// Acción que ejecuta el formulario al enviarse: el primer botón de
+    /// `ui_config` que sea una acción o, si no hay, la primera por nombre
+    pub fn default_action(&self) -> Option<&str> {
+        let buttons = self
+            .form
+            .ui_config
+            .as_ref()
+            .and_then(|ui| ui.buttons.as_ref());
+        if let Some(button) = buttons
+            .into_iter()
+            .flatten()
+            .find(|button| self.form.actions.contains_key(button.as_str()))
+        {
+            return Some(button.as_str());
+        }
+        self.form.actions.keys().map(|name| name.as_str()).min()
+    }
+
     /// Setear valor de campo
     pub fn set_field_value(&mut self, field_name: &str, value: String) -> FormRenderResult<()> {
         let state = self
@@ -153,21 +197,17 @@ impl FormRenderer {
     }
 
     /// Validar campo
+    ///
+    /// El resultado queda en el estado del campo (`valid` y `errors`); sólo
+    /// falla si el campo no existe.
     pub fn validate_field(&mut self, field_name: &str) -> FormRenderResult<()> {
-        let field = self
-            .form
-            .fields
-            .get(field_name)
-            .ok_or_else(|| FormRenderError::FieldNotFound(field_name.to_string()))?
-            .clone();
+        let result = self.check_field(field_name)?;
 
         let state = self
             .field_states
             .get_mut(field_name)
             .ok_or_else(|| FormRenderError::FieldNotFound(field_name.to_string()))?;
-
-        // Validar con FormValidator
-        match self.validator.validate_field(&field, &state.value) {
+        match result {
             Ok(_) => {
                 state.valid = true;
                 state.errors.clear();
@@ -181,21 +221,69 @@ impl FormRenderer {
         Ok(())
     }
 
+    /// Comprobar el valor actual de un campo: obligatorio y después
+    /// `FormValidator`
+    fn check_field(&self, field_name: &str) -> FormRenderResult<Result<(), ValidationError>> {
+        let not_found = || FormRenderError::FieldNotFound(field_name.to_string());
+        let field = self.form.fields.get(field_name).ok_or_else(not_found)?;
+        let value = &self
+            .field_states
+            .get(field_name)
+            .ok_or_else(not_found)?
+            .value;
+
+        if field.required && value.trim().is_empty() {
+            return Ok(Err(ValidationError::RequiredField(field.label.clone())));
+        }
+        Ok(self.validator.validate_field(field, value))
+    }
+
     /// Validar todos los campos
     pub fn validate_all(&mut self) -> FormRenderResult<()> {
         let mut all_errors = Vec::new();
 
-        for field_name in self.field_order.clone() {
-            if let Err(FormRenderError::ValidationErrors(errors)) = self.validate_field(&field_name)
-            {
-                all_errors.extend(errors);
+        // Los campos ocultos no se validan
+        let visible: Vec<String> = self
+            .visible_fields()?
+            .into_iter()
+            .map(String::from)
+            .collect();
+        for field_name in &visible {
+            if let Err(error) = self.check_field(field_name)? {
+                all_errors.push(error);
+            }
+            self.validate_field(field_name)?;
+        }
+
+        if !all_errors.is_empty() {
+            return Err(FormRenderError::ValidationErrors(all_errors));
+        }
+
+        // Reglas entre campos, sólo con los valores visibles; el mensaje se
+        // muestra en cada campo de la regla
+        let values: HashMap<String, String> = visible
+            .iter()
+            .filter_map(|name| Some((name.clone(), self.field_states.get(name)?.value.clone())))
+            .collect();
+        let errors = self
+            .validator
+            .validate_cross_fields(&values, &self.form.cross_field_rules);
+        for error in &errors {
+            let ValidationError::CrossField(fields, _) = error else {
+                continue;
+            };
+            for field_name in fields {
+                if let Some(state) = self.field_states.get_mut(field_name) {
+                    state.valid = false;
+                    state.errors.push(error.to_string());
+                }
             }
         }
 
-        if all_errors.is_empty() {
+        if errors.is_empty() {
             Ok(())
         } else {
-            Err(FormRenderError::ValidationErrors(all_errors))
+            Err(FormRenderError::CrossFieldErrors(errors))
         }
     }
 
@@ -207,28 +295,78 @@ impl FormRenderer {
             .collect()
     }
 
-    /// Navegar al siguiente campo
-    pub fn focus_next(&mut self) {
-        // Desenfocar campo actual
-        if let Some(field_name) = self.field_order.get(self.focused_field_index) {
-            if let Some(state) = self.field_states.get_mut(field_name) {
-                state.focused = false;
+    /// Verificar si un campo es visible según su `visible_when`
+    pub fn is_field_visible(&self, field_name: &str) -> FormRenderResult<bool> {
+        let field = self
+            .form
+            .fields
+            .get(field_name)
+            .ok_or_else(|| FormRenderError::FieldNotFound(field_name.to_string()))?;
+
+        match &field.visible_when {
+            Some(condition) => {
+                evaluate_condition(condition, &self.get_values()).map_err(|message| {
+                    FormRenderError::InvalidVisibilityCondition {
+                        field: field_name.to_string(),
+                        message,
+                    }
+                })
             }
+            None => Ok(true),
         }
+    }
 
-        // Mover índice
-        self.focused_field_index = (self.focused_field_index + 1) % self.field_order.len();
-
-        // Enfocar nuevo campo
-        if let Some(field_name) = self.field_order.get(self.focused_field_index) {
-            if let Some(state) = self.field_states.get_mut(field_name) {
-                state.focused = true;
+    /// Campos visibles con los valores actuales, en orden de navegación
+    pub fn visible_fields(&self) -> FormRenderResult<Vec<&str>> {
+        let mut visible = Vec::new();
+        for field_name in &self.field_order {
+            if self.is_field_visible(field_name)? {
+                visible.push(field_name.as_str());
             }
         }
+        Ok(visible)
+    }
+
+    /// Campos visibles; con una condición inválida se muestran todos
+    fn visible_or_all(&self) -> Vec<&str> {
+        self.visible_fields()
+            .unwrap_or_else(|_| self.field_order.iter().map(|s| s.as_str()).collect())
+    }
+
+    /// Navegar al siguiente campo
+    pub fn focus_next(&mut self) {
+        self.move_focus(|index, len| (index + 1) % len);
     }
 
     /// Navegar al campo anterior
     pub fn focus_previous(&mut self) {
+        self.move_focus(|index, len| if index == 0 { len - 1 } else { index - 1 });
+    }
+
+    /// Mover el foco saltando campos ocultos
+    fn move_focus(&mut self, step: impl Fn(usize, usize) -> usize) {
+        let len = self.field_order.len();
+        if len == 0 {
+            return;
+        }
+
+        let visible: Vec<String> = self
+            .visible_or_all()
+            .into_iter()
+            .map(String::from)
+            .collect();
+        let mut index = self.focused_field_index;
+        for _ in 0..len {
+            index = step(index, len);
+            if visible.contains(&self.field_order[index]) {
+                break;
+            }
+        }
+        self.set_focus(index);
+    }
+
+    /// Enfocar el campo en `index`
+    fn set_focus(&mut self, index: usize) {
         // Desenfocar campo actual
         if let Some(field_name) = self.field_order.get(self.focused_field_index) {
             if let Some(state) = self.field_states.get_mut(field_name) {
@@ -236,12 +374,7 @@ impl FormRenderer {
             }
         }
 
-        // Mover índice (wrap around)
-        if self.focused_field_index == 0 {
-            self.focused_field_index = self.field_order.len() - 1;
-        } else {
-            self.focused_field_index -= 1;
-        }
+        self.focused_field_index = index;
 
         // Enfocar nuevo campo
         if let Some(field_name) = self.field_order.get(self.focused_field_index) {
@@ -259,7 +392,16 @@ impl FormRenderer {
     }
 
     /// Renderizar formulario usando Ratatui (nuevo método)
+    ///
+    /// Los campos cuyo `visible_when` no se cumple con los valores actuales
+    /// no ocupan espacio. Si alguna condición es inválida se muestran todos
+    /// los campos junto con el error.
     pub fn render(&self, frame: &mut Frame, area: Rect) {
+        let (visible, condition_error) = match self.visible_fields() {
+            Ok(visible) => (visible, None),
+            Err(e) => (self.visible_or_all(), Some(e.to_string())),
+        };
+
         // Layout principal: header, fields, footer
         let chunks = Layout::default()
             .direction(Direction::Vertical)
@@ -275,7 +417,7 @@ impl FormRenderer {
         self.render_header(frame, chunks[0]);
 
         // Campos
-        self.render_fields(frame, chunks[1]);
+        self.render_fields(frame, chunks[1], &visible, condition_error.as_deref());
 
         // Botones
         self.render_actions(frame, chunks[2]);
@@ -317,18 +459,31 @@ impl FormRenderer {
         frame.render_widget(paragraph, area);
     }
 
-    /// Renderizar campos
-    fn render_fields(&self, frame: &mut Frame, area: Rect) {
+    /// Renderizar campos visibles
+    fn render_fields(
+        &self,
+        frame: &mut Frame,
+        area: Rect,
+        visible: &[&str],
+        condition_error: Option<&str>,
+    ) {
         let mut items = Vec::new();
 
-        for (i, field_name) in self.field_order.iter().enumerate() {
+        if let Some(error) = condition_error {
+            items.push(ListItem::new(Line::from(Span::styled(
+                format!("⚠ {}", error),
+                Style::default().fg(Color::Red),
+            ))));
+        }
+
+        for (i, field_name) in visible.iter().enumerate() {
             if i < self.scroll_offset {
                 continue;
             }
 
             if let (Some(field), Some(state)) = (
-                self.form.fields.get(field_name),
-                self.field_states.get(field_name),
+                self.form.fields.get(*field_name),
+                self.field_states.get(*field_name),
             ) {
                 // Línea del label
                 let focus_marker = if state.focused { "▶" } else { " " };
@@ -419,10 +574,8 @@ impl FormRenderer {
 
     /// Renderizar línea de ayuda
     fn render_help(&self, frame: &mut Frame, area: Rect) {
-        let help_text = " TAB=Next | Shift+TAB=Prev | ENTER=Submit | ESC=Cancel";
-
         let text = Text::from(Line::from(Span::styled(
-            help_text,
+            self.help_text.as_str(),
             Style::default().fg(Color::Gray),
         )));
 
@@ -448,7 +601,7 @@ impl FormRenderer {
         }
 
         // Campos
-        for field_name in &self.field_order {
+        for field_name in self.visible_or_all() {
             if let (Some(field), Some(state)) = (
                 self.form.fields.get(field_name),
                 self.field_states.get(field_name),
@@ -495,6 +648,144 @@ impl FormRenderer {
     }
 }
 
+/// Token de una condición `visible_when`
+#[derive(Debug, Clone, PartialEq)]
+enum ConditionToken {
+    Field(String),
+    Literal(String),
+    Op(&'static str),
+    And,
+    Or,
+}
+
+/// Evaluar una condición `visible_when` contra los valores del formulario
+///
+/// Gramática: `cmp ((AND | OR) cmp)*` con `cmp := operando op operando`,
+/// `op` en `==`, `!=`, `>`, `<`. AND tiene precedencia sobre OR. Los
+/// operandos son nombres de campo, literales `'texto'` o números; `>` y `<`
+/// comparan numéricamente si ambos lados son números.
+fn evaluate_condition(condition: &str, values: &HashMap<String, String>) -> Result<bool, String> {
+    let tokens = tokenize_condition(condition)?;
+    let mut pos = 0;
+    let mut result = false;
+
+    // OR de grupos AND
+    loop {
+        let mut group = true;
+        loop {
+            group &= evaluate_comparison(&tokens, &mut pos, values)?;
+            if tokens.get(pos) != Some(&ConditionToken::And) {
+                break;
+            }
+            pos += 1;
+        }
+        result |= group;
+
+        match tokens.get(pos) {
+            Some(ConditionToken::Or) => pos += 1,
+            None => return Ok(result),
+            Some(token) => return Err(format!("token inesperado {:?}", token)),
+        }
+    }
+}
+
+/// Evaluar `operando op operando` a partir de `pos`
+fn evaluate_comparison(
+    tokens: &[ConditionToken],
+    pos: &mut usize,
+    values: &HashMap<String, String>,
+) -> Result<bool, String> {
+    let operand = |token: Option<&ConditionToken>| match token {
+        Some(ConditionToken::Field(name)) => values
+            .get(name)
+            .cloned()
+            .ok_or_else(|| format!("campo '{}' no existe", name)),
+        Some(ConditionToken::Literal(value)) => Ok(value.clone()),
+        other => Err(format!("se esperaba un operando, se encontró {:?}", other)),
+    };
+
+    let left = operand(tokens.get(*pos))?;
+    let op = match tokens.get(*pos + 1) {
+        Some(ConditionToken::Op(op)) => *op,
+        other => return Err(format!("se esperaba un operador, se encontró {:?}", other)),
+    };
+    let right = operand(tokens.get(*pos + 2))?;
+    *pos += 3;
+
+    // Los números se comparan como números; el resto, como texto
+    let value = |text: String| match text.trim().parse::<f64>() {
+        Ok(number) => Value::Float(number),
+        Err(_) => Value::Text(text),
+    };
+    let ordering = coerce::compare_sql(&value(left), &value(right), Collation::Binary);
+
+    Ok(match op {
+        "==" => ordering == Some(std::cmp::Ordering::Equal),
+        "!=" => ordering != Some(std::cmp::Ordering::Equal),
+        ">" => ordering == Some(std::cmp::Ordering::Greater),
+        _ => ordering == Some(std::cmp::Ordering::Less),
+    })
+}
+
+/// Dividir una condición en tokens
+fn tokenize_condition(condition: &str) -> Result<Vec<ConditionToken>, String> {
+    let mut tokens = Vec::new();
+    let mut chars = condition.chars().peekable();
+
+    while let Some(&c) = chars.peek() {
+        match c {
+            c if c.is_whitespace() => {
+                chars.next();
+            }
+            '\'' | '"' => {
+                chars.next();
+                let mut literal = String::new();
+                loop {
+                    match chars.next() {
+                        Some(ch) if ch == c => break,
+                        Some(ch) => literal.push(ch),
+                        None => return Err("literal sin cerrar".to_string()),
+                    }
+                }
+                tokens.push(ConditionToken::Literal(literal));
+            }
+            '=' | '!' => {
+                chars.next();
+                if chars.next() != Some('=') {
+                    return Err(format!("operador '{}' inválido, use '==' o '!='", c));
+                }
+                tokens.push(ConditionToken::Op(if c == '=' { "==" } else { "!=" }));
+            }
+            '>' | '<' => {
+                chars.next();
+                tokens.push(ConditionToken::Op(if c == '>' { ">" } else { "<" }));
+            }
+            c if c.is_alphanumeric() || c == '_' || c == '-' || c == '.' => {
+                let mut word = String::new();
+                while let Some(&ch) = chars.peek() {
+                    if !(ch.is_alphanumeric() || ch == '_' || ch == '-' || ch == '.') {
+                        break;
+                    }
+                    word.push(ch);
+                    chars.next();
+                }
+                tokens.push(match word.to_uppercase().as_str() {
+                    "AND" => ConditionToken::And,
+                    "OR" => ConditionToken::Or,
+                    _ if word.parse::<f64>().is_ok() => ConditionToken::Literal(word),
+                    _ => ConditionToken::Field(word),
+                });
+            }
+            other => return Err(format!("carácter inesperado '{}'", other)),
+        }
+    }
+
+    if tokens.is_empty() {
+        return Err("condición vacía".to_string());
+    }
+    Ok(tokens)
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -511,6 +802,9 @@ mod tests {
                 width: None,
                 default: None,
                 validations: None,
+                visible_when: None,
+                computed_from: Vec::new(),
+                formula: None,
             },
         );
         fields.insert(
@@ -522,6 +816,9 @@ mod tests {
                 width: None,
                 default: None,
                 validations: None,
+                visible_when: None,
+                computed_from: Vec::new(),
+                formula: None,
             },
         );
 
@@ -530,9 +827,11 @@ mod tests {
             schema: None,
             description: Some("A test form".to_string()),
             fields,
+            field_order: Vec::new(),
             actions: HashMap::new(),
             ui_config: None,
             pagination: None,
+            cross_field_rules: Vec::new(),
         }
     }
 
@@ -593,4 +892,227 @@ mod tests {
         assert!(output.contains("Name"));
         assert!(output.contains("Email"));
     }
+
+    fn create_conditional_form() -> Form {
+        let mut fields = HashMap::new();
+        fields.insert(
+            "tipo".to_string(),
+            FormField {
+                label: "Tipo de cliente".to_string(),
+                field_type: FieldType::Select {
+                    options: vec!["persona".to_string(), "empresa".to_string()],
+                },
+                required: true,
+                width: None,
+                default: Some("persona".to_string()),
+                validations: None,
+                visible_when: None,
+                computed_from: Vec::new(),
+                formula: None,
+            },
+        );
+        fields.insert(
+            "razon_social".to_string(),
+            FormField {
+                label: "Razon social".to_string(),
+                field_type: FieldType::Text,
+                required: true,
+                width: None,
+                default: None,
+                validations: None,
+                visible_when: Some("tipo == 'empresa'".to_string()),
+                computed_from: Vec::new(),
+                formula: None,
+            },
+        );
+
+        Form {
+            title: "Clientes".to_string(),
+            schema: None,
+            description: None,
+            fields,
+            field_order: Vec::new(),
+            actions: HashMap::new(),
+            ui_config: None,
+            pagination: None,
+            cross_field_rules: Vec::new(),
+        }
+    }
+
+    fn render_text(renderer: &FormRenderer) -> String {
+        use ratatui::{backend::TestBackend, Terminal};
+
+        let mut terminal = Terminal::new(TestBackend::new(60, 20)).unwrap();
+        terminal
+            .draw(|frame| renderer.render(frame, frame.area()))
+            .unwrap();
+        let buffer = terminal.backend().buffer();
+        buffer.content().iter().map(|cell| cell.symbol()).collect()
+    }
+
+    #[test]
+    fn test_field_visible_when_dropdown_matches() {
+        let mut renderer = FormRenderer::new(create_conditional_form());
+
+        let output = render_text(&renderer);
+        assert!(output.contains("Tipo de cliente"));
+        assert!(!output.contains("Razon social"));
+        assert_eq!(renderer.visible_fields().unwrap(), vec!["tipo"]);
+
+        // El campo oculto (requerido y vacío) no bloquea la validación
+        assert!(renderer.validate_all().is_ok());
+
+        renderer
+            .set_field_value("tipo", "empresa".to_string())
+            .unwrap();
+        let output = render_text(&renderer);
+        assert!(output.contains("Tipo de cliente"));
+        assert!(output.contains("Razon social"));
+        assert!(renderer.is_field_visible("razon_social").unwrap());
+    }
+
+    #[test]
+    fn test_focus_skips_hidden_fields() {
+        let mut renderer = FormRenderer::new(create_conditional_form());
+        let start = renderer
+            .field_order
+            .iter()
+            .position(|name| name == "tipo")
+            .unwrap();
+        renderer.set_focus(start);
+
+        renderer.focus_next();
+        assert_eq!(renderer.get_focused_field(), Some("tipo"));
+        renderer.focus_previous();
+        assert_eq!(renderer.get_focused_field(), Some("tipo"));
+    }
+
+    #[test]
+    fn test_evaluate_condition() {
+        let values: HashMap<String, String> = [
+            ("tipo".to_string(), "empresa".to_string()),
+            ("edad".to_string(), "42".to_string()),
+        ]
+        .into_iter()
+        .collect();
+
+        assert!(evaluate_condition("tipo == 'empresa'", &values).unwrap());
+        assert!(evaluate_condition("tipo != \"persona\"", &values).unwrap());
+        assert!(evaluate_condition("edad > 9 AND edad < 100", &values).unwrap());
+        assert!(!evaluate_condition("edad > 50 and tipo == 'empresa'", &values).unwrap());
+        assert!(evaluate_condition("edad > 50 OR tipo == 'empresa'", &values).unwrap());
+        // AND tiene precedencia sobre OR
+        assert!(evaluate_condition("tipo == 'x' AND edad > 50 OR edad == 42", &values).unwrap());
+
+        assert!(evaluate_condition("tipo = 'empresa'", &values).is_err());
+        assert!(evaluate_condition("desconocido == 'x'", &values).is_err());
+        assert!(evaluate_condition("tipo == 'empresa", &values).is_err());
+        assert!(evaluate_condition("tipo == 'a' AND", &values).is_err());
+    }
+
+    #[test]
+    fn test_invalid_visibility_condition() {
+        let mut form = create_conditional_form();
+        form.fields.get_mut("razon_social").unwrap().visible_when =
+            Some("tipo === 'empresa'".to_string());
+        let renderer = FormRenderer::new(form);
+
+        let err = renderer.is_field_visible("razon_social").unwrap_err();
+        assert!(matches!(
+            err,
+            FormRenderError::InvalidVisibilityCondition { ref field, .. } if field == "razon_social"
+        ));
+
+        // Con una condición inválida se muestran todos los campos y el error
+        let output = render_text(&renderer);
+        assert!(output.contains("Razon social"));
+        assert!(output.contains("inválida"));
+    }
+
+    #[test]
+    fn test_validate_all_reports_cross_field_errors() {
+        let mut form = create_test_form();
+        form.fields.clear();
+        for name in ["start_date", "end_date"] {
+            form.fields.insert(
+                name.to_string(),
+                FormField {
+                    label: name.to_string(),
+                    field_type: FieldType::Date,
+                    required: true,
+                    width: None,
+                    default: None,
+                    validations: None,
+                    visible_when: None,
+                    computed_from: Vec::new(),
+                    formula: None,
+                },
+            );
+        }
+        form.cross_field_rules.push(noctra_formlib::CrossFieldRule {
+            fields: vec!["start_date".to_string(), "end_date".to_string()],
+            condition: "start_date < end_date".to_string(),
+            message: "End must be after start".to_string(),
+        });
+        let mut renderer = FormRenderer::new(form);
+        renderer
+            .set_field_value("start_date", "2024-05-02".to_string())
+            .unwrap();
+        renderer
+            .set_field_value("end_date", "2024-05-01".to_string())
+            .unwrap();
+
+        match renderer.validate_all() {
+            Err(FormRenderError::CrossFieldErrors(errors)) => {
+                assert_eq!(errors.len(), 1);
+                assert!(errors[0].to_string().starts_with("End must be after start"));
+            }
+            other => panic!("se esperaba CrossFieldErrors, se obtuvo {:?}", other),
+        }
+        assert!(!renderer.field_states["end_date"].valid);
+
+        renderer
+            .set_field_value("end_date", "2024-05-09".to_string())
+            .unwrap();
+        assert!(renderer.validate_all().is_ok());
+    }
+
+    #[test]
+    fn test_reset_and_default_action() {
+        let mut form = create_test_form();
+        form.fields.get_mut("email").unwrap().default = Some("a@b.com".to_string());
+        for name in ["save", "delete"] {
+            form.actions.insert(
+                name.to_string(),
+                noctra_formlib::FormAction {
+                    action_type: noctra_formlib::ActionType::Query,
+                    sql: Some("SELECT 1".to_string()),
+                    params: None,
+                    param_type: noctra_formlib::ParamType::Named,
+                    pipeline: None,
+                },
+            );
+        }
+        let mut renderer = FormRenderer::new(form);
+        assert_eq!(renderer.get_focused_field(), Some("email"));
+        assert_eq!(renderer.default_action(), Some("delete"));
+
+        renderer.focus_next();
+        renderer.set_field_value("email", String::new()).unwrap();
+        renderer.set_field_value("name", "Ana".to_string()).unwrap();
+        renderer.reset();
+        assert_eq!(renderer.get_field_value("email"), Some("a@b.com"));
+        assert_eq!(renderer.get_field_value("name"), Some(""));
+        assert_eq!(renderer.get_focused_field(), Some("email"));
+        assert!(renderer.field_states["name"].errors.is_empty());
+
+        renderer.form.ui_config = Some(noctra_formlib::UiConfig {
+            width: None,
+            height: None,
+            layout: None,
+            theme: None,
+            buttons: Some(vec!["cancel".to_string(), "save".to_string()]),
+        });
+        assert_eq!(renderer.default_action(), Some("save"));
+    }
 }
@@ -0,0 +1,277 @@
+//! Resaltado de sintaxis SQL/RQL
+//!
+//! Tokenizador ligero para el editor de comandos: palabras clave,
+//! identificadores, literales de texto, números y comentarios.
+
+use ratatui::{
+    buffer::Buffer,
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    text::{Line, Span},
+};
+use std::ops::Range;
+use unicode_width::UnicodeWidthStr;
+
+/// Palabras clave SQL y comandos RQL resaltados
+pub(crate) const KEYWORDS: &[&str] = &[
+    "ALL", "ALTER", "AND", "AS", "ASC", "ASSERT", "BEGIN", "BETWEEN", "BLOB", "BY", "CASE",
+    "COMMIT", "CREATE", "CROSS", "DELETE", "DESC", "DESCRIBE", "DISTINCT", "DROP", "ELSE", "END",
+    "EXISTS", "EXPORT", "FALSE", "FROM", "FULL", "GROUP", "HAVING", "IMPORT", "IN", "INNER",
+    "INSERT", "INTO", "IS", "JOIN", "LEFT", "LET", "LIKE", "LIMIT", "MAP", "MATCHED", "MERGE",
+    "NOT", "NULL", "OFFSET", "ON", "OR", "ORDER", "OUTER", "PROFILE", "RELEASE", "RIGHT",
+    "ROLLBACK", "SAMPLE", "SAVE", "SCHEMA", "SELECT", "SET", "SHOW", "SOURCE", "SOURCES", "TABLE",
+    "TABLES", "THEN", "TRUE", "UNION", "UNSET", "UPDATE", "USE", "USING", "VALUES", "VIEW",
+    "WATCH", "WHEN", "WHERE", "WITH",
+];
+
+/// Tipo de token reconocido
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum TokenKind {
+    Keyword,
+    Identifier,
+    String,
+    Number,
+    Comment,
+    Other,
+}
+
+impl TokenKind {
+    fn style(self) -> Style {
+        match self {
+            TokenKind::Keyword => Style::default()
+                .fg(Color::Cyan)
+                .add_modifier(Modifier::BOLD),
+            TokenKind::String => Style::default().fg(Color::Green),
+            TokenKind::Number => Style::default().fg(Color::Yellow),
+            TokenKind::Comment => Style::default().fg(Color::DarkGray),
+            TokenKind::Identifier | TokenKind::Other => Style::default(),
+        }
+    }
+}
+
+/// Resaltar líneas SQL/RQL
+///
+/// Los comentarios `/* ... */` pueden abarcar varias líneas; los literales
+/// de texto sin cerrar terminan al final de la línea.
+pub fn highlight_sql(lines: &[String]) -> Vec<Line<'_>> {
+    let mut in_block_comment = false;
+    lines
+        .iter()
+        .map(|line| {
+            let spans: Vec<Span> = tokenize(line, &mut in_block_comment)
+                .into_iter()
+                .map(|(kind, range)| Span::styled(&line[range], kind.style()))
+                .collect();
+            Line::from(spans)
+        })
+        .collect()
+}
+
+/// Aplicar el resaltado sobre un área ya renderizada (p. ej. el `TextArea`)
+///
+/// Sólo se modifican los colores de cada celda, por lo que el scroll y el
+/// cursor del editor se conservan. Cada fila se tokeniza por separado.
+pub fn highlight_buffer(buf: &mut Buffer, area: Rect) {
+    let area = area.intersection(buf.area);
+    for y in area.top()..area.bottom() {
+        // Texto visible de la fila y offset (en bytes) de cada celda
+        let mut text = String::new();
+        let mut cells: Vec<(usize, u16)> = Vec::new();
+        let mut x = area.left();
+        while x < area.right() {
+            let symbol = buf[(x, y)].symbol();
+            cells.push((text.len(), x));
+            text.push_str(symbol);
+            // Las celdas que siguen a un carácter ancho no aportan texto
+            x += (symbol.width() as u16).max(1);
+        }
+
+        let mut in_block_comment = false;
+        for (kind, range) in tokenize(&text, &mut in_block_comment) {
+            if kind == TokenKind::Other || kind == TokenKind::Identifier {
+                continue;
+            }
+            for &(offset, cell_x) in &cells {
+                if range.contains(&offset) {
+                    buf[(cell_x, y)].set_style(kind.style());
+                }
+            }
+        }
+    }
+}
+
+/// Dividir una línea en tokens (rangos en bytes)
+fn tokenize(line: &str, in_block_comment: &mut bool) -> Vec<(TokenKind, Range<usize>)> {
+    let bytes = line.as_bytes();
+    let mut tokens: Vec<(TokenKind, Range<usize>)> = Vec::new();
+    let mut pos = 0;
+
+    while pos < line.len() {
+        let start = pos;
+        let rest = &line[pos..];
+
+        let kind = if *in_block_comment || rest.starts_with("/*") {
+            match rest[if *in_block_comment { 0 } else { 2 }..].find("*/") {
+                Some(end) => {
+                    pos += end + if *in_block_comment { 2 } else { 4 };
+                    *in_block_comment = false;
+                }
+                None => {
+                    pos = line.len();
+                    *in_block_comment = true;
+                }
+            }
+            TokenKind::Comment
+        } else if rest.starts_with("--") {
+            pos = line.len();
+            TokenKind::Comment
+        } else if bytes[pos] == b'\'' {
+            pos += 1;
+            loop {
+                match line[pos..].find('\'') {
+                    // '' es una comilla escapada dentro del literal
+                    Some(end) if line[pos + end + 1..].starts_with('\'') => pos += end + 2,
+                    Some(end) => {
+                        pos += end + 1;
+                        break;
+                    }
+                    None => {
+                        pos = line.len();
+                        break;
+                    }
+                }
+            }
+            TokenKind::String
+        } else if bytes[pos] == b'"' {
+            pos = line[pos + 1..]
+                .find('"')
+                .map_or(line.len(), |end| pos + end + 2);
+            TokenKind::Identifier
+        } else if bytes[pos].is_ascii_digit() {
+            pos += rest
+                .find(|c: char| !(c.is_ascii_digit() || c == '.'))
+                .unwrap_or(rest.len());
+            TokenKind::Number
+        } else if is_word_char(rest.chars().next().unwrap_or(' ')) {
+            pos += rest.find(|c: char| !is_word_char(c)).unwrap_or(rest.len());
+            if KEYWORDS.contains(&line[start..pos].to_uppercase().as_str()) {
+                TokenKind::Keyword
+            } else {
+                TokenKind::Identifier
+            }
+        } else {
+            pos += rest.chars().next().map_or(1, char::len_utf8);
+            TokenKind::Other
+        };
+
+        // Fusionar caracteres sueltos consecutivos en un mismo token
+        match tokens.last_mut() {
+            Some((TokenKind::Other, range)) if kind == TokenKind::Other => range.end = pos,
+            _ => tokens.push((kind, start..pos)),
+        }
+    }
+
+    tokens
+}
+
+/// Caracteres válidos en identificadores y palabras clave
+///
+/// Incluye `$`, `:` y `#` para que los parámetros RQL (`$id`, `:name`, `#1`)
+/// no se resalten como números.
+fn is_word_char(c: char) -> bool {
+    c.is_alphanumeric() || matches!(c, '_' | '$' | ':' | '#')
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ratatui::{backend::TestBackend, widgets::Paragraph, Terminal};
+
+    fn kinds(line: &str) -> Vec<(TokenKind, &str)> {
+        tokenize(line, &mut false)
+            .into_iter()
+            .map(|(kind, range)| (kind, &line[range]))
+            .collect()
+    }
+
+    #[test]
+    fn test_tokenize_sql() {
+        assert_eq!(
+            kinds("select nombre, 'O''Brien' FROM t WHERE id = 42.5 -- fin"),
+            vec![
+                (TokenKind::Keyword, "select"),
+                (TokenKind::Other, " "),
+                (TokenKind::Identifier, "nombre"),
+                (TokenKind::Other, ", "),
+                (TokenKind::String, "'O''Brien'"),
+                (TokenKind::Other, " "),
+                (TokenKind::Keyword, "FROM"),
+                (TokenKind::Other, " "),
+                (TokenKind::Identifier, "t"),
+                (TokenKind::Other, " "),
+                (TokenKind::Keyword, "WHERE"),
+                (TokenKind::Other, " "),
+                (TokenKind::Identifier, "id"),
+                (TokenKind::Other, " = "),
+                (TokenKind::Number, "42.5"),
+                (TokenKind::Other, " "),
+                (TokenKind::Comment, "-- fin"),
+            ]
+        );
+        assert_eq!(kinds("$id1")[0], (TokenKind::Identifier, "$id1"));
+    }
+
+    #[test]
+    fn test_block_comment_spans_lines() {
+        let lines = vec!["SELECT /* varias".to_string(), "lineas */ 1".to_string()];
+        let highlighted = highlight_sql(&lines);
+
+        assert_eq!(highlighted[0].spans[2].content, "/* varias");
+        assert_eq!(highlighted[0].spans[2].style, TokenKind::Comment.style());
+        assert_eq!(highlighted[1].spans[0].content, "lineas */");
+        assert_eq!(highlighted[1].spans[0].style, TokenKind::Comment.style());
+        assert_eq!(highlighted[1].spans[2].style, TokenKind::Number.style());
+    }
+
+    #[test]
+    fn test_highlight_snapshot() {
+        let lines = vec![
+            "SELECT id, 'a' FROM t".to_string(),
+            "WHERE n > 10 -- x".to_string(),
+        ];
+        let mut terminal = Terminal::new(TestBackend::new(24, 2)).unwrap();
+        terminal
+            .draw(|frame| {
+                frame.render_widget(Paragraph::new(highlight_sql(&lines)), frame.area());
+            })
+            .unwrap();
+
+        let keyword = TokenKind::Keyword.style();
+        let mut expected =
+            Buffer::with_lines(["SELECT id, 'a' FROM t   ", "WHERE n > 10 -- x       "]);
+        expected.set_style(Rect::new(0, 0, 6, 1), keyword);
+        expected.set_style(Rect::new(11, 0, 3, 1), TokenKind::String.style());
+        expected.set_style(Rect::new(15, 0, 4, 1), keyword);
+        expected.set_style(Rect::new(0, 1, 5, 1), keyword);
+        expected.set_style(Rect::new(10, 1, 2, 1), TokenKind::Number.style());
+        expected.set_style(Rect::new(13, 1, 4, 1), TokenKind::Comment.style());
+
+        terminal.backend().assert_buffer(&expected);
+    }
+
+    #[test]
+    fn test_highlight_buffer_keeps_cursor_style() {
+        let mut buf = Buffer::with_lines(["SELECT 'x'"]);
+        let cursor = Style::default().add_modifier(Modifier::REVERSED);
+        buf[(0, 0)].set_style(cursor);
+
+        let area = buf.area;
+        highlight_buffer(&mut buf, area);
+
+        let mut expected = Buffer::with_lines(["SELECT 'x'"]);
+        expected.set_style(Rect::new(0, 0, 6, 1), TokenKind::Keyword.style());
+        expected.set_style(Rect::new(7, 0, 3, 1), TokenKind::String.style());
+        expected[(0, 0)].set_style(cursor);
+        assert_eq!(buf, expected);
+    }
+}
@@ -3,17 +3,25 @@
 //! Interfaz de usuario para terminal con componentes para formularios,
 //! tablas de resultados y navegación interactiva.
 
+pub mod completion;
 pub mod components;
 pub mod form_renderer;
+pub mod highlight;
 pub mod layout;
 pub mod noctra_tui;
 pub mod nwm;
+pub mod query_buffer;
 pub mod renderer;
+pub mod watch;
 pub mod widgets;
 
+pub use completion::{CompletionContext, CompletionEngine};
 pub use components::*;
 pub use form_renderer::{FormRenderError, FormRenderer};
+pub use highlight::highlight_sql;
 pub use layout::LayoutManager;
 pub use noctra_tui::{NoctraTui, QueryResults};
 pub use nwm::{NoctraWindowManager, NwmConfig, NwmWindow, UiMode, WindowContent};
+pub use query_buffer::QueryBuffer;
 pub use renderer::{TuiApp, TuiConfig, TuiConfigBuilder, TuiRenderer};
+pub use watch::{RowChange, RowDiff, WatchSpec};
@@ -4,7 +4,7 @@
 //! Incluye layout fijo, modos de trabajo y gestión de comandos SQL/RQL.
 
 use crossterm::{
-    event::{self, Event, KeyCode, KeyEvent},
+    event::{self, Event, KeyCode, KeyEvent, KeyModifiers},
     execute,
     terminal::{disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen},
 };
@@ -18,17 +18,45 @@ use ratatui::{
 };
 use std::collections::HashMap;
 use std::io::{stdout, Stdout};
-use std::time::Duration;
+use std::path::{Path, PathBuf};
+use std::time::{Duration, Instant};
 use tui_textarea::{Input, TextArea};
 
 // Backend integration
-use noctra_core::{Executor, ResultSet, Session, RqlQuery, NoctraError};
-use noctra_parser::{RqlProcessor, RqlStatement};
+use noctra_core::assertion::check_assertion;
+use noctra_core::datasource::ColumnInfo;
+use noctra_core::{
+    CursorId, Executor, ExecutorOptions, NoctraError, Parameters, PlanNode, QueryPlan, ResultSet,
+    RqlQuery, Session, SourceDescriptor, Value,
+};
+use noctra_formlib::loader::{FormLoader, LoaderConfig};
+use noctra_formlib::FormPipelineExecutor;
+use noctra_parser::{RqlProcessor, RqlStatement, TransactionCommand};
+
+use crate::completion::{word_before_cursor, CompletionContext, CompletionEngine, CompletionPopup};
+use crate::form_renderer::FormRenderer;
+use crate::highlight::highlight_buffer;
+use crate::nwm::{NwmConfig, UiMode};
+use crate::query_buffer::{cycle_index, render_tab_bar, QueryBuffer};
+use crate::watch::{diff_rows, RowChange, RowDiff, WatchSpec};
+
+/// Tiempo que el aviso permanece en el header
+const TOAST_DURATION: Duration = Duration::from_secs(2);
+
+/// Filas que se leen del cursor en cada bloque del modo Result
+const RESULT_FETCH_SIZE: usize = 200;
+
+/// Filas que avanzan AvPág/RePág en el modo Result
+const RESULT_SCROLL_PAGE: usize = 20;
+
+/// Espera máxima de `event::poll` en cada vuelta del bucle
+const EVENT_POLL_TIMEOUT: Duration = Duration::from_millis(100);
 
-use crate::nwm::UiMode;
+/// Teclas del modo Form
+const FORM_HELP: &str = " TAB/↑↓=Campo | F5=Enviar | F9=Restablecer | ESC=Cerrar";
 
 /// Estado del TUI de Noctra
-pub struct NoctraTui<'a> {
+pub struct NoctraTui {
     /// Terminal de Ratatui
     terminal: Terminal<CrosstermBackend<Stdout>>,
 
@@ -38,11 +66,23 @@ pub struct NoctraTui<'a> {
     /// Sesión de usuario con variables y estado
     session: Session,
 
+    /// Archivo donde se guarda la sesión tras cada comando (`--session`)
+    session_file: Option<PathBuf>,
+
     /// Modo actual de la interfaz
     mode: UiMode,
 
-    /// Editor de comandos (para modo Command)
-    command_editor: TextArea<'a>,
+    /// Modo al que se vuelve tras resultados/diálogos (Command o Split)
+    home_mode: UiMode,
+
+    /// Buffers de consulta abiertos (una pestaña por buffer)
+    buffers: Vec<QueryBuffer>,
+
+    /// Índice del buffer activo en `buffers`
+    active_buffer_idx: usize,
+
+    /// Número del próximo buffer (para nombrar "Consulta N")
+    next_buffer_number: usize,
 
     /// Historial de comandos ejecutados
     command_history: Vec<String>,
@@ -53,9 +93,6 @@ pub struct NoctraTui<'a> {
     /// Índice en el historial
     history_index: Option<usize>,
 
-    /// Resultados SQL (para modo Result)
-    current_results: Option<QueryResults>,
-
     /// Mensaje de diálogo (para modo Dialog)
     dialog_message: Option<String>,
 
@@ -65,6 +102,34 @@ pub struct NoctraTui<'a> {
     /// Opción seleccionada en diálogo
     dialog_selected: usize,
 
+    /// Nombres de CTEs de la última consulta WITH (pseudo-tablas para DESCRIBE)
+    cte_names: Vec<String>,
+
+    /// Sugerencias de autocompletado abiertas (Tab en modo Command)
+    completion: Option<CompletionPopup>,
+
+    /// Aviso breve en el header y momento en que se mostró
+    toast: Option<(String, Instant)>,
+
+    /// Cursor del resultado en pantalla, mientras le queden filas
+    result_cursor: Option<ResultCursor>,
+
+    /// Consulta vigilada con WATCH (se detiene con Esc)
+    active_watch: Option<WatchSpec>,
+
+    /// Último resultado completo, sobre el que trabajan MAP y FILTER
+    pipeline_result: Option<ResultSet>,
+
+    /// Consulta del último resultado leído por bloques; MAP y FILTER la
+    /// vuelven a ejecutar entera
+    pipeline_query: Option<RqlQuery>,
+
+    /// Formulario abierto con FORM LOAD (modo Form)
+    form: Option<FormRenderer>,
+
+    /// Directorios permitidos para IMPORT/EXPORT (vacío = lista de bloqueo)
+    allowed_paths: Vec<PathBuf>,
+
     /// Flag para salir del TUI
     should_quit: bool,
 }
@@ -78,11 +143,56 @@ pub struct QueryResults {
     /// Filas de datos
     pub rows: Vec<Vec<String>>,
 
+    /// Valores de las filas sin formatear (para exportarlos, ver `export_screenshot`)
+    pub values: Vec<Vec<Value>>,
+
+    /// Columnas numéricas (se alinean a la derecha)
+    pub numeric: Vec<bool>,
+
     /// Mensaje de estado
     pub status: String,
+
+    /// Primera fila visible (scroll del modo Result)
+    pub scroll: usize,
+
+    /// Si quedan filas por leer del cursor
+    pub has_more: bool,
+
+    /// Cambios respecto de la ejecución anterior (consultas vigiladas)
+    pub diff: Option<RowDiff>,
 }
 
-impl<'a> NoctraTui<'a> {
+impl QueryResults {
+    /// Si desplazarse `delta` filas acerca la vista al final de las filas
+    /// leídas mientras el cursor tiene más (hay que leer otro bloque)
+    pub fn needs_more_rows(&self, delta: isize) -> bool {
+        self.has_more
+            && self.scroll.saturating_add_signed(delta) + RESULT_SCROLL_PAGE >= self.rows.len()
+    }
+
+    /// Desplazar la primera fila visible `delta` filas, sin pasar de la última
+    pub fn scroll_by(&mut self, delta: isize) {
+        self.scroll = self
+            .scroll
+            .saturating_add_signed(delta)
+            .min(self.rows.len().saturating_sub(1));
+    }
+}
+
+/// Cursor con filas pendientes del resultado mostrado
+#[derive(Debug, Clone)]
+struct ResultCursor {
+    /// Buffer cuyo resultado se lee
+    buffer_id: uuid::Uuid,
+
+    /// Cursor abierto en el executor
+    cursor: CursorId,
+
+    /// Comando que abrió el cursor (para el mensaje de estado)
+    command: String,
+}
+
+impl NoctraTui {
     /// Crear nueva instancia del TUI con base de datos en memoria
     pub fn new() -> Result<Self, Box<dyn std::error::Error>> {
         let executor = Executor::new_sqlite_memory()?;
@@ -96,7 +206,12 @@ impl<'a> NoctraTui<'a> {
     }
 
     /// Crear TUI con executor personalizado
-    fn with_executor(executor: Executor) -> Result<Self, Box<dyn std::error::Error>> {
+    fn with_executor(mut executor: Executor) -> Result<Self, Box<dyn std::error::Error>> {
+        noctra_duckdb::register_factories(
+            executor.source_registry_mut(),
+            &noctra_duckdb::DuckDBConfig::default(),
+        );
+
         // Configurar terminal
         enable_raw_mode()?;
         let mut stdout = stdout();
@@ -105,46 +220,164 @@ impl<'a> NoctraTui<'a> {
         let backend = CrosstermBackend::new(stdout);
         let terminal = Terminal::new(backend)?;
 
-        // Crear editor de comandos
-        let mut command_editor = TextArea::default();
-        command_editor.set_block(
-            Block::default()
-                .borders(Borders::NONE)
-                .style(Style::default()),
-        );
-        command_editor.set_cursor_line_style(Style::default());
-        command_editor.set_cursor_style(Style::default().add_modifier(Modifier::REVERSED));
-
         // Crear sesión
         let session = Session::new();
+        let home_mode = NwmConfig::default().default_mode;
 
         Ok(Self {
             terminal,
             executor,
             session,
-            mode: UiMode::Command,
-            command_editor,
+            session_file: None,
+            mode: home_mode,
+            home_mode,
+            buffers: vec![QueryBuffer::new("Consulta 1")],
+            active_buffer_idx: 0,
+            next_buffer_number: 2,
             command_history: Vec::new(),
             command_number: 1,
             history_index: None,
-            current_results: None,
             dialog_message: None,
             dialog_options: Vec::new(),
             dialog_selected: 0,
+            cte_names: Vec::new(),
+            completion: None,
+            toast: None,
+            result_cursor: None,
+            active_watch: None,
+            pipeline_result: None,
+            pipeline_query: None,
+            form: None,
+            allowed_paths: Vec::new(),
             should_quit: false,
         })
     }
 
+    /// Aplicar configuración del NWM (modo inicial)
+    pub fn apply_nwm_config(&mut self, config: &NwmConfig) {
+        self.home_mode = match config.default_mode {
+            UiMode::Split => UiMode::Split,
+            _ => UiMode::Command,
+        };
+        self.mode = self.home_mode;
+    }
+
+    /// Aplicar el timeout y el máximo de filas a las consultas del TUI
+    pub fn set_executor_options(&mut self, options: ExecutorOptions) {
+        self.executor.set_options(options);
+    }
+
+    /// Registrar las consultas del TUI en `logger` (ver `Executor::set_query_logger`)
+    pub fn set_query_logger(&mut self, logger: Box<dyn noctra_core::QueryLogger>) {
+        self.executor.set_query_logger(logger);
+    }
+
+    /// Limitar los archivos de IMPORT, EXPORT, SAVE y USE a `roots` (ver `fs_guard`)
+    ///
+    /// Las fábricas de fuentes se registran de nuevo con las raíces; las
+    /// últimas registradas tienen prioridad sobre las de `with_executor`.
+    pub fn set_allowed_paths(&mut self, roots: Vec<PathBuf>) {
+        noctra_duckdb::register_factories(
+            self.executor.source_registry_mut(),
+            &noctra_duckdb::DuckDBConfig {
+                allowed_paths: roots.clone(),
+                ..noctra_duckdb::DuckDBConfig::default()
+            },
+        );
+        self.allowed_paths = roots;
+    }
+
+    /// Restaurar la sesión guardada en `path` y seguir guardándola ahí
+    ///
+    /// Recupera variables e historial y vuelve a ejecutar los USE de las
+    /// fuentes registradas. Si el archivo está dañado se avisa y se empieza
+    /// una sesión nueva.
+    pub fn restore_session(&mut self, path: PathBuf) {
+        let (session, error) = Session::restore(&path);
+        self.session = session;
+        self.session_file = Some(path);
+        self.command_history = self.session.history().to_vec();
+        self.command_number = self.command_history.len() + 1;
+
+        let mut messages = Vec::new();
+        if let Some(e) = error {
+            messages.push(format!(
+                "⚠️ No se pudo restaurar la sesión, se inicia una nueva: {}",
+                e
+            ));
+        }
+        let active = self.session.active_source().map(str::to_string);
+        for source in self.session.sources().to_vec() {
+            if let Err(e) =
+                self.handle_use_source(&source.path, source.alias.as_deref(), &source.options)
+            {
+                messages.push(format!(
+                    "⚠️ No se pudo restaurar la fuente '{}': {}",
+                    source.name(),
+                    e
+                ));
+                // No volver a guardar una fuente que ya no se puede abrir
+                self.session.forget_source(source.name());
+            }
+        }
+        if let Some(alias) = active {
+            if self
+                .executor
+                .source_registry_mut()
+                .set_active(&alias)
+                .is_ok()
+            {
+                self.session.set_active_source(Some(alias));
+            }
+        }
+
+        if messages.is_empty() {
+            // Los USE restaurados abren su diálogo; se vuelve al modo inicial
+            self.mode = self.home_mode;
+            self.dialog_message = None;
+        } else {
+            self.show_error_dialog(&messages.join("\n"));
+        }
+    }
+
+    /// Guardar la sesión en el archivo de `--session`
+    fn autosave_session(&mut self) {
+        let Some(path) = &self.session_file else {
+            return;
+        };
+        let active = self
+            .executor
+            .source_registry()
+            .active_alias()
+            .map(str::to_string);
+        self.session.set_active_source(active);
+        if let Err(e) = self.session.save_to(path) {
+            self.toast = Some((
+                format!("⚠️ No se pudo guardar la sesión: {}", e),
+                Instant::now(),
+            ));
+        }
+    }
+
     /// Ejecutar el TUI principal
     pub fn run(&mut self) -> Result<(), Box<dyn std::error::Error>> {
         while !self.should_quit {
             // Renderizar
             let mode = self.mode;
             let command_number = self.command_number;
-            let current_results = self.current_results.clone();
             let dialog_message = self.dialog_message.clone();
             let dialog_options = self.dialog_options.clone();
             let dialog_selected = self.dialog_selected;
+            let completion = self.completion.clone();
+            if self
+                .toast
+                .as_ref()
+                .is_some_and(|(_, shown)| shown.elapsed() >= TOAST_DURATION)
+            {
+                self.toast = None;
+            }
+            let toast = self.toast.as_ref().map(|(message, _)| message.clone());
+            let watch = self.active_watch.as_ref().map(WatchSpec::indicator);
 
             // Obtener fuente activa y tabla actual
             let active_source = self.executor.source_registry()
@@ -153,7 +386,7 @@ impl<'a> NoctraTui<'a> {
                     let source_name = source.name().to_string();
 
                     // Intentar extraer nombre de tabla del último resultado
-                    if let Some(results) = &current_results {
+                    if let Some(results) = &self.buffers[self.active_buffer_idx].results {
                         // Extraer tabla del comando SQL (ej: "SELECT * FROM clientes")
                         if let Some(table) = Self::extract_table_name(&results.status) {
                             return format!("{}:{}", source_name, table);
@@ -168,21 +401,31 @@ impl<'a> NoctraTui<'a> {
                     frame,
                     mode,
                     command_number,
-                    &mut self.command_editor,
-                    current_results.as_ref(),
+                    &mut self.buffers,
+                    self.active_buffer_idx,
                     dialog_message.as_deref(),
                     &dialog_options,
                     dialog_selected,
                     active_source.as_deref(),
+                    completion.as_ref(),
+                    toast.as_deref(),
+                    watch.as_deref(),
+                    self.form.as_ref(),
                 );
             })?;
 
-            // Procesar eventos
-            if event::poll(Duration::from_millis(100))? {
+            // Procesar eventos (sin esperar más allá de la próxima
+            // ejecución de la consulta vigilada)
+            let timeout = match &self.active_watch {
+                Some(watch) => watch.poll_timeout(Instant::now(), EVENT_POLL_TIMEOUT),
+                None => EVENT_POLL_TIMEOUT,
+            };
+            if event::poll(timeout)? {
                 if let Event::Key(key) = event::read()? {
                     self.handle_key_event(key)?;
                 }
             }
+            self.tick_watch(Instant::now());
         }
 
         // Limpiar terminal
@@ -196,20 +439,25 @@ impl<'a> NoctraTui<'a> {
         frame: &mut Frame,
         mode: UiMode,
         command_number: usize,
-        command_editor: &mut TextArea,
-        current_results: Option<&QueryResults>,
+        buffers: &mut [QueryBuffer],
+        active_buffer_idx: usize,
         dialog_message: Option<&str>,
         dialog_options: &[String],
         dialog_selected: usize,
         active_source: Option<&str>,
+        completion: Option<&CompletionPopup>,
+        toast: Option<&str>,
+        watch: Option<&str>,
+        form: Option<&FormRenderer>,
     ) {
         let size = frame.area();
 
-        // Layout principal: Header + Workspace + Separator + Shortcuts
+        // Layout principal: Header + Pestañas + Workspace + Separator + Shortcuts
         let chunks = Layout::default()
             .direction(Direction::Vertical)
             .constraints([
                 Constraint::Length(3), // Header
+                Constraint::Length(1), // Tab bar
                 Constraint::Min(10),   // Workspace (área dinámica)
                 Constraint::Length(1), // Separator
                 Constraint::Length(7), // Shortcuts bar
@@ -217,38 +465,72 @@ impl<'a> NoctraTui<'a> {
             .split(size);
 
         // Renderizar componentes
-        Self::render_header(frame, chunks[0], mode, command_number, active_source);
+        Self::render_header(
+            frame,
+            chunks[0],
+            mode,
+            command_number,
+            active_source,
+            toast,
+            watch,
+        );
+        render_tab_bar(frame, chunks[1], buffers, active_buffer_idx);
+        let buffer = &mut buffers[active_buffer_idx];
         Self::render_workspace(
             frame,
-            chunks[1],
+            chunks[2],
             mode,
-            command_editor,
-            current_results,
+            &mut buffer.editor,
+            buffer.results.as_ref(),
+            buffer.plan.as_ref(),
             dialog_message,
             dialog_options,
             dialog_selected,
+            form,
         );
-        Self::render_separator(frame, chunks[2]);
-        Self::render_shortcuts(frame, chunks[3]);
+        if let (UiMode::Command, Some(popup)) = (mode, completion) {
+            let (row, col) = buffer.editor.cursor();
+            let x = chunks[2].x.saturating_add(col as u16);
+            let y = chunks[2].y.saturating_add(row as u16);
+            popup.render(frame, chunks[2], x, y);
+        }
+        Self::render_separator(frame, chunks[3]);
+        Self::render_shortcuts(frame, chunks[4]);
     }
 
     /// Renderizar barra de header
-    fn render_header(frame: &mut Frame, area: Rect, mode: UiMode, command_number: usize, active_source: Option<&str>) {
+    fn render_header(
+        frame: &mut Frame,
+        area: Rect,
+        mode: UiMode,
+        command_number: usize,
+        active_source: Option<&str>,
+        toast: Option<&str>,
+        watch: Option<&str>,
+    ) {
         let mode_text = match mode {
             UiMode::Command => "INSERTAR",
             UiMode::Result => "RESULTADO",
             UiMode::Form => "FORMULARIO",
             UiMode::Dialog => "DIÁLOGO",
+            UiMode::Split => "DIVIDIDO",
+            UiMode::PlanView => "PLAN",
         };
 
         let header_text = format!("──( {} ) SQL Noctra 0.1.0", mode_text);
 
         // Agregar indicador de fuente activa si existe
-        let source_text = if let Some(source_name) = active_source {
+        let mut source_text = if let Some(source_name) = active_source {
             format!(" ── Fuente: {} ──", source_name)
         } else {
             String::new()
         };
+        if let Some(indicator) = watch {
+            source_text.push_str(&format!(" ── {} ──", indicator));
+        }
+        if let Some(message) = toast {
+            source_text.push_str(&format!(" ── {} ──", message));
+        }
 
         let cmd_text = format!("Cmd: {}───", command_number);
 
@@ -279,9 +561,11 @@ impl<'a> NoctraTui<'a> {
         mode: UiMode,
         command_editor: &mut TextArea,
         current_results: Option<&QueryResults>,
+        plan: Option<&PlanNode>,
         dialog_message: Option<&str>,
         dialog_options: &[String],
         dialog_selected: usize,
+        form: Option<&FormRenderer>,
     ) {
         match mode {
             UiMode::Command => Self::render_command_mode(frame, area, command_editor),
@@ -293,13 +577,47 @@ impl<'a> NoctraTui<'a> {
                 dialog_options,
                 dialog_selected,
             ),
-            UiMode::Form => Self::render_form_mode(frame, area),
+            UiMode::Form => Self::render_form_mode(frame, area, form),
+            UiMode::Split => {
+                Self::render_split_mode(frame, area, command_editor, current_results)
+            }
+            UiMode::PlanView => Self::render_plan_mode(frame, area, plan),
         }
     }
 
+    /// Calcular paneles del modo Split (editor 40%, resultados 60%)
+    fn split_areas(area: Rect) -> (Rect, Rect) {
+        let chunks = Layout::default()
+            .direction(Direction::Vertical)
+            .constraints([Constraint::Percentage(40), Constraint::Percentage(60)])
+            .split(area);
+        (chunks[0], chunks[1])
+    }
+
+    /// Renderizar modo Split (editor arriba, resultados abajo)
+    fn render_split_mode(
+        frame: &mut Frame,
+        area: Rect,
+        command_editor: &TextArea,
+        current_results: Option<&QueryResults>,
+    ) {
+        let (editor_area, results_area) = Self::split_areas(area);
+
+        let editor_block = Block::default()
+            .borders(Borders::BOTTOM)
+            .border_style(Style::default().fg(Color::DarkGray));
+        let editor_inner = editor_block.inner(editor_area);
+        frame.render_widget(editor_block, editor_area);
+        frame.render_widget(command_editor, editor_inner);
+        highlight_buffer(frame.buffer_mut(), editor_inner);
+
+        Self::render_result_mode(frame, results_area, current_results);
+    }
+
     /// Renderizar modo Command (editor de SQL)
     fn render_command_mode(frame: &mut Frame, area: Rect, command_editor: &TextArea) {
         frame.render_widget(command_editor, area);
+        highlight_buffer(frame.buffer_mut(), area);
     }
 
     /// Renderizar modo Result (tabla de resultados)
@@ -314,10 +632,33 @@ impl<'a> NoctraTui<'a> {
                 .style(Style::default().fg(Color::Yellow))
                 .height(1);
 
-            let rows = results.rows.iter().map(|row| {
-                let cells = row.iter().map(|cell| Cell::from(cell.as_str()));
-                Row::new(cells).height(1)
+            // Consultas vigiladas: filas nuevas en verde, valores cambiados
+            // en amarillo y, al final, las filas borradas en rojo
+            let diff = results.diff.as_ref();
+            let current = results.rows.iter().enumerate().map(|(index, row)| {
+                let change = diff.map_or(&RowChange::Unchanged, |diff| diff.change(index));
+                let cells = row.iter().enumerate().map(|(column, cell)| match change {
+                    RowChange::Changed(columns) if columns.contains(&column) => {
+                        result_cell(results, column, cell).style(Style::default().fg(Color::Yellow))
+                    }
+                    _ => result_cell(results, column, cell),
+                });
+                let row = Row::new(cells).height(1);
+                match change {
+                    RowChange::Added => row.style(Style::default().fg(Color::Green)),
+                    _ => row,
+                }
+            });
+            let removed = diff.into_iter().flat_map(|diff| &diff.removed).map(|row| {
+                let cells = row
+                    .iter()
+                    .enumerate()
+                    .map(|(column, cell)| result_cell(results, column, cell));
+                Row::new(cells)
+                    .height(1)
+                    .style(Style::default().fg(Color::Red).add_modifier(Modifier::CROSSED_OUT))
             });
+            let rows = current.chain(removed).skip(results.scroll);
 
             // Calcular ancho de columnas automáticamente
             let col_widths: Vec<Constraint> = results
@@ -357,6 +698,28 @@ impl<'a> NoctraTui<'a> {
         }
     }
 
+    /// Renderizar modo PlanView (árbol de operadores sangrado)
+    fn render_plan_mode(frame: &mut Frame, area: Rect, plan: Option<&PlanNode>) {
+        let Some(plan) = plan else {
+            let empty = Paragraph::new("No hay plan para mostrar")
+                .style(Style::default().fg(Color::Gray))
+                .alignment(Alignment::Center);
+            frame.render_widget(empty, area);
+            return;
+        };
+
+        let lines: Vec<Line> = plan.tree_lines().into_iter().map(Line::from).collect();
+        let tree = Paragraph::new(lines)
+            .block(
+                Block::default()
+                    .borders(Borders::ALL)
+                    .title(" EXPLAIN ANALYZE (Esc: volver) "),
+            )
+            .style(Style::default().fg(Color::White));
+
+        frame.render_widget(tree, area);
+    }
+
     /// Renderizar modo Dialog (confirmaciones)
     fn render_dialog_mode(
         frame: &mut Frame,
@@ -366,9 +729,12 @@ impl<'a> NoctraTui<'a> {
         dialog_selected: usize,
     ) {
         if let Some(message) = dialog_message {
-            // Calcular tamaño de la ventana modal
+            // Calcular tamaño de la ventana modal: los mensajes de más de
+            // dos líneas (p. ej. con el fragmento de un error de sintaxis)
+            // la agrandan y se alinean a la izquierda para conservar el `^`
+            let message_lines = message.lines().count().max(2) as u16;
             let dialog_width = 60.min(area.width);
-            let dialog_height = 8.min(area.height);
+            let dialog_height = (message_lines + 6).min(area.height);
 
             let dialog_area = Rect {
                 x: (area.width.saturating_sub(dialog_width)) / 2,
@@ -391,15 +757,16 @@ impl<'a> NoctraTui<'a> {
             let chunks = Layout::default()
                 .direction(Direction::Vertical)
                 .constraints([
-                    Constraint::Length(2), // Mensaje
+                    Constraint::Length(message_lines), // Mensaje
                     Constraint::Length(1), // Espacio
                     Constraint::Length(3), // Botones
                 ])
                 .split(inner);
 
             // Mensaje
+            let alignment = if message_lines > 2 { Alignment::Left } else { Alignment::Center };
             let msg = Paragraph::new(message)
-                .alignment(Alignment::Center)
+                .alignment(alignment)
                 .style(Style::default().fg(Color::White));
 
             frame.render_widget(msg, chunks[0]);
@@ -435,13 +802,17 @@ impl<'a> NoctraTui<'a> {
         }
     }
 
-    /// Renderizar modo Form (pendiente de implementación)
-    fn render_form_mode(frame: &mut Frame, area: Rect) {
-        let placeholder = Paragraph::new("Modo formulario - En desarrollo")
-            .style(Style::default().fg(Color::Gray))
-            .alignment(Alignment::Center);
-
-        frame.render_widget(placeholder, area);
+    /// Renderizar modo Form
+    fn render_form_mode(frame: &mut Frame, area: Rect, form: Option<&FormRenderer>) {
+        match form {
+            Some(form) => form.render(frame, area),
+            None => {
+                let placeholder = Paragraph::new("Sin formulario (use FORM LOAD 'archivo.toml')")
+                    .style(Style::default().fg(Color::Gray))
+                    .alignment(Alignment::Center);
+                frame.render_widget(placeholder, area);
+            }
+        }
     }
 
     /// Renderizar línea separadora
@@ -458,13 +829,18 @@ impl<'a> NoctraTui<'a> {
             ("F5", "Procesar comando"),
             ("End", "Terminar sesión de Noctra"),
             ("F1", "Ayuda comandos editor"),
+            ("F6", "Vista dividida editor/resultados"),
             ("F8", "Interrumpir procesamiento"),
             ("Prox. pantal", "Comando siguiente"),
             ("Pantall. pre", "Comando anterior"),
             ("Insert", "Insertar espacio"),
+            ("Ctrl+E", "Plan con EXPLAIN ANALYZE"),
             ("Delete", "Borrar un carácter"),
             ("Alt+r", "Leer desde archivo"),
             ("Alt+w", "Grabar en archivo"),
+            ("Alt+s", "Exportar resultado a CSV"),
+            ("Ctrl+T / W", "Abrir / cerrar pestaña"),
+            ("Ctrl+Tab", "Pestaña siguiente"),
         ];
 
         let lines: Vec<Line> = shortcuts
@@ -495,22 +871,178 @@ impl<'a> NoctraTui<'a> {
 
     /// Manejar eventos de teclado
     fn handle_key_event(&mut self, key: KeyEvent) -> Result<(), Box<dyn std::error::Error>> {
+        let buffer_mode = matches!(self.mode, UiMode::Command | UiMode::Split | UiMode::Result);
+        if buffer_mode && self.handle_buffer_keys(key) {
+            return Ok(());
+        }
         match self.mode {
             UiMode::Command => self.handle_command_keys(key)?,
             UiMode::Result => self.handle_result_keys(key)?,
             UiMode::Dialog => self.handle_dialog_keys(key)?,
             UiMode::Form => self.handle_form_keys(key)?,
+            UiMode::Split => self.handle_split_keys(key)?,
+            UiMode::PlanView => self.handle_plan_keys(key)?,
+        }
+        self.release_hidden_result_cursor();
+        Ok(())
+    }
+
+    /// Atajos de pestañas (Ctrl+T, Ctrl+W, Ctrl+Tab y Ctrl+Shift+Tab) y del
+    /// plan de la consulta (Ctrl+E)
+    ///
+    /// Devuelve `true` si la tecla se consumió.
+    fn handle_buffer_keys(&mut self, key: KeyEvent) -> bool {
+        if !key.modifiers.contains(KeyModifiers::CONTROL) {
+            return false;
+        }
+        match key.code {
+            KeyCode::Char('t') => self.open_buffer(),
+            KeyCode::Char('w') => self.request_close_buffer(),
+            KeyCode::Tab if key.modifiers.contains(KeyModifiers::SHIFT) => self.cycle_buffer(false),
+            KeyCode::Tab => self.cycle_buffer(true),
+            KeyCode::BackTab => self.cycle_buffer(false),
+            KeyCode::Char('e') => self.explain_editor_query(),
+            _ => return false,
+        }
+        true
+    }
+
+    /// Buffer de consulta activo
+    fn active_buffer(&self) -> &QueryBuffer {
+        &self.buffers[self.active_buffer_idx]
+    }
+
+    /// Buffer de consulta activo (mutable)
+    fn active_buffer_mut(&mut self) -> &mut QueryBuffer {
+        &mut self.buffers[self.active_buffer_idx]
+    }
+
+    /// Abrir un buffer vacío y activarlo (Ctrl+T)
+    fn open_buffer(&mut self) {
+        let name = format!("Consulta {}", self.next_buffer_number);
+        self.buffers.push(QueryBuffer::new(name));
+        self.next_buffer_number += 1;
+        self.active_buffer_idx = self.buffers.len() - 1;
+        self.completion = None;
+        self.history_index = None;
+    }
+
+    /// Pasar a la pestaña siguiente o anterior
+    fn cycle_buffer(&mut self, forward: bool) {
+        self.active_buffer_idx = cycle_index(self.active_buffer_idx, self.buffers.len(), forward);
+        self.completion = None;
+        self.history_index = None;
+    }
+
+    /// Cerrar el buffer activo (Ctrl+W), confirmando si tiene texto sin ejecutar
+    fn request_close_buffer(&mut self) {
+        if !self.active_buffer().has_unsaved_changes() {
+            self.close_active_buffer();
+            return;
+        }
+        self.dialog_message = Some(format!(
+            "La pestaña '{}' tiene texto sin ejecutar. ¿Cerrarla igualmente?",
+            self.active_buffer().name
+        ));
+        self.dialog_options = vec!["CERRAR".to_string(), "CANCELAR".to_string()];
+        self.dialog_selected = 1; // Default: CANCELAR
+        self.mode = UiMode::Dialog;
+    }
+
+    /// Cerrar el buffer activo; al cerrar el último se abre uno vacío
+    fn close_active_buffer(&mut self) {
+        self.buffers.remove(self.active_buffer_idx);
+        if self.buffers.is_empty() {
+            self.open_buffer();
+        }
+        self.active_buffer_idx = self.active_buffer_idx.min(self.buffers.len() - 1);
+        self.completion = None;
+        self.history_index = None;
+    }
+
+    /// Alternar entre modo Command y Split (F6)
+    fn toggle_split_mode(&mut self) {
+        self.home_mode = if self.home_mode == UiMode::Split {
+            UiMode::Command
+        } else {
+            UiMode::Split
+        };
+        self.mode = self.home_mode;
+    }
+
+    /// Manejar teclas en modo Split
+    fn handle_split_keys(&mut self, key: KeyEvent) -> Result<(), Box<dyn std::error::Error>> {
+        match key.code {
+            KeyCode::F(5) => {
+                // Ejecutar y actualizar el panel inferior sin salir del editor
+                self.execute_command()?;
+                if self.mode == UiMode::Result {
+                    self.mode = UiMode::Split;
+                }
+            }
+            KeyCode::F(6) => self.toggle_split_mode(),
+            KeyCode::Char('s') if key.modifiers.contains(KeyModifiers::ALT) => {
+                self.export_results_shortcut();
+            }
+            KeyCode::Esc if self.active_watch.is_some() => {
+                self.stop_watch();
+            }
+            KeyCode::End => self.show_exit_dialog(),
+            KeyCode::PageDown => self.next_command(),
+            KeyCode::PageUp => self.previous_command(),
+            _ => {
+                self.active_buffer_mut().editor.input(Input::from(key));
+            }
         }
         Ok(())
     }
 
     /// Manejar teclas en modo Command
     fn handle_command_keys(&mut self, key: KeyEvent) -> Result<(), Box<dyn std::error::Error>> {
+        if let Some(popup) = self.completion.as_mut() {
+            match key.code {
+                KeyCode::Down => {
+                    popup.next();
+                    return Ok(());
+                }
+                KeyCode::Up => {
+                    popup.previous();
+                    return Ok(());
+                }
+                KeyCode::Enter | KeyCode::Tab => {
+                    if let Some(item) = popup.selected_item() {
+                        let editor = &mut self.buffers[self.active_buffer_idx].editor;
+                        Self::apply_completion(editor, &popup.prefix, item);
+                    }
+                    self.completion = None;
+                    return Ok(());
+                }
+                KeyCode::Esc => {
+                    self.completion = None;
+                    return Ok(());
+                }
+                // Cualquier otra tecla cierra la lista y se procesa normalmente
+                _ => self.completion = None,
+            }
+        }
+
         match key.code {
+            KeyCode::Tab => {
+                // Autocompletar palabra bajo el cursor
+                self.complete_word(key);
+            }
             KeyCode::F(5) => {
                 // Ejecutar comando
                 self.execute_command()?;
             }
+            KeyCode::F(6) => {
+                // Alternar vista dividida
+                self.toggle_split_mode();
+            }
+            KeyCode::Char('s') if key.modifiers.contains(KeyModifiers::ALT) => {
+                // Exportar el resultado actual a CSV
+                self.export_results_shortcut();
+            }
             KeyCode::End => {
                 // Mostrar diálogo de salida
                 self.show_exit_dialog();
@@ -525,53 +1057,286 @@ impl<'a> NoctraTui<'a> {
             }
             _ => {
                 // Pasar la tecla al editor
-                self.command_editor.input(Input::from(key));
+                self.active_buffer_mut().editor.input(Input::from(key));
             }
         }
         Ok(())
     }
 
+    /// Autocompletar la palabra a la izquierda del cursor
+    ///
+    /// Una única sugerencia se inserta directamente; varias abren la lista.
+    /// Sin palabra, el Tab llega al editor.
+    fn complete_word(&mut self, key: KeyEvent) {
+        let editor = &self.active_buffer().editor;
+        let (row, col) = editor.cursor();
+        let line = editor.lines().get(row).cloned().unwrap_or_default();
+        let prefix = word_before_cursor(&line, col);
+        if prefix.is_empty() {
+            self.active_buffer_mut().editor.input(Input::from(key));
+            return;
+        }
+
+        let context = self
+            .executor
+            .catalog()
+            .map(|catalog| CompletionContext::from_catalog(&catalog))
+            .unwrap_or_default();
+        let mut items = CompletionEngine::new().complete(prefix, context);
+        match items.len() {
+            0 => {}
+            1 => {
+                let item = items.remove(0);
+                Self::apply_completion(&mut self.active_buffer_mut().editor, prefix, &item);
+            }
+            _ => self.completion = Some(CompletionPopup::new(prefix, items)),
+        }
+    }
+
+    /// Reemplazar `prefix` (a la izquierda del cursor) por la sugerencia
+    fn apply_completion(command_editor: &mut TextArea, prefix: &str, item: &str) {
+        for _ in prefix.chars() {
+            command_editor.delete_char();
+        }
+        command_editor.insert_str(item);
+    }
+
     /// Manejar teclas en modo Result
     fn handle_result_keys(&mut self, key: KeyEvent) -> Result<(), Box<dyn std::error::Error>> {
         match key.code {
             KeyCode::Esc | KeyCode::Char('q') => {
-                // Volver a modo Command
-                self.mode = UiMode::Command;
+                // Detener la consulta vigilada y volver a modo Command (o Split)
+                self.stop_watch();
+                self.mode = self.home_mode;
+            }
+            KeyCode::Char('s') if key.modifiers.contains(KeyModifiers::ALT) => {
+                self.export_results_shortcut();
             }
             KeyCode::End => {
                 self.show_exit_dialog();
             }
+            KeyCode::Down | KeyCode::Char('j') => self.scroll_results(1),
+            KeyCode::Up | KeyCode::Char('k') => self.scroll_results(-1),
+            KeyCode::PageDown => self.scroll_results(RESULT_SCROLL_PAGE as isize),
+            KeyCode::PageUp => self.scroll_results(-(RESULT_SCROLL_PAGE as isize)),
+            KeyCode::Home => self.scroll_results(isize::MIN),
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Manejar teclas en modo PlanView
+    fn handle_plan_keys(&mut self, key: KeyEvent) -> Result<(), Box<dyn std::error::Error>> {
+        match key.code {
+            KeyCode::Esc | KeyCode::Char('q') => self.mode = self.home_mode,
+            KeyCode::End => self.show_exit_dialog(),
             _ => {}
         }
         Ok(())
     }
 
+    /// Ejecutar EXPLAIN ANALYZE sobre el texto del editor y mostrar el plan
+    /// (Ctrl+E)
+    fn explain_editor_query(&mut self) {
+        let text = self.active_buffer().text();
+        let sql = text.trim().trim_end_matches(';').trim_end();
+        if sql.is_empty() {
+            return;
+        }
+
+        match self.executor.explain_analyze(&self.session, sql) {
+            Ok(QueryPlan {
+                root: Some(root), ..
+            }) => {
+                self.active_buffer_mut().plan = Some(root);
+                self.mode = UiMode::PlanView;
+            }
+            Ok(_) => self.show_error_dialog("❌ EXPLAIN ANALYZE no devolvió operadores"),
+            Err(e) => self.show_error_dialog(&format!("❌ Error en EXPLAIN ANALYZE: {}", e)),
+        }
+    }
+
+    /// Desplazar el resultado, leyendo otro bloque del cursor si hace falta
+    fn scroll_results(&mut self, delta: isize) {
+        let needs_more = self
+            .active_buffer()
+            .results
+            .as_ref()
+            .is_some_and(|results| results.needs_more_rows(delta));
+        if needs_more {
+            self.fetch_more_results();
+        }
+        if let Some(results) = self.active_buffer_mut().results.as_mut() {
+            results.scroll_by(delta);
+        }
+    }
+
+    /// Añadir al resultado activo el siguiente bloque de su cursor
+    fn fetch_more_results(&mut self) {
+        let Some(result_cursor) = self.result_cursor.clone() else {
+            return;
+        };
+        if result_cursor.buffer_id != self.active_buffer().id {
+            return;
+        }
+
+        let page =
+            self.executor
+                .fetch_cursor(&self.session, result_cursor.cursor, RESULT_FETCH_SIZE);
+        let Some(results) = self.active_buffer_mut().results.as_mut() else {
+            return;
+        };
+        let error = match page {
+            Ok(page) => {
+                results.rows.extend(page.result_set.rows.iter().map(|row| {
+                    row.values
+                        .iter()
+                        .map(noctra_core::Value::display_cell)
+                        .collect()
+                }));
+                results
+                    .values
+                    .extend(page.result_set.rows.into_iter().map(|row| row.values));
+                results.has_more = page.has_more;
+                results.status =
+                    cursor_status(results.rows.len(), page.has_more, &result_cursor.command);
+                None
+            }
+            Err(e) => {
+                results.has_more = false;
+                Some(e)
+            }
+        };
+
+        // Agotado (o fallido), el executor ya cerró el cursor
+        if !results.has_more {
+            self.result_cursor = None;
+        }
+        if let Some(e) = error {
+            self.toast = Some((format!("❌ {}", e), Instant::now()));
+        }
+    }
+
+    /// Cerrar el cursor del resultado si ya no está a la vista
+    ///
+    /// En SQLite un cursor con filas pendientes retiene la conexión, así que
+    /// sólo se mantiene mientras su buffer se muestra en modo Result; el
+    /// resto de comandos (y el autocompletado) necesitan la base libre.
+    fn release_hidden_result_cursor(&mut self) {
+        let visible = self.result_cursor.as_ref().is_some_and(|result_cursor| {
+            self.mode == UiMode::Result && result_cursor.buffer_id == self.active_buffer().id
+        });
+        if !visible {
+            self.release_result_cursor();
+        }
+    }
+
+    /// Cerrar el cursor del resultado; las filas ya leídas se conservan
+    fn release_result_cursor(&mut self) {
+        let Some(result_cursor) = self.result_cursor.take() else {
+            return;
+        };
+        self.executor
+            .close_cursor(&self.session, result_cursor.cursor);
+        if let Some(buffer) = self
+            .buffers
+            .iter_mut()
+            .find(|buffer| buffer.id == result_cursor.buffer_id)
+        {
+            if let Some(results) = buffer.results.as_mut().filter(|results| results.has_more) {
+                results.has_more = false;
+                results.status = format!(
+                    "{} fila(s) leída(s); cursor cerrado, vuelve a ejecutar para ver el resto - Comando: {}",
+                    results.rows.len(),
+                    result_cursor.command
+                );
+            }
+        }
+    }
+
+    /// Guardar el resultado mostrado como CSV en el directorio actual
+    ///
+    /// El archivo se llama `noctra_export_AAAAMMDD_HHMMSS.csv` (ver
+    /// `export_buffer`); la ruta se anuncia con un aviso en el header.
+    pub fn export_screenshot(&mut self) -> Result<PathBuf, Box<dyn std::error::Error>> {
+        let path = Self::export_buffer(self.active_buffer(), Path::new("."))?;
+        self.toast = Some((format!("Exportado a {}", path.display()), Instant::now()));
+        Ok(path)
+    }
+
+    /// Guardar el resultado de `buffer` como CSV con nombre fechado dentro de `dir`
+    ///
+    /// Nunca sobrescribe: si ya hay un archivo con ese nombre se añade
+    /// `_1`, `_2`... Los valores se escriben como en EXPORT (NULL es un
+    /// campo vacío).
+    fn export_buffer(
+        buffer: &QueryBuffer,
+        dir: &Path,
+    ) -> Result<PathBuf, Box<dyn std::error::Error>> {
+        let results = buffer.results.as_ref().ok_or_else(|| {
+            NoctraError::Validation("No hay resultados para exportar".to_string())
+        })?;
+        Self::write_results_csv(results, dir)
+    }
+
+    /// Alt+S: exportar y avisar del resultado (también de los errores)
+    fn export_results_shortcut(&mut self) {
+        if let Err(e) = self.export_screenshot() {
+            self.toast = Some((format!("❌ {}", e), Instant::now()));
+        }
+    }
+
+    /// Escribir `results` como CSV con nombre fechado dentro de `dir`
+    fn write_results_csv(
+        results: &QueryResults,
+        dir: &Path,
+    ) -> Result<PathBuf, Box<dyn std::error::Error>> {
+        use std::io::Write;
+
+        let stem = format!(
+            "noctra_export_{}",
+            chrono::Local::now().format("%Y%m%d_%H%M%S")
+        );
+        let (path, file) = create_new_numbered(dir, &stem, "csv")?;
+
+        let mut file = std::io::BufWriter::new(file);
+        let header: Vec<String> = results.columns.iter().map(|c| csv_field(c, ',')).collect();
+        writeln!(file, "{}", header.join(","))?;
+        for row in &results.values {
+            let fields: Vec<String> = row.iter().map(|v| csv_value(v, ',')).collect();
+            writeln!(file, "{}", fields.join(","))?;
+        }
+        file.flush()?;
+
+        Ok(path)
+    }
+
     /// Manejar teclas en modo Dialog
     fn handle_dialog_keys(&mut self, key: KeyEvent) -> Result<(), Box<dyn std::error::Error>> {
         match key.code {
-            KeyCode::Left => {
-                if self.dialog_selected > 0 {
-                    self.dialog_selected -= 1;
-                }
+            KeyCode::Left if self.dialog_selected > 0 => {
+                self.dialog_selected -= 1;
             }
-            KeyCode::Right => {
-                if self.dialog_selected < self.dialog_options.len().saturating_sub(1) {
-                    self.dialog_selected += 1;
-                }
+            KeyCode::Right if self.dialog_selected < self.dialog_options.len().saturating_sub(1) => {
+                self.dialog_selected += 1;
             }
             KeyCode::Enter => {
                 // Ejecutar acción según la opción seleccionada
                 if self.dialog_options[self.dialog_selected] == "SI" {
                     self.should_quit = true;
+                } else if self.dialog_options[self.dialog_selected] == "CERRAR" {
+                    self.close_active_buffer();
+                    self.mode = self.home_mode;
+                    self.dialog_message = None;
                 } else {
-                    // Cancelar - volver a Command
-                    self.mode = UiMode::Command;
+                    // Cancelar - volver a Command (o Split)
+                    self.mode = self.home_mode;
                     self.dialog_message = None;
                 }
             }
             KeyCode::Esc => {
                 // Cancelar
-                self.mode = UiMode::Command;
+                self.mode = self.home_mode;
                 self.dialog_message = None;
             }
             _ => {}
@@ -580,28 +1345,109 @@ impl<'a> NoctraTui<'a> {
     }
 
     /// Manejar teclas en modo Form
+    ///
+    /// Tab y las flechas cambian de campo, lo escrito va al campo enfocado,
+    /// F5 envía el formulario, F9 lo restablece y Esc lo cierra.
     fn handle_form_keys(&mut self, key: KeyEvent) -> Result<(), Box<dyn std::error::Error>> {
-        // TODO: Implementar cuando tengamos formularios integrados
-        if key.code == KeyCode::Esc {
-            self.mode = UiMode::Command;
+        let Some(form) = self.form.as_mut() else {
+            self.mode = self.home_mode;
+            return Ok(());
+        };
+
+        match key.code {
+            KeyCode::Esc => {
+                self.form = None;
+                self.mode = self.home_mode;
+            }
+            KeyCode::Tab | KeyCode::Down => form.focus_next(),
+            KeyCode::BackTab | KeyCode::Up => form.focus_previous(),
+            KeyCode::F(5) => self.submit_form()?,
+            KeyCode::F(9) => form.reset(),
+            KeyCode::Backspace => {
+                if let Some(field) = form.get_focused_field().map(str::to_string) {
+                    let mut value = form.get_field_value(&field).unwrap_or("").to_string();
+                    value.pop();
+                    form.set_field_value(&field, value)?;
+                }
+            }
+            KeyCode::Char(c) if !key.modifiers.contains(KeyModifiers::CONTROL) => {
+                if let Some(field) = form.get_focused_field().map(str::to_string) {
+                    let mut value = form.get_field_value(&field).unwrap_or("").to_string();
+                    value.push(c);
+                    form.set_field_value(&field, value)?;
+                }
+            }
+            _ => {}
+        }
+        Ok(())
+    }
+
+    /// Abrir un formulario FDL2 en modo Form
+    pub fn load_form(&mut self, path: &str) -> Result<(), Box<dyn std::error::Error>> {
+        // Validar ruta de archivo (sandboxing)
+        let path = noctra_core::fs_guard::check_path(path, &self.allowed_paths)?;
+        let form = FormLoader::new(LoaderConfig::default()).load_from_path(&path)?;
+        self.form = Some(FormRenderer::new(form).with_help(FORM_HELP));
+        self.mode = UiMode::Form;
+        Ok(())
+    }
+
+    /// Enviar el formulario: ejecutar su acción por defecto con los valores
+    /// de los campos como parámetros nombrados
+    ///
+    /// Si la validación falla los errores quedan bajo cada campo y no se
+    /// ejecuta nada. Una acción que devuelve filas pasa al modo Result; las
+    /// escrituras y los errores se informan en el header sin salir del
+    /// formulario.
+    fn submit_form(&mut self) -> Result<(), Box<dyn std::error::Error>> {
+        let Some(form) = self.form.as_mut() else {
+            return Ok(());
+        };
+        if form.validate_all().is_err() {
+            return Ok(());
+        }
+        let Some(action_name) = form.default_action().map(str::to_string) else {
+            self.toast = Some((
+                "❌ El formulario no tiene acciones".to_string(),
+                Instant::now(),
+            ));
+            return Ok(());
+        };
+
+        let action = &form.form.actions[&action_name];
+        let params = Parameters::from_text_map(form.get_values());
+        match FormPipelineExecutor::run_action(action, params, &self.executor) {
+            Ok(outcome) => match outcome.data {
+                Some(data) => {
+                    let command = format!("{} ({})", form.form.title, action_name);
+                    self.show_execution_result(Ok(data), &command)?;
+                }
+                None => self.toast = Some((format!("✅ {}", outcome.message), Instant::now())),
+            },
+            Err(e) => self.toast = Some((format!("❌ {}", e), Instant::now())),
         }
         Ok(())
     }
 
     /// Convertir ResultSet de noctra-core a QueryResults del TUI
-    fn convert_result_set(&self, result_set: ResultSet, command: &str) -> QueryResults {
+    fn convert_result_set(result_set: ResultSet, command: &str) -> QueryResults {
         // Extraer nombres de columnas
         let columns: Vec<String> = result_set
             .columns
             .iter()
             .map(|col| col.name.clone())
             .collect();
+        let numeric: Vec<bool> = result_set
+            .columns
+            .iter()
+            .map(|col| col.is_numeric())
+            .collect();
 
         // Convertir valores a strings usando Display trait
         let rows: Vec<Vec<String>> = result_set
             .rows
             .iter()
-            .map(|row| row.values.iter().map(|value| value.to_string()).collect())
+            .map(|row| row.values.iter().map(noctra_core::Value::display_cell).collect())
             .collect();
 
         // Construir mensaje de estado
@@ -626,6 +1472,12 @@ impl<'a> NoctraTui<'a> {
             let row_count = result_set.row_count();
             if row_count == 0 {
                 format!("Sin resultados - Comando: {}", command.trim())
+            } else if result_set.truncated {
+                format!(
+                    "Mostrando las primeras {} fila(s) (resultado truncado) - Comando: {}",
+                    row_count,
+                    command.trim()
+                )
             } else {
                 format!(
                     "{} fila(s) retornada(s) - Comando: {}",
@@ -638,13 +1490,18 @@ impl<'a> NoctraTui<'a> {
         QueryResults {
             columns,
             rows,
+            values: result_set.rows.into_iter().map(|row| row.values).collect(),
+            numeric,
             status,
+            scroll: 0,
+            has_more: false,
+            diff: None,
         }
     }
 
     /// Ejecutar comando SQL actual
     fn execute_command(&mut self) -> Result<(), Box<dyn std::error::Error>> {
-        let command_text = self.command_editor.lines().join("\n");
+        let command_text = self.active_buffer().text();
 
         if command_text.trim().is_empty() {
             return Ok(());
@@ -652,11 +1509,24 @@ impl<'a> NoctraTui<'a> {
 
         // Agregar al historial
         self.command_history.push(command_text.clone());
+        self.session.push_history(command_text.as_str());
         self.command_number += 1;
 
+        let result = self.run_command_text(&command_text);
+        self.autosave_session();
+        result?;
+
+        // Limpiar editor para próximo comando
+        self.clear_command_editor();
+
+        Ok(())
+    }
+
+    /// Parsear y ejecutar los statements de un comando
+    fn run_command_text(&mut self, command_text: &str) -> Result<(), Box<dyn std::error::Error>> {
         // Parsear con RqlProcessor
         // Ejecutar en un thread separado para evitar conflictos con runtime de Tokio
-        let cmd = command_text.clone();
+        let cmd = command_text.to_string();
         let result = std::thread::spawn(move || {
             let rt = tokio::runtime::Runtime::new().unwrap();
             let processor = RqlProcessor::new();
@@ -670,18 +1540,31 @@ impl<'a> NoctraTui<'a> {
             Err(_) => return Err("Thread panic during parsing".into()),
         };
 
+        // Un comando nuevo reemplaza a la consulta vigilada
+        self.active_watch = None;
+
         match ast {
             Ok(ast) => {
-                // Procesar cada statement
+                // Recordar CTEs definidas para resolver DESCRIBE sobre ellas
+                if !ast.cte_names.is_empty() {
+                    self.cte_names = ast.cte_names.clone();
+                }
+
+                // Procesar cada statement; el cursor de una consulta anterior
+                // del mismo comando se cierra antes de seguir
                 for statement in &ast.statements {
+                    self.release_result_cursor();
                     match statement {
-                        RqlStatement::Sql { sql, .. } => {
+                        RqlStatement::Sql { sql, is_duckdb_specific, .. } => {
                             // Ejecutar SQL normal con execute_rql (usa fuente activa si existe)
-                            self.execute_sql_statement(sql)?;
+                            self.execute_sql_statement(sql, *is_duckdb_specific)?;
                         }
                         RqlStatement::UseSource { path, alias, options } => {
                             self.handle_use_source(path, alias.as_deref(), options)?;
                         }
+                        RqlStatement::UseSourceAlias { alias } => {
+                            self.handle_use_source_alias(alias)?;
+                        }
                         RqlStatement::ShowSources => {
                             self.handle_show_sources()?;
                         }
@@ -694,6 +1577,27 @@ impl<'a> NoctraTui<'a> {
                         RqlStatement::Describe { source, table } => {
                             self.handle_describe(source.as_deref(), table)?;
                         }
+                        RqlStatement::Profile { source, table } => {
+                            self.handle_profile(source.as_deref(), table)?;
+                        }
+                        RqlStatement::Sample { source, table, limit } => {
+                            self.handle_sample(source.as_deref(), table, *limit)?;
+                        }
+                        RqlStatement::Checkpoint { source } => {
+                            self.handle_checkpoint(source.as_deref())?;
+                        }
+                        RqlStatement::Watch { interval_secs, sql } => {
+                            self.handle_watch(*interval_secs, sql);
+                        }
+                        RqlStatement::CreateView { name, sql, statement } => {
+                            self.handle_create_view(name, sql, statement)?;
+                        }
+                        RqlStatement::Merge { .. } => {
+                            self.handle_merge(statement)?;
+                        }
+                        RqlStatement::Transaction { command } => {
+                            self.handle_transaction(command)?;
+                        }
                         RqlStatement::Let { variable, expression } => {
                             self.handle_let(variable, expression)?;
                         }
@@ -706,38 +1610,254 @@ impl<'a> NoctraTui<'a> {
                         RqlStatement::Export { query, file, format, options } => {
                             self.handle_export(query, file, format, options)?;
                         }
+                        RqlStatement::ExportSchema { file } => {
+                            self.handle_export_schema(file)?;
+                        }
+                        RqlStatement::ExportSource { alias, path } => {
+                            self.handle_export_source(alias, path)?;
+                        }
+                        RqlStatement::FormLoad { form_path } => {
+                            if let Err(e) = self.load_form(form_path) {
+                                self.show_error_dialog(&format!("❌ Error cargando formulario: {}", e));
+                            }
+                        }
+                        RqlStatement::SaveBlob { sql, file } => {
+                            self.handle_save_blob(sql, file)?;
+                        }
+                        RqlStatement::SelectInto { sql, file, format, options } => {
+                            self.handle_select_into(sql, file, *format, options)?;
+                        }
                         RqlStatement::Map { expressions } => {
                             self.handle_map(expressions)?;
                         }
                         RqlStatement::Filter { condition } => {
                             self.handle_filter(condition)?;
                         }
+                        RqlStatement::Assert { sql, expected_count, expected_value, label } => {
+                            self.handle_assert(sql, *expected_count, expected_value.as_ref(), label.as_deref())?;
+                        }
                         _ => {
                             self.show_error_dialog(&format!("⚠️ Comando no implementado: {:?}", statement.statement_type()));
                         }
                     }
                 }
             }
-            Err(e) => {
-                self.show_error_dialog(&format!("❌ Error de parseo: {}", e));
+            Err(e) => {
+                let mut message = format!("❌ Error de parseo: {}", e);
+                if let Some(snippet) = e.snippet() {
+                    message.push_str(&format!("\n\n{}", snippet));
+                }
+                self.show_error_dialog(&message);
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Ejecutar statement SQL directo
+    fn execute_sql_statement(
+        &mut self,
+        sql: &str,
+        duckdb_specific: bool,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let params = Parameters::new();
+        let rql_query = RqlQuery::new(sql, params).with_duckdb_specific(duckdb_specific);
+
+        if returns_rows(sql) {
+            return self.open_result_cursor(rql_query, sql);
+        }
+        let result = self.executor.execute_rql(&self.session, rql_query);
+        self.show_execution_result(result, sql)
+    }
+
+    /// Manejar WATCH: mostrar la consulta y repetirla cada `interval_secs`
+    fn handle_watch(&mut self, interval_secs: u64, sql: &str) {
+        self.release_result_cursor();
+        match Self::watch_results(&self.executor, &self.session, sql, None) {
+            Ok(results) => {
+                self.active_buffer_mut().results = Some(results);
+                self.active_watch = Some(WatchSpec::new(
+                    Duration::from_secs(interval_secs),
+                    sql,
+                    self.active_buffer().id,
+                ));
+                self.mode = UiMode::Result;
+            }
+            Err(e) => self.show_error_dialog(&execution_error_message(&e)),
+        }
+    }
+
+    /// Repetir la consulta vigilada si en `now` ya toca
+    fn tick_watch(&mut self, now: Instant) {
+        if let Err(e) = Self::refresh_watch(
+            &mut self.active_watch,
+            &mut self.buffers,
+            &self.executor,
+            &self.session,
+            now,
+        ) {
+            self.toast = Some((format!("❌ {}", e), now));
+        }
+    }
+
+    /// Volver a ejecutar `active_watch` en su buffer si en `now` ya toca
+    ///
+    /// La vigilancia se descarta si su buffer se cerró.
+    fn refresh_watch(
+        active_watch: &mut Option<WatchSpec>,
+        buffers: &mut [QueryBuffer],
+        executor: &Executor,
+        session: &Session,
+        now: Instant,
+    ) -> Result<(), NoctraError> {
+        let Some(watch) = active_watch.as_mut() else {
+            return Ok(());
+        };
+        if !watch.poll(now) {
+            return Ok(());
+        }
+        let Some(buffer) = buffers.iter_mut().find(|b| b.id == watch.buffer_id) else {
+            // Se cerró el buffer de la consulta
+            *active_watch = None;
+            return Ok(());
+        };
+
+        let mut results =
+            Self::watch_results(executor, session, &watch.sql, buffer.results.as_ref())?;
+        if let Some(previous) = &buffer.results {
+            results.scroll = previous.scroll.min(results.rows.len().saturating_sub(1));
+        }
+        buffer.results = Some(results);
+        Ok(())
+    }
+
+    /// Ejecutar una consulta vigilada y compararla con su resultado anterior
+    fn watch_results(
+        executor: &Executor,
+        session: &Session,
+        sql: &str,
+        previous: Option<&QueryResults>,
+    ) -> Result<QueryResults, NoctraError> {
+        let result_set = executor.execute_rql(session, RqlQuery::new(sql, Parameters::new()))?;
+        let mut results = Self::convert_result_set(result_set, sql);
+        if let Some(previous) = previous.filter(|previous| previous.columns == results.columns) {
+            results.diff = Some(diff_rows(&previous.rows, &results.rows));
+        }
+        Ok(results)
+    }
+
+    /// Detener la consulta vigilada; `false` si no había ninguna
+    fn stop_watch(&mut self) -> bool {
+        if self.active_watch.take().is_none() {
+            return false;
+        }
+        self.toast = Some(("Vigilancia detenida".to_string(), Instant::now()));
+        true
+    }
+
+    /// Ejecutar una consulta que devuelve filas leyendo sólo el primer bloque
+    ///
+    /// Las demás filas se leen del cursor al desplazarse por el resultado en
+    /// modo Result (`scroll_results`).
+    fn open_result_cursor(
+        &mut self,
+        rql_query: RqlQuery,
+        sql: &str,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        self.release_result_cursor();
+        self.pipeline_result = None;
+        self.pipeline_query = Some(rql_query.clone());
+
+        let cursor = match self.executor.open_cursor(&self.session, rql_query) {
+            Ok(cursor) => cursor,
+            Err(e) => return self.show_execution_result(Err(e), sql),
+        };
+        let page = match self
+            .executor
+            .fetch_cursor(&self.session, cursor, RESULT_FETCH_SIZE)
+        {
+            Ok(page) => page,
+            Err(e) => {
+                self.executor.close_cursor(&self.session, cursor);
+                return self.show_execution_result(Err(e), sql);
+            }
+        };
+
+        let mut results = Self::convert_result_set(page.result_set, sql);
+        if page.has_more {
+            results.has_more = true;
+            results.status = cursor_status(results.rows.len(), true, sql.trim());
+            self.result_cursor = Some(ResultCursor {
+                buffer_id: self.active_buffer().id,
+                cursor,
+                command: sql.trim().to_string(),
+            });
+        }
+        self.active_buffer_mut().results = Some(results);
+        self.mode = UiMode::Result;
+        Ok(())
+    }
+
+    /// Manejar MERGE INTO (nativo o emulado en SQLite)
+    fn handle_merge(&mut self, statement: &RqlStatement) -> Result<(), Box<dyn std::error::Error>> {
+        let merge_sql = statement.merge_sql().unwrap_or_default();
+        let sqlite_statements = statement.sqlite_merge_statements().unwrap_or_default();
+        let result = self
+            .executor
+            .execute_merge(&self.session, &merge_sql, &sqlite_statements);
+        self.show_execution_result(result, &merge_sql)
+    }
+
+    /// Manejar BEGIN/COMMIT/ROLLBACK/SAVEPOINT
+    fn handle_transaction(
+        &mut self,
+        command: &TransactionCommand,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let message = match command {
+            TransactionCommand::Begin => {
+                self.executor.begin(&self.session)?;
+                "✅ Transacción iniciada".to_string()
+            }
+            TransactionCommand::Commit => {
+                self.executor.commit(&self.session)?;
+                "✅ Transacción confirmada".to_string()
+            }
+            TransactionCommand::Rollback => {
+                self.executor.rollback(&self.session)?;
+                "✅ Transacción revertida".to_string()
+            }
+            TransactionCommand::Savepoint(name) => {
+                self.executor.savepoint(&self.session, name)?;
+                format!("✅ Savepoint '{}' creado", name)
             }
-        }
-
-        // Limpiar editor para próximo comando
-        self.clear_command_editor();
-
+            TransactionCommand::RollbackTo(name) => {
+                self.executor.rollback_to_savepoint(&self.session, name)?;
+                format!("✅ Revertido al savepoint '{}'", name)
+            }
+            TransactionCommand::Release(name) => {
+                self.executor.release_savepoint(&self.session, name)?;
+                format!("✅ Savepoint '{}' liberado", name)
+            }
+        };
+        self.show_info_dialog(&message);
         Ok(())
     }
 
-    /// Ejecutar statement SQL directo
-    fn execute_sql_statement(&mut self, sql: &str) -> Result<(), Box<dyn std::error::Error>> {
-        let params = HashMap::new();
-        let rql_query = RqlQuery::new(sql, params);
-
-        match self.executor.execute_rql(&self.session, rql_query) {
+    /// Mostrar el resultado de una sentencia en Result Mode (o su error)
+    fn show_execution_result(
+        &mut self,
+        result: noctra_core::error::Result<ResultSet>,
+        sql: &str,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        match result {
             Ok(result_set) => {
+                if !result_set.columns.is_empty() {
+                    self.pipeline_result = Some(result_set.clone());
+                    self.pipeline_query = None;
+                }
+
                 // Convertir ResultSet a QueryResults
-                self.current_results = Some(self.convert_result_set(result_set, sql));
+                self.active_buffer_mut().results = Some(Self::convert_result_set(result_set, sql));
 
                 // Cambiar a modo Result
                 self.mode = UiMode::Result;
@@ -745,46 +1865,86 @@ impl<'a> NoctraTui<'a> {
             }
             Err(e) => {
                 // Mostrar error en Dialog Mode
-                self.show_error_dialog(&format!("❌ Error de ejecución SQL: {}", e));
+                self.show_error_dialog(&execution_error_message(&e));
                 Err(Box::new(e))
             }
         }
     }
 
     /// Manejar comando USE SOURCE
-    fn handle_use_source(&mut self, path: &str, alias: Option<&str>, _options: &HashMap<String, String>) -> Result<(), Box<dyn std::error::Error>> {
-        // Detectar tipo de fuente por extensión
-        if path.ends_with(".csv") || path.ends_with(".json") || path.ends_with(".parquet") {
-            // Crear fuente DuckDB (reemplaza CsvDataSource)
-            let source_name = alias.unwrap_or(path);
-            eprintln!("[DEBUG TUI] Loading DuckDB source: {} as {}", path, source_name);
+    fn handle_use_source(
+        &mut self,
+        path: &str,
+        alias: Option<&str>,
+        options: &HashMap<String, String>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        // La fábrica se elige por el esquema de la URI (`csv://`, `file://`,
+        // los registrados por otros crates) o, sin esquema, por la extensión
+        let factory = match self.executor.source_registry().factory_for(path) {
+            Ok(factory) => factory,
+            Err(e) => {
+                self.show_error_dialog(&format!("❌ {}\n(Soportados: .csv, .json, .ndjson, .parquet, .xlsx, .xls; .csv/.json/.ndjson también con .gz o .zst)", e));
+                return Ok(());
+            }
+        };
 
-            // Usar DuckDBSource desde noctra-duckdb
-            let mut duckdb_source = noctra_duckdb::DuckDBSource::new_in_memory()
-                .map_err(|e| NoctraError::Internal(format!("Error creating DuckDB source: {}", e)))?;
+        let source_name = alias.unwrap_or(path);
 
-            duckdb_source.register_file(path, &source_name)
-                .map_err(|e| NoctraError::Internal(format!("Error registering file: {}", e)))?;
+        // Columnas de una carga anterior con el mismo alias (re-USE)
+        let previous_columns = self.table_columns(source_name);
 
-            eprintln!("[DEBUG TUI] DuckDB source created successfully");
+        let created = factory.create(path, source_name, options)?;
+        let kind = created.source.name().to_string();
 
-            // Registrar fuente
-            self.executor.source_registry_mut()
-                .register(source_name.to_string(), Box::new(duckdb_source))
-                .map_err(|e| NoctraError::Internal(format!("Error registering source: {}", e)))?;
+        // Registrar fuente (vincula la base SQLite si es un archivo)
+        let link_result = self
+            .executor
+            .register_source(source_name.to_string(), created.source);
 
-            eprintln!("[DEBUG TUI] DuckDB source registered");
-            eprintln!("[DEBUG TUI] Active source: {:?}",
-                self.executor.source_registry().active().map(|s| s.name()));
+        self.session.record_source(SourceDescriptor {
+            path: path.to_string(),
+            alias: alias.map(str::to_string),
+            options: options.clone(),
+        });
 
-            self.show_info_dialog(&format!("✅ Fuente '{}' cargada como '{}' (DuckDB)", path, source_name));
-        } else {
-            self.show_error_dialog(&format!("❌ Tipo de fuente no soportado: {}\n(Soportados: .csv, .json, .parquet)", path));
+        let mut message = format!(
+            "✅ Fuente '{}' cargada como '{}' ({})",
+            path, source_name, kind
+        );
+        for warning in &created.warnings {
+            message.push_str(&format!("\n⚠️ {}", warning));
+        }
+        if let (Some(previous), Some(current)) = (previous_columns, self.table_columns(source_name))
+        {
+            let diff = noctra_duckdb::SchemaDiff::between(&previous, &current);
+            if !diff.is_empty() {
+                message.push_str(&format!("\n{}", schema_diff_message(&diff)));
+            }
+        }
+        match link_result {
+            Ok(linked) if !linked.is_empty() => {
+                message.push_str(&format!(
+                    "\n🔗 Tablas SQLite vinculadas: {}",
+                    linked.join(", ")
+                ));
+            }
+            Ok(_) => {}
+            Err(e) => message.push_str(&format!("\n⚠️ No se pudo vincular la base SQLite: {}", e)),
         }
+        self.show_info_dialog(&message);
 
         Ok(())
     }
 
+    /// Columnas de la tabla `table` en la fuente del mismo nombre, si existe
+    fn table_columns(&self, table: &str) -> Option<Vec<ColumnInfo>> {
+        let tables = self.executor.source_registry().get(table)?.schema().ok()?;
+        tables
+            .into_iter()
+            .find(|t| t.name == table)
+            .map(|t| t.columns)
+    }
+
     /// Mostrar diálogo informativo
     fn show_info_dialog(&mut self, message: &str) {
         self.dialog_message = Some(message.to_string());
@@ -793,17 +1953,44 @@ impl<'a> NoctraTui<'a> {
         self.mode = UiMode::Dialog;
     }
 
+    /// Manejar comando USE SOURCE alias
+    fn handle_use_source_alias(&mut self, alias: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let registry = self.executor.source_registry_mut();
+        if registry.set_active(alias).is_err() {
+            let aliases = registry.aliases();
+            let message = if aliases.is_empty() {
+                format!(
+                    "Fuente '{}' no encontrada (no hay fuentes registradas)",
+                    alias
+                )
+            } else {
+                format!(
+                    "Fuente '{}' no encontrada. Disponibles: {}",
+                    alias,
+                    aliases.join(", ")
+                )
+            };
+            return Err(Box::new(NoctraError::Validation(message)));
+        }
+
+        self.show_info_dialog(&format!("✅ Fuente activa: '{}'", alias));
+        Ok(())
+    }
+
     /// Manejar comando SHOW SOURCES
     fn handle_show_sources(&mut self) -> Result<(), Box<dyn std::error::Error>> {
         use noctra_core::types::{Column, Row, Value};
 
-        let sources = self.executor.source_registry().list_sources();
+        let registry = self.executor.source_registry();
+        let sources = registry.list_sources();
+        let active = registry.active_alias();
 
         // Crear columnas
         let columns = vec![
             Column { name: "Alias".to_string(), data_type: "TEXT".to_string(), ordinal: 0 },
             Column { name: "Tipo".to_string(), data_type: "TEXT".to_string(), ordinal: 1 },
             Column { name: "Path".to_string(), data_type: "TEXT".to_string(), ordinal: 2 },
+            Column { name: "Activa".to_string(), data_type: "TEXT".to_string(), ordinal: 3 },
         ];
 
         // Crear filas
@@ -813,6 +2000,7 @@ impl<'a> NoctraTui<'a> {
                     Value::Text(alias.clone()),
                     Value::Text(source_type.type_name().to_string()),
                     Value::Text(source_type.display_path().to_string()),
+                    Value::Text(if active == Some(alias.as_str()) { "▶" } else { "" }.to_string()),
                 ]
             }
         }).collect();
@@ -822,17 +2010,22 @@ impl<'a> NoctraTui<'a> {
             rows,
             rows_affected: None,
             last_insert_rowid: None,
+            truncated: false,
         };
 
         // Mostrar como resultado de tabla
-        self.current_results = Some(self.convert_result_set(result_set, "SHOW SOURCES"));
+        self.active_buffer_mut().results =
+            Some(Self::convert_result_set(result_set, "SHOW SOURCES"));
         self.mode = UiMode::Result;
 
         Ok(())
     }
 
     /// Manejar comando SHOW TABLES
-    fn handle_show_tables(&mut self, source: Option<&str>) -> Result<(), Box<dyn std::error::Error>> {
+    fn handle_show_tables(
+        &mut self,
+        source: Option<&str>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
         use noctra_core::types::{Column, Row, Value};
 
         let mut table_list = Vec::new();
@@ -843,15 +2036,15 @@ impl<'a> NoctraTui<'a> {
                 match data_source.schema() {
                     Ok(tables) => {
                         for table in tables {
-                            table_list.push(table.name);
+                            table_list.push((table.name, table.is_view, table.row_count));
                         }
                     }
                     Err(e) => {
-                        return Err(Box::new(NoctraError::Internal(format!("Error obteniendo schema: {}", e))));
+                        return Err(Box::new(e));
                     }
                 }
             } else {
-                return Err(Box::new(NoctraError::Internal(format!("Fuente '{}' no encontrada", source_name))));
+                return Err(Box::new(NoctraError::not_found("fuente", source_name)));
             }
         } else {
             // Mostrar todas las tablas de todas las fuentes
@@ -860,34 +2053,49 @@ impl<'a> NoctraTui<'a> {
                 if let Some(data_source) = self.executor.source_registry().get(&alias) {
                     if let Ok(tables) = data_source.schema() {
                         for table in tables {
-                            table_list.push(table.name);
+                            table_list.push((table.name, table.is_view, table.row_count));
                         }
                     }
                 }
             }
         }
 
-        // Crear columnas
+        // Crear columnas (filas = conteo aproximado en caché, sin escanear)
         let columns = vec![
             Column { name: "table".to_string(), data_type: "TEXT".to_string(), ordinal: 0 },
+            Column { name: "type".to_string(), data_type: "TEXT".to_string(), ordinal: 1 },
+            Column { name: "rows".to_string(), data_type: "TEXT".to_string(), ordinal: 2 },
         ];
 
         // Crear filas
-        let rows: Vec<Row> = table_list.iter().map(|table_name| {
-            Row {
-                values: vec![Value::Text(table_name.clone())]
-            }
-        }).collect();
+        let rows: Vec<Row> = table_list
+            .iter()
+            .map(|(table_name, is_view, row_count)| {
+                let rows_value = match row_count {
+                    Some(count) => Value::Text(format!("~{}", count)),
+                    None => Value::Null,
+                };
+                Row {
+                    values: vec![
+                        Value::Text(table_name.clone()),
+                        Value::Text(if *is_view { "view" } else { "table" }.to_string()),
+                        rows_value,
+                    ],
+                }
+        })
+            .collect();
 
         let result_set = ResultSet {
             columns,
             rows,
             rows_affected: None,
             last_insert_rowid: None,
+            truncated: false,
         };
 
         // Mostrar como resultado de tabla
-        self.current_results = Some(self.convert_result_set(result_set, "SHOW TABLES"));
+        self.active_buffer_mut().results =
+            Some(Self::convert_result_set(result_set, "SHOW TABLES"));
         self.mode = UiMode::Result;
 
         Ok(())
@@ -898,11 +2106,14 @@ impl<'a> NoctraTui<'a> {
         use noctra_core::types::{Column, Row, Value};
 
         let vars = self.session.list_variables();
+        let used = self.executor.last_used_variables(&self.session);
 
-        // Crear columnas
+        // Crear columnas (Usada = expandida en la última consulta)
         let columns = vec![
             Column { name: "Variable".to_string(), data_type: "TEXT".to_string(), ordinal: 0 },
             Column { name: "Valor".to_string(), data_type: "TEXT".to_string(), ordinal: 1 },
+            Column { name: "Tipo".to_string(), data_type: "TEXT".to_string(), ordinal: 2 },
+            Column { name: "Usada".to_string(), data_type: "TEXT".to_string(), ordinal: 3 },
         ];
 
         // Crear filas
@@ -911,6 +2122,8 @@ impl<'a> NoctraTui<'a> {
                 values: vec![
                     Value::Text(name.clone()),
                     Value::Text(value.to_string()),
+                    Value::Text(value.type_name().to_string()),
+                    Value::Text(if used.contains(name) { "✓" } else { "" }.to_string()),
                 ]
             }
         }).collect();
@@ -920,76 +2133,188 @@ impl<'a> NoctraTui<'a> {
             rows,
             rows_affected: None,
             last_insert_rowid: None,
+            truncated: false,
         };
 
         // Mostrar como resultado de tabla
-        self.current_results = Some(self.convert_result_set(result_set, "SHOW VARS"));
+        self.active_buffer_mut().results = Some(Self::convert_result_set(result_set, "SHOW VARS"));
         self.mode = UiMode::Result;
 
         Ok(())
     }
 
     /// Manejar comando DESCRIBE
-    fn handle_describe(&mut self, source: Option<&str>, table: &str) -> Result<(), Box<dyn std::error::Error>> {
-        use noctra_core::types::{Column, Row, Value};
-
+    fn handle_describe(
+        &mut self,
+        source: Option<&str>,
+        table: &str,
+    ) -> Result<(), Box<dyn std::error::Error>> {
         if let Some(source_name) = source {
             // Describir tabla de una fuente específica
             if let Some(data_source) = self.executor.source_registry().get(source_name) {
                 match data_source.schema() {
                     Ok(tables) => {
                         if let Some(table_info) = tables.iter().find(|t| t.name == table) {
-                            // Crear columnas
-                            let columns = vec![
-                                Column { name: "Campos".to_string(), data_type: "TEXT".to_string(), ordinal: 0 },
-                                Column { name: "Tipo".to_string(), data_type: "TEXT".to_string(), ordinal: 1 },
-                            ];
-
-                            // Crear filas
-                            let rows: Vec<Row> = table_info.columns.iter().map(|col| {
-                                Row {
-                                    values: vec![
-                                        Value::Text(col.name.clone()),
-                                        Value::Text(col.data_type.clone()),
-                                    ]
-                                }
-                            }).collect();
-
-                            let result_set = ResultSet {
-                                columns,
-                                rows,
-                                rows_affected: None,
-                                last_insert_rowid: None,
-                            };
+                            let result_set = describe_result_set(&table_info.columns);
 
                             // Mostrar como resultado de tabla
-                            self.current_results = Some(self.convert_result_set(result_set, &format!("DESCRIBE {}.{}", source_name, table)));
+                            self.active_buffer_mut().results = Some(Self::convert_result_set(result_set, &format!("DESCRIBE {}.{}", source_name, table)));
                             self.mode = UiMode::Result;
 
-                            return Ok(());
+                            Ok(())
+                        } else if self.is_cte_name(table) {
+                            self.show_cte_info(table);
+                            Ok(())
                         } else {
-                            return Err(Box::new(NoctraError::Internal(format!("Tabla '{}' no encontrada en '{}'", table, source_name))));
+                            Err(Box::new(NoctraError::not_found("tabla", format!("{}.{}", source_name, table))))
                         }
                     }
                     Err(e) => {
-                        return Err(Box::new(NoctraError::Internal(format!("Error obteniendo schema: {}", e))));
+                        Err(Box::new(e))
                     }
                 }
             } else {
-                return Err(Box::new(NoctraError::Internal(format!("Fuente '{}' no encontrada", source_name))));
+                Err(Box::new(NoctraError::not_found("fuente", source_name)))
             }
+        } else if self.is_cte_name(table) {
+            self.show_cte_info(table);
+            Ok(())
         } else {
-            return Err(Box::new(NoctraError::Internal("DESCRIBE requiere especificar la fuente: DESCRIBE source.table".to_string())));
+            // Sin fuente: se resuelve en el catálogo (fuentes y backend)
+            let entry = self.executor.lookup_table(table)?;
+            let result_set = describe_result_set(&entry.table.columns);
+            let title = format!("DESCRIBE {}", entry.qualified_name());
+            self.active_buffer_mut().results = Some(Self::convert_result_set(result_set, &title));
+            self.mode = UiMode::Result;
+            Ok(())
         }
     }
 
+    /// Manejar comando PROFILE (estadísticas por columna)
+    fn handle_profile(
+        &mut self,
+        source: Option<&str>,
+        table: &str,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let registry = self.executor.source_registry();
+        let data_source = match source {
+            Some(source_name) => registry
+                .get(source_name)
+                .ok_or_else(|| NoctraError::not_found("fuente", source_name))?,
+            None => registry.active().ok_or_else(|| {
+                NoctraError::Validation("No hay fuente activa: PROFILE source.table".to_string())
+            })?,
+        };
+
+        let title = format!("PROFILE {}.{}", data_source.name(), table);
+        let result_set = data_source.profile(table)?;
+
+        self.active_buffer_mut().results = Some(Self::convert_result_set(result_set, &title));
+        self.mode = UiMode::Result;
+        Ok(())
+    }
+
+    /// Manejar comando SAMPLE (muestra de filas en modo resultado)
+    fn handle_sample(
+        &mut self,
+        source: Option<&str>,
+        table: &str,
+        limit: usize,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let registry = self.executor.source_registry();
+        let data_source = match source {
+            Some(source_name) => registry
+                .get(source_name)
+                .ok_or_else(|| NoctraError::not_found("fuente", source_name))?,
+            None => registry.active().ok_or_else(|| {
+                NoctraError::Validation("No hay fuente activa: SAMPLE source.table".to_string())
+            })?,
+        };
+
+        let title = format!("SAMPLE {}.{} LIMIT {}", data_source.name(), table, limit);
+        let result_set = data_source.sample(table, limit)?;
+
+        self.active_buffer_mut().results = Some(Self::convert_result_set(result_set, &title));
+        self.mode = UiMode::Result;
+        Ok(())
+    }
+
+    /// Manejar comando CHECKPOINT
+    fn handle_checkpoint(
+        &mut self,
+        source: Option<&str>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let registry = self.executor.source_registry();
+        let data_source = match source {
+            Some(source_name) => registry
+                .get(source_name)
+                .ok_or_else(|| NoctraError::not_found("fuente", source_name))?,
+            None => registry.active().ok_or_else(|| {
+                NoctraError::Validation("No hay fuente activa: CHECKPOINT source".to_string())
+            })?,
+        };
+
+        data_source.checkpoint()?;
+        let message = format!("✅ Checkpoint completado en '{}'", data_source.name());
+        self.show_info_dialog(&message);
+        Ok(())
+    }
+
+    /// Manejar comando CREATE VIEW
+    ///
+    /// Con una fuente activa la vista se crea en ella; si no, la sentencia
+    /// se ejecuta como SQL sobre el backend.
+    fn handle_create_view(
+        &mut self,
+        name: &str,
+        sql: &str,
+        statement: &str,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let Some(data_source) = self.executor.source_registry_mut().active_mut() else {
+            return self.execute_sql_statement(statement, false);
+        };
+
+        data_source.create_view(name, sql)?;
+        let message = format!("✅ Vista '{}' creada en '{}'", name, data_source.name());
+        self.show_info_dialog(&message);
+        Ok(())
+    }
+
+    /// Verificar si un nombre corresponde a una CTE conocida
+    fn is_cte_name(&self, name: &str) -> bool {
+        self.cte_names
+            .iter()
+            .any(|cte| cte.eq_ignore_ascii_case(name))
+    }
+
+    /// Mostrar información de una CTE (pseudo-tabla sin schema persistente)
+    fn show_cte_info(&mut self, name: &str) {
+        self.show_info_dialog(&format!(
+            "ℹ️ '{}' es una CTE (WITH) definida en la última consulta.\n\nCTEs disponibles: {}",
+            name,
+            self.cte_names.join(", ")
+        ));
+    }
+
     /// Manejar comando LET
-    fn handle_let(&mut self, variable: &str, expression: &str) -> Result<(), Box<dyn std::error::Error>> {
-        // Evaluar la expresión (por ahora, simplemente tomar el valor literal)
-        let value = expression.trim_matches('\'').trim_matches('"');
-        self.session.set_variable(variable.to_string(), value.to_string());
+    fn handle_let(
+        &mut self,
+        variable: &str,
+        expression: &str,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        match self.session.evaluate(expression) {
+            Ok(value) => {
+                self.show_info_dialog(&format!(
+                    "✅ Variable '{}' = '{}' ({})",
+                    variable,
+                    value,
+                    value.type_name()
+                ));
+                self.session.set_variable(variable.to_string(), value);
+            }
+            Err(e) => self.show_error_dialog(&format!("❌ Error en LET {}: {}", variable, e)),
+        }
 
-        self.show_info_dialog(&format!("✅ Variable '{}' = '{}'", variable, value));
         Ok(())
     }
 
@@ -1007,13 +2332,18 @@ impl<'a> NoctraTui<'a> {
 
     /// Manejar comando IMPORT
     /// Sintaxis: IMPORT 'file.csv' AS table OPTIONS (delimiter=',', header=true)
-    fn handle_import(&mut self, file: &str, table: &str, options: &HashMap<String, String>) -> Result<(), Box<dyn std::error::Error>> {
+    fn handle_import(
+        &mut self,
+        file: &str,
+        table: &str,
+        options: &HashMap<String, String>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
         use std::fs::File;
         use std::io::{BufRead, BufReader};
         use std::path::Path;
 
         // Validar ruta de archivo (sandboxing)
-        Self::validate_file_path(file)?;
+        noctra_core::fs_guard::check_path(file, &self.allowed_paths)?;
 
         // Validar nombre de tabla (SQL injection prevention)
         Self::validate_table_name(table)?;
@@ -1023,7 +2353,7 @@ impl<'a> NoctraTui<'a> {
         let is_json = file.ends_with(".json");
 
         if !is_csv && !is_json {
-            return Err(Box::new(NoctraError::Internal(
+            return Err(Box::new(NoctraError::Validation(
                 format!("Formato de archivo no soportado: {} (solo .csv y .json)", file)
             )));
         }
@@ -1034,7 +2364,7 @@ impl<'a> NoctraTui<'a> {
             let metadata = std::fs::metadata(path)?;
             const MAX_FILE_SIZE: u64 = 100 * 1024 * 1024;
             if metadata.len() > MAX_FILE_SIZE {
-                return Err(Box::new(NoctraError::Internal(format!(
+                return Err(Box::new(NoctraError::Validation(format!(
                     "Archivo demasiado grande: {} bytes (máx: {} bytes)",
                     metadata.len(),
                     MAX_FILE_SIZE
@@ -1044,7 +2374,7 @@ impl<'a> NoctraTui<'a> {
 
         // Leer archivo
         let file_handle = File::open(file)
-            .map_err(|e| NoctraError::Internal(format!("Error abriendo archivo: {}", e)))?;
+            .map_err(|e| NoctraError::Io(format!("Error abriendo archivo: {}", e)))?;
         let reader = BufReader::new(file_handle);
 
         if is_csv {
@@ -1062,7 +2392,7 @@ impl<'a> NoctraTui<'a> {
             let header_line = if let Some(Ok(line)) = lines.next() {
                 line
             } else {
-                return Err(Box::new(NoctraError::Internal("Archivo CSV vacío".into())));
+                return Err(Box::new(NoctraError::Validation("Archivo CSV vacío".into())));
             };
 
             let columns: Vec<String> = header_line
@@ -1071,7 +2401,7 @@ impl<'a> NoctraTui<'a> {
                 .collect();
 
             if columns.is_empty() {
-                return Err(Box::new(NoctraError::Internal("No se encontraron columnas en CSV".into())));
+                return Err(Box::new(NoctraError::Validation("No se encontraron columnas en CSV".into())));
             }
 
             // Crear tabla en SQLite
@@ -1080,8 +2410,7 @@ impl<'a> NoctraTui<'a> {
                 .collect();
             let create_sql = format!("CREATE TABLE IF NOT EXISTS {} ({})", table, column_defs.join(", "));
 
-            self.executor.execute_sql(&self.session, &create_sql)
-                .map_err(|e| NoctraError::Internal(format!("Error creando tabla: {}", e)))?;
+            self.executor.execute_sql(&self.session, &create_sql)?;
 
             // Insertar datos
             let mut rows_imported = 0;
@@ -1106,7 +2435,7 @@ impl<'a> NoctraTui<'a> {
             // Procesar resto de líneas
             for line_result in lines {
                 let line = line_result
-                    .map_err(|e| NoctraError::Internal(format!("Error leyendo línea: {}", e)))?;
+                    .map_err(|e| NoctraError::Io(format!("Error leyendo línea: {}", e)))?;
 
                 let values: Vec<String> = line
                     .split(delimiter)
@@ -1135,28 +2464,28 @@ impl<'a> NoctraTui<'a> {
 
             // Leer todo el archivo
             let json_content = std::io::read_to_string(reader)
-                .map_err(|e| NoctraError::Internal(format!("Error leyendo JSON: {}", e)))?;
+                .map_err(|e| NoctraError::Io(format!("Error leyendo JSON: {}", e)))?;
 
             // Parsear JSON
             let json_data: JsonValue = serde_json::from_str(&json_content)
-                .map_err(|e| NoctraError::Internal(format!("Error parseando JSON: {}", e)))?;
+                .map_err(|e| NoctraError::Serialization(format!("Error parseando JSON: {}", e)))?;
 
             // Verificar que es un array
             let array = match json_data {
                 JsonValue::Array(arr) => arr,
-                _ => return Err(Box::new(NoctraError::Internal(
+                _ => return Err(Box::new(NoctraError::Validation(
                     "JSON debe ser un array de objetos".into()
                 ))),
             };
 
             if array.is_empty() {
-                return Err(Box::new(NoctraError::Internal("Array JSON vacío".into())));
+                return Err(Box::new(NoctraError::Validation("Array JSON vacío".into())));
             }
 
             // Extraer columnas del primer objeto
             let first_obj = match &array[0] {
                 JsonValue::Object(obj) => obj,
-                _ => return Err(Box::new(NoctraError::Internal(
+                _ => return Err(Box::new(NoctraError::Validation(
                     "Elementos del array deben ser objetos".into()
                 ))),
             };
@@ -1164,7 +2493,7 @@ impl<'a> NoctraTui<'a> {
             let columns: Vec<String> = first_obj.keys().cloned().collect();
 
             if columns.is_empty() {
-                return Err(Box::new(NoctraError::Internal("No se encontraron columnas en JSON".into())));
+                return Err(Box::new(NoctraError::Validation("No se encontraron columnas en JSON".into())));
             }
 
             // Inferir tipos de datos del primer objeto
@@ -1192,8 +2521,7 @@ impl<'a> NoctraTui<'a> {
                 .collect();
             let create_sql = format!("CREATE TABLE IF NOT EXISTS {} ({})", table, column_defs.join(", "));
 
-            self.executor.execute_sql(&self.session, &create_sql)
-                .map_err(|e| NoctraError::Internal(format!("Error creando tabla: {}", e)))?;
+            self.executor.execute_sql(&self.session, &create_sql)?;
 
             // Insertar datos
             let mut rows_imported = 0;
@@ -1238,12 +2566,18 @@ impl<'a> NoctraTui<'a> {
 
     /// Manejar comando EXPORT
     /// Sintaxis: EXPORT table TO 'file.csv' FORMAT CSV OPTIONS (delimiter=',', header=true)
-    fn handle_export(&mut self, query: &str, file: &str, format: &noctra_parser::ExportFormat, options: &HashMap<String, String>) -> Result<(), Box<dyn std::error::Error>> {
+    fn handle_export(
+        &mut self,
+        query: &str,
+        file: &str,
+        format: &noctra_parser::ExportFormat,
+        options: &HashMap<String, String>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
         use std::fs::File;
         use std::io::Write;
 
         // Validar ruta de archivo (sandboxing)
-        Self::validate_file_path(file)?;
+        noctra_core::fs_guard::check_path(file, &self.allowed_paths)?;
 
         // Validar nombre de tabla si no es SELECT
         if !query.to_uppercase().starts_with("SELECT ") {
@@ -1251,18 +2585,17 @@ impl<'a> NoctraTui<'a> {
         }
 
         // Ejecutar query para obtener datos
-        let result = if query.to_uppercase().starts_with("SELECT ") {
+        let select_query = if query.to_uppercase().starts_with("SELECT ") {
             // Es una query completa
-            let params = HashMap::new();
-            let rql_query = RqlQuery::new(query, params);
-            self.executor.execute_rql(&self.session, rql_query)?
+            query.to_string()
         } else {
             // Es un nombre de tabla, generar SELECT *
-            let select_query = format!("SELECT * FROM {}", query);
-            let params = HashMap::new();
-            let rql_query = RqlQuery::new(&select_query, params);
-            self.executor.execute_rql(&self.session, rql_query)?
+            format!("SELECT * FROM {}", query)
         };
+        // Todas las filas: `execute_rql` recorta a `max_rows`
+        let rql_query = RqlQuery::new(&select_query, Parameters::new());
+        let stream = self.executor.execute_rql_stream(&self.session, rql_query)?;
+        let result = ResultSet::from_stream(stream, None)?;
 
         match format {
             noctra_parser::ExportFormat::Csv => {
@@ -1274,7 +2607,7 @@ impl<'a> NoctraTui<'a> {
                     .unwrap_or(true);
 
                 let mut file_handle = File::create(file)
-                    .map_err(|e| NoctraError::Internal(format!("Error creando archivo: {}", e)))?;
+                    .map_err(|e| NoctraError::Io(format!("Error creando archivo: {}", e)))?;
 
                 // Escribir header si está habilitado
                 if has_header {
@@ -1283,33 +2616,17 @@ impl<'a> NoctraTui<'a> {
                         .collect();
                     let header_line = header_names.join(&delimiter.to_string());
                     writeln!(file_handle, "{}", header_line)
-                        .map_err(|e| NoctraError::Internal(format!("Error escribiendo header: {}", e)))?;
+                        .map_err(|e| NoctraError::Io(format!("Error escribiendo header: {}", e)))?;
                 }
 
                 // Escribir filas
                 for row in &result.rows {
                     let row_values: Vec<String> = row.values.iter()
-                        .map(|v| {
-                            match v {
-                                noctra_core::Value::Text(s) => {
-                                    // Escapar comillas dobles y envolver en comillas si contiene delimitador
-                                    if s.contains(delimiter) || s.contains('"') || s.contains('\n') {
-                                        format!("\"{}\"", s.replace('"', "\"\""))
-                                    } else {
-                                        s.clone()
-                                    }
-                                }
-                                noctra_core::Value::Integer(i) => i.to_string(),
-                                noctra_core::Value::Float(f) => f.to_string(),
-                                noctra_core::Value::Boolean(b) => b.to_string(),
-                                noctra_core::Value::Null => String::new(),
-                                _ => format!("{:?}", v),
-                            }
-                        })
+                        .map(|v| csv_value(v, delimiter))
                         .collect();
 
                     writeln!(file_handle, "{}", row_values.join(&delimiter.to_string()))
-                        .map_err(|e| NoctraError::Internal(format!("Error escribiendo fila: {}", e)))?;
+                        .map_err(|e| NoctraError::Io(format!("Error escribiendo fila: {}", e)))?;
                 }
 
                 self.show_info_dialog(&format!("✅ Exportadas {} filas a '{}'", result.rows.len(), file));
@@ -1318,7 +2635,7 @@ impl<'a> NoctraTui<'a> {
                 use serde_json::{json, Value as JsonValue};
 
                 let mut file_handle = File::create(file)
-                    .map_err(|e| NoctraError::Internal(format!("Error creando archivo: {}", e)))?;
+                    .map_err(|e| NoctraError::Io(format!("Error creando archivo: {}", e)))?;
 
                 // Convertir ResultSet a JSON array
                 let rows_json: Vec<JsonValue> = result.rows.iter()
@@ -1338,7 +2655,11 @@ impl<'a> NoctraTui<'a> {
                                 }
                                 noctra_core::Value::Boolean(b) => JsonValue::Bool(*b),
                                 noctra_core::Value::Null => JsonValue::Null,
-                                _ => JsonValue::String(format!("{:?}", value)),
+                                // Decimales como número si no pierden dígitos; el resto como texto
+                                _ => value
+                                    .to_json_number()
+                                    .map(JsonValue::Number)
+                                    .unwrap_or_else(|| JsonValue::String(value.to_string())),
                             };
                             obj.insert(col.name.clone(), json_val);
                         }
@@ -1348,85 +2669,208 @@ impl<'a> NoctraTui<'a> {
 
                 let json_output = json!(rows_json);
                 writeln!(file_handle, "{}", serde_json::to_string_pretty(&json_output)
-                    .map_err(|e| NoctraError::Internal(format!("Error serializando JSON: {}", e)))?)
-                    .map_err(|e| NoctraError::Internal(format!("Error escribiendo JSON: {}", e)))?;
+                    .map_err(|e| NoctraError::Serialization(format!("Error serializando JSON: {}", e)))?)
+                    .map_err(|e| NoctraError::Io(format!("Error escribiendo JSON: {}", e)))?;
 
                 self.show_info_dialog(&format!("✅ Exportadas {} filas a '{}'", result.rows.len(), file));
             }
             noctra_parser::ExportFormat::Xlsx => {
-                return Err(Box::new(NoctraError::Internal(
-                    "Exportación a XLSX no implementada en M4 (planeado para M5)".into()
-                )));
+                let xlsx_options = noctra_core::export::XlsxOptions::from_options(options)?;
+                noctra_core::export::write_xlsx(&result, file, &xlsx_options)?;
+
+                self.show_info_dialog(&format!("✅ Exportadas {} filas a '{}'", result.rows.len(), file));
             }
         }
 
         Ok(())
     }
 
-    /// Manejar comando MAP
-    /// Sintaxis: MAP expression1 AS alias1, expression2 AS alias2, ...
-    fn handle_map(&mut self, _expressions: &[noctra_parser::MapExpression]) -> Result<(), Box<dyn std::error::Error>> {
-        // MAP no implementado completamente en M4 - requiere pipeline de transformación
-        // Por ahora, mostrar mensaje informativo
-        self.show_info_dialog("⚠️ MAP: Transformaciones declarativas\n\nNo implementado completamente en M4.\nUse SELECT para transformaciones simples.\n\nEjemplo:\nSELECT UPPER(nombre) AS nombre, precio * 1.1 AS precio_nuevo\nFROM productos;");
+    /// Manejar comando EXPORT SCHEMA
+    /// Sintaxis: EXPORT SCHEMA TO 'catalog.toml'
+    fn handle_export_schema(&mut self, file: &str) -> Result<(), Box<dyn std::error::Error>> {
+        noctra_core::fs_guard::check_path(file, &self.allowed_paths)?;
+
+        let registry = self.executor.source_registry();
+        registry.export_schema(std::path::Path::new(file))?;
+        let message = format!(
+            "✅ Esquema de {} fuente(s) exportado a '{}'",
+            registry.aliases().len(),
+            file
+        );
+        self.show_info_dialog(&message);
         Ok(())
     }
 
-    /// Manejar comando FILTER
-    /// Sintaxis: FILTER condition
-    fn handle_filter(&mut self, _condition: &str) -> Result<(), Box<dyn std::error::Error>> {
-        // FILTER no implementado completamente en M4 - requiere pipeline de transformación
-        // Por ahora, mostrar mensaje informativo
-        self.show_info_dialog("⚠️ FILTER: Filtrado declarativo\n\nNo implementado completamente en M4.\nUse WHERE en SELECT.\n\nEjemplo:\nSELECT * FROM productos\nWHERE precio > 100;");
+    /// Manejar comando EXPORT SOURCE
+    /// Sintaxis: EXPORT SOURCE csv TO 'bundle/'
+    fn handle_export_source(
+        &mut self,
+        alias: &str,
+        path: &str,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        // Se valida el manifiesto: el directorio del paquete puede existir ya
+        let dir = std::path::Path::new(path);
+        noctra_core::fs_guard::check_path(
+            dir.join(noctra_core::bundle::MANIFEST_FILE),
+            &self.allowed_paths,
+        )?;
+
+        let manifest = self.executor.export_bundle(alias, dir)?;
+        let rows: u64 = manifest.tables.iter().map(|table| table.rows).sum();
+        let message = format!(
+            "✅ Fuente '{}' exportada a '{}' ({} tablas, {} filas)",
+            alias,
+            path,
+            manifest.tables.len(),
+            rows
+        );
+        self.show_info_dialog(&message);
         Ok(())
     }
 
-    /// Validar ruta de archivo (sandboxing)
-    fn validate_file_path(file: &str) -> Result<(), Box<dyn std::error::Error>> {
-        use std::path::Path;
+    /// Manejar comando SAVE BLOB
+    /// Sintaxis: SAVE BLOB (SELECT doc FROM files WHERE id = :id) TO 'out.pdf'
+    fn handle_save_blob(
+        &mut self,
+        sql: &str,
+        file: &str,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        // Se escribe en la ruta ya validada, no en la que escribió el usuario
+        let path = noctra_core::fs_guard::check_path(file, &self.allowed_paths)?;
+
+        let result = self
+            .executor
+            .execute_rql(&self.session, RqlQuery::new(sql, Parameters::new()))?;
+        let bytes = result.single_blob()?;
+        std::fs::write(&path, bytes)
+            .map_err(|e| NoctraError::Io(format!("Error escribiendo '{}': {}", file, e)))?;
+
+        self.show_info_dialog(&format!("✅ {} bytes guardados en '{}'", bytes.len(), file));
+        Ok(())
+    }
 
-        let path = Path::new(file);
-        let path_str = path.to_string_lossy();
-
-        // Directorios bloqueados
-        let blocked_dirs = [
-            "/etc/",
-            "/sys/",
-            "/proc/",
-            "/dev/",
-            "/root/",
-            "/boot/",
-            "C:\\Windows\\",
-            "C:\\Program Files\\",
-        ];
+    /// Manejar SELECT ... INTO 'file'
+    /// Sintaxis: SELECT * FROM ventas INTO 'ventas.parquet' FORMAT PARQUET OPTIONS (compression=zstd)
+    fn handle_select_into(
+        &mut self,
+        sql: &str,
+        file: &str,
+        format: noctra_parser::OutfileFormat,
+        options: &HashMap<String, String>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        noctra_core::fs_guard::check_path(file, &self.allowed_paths)?;
+        let format = match format {
+            noctra_parser::OutfileFormat::Parquet => noctra_duckdb::WriteFormat::Parquet(
+                noctra_duckdb::ParquetWriteOptions::from_options(options)
+                    .map_err(NoctraError::from)?,
+            ),
+            noctra_parser::OutfileFormat::Csv => noctra_duckdb::WriteFormat::Csv(
+                noctra_duckdb::CsvWriteOptions::from_options(options).map_err(NoctraError::from)?,
+            ),
+        };
 
-        for blocked in &blocked_dirs {
-            if path_str.starts_with(blocked) {
-                return Err(Box::new(NoctraError::Internal(format!(
-                    "Acceso denegado: No se puede acceder a directorio del sistema: {}",
-                    path_str
-                ))));
+        // Todas las filas: `execute_rql` recorta a `max_rows`
+        let stream = self
+            .executor
+            .execute_rql_stream(&self.session, RqlQuery::new(sql, Parameters::new()))?;
+        let result = ResultSet::from_stream(stream, None)?;
+        let written = noctra_duckdb::DuckDBSource::new_in_memory()
+            .and_then(|mut writer| writer.write_table(&result, file, format))
+            .map_err(NoctraError::from)?;
+
+        self.show_info_dialog(&format!("✅ {} filas escritas en '{}'", written, file));
+        Ok(())
+    }
+
+    /// Manejar comando MAP
+    /// Sintaxis: MAP expression1 AS alias1, expression2 AS alias2, ...
+    ///
+    /// Transforma el último resultado y lo reemplaza por la salida, así que
+    /// MAP y FILTER se pueden encadenar.
+    fn handle_map(
+        &mut self,
+        expressions: &[noctra_parser::MapExpression],
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let command = format!(
+            "MAP {}",
+            expressions
+                .iter()
+                .map(|e| match &e.alias {
+                    Some(alias) => format!("{} AS {}", e.expression, alias),
+                    None => e.expression.clone(),
+                })
+                .collect::<Vec<_>>()
+                .join(", ")
+        );
+        let expressions: Vec<(&str, Option<&str>)> = expressions
+            .iter()
+            .map(|e| (e.expression.as_str(), e.alias.as_deref()))
+            .collect();
+        let result = self
+            .pipeline_input("MAP")
+            .and_then(|last| self.session.map_result(&last, &expressions));
+        self.show_execution_result(result, &command)
+    }
+
+    /// Manejar comando FILTER
+    /// Sintaxis: FILTER condition
+    ///
+    /// Se queda con las filas del último resultado que cumplen la condición.
+    fn handle_filter(&mut self, condition: &str) -> Result<(), Box<dyn std::error::Error>> {
+        let result = self
+            .pipeline_input("FILTER")
+            .and_then(|last| self.session.filter_result(&last, condition));
+        self.show_execution_result(result, &format!("FILTER {}", condition))
+    }
+
+    /// Resultado completo sobre el que aplicar MAP/FILTER
+    ///
+    /// Si el último resultado se leyó por bloques, su consulta se vuelve a
+    /// ejecutar entera.
+    fn pipeline_input(&mut self, command: &str) -> noctra_core::error::Result<ResultSet> {
+        if let Some(result) = &self.pipeline_result {
+            return Ok(result.clone());
+        }
+        match self.pipeline_query.clone() {
+            Some(query) => {
+                self.release_result_cursor();
+                self.executor.execute_rql(&self.session, query)
             }
+            None => Err(NoctraError::Validation(format!(
+                "{} se aplica al último resultado; ejecute antes una consulta",
+                command
+            ))),
         }
+    }
 
-        // Prevenir path traversal
-        if path_str.contains("..") {
-            return Err(Box::new(NoctraError::Internal(
-                "Acceso denegado: Path traversal no permitido".to_string(),
-            )));
-        }
+    /// Manejar comando ASSERT
+    /// Sintaxis: ASSERT [ROWCOUNT] (query) = valor [LABEL 'texto']
+    fn handle_assert(
+        &mut self,
+        sql: &str,
+        expected_count: Option<usize>,
+        expected_value: Option<&serde_json::Value>,
+        label: Option<&str>,
+    ) -> Result<(), Box<dyn std::error::Error>> {
+        let rql_query = RqlQuery::new(sql, Parameters::new());
+        let result_set = match self.executor.execute_rql(&self.session, rql_query) {
+            Ok(result_set) => result_set,
+            Err(e) => {
+                self.show_error_dialog(&execution_error_message(&e));
+                return Err(Box::new(e));
+            }
+        };
 
-        // Validar que es un archivo regular
-        if path.exists() {
-            let metadata = std::fs::metadata(path)?;
-            if !metadata.is_file() {
-                return Err(Box::new(NoctraError::Internal(
-                    "Acceso denegado: La ruta debe ser un archivo regular".to_string(),
-                )));
+        match check_assertion(&result_set, expected_count, expected_value, label) {
+            Ok(()) => {
+                self.show_info_dialog(&format!("✅ ASSERT OK: {}", label.unwrap_or(sql)));
+                Ok(())
+            }
+            Err(e) => {
+                self.show_error_dialog(&format!("❌ {}", e));
+                Err(Box::new(e))
             }
         }
-
-        Ok(())
     }
 
     /// Validar nombre de tabla (SQL injection prevention)
@@ -1438,7 +2882,7 @@ impl<'a> NoctraTui<'a> {
         {
             Ok(())
         } else {
-            Err(Box::new(NoctraError::Internal(format!(
+            Err(Box::new(NoctraError::Validation(format!(
                 "Nombre de tabla inválido: '{}' (solo alfanuméricos, _, - permitidos)",
                 name
             ))))
@@ -1474,12 +2918,7 @@ impl<'a> NoctraTui<'a> {
 
     /// Limpiar el editor de comandos
     fn clear_command_editor(&mut self) {
-        self.command_editor = TextArea::default();
-        self.command_editor
-            .set_block(Block::default().borders(Borders::NONE));
-        self.command_editor.set_cursor_line_style(Style::default());
-        self.command_editor
-            .set_cursor_style(Style::default().add_modifier(Modifier::REVERSED));
+        self.active_buffer_mut().clear_editor();
     }
 
     /// Mostrar diálogo de error
@@ -1524,10 +2963,8 @@ impl<'a> NoctraTui<'a> {
     /// Cargar comando del historial al editor
     fn load_command_from_history(&mut self) {
         if let Some(idx) = self.history_index {
-            if let Some(cmd) = self.command_history.get(idx) {
-                self.command_editor = TextArea::from(cmd.lines());
-                self.command_editor
-                    .set_block(Block::default().borders(Borders::NONE));
+            if let Some(cmd) = self.command_history.get(idx).cloned() {
+                self.active_buffer_mut().set_text(&cmd);
             }
         }
     }
@@ -1541,8 +2978,680 @@ impl<'a> NoctraTui<'a> {
     }
 }
 
-impl<'a> Drop for NoctraTui<'a> {
+/// Celda de resultados; las columnas numéricas se alinean a la derecha
+fn result_cell<'a>(results: &QueryResults, column: usize, text: &'a str) -> Cell<'a> {
+    if results.numeric.get(column).copied().unwrap_or(false) {
+        Cell::from(Line::from(text).alignment(Alignment::Right))
+    } else {
+        Cell::from(text)
+    }
+}
+
+/// Resultado de DESCRIBE: una fila por columna con su nombre, tipo y
+/// restricciones (PRIMARY KEY, NOT NULL, DEFAULT)
+fn describe_result_set(columns: &[ColumnInfo]) -> ResultSet {
+    use noctra_core::types::{Column, Row, Value};
+
+    ResultSet {
+        columns: vec![
+            Column {
+                name: "Campos".to_string(),
+                data_type: "TEXT".to_string(),
+                ordinal: 0,
+            },
+            Column {
+                name: "Tipo".to_string(),
+                data_type: "TEXT".to_string(),
+                ordinal: 1,
+            },
+            Column {
+                name: "Restricciones".to_string(),
+                data_type: "TEXT".to_string(),
+                ordinal: 2,
+            },
+        ],
+        rows: columns
+            .iter()
+            .map(|col| Row {
+                values: vec![
+                    Value::Text(col.name.clone()),
+                    Value::Text(col.data_type.clone()),
+                    Value::Text(col.constraints()),
+                ],
+            })
+            .collect(),
+        rows_affected: None,
+        last_insert_rowid: None,
+        truncated: false,
+    }
+}
+
+/// Campo CSV: entre comillas (duplicando las internas) si contiene el
+/// delimitador, comillas o saltos de línea
+fn csv_field(value: &str, delimiter: char) -> String {
+    if value.contains(delimiter) || value.contains('"') || value.contains('\n') {
+        format!("\"{}\"", value.replace('"', "\"\""))
+    } else {
+        value.to_string()
+    }
+}
+
+/// Campo CSV de un valor (NULL es un campo vacío)
+fn csv_value(value: &Value, delimiter: char) -> String {
+    match value {
+        Value::Text(s) => csv_field(s, delimiter),
+        Value::Integer(i) => i.to_string(),
+        Value::Float(f) => f.to_string(),
+        Value::Boolean(b) => b.to_string(),
+        Value::Null => String::new(),
+        // Decimales (feature `decimal`), fechas, ... en su forma textual exacta
+        _ => csv_field(&value.to_string(), delimiter),
+    }
+}
+
+/// Crear `dir/stem.ext` sin sobrescribir nada: si existe prueba
+/// `stem_1.ext`, `stem_2.ext`...
+fn create_new_numbered(
+    dir: &Path,
+    stem: &str,
+    ext: &str,
+) -> Result<(PathBuf, std::fs::File), NoctraError> {
+    let mut attempt = 0;
+    loop {
+        let name = match attempt {
+            0 => format!("{}.{}", stem, ext),
+            n => format!("{}_{}.{}", stem, n, ext),
+        };
+        let path = dir.join(name);
+        match std::fs::OpenOptions::new()
+            .write(true)
+            .create_new(true)
+            .open(&path)
+        {
+            Ok(file) => return Ok((path, file)),
+            Err(e) if e.kind() == std::io::ErrorKind::AlreadyExists && attempt < 1000 => {
+                attempt += 1
+            }
+            Err(e) => return Err(NoctraError::Io(format!("Error creando archivo: {}", e))),
+        }
+    }
+}
+
+/// Si la sentencia devuelve filas (se lee por bloques con un cursor)
+fn returns_rows(sql: &str) -> bool {
+    let keyword = sql.split_whitespace().next().unwrap_or("").to_uppercase();
+    matches!(keyword.as_str(), "SELECT" | "WITH" | "VALUES")
+}
+
+/// Mensaje de estado de un resultado leído con cursor
+fn cursor_status(rows: usize, has_more: bool, command: &str) -> String {
+    if has_more {
+        format!(
+            "{} fila(s) leída(s), hay más (↓/AvPág para seguir) - Comando: {}",
+            rows, command
+        )
+    } else {
+        format!("{} fila(s) retornada(s) - Comando: {}", rows, command)
+    }
+}
+
+/// Mensaje del diálogo de error de ejecución, con su código y la sugerencia si la hay
+fn execution_error_message(error: &NoctraError) -> String {
+    let message = format!("❌ Error de ejecución SQL: {} [{}]", error, error.code());
+    match error.hint() {
+        Some(hint) => format!("{}\n💡 {}", message, hint),
+        None => message,
+    }
+}
+
+/// Resumen de los cambios de columnas tras volver a cargar un archivo
+fn schema_diff_message(diff: &noctra_duckdb::SchemaDiff) -> String {
+    let mut lines = vec!["🔀 El esquema cambió desde la carga anterior:".to_string()];
+    for column in &diff.added {
+        lines.push(format!("  + {} ({})", column.name, column.data_type));
+    }
+    for column in &diff.removed {
+        lines.push(format!("  - {} ({})", column.name, column.data_type));
+    }
+    for change in &diff.retyped {
+        lines.push(format!(
+            "  ~ {}: {} → {}",
+            change.name, change.old_type, change.new_type
+        ));
+    }
+    lines.join("\n")
+}
+
+impl Drop for NoctraTui {
     fn drop(&mut self) {
         let _ = self.cleanup();
     }
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ratatui::backend::TestBackend;
+    use ratatui::buffer::Buffer;
+
+    /// Buscar la fila (coordenada y) donde aparece un texto
+    fn find_row(buffer: &Buffer, needle: &str) -> Option<u16> {
+        let area = buffer.area;
+        (area.y..area.y + area.height).find(|&y| {
+            let line: String = (area.x..area.x + area.width)
+                .map(|x| buffer[(x, y)].symbol())
+                .collect();
+            line.contains(needle)
+        })
+    }
+
+    #[test]
+    fn test_render_split_mode_shows_editor_and_results() {
+        let mut terminal = Terminal::new(TestBackend::new(100, 40)).unwrap();
+        let mut buffer = QueryBuffer::new("Consulta 1");
+        buffer.set_text("SELECT id FROM clientes");
+        buffer.results = Some(QueryResults {
+            columns: vec!["id_cliente".to_string()],
+            rows: vec![vec!["42".to_string()]],
+            values: vec![vec![Value::Integer(42)]],
+            numeric: vec![true],
+            status: "1 fila".to_string(),
+            scroll: 0,
+            has_more: false,
+            diff: None,
+        });
+        let mut buffers = vec![buffer];
+
+        let frame_area = terminal
+            .draw(|frame| {
+                NoctraTui::render_frame(
+                    frame,
+                    UiMode::Split,
+                    1,
+                    &mut buffers,
+                    0,
+                    None,
+                    &[],
+                    0,
+                    None,
+                    None,
+                    None,
+                    None,
+                    None,
+                );
+            })
+            .unwrap()
+            .area;
+
+        // Workspace = total - header(3) - pestañas(1) - separador(1) - atajos(7)
+        let workspace = Rect {
+            y: 4,
+            height: frame_area.height - 12,
+            ..frame_area
+        };
+        let (editor_area, results_area) = NoctraTui::split_areas(workspace);
+        let buffer = terminal.backend().buffer();
+
+        let editor_row = find_row(buffer, "SELECT id FROM clientes").unwrap();
+        assert!(editor_row >= editor_area.y && editor_row < editor_area.bottom());
+
+        let header_row = find_row(buffer, "id_cliente").unwrap();
+        let value_row = find_row(buffer, "42").unwrap();
+        assert!(header_row >= results_area.y && header_row < results_area.bottom());
+        assert!(value_row > header_row && value_row < results_area.bottom());
+
+        assert!(find_row(buffer, "DIVIDIDO").is_some());
+    }
+
+    #[test]
+    fn test_render_plan_view_indents_children() {
+        let mut terminal = Terminal::new(TestBackend::new(100, 40)).unwrap();
+        let mut buffer = QueryBuffer::new("Consulta 1");
+        buffer.plan = Some(PlanNode {
+            operator: "PROJECTION".to_string(),
+            estimated_rows: 20,
+            actual_rows: 89,
+            actual_time_ms: 0.01,
+            children: vec![PlanNode {
+                operator: "TABLE_SCAN".to_string(),
+                estimated_rows: 20,
+                actual_rows: 89,
+                actual_time_ms: 1.5,
+                children: Vec::new(),
+            }],
+        });
+        let mut buffers = vec![buffer];
+
+        terminal
+            .draw(|frame| {
+                NoctraTui::render_frame(
+                    frame,
+                    UiMode::PlanView,
+                    1,
+                    &mut buffers,
+                    0,
+                    None,
+                    &[],
+                    0,
+                    None,
+                    None,
+                    None,
+                    None,
+                    None,
+                );
+            })
+            .unwrap();
+        let buffer = terminal.backend().buffer();
+
+        let column = |needle: &str| {
+            let y = find_row(buffer, needle).unwrap();
+            let line: String = (0..buffer.area.width)
+                .map(|x| buffer[(x, y)].symbol())
+                .collect();
+            line.find(needle).unwrap()
+        };
+        assert!(find_row(buffer, "PLAN").is_some());
+        assert_eq!(column("TABLE_SCAN"), column("PROJECTION") + 2);
+        assert!(find_row(buffer, "filas=89 (est. 20)  1.500 ms").is_some());
+    }
+
+    #[test]
+    fn test_render_three_tabs_and_switch() {
+        let mut terminal = Terminal::new(TestBackend::new(100, 30)).unwrap();
+        let mut buffers: Vec<QueryBuffer> =
+            ["Consulta 1", "ventas por región y trimestre", "Consulta 3"]
+                .into_iter()
+                .map(QueryBuffer::new)
+                .collect();
+        buffers[0].set_text("SELECT * FROM clientes");
+        buffers[1].set_text("SELECT * FROM ventas");
+
+        let mut render = |buffers: &mut [QueryBuffer], active: usize| {
+            terminal
+                .draw(|frame| {
+                    NoctraTui::render_frame(
+                        frame,
+                        UiMode::Command,
+                        1,
+                        buffers,
+                        active,
+                        None,
+                        &[],
+                        0,
+                        None,
+                        None,
+                        None,
+                        None,
+                        None,
+                    );
+                })
+                .unwrap();
+            terminal.backend().buffer().clone()
+        };
+
+        // Las tres pestañas aparecen bajo el header, con el nombre recortado
+        let screen = render(&mut buffers, 0);
+        assert_eq!(
+            find_row(&screen, "Consulta 1 │ ventas por región y… │ Consulta 3"),
+            Some(3)
+        );
+        assert_eq!(screen[(1, 3)].bg, Color::Cyan);
+        assert!(find_row(&screen, "SELECT * FROM clientes").is_some());
+
+        // Al cambiar de pestaña se muestra el editor de ese buffer...
+        let active = cycle_index(0, buffers.len(), true);
+        let screen = render(&mut buffers, active);
+        assert!(find_row(&screen, "SELECT * FROM ventas").is_some());
+        assert!(find_row(&screen, "SELECT * FROM clientes").is_none());
+        assert_ne!(screen[(1, 3)].bg, Color::Cyan);
+
+        // ...y al volver se recupera el texto del primero
+        let active = cycle_index(active, buffers.len(), false);
+        let screen = render(&mut buffers, active);
+        assert!(find_row(&screen, "SELECT * FROM clientes").is_some());
+        assert_eq!(buffers[0].text(), "SELECT * FROM clientes");
+    }
+
+    #[test]
+    fn test_split_areas_proportions() {
+        let (top, bottom) = NoctraTui::split_areas(Rect::new(0, 0, 80, 50));
+        assert_eq!(top.height, 20);
+        assert_eq!(bottom.height, 30);
+        assert_eq!(bottom.y, top.bottom());
+    }
+
+    #[test]
+    fn test_tab_completion_popup() {
+        let mut terminal = Terminal::new(TestBackend::new(80, 30)).unwrap();
+        let mut buffer = QueryBuffer::new("Consulta 1");
+        buffer.set_text("SEL");
+        buffer.editor.move_cursor(tui_textarea::CursorMove::End);
+        let mut buffers = vec![buffer];
+        let editor = &buffers[0].editor;
+
+        let mut context = CompletionContext::default();
+        context
+            .tables
+            .insert("sellers".to_string(), vec!["id".to_string()]);
+        let (row, col) = editor.cursor();
+        let line = editor.lines()[row].clone();
+        let prefix = word_before_cursor(&line, col);
+        let items = CompletionEngine::new().complete(prefix, context);
+        assert_eq!(items, vec!["SELECT", "sellers"]);
+        let popup = CompletionPopup::new(prefix, items);
+
+        terminal
+            .draw(|frame| {
+                NoctraTui::render_frame(
+                    frame,
+                    UiMode::Command,
+                    1,
+                    &mut buffers,
+                    0,
+                    None,
+                    &[],
+                    0,
+                    None,
+                    Some(&popup),
+                    None,
+                    None,
+                    None,
+                );
+            })
+            .unwrap();
+
+        // La lista flota bajo la línea del editor (fila 4)
+        let buffer = terminal.backend().buffer();
+        let select_row = find_row(buffer, "│SELECT").unwrap();
+        assert_eq!(find_row(buffer, "│sellers").unwrap(), select_row + 1);
+        assert!(select_row > 4);
+
+        let editor = &mut buffers[0].editor;
+        NoctraTui::apply_completion(editor, &popup.prefix, popup.selected_item().unwrap());
+        assert_eq!(editor.lines(), ["SELECT"]);
+    }
+
+    #[test]
+    fn test_export_results_csv() {
+        let dir = tempfile::tempdir().unwrap();
+        let mut buffer = QueryBuffer::new("Consulta 1");
+        assert!(NoctraTui::export_buffer(&buffer, dir.path()).is_err());
+
+        let mut result_set = ResultSet::new(vec![
+            noctra_core::Column::new("id", "INTEGER", 0),
+            noctra_core::Column::new("nombre", "TEXT", 1),
+        ]);
+        result_set.rows = [
+            vec![Value::Integer(1), Value::Text("Ana".to_string())],
+            vec![Value::Integer(2), Value::Text("Pérez, Luis".to_string())],
+            vec![Value::Integer(3), Value::Null],
+            vec![Value::Integer(4), Value::Text("NULL".to_string())],
+        ]
+        .into_iter()
+        .map(noctra_core::Row::new)
+        .collect();
+        buffer.results = Some(NoctraTui::convert_result_set(
+            result_set,
+            "SELECT * FROM clientes",
+        ));
+
+        let path = NoctraTui::export_buffer(&buffer, dir.path()).unwrap();
+        let name = path.file_name().unwrap().to_str().unwrap();
+        assert!(name.starts_with("noctra_export_") && name.ends_with(".csv"));
+        assert_eq!(name.len(), "noctra_export_20240101_120000.csv".len());
+
+        let content = std::fs::read_to_string(&path).unwrap();
+        let lines: Vec<&str> = content.lines().collect();
+        assert_eq!(
+            lines,
+            ["id,nombre", "1,Ana", "2,\"Pérez, Luis\"", "3,", "4,NULL"]
+        );
+
+        // Otro archivo con el mismo nombre no se sobrescribe
+        let (first, _) = create_new_numbered(dir.path(), "export", "csv").unwrap();
+        let (second, _) = create_new_numbered(dir.path(), "export", "csv").unwrap();
+        assert_eq!(first, dir.path().join("export.csv"));
+        assert_eq!(second, dir.path().join("export_1.csv"));
+    }
+
+    #[test]
+    fn test_header_shows_toast() {
+        let mut terminal = Terminal::new(TestBackend::new(100, 3)).unwrap();
+        terminal
+            .draw(|frame| {
+                NoctraTui::render_header(
+                    frame,
+                    frame.area(),
+                    UiMode::Result,
+                    2,
+                    None,
+                    Some("Exportado a ./noctra_export.csv"),
+                    None,
+                );
+            })
+            .unwrap();
+
+        assert!(find_row(
+            terminal.backend().buffer(),
+            "Exportado a ./noctra_export.csv"
+        )
+        .is_some());
+    }
+
+    #[test]
+    fn test_watch_fires_after_interval_and_refreshes_results() {
+        let executor = Executor::new_sqlite_memory().unwrap();
+        let session = Session::new();
+        let run = |sql: &str| {
+            executor.execute_rql(&session, RqlQuery::sql(sql)).unwrap();
+        };
+        run("CREATE TABLE orders (id INTEGER, total INTEGER)");
+        run("INSERT INTO orders VALUES (1, 10), (2, 20)");
+
+        let sql = "SELECT id, total FROM orders ORDER BY id";
+        let mut buffers = vec![QueryBuffer::new("Consulta 1")];
+        let first = NoctraTui::watch_results(&executor, &session, sql, None).unwrap();
+        assert!(first.diff.is_none());
+        buffers[0].results = Some(first);
+        let start = Instant::now();
+        let mut watch = Some(WatchSpec::new(Duration::from_secs(5), sql, buffers[0].id));
+
+        run("UPDATE orders SET total = 15 WHERE id = 1");
+        run("DELETE FROM orders WHERE id = 2");
+        run("INSERT INTO orders VALUES (3, 30)");
+
+        // Antes del intervalo no se repite
+        NoctraTui::refresh_watch(&mut watch, &mut buffers, &executor, &session, start).unwrap();
+        let results = buffers[0].results.as_ref().unwrap();
+        assert_eq!(results.rows, vec![vec!["1", "10"], vec!["2", "20"]]);
+        assert!(results.diff.is_none());
+
+        let later = start + Duration::from_secs(6);
+        NoctraTui::refresh_watch(&mut watch, &mut buffers, &executor, &session, later).unwrap();
+        let second = buffers[0].results.clone().unwrap();
+        assert_eq!(second.rows, vec![vec!["1", "15"], vec!["3", "30"]]);
+        let diff = second.diff.as_ref().unwrap();
+        assert_eq!(
+            diff.rows,
+            vec![RowChange::Changed(vec![1]), RowChange::Added]
+        );
+        assert_eq!(diff.removed, vec![vec!["2".to_string(), "20".to_string()]]);
+
+        // La siguiente repetición se programa desde la última
+        run("INSERT INTO orders VALUES (4, 40)");
+        let soon = later + Duration::from_secs(1);
+        NoctraTui::refresh_watch(&mut watch, &mut buffers, &executor, &session, soon).unwrap();
+        assert_eq!(buffers[0].results.as_ref().unwrap().rows.len(), 2);
+
+        // Cerrar el buffer termina la vigilancia
+        let end = later + Duration::from_secs(10);
+        NoctraTui::refresh_watch(&mut watch, &mut [], &executor, &session, end).unwrap();
+        assert!(watch.is_none());
+
+        // Indicador en el header y filas resaltadas en el resultado
+        let indicator = WatchSpec::new(Duration::from_secs(5), sql, buffers[0].id).indicator();
+        let mut terminal = Terminal::new(TestBackend::new(100, 20)).unwrap();
+        terminal
+            .draw(|frame| {
+                let header = Rect {
+                    height: 3,
+                    ..frame.area()
+                };
+                let body = Rect {
+                    y: 3,
+                    height: 17,
+                    ..frame.area()
+                };
+                NoctraTui::render_header(
+                    frame,
+                    header,
+                    UiMode::Result,
+                    1,
+                    None,
+                    None,
+                    Some(&indicator),
+                );
+                NoctraTui::render_result_mode(frame, body, Some(&second));
+            })
+            .unwrap();
+        let buffer = terminal.backend().buffer();
+        assert!(find_row(buffer, "VIGILANDO (cada 5s) — Esc para detener").is_some());
+        let added = find_row(buffer, "30").unwrap();
+        let removed = find_row(buffer, "20").unwrap();
+        assert_eq!(buffer[(1, added)].fg, Color::Green);
+        assert_eq!(buffer[(1, removed)].fg, Color::Red);
+    }
+
+    #[test]
+    fn test_schema_diff_message() {
+        let column = |name: &str, data_type: &str| ColumnInfo {
+            name: name.to_string(),
+            data_type: data_type.to_string(),
+            nullable: true,
+            default_value: None,
+            primary_key: false,
+        };
+        let diff = noctra_duckdb::SchemaDiff::between(
+            &[column("id", "BIGINT"), column("name", "VARCHAR")],
+            &[column("id", "VARCHAR"), column("full_name", "VARCHAR")],
+        );
+
+        let message = schema_diff_message(&diff);
+        assert!(message.contains("+ full_name (VARCHAR)"));
+        assert!(message.contains("- name (VARCHAR)"));
+        assert!(message.contains("~ id: BIGINT → VARCHAR"));
+    }
+
+    #[test]
+    fn test_result_scroll_requests_more_rows() {
+        let mut results = QueryResults {
+            columns: vec!["n".to_string()],
+            rows: (0..50).map(|n| vec![n.to_string()]).collect(),
+            values: (0..50).map(|n| vec![Value::Integer(n)]).collect(),
+            numeric: vec![true],
+            status: String::new(),
+            scroll: 0,
+            has_more: true,
+            diff: None,
+        };
+
+        assert!(!results.needs_more_rows(1));
+        assert!(results.needs_more_rows(30));
+
+        results.scroll_by(100);
+        assert_eq!(results.scroll, 49);
+        results.scroll_by(isize::MIN);
+        assert_eq!(results.scroll, 0);
+
+        results.has_more = false;
+        assert!(!results.needs_more_rows(30));
+        assert!(returns_rows("  with t AS (SELECT 1) SELECT * FROM t"));
+        assert!(!returns_rows("INSERT INTO t VALUES (1)"));
+    }
+
+    /// Buscar la posición (x, y) de la primera celda de un texto ASCII
+    fn find_position(buffer: &Buffer, needle: &str) -> Option<(u16, u16)> {
+        let area = buffer.area;
+        let needle: Vec<String> = needle.chars().map(String::from).collect();
+        (area.y..area.bottom()).find_map(|y| {
+            let cells: Vec<&str> = (area.x..area.right())
+                .map(|x| buffer[(x, y)].symbol())
+                .collect();
+            cells
+                .windows(needle.len())
+                .position(|window| window.iter().zip(&needle).all(|(cell, c)| cell == c))
+                .map(|x| (area.x + x as u16, y))
+        })
+    }
+
+    #[test]
+    fn test_render_form_mode_places_labels_and_errors() {
+        use noctra_formlib::{FieldType, Form, FormField};
+
+        let field = |label: &str, required: bool| FormField {
+            label: label.to_string(),
+            field_type: FieldType::Text,
+            required,
+            width: None,
+            default: None,
+            validations: None,
+            visible_when: None,
+            computed_from: Vec::new(),
+            formula: None,
+        };
+        let form = Form {
+            title: "Alta de cliente".to_string(),
+            schema: None,
+            description: None,
+            fields: HashMap::from([
+                ("ciudad".to_string(), field("Ciudad", false)),
+                ("nombre".to_string(), field("Nombre", true)),
+            ]),
+            field_order: vec!["nombre".to_string(), "ciudad".to_string()],
+            actions: HashMap::new(),
+            ui_config: None,
+            pagination: None,
+            cross_field_rules: Vec::new(),
+        };
+        let mut form = FormRenderer::new(form).with_help(FORM_HELP);
+        form.set_field_value("ciudad", "Rosario".to_string())
+            .unwrap();
+        assert!(form.validate_all().is_err());
+
+        let mut terminal = Terminal::new(TestBackend::new(80, 40)).unwrap();
+        let mut buffers = vec![QueryBuffer::new("Consulta 1")];
+        terminal
+            .draw(|frame| {
+                NoctraTui::render_frame(
+                    frame,
+                    UiMode::Form,
+                    1,
+                    &mut buffers,
+                    0,
+                    None,
+                    &[],
+                    0,
+                    None,
+                    None,
+                    None,
+                    None,
+                    Some(&form),
+                );
+            })
+            .unwrap();
+        let buffer = terminal.backend().buffer();
+
+        // Workspace en y=4; título (3 filas) y borde de la lista de campos.
+        // Cada campo ocupa label, valor y separación: "▶  Ciudad: " y
+        // "  *Nombre: ", en orden alfabético
+        assert_eq!(find_position(buffer, "Ciudad:"), Some((4, 8)));
+        assert_eq!(find_position(buffer, "Rosario"), Some((4, 9)));
+        assert_eq!(find_position(buffer, "*Nombre:"), Some((3, 11)));
+
+        // El error del campo obligatorio vacío, en rojo bajo su valor
+        let (x, y) = find_position(buffer, "Campo requerido 'Nombre'").unwrap();
+        assert_eq!(y, 13);
+        assert_eq!(buffer[(x, y)].fg, Color::Red);
+        assert!(find_row(buffer, "F9=Restablecer").is_some());
+    }
+}
@@ -48,6 +48,12 @@ pub enum UiMode {
 
     /// Modo diálogo (mensajes, confirmaciones)
     Dialog,
+
+    /// Modo dividido (editor arriba, resultados abajo)
+    Split,
+
+    /// Modo plan (árbol de EXPLAIN ANALYZE)
+    PlanView,
 }
 
 impl UiMode {
@@ -58,6 +64,8 @@ impl UiMode {
             UiMode::Result => "Result Mode - Data Display",
             UiMode::Form => "Form Mode - Data Entry",
             UiMode::Dialog => "Dialog Mode - Messages",
+            UiMode::Split => "Split Mode - Editor + Results",
+            UiMode::PlanView => "Plan Mode - EXPLAIN ANALYZE",
         }
     }
 
@@ -68,6 +76,8 @@ impl UiMode {
             UiMode::Result => "📊",
             UiMode::Form => "📝",
             UiMode::Dialog => "💬",
+            UiMode::Split => "⊟",
+            UiMode::PlanView => "🌳",
         }
     }
 }
@@ -213,6 +223,9 @@ pub struct NwmConfig {
 
     /// Tamaño mínimo de ventana (ancho, alto)
     pub min_window_size: (usize, usize),
+
+    /// Modo inicial y de retorno (Command o Split)
+    pub default_mode: UiMode,
 }
 
 impl Default for NwmConfig {
@@ -224,6 +237,7 @@ impl Default for NwmConfig {
             footer_height: 2,
             theme: "default".to_string(),
             min_window_size: (80, 24),
+            default_mode: UiMode::Command,
         }
     }
 }
@@ -558,9 +572,11 @@ mod tests {
                 schema: None,
                 description: None,
                 fields: std::collections::HashMap::new(),
+                field_order: Vec::new(),
                 actions: std::collections::HashMap::new(),
                 ui_config: None,
                 pagination: None,
+                cross_field_rules: Vec::new(),
             },
         ));
 
@@ -0,0 +1,174 @@
+//! Buffers de consulta (pestañas del TUI)
+//!
+//! Cada buffer tiene su propio editor y su último resultado, así que se
+//! puede alternar entre varias consultas sin perder el texto escrito.
+
+use noctra_core::PlanNode;
+use ratatui::{
+    layout::Rect,
+    style::{Color, Modifier, Style},
+    widgets::{Block, Borders, Tabs},
+    Frame,
+};
+use tui_textarea::TextArea;
+use uuid::Uuid;
+
+use crate::noctra_tui::QueryResults;
+
+/// Longitud máxima (en caracteres) del nombre mostrado en una pestaña
+pub const MAX_TAB_NAME_LEN: usize = 20;
+
+/// Buffer de consulta independiente
+#[derive(Debug, Clone)]
+pub struct QueryBuffer {
+    /// Identificador único del buffer
+    pub id: Uuid,
+
+    /// Nombre mostrado en la pestaña
+    pub name: String,
+
+    /// Editor de comandos del buffer
+    pub editor: TextArea<'static>,
+
+    /// Último resultado obtenido en este buffer
+    pub results: Option<QueryResults>,
+
+    /// Último plan de EXPLAIN ANALYZE (Ctrl+E) de este buffer
+    pub plan: Option<PlanNode>,
+}
+
+impl QueryBuffer {
+    /// Crear un buffer vacío
+    pub fn new(name: impl Into<String>) -> Self {
+        Self {
+            id: Uuid::new_v4(),
+            name: name.into(),
+            editor: new_editor(Vec::new()),
+            results: None,
+            plan: None,
+        }
+    }
+
+    /// Texto del editor
+    pub fn text(&self) -> String {
+        self.editor.lines().join("\n")
+    }
+
+    /// Reemplazar el contenido del editor
+    pub fn set_text(&mut self, text: &str) {
+        self.editor = new_editor(text.lines().map(str::to_string).collect());
+    }
+
+    /// Vaciar el editor
+    pub fn clear_editor(&mut self) {
+        self.editor = new_editor(Vec::new());
+    }
+
+    /// Si el editor tiene texto que todavía no se ejecutó
+    ///
+    /// El editor se vacía tras ejecutar un comando, así que cualquier texto
+    /// pendiente se perdería al cerrar el buffer.
+    pub fn has_unsaved_changes(&self) -> bool {
+        self.editor
+            .lines()
+            .iter()
+            .any(|line| !line.trim().is_empty())
+    }
+
+    /// Nombre de la pestaña, recortado a `MAX_TAB_NAME_LEN` caracteres
+    pub fn tab_label(&self) -> String {
+        if self.name.chars().count() <= MAX_TAB_NAME_LEN {
+            return self.name.clone();
+        }
+        let mut label: String = self.name.chars().take(MAX_TAB_NAME_LEN - 1).collect();
+        label.push('…');
+        label
+    }
+}
+
+/// Editor con el estilo del TUI (sin bordes, cursor en video inverso)
+fn new_editor(lines: Vec<String>) -> TextArea<'static> {
+    let mut editor = TextArea::new(lines);
+    editor.set_block(Block::default().borders(Borders::NONE));
+    editor.set_cursor_line_style(Style::default());
+    editor.set_cursor_style(Style::default().add_modifier(Modifier::REVERSED));
+    editor
+}
+
+/// Índice de la pestaña siguiente (o anterior) con vuelta al principio
+pub fn cycle_index(active: usize, len: usize, forward: bool) -> usize {
+    if len == 0 {
+        return 0;
+    }
+    if forward {
+        (active + 1) % len
+    } else {
+        (active + len - 1) % len
+    }
+}
+
+/// Renderizar la barra de pestañas con el buffer activo resaltado
+pub fn render_tab_bar(frame: &mut Frame, area: Rect, buffers: &[QueryBuffer], active: usize) {
+    let tabs = Tabs::new(buffers.iter().map(QueryBuffer::tab_label))
+        .select(active)
+        .style(Style::default().fg(Color::DarkGray))
+        .highlight_style(
+            Style::default()
+                .fg(Color::Black)
+                .bg(Color::Cyan)
+                .add_modifier(Modifier::BOLD),
+        );
+    frame.render_widget(tabs, area);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use ratatui::{backend::TestBackend, Terminal};
+
+    #[test]
+    fn test_tab_label_truncated() {
+        let buffer = QueryBuffer::new("ventas por región y trimestre");
+        assert_eq!(buffer.tab_label(), "ventas por región y…");
+        assert_eq!(buffer.tab_label().chars().count(), MAX_TAB_NAME_LEN);
+        assert_eq!(QueryBuffer::new("Consulta 1").tab_label(), "Consulta 1");
+    }
+
+    #[test]
+    fn test_cycle_index_wraps() {
+        assert_eq!(cycle_index(0, 3, true), 1);
+        assert_eq!(cycle_index(2, 3, true), 0);
+        assert_eq!(cycle_index(0, 3, false), 2);
+        assert_eq!(cycle_index(0, 1, true), 0);
+    }
+
+    #[test]
+    fn test_unsaved_changes() {
+        let mut buffer = QueryBuffer::new("Consulta 1");
+        assert!(!buffer.has_unsaved_changes());
+        buffer.set_text("SELECT 1");
+        assert!(buffer.has_unsaved_changes());
+        buffer.clear_editor();
+        assert!(!buffer.has_unsaved_changes());
+    }
+
+    #[test]
+    fn test_render_tab_bar_highlights_active() {
+        let buffers: Vec<QueryBuffer> = ["Consulta 1", "clientes", "Consulta 3"]
+            .into_iter()
+            .map(QueryBuffer::new)
+            .collect();
+        let mut terminal = Terminal::new(TestBackend::new(50, 1)).unwrap();
+        terminal
+            .draw(|frame| render_tab_bar(frame, frame.area(), &buffers, 1))
+            .unwrap();
+
+        let buffer = terminal.backend().buffer();
+        let line: String = (0..50).map(|x| buffer[(x, 0)].symbol()).collect();
+        assert_eq!(line.trim_end(), " Consulta 1 │ clientes │ Consulta 3");
+
+        let active = line[..line.find("clientes").unwrap()].chars().count() as u16;
+        assert_eq!(buffer[(active, 0)].bg, Color::Cyan);
+        assert_ne!(buffer[(1, 0)].bg, Color::Cyan);
+    }
+}
@@ -324,30 +324,26 @@ impl TuiRenderer {
 
             ComponentEvent::Custom(event_type) => {
                 match event_type.as_str() {
-                    "next_component" => {
-                        if !self.components.is_empty() {
-                            let next_index = if let Some(current) = self.focused_component {
-                                (current + 1) % self.components.len()
-                            } else {
-                                0
-                            };
-                            self.set_focused_component(next_index);
-                        }
+                    "next_component" if !self.components.is_empty() => {
+                        let next_index = if let Some(current) = self.focused_component {
+                            (current + 1) % self.components.len()
+                        } else {
+                            0
+                        };
+                        self.set_focused_component(next_index);
                     }
 
-                    "prev_component" => {
-                        if !self.components.is_empty() {
-                            let prev_index = if let Some(current) = self.focused_component {
-                                if current == 0 {
-                                    self.components.len() - 1
-                                } else {
-                                    current - 1
-                                }
+                    "prev_component" if !self.components.is_empty() => {
+                        let prev_index = if let Some(current) = self.focused_component {
+                            if current == 0 {
+                                self.components.len() - 1
                             } else {
-                                0
-                            };
-                            self.set_focused_component(prev_index);
-                        }
+                                current - 1
+                            }
+                        } else {
+                            0
+                        };
+                        self.set_focused_component(prev_index);
                     }
 
                     _ => {
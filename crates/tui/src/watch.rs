@@ -0,0 +1,203 @@
+//! Consultas vigiladas (`WATCH n sql`)
+//!
+//! El TUI vuelve a ejecutar la consulta vigilada desde su bucle de eventos:
+//! `WatchSpec::poll_timeout` acorta la espera de `event::poll` hasta la
+//! próxima ejecución y `WatchSpec::poll` indica cuándo toca. `diff_rows`
+//! compara cada resultado con el anterior para resaltar las filas nuevas,
+//! las borradas y los valores cambiados.
+
+use std::collections::{HashMap, VecDeque};
+use std::time::{Duration, Instant};
+use uuid::Uuid;
+
+/// Consulta que se repite cada `interval`
+#[derive(Debug, Clone)]
+pub struct WatchSpec {
+    /// Intervalo entre ejecuciones
+    pub interval: Duration,
+
+    /// Consulta vigilada
+    pub sql: String,
+
+    /// Buffer donde se muestra el resultado
+    pub buffer_id: Uuid,
+
+    /// Momento de la próxima ejecución
+    next_run: Instant,
+}
+
+impl WatchSpec {
+    /// Vigilar `sql` a partir de ahora (la primera repetición es dentro de
+    /// `interval`)
+    pub fn new(interval: Duration, sql: impl Into<String>, buffer_id: Uuid) -> Self {
+        Self {
+            interval,
+            sql: sql.into(),
+            buffer_id,
+            next_run: Instant::now() + interval,
+        }
+    }
+
+    /// Si toca ejecutar en `now`; en ese caso programa la siguiente
+    pub fn poll(&mut self, now: Instant) -> bool {
+        if now < self.next_run {
+            return false;
+        }
+        self.next_run = now + self.interval;
+        true
+    }
+
+    /// Espera del bucle de eventos: hasta la próxima ejecución, como mucho
+    /// `max`
+    pub fn poll_timeout(&self, now: Instant, max: Duration) -> Duration {
+        self.next_run.saturating_duration_since(now).min(max)
+    }
+
+    /// Indicador del header
+    pub fn indicator(&self) -> String {
+        format!(
+            "VIGILANDO (cada {}s) — Esc para detener",
+            self.interval.as_secs()
+        )
+    }
+}
+
+/// Cambio de una fila respecto de la ejecución anterior
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub enum RowChange {
+    /// Igual que antes
+    Unchanged,
+
+    /// No estaba
+    Added,
+
+    /// Misma clave (primera columna) con otros valores; índices de las
+    /// columnas que cambiaron
+    Changed(Vec<usize>),
+}
+
+/// Diferencias entre dos resultados de una consulta vigilada
+#[derive(Debug, Clone, Default, PartialEq, Eq)]
+pub struct RowDiff {
+    /// Cambio de cada fila del resultado nuevo, en orden
+    pub rows: Vec<RowChange>,
+
+    /// Filas del resultado anterior que ya no están
+    pub removed: Vec<Vec<String>>,
+}
+
+impl RowDiff {
+    /// Cambio de la fila `index` del resultado nuevo
+    pub fn change(&self, index: usize) -> &RowChange {
+        self.rows.get(index).unwrap_or(&RowChange::Unchanged)
+    }
+}
+
+/// Comparar el resultado nuevo con el anterior
+///
+/// Primero se emparejan las filas idénticas; del resto, las que comparten
+/// la primera columna se consideran la misma fila con valores cambiados.
+pub fn diff_rows(previous: &[Vec<String>], current: &[Vec<String>]) -> RowDiff {
+    let mut used = vec![false; previous.len()];
+
+    let mut identical: HashMap<&[String], VecDeque<usize>> = HashMap::new();
+    for (index, row) in previous.iter().enumerate() {
+        identical
+            .entry(row.as_slice())
+            .or_default()
+            .push_back(index);
+    }
+    let mut rows: Vec<Option<RowChange>> = current
+        .iter()
+        .map(|row| {
+            let index = identical.get_mut(row.as_slice())?.pop_front()?;
+            used[index] = true;
+            Some(RowChange::Unchanged)
+        })
+        .collect();
+
+    let mut by_key: HashMap<&str, VecDeque<usize>> = HashMap::new();
+    for (index, row) in previous.iter().enumerate() {
+        if let (false, Some(key)) = (used[index], row.first()) {
+            by_key.entry(key.as_str()).or_default().push_back(index);
+        }
+    }
+    for (row, change) in current.iter().zip(rows.iter_mut()) {
+        if change.is_some() {
+            continue;
+        }
+        let matched = row
+            .first()
+            .and_then(|key| by_key.get_mut(key.as_str())?.pop_front());
+        *change = Some(match matched {
+            Some(index) => {
+                used[index] = true;
+                let changed = (0..row.len().max(previous[index].len()))
+                    .filter(|&column| row.get(column) != previous[index].get(column))
+                    .collect();
+                RowChange::Changed(changed)
+            }
+            None => RowChange::Added,
+        });
+    }
+
+    RowDiff {
+        rows: rows.into_iter().flatten().collect(),
+        removed: previous
+            .iter()
+            .zip(&used)
+            .filter(|(_, used)| !**used)
+            .map(|(row, _)| row.clone())
+            .collect(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn rows(data: &[&[&str]]) -> Vec<Vec<String>> {
+        data.iter()
+            .map(|row| row.iter().map(|cell| cell.to_string()).collect())
+            .collect()
+    }
+
+    #[test]
+    fn test_diff_rows_marks_added_removed_and_changed() {
+        let previous = rows(&[
+            &["1", "ana", "10"],
+            &["2", "luis", "20"],
+            &["3", "eva", "30"],
+        ]);
+        let current = rows(&[
+            &["1", "ana", "10"],
+            &["3", "eva", "35"],
+            &["4", "rosa", "40"],
+        ]);
+
+        let diff = diff_rows(&previous, &current);
+        assert_eq!(
+            diff.rows,
+            vec![
+                RowChange::Unchanged,
+                RowChange::Changed(vec![2]),
+                RowChange::Added
+            ]
+        );
+        assert_eq!(diff.removed, rows(&[&["2", "luis", "20"]]));
+        assert_eq!(diff.change(10), &RowChange::Unchanged);
+    }
+
+    #[test]
+    fn test_watch_spec_polls_after_interval() {
+        let mut watch = WatchSpec::new(Duration::from_secs(5), "SELECT 1", Uuid::new_v4());
+        let now = Instant::now();
+
+        assert!(!watch.poll(now));
+        assert!(watch.poll_timeout(now, Duration::from_millis(100)) <= Duration::from_millis(100));
+        assert!(watch.poll(now + Duration::from_secs(5)));
+        assert!(!watch.poll(now + Duration::from_secs(6)));
+        assert!(watch.poll(now + Duration::from_secs(10)));
+        assert_eq!(watch.indicator(), "VIGILANDO (cada 5s) — Esc para detener");
+    }
+}
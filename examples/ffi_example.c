@@ -0,0 +1,63 @@
+/*
+ * Ejemplo de uso de noctra-ffi desde C
+ *
+ * Registra examples/clientes.csv como fuente y la consulta.
+ *
+ * Compilar (desde la raíz del repositorio):
+ *   cargo build --release -p noctra-ffi
+ *   cc examples/ffi_example.c -o ffi_example -Ltarget/release -lnoctra_ffi
+ *   LD_LIBRARY_PATH=target/release ./ffi_example
+ */
+
+#include <stdio.h>
+
+/* Códigos de resultado */
+#define FFI_SUCCESS 0
+#define FFI_ERROR -1
+#define FFI_INVALID_INPUT -2
+#define FFI_NOT_FOUND -3
+
+/* Funciones exportadas por libnoctra_ffi */
+extern int noctra_init(void);
+extern void noctra_shutdown(void);
+extern const char *noctra_version(void);
+extern int noctra_register_file(const char *path, const char *alias);
+extern int noctra_query_source(const char *alias, const char *sql, char **out_json);
+extern int noctra_list_sources(char **out_json);
+extern void noctra_free(char *ptr);
+
+int main(void) {
+    char *json = NULL;
+    int rc;
+
+    if (noctra_init() != FFI_SUCCESS) {
+        fprintf(stderr, "No se pudo inicializar Noctra\n");
+        return 1;
+    }
+    printf("Noctra %s\n", noctra_version());
+
+    rc = noctra_register_file("examples/clientes.csv", "clientes");
+    if (rc != FFI_SUCCESS) {
+        fprintf(stderr, "Error registrando clientes.csv (%d)\n", rc);
+        noctra_shutdown();
+        return 1;
+    }
+
+    if (noctra_list_sources(&json) == FFI_SUCCESS) {
+        printf("Fuentes: %s\n", json);
+        noctra_free(json);
+    }
+
+    /* Si la consulta falla, json trae {"success": false, "error": "..."} */
+    json = NULL;
+    rc = noctra_query_source("clientes",
+                             "SELECT nombre, ciudad FROM clientes WHERE edad > 26 ORDER BY nombre",
+                             &json);
+    if (json != NULL) {
+        printf("%s: %s\n", rc == FFI_SUCCESS ? "Resultado" : "Error", json);
+        noctra_free(json);
+    }
+
+    noctra_shutdown();
+    return rc == FFI_SUCCESS ? 0 : 1;
+}